@@ -0,0 +1,32 @@
+use std::error::Error;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> Result<(), Box<dyn Error>> {
+    use wgpu_learning::{
+        app,
+        boids::{BoidsApp, DEFAULT_BOID_COUNT},
+        run_config::RunConfig,
+    };
+
+    // `--count 50000` flies 50000 boids instead of the default
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let count = match args.iter().position(|arg| arg == "--count") {
+        Some(position) => match args.get(position + 1).map(|count| count.parse::<u32>()) {
+            Some(Ok(count)) => count,
+            _ => return Err("--count needs a number of boids, e.g. --count 50000".into()),
+        },
+        None => DEFAULT_BOID_COUNT,
+    };
+
+    pollster::block_on(app::run_app_with_init(RunConfig::default(), move |ctx| {
+        BoidsApp::new(ctx, count)
+    }))?;
+
+    Ok(())
+}
+
+// The app runner is native only for now
+#[cfg(target_arch = "wasm32")]
+fn main() -> Result<(), Box<dyn Error>> {
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use std::error::Error;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> Result<(), Box<dyn Error>> {
+    use wgpu_learning::{app, clear_app::ClearApp};
+
+    pollster::block_on(app::run_app::<ClearApp>())?;
+
+    Ok(())
+}
+
+// The app runner is native only for now
+#[cfg(target_arch = "wasm32")]
+fn main() -> Result<(), Box<dyn Error>> {
+    Ok(())
+}
@@ -0,0 +1,12 @@
+// A shader to start from with `cargo run -- --shadertoy examples/shadertoy.wgsl`.
+// Saving the file reloads it, `globals` has the time, the resolution and the
+// cursor in pixels.
+
+fn main_image(frag_coord: vec2<f32>) -> vec4<f32> {
+    let uv = frag_coord / globals.resolution;
+    // Rings around the cursor, moving outwards
+    let d = length(frag_coord - globals.cursor) / globals.resolution.y;
+    let rings = 0.5 + 0.5 * sin(d * 40.0 - globals.time * 4.0);
+    let color = vec3<f32>(uv, 0.5 + 0.5 * sin(globals.time)) * rings;
+    return vec4<f32>(color, 1.0);
+}
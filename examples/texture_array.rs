@@ -0,0 +1,19 @@
+use std::error::Error;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> Result<(), Box<dyn Error>> {
+    use wgpu_learning::{app, run_config::RunConfig, texture_array::TextureArrayApp};
+
+    pollster::block_on(app::run_app_with_init(
+        RunConfig::default(),
+        TextureArrayApp::new,
+    ))?;
+
+    Ok(())
+}
+
+// The app runner is native only for now
+#[cfg(target_arch = "wasm32")]
+fn main() -> Result<(), Box<dyn Error>> {
+    Ok(())
+}
@@ -0,0 +1,88 @@
+// Bloom with the dual filter from Marius Bjørge's "Bandwidth-Efficient
+// Rendering", see bloom.rs. Each pass reads the previous one's texture and
+// draws the same fullscreen triangle as post.wgsl.
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+struct Bloom {
+    threshold: f32,
+    intensity: f32,
+};
+@group(1) @binding(0)
+var<uniform> bloom: Bloom;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    // Texture coordinates go down, clip space goes up
+    out.tex_coords = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+fn sample(uv: vec2<f32>) -> vec3<f32> {
+    return textureSample(t_source, s_source, uv).rgb;
+}
+
+// Into a target half the size of the source. The linear sampler averages
+// 2x2 texels with every sample.
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let offset = 1.0 / vec2<f32>(textureDimensions(t_source));
+    var sum = sample(uv) * 4.0;
+    sum += sample(uv - offset);
+    sum += sample(uv + offset);
+    sum += sample(uv + vec2<f32>(offset.x, -offset.y));
+    sum += sample(uv - vec2<f32>(offset.x, -offset.y));
+    return sum / 8.0;
+}
+
+// Into a target twice the size of the source, as a tent around the middle
+fn upsample(uv: vec2<f32>) -> vec3<f32> {
+    let offset = 0.5 / vec2<f32>(textureDimensions(t_source));
+    var sum = sample(uv + vec2<f32>(-offset.x * 2.0, 0.0));
+    sum += sample(uv + vec2<f32>(-offset.x, offset.y)) * 2.0;
+    sum += sample(uv + vec2<f32>(0.0, offset.y * 2.0));
+    sum += sample(uv + vec2<f32>(offset.x, offset.y)) * 2.0;
+    sum += sample(uv + vec2<f32>(offset.x * 2.0, 0.0));
+    sum += sample(uv + vec2<f32>(offset.x, -offset.y)) * 2.0;
+    sum += sample(uv + vec2<f32>(0.0, -offset.y * 2.0));
+    sum += sample(uv + vec2<f32>(-offset.x, -offset.y)) * 2.0;
+    return sum / 12.0;
+}
+
+// Keeps only what's brighter than the threshold, scaled so the brightest
+// parts keep their color
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = downsample(in.tex_coords);
+    let brightness = max(color.r, max(color.g, color.b));
+    let contribution = max(brightness - bloom.threshold, 0.0) / max(brightness, 0.0001);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.tex_coords), 1.0);
+}
+
+// Added onto the bigger mip, which still has its own downsampled colors
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(in.tex_coords), 1.0);
+}
+
+// Added onto the scene, whose alpha stays as it is
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(in.tex_coords) * bloom.intensity, 0.0);
+}
@@ -0,0 +1,172 @@
+// Compute shader
+
+struct Boid {
+    position: vec2<f32>,
+    velocity: vec2<f32>,
+};
+
+struct SimParams {
+    separation_radius: f32,
+    alignment_radius: f32,
+    cohesion_radius: f32,
+    separation_weight: f32,
+    alignment_weight: f32,
+    cohesion_weight: f32,
+    max_speed: f32,
+    min_speed: f32,
+    dt: f32,
+    count: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: SimParams;
+// Read from one buffer and written into the other, so every boid sees
+// where the others were at the start of the step
+@group(0) @binding(1)
+var<storage, read> src_boids: array<Boid>;
+@group(0) @binding(2)
+var<storage, read_write> dst_boids: array<Boid>;
+
+// Must match compute::WORKGROUP_SIZE
+const TILE_SIZE: u32 = 64u;
+var<workgroup> tile: array<Boid, 64>;
+
+@compute @workgroup_size(64)
+fn cs_main(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let index = id.x;
+    // The invocations past the end still help loading the tiles
+    let in_range = index < params.count;
+    var boid: Boid;
+    if in_range {
+        boid = src_boids[index];
+    }
+
+    var separation = vec2<f32>(0.0);
+    var velocity_sum = vec2<f32>(0.0);
+    var alignment_count = 0u;
+    var position_sum = vec2<f32>(0.0);
+    var cohesion_count = 0u;
+    // Every boid looks at every other boid, a tile at a time. Each
+    // invocation loads one boid of the tile into workgroup memory, where
+    // the whole workgroup reads it from
+    for (var start = 0u; start < params.count; start += TILE_SIZE) {
+        if start + local_index < params.count {
+            tile[local_index] = src_boids[start + local_index];
+        }
+        workgroupBarrier();
+
+        let tile_count = min(TILE_SIZE, params.count - start);
+        for (var i = 0u; i < tile_count; i++) {
+            if start + i == index {
+                continue;
+            }
+            let other = tile[i];
+            let offset = other.position - boid.position;
+            let distance = length(offset);
+            if distance < params.separation_radius {
+                separation -= offset;
+            }
+            if distance < params.alignment_radius {
+                velocity_sum += other.velocity;
+                alignment_count++;
+            }
+            if distance < params.cohesion_radius {
+                position_sum += other.position;
+                cohesion_count++;
+            }
+        }
+        // Nobody may load the next tile while it's still being read
+        workgroupBarrier();
+    }
+    if !in_range {
+        return;
+    }
+
+    var velocity = boid.velocity + separation * params.separation_weight * params.dt;
+    if alignment_count > 0u {
+        let average = velocity_sum / f32(alignment_count);
+        velocity += (average - boid.velocity) * params.alignment_weight * params.dt;
+    }
+    if cohesion_count > 0u {
+        let center = position_sum / f32(cohesion_count);
+        velocity += (center - boid.position) * params.cohesion_weight * params.dt;
+    }
+    let speed = length(velocity);
+    if speed > 0.0 {
+        velocity *= clamp(speed, params.min_speed, params.max_speed) / speed;
+    }
+
+    // Leaving on one side comes back in on the other
+    var position = boid.position + velocity * params.dt;
+    position -= 2.0 * floor((position + 1.0) / 2.0);
+
+    dst_boids[index] = Boid(position, velocity);
+}
+
+// Vertex shader
+
+// The boid buffer doubles as the instance buffer
+struct InstanceInput {
+    @location(0) position: vec2<f32>,
+    @location(1) velocity: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+const BOID_SIZE: f32 = 0.01;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    boid: InstanceInput,
+) -> VertexOutput {
+    // A counter-clockwise triangle with its nose along x
+    var shape = array<vec2<f32>, 3>(
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(-0.5, 0.5),
+        vec2<f32>(-0.5, -0.5),
+    );
+    let corner = shape[vertex_index];
+
+    // Turns the nose where the boid is flying
+    let heading = atan2(boid.velocity.y, boid.velocity.x);
+    let c = cos(heading);
+    let s = sin(heading);
+    let rotated = vec2<f32>(corner.x * c - corner.y * s, corner.x * s + corner.y * c);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(boid.position + rotated * BOID_SIZE, 0.0, 1.0);
+    // The heading picks the hue, so flocks stand out by color
+    out.color = 0.5 + 0.5 * cos(vec3<f32>(heading) + vec3<f32>(0.0, 2.094, 4.189));
+    return out;
+}
+
+// Fragment shader
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
+
+@fragment
+fn fs_main_encode_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(
+        linear_to_srgb(in.color.r),
+        linear_to_srgb(in.color.g),
+        linear_to_srgb(in.color.b),
+        1.0,
+    );
+}
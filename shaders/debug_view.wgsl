@@ -0,0 +1,115 @@
+// Shows what the passes before it left in their targets, see
+// debug_view.rs. Drawn over the whole frame, or over the inset for the
+// shadow map. Same fullscreen triangle as post.wgsl.
+
+// What the scene pass drew, with the normals or the UVs instead of the
+// shading for those views and the overdraw counts in red for that one
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+// The depth targets are bound as plain textures, GLSL can't load texels of
+// depth textures
+@group(0) @binding(1)
+var t_depth: texture_2d<f32>;
+@group(0) @binding(2)
+var t_shadow_map: texture_2d<f32>;
+
+struct DebugView {
+    // Of the camera, to linearize the depth
+    near: f32,
+    far: f32,
+    // See `DebugView::index`
+    view: u32,
+};
+@group(0) @binding(3)
+var<uniform> debug: DebugView;
+
+const VIEW_DEPTH: u32 = 1u;
+const VIEW_OVERDRAW: u32 = 4u;
+const VIEW_SHADOW_MAP: u32 = 5u;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    // Texture coordinates go down, clip space goes up
+    out.tex_coords = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+// The texel at `tex_coords`, the targets can be smaller or bigger than the
+// frame with a render scale
+fn texel(size: vec2<u32>, tex_coords: vec2<f32>) -> vec2<u32> {
+    return min(vec2<u32>(tex_coords * vec2<f32>(size)), size - 1u);
+}
+
+// The distance from the camera of a depth buffer value, see
+// debug_view::linearize_depth
+fn linearize_depth(depth: f32) -> f32 {
+    return debug.near * debug.far / (debug.far - depth * (debug.far - debug.near));
+}
+
+// Nothing, then blue, green, yellow and red for 4 layers or more
+fn heat(count: f32) -> vec3<f32> {
+    if count < 0.5 {
+        return vec3<f32>(0.0);
+    }
+    if count < 1.5 {
+        return vec3<f32>(0.0, 0.2, 1.0);
+    }
+    if count < 2.5 {
+        return vec3<f32>(0.0, 1.0, 0.2);
+    }
+    if count < 3.5 {
+        return vec3<f32>(1.0, 1.0, 0.0);
+    }
+    return vec3<f32>(1.0, 0.0, 0.0);
+}
+
+fn debug_color(tex_coords: vec2<f32>) -> vec3<f32> {
+    switch debug.view {
+        case VIEW_DEPTH: {
+            let depth = textureLoad(t_depth, texel(textureDimensions(t_depth), tex_coords), 0).r;
+            // Black at the near plane to white at the far one
+            let distance = linearize_depth(depth);
+            return vec3<f32>((distance - debug.near) / (debug.far - debug.near));
+        }
+        case VIEW_OVERDRAW: {
+            let count = textureLoad(t_scene, texel(textureDimensions(t_scene), tex_coords), 0).r;
+            return heat(count);
+        }
+        case VIEW_SHADOW_MAP: {
+            let size = textureDimensions(t_shadow_map);
+            return vec3<f32>(textureLoad(t_shadow_map, texel(size, tex_coords), 0).r);
+        }
+        default: {
+            return textureLoad(t_scene, texel(textureDimensions(t_scene), tex_coords), 0).rgb;
+        }
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        return c / 12.92;
+    }
+    return pow((c + 0.055) / 1.055, 2.4);
+}
+
+// The values are meant to be seen as they are, so formats that encode them
+// on write get them decoded first
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = debug_color(in.tex_coords);
+    return vec4<f32>(srgb_to_linear(color.r), srgb_to_linear(color.g), srgb_to_linear(color.b), 1.0);
+}
+
+@fragment
+fn fs_main_encode_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(debug_color(in.tex_coords), 1.0);
+}
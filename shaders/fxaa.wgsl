@@ -0,0 +1,181 @@
+// FXAA 3.11 with the default quality preset (12), see fxaa.rs. Finds the
+// edges by the contrast in luma around each pixel, walks along them to
+// find where they end and blends the pixel with its neighbour across the
+// edge by how close it is to an end. Same fullscreen triangle as post.wgsl.
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+struct Fxaa {
+    texel_size: vec2<f32>,
+    // How much to blur pixels thinner than an edge, 0 to 1
+    subpixel: f32,
+    // How much contrast an edge needs, relative to the brightest luma
+    edge_threshold: f32,
+    // And at least, so dark areas aren't processed
+    edge_threshold_min: f32,
+    // Non-zero when the texture decodes to linear colors on read
+    linear_input: u32,
+};
+@group(0) @binding(2)
+var<uniform> fxaa: Fxaa;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    // Texture coordinates go down, clip space goes up
+    out.tex_coords = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+// FXAA wants perceptual luma, the square root is close enough to the sRGB
+// curve
+fn luma(color: vec4<f32>) -> f32 {
+    let luma = dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114));
+    if fxaa.linear_input != 0u {
+        return sqrt(luma);
+    }
+    return luma;
+}
+
+fn luma_at(tex_coords: vec2<f32>) -> f32 {
+    return luma(textureSampleLevel(t_source, s_source, tex_coords, 0.0));
+}
+
+fn luma_offset(tex_coords: vec2<f32>, offset: vec2<f32>) -> f32 {
+    return luma_at(tex_coords + offset * fxaa.texel_size);
+}
+
+// The colors are written as they are read, the sRGB encoding is up to the
+// formats
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pos_m = in.tex_coords;
+    let color_m = textureSampleLevel(t_source, s_source, pos_m, 0.0);
+    let luma_m = luma(color_m);
+    var luma_s = luma_offset(pos_m, vec2<f32>(0.0, 1.0));
+    let luma_e = luma_offset(pos_m, vec2<f32>(1.0, 0.0));
+    var luma_n = luma_offset(pos_m, vec2<f32>(0.0, -1.0));
+    let luma_w = luma_offset(pos_m, vec2<f32>(-1.0, 0.0));
+
+    let range_max = max(max(max(luma_s, luma_e), max(luma_n, luma_w)), luma_m);
+    let range_min = min(min(min(luma_s, luma_e), min(luma_n, luma_w)), luma_m);
+    let range = range_max - range_min;
+    if range < max(fxaa.edge_threshold_min, range_max * fxaa.edge_threshold) {
+        return color_m;
+    }
+
+    let luma_nw = luma_offset(pos_m, vec2<f32>(-1.0, -1.0));
+    let luma_se = luma_offset(pos_m, vec2<f32>(1.0, 1.0));
+    let luma_ne = luma_offset(pos_m, vec2<f32>(1.0, -1.0));
+    let luma_sw = luma_offset(pos_m, vec2<f32>(-1.0, 1.0));
+
+    // Whether the edge runs horizontally, from how much the luma changes
+    // across rows compared to across columns
+    let luma_ns = luma_n + luma_s;
+    let luma_we = luma_w + luma_e;
+    let luma_nese = luma_ne + luma_se;
+    let luma_nwne = luma_nw + luma_ne;
+    let luma_nwsw = luma_nw + luma_sw;
+    let luma_swse = luma_sw + luma_se;
+    let edge_horz = abs(-2.0 * luma_w + luma_nwsw)
+        + abs(-2.0 * luma_m + luma_ns) * 2.0
+        + abs(-2.0 * luma_e + luma_nese);
+    let edge_vert = abs(-2.0 * luma_s + luma_swse)
+        + abs(-2.0 * luma_m + luma_we) * 2.0
+        + abs(-2.0 * luma_n + luma_nwne);
+    let horz_span = edge_horz >= edge_vert;
+
+    // How much the pixel differs from its neighbourhood, for the subpixel
+    // blur
+    let subpix_a = (luma_ns + luma_we) * 2.0 + luma_nwsw + luma_nese;
+    let subpix_b = subpix_a * (1.0 / 12.0) - luma_m;
+    let subpix_c = saturate(abs(subpix_b) / range);
+
+    // From here on N and S are the neighbours across the edge
+    var length_sign = fxaa.texel_size.x;
+    if horz_span {
+        length_sign = fxaa.texel_size.y;
+    } else {
+        luma_n = luma_w;
+        luma_s = luma_e;
+    }
+    let gradient_n = luma_n - luma_m;
+    let gradient_s = luma_s - luma_m;
+    let pair_n = abs(gradient_n) >= abs(gradient_s);
+    let gradient = max(abs(gradient_n), abs(gradient_s));
+    var luma_nn = luma_s + luma_m;
+    if pair_n {
+        length_sign = -length_sign;
+        luma_nn = luma_n + luma_m;
+    }
+
+    // Walk along the edge both ways, halfway between the pixel and the
+    // neighbour with the bigger gradient, until the luma differs from the
+    // edge's average by more than a quarter of the gradient
+    var pos_b = pos_m;
+    var off_np = vec2<f32>(0.0, fxaa.texel_size.y);
+    if horz_span {
+        pos_b.y += length_sign * 0.5;
+        off_np = vec2<f32>(fxaa.texel_size.x, 0.0);
+    } else {
+        pos_b.x += length_sign * 0.5;
+    }
+    // The quality preset's steps, growing as the search goes on
+    var steps = array<f32, 5>(1.0, 1.5, 2.0, 4.0, 12.0);
+    let gradient_scaled = gradient * 0.25;
+    let luma_mm = luma_m - luma_nn * 0.5;
+    var pos_n = pos_b - off_np * steps[0];
+    var pos_p = pos_b + off_np * steps[0];
+    var luma_end_n = luma_at(pos_n) - luma_nn * 0.5;
+    var luma_end_p = luma_at(pos_p) - luma_nn * 0.5;
+    var done_n = abs(luma_end_n) >= gradient_scaled;
+    var done_p = abs(luma_end_p) >= gradient_scaled;
+    for (var i = 1u; i < 5u && !(done_n && done_p); i++) {
+        if !done_n {
+            pos_n -= off_np * steps[i];
+            luma_end_n = luma_at(pos_n) - luma_nn * 0.5;
+            done_n = abs(luma_end_n) >= gradient_scaled;
+        }
+        if !done_p {
+            pos_p += off_np * steps[i];
+            luma_end_p = luma_at(pos_p) - luma_nn * 0.5;
+            done_p = abs(luma_end_p) >= gradient_scaled;
+        }
+    }
+
+    // Blend towards the closer end, as long as the luma there goes the
+    // same way as at the pixel
+    var dst_n = pos_m.y - pos_n.y;
+    var dst_p = pos_p.y - pos_m.y;
+    if horz_span {
+        dst_n = pos_m.x - pos_n.x;
+        dst_p = pos_p.x - pos_m.x;
+    }
+    let luma_m_lt_zero = luma_mm < 0.0;
+    let good_span_n = (luma_end_n < 0.0) != luma_m_lt_zero;
+    let good_span_p = (luma_end_p < 0.0) != luma_m_lt_zero;
+    let good_span = select(good_span_p, good_span_n, dst_n < dst_p);
+    let pixel_offset = min(dst_n, dst_p) * (-1.0 / (dst_n + dst_p)) + 0.5;
+    let subpix_f = (-2.0 * subpix_c + 3.0) * subpix_c * subpix_c;
+    let subpix_h = subpix_f * subpix_f * fxaa.subpixel;
+    let offset = max(select(0.0, pixel_offset, good_span), subpix_h) * length_sign;
+
+    var pos = pos_m;
+    if horz_span {
+        pos.y += offset;
+    } else {
+        pos.x += offset;
+    }
+    return vec4<f32>(textureSampleLevel(t_source, s_source, pos, 0.0).rgb, color_m.a);
+}
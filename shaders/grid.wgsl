@@ -0,0 +1,108 @@
+// The ground grid, see grid.rs. A fullscreen triangle casts a ray through
+// every pixel and draws the lines where it hits the y = 0 plane, at the
+// depth of the hit so the scene sits on the grid and hides it.
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_sky_view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// See grid::MIN_HEIGHT
+const MIN_HEIGHT: f32 = 0.01;
+const MINOR_ALPHA: f32 = 0.35;
+const MAJOR_ALPHA: f32 = 0.7;
+const LINE_COLOR: vec3<f32> = vec3<f32>(0.6, 0.6, 0.6);
+const X_AXIS_COLOR: vec3<f32> = vec3<f32>(1.0, 0.1, 0.1);
+const Z_AXIS_COLOR: vec3<f32> = vec3<f32>(0.1, 0.2, 1.0);
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    var out: VertexOutput;
+    out.ndc = uv * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(out.ndc, 0.0, 1.0);
+    return out;
+}
+
+fn unproject(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    let world = camera.inv_view_proj * vec4<f32>(ndc, depth, 1.0);
+    return world.xyz / world.w;
+}
+
+// How much of the pixel a line every `spacing` covers at `position`, faded
+// out before the cells get too small for the pixels to tell them apart
+fn grid_lines(position: vec2<f32>, spacing: f32) -> f32 {
+    let coord = position / spacing;
+    let cells_per_pixel = fwidth(coord);
+    let distance = abs(fract(coord - 0.5) - 0.5) / cells_per_pixel;
+    let line = 1.0 - min(min(distance.x, distance.y), 1.0);
+    return line * (1.0 - smoothstep(0.1, 0.3, max(cells_per_pixel.x, cells_per_pixel.y)));
+}
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+};
+
+// Works for any projection, the ray goes from the near to the far plane
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let near = unproject(in.ndc, 0.0);
+    let far = unproject(in.ndc, 1.0);
+    let ray = far - near;
+    let parallel = abs(ray.y) < 1e-6;
+    let t = -near.y / select(ray.y, 1.0, parallel);
+    let hit = near + ray * t;
+
+    // The lines are every power of ten under the camera's height, and the
+    // next power up is brighter. As the camera rises the fine lines fade
+    // out and the bright ones dim, so there's no pop at the next power
+    let level = log2(max(abs(camera.view_pos.y), MIN_HEIGHT)) / log2(10.0);
+    let spacing = pow(10.0, floor(level));
+    let blend = fract(level);
+    var alpha = max(
+        grid_lines(hit.xz, spacing) * MINOR_ALPHA * (1.0 - blend),
+        grid_lines(hit.xz, spacing * 10.0) * mix(MAJOR_ALPHA, MINOR_ALPHA, blend),
+    );
+    alpha = max(alpha, grid_lines(hit.xz, spacing * 100.0) * MAJOR_ALPHA * blend);
+
+    // The X axis runs where z is 0 and the Z axis where x is 0
+    let axis_width = fwidth(hit.xz) * 1.5;
+    var color = LINE_COLOR;
+    if abs(hit.z) < axis_width.y {
+        color = X_AXIS_COLOR;
+        alpha = 1.0;
+    } else if abs(hit.x) < axis_width.x {
+        color = Z_AXIS_COLOR;
+        alpha = 1.0;
+    }
+
+    // Far away the lines would blur into noise, and close to the horizon
+    // they bunch up even before that
+    let distance = length(hit - camera.view_pos.xyz);
+    let height = max(abs(camera.view_pos.y), 1.0);
+    alpha *= 1.0 - smoothstep(10.0 * height, 40.0 * height, distance);
+    alpha *= smoothstep(0.0, 0.15, abs(normalize(ray).y));
+    // Only now, the derivatives need the neighbouring pixels. Parallel to
+    // the ground or with the ground outside the planes there's nothing
+    // to draw
+    if parallel || t < 0.0 || t > 1.0 || alpha <= 0.0 {
+        discard;
+    }
+
+    let clip = camera.view_proj * vec4<f32>(hit, 1.0);
+    var out: FragmentOutput;
+    out.color = vec4<f32>(color, alpha);
+    out.depth = clip.z / clip.w;
+    return out;
+}
@@ -0,0 +1,74 @@
+// Vertex shader
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_sky_view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct Light {
+    position: vec3<f32>,
+    intensity: f32,
+    color: vec3<f32>,
+    radius: f32,
+};
+struct Lights {
+    ambient: vec3<f32>,
+    count: u32,
+};
+@group(1) @binding(0)
+var<uniform> scene_lights: Lights;
+// Replaced with a uniform array where shaders can't read storage buffers,
+// see light::shader_source
+@group(1) @binding(1)
+var<storage, read> lights: array<Light>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    // Each light source is drawn as a small copy of the model at its
+    // position, one instance per light
+    let light = lights[instance_index];
+    let scale = 0.25;
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(model.position * scale + light.position, 1.0);
+    out.color = light.color * light.intensity;
+    return out;
+}
+
+// Fragment shader
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
+
+@fragment
+fn fs_main_encode_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(
+        linear_to_srgb(in.color.r),
+        linear_to_srgb(in.color.g),
+        linear_to_srgb(in.color.b),
+        1.0,
+    );
+}
@@ -0,0 +1,57 @@
+// Downsamples one mip level into the next one. Each texel of the smaller
+// level is the average of the texels it covers: 2 along an axis the source
+// is even on, and along an odd one 3, the outer ones only partly, so
+// nothing of the last row or column gets left out. Drawn as a single
+// triangle covering the whole level, like the post-processing passes.
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> @builtin(position) vec4<f32> {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// The source texels from `first` on that a texel covers along one axis, and
+// how much each of them counts.
+struct Footprint {
+    first: u32,
+    weights: vec3<f32>,
+};
+
+fn footprint(texel: u32, source_size: u32) -> Footprint {
+    if source_size == 1u {
+        return Footprint(0u, vec3<f32>(1.0, 0.0, 0.0));
+    }
+    if source_size % 2u == 0u {
+        return Footprint(2u * texel, vec3<f32>(0.5, 0.5, 0.0));
+    }
+    // 2n + 1 texels go into n, e.g. 5 into 2 as 1, 1, 1/2 and 1/2, 1, 1
+    let size = source_size / 2u;
+    let weights = vec3<f32>(f32(size - texel), f32(size), f32(texel + 1u));
+    return Footprint(2u * texel, weights / f32(source_size));
+}
+
+// sRGB textures decode when loaded and encode when written, so there's no
+// variant encoding the output: the average is taken in linear space either
+// way, and data textures like normal maps shouldn't be encoded at all.
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let source_size = textureDimensions(t_source);
+    let texel = vec2<u32>(position.xy);
+    let x = footprint(texel.x, source_size.x);
+    let y = footprint(texel.y, source_size.y);
+    var sum = vec4<f32>(0.0);
+    for (var j = 0u; j < 3u; j++) {
+        for (var i = 0u; i < 3u; i++) {
+            let weight = x.weights[i] * y.weights[j];
+            if weight > 0.0 {
+                let source = vec2<u32>(x.first + i, y.first + j);
+                sum += weight * textureLoad(t_source, source, 0);
+            }
+        }
+    }
+    return sum;
+}
@@ -0,0 +1,77 @@
+// Draws the outline around the selected instance, see outline.rs. The
+// instance is drawn once as it is to mark it in the stencil buffer, then
+// grown by the thickness with a flat color where it isn't marked.
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_sky_view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct Outline {
+    color: vec4<f32>,
+    // How much bigger the outlined instance gets, as a fraction of its size
+    thickness: f32,
+};
+@group(1) @binding(0)
+var<uniform> outline: Outline;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+};
+
+fn clip_position(model: VertexInput, instance: InstanceInput, scale: f32) -> vec4<f32> {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    return camera.view_proj * model_matrix * vec4<f32>(model.position * scale, 1.0);
+}
+
+// The instance as it is, for the stencil mark
+@vertex
+fn vs_mask(model: VertexInput, instance: InstanceInput) -> @builtin(position) vec4<f32> {
+    return clip_position(model, instance, 1.0);
+}
+
+// Grown around its origin, only what sticks out past the mark gets drawn
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput) -> @builtin(position) vec4<f32> {
+    return clip_position(model, instance, 1.0 + outline.thickness);
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+// Used when the target format is sRGB or float: the output stays linear.
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return outline.color;
+}
+
+// Used when the target format is neither: we have to encode the output
+// ourselves or everything comes out darker.
+@fragment
+fn fs_main_encode_srgb() -> @location(0) vec4<f32> {
+    return vec4<f32>(
+        linear_to_srgb(outline.color.r),
+        linear_to_srgb(outline.color.g),
+        linear_to_srgb(outline.color.b),
+        outline.color.a,
+    );
+}
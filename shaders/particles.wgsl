@@ -0,0 +1,224 @@
+// Compute shaders
+
+struct Particle {
+    position: vec3<f32>,
+    age: f32,
+    velocity: vec3<f32>,
+    lifetime: f32,
+};
+
+struct SimParams {
+    gravity: vec3<f32>,
+    dt: f32,
+    eye: vec3<f32>,
+    capacity: u32,
+    // Which of the two entries in draws counts the particles read
+    src: u32,
+    spawn_count: u32,
+};
+
+// The arguments the particles get drawn with, the instance count is how
+// many of them are alive
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    first_vertex: u32,
+    first_instance: u32,
+};
+
+struct SortStep {
+    k: u32,
+    j: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: SimParams;
+// The particles are read from one buffer and written into the other, so
+// no invocation reads what another one has already written
+@group(0) @binding(1)
+var<storage, read> src_particles: array<Particle>;
+@group(0) @binding(2)
+var<storage, read_write> dst_particles: array<Particle>;
+@group(0) @binding(3)
+var<storage, read_write> draws: array<DrawArgs, 2>;
+@group(0) @binding(4)
+var<storage, read> spawns: array<Particle>;
+@group(0) @binding(5)
+var<uniform> sort_step: SortStep;
+
+// How much speed a particle keeps when bouncing off the ground
+const RESTITUTION: f32 = 0.6;
+
+// Claims a slot in the output buffer. Anything at or past the capacity
+// means the buffer is full.
+fn allocate() -> u32 {
+    let dst = 1u - params.src;
+    let slot = atomicAdd(&draws[dst].instance_count, 1u);
+    if slot >= params.capacity {
+        // Keeps the count from going past the end of the buffer
+        atomicSub(&draws[dst].instance_count, 1u);
+    }
+    return slot;
+}
+
+// Must match compute::WORKGROUP_SIZE
+@compute @workgroup_size(64)
+fn cs_simulate(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    // Enough workgroups are dispatched for a full buffer, usually fewer
+    // particles are alive
+    if index >= atomicLoad(&draws[params.src].instance_count) {
+        return;
+    }
+
+    var particle = src_particles[index];
+    particle.age += params.dt;
+    // Expired particles simply aren't written out again
+    if particle.age >= particle.lifetime {
+        return;
+    }
+    particle.velocity += params.gravity * params.dt;
+    particle.position += particle.velocity * params.dt;
+
+    if particle.position.y < 0.0 {
+        particle.position.y = -particle.position.y;
+        particle.velocity = particle.velocity * RESTITUTION;
+        particle.velocity.y = -particle.velocity.y;
+    }
+
+    let slot = allocate();
+    if slot < params.capacity {
+        dst_particles[slot] = particle;
+    }
+}
+
+@compute @workgroup_size(64)
+fn cs_emit(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.spawn_count {
+        return;
+    }
+    let slot = allocate();
+    if slot < params.capacity {
+        dst_particles[slot] = spawns[id.x];
+    }
+}
+
+fn distance_to_eye(particle: Particle) -> f32 {
+    let offset = particle.position - params.eye;
+    return dot(offset, offset);
+}
+
+// One step of a bitonic sort putting the farthest particles first. Each
+// invocation compares one pair.
+@compute @workgroup_size(64)
+fn cs_sort(@builtin(global_invocation_id) id: vec3<u32>) {
+    let t = id.x;
+    let k = sort_step.k;
+    let j = sort_step.j;
+    let low = 2u * j * (t / j) + t % j;
+    var high = low + j;
+    // The first step of each merge compares mirrored pairs, that way every
+    // comparison sorts the same way around
+    if j == k / 2u {
+        high = (low / k) * k + k - 1u - t % j;
+    }
+    // The rest of the buffer counts as infinitely close, it never moves
+    if high >= atomicLoad(&draws[1u - params.src].instance_count) {
+        return;
+    }
+
+    let a = dst_particles[low];
+    let b = dst_particles[high];
+    if distance_to_eye(b) > distance_to_eye(a) {
+        dst_particles[low] = b;
+        dst_particles[high] = a;
+    }
+}
+
+// Vertex shader
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_sky_view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// The particle buffer doubles as the instance buffer
+struct InstanceInput {
+    @location(0) position_age: vec4<f32>,
+    @location(1) velocity_lifetime: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    // Where in the quad the fragment is, from -1 to 1
+    @location(1) corner: vec2<f32>,
+};
+
+const PARTICLE_SIZE: f32 = 0.05;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    particle: InstanceInput,
+) -> VertexOutput {
+    // Two counter-clockwise triangles making a quad facing the camera
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+
+    let corner = corners[vertex_index];
+
+    var out: VertexOutput;
+    // Offsetting the corners in clip space keeps the quad facing the
+    // camera, and it still shrinks with distance once divided by w
+    out.clip_position = camera.view_proj * vec4<f32>(particle.position_age.xyz, 1.0)
+        + vec4<f32>(corner * PARTICLE_SIZE, 0.0, 0.0);
+    out.corner = corner;
+    // Fast particles are yellow, slow ones red, and they fade out with age
+    let speed = clamp(length(particle.velocity_lifetime.xyz) / 10.0, 0.0, 1.0);
+    let fade = 1.0 - clamp(particle.position_age.w / particle.velocity_lifetime.w, 0.0, 1.0);
+    out.color = vec4<f32>(1.0, speed, 0.1, fade);
+    return out;
+}
+
+// Fragment shader
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+// A round spot, softer towards the edge
+fn spot_alpha(in: VertexOutput) -> f32 {
+    let distance = length(in.corner);
+    if distance > 1.0 {
+        discard;
+    }
+    return in.color.a * (1.0 - distance * distance);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color.rgb, spot_alpha(in));
+}
+
+@fragment
+fn fs_main_encode_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(
+        linear_to_srgb(in.color.r),
+        linear_to_srgb(in.color.g),
+        linear_to_srgb(in.color.b),
+        spot_alpha(in),
+    );
+}
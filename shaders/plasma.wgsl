@@ -0,0 +1,54 @@
+// An animated plasma covering the background, drawn instead of the skybox.
+// Shows off the globals every scene pipeline has at group 0.
+
+struct Globals {
+    resolution: vec2<f32>,
+    cursor: vec2<f32>,
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+};
+@group(0) @binding(1)
+var<uniform> globals: Globals;
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the screen at the far plane, like the skybox
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 1.0, 1.0);
+}
+
+fn plasma(position: vec2<f32>) -> vec3<f32> {
+    // Pixels to about -1..1 across the height, the cursor moves the center
+    let p = (position - globals.cursor) / globals.resolution.y * 4.0;
+    let t = globals.time;
+    let v = sin(p.x + t)
+        + sin((p.y + t) * 0.5)
+        + sin((p.x + p.y + t) * 0.5)
+        + sin(length(p + vec2<f32>(sin(t / 3.0), cos(t / 2.0)) * 2.0) + t);
+    // The colors go around the hue circle, at the same brightness
+    return 0.5 + 0.5 * cos(v * 1.5 + vec3<f32>(0.0, 2.094, 4.189));
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(plasma(position.xy), 1.0);
+}
+
+@fragment
+fn fs_main_encode_srgb(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = plasma(position.xy);
+    return vec4<f32>(
+        linear_to_srgb(color.r),
+        linear_to_srgb(color.g),
+        linear_to_srgb(color.b),
+        1.0,
+    );
+}
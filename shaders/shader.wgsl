@@ -0,0 +1,314 @@
+// Vertex shader
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_sky_view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct Globals {
+    resolution: vec2<f32>,
+    cursor: vec2<f32>,
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+    // See `DebugView::index`
+    debug_view: u32,
+};
+@group(0) @binding(1)
+var<uniform> globals: Globals;
+
+const DEBUG_VIEW_NORMALS: u32 = 2u;
+const DEBUG_VIEW_UVS: u32 = 3u;
+
+struct Light {
+    position: vec3<f32>,
+    intensity: f32,
+    color: vec3<f32>,
+    radius: f32,
+};
+struct Lights {
+    ambient: vec3<f32>,
+    count: u32,
+};
+@group(2) @binding(0)
+var<uniform> scene_lights: Lights;
+// Replaced with a uniform array where shaders can't read storage buffers,
+// see light::shader_source
+@group(2) @binding(1)
+var<storage, read> lights: array<Light>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) tangent: vec3<f32>,
+    @location(4) bitangent: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) normal_matrix_0: vec3<f32>,
+    @location(10) normal_matrix_1: vec3<f32>,
+    @location(11) normal_matrix_2: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) world_tangent: vec3<f32>,
+    @location(3) world_bitangent: vec3<f32>,
+    @location(4) world_position: vec3<f32>,
+};
+
+// The joint matrices of skinned models, bound with their materials.
+// Replaced with a uniform array where vertex shaders can't read storage
+// buffers, see animation::shader_source
+@group(1) @binding(6)
+var<storage, read> joints: array<mat4x4<f32>>;
+
+struct SkinInput {
+    @location(12) joints: vec4<u32>,
+    @location(13) weights: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    return transform_vertex(model, instance);
+}
+
+// Blends the vertex between its joints before the instance moves it. The
+// normals go through the same matrix, which is close enough as long as the
+// joints don't scale unevenly.
+@vertex
+fn vs_skinned(
+    model: VertexInput,
+    instance: InstanceInput,
+    skin: SkinInput,
+) -> VertexOutput {
+    let skin_matrix = skin.weights.x * joints[skin.joints.x]
+        + skin.weights.y * joints[skin.joints.y]
+        + skin.weights.z * joints[skin.joints.z]
+        + skin.weights.w * joints[skin.joints.w];
+    let skin_normal_matrix = mat3x3<f32>(
+        skin_matrix[0].xyz,
+        skin_matrix[1].xyz,
+        skin_matrix[2].xyz,
+    );
+
+    var skinned = model;
+    skinned.position = (skin_matrix * vec4<f32>(model.position, 1.0)).xyz;
+    skinned.normal = skin_normal_matrix * model.normal;
+    skinned.tangent = skin_normal_matrix * model.tangent;
+    skinned.bitangent = skin_normal_matrix * model.bitangent;
+    return transform_vertex(skinned, instance);
+}
+
+fn transform_vertex(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let normal_matrix = mat3x3<f32>(
+        instance.normal_matrix_0,
+        instance.normal_matrix_1,
+        instance.normal_matrix_2,
+    );
+
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.world_normal = normal_matrix * model.normal;
+    out.world_tangent = normal_matrix * model.tangent;
+    out.world_bitangent = normal_matrix * model.bitangent;
+    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+    out.world_position = world_position.xyz;
+    out.clip_position = camera.view_proj * world_position;
+    return out;
+}
+
+// Fragment shader
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+@group(1) @binding(2)
+var t_normal: texture_2d<f32>;
+@group(1) @binding(3)
+var s_normal: sampler;
+// Roughness in green, metallic in blue, like glTF
+@group(1) @binding(4)
+var t_metallic_roughness: texture_2d<f32>;
+@group(1) @binding(5)
+var s_metallic_roughness: sampler;
+
+struct Shadow {
+    light_view_proj: mat4x4<f32>,
+    enabled: u32,
+};
+@group(3) @binding(0)
+var<uniform> shadow: Shadow;
+@group(3) @binding(1)
+var t_shadow: texture_depth_2d;
+@group(3) @binding(2)
+var s_shadow: sampler_comparison;
+
+// How much of the light reaches `world_position`, from 0 in full shadow to
+// 1. Averages the comparisons of 3x3 texels around it (percentage-closer
+// filtering) so the edges of the shadows aren't jagged.
+fn shadow_factor(world_position: vec3<f32>) -> f32 {
+    if shadow.enabled == 0u {
+        return 1.0;
+    }
+    let light_clip = shadow.light_view_proj * vec4<f32>(world_position, 1.0);
+    let light_ndc = light_clip.xyz / light_clip.w;
+    // Texture coordinates go down where clip space goes up
+    let uv = vec2<f32>(light_ndc.x * 0.5 + 0.5, -light_ndc.y * 0.5 + 0.5);
+    // Outside of the shadow map nothing casts shadows
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || light_ndc.z > 1.0 {
+        return 1.0;
+    }
+
+    let texel_size = 1.0 / vec2<f32>(textureDimensions(t_shadow));
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel_size;
+            lit += textureSampleCompareLevel(t_shadow, s_shadow, uv + offset, light_ndc.z);
+        }
+    }
+    return lit / 9.0;
+}
+
+// How much of a light reaches `distance` away from it, fading out smoothly
+// to 0 at its radius.
+fn attenuation(distance: f32, radius: f32) -> f32 {
+    let falloff = clamp(1.0 - pow(distance / radius, 2.0), 0.0, 1.0);
+    return falloff * falloff;
+}
+
+// The normal map is in tangent space, the TBN matrix brings it into world
+// space where the lighting happens.
+fn surface_normal(in: VertexOutput, object_normal: vec3<f32>) -> vec3<f32> {
+    let tangent_matrix = mat3x3<f32>(
+        normalize(in.world_tangent),
+        normalize(in.world_bitangent),
+        normalize(in.world_normal),
+    );
+    let tangent_normal = object_normal * 2.0 - 1.0;
+    return normalize(tangent_matrix * tangent_normal);
+}
+
+// Blinn-Phong shading, summed over the lights. The texture is sRGB so
+// sampling it already gives us linear values.
+//
+// The metallic-roughness map is approximated: rougher surfaces get a wider
+// and dimmer highlight, and metals lose their diffuse light. A smooth
+// dielectric (0, 0) shades as plain Blinn-Phong.
+fn shade(in: VertexOutput) -> vec3<f32> {
+    let object_color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    let object_normal = textureSample(t_normal, s_normal, in.tex_coords);
+    let metallic_roughness = textureSample(t_metallic_roughness, s_metallic_roughness, in.tex_coords);
+    let roughness = metallic_roughness.g;
+    let metallic = metallic_roughness.b;
+    let shininess = mix(32.0, 2.0, roughness);
+    let specular_weight = 1.0 - 0.75 * roughness;
+    let diffuse_weight = 1.0 - metallic;
+
+    let normal = surface_normal(in, object_normal.xyz);
+    let view_dir = normalize(camera.view_pos.xyz - in.world_position);
+    // Only the first light casts shadows
+    let shadow_lit = shadow_factor(in.world_position);
+
+    // The ambient light is the same whatever the lights, so it's only
+    // added once and reaches into the shadows
+    var color = scene_lights.ambient;
+    for (var i = 0u; i < scene_lights.count; i++) {
+        let light = lights[i];
+        let to_light = light.position - in.world_position;
+        let light_dir = normalize(to_light);
+        let half_dir = normalize(view_dir + light_dir);
+
+        let diffuse_strength = max(dot(normal, light_dir), 0.0);
+        let specular_strength = pow(max(dot(normal, half_dir), 0.0), shininess) * specular_weight;
+
+        var lit = attenuation(length(to_light), light.radius) * light.intensity;
+        if i == 0u {
+            lit *= shadow_lit;
+        }
+        color += lit * (diffuse_strength * diffuse_weight + specular_strength) * light.color;
+    }
+    return color * object_color.rgb;
+}
+
+// What the debug views showing the surfaces see instead of the shading,
+// or the shading without one
+fn surface_color(in: VertexOutput) -> vec3<f32> {
+    switch globals.debug_view {
+        case DEBUG_VIEW_NORMALS: {
+            let object_normal = textureSample(t_normal, s_normal, in.tex_coords).xyz;
+            return surface_normal(in, object_normal) * 0.5 + 0.5;
+        }
+        case DEBUG_VIEW_UVS: {
+            return vec3<f32>(fract(in.tex_coords), 0.0);
+        }
+        default: {
+            return shade(in);
+        }
+    }
+}
+
+// Converts a linear color component to the sRGB transfer curve.
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+// Used when the surface format is sRGB: the GPU encodes the output for us.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(surface_color(in), 1.0);
+}
+
+// Used when the surface format is not sRGB: we have to encode the output
+// ourselves or everything comes out darker.
+@fragment
+fn fs_main_encode_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = surface_color(in);
+    return vec4<f32>(
+        linear_to_srgb(color.r),
+        linear_to_srgb(color.g),
+        linear_to_srgb(color.b),
+        1.0,
+    );
+}
+
+// Adds 1 to the red of every fragment with additive blending, for
+// `DebugView::Overdraw` to count
+@fragment
+fn fs_overdraw(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+}
+
+// The lines of `DebugView::WireframeOverlay`, bright enough to show over
+// anything after the tonemapping
+@fragment
+fn fs_wireframe(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.2, 4.0, 0.4, 1.0);
+}
@@ -0,0 +1,104 @@
+// Vertex shader
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_sky_view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// Every layer of the array is the texture of some of the instances
+@group(1) @binding(0)
+var t_layers: texture_2d_array<f32>;
+@group(1) @binding(1)
+var s_layers: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) normal_matrix_0: vec3<f32>,
+    @location(10) normal_matrix_1: vec3<f32>,
+    @location(11) normal_matrix_2: vec3<f32>,
+    @location(14) layer: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_normal: vec3<f32>,
+    // Integers can't be interpolated, every vertex of the instance has the
+    // same layer anyway
+    @location(2) @interpolate(flat) layer: u32,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let normal_matrix = mat3x3<f32>(
+        instance.normal_matrix_0,
+        instance.normal_matrix_1,
+        instance.normal_matrix_2,
+    );
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
+    out.tex_coords = model.tex_coords;
+    out.world_normal = normal_matrix * model.normal;
+    out.layer = instance.layer;
+    return out;
+}
+
+// Fragment shader
+
+// A light from the top right behind the camera, enough to tell the faces
+// apart
+const LIGHT_DIRECTION: vec3<f32> = vec3<f32>(0.3, 0.8, 0.5);
+const AMBIENT: f32 = 0.35;
+
+fn shade(in: VertexOutput) -> vec3<f32> {
+    let color = textureSample(t_layers, s_layers, in.tex_coords, in.layer).rgb;
+    let diffuse = max(dot(normalize(in.world_normal), normalize(LIGHT_DIRECTION)), 0.0);
+    return color * min(AMBIENT + diffuse, 1.0);
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+// Used when the surface format is sRGB: the GPU encodes the output for us.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(shade(in), 1.0);
+}
+
+// Used when the surface format is not sRGB: we have to encode the output
+// ourselves or everything comes out darker.
+@fragment
+fn fs_main_encode_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = shade(in);
+    return vec4<f32>(
+        linear_to_srgb(color.r),
+        linear_to_srgb(color.g),
+        linear_to_srgb(color.b),
+        1.0,
+    );
+}
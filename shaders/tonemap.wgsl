@@ -0,0 +1,88 @@
+// Brings the HDR scene down to what the frame can show, see tonemap.rs.
+// Same fullscreen triangle as post.wgsl.
+
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(1)
+var s_scene: sampler;
+
+struct Tonemap {
+    exposure: f32,
+    // The operator: 0 clamps, 1 is Reinhard and 2 is ACES. `operator` is
+    // reserved in WGSL
+    curve: u32,
+};
+@group(1) @binding(0)
+var<uniform> tonemap: Tonemap;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    // Texture coordinates go down, clip space goes up
+    out.tex_coords = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+fn reinhard(c: vec3<f32>) -> vec3<f32> {
+    return c / (vec3<f32>(1.0) + c);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+fn aces(c: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c2 = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((c * (a * c + b)) / (c * (c2 * c + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// Still linear, the format or fs_main_encode_srgb encodes it afterwards
+fn tonemapped(in: VertexOutput) -> vec4<f32> {
+    let scene = textureSample(t_scene, s_scene, in.tex_coords);
+    let color = scene.rgb * tonemap.exposure;
+    var mapped: vec3<f32>;
+    switch tonemap.curve {
+        case 1u: {
+            mapped = reinhard(color);
+        }
+        case 2u: {
+            mapped = aces(color);
+        }
+        default: {
+            mapped = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+        }
+    }
+    return vec4<f32>(mapped, scene.a);
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return tonemapped(in);
+}
+
+@fragment
+fn fs_main_encode_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = tonemapped(in);
+    return vec4<f32>(
+        linear_to_srgb(color.r),
+        linear_to_srgb(color.g),
+        linear_to_srgb(color.b),
+        color.a,
+    );
+}
@@ -0,0 +1,101 @@
+// Vertex shader
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_sky_view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) normal_matrix_0: vec3<f32>,
+    @location(10) normal_matrix_1: vec3<f32>,
+    @location(11) normal_matrix_2: vec3<f32>,
+    @location(14) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_normal: vec3<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    let normal_matrix = mat3x3<f32>(
+        instance.normal_matrix_0,
+        instance.normal_matrix_1,
+        instance.normal_matrix_2,
+    );
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
+    out.world_normal = normal_matrix * model.normal;
+    out.color = instance.color;
+    return out;
+}
+
+// Fragment shader
+
+// The same light as the texture array demo
+const LIGHT_DIRECTION: vec3<f32> = vec3<f32>(0.3, 0.8, 0.5);
+const AMBIENT: f32 = 0.35;
+
+// The alpha is left alone, the blend state decides what it means
+fn shade(in: VertexOutput, front_facing: bool) -> vec4<f32> {
+    // Glass is seen from both sides, the back faces light like the front
+    var normal = normalize(in.world_normal);
+    if !front_facing {
+        normal = -normal;
+    }
+    let diffuse = max(dot(normal, normalize(LIGHT_DIRECTION)), 0.0);
+    return vec4<f32>(in.color.rgb * min(AMBIENT + diffuse, 1.0), in.color.a);
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+// Used when the surface format is sRGB: the GPU encodes the output for us.
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    return shade(in, front_facing);
+}
+
+// Used when the surface format is not sRGB: we have to encode the output
+// ourselves or everything comes out darker.
+@fragment
+fn fs_main_encode_srgb(
+    in: VertexOutput,
+    @builtin(front_facing) front_facing: bool,
+) -> @location(0) vec4<f32> {
+    let color = shade(in, front_facing);
+    return vec4<f32>(
+        linear_to_srgb(color.r),
+        linear_to_srgb(color.g),
+        linear_to_srgb(color.b),
+        color.a,
+    );
+}
@@ -0,0 +1,54 @@
+// Draws the scene rendered at another size into the frame, see
+// render_scale.rs. Same fullscreen triangle as post.wgsl.
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+struct Upscale {
+    target_size: vec2<f32>,
+    // Samples across each pixel, 1 when the source isn't bigger
+    taps: u32,
+};
+@group(0) @binding(2)
+var<uniform> upscale: Upscale;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    // Texture coordinates go down, clip space goes up
+    out.tex_coords = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+// The colors are written as they are read, the sRGB encoding is up to the
+// formats
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if upscale.taps <= 1u {
+        return textureSampleLevel(t_source, s_source, in.tex_coords, 0.0);
+    }
+    // A box filter: the average of taps x taps samples spread evenly over
+    // the part of the source the pixel covers, one per texel when the
+    // scale is a whole number
+    let pixel = 1.0 / upscale.target_size;
+    let corner = in.tex_coords - pixel * 0.5;
+    let taps = f32(upscale.taps);
+    var sum = vec4<f32>(0.0);
+    for (var y = 0u; y < upscale.taps; y++) {
+        for (var x = 0u; x < upscale.taps; x++) {
+            let offset = (vec2<f32>(f32(x), f32(y)) + 0.5) / taps;
+            sum += textureSampleLevel(t_source, s_source, corner + offset * pixel, 0.0);
+        }
+    }
+    return sum / (taps * taps);
+}
@@ -0,0 +1,57 @@
+// Darkens the scene towards the corners of the frame. Same fullscreen
+// triangle as post.wgsl.
+
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(1)
+var s_scene: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> VertexOutput {
+    // (-1, -1), (3, -1), (-1, 3)
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    // Texture coordinates go down, clip space goes up
+    out.tex_coords = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+// How dark the corners get, 1 would make them black
+const STRENGTH: f32 = 0.7;
+
+fn vignette(in: VertexOutput) -> vec4<f32> {
+    let color = textureSample(t_scene, s_scene, in.tex_coords);
+    // 0 in the center, about 0.71 in the corners
+    let distance = length(in.tex_coords - vec2<f32>(0.5));
+    let darkening = smoothstep(0.3, 0.75, distance) * STRENGTH;
+    return vec4<f32>(color.rgb * (1.0 - darkening), color.a);
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vignette(in);
+}
+
+@fragment
+fn fs_main_encode_srgb(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = vignette(in);
+    return vec4<f32>(
+        linear_to_srgb(color.r),
+        linear_to_srgb(color.g),
+        linear_to_srgb(color.b),
+        color.a,
+    );
+}
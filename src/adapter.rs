@@ -0,0 +1,134 @@
+use crate::{device_config::DeviceConfig, error::AppError};
+
+/// Which GPU to render with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AdapterSelection {
+    /// Lets wgpu pick, based on the power preference.
+    Auto(wgpu::PowerPreference),
+    /// The adapter at this index in `list_adapters`.
+    ByIndex(usize),
+    /// The first adapter whose name contains this, ignoring case.
+    ByNameSubstring(String),
+}
+
+impl Default for AdapterSelection {
+    fn default() -> Self {
+        Self::Auto(wgpu::PowerPreference::LowPower)
+    }
+}
+
+/// The adapters available on this machine, in the order `ByIndex` uses.
+/// Always empty on the web, where adapters can't be enumerated.
+pub fn list_adapters() -> Vec<wgpu::AdapterInfo> {
+    list_adapters_on(DeviceConfig::default().backends)
+}
+
+/// Like `list_adapters`, only the ones on `backends`.
+pub fn list_adapters_on(backends: wgpu::Backends) -> Vec<wgpu::AdapterInfo> {
+    enumerate_adapters(&crate::create_instance(backends))
+        .iter()
+        .map(wgpu::Adapter::get_info)
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn enumerate_adapters(instance: &wgpu::Instance) -> Vec<wgpu::Adapter> {
+    instance.enumerate_adapters(wgpu::Backends::all())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn enumerate_adapters(_instance: &wgpu::Instance) -> Vec<wgpu::Adapter> {
+    Vec::new()
+}
+
+/// Finds the adapter for `selection`. If there is a `surface` the adapter
+/// must be able to present to it.
+pub async fn select_adapter(
+    instance: &wgpu::Instance,
+    selection: &AdapterSelection,
+    surface: Option<&wgpu::Surface<'_>>,
+) -> Result<wgpu::Adapter, AppError> {
+    let adapter = match selection {
+        AdapterSelection::Auto(power_preference) => instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: *power_preference,
+                compatible_surface: surface,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or_else(|| no_adapter_error(instance, "No adapter found"))?,
+        AdapterSelection::ByIndex(index) => enumerate_adapters(instance)
+            .into_iter()
+            .nth(*index)
+            .ok_or_else(|| no_adapter_error(instance, &format!("No adapter at index {index}")))?,
+        AdapterSelection::ByNameSubstring(name) => {
+            let lowercase_name = name.to_lowercase();
+            enumerate_adapters(instance)
+                .into_iter()
+                .find(|adapter| {
+                    adapter
+                        .get_info()
+                        .name
+                        .to_lowercase()
+                        .contains(&lowercase_name)
+                })
+                .ok_or_else(|| {
+                    no_adapter_error(instance, &format!("No adapter name contains {name:?}"))
+                })?
+        }
+    };
+
+    match surface {
+        Some(surface) if !adapter.is_surface_supported(surface) => Err(no_adapter_error(
+            instance,
+            &format!(
+                "{} can't present to the window's surface",
+                describe_adapter(&adapter.get_info())
+            ),
+        )),
+        _ => Ok(adapter),
+    }
+}
+
+/// An error saying why no adapter could be used, listing the available ones.
+fn no_adapter_error(instance: &wgpu::Instance, reason: &str) -> AppError {
+    AppError::NoCompatibleAdapter {
+        reason: reason.to_owned(),
+        available: enumerate_adapters(instance)
+            .iter()
+            .map(|adapter| describe_adapter(&adapter.get_info()))
+            .collect(),
+    }
+}
+
+/// e.g. `NVIDIA GeForce RTX 3060 (Vulkan, DiscreteGpu)`
+pub fn describe_adapter(info: &wgpu::AdapterInfo) -> String {
+    format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type)
+}
+
+/// What the adapter the state renders with is and can do.
+#[derive(Clone, Debug)]
+pub struct GpuInfo {
+    pub info: wgpu::AdapterInfo,
+    pub limits: wgpu::Limits,
+    pub features: wgpu::Features,
+}
+
+impl GpuInfo {
+    pub fn new(adapter: &wgpu::Adapter) -> Self {
+        Self {
+            info: adapter.get_info(),
+            limits: adapter.limits(),
+            features: adapter.features(),
+        }
+    }
+
+    /// `describe_adapter`, then the driver if the backend says which.
+    pub fn describe(&self) -> String {
+        let driver = format!("{} {}", self.info.driver, self.info.driver_info);
+        match driver.trim() {
+            "" => describe_adapter(&self.info),
+            driver => format!("{}\nDriver: {driver}", describe_adapter(&self.info)),
+        }
+    }
+}
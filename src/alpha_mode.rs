@@ -0,0 +1,47 @@
+use wgpu::CompositeAlphaMode;
+
+/// Whether the compositor lets what's behind the window show through where
+/// `mode` has alpha below 1.
+pub fn is_transparent(mode: CompositeAlphaMode) -> bool {
+    matches!(
+        mode,
+        CompositeAlphaMode::PreMultiplied | CompositeAlphaMode::PostMultiplied
+    )
+}
+
+/// Picks one of `supported`, the surface's alpha modes. Transparent windows
+/// prefer `PreMultiplied`, which is what blending onto a transparent clear
+/// color produces, then `PostMultiplied`. Without either of them, and for
+/// opaque windows, it's `Opaque` if supported, otherwise the surface's
+/// default.
+pub fn select_alpha_mode(
+    transparent: bool,
+    supported: &[CompositeAlphaMode],
+) -> CompositeAlphaMode {
+    let transparent_modes: &[_] = if transparent {
+        &[
+            CompositeAlphaMode::PreMultiplied,
+            CompositeAlphaMode::PostMultiplied,
+        ]
+    } else {
+        &[]
+    };
+    transparent_modes
+        .iter()
+        .chain(&[CompositeAlphaMode::Opaque])
+        .copied()
+        .find(|mode| supported.contains(mode))
+        .or_else(|| supported.first().copied())
+        .unwrap_or(CompositeAlphaMode::Auto)
+}
+
+/// How the scene pipelines write their colors for `mode`. With
+/// `PreMultiplied`, blending with `ALPHA_BLENDING` onto a target cleared to
+/// alpha 0 leaves premultiplied colors behind. The other modes take the
+/// colors as they are.
+pub fn blend_state(mode: CompositeAlphaMode) -> wgpu::BlendState {
+    match mode {
+        CompositeAlphaMode::PreMultiplied => wgpu::BlendState::ALPHA_BLENDING,
+        _ => wgpu::BlendState::REPLACE,
+    }
+}
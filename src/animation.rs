@@ -0,0 +1,457 @@
+//! Skeletal animation for glTF models. An `Animator` plays the keyframes of
+//! a clip on the nodes of the model and turns the joints' transforms into
+//! the joint matrices the skinned vertex shader blends the vertices with.
+
+use std::{borrow::Cow, time::Duration};
+
+use glam::{Mat4, Quat, Vec3};
+use thiserror::Error;
+
+use crate::storage::{ArrayBinding, StorageBuffer, StorageSupport};
+
+/// How many joints the shaders can see where the joint matrices are in a
+/// uniform array instead of a storage buffer. 8 KiB of matrices, half the
+/// smallest uniform buffer binding wgpu allows.
+pub const MAX_UNIFORM_JOINTS: usize = 128;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum AnimationError {
+    #[error("there's no animation called {0:?}")]
+    UnknownClip(String),
+    #[error("the model has no skins to animate")]
+    NotSkinned,
+    #[error(
+        "the model has {count} joints, but this device can only skin with {max} \
+         (it has no storage buffers in vertex shaders)"
+    )]
+    TooManyJoints { count: usize, max: usize },
+}
+
+/// How the joint matrices get to the skinned vertex shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JointBinding {
+    /// A read-only storage buffer, as big as it needs to be.
+    Storage,
+    /// An array of `MAX_UNIFORM_JOINTS` in a uniform buffer, for WebGL2 and
+    /// other downlevel targets whose vertex shaders can't read storage
+    /// buffers.
+    Uniform,
+}
+
+impl JointBinding {
+    /// Storage buffers where the vertex shaders can read them.
+    pub fn for_support(support: StorageSupport) -> Self {
+        match support.binding(wgpu::ShaderStages::VERTEX, MAX_UNIFORM_JOINTS) {
+            ArrayBinding::Storage => Self::Storage,
+            ArrayBinding::Uniform { .. } => Self::Uniform,
+        }
+    }
+
+    pub fn array_binding(self) -> ArrayBinding {
+        match self {
+            Self::Storage => ArrayBinding::Storage,
+            Self::Uniform => ArrayBinding::Uniform {
+                len: MAX_UNIFORM_JOINTS,
+            },
+        }
+    }
+
+    /// The most joints a model can have, `None` if only the size of the
+    /// buffer limits them.
+    pub fn max_joints(self) -> Option<usize> {
+        self.array_binding().max_len()
+    }
+
+    /// Fails if a model with `count` joints can't be skinned like this.
+    pub fn check_joint_count(self, count: usize) -> Result<(), AnimationError> {
+        match self.max_joints() {
+            Some(max) if count > max => Err(AnimationError::TooManyJoints { count, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The layout entry for the joint matrices at `binding`.
+    pub fn layout_entry(self, binding: u32) -> wgpu::BindGroupLayoutEntry {
+        self.array_binding()
+            .layout_entry(binding, wgpu::ShaderStages::VERTEX)
+    }
+}
+
+/// Adapts the WGSL `source` of a shader reading the joint matrices to
+/// `binding`, like `light::shader_source` does for the lights.
+pub fn shader_source(source: &str, binding: JointBinding) -> Cow<'_, str> {
+    binding
+        .array_binding()
+        .shader_source(source, "joints", "mat4x4<f32>")
+}
+
+/// Which joints move a vertex and how much, in a second vertex buffer next
+/// to the `ModelVertex`es of skinned meshes.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkinVertex {
+    /// Indices into all of the model's joint matrices.
+    pub joints: [u32; 4],
+    /// Add up to 1.
+    pub weights: [f32; 4],
+}
+
+impl SkinVertex {
+    /// After the `ModelVertex` and `InstanceRaw` attributes.
+    pub const ATTRIBS: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![
+        12 => Uint32x4,
+        13 => Float32x4,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SkinVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// A node's transform relative to its parent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<usize>,
+    /// Where it is when no animation moves it.
+    pub rest: Transform,
+}
+
+/// The joints moving a skinned mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skin {
+    /// Indices into the nodes.
+    pub joints: Vec<usize>,
+    /// Brings the mesh into the space of each joint, for the pose it was
+    /// modeled in.
+    pub inverse_bind_matrices: Vec<Mat4>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// Jumps from one keyframe to the next.
+    Step,
+    /// Lerps translations and scales, slerps rotations.
+    #[default]
+    Linear,
+}
+
+/// The values a channel animates, one per keyframe.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Keyframes {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+}
+
+impl Keyframes {
+    fn len(&self) -> usize {
+        match self {
+            Self::Translation(values) | Self::Scale(values) => values.len(),
+            Self::Rotation(values) => values.len(),
+        }
+    }
+}
+
+/// Animates one property of one node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub node: usize,
+    /// In seconds, increasing, as many as there are keyframes.
+    pub times: Vec<f32>,
+    pub keyframes: Keyframes,
+    pub interpolation: Interpolation,
+}
+
+impl Channel {
+    /// Sets the property of `transform` for `time`, holding the first and
+    /// last keyframes before and after them.
+    fn apply(&self, time: f32, transform: &mut Transform) {
+        let count = self.times.len().min(self.keyframes.len());
+        if count == 0 {
+            return;
+        }
+        let next = self.times[..count].partition_point(|&t| t <= time);
+        let (from, to, factor) = if next == 0 {
+            (0, 0, 0.0)
+        } else if next == count {
+            (count - 1, count - 1, 0.0)
+        } else {
+            let (start, end) = (self.times[next - 1], self.times[next]);
+            let factor = match self.interpolation {
+                Interpolation::Step => 0.0,
+                Interpolation::Linear => (time - start) / (end - start).max(f32::EPSILON),
+            };
+            (next - 1, next, factor)
+        };
+
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                transform.translation = values[from].lerp(values[to], factor);
+            }
+            Keyframes::Rotation(values) => {
+                transform.rotation = values[from].slerp(values[to], factor).normalize();
+            }
+            Keyframes::Scale(values) => transform.scale = values[from].lerp(values[to], factor),
+        }
+    }
+}
+
+/// A named animation, e.g. "Walk".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+    pub name: String,
+    pub channels: Vec<Channel>,
+}
+
+impl Clip {
+    /// When the last keyframe is, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .filter_map(|channel| channel.times.last())
+            .fold(0.0, |duration: f32, &time| duration.max(time))
+    }
+}
+
+/// Everything an `Animator` needs from a model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton {
+    pub nodes: Vec<Node>,
+    pub skins: Vec<Skin>,
+    pub clips: Vec<Clip>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Playback {
+    clip: usize,
+    time: f32,
+    looping: bool,
+}
+
+/// Plays a skeleton's clips and keeps its joint matrices up to date.
+/// Switching clips jumps straight to the new one, without blending.
+#[derive(Debug, Clone)]
+pub struct Animator {
+    skeleton: Skeleton,
+    /// Parents before their children, so the world transforms can be
+    /// computed in one go.
+    order: Vec<usize>,
+    pose: Vec<Transform>,
+    world: Vec<Mat4>,
+    /// Where each skin's matrices start in `joint_matrices`.
+    joint_offsets: Vec<usize>,
+    joint_matrices: Vec<Mat4>,
+    playback: Option<Playback>,
+}
+
+impl Animator {
+    /// Starts out in the rest pose. The nodes' parents must not form a
+    /// cycle, `gltf::read` makes sure of that.
+    pub fn new(skeleton: Skeleton) -> Self {
+        let depth = |mut node: usize| {
+            let mut depth = 0;
+            while let Some(parent) = skeleton.nodes[node].parent {
+                node = parent;
+                depth += 1;
+            }
+            depth
+        };
+        let mut order: Vec<usize> = (0..skeleton.nodes.len()).collect();
+        order.sort_by_cached_key(|&node| depth(node));
+
+        let mut joint_offsets = Vec::with_capacity(skeleton.skins.len());
+        let mut joint_count = 0;
+        for skin in &skeleton.skins {
+            joint_offsets.push(joint_count);
+            joint_count += skin.joints.len();
+        }
+
+        let mut animator = Self {
+            pose: skeleton.nodes.iter().map(|node| node.rest).collect(),
+            world: vec![Mat4::IDENTITY; skeleton.nodes.len()],
+            joint_matrices: vec![Mat4::IDENTITY; joint_count],
+            skeleton,
+            order,
+            joint_offsets,
+            playback: None,
+        };
+        animator.update_joints();
+        animator
+    }
+
+    pub fn skeleton(&self) -> &Skeleton {
+        &self.skeleton
+    }
+
+    pub fn clip_names(&self) -> impl Iterator<Item = &str> {
+        self.skeleton.clips.iter().map(|clip| clip.name.as_str())
+    }
+
+    /// Starts the clip `name` from the beginning, replacing the one that's
+    /// playing.
+    pub fn play(&mut self, name: &str, looping: bool) -> Result<(), AnimationError> {
+        let clip = self
+            .skeleton
+            .clips
+            .iter()
+            .position(|clip| clip.name == name)
+            .ok_or_else(|| AnimationError::UnknownClip(name.to_owned()))?;
+        self.playback = Some(Playback {
+            clip,
+            time: 0.0,
+            looping,
+        });
+        self.sample();
+        Ok(())
+    }
+
+    /// Goes back to the rest pose.
+    pub fn stop(&mut self) {
+        self.playback = None;
+        self.sample();
+    }
+
+    /// The name of the clip that's playing. Clips that don't loop keep
+    /// playing their last pose once they're done.
+    pub fn playing(&self) -> Option<&str> {
+        self.playback
+            .map(|playback| self.skeleton.clips[playback.clip].name.as_str())
+    }
+
+    /// Whether the clip starts over once it's done.
+    pub fn looping(&self) -> bool {
+        self.playback.is_some_and(|playback| playback.looping)
+    }
+
+    /// How far into the clip it is, in seconds.
+    pub fn time(&self) -> f32 {
+        self.playback.map_or(0.0, |playback| playback.time)
+    }
+
+    /// Moves the clip forward by `dt`.
+    pub fn update(&mut self, dt: Duration) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        let duration = self.skeleton.clips[playback.clip].duration();
+        let time = playback.time + dt.as_secs_f32();
+        playback.time = if duration <= 0.0 {
+            0.0
+        } else if playback.looping {
+            time.rem_euclid(duration)
+        } else {
+            time.min(duration)
+        };
+        self.sample();
+    }
+
+    /// Every skin's joint matrices one after the other, see `joint_offset`.
+    pub fn joint_matrices(&self) -> &[Mat4] {
+        &self.joint_matrices
+    }
+
+    /// Where the matrices of `skin` start in `joint_matrices`.
+    pub fn joint_offset(&self, skin: usize) -> usize {
+        self.joint_offsets[skin]
+    }
+
+    /// Poses the nodes for the current time and updates the joint
+    /// matrices.
+    fn sample(&mut self) {
+        for (pose, node) in self.pose.iter_mut().zip(&self.skeleton.nodes) {
+            *pose = node.rest;
+        }
+        if let Some(playback) = self.playback {
+            for channel in &self.skeleton.clips[playback.clip].channels {
+                if let Some(pose) = self.pose.get_mut(channel.node) {
+                    channel.apply(playback.time, pose);
+                }
+            }
+        }
+        self.update_joints();
+    }
+
+    fn update_joints(&mut self) {
+        for &node in &self.order {
+            let local = self.pose[node].matrix();
+            self.world[node] = match self.skeleton.nodes[node].parent {
+                Some(parent) => self.world[parent] * local,
+                None => local,
+            };
+        }
+        for (skin, &offset) in self.skeleton.skins.iter().zip(&self.joint_offsets) {
+            for (i, (&joint, inverse_bind)) in skin
+                .joints
+                .iter()
+                .zip(&skin.inverse_bind_matrices)
+                .enumerate()
+            {
+                self.joint_matrices[offset + i] = self.world[joint] * *inverse_bind;
+            }
+        }
+    }
+}
+
+/// An `Animator` and the buffer its joint matrices get uploaded to, for a
+/// skinned `Model`.
+pub struct Skinning {
+    pub animator: Animator,
+    joint_buffer: StorageBuffer<Mat4>,
+}
+
+impl Skinning {
+    /// Fails if the skeleton has more joints than `binding` can bind.
+    pub fn new(
+        device: &wgpu::Device,
+        animator: Animator,
+        binding: JointBinding,
+    ) -> Result<Self, AnimationError> {
+        let count = animator.joint_matrices().len();
+        binding.check_joint_count(count)?;
+        let joint_buffer =
+            StorageBuffer::new(device, "Joint Buffer", binding.array_binding(), count);
+        Ok(Self {
+            animator,
+            joint_buffer,
+        })
+    }
+
+    /// Bound next to the materials of skinned meshes.
+    pub fn joint_buffer(&self) -> &wgpu::Buffer {
+        self.joint_buffer.buffer()
+    }
+
+    /// Uploads the animator's current joint matrices.
+    pub fn write(&self, queue: &wgpu::Queue) {
+        self.joint_buffer
+            .write(queue, self.animator.joint_matrices());
+    }
+}
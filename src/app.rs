@@ -0,0 +1,316 @@
+use std::{sync::Arc, time::Duration};
+
+use winit::{dpi::PhysicalSize, event::WindowEvent, window::Window};
+
+use crate::{
+    adapter::{self, AdapterSelection},
+    color,
+    device_config::DeviceConfig,
+    error::AppError,
+    minimize::{FrameAction, MinimizeTracker},
+    present_mode::PresentModePreference,
+    SurfaceErrorAction,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    input_map::{names, Input, InputBindings},
+    run_config::RunConfig,
+    MAX_FRAME_TIME,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use web_time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use winit::{
+    event::{ElementState, Event, KeyEvent},
+    event_loop::{ControlFlow, EventLoop},
+};
+
+/// Your own update and render logic, run by `run_app`. The runner owns the
+/// window and the surface, and takes care of resizing and surface errors.
+pub trait App {
+    fn init(ctx: &GpuContext) -> Self
+    where
+        Self: Sized;
+
+    /// Returns `true` if the event was consumed. The runner only handles
+    /// the events the app didn't consume, e.g. Escape to exit.
+    fn input(&mut self, _event: &WindowEvent) -> bool {
+        false
+    }
+
+    /// Called after the surface was resized to `size`, which is never 0x0.
+    fn resize(&mut self, _ctx: &GpuContext, _size: PhysicalSize<u32>) {}
+
+    /// Advances the app by `dt`, the time since the last update clamped to
+    /// `MAX_FRAME_TIME`.
+    fn update(&mut self, _ctx: &GpuContext, _dt: Duration) {}
+
+    /// Records drawing a frame into `view`, the texture of the surface. The
+    /// runner submits `encoder` and presents the frame afterwards.
+    fn render(
+        &mut self,
+        ctx: &GpuContext,
+        view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    );
+
+    /// Runs once before the app exits, the GPU work submitted until then
+    /// gets finished afterwards.
+    fn on_exit(&mut self, _ctx: &GpuContext) {}
+}
+
+/// The window and what's needed to render to it.
+pub struct GpuContext {
+    // Keeps the window alive as long as the surface
+    window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    minimized: MinimizeTracker,
+}
+
+impl GpuContext {
+    /// Creates a surface for `window` and configures it, like `State::new`.
+    pub async fn new(
+        window: Window,
+        present_mode: PresentModePreference,
+        adapter_selection: &AdapterSelection,
+        device_config: &DeviceConfig,
+    ) -> Result<Self, AppError> {
+        let window = Arc::new(window);
+        let instance = crate::create_instance(device_config.backends);
+        let surface = instance.create_surface(Arc::clone(&window))?;
+        let adapter = adapter::select_adapter(&instance, adapter_selection, Some(&surface)).await?;
+        let (device, queue) = crate::request_device(&adapter, device_config).await?;
+
+        let mut minimized = MinimizeTracker::default();
+        let size = window.inner_size();
+        minimized.resized(size);
+        let config = crate::surface_config(&surface, &adapter, size, present_mode)?;
+        surface.configure(&device, &config);
+        log::info!(
+            "Using {}, surface format: {:?} (sRGB encoding: {:?}), present mode: {:?}",
+            adapter::describe_adapter(&adapter.get_info()),
+            config.format,
+            color::srgb_encoding(&config),
+            config.present_mode
+        );
+
+        Ok(Self {
+            window,
+            surface,
+            adapter,
+            device,
+            queue,
+            config,
+            minimized,
+        })
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    pub fn adapter(&self) -> &wgpu::Adapter {
+        &self.adapter
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// The current configuration of the surface, e.g. for its format.
+    pub fn config(&self) -> &wgpu::SurfaceConfiguration {
+        &self.config
+    }
+
+    /// The format of the views `App::render` gets, which pipelines drawing
+    /// to them have to target. It's sRGB more often than `config().format`.
+    pub fn render_format(&self) -> wgpu::TextureFormat {
+        color::render_format(&self.config)
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.config.width, self.config.height)
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.minimized.is_minimized()
+    }
+
+    /// Reconfigures the surface with `size`. Returns `false` if it was left
+    /// alone because the window is minimized.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) -> bool {
+        if !self.minimized.resized(size) {
+            return false;
+        }
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(&self.device, &self.config);
+        true
+    }
+
+    /// `resize`, then lets `app` know if the surface was reconfigured.
+    fn resize_app(&mut self, app: &mut impl App, size: PhysicalSize<u32>) -> bool {
+        let resized = self.resize(size);
+        if resized {
+            app.resize(self, size);
+        }
+        resized
+    }
+
+    /// Has `app` render a frame to the surface and presents it. Nothing is
+    /// rendered while the window is minimized.
+    pub fn render_frame(&mut self, app: &mut impl App) -> Result<(), wgpu::SurfaceError> {
+        match self.minimized.begin_frame(self.window.inner_size()) {
+            FrameAction::Render => {}
+            FrameAction::Reconfigure(size) => {
+                self.resize_app(app, size);
+            }
+            FrameAction::Skip => return Ok(()),
+        }
+
+        let output = self.surface.get_current_texture()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(self.render_format()),
+            ..Default::default()
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("App Render Encoder"),
+            });
+        app.render(self, &view, &mut encoder);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+
+    /// Recovers from a `render_frame` error where possible, like
+    /// `State::handle_surface_error`.
+    pub fn handle_surface_error(
+        &mut self,
+        app: &mut impl App,
+        err: wgpu::SurfaceError,
+    ) -> SurfaceErrorAction {
+        let action = match err {
+            wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated => {
+                // A minimized window has a 0 size, which the surface can't be
+                // configured with
+                if self.resize_app(app, self.window.inner_size()) {
+                    SurfaceErrorAction::Reconfigured
+                } else {
+                    SurfaceErrorAction::SkipFrame
+                }
+            }
+            wgpu::SurfaceError::Timeout => SurfaceErrorAction::SkipFrame,
+            wgpu::SurfaceError::OutOfMemory => SurfaceErrorAction::Exit,
+        };
+        crate::log_surface_error(err, action);
+        action
+    }
+}
+
+/// Runs `A` in a default window, see `run_app_with`.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_app<A: App + 'static>() -> Result<(), AppError> {
+    run_app_with::<A>(RunConfig::default()).await
+}
+
+/// Opens a window described by `config.window` and runs `A` in it until
+/// the window is closed or Escape is pressed. Only the window, adapter,
+/// present mode, device and verbosity settings of `config` apply, the rest
+/// are for the bundled scene.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_app_with<A: App + 'static>(config: RunConfig) -> Result<(), AppError> {
+    run_app_with_init(config, |ctx| Ok(A::init(ctx))).await
+}
+
+/// Like `run_app_with`, with `init` creating the app instead of
+/// `App::init`. Lets the app take settings of its own.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_app_with_init<A: App + 'static>(
+    config: RunConfig,
+    init: impl FnOnce(&GpuContext) -> Result<A, AppError>,
+) -> Result<(), AppError> {
+    crate::init_logger(&config);
+    let evt_loop = EventLoop::new()?;
+    let window = config.window.build(&evt_loop)?;
+    let mut ctx =
+        GpuContext::new(window, config.present_mode, &config.adapter, &config.device).await?;
+    let mut app = init(&ctx)?;
+
+    // Only exiting is handled by the runner, the app gets every other key
+    let exit_inputs = InputBindings::default().inputs(names::EXIT).to_vec();
+    let mut exiting = false;
+    let mut last_frame = Instant::now();
+    evt_loop.set_control_flow(ControlFlow::Poll);
+    evt_loop.run(move |event, event_loop_window_target| {
+        let mut exit = |app: &mut A, ctx: &GpuContext| {
+            if !exiting {
+                exiting = true;
+                log::info!("Exiting the program...");
+                app.on_exit(ctx);
+                ctx.device.poll(wgpu::Maintain::Wait);
+            }
+            event_loop_window_target.exit();
+        };
+
+        match event {
+            Event::WindowEvent { ref event, .. } => {
+                if app.input(event) {
+                    return;
+                }
+                match event {
+                    WindowEvent::CloseRequested => exit(&mut app, &ctx),
+                    WindowEvent::KeyboardInput {
+                        event:
+                            KeyEvent {
+                                physical_key,
+                                logical_key,
+                                state: ElementState::Pressed,
+                                ..
+                            },
+                        ..
+                    } if Input::of_key(physical_key, logical_key)
+                        .any(|input| exit_inputs.contains(&input)) =>
+                    {
+                        exit(&mut app, &ctx)
+                    }
+                    WindowEvent::Resized(size) => {
+                        ctx.resize_app(&mut app, *size);
+                    }
+                    WindowEvent::Occluded(occluded) => ctx.minimized.set_occluded(*occluded),
+                    WindowEvent::RedrawRequested => {
+                        let now = Instant::now();
+                        app.update(&ctx, (now - last_frame).min(MAX_FRAME_TIME));
+                        last_frame = now;
+                        if let Err(err) = ctx.render_frame(&mut app) {
+                            if ctx.handle_surface_error(&mut app, err) == SurfaceErrorAction::Exit {
+                                exit(&mut app, &ctx);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            // Wait for the window to come back instead of spinning
+            Event::AboutToWait if ctx.is_minimized() => {
+                event_loop_window_target.set_control_flow(ControlFlow::Wait);
+            }
+            Event::AboutToWait => {
+                event_loop_window_target.set_control_flow(ControlFlow::Poll);
+                ctx.window.request_redraw();
+            }
+            _ => {}
+        }
+    })?;
+
+    Ok(())
+}
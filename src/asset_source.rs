@@ -0,0 +1,203 @@
+//! Where the assets and shaders are read from: files next to the executable
+//! or in the crate while developing, the copies built into the binary, or
+//! URLs relative to the page on the web. Paths are relative to the root of
+//! the source, like `assets/cube/cube.obj`.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Reads the files the loaders ask for.
+pub trait AssetSource: Send + Sync {
+    /// The whole file at `path`. The errors don't repeat the path.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Where `path` is on disk, so it can be watched for changes. `None` for
+    /// sources that don't read files.
+    fn file_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// The URL `path` has to be fetched from, for sources that can't `read`
+    /// without waiting. `AssetLoader` fetches these instead.
+    fn url(&self, _path: &Path) -> Option<String> {
+        None
+    }
+}
+
+/// Files in `base_dir`.
+#[derive(Debug, Clone)]
+pub struct FsSource {
+    base_dir: PathBuf,
+}
+
+impl FsSource {
+    /// Absolute paths are read as they are, the others relative to
+    /// `base_dir`. An empty one reads relative to the working directory.
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+        }
+    }
+
+    /// The executable's directory if the assets were copied next to it, the
+    /// crate's otherwise, which is where they are when running with cargo.
+    pub fn locate() -> Self {
+        let beside_executable = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_owned))
+            .filter(|dir| dir.join("assets").is_dir());
+        Self::new(beside_executable.unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR"))))
+    }
+
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+}
+
+impl AssetSource for FsSource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(self.base_dir.join(path))
+    }
+
+    fn file_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.base_dir.join(path))
+    }
+}
+
+/// Lists files of the crate with their contents, included at compile time.
+macro_rules! embed {
+    ($($path:literal),* $(,)?) => {
+        &[$(($path, include_bytes!(concat!("../", $path)) as &[u8])),*]
+    };
+}
+
+/// Everything the examples load, so the binary runs from anywhere.
+const EMBEDDED: &[(&str, &[u8])] = embed![
+    "assets/cube/cube.obj",
+    "assets/cube/cube.mtl",
+    "assets/cube/cube-diffuse.png",
+    "assets/cube/cube-normal.png",
+    "assets/skybox/px.png",
+    "assets/skybox/nx.png",
+    "assets/skybox/py.png",
+    "assets/skybox/ny.png",
+    "assets/skybox/pz.png",
+    "assets/skybox/nz.png",
+    "shaders/shader.wgsl",
+    "shaders/light.wgsl",
+    "shaders/skybox.wgsl",
+    "shaders/particles.wgsl",
+    "shaders/plasma.wgsl",
+];
+
+/// The assets built into the binary.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmbeddedSource;
+
+impl EmbeddedSource {
+    /// The paths there are files for.
+    pub fn paths(&self) -> impl Iterator<Item = &'static str> {
+        EMBEDDED.iter().map(|(path, _)| *path)
+    }
+}
+
+impl AssetSource for EmbeddedSource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        // `a/./b/../c` is `a/c`, and the separators are `/` whatever the
+        // platform
+        let mut parts = Vec::new();
+        for component in path.components() {
+            match component {
+                std::path::Component::Normal(part) => parts.push(part.to_string_lossy()),
+                std::path::Component::ParentDir => {
+                    parts.pop();
+                }
+                _ => {}
+            }
+        }
+        let key = parts.join("/");
+        EMBEDDED
+            .iter()
+            .find(|(path, _)| *path == key)
+            .map(|(_, bytes)| bytes.to_vec())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not built into the binary"))
+    }
+}
+
+/// URLs relative to `base_url`, which is relative to the page itself when
+/// it's empty. They can only be fetched, through `AssetLoader` or `fetch`.
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    base_url: String,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl HttpSource {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl AssetSource for HttpSource {
+    fn read(&self, _path: &Path) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "files on the web can only be fetched",
+        ))
+    }
+
+    fn url(&self, path: &Path) -> Option<String> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        Some(match self.base_url.trim_end_matches('/') {
+            "" => path,
+            base_url => format!("{base_url}/{path}"),
+        })
+    }
+}
+
+/// Fetches `url`, relative to the page.
+#[cfg(target_arch = "wasm32")]
+pub async fn fetch(url: &str) -> io::Result<Vec<u8>> {
+    use wasm_bindgen::JsCast;
+
+    let failed = |err: wasm_bindgen::JsValue| io::Error::other(format!("{url}: {err:?}"));
+    let window =
+        web_sys::window().ok_or_else(|| io::Error::other(format!("{url}: there's no window")))?;
+    let response = wasm_bindgen_futures::JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(failed)?;
+    let response: web_sys::Response = response.dyn_into().map_err(failed)?;
+    if !response.ok() {
+        let kind = if response.status() == 404 {
+            io::ErrorKind::NotFound
+        } else {
+            io::ErrorKind::Other
+        };
+        return Err(io::Error::new(
+            kind,
+            format!("{url}: HTTP {}", response.status()),
+        ));
+    }
+    let buffer = wasm_bindgen_futures::JsFuture::from(response.array_buffer().map_err(failed)?)
+        .await
+        .map_err(failed)?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// Where the examples read from. Debug builds read the files, so changing
+/// them doesn't need a rebuild, and release builds and the web the embedded
+/// copies.
+pub fn default_source() -> Arc<dyn AssetSource> {
+    if cfg!(all(debug_assertions, not(target_arch = "wasm32"))) {
+        Arc::new(FsSource::locate())
+    } else {
+        Arc::new(EmbeddedSource)
+    }
+}
@@ -0,0 +1,365 @@
+//! Loading files without blocking the main thread, and keeping what was
+//! loaded around so it's only uploaded once. Reading and decoding happens on
+//! a background thread, or in a fetch on the web, and `AssetLoader::finished`
+//! hands the results to the main thread, which uploads them to the GPU and
+//! into `Assets`.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+};
+
+use thiserror::Error;
+
+use crate::{asset_source::AssetSource, mipmap::MipmapGenerator, texture::Texture};
+
+/// Refers to an asset that may still be loading, see `AssetLoader::state`.
+/// `T` is what it loads into.
+pub struct Handle<T> {
+    id: u64,
+    _asset: PhantomData<fn() -> T>,
+}
+
+// Derived, these would need `T` to implement the traits too
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({})", self.id)
+    }
+}
+
+/// Where an asset is at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetState {
+    Loading,
+    Loaded,
+    /// Why it couldn't be read or decoded.
+    Failed(String),
+}
+
+/// Why the loader couldn't take an asset.
+#[derive(Debug, Error)]
+pub enum AssetError {
+    /// The thread the files are loaded on is gone, e.g. because reading or
+    /// decoding one panicked.
+    #[error("the asset loader thread stopped")]
+    LoaderStopped,
+}
+
+/// A finished image, decoded or why it couldn't be.
+pub type LoadedImage = (
+    Handle<image::DynamicImage>,
+    Result<image::DynamicImage, String>,
+);
+
+#[cfg(not(target_arch = "wasm32"))]
+type Job = (u64, PathBuf);
+
+/// Loads images in the background and hands them back when they're done.
+pub struct AssetLoader {
+    next_id: u64,
+    states: HashMap<u64, AssetState>,
+    #[cfg(target_arch = "wasm32")]
+    source: Arc<dyn AssetSource>,
+    #[cfg(not(target_arch = "wasm32"))]
+    jobs: mpsc::Sender<Job>,
+    /// Cloned into each fetch on the web.
+    #[cfg(target_arch = "wasm32")]
+    sender: mpsc::Sender<(u64, Result<image::DynamicImage, String>)>,
+    receiver: mpsc::Receiver<(u64, Result<image::DynamicImage, String>)>,
+}
+
+impl AssetLoader {
+    /// Starts the thread the files of `source` are loaded on, natively.
+    pub fn new(source: Arc<dyn AssetSource>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        #[cfg(not(target_arch = "wasm32"))]
+        let jobs = {
+            let (jobs, queue) = mpsc::channel::<Job>();
+            // Ends when the loader and with it the sender of the jobs is
+            // dropped
+            std::thread::Builder::new()
+                .name("asset loader".to_owned())
+                .spawn(move || {
+                    for (id, path) in queue {
+                        let image = source
+                            .read(&path)
+                            .map_err(|err| format!("{}: {err}", path.display()))
+                            .and_then(|bytes| decode(&path, &bytes));
+                        if sender.send((id, image)).is_err() {
+                            break;
+                        }
+                    }
+                })
+                .expect("failed to start the asset loader thread");
+            jobs
+        };
+        Self {
+            next_id: 0,
+            states: HashMap::new(),
+            #[cfg(target_arch = "wasm32")]
+            source,
+            #[cfg(not(target_arch = "wasm32"))]
+            jobs,
+            #[cfg(target_arch = "wasm32")]
+            sender,
+            receiver,
+        }
+    }
+
+    /// Starts loading the image at `path` in the source, the handle is
+    /// `Loading` until `finished` returns it. Sources with URLs are fetched
+    /// on the web, the others are read right away.
+    pub fn load_image(
+        &mut self,
+        path: impl Into<PathBuf>,
+    ) -> Result<Handle<image::DynamicImage>, AssetError> {
+        let path = path.into();
+        let id = self.next_id + 1;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.jobs
+            .send((id, path))
+            .map_err(|_| AssetError::LoaderStopped)?;
+        #[cfg(target_arch = "wasm32")]
+        match self.source.url(&path) {
+            Some(url) => {
+                let sender = self.sender.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let image = crate::asset_source::fetch(&url)
+                        .await
+                        .map_err(|err| err.to_string())
+                        .and_then(|bytes| decode(&path, &bytes));
+                    let _ = sender.send((id, image));
+                });
+            }
+            None => {
+                let image = self
+                    .source
+                    .read(&path)
+                    .map_err(|err| format!("{}: {err}", path.display()))
+                    .and_then(|bytes| decode(&path, &bytes));
+                let _ = self.sender.send((id, image));
+            }
+        }
+
+        self.next_id = id;
+        self.states.insert(id, AssetState::Loading);
+        Ok(Handle {
+            id,
+            _asset: PhantomData,
+        })
+    }
+
+    /// Handles the loader didn't give out count as failed.
+    pub fn state<T>(&self, handle: Handle<T>) -> AssetState {
+        self.states
+            .get(&handle.id)
+            .cloned()
+            .unwrap_or_else(|| AssetState::Failed("unknown handle".to_owned()))
+    }
+
+    /// How many assets are still loading.
+    pub fn loading(&self) -> usize {
+        self.states
+            .values()
+            .filter(|state| **state == AssetState::Loading)
+            .count()
+    }
+
+    /// The images that finished since the last call, without waiting for
+    /// the others.
+    pub fn finished(&mut self) -> Vec<LoadedImage> {
+        let finished: Vec<_> = self.receiver.try_iter().collect();
+        finished
+            .into_iter()
+            .map(|(id, image)| self.complete(id, image))
+            .collect()
+    }
+
+    /// Like `finished`, but waits until nothing is loading anymore.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait(&mut self) -> Vec<LoadedImage> {
+        let mut finished = Vec::new();
+        while self.loading() > 0 {
+            let Ok((id, image)) = self.receiver.recv() else {
+                break;
+            };
+            finished.push(self.complete(id, image));
+        }
+        finished
+    }
+
+    fn complete(&mut self, id: u64, image: Result<image::DynamicImage, String>) -> LoadedImage {
+        let state = match &image {
+            Ok(_) => AssetState::Loaded,
+            Err(err) => AssetState::Failed(err.clone()),
+        };
+        self.states.insert(id, state);
+        let handle = Handle {
+            id,
+            _asset: PhantomData,
+        };
+        (handle, image)
+    }
+}
+
+fn decode(path: &std::path::Path, bytes: &[u8]) -> Result<image::DynamicImage, String> {
+    image::load_from_memory(bytes).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// The textures loaded so far, shared by the materials using them. Each file
+/// is uploaded once for each format it's loaded as.
+#[derive(Default)]
+pub struct Assets {
+    next_id: u64,
+    textures: HashMap<u64, Arc<Texture>>,
+    paths: HashMap<(PathBuf, wgpu::TextureFormat), Handle<Texture>>,
+    /// Created with the first texture that needs mipmaps.
+    mipmaps: Option<MipmapGenerator>,
+}
+
+impl Assets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the texture at `path` in `source` as `format`, or returns the
+    /// handle of the one loaded from there before. Compressed `.ktx2` and
+    /// `.dds` files come with their own format and mipmaps, images get them
+    /// generated.
+    pub fn load_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &dyn AssetSource,
+        path: impl AsRef<Path>,
+        format: wgpu::TextureFormat,
+    ) -> Result<Handle<Texture>, String> {
+        let path = path.as_ref();
+        if let Some(handle) = self.find(path, format) {
+            return Ok(handle);
+        }
+        let label = path.to_string_lossy();
+        let failed = |err: &dyn fmt::Display| format!("{label}: {err}");
+
+        let bytes = source.read(path).map_err(|err| failed(&err))?;
+        let texture = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ktx2") => Texture::from_ktx2(device, queue, &bytes, Some(&label)),
+            Some("dds") => Texture::from_dds(device, queue, &bytes, Some(&label)),
+            _ => {
+                let image = image::load_from_memory(&bytes).map_err(|err| failed(&err))?;
+                return Ok(self.insert_image(device, queue, path, format, &image));
+            }
+        };
+        let texture = texture.map_err(|err| failed(&err))?;
+        Ok(self.insert(path, format, texture))
+    }
+
+    /// Uploads `image` with mipmaps as the texture of `path`, unless there
+    /// already is one. `path` only has to be unique, it isn't read.
+    pub fn insert_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+        format: wgpu::TextureFormat,
+        image: &image::DynamicImage,
+    ) -> Handle<Texture> {
+        let path = path.as_ref();
+        if let Some(handle) = self.find(path, format) {
+            return handle;
+        }
+        let label = path.to_string_lossy();
+        let mipmaps = self
+            .mipmaps
+            .get_or_insert_with(|| MipmapGenerator::new(device));
+        let texture =
+            Texture::from_image_mipmapped(device, queue, mipmaps, image, Some(&label), format)
+                .unwrap_or_else(|err| {
+                    log::warn!("Failed to generate the mipmaps of {label}: {err}");
+                    Texture::from_image_with_format(device, queue, image, Some(&label), format)
+                });
+        self.insert(path, format, texture)
+    }
+
+    /// Adds a texture under `path`, replacing the one there was. Materials
+    /// keep using the old one until they're given the new one.
+    pub fn insert(
+        &mut self,
+        path: impl Into<PathBuf>,
+        format: wgpu::TextureFormat,
+        texture: Texture,
+    ) -> Handle<Texture> {
+        self.next_id += 1;
+        let handle = Handle {
+            id: self.next_id,
+            _asset: PhantomData,
+        };
+        self.textures.insert(handle.id, Arc::new(texture));
+        if let Some(old) = self.paths.insert((path.into(), format), handle) {
+            self.textures.remove(&old.id);
+        }
+        handle
+    }
+
+    /// The texture loaded from `path` as `format`, if there is one.
+    pub fn find(&self, path: &Path, format: wgpu::TextureFormat) -> Option<Handle<Texture>> {
+        self.paths.get(&(path.to_owned(), format)).copied()
+    }
+
+    /// `None` once the texture was removed.
+    pub fn get(&self, handle: Handle<Texture>) -> Option<&Texture> {
+        self.textures.get(&handle.id).map(Arc::as_ref)
+    }
+
+    /// The texture to put into a material, it won't be removed while a
+    /// material holds it.
+    pub fn shared(&self, handle: Handle<Texture>) -> Option<Arc<Texture>> {
+        self.textures.get(&handle.id).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// Drops the textures nothing outside the cache holds anymore, and
+    /// returns how many there were. Their handles return `None` after that.
+    pub fn remove_unused(&mut self) -> usize {
+        let before = self.textures.len();
+        self.textures
+            .retain(|_, texture| Arc::strong_count(texture) > 1);
+        self.paths
+            .retain(|_, handle| self.textures.contains_key(&handle.id));
+        before - self.textures.len()
+    }
+}
@@ -0,0 +1,110 @@
+//! The report of `run_benchmark`, which renders frames offscreen as fast as
+//! possible to compare adapters and backends.
+
+use std::{fmt, time::Duration};
+
+use crate::adapter;
+
+/// CPU frame times of a benchmark run. A frame is timed from the start of
+/// its update until the GPU finished rendering it.
+#[derive(Clone, Debug)]
+pub struct BenchReport {
+    pub frames: u32,
+    pub width: u32,
+    pub height: u32,
+    pub adapter: wgpu::AdapterInfo,
+    /// Wall time of all timed frames together.
+    pub total: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl BenchReport {
+    /// Sums up the time each frame took. All durations are 0 without frames.
+    pub fn new(
+        adapter: wgpu::AdapterInfo,
+        width: u32,
+        height: u32,
+        frame_times: &[Duration],
+        total: Duration,
+    ) -> Self {
+        let mut sorted = frame_times.to_vec();
+        sorted.sort_unstable();
+        Self {
+            frames: frame_times.len() as u32,
+            width,
+            height,
+            adapter,
+            total,
+            p50: percentile(&sorted, 50.0),
+            p95: percentile(&sorted, 95.0),
+            p99: percentile(&sorted, 99.0),
+        }
+    }
+
+    /// The report on one line, for scripts comparing runs.
+    pub fn to_json(&self) -> String {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        format!(
+            concat!(
+                "{{\"frames\":{},\"width\":{},\"height\":{},",
+                "\"adapter\":{{\"name\":{},\"backend\":{},\"device_type\":{},\"driver\":{}}},",
+                "\"total_ms\":{:.3},\"p50_ms\":{:.3},\"p95_ms\":{:.3},\"p99_ms\":{:.3}}}"
+            ),
+            self.frames,
+            self.width,
+            self.height,
+            json_string(&self.adapter.name),
+            json_string(&format!("{:?}", self.adapter.backend)),
+            json_string(&format!("{:?}", self.adapter.device_type)),
+            json_string(&self.adapter.driver),
+            ms(self.total),
+            ms(self.p50),
+            ms(self.p95),
+            ms(self.p99),
+        )
+    }
+}
+
+/// A table for people to read.
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        writeln!(f, "Adapter  {}", adapter::describe_adapter(&self.adapter))?;
+        writeln!(
+            f,
+            "Frames   {} at {}x{}",
+            self.frames, self.width, self.height
+        )?;
+        writeln!(f, "Total    {:>9.3} ms", ms(self.total))?;
+        writeln!(f, "p50      {:>9.3} ms", ms(self.p50))?;
+        writeln!(f, "p95      {:>9.3} ms", ms(self.p95))?;
+        write!(f, "p99      {:>9.3} ms", ms(self.p99))
+    }
+}
+
+/// The nearest-rank percentile `p` (0 to 100) of `sorted`, which has to be
+/// sorted. 0 if it's empty.
+pub fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
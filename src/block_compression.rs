@@ -0,0 +1,673 @@
+//! Software decoders for the block compressed formats, used when the adapter
+//! can't sample one of them. Every format here stores 4x4 texel blocks.
+//!
+//! BC1-3 and BC7 come from the D3D specs, ETC2 and EAC from the OpenGL ES 3.0
+//! spec. ASTC isn't decoded, its blocks are too involved to be worth it here.
+
+/// A decoded block, the RGBA8 texels in row-major order.
+pub type Block = [[u8; 4]; 16];
+
+/// Whether `decode_block` can decode `format`.
+pub fn can_decode(format: wgpu::TextureFormat) -> bool {
+    use wgpu::TextureFormat as F;
+    matches!(
+        format.remove_srgb_suffix(),
+        F::Bc1RgbaUnorm
+            | F::Bc2RgbaUnorm
+            | F::Bc3RgbaUnorm
+            | F::Bc7RgbaUnorm
+            | F::Etc2Rgb8Unorm
+            | F::Etc2Rgb8A1Unorm
+            | F::Etc2Rgba8Unorm
+    )
+}
+
+/// Decodes a single block of `format`, `None` if the format isn't supported
+/// or `block` has the wrong size.
+pub fn decode_block(format: wgpu::TextureFormat, block: &[u8]) -> Option<Block> {
+    use wgpu::TextureFormat as F;
+    let decoded = match (format.remove_srgb_suffix(), block.len()) {
+        (F::Bc1RgbaUnorm, 8) => bc1(block, false),
+        (F::Bc2RgbaUnorm, 16) => {
+            let mut texels = bc1(&block[8..], true);
+            let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+            for (i, texel) in texels.iter_mut().enumerate() {
+                texel[3] = ((alpha >> (4 * i)) & 0xf) as u8 * 17;
+            }
+            texels
+        }
+        (F::Bc3RgbaUnorm, 16) => {
+            let mut texels = bc1(&block[8..], true);
+            for (texel, alpha) in texels.iter_mut().zip(bc3_alpha(&block[..8])) {
+                texel[3] = alpha;
+            }
+            texels
+        }
+        (F::Bc7RgbaUnorm, 16) => bc7(block.try_into().unwrap()),
+        (F::Etc2Rgb8Unorm, 8) => etc2(block, false),
+        (F::Etc2Rgb8A1Unorm, 8) => etc2(block, true),
+        (F::Etc2Rgba8Unorm, 16) => {
+            let mut texels = etc2(&block[8..], false);
+            for (texel, alpha) in texels.iter_mut().zip(eac_alpha(&block[..8])) {
+                texel[3] = alpha;
+            }
+            texels
+        }
+        _ => return None,
+    };
+    Some(decoded)
+}
+
+/// Decodes a whole `width`x`height` image of `format` into tightly packed
+/// RGBA8 rows. Blocks sticking out of the image are cropped.
+pub fn decompress(
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Option<Vec<u8>> {
+    let block_bytes = format.block_copy_size(None)? as usize;
+    let (width, height) = (width as usize, height as usize);
+    let blocks_wide = width.div_ceil(4);
+    let blocks_high = height.div_ceil(4);
+    if data.len() < blocks_wide * blocks_high * block_bytes {
+        return None;
+    }
+
+    let mut rgba = vec![0; width * height * 4];
+    for (i, block) in data
+        .chunks_exact(block_bytes)
+        .take(blocks_wide * blocks_high)
+        .enumerate()
+    {
+        let texels = decode_block(format, block)?;
+        let (block_x, block_y) = (i % blocks_wide * 4, i / blocks_wide * 4);
+        for (j, texel) in texels.iter().enumerate() {
+            let (x, y) = (block_x + j % 4, block_y + j / 4);
+            if x < width && y < height {
+                let offset = (y * width + x) * 4;
+                rgba[offset..offset + 4].copy_from_slice(texel);
+            }
+        }
+    }
+    Some(rgba)
+}
+
+fn rgb565(color: u16) -> [u8; 4] {
+    let r = (color >> 11) as u8 & 0x1f;
+    let g = (color >> 5) as u8 & 0x3f;
+    let b = color as u8 & 0x1f;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+        255,
+    ]
+}
+
+/// The color block shared by BC1-3. BC2 and BC3 always use four colors,
+/// they store alpha separately.
+fn bc1(block: &[u8], always_opaque: bool) -> Block {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+    let mix = |a: u8, b: u8, wa: u16, wb: u16| {
+        ((u16::from(a) * wa + u16::from(b) * wb) / (wa + wb)) as u8
+    };
+
+    let mut palette = [e0, e1, [0; 4], [0; 4]];
+    if c0 > c1 || always_opaque {
+        for c in 0..3 {
+            palette[2][c] = mix(e0[c], e1[c], 2, 1);
+            palette[3][c] = mix(e0[c], e1[c], 1, 2);
+        }
+        palette[2][3] = 255;
+        palette[3][3] = 255;
+    } else {
+        // The fourth color is transparent black
+        for c in 0..3 {
+            palette[2][c] = mix(e0[c], e1[c], 1, 1);
+        }
+        palette[2][3] = 255;
+    }
+
+    let indices = u32::from_le_bytes(block[4..8].try_into().unwrap());
+    std::array::from_fn(|i| palette[((indices >> (2 * i)) & 3) as usize])
+}
+
+fn bc3_alpha(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (u32::from(block[0]), u32::from(block[1]));
+    let mut palette = [0u8; 8];
+    palette[0] = a0 as u8;
+    palette[1] = a1 as u8;
+    if a0 > a1 {
+        for i in 1..7 {
+            palette[i + 1] = (((7 - i as u32) * a0 + i as u32 * a1) / 7) as u8;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = (((5 - i as u32) * a0 + i as u32 * a1) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    let mut bits = [0; 8];
+    bits[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bits);
+    std::array::from_fn(|i| palette[((indices >> (3 * i)) & 7) as usize])
+}
+
+/// Reads a BC7 block from its lowest bit up.
+struct BitReader {
+    bits: u128,
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u8 {
+        let value = (self.bits & ((1 << count) - 1)) as u8;
+        self.bits >>= count;
+        value
+    }
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_p_bits: bool,
+    shared_p_bits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32,
+}
+
+/// The arguments are paired up as `(rotation, index selection)`, `(color,
+/// alpha)`, `(per endpoint, shared)` p-bits and `(primary, secondary)` index
+/// bits, like the columns of the mode table in the spec.
+const fn bc7_mode(
+    subsets: usize,
+    partition_bits: u32,
+    selection_bits: (u32, u32),
+    endpoint_bits: (u32, u32),
+    p_bits: (bool, bool),
+    index_bits: (u32, u32),
+) -> Bc7Mode {
+    Bc7Mode {
+        subsets,
+        partition_bits,
+        rotation_bits: selection_bits.0,
+        index_selection_bits: selection_bits.1,
+        color_bits: endpoint_bits.0,
+        alpha_bits: endpoint_bits.1,
+        endpoint_p_bits: p_bits.0,
+        shared_p_bits: p_bits.1,
+        index_bits: index_bits.0,
+        secondary_index_bits: index_bits.1,
+    }
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    bc7_mode(3, 4, (0, 0), (4, 0), (true, false), (3, 0)),
+    bc7_mode(2, 6, (0, 0), (6, 0), (false, true), (3, 0)),
+    bc7_mode(3, 6, (0, 0), (5, 0), (false, false), (2, 0)),
+    bc7_mode(2, 6, (0, 0), (7, 0), (true, false), (2, 0)),
+    bc7_mode(1, 0, (2, 1), (5, 6), (false, false), (2, 3)),
+    bc7_mode(1, 0, (2, 0), (7, 8), (false, false), (2, 2)),
+    bc7_mode(1, 0, (0, 0), (7, 7), (true, false), (4, 0)),
+    bc7_mode(2, 6, (0, 0), (5, 5), (true, false), (2, 0)),
+];
+
+/// Bit `i` is set where texel `i` is in the second subset.
+const BC7_PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80, 0xc800, 0xffec, 0xfe80, 0xe800,
+    0xffe8, 0xff00, 0xfff0, 0xf000, 0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c, 0xaaaa, 0xf0f0, 0x5a5a, 0x33cc,
+    0x3c3c, 0x55aa, 0x9696, 0xa55a, 0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c, 0x9336, 0x9cc6, 0x817e, 0xe718,
+    0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+/// The subset of each texel, two bits per texel starting from the lowest.
+const BC7_PARTITIONS_3: [u32; 64] = [
+    0xaa685050, 0x6a5a5040, 0x5a5a4200, 0x5450a0a8, 0xa5a50000, 0xa0a05050, 0x5555a0a0, 0x5a5a5050,
+    0xaa550000, 0xaa555500, 0xaaaa5500, 0x90909090, 0x94949494, 0xa4a4a4a4, 0xa9a59450, 0x2a0a4250,
+    0xa5945040, 0x0a425054, 0xa5a5a500, 0x55a0a0a0, 0xa8a85454, 0x6a6a4040, 0xa4a45000, 0x1a1a0500,
+    0x0050a4a4, 0xaaa59090, 0x14696914, 0x69691400, 0xa08585a0, 0xaa821414, 0x50a4a450, 0x6a5a0200,
+    0xa9a58000, 0x5090a0a8, 0xa8a09050, 0x24242424, 0x00aa5500, 0x24924924, 0x24499224, 0x50a50a50,
+    0x500aa550, 0xaaaa4444, 0x66660000, 0xa5a0a5a0, 0x50a050a0, 0x69286928, 0x44aaaa44, 0x66666600,
+    0xaa444444, 0x54a854a8, 0x95809580, 0x96969600, 0xa85454a8, 0x80959580, 0xaa141414, 0x96960000,
+    0xaaaa1414, 0xa05050a0, 0xa0a5a5a0, 0x96000000, 0x40804080, 0xa9a8a9a8, 0xaaaaaa44, 0x2a4a5254,
+];
+
+/// The anchor texel of the second subset of the 2 subset partitions, the
+/// first subset's is always texel 0.
+const BC7_ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// The anchor texels of the second and third subsets of the 3 subset
+/// partitions.
+const BC7_ANCHORS_3: [[u8; 2]; 64] = [
+    [3, 15],
+    [3, 8],
+    [15, 8],
+    [15, 3],
+    [8, 15],
+    [3, 15],
+    [15, 3],
+    [15, 8],
+    [8, 15],
+    [8, 15],
+    [6, 15],
+    [6, 15],
+    [6, 15],
+    [5, 15],
+    [3, 15],
+    [3, 8],
+    [3, 15],
+    [3, 8],
+    [8, 15],
+    [15, 3],
+    [3, 15],
+    [3, 8],
+    [6, 15],
+    [10, 8],
+    [5, 3],
+    [8, 15],
+    [8, 6],
+    [6, 10],
+    [8, 15],
+    [5, 15],
+    [15, 10],
+    [15, 8],
+    [8, 15],
+    [15, 3],
+    [3, 15],
+    [5, 10],
+    [6, 10],
+    [10, 8],
+    [8, 9],
+    [15, 10],
+    [15, 6],
+    [3, 15],
+    [15, 8],
+    [5, 15],
+    [15, 3],
+    [15, 6],
+    [15, 6],
+    [15, 8],
+    [3, 15],
+    [15, 3],
+    [5, 15],
+    [5, 15],
+    [5, 15],
+    [8, 15],
+    [5, 15],
+    [10, 15],
+    [5, 15],
+    [10, 15],
+    [8, 15],
+    [13, 15],
+    [15, 3],
+    [12, 15],
+    [3, 15],
+    [3, 8],
+];
+
+fn bc7_weights(bits: u32) -> &'static [u16] {
+    match bits {
+        2 => &[0, 21, 43, 64],
+        3 => &[0, 9, 18, 27, 37, 46, 55, 64],
+        _ => &[0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64],
+    }
+}
+
+fn bc7(block: &[u8; 16]) -> Block {
+    let mut bits = BitReader {
+        bits: u128::from_le_bytes(*block),
+    };
+    let Some(mode_index) = (0..8).find(|&mode| block[0] & (1 << mode) != 0) else {
+        // Reserved, decoders output transparent black
+        return [[0; 4]; 16];
+    };
+    let mode = &BC7_MODES[mode_index];
+    bits.read(mode_index as u32 + 1);
+
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    // Two endpoints per subset, their channels are stored one after the
+    // other: all the reds first, then the greens...
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u8; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[channel] = bits.read(mode.color_bits);
+        }
+    }
+    for endpoint in &mut endpoints[..endpoint_count] {
+        endpoint[3] = bits.read(mode.alpha_bits);
+    }
+
+    // P-bits are an extra lowest bit for every channel of an endpoint, or
+    // shared by both endpoints of a subset
+    let mut color_bits = mode.color_bits;
+    let mut alpha_bits = mode.alpha_bits;
+    if mode.endpoint_p_bits || mode.shared_p_bits {
+        let mut p_bits = [0u8; 6];
+        if mode.endpoint_p_bits {
+            for p_bit in &mut p_bits[..endpoint_count] {
+                *p_bit = bits.read(1);
+            }
+        } else {
+            for subset in 0..mode.subsets {
+                let p_bit = bits.read(1);
+                p_bits[subset * 2] = p_bit;
+                p_bits[subset * 2 + 1] = p_bit;
+            }
+        }
+        for (endpoint, p_bit) in endpoints.iter_mut().zip(p_bits) {
+            for channel in endpoint.iter_mut() {
+                *channel = (*channel << 1) | p_bit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+    for endpoint in &mut endpoints[..endpoint_count] {
+        for channel in &mut endpoint[..3] {
+            *channel = expand_bits(*channel, color_bits);
+        }
+        endpoint[3] = if alpha_bits > 0 {
+            expand_bits(endpoint[3], alpha_bits)
+        } else {
+            255
+        };
+    }
+
+    let subset_of = |texel: usize| match mode.subsets {
+        1 => 0,
+        2 => usize::from(BC7_PARTITIONS_2[partition] & (1 << texel) != 0),
+        _ => ((BC7_PARTITIONS_3[partition] >> (2 * texel)) & 3) as usize,
+    };
+    // The highest bit of an anchor texel's index is always 0, so it isn't
+    // stored. Only single subset modes have secondary indices, their anchor
+    // is texel 0 as well.
+    let is_anchor = |texel: usize| match mode.subsets {
+        1 => texel == 0,
+        2 => texel == 0 || texel == usize::from(BC7_ANCHORS_2[partition]),
+        _ => texel == 0 || BC7_ANCHORS_3[partition].contains(&(texel as u8)),
+    };
+    let mut read_indices = |index_bits: u32| -> [u8; 16] {
+        std::array::from_fn(|texel| bits.read(index_bits - u32::from(is_anchor(texel))))
+    };
+    let indices = read_indices(mode.index_bits);
+    let secondary_indices = if mode.secondary_index_bits > 0 {
+        Some(read_indices(mode.secondary_index_bits))
+    } else {
+        None
+    };
+
+    std::array::from_fn(|texel| {
+        let subset = subset_of(texel);
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        let interpolate = |channel: usize, index: u8, index_bits: u32| {
+            let weight = bc7_weights(index_bits)[usize::from(index)];
+            (((64 - weight) * u16::from(e0[channel]) + weight * u16::from(e1[channel]) + 32) >> 6)
+                as u8
+        };
+
+        let mut texel_color = [0u8; 4];
+        match secondary_indices {
+            Some(secondary) => {
+                // Modes 4 and 5 index color and alpha separately, the index
+                // selection bit swaps which indices are used for which
+                let (color_index, color_bits, alpha_index, alpha_bits) = if index_selection == 0 {
+                    (
+                        indices[texel],
+                        mode.index_bits,
+                        secondary[texel],
+                        mode.secondary_index_bits,
+                    )
+                } else {
+                    (
+                        secondary[texel],
+                        mode.secondary_index_bits,
+                        indices[texel],
+                        mode.index_bits,
+                    )
+                };
+                for (channel, value) in texel_color.iter_mut().enumerate() {
+                    *value = if channel < 3 {
+                        interpolate(channel, color_index, color_bits)
+                    } else {
+                        interpolate(channel, alpha_index, alpha_bits)
+                    };
+                }
+            }
+            None => {
+                for (channel, value) in texel_color.iter_mut().enumerate() {
+                    *value = interpolate(channel, indices[texel], mode.index_bits);
+                }
+            }
+        }
+        // The rotation swaps alpha with one of the color channels
+        if rotation > 0 {
+            texel_color.swap(3, usize::from(rotation - 1));
+        }
+        texel_color
+    })
+}
+
+/// Widens a `bits` bit value to 8 bits by repeating its highest bits.
+fn expand_bits(value: u8, bits: u32) -> u8 {
+    if bits >= 8 {
+        return value;
+    }
+    let value = value << (8 - bits);
+    value | (value >> bits)
+}
+
+const ETC1_MODIFIERS: [[i16; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+const ETC2_DISTANCES: [i16; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+fn clamp_channel(value: i16) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+fn add_to_color(color: [u8; 4], amount: i16) -> [u8; 4] {
+    [
+        clamp_channel(i16::from(color[0]) + amount),
+        clamp_channel(i16::from(color[1]) + amount),
+        clamp_channel(i16::from(color[2]) + amount),
+        255,
+    ]
+}
+
+/// An ETC2 RGB block, with `punchthrough` for the 1-bit alpha variant. ETC
+/// blocks are big endian and their texels go column by column.
+fn etc2(block: &[u8], punchthrough: bool) -> Block {
+    let bits = u64::from_be_bytes(block.try_into().unwrap());
+    let field = |shift: u32, count: u32| ((bits >> shift) & ((1 << count) - 1)) as i16;
+    let expand4 = |value: i16| (value as u8) * 17;
+    let expand5 = |value: i16| expand_bits(value as u8, 5);
+    let texel_index = |i: usize| {
+        let msb = (bits >> (16 + i)) & 1;
+        let lsb = (bits >> i) & 1;
+        (msb << 1 | lsb) as usize
+    };
+    // Column-major texel `i` of the block, in row-major order
+    let row_major = |i: usize| (i % 4) * 4 + i / 4;
+
+    // The 1-bit alpha variant has no individual mode, that bit says whether
+    // the block is opaque
+    let differential = punchthrough || field(33, 1) == 1;
+    let transparent = punchthrough && field(33, 1) == 0;
+    let mut texels = [[0u8; 4]; 16];
+
+    let base = [field(59, 5), field(51, 5), field(43, 5)];
+    let delta = [field(56, 3), field(48, 3), field(40, 3)].map(|d| (d << 13) >> 13);
+    let overflowing = |channel: usize| !(0..32).contains(&(base[channel] + delta[channel]));
+    if differential && overflowing(0) {
+        // T mode
+        let c0 = [
+            (field(59, 2) << 2) | field(56, 2),
+            field(52, 4),
+            field(48, 4),
+        ]
+        .map(expand4);
+        let c1 = [field(44, 4), field(40, 4), field(36, 4)].map(expand4);
+        let distance = ETC2_DISTANCES[((field(34, 2) << 1) | field(32, 1)) as usize];
+        let c0 = [c0[0], c0[1], c0[2], 255];
+        let c1 = [c1[0], c1[1], c1[2], 255];
+        let paint = [
+            c0,
+            add_to_color(c1, distance),
+            c1,
+            add_to_color(c1, -distance),
+        ];
+        for i in 0..16 {
+            texels[row_major(i)] = paint_texel(paint, texel_index(i), transparent);
+        }
+    } else if differential && overflowing(1) {
+        // H mode
+        let r0 = field(59, 4);
+        let g0 = (field(56, 3) << 1) | field(52, 1);
+        let b0 = (field(51, 1) << 3) | field(47, 3);
+        let (r1, g1, b1) = (field(43, 4), field(39, 4), field(35, 4));
+        let ordered = (r0 << 8 | g0 << 4 | b0) >= (r1 << 8 | g1 << 4 | b1);
+        let distance_index = (field(34, 1) << 2) | (field(32, 1) << 1) | i16::from(ordered);
+        let distance = ETC2_DISTANCES[distance_index as usize];
+        let c0 = [expand4(r0), expand4(g0), expand4(b0), 255];
+        let c1 = [expand4(r1), expand4(g1), expand4(b1), 255];
+        let paint = [
+            add_to_color(c0, distance),
+            add_to_color(c0, -distance),
+            add_to_color(c1, distance),
+            add_to_color(c1, -distance),
+        ];
+        for i in 0..16 {
+            texels[row_major(i)] = paint_texel(paint, texel_index(i), transparent);
+        }
+    } else if differential && overflowing(2) {
+        // Planar mode, a gradient between three colors that's always opaque
+        let expand6 = |value: i16| i16::from(expand_bits(value as u8, 6));
+        let expand7 = |value: i16| i16::from(expand_bits(value as u8, 7));
+        let origin = [
+            expand6(field(57, 6)),
+            expand7((field(56, 1) << 6) | field(49, 6)),
+            expand6((field(48, 1) << 5) | (field(43, 2) << 3) | field(39, 3)),
+        ];
+        let horizontal = [
+            expand6((field(34, 5) << 1) | field(32, 1)),
+            expand7(field(25, 7)),
+            expand6(field(19, 6)),
+        ];
+        let vertical = [
+            expand6(field(13, 6)),
+            expand7(field(6, 7)),
+            expand6(field(0, 6)),
+        ];
+        for (i, texel) in texels.iter_mut().enumerate() {
+            let (x, y) = ((i % 4) as i16, (i / 4) as i16);
+            for channel in 0..3 {
+                let value = x * (horizontal[channel] - origin[channel])
+                    + y * (vertical[channel] - origin[channel])
+                    + 4 * origin[channel]
+                    + 2;
+                texel[channel] = clamp_channel(value >> 2);
+            }
+            texel[3] = 255;
+        }
+    } else {
+        // The block is split in two halves, side by side or one above the
+        // other, each with its own base color and modifiers
+        let (c0, c1) = if differential {
+            let c1 = std::array::from_fn::<_, 3, _>(|c| expand5(base[c] + delta[c]));
+            (base.map(expand5), c1)
+        } else {
+            (
+                [field(60, 4), field(52, 4), field(44, 4)].map(expand4),
+                [field(56, 4), field(48, 4), field(40, 4)].map(expand4),
+            )
+        };
+        let tables = [field(37, 3), field(34, 3)];
+        let flipped = field(32, 1) == 1;
+        for i in 0..16 {
+            let (x, y) = (i / 4, i % 4);
+            let half = usize::from(if flipped { y >= 2 } else { x >= 2 });
+            let color = if half == 0 { c0 } else { c1 };
+            let [small, large] = ETC1_MODIFIERS[tables[half] as usize];
+            let index = texel_index(i);
+            texels[row_major(i)] = if transparent && index == 2 {
+                [0; 4]
+            } else {
+                // Without the opaque bit the small modifiers are 0
+                let small = if transparent { 0 } else { small };
+                let modifier = [small, large, -small, -large][index];
+                add_to_color([color[0], color[1], color[2], 255], modifier)
+            };
+        }
+    }
+    texels
+}
+
+fn paint_texel(paint: [[u8; 4]; 4], index: usize, transparent: bool) -> [u8; 4] {
+    if transparent && index == 2 {
+        [0; 4]
+    } else {
+        paint[index]
+    }
+}
+
+const EAC_MODIFIERS: [[i16; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/// The alpha block of ETC2 RGBA8, in row-major order.
+fn eac_alpha(block: &[u8]) -> [u8; 16] {
+    let bits = u64::from_be_bytes(block.try_into().unwrap());
+    let base = i16::from(block[0]);
+    let multiplier = i16::from(block[1] >> 4);
+    let modifiers = EAC_MODIFIERS[usize::from(block[1] & 0xf)];
+
+    let mut alpha = [0; 16];
+    for i in 0..16 {
+        let index = ((bits >> (45 - 3 * i)) & 7) as usize;
+        alpha[(i % 4) * 4 + i / 4] = clamp_channel(base + modifiers[index] * multiplier);
+    }
+    alpha
+}
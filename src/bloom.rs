@@ -0,0 +1,422 @@
+//! Makes the brightest parts of the HDR scene bleed light into their
+//! surroundings. What's above the threshold gets blurred through a chain of
+//! ever smaller mips and back up, then added onto the scene before the
+//! tonemapping.
+
+use std::mem::offset_of;
+
+use crate::{
+    gpu_memory::GpuAllocation,
+    pipeline::{PipelineBuilder, RenderTargets},
+    post_process::{encode_fullscreen_pass, HDR_FORMAT},
+    shader::ShaderError,
+    uniform::{Uniform, UniformBuffer, UniformField, WgslType},
+};
+
+pub const BLOOM_SHADER_SOURCE: &str = include_str!("../shaders/bloom.wgsl");
+
+/// More mips blur further, each one adds two passes.
+pub const MAX_BLOOM_MIPS: usize = 6;
+
+/// Adds to what's already in the target, without touching its alpha.
+const ADDITIVE: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+};
+
+/// The sizes of the mips for a `width`x`height` scene. The first is half
+/// its size and each one after is half the one before, down to 1x1 at most.
+pub fn mip_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let mut sizes = Vec::new();
+    let (mut width, mut height) = (width, height);
+    while sizes.len() < MAX_BLOOM_MIPS && (width > 1 || height > 1) {
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+        sizes.push((width, height));
+    }
+    // Even a 1x1 scene gets a mip to blur into
+    if sizes.is_empty() {
+        sizes.push((1, 1));
+    }
+    sizes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    /// Only colors brighter than this bloom, 1 is the brightest a frame can
+    /// show without HDR.
+    pub threshold: f32,
+    /// How much of the blurred light gets added to the scene.
+    pub intensity: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.3,
+        }
+    }
+}
+
+impl BloomSettings {
+    pub fn uniform(&self) -> BloomUniform {
+        BloomUniform {
+            threshold: self.threshold,
+            intensity: self.intensity,
+            _padding: [0; 2],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BloomUniform {
+    pub threshold: f32,
+    pub intensity: f32,
+    _padding: [u32; 2],
+}
+
+impl Uniform for BloomUniform {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "threshold",
+            offset_of!(BloomUniform, threshold),
+            WgslType::F32,
+        ),
+        UniformField::new(
+            "intensity",
+            offset_of!(BloomUniform, intensity),
+            WgslType::F32,
+        ),
+    ];
+}
+
+/// The texture the bloom gets blurred in, and what reads it. Depends on the
+/// size of the scene.
+struct MipChain {
+    texture: wgpu::Texture,
+    _allocation: GpuAllocation,
+    sizes: Vec<(u32, u32)>,
+    /// One per mip, for drawing into it.
+    views: Vec<wgpu::TextureView>,
+    /// One per mip, for reading it in the next pass.
+    bind_groups: Vec<wgpu::BindGroup>,
+    /// Reads the scene, for the first pass.
+    scene_bind_group: wgpu::BindGroup,
+}
+
+impl MipChain {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        config: &wgpu::SurfaceConfiguration,
+        hdr_view: &wgpu::TextureView,
+    ) -> Self {
+        let sizes = mip_sizes(config.width, config.height);
+        let (width, height) = sizes[0];
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("bloom_texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: sizes.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let views: Vec<_> = (0..sizes.len() as u32)
+            .map(|mip| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("bloom_mip_view"),
+                    base_mip_level: mip,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let bind_groups = views
+            .iter()
+            .map(|view| create_texture_bind_group(device, layout, view, sampler))
+            .collect();
+        let scene_bind_group = create_texture_bind_group(device, layout, hdr_view, sampler);
+
+        Self {
+            _allocation: GpuAllocation::texture(&texture),
+            texture,
+            sizes,
+            views,
+            bind_groups,
+            scene_bind_group,
+        }
+    }
+}
+
+/// Draws the bloom of the HDR scene onto it, see `draw`.
+pub struct Bloom {
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    settings: BloomSettings,
+    settings_buffer: UniformBuffer<BloomUniform>,
+    settings_bind_group: wgpu::BindGroup,
+    prefilter_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    upsample_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+    chain: MipChain,
+    enabled: bool,
+}
+
+impl Bloom {
+    /// For the `hdr_view` of a scene the size of `config`, enabled with the
+    /// default settings.
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        hdr_view: &wgpu::TextureView,
+    ) -> Result<Self, ShaderError> {
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("bloom_texture_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+        // The filters sample between texels to average several at once
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("bloom_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let settings = BloomSettings::default();
+        let settings_buffer = UniformBuffer::new(device, "Bloom Buffer", &settings.uniform());
+        let settings_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("bloom_settings_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let settings_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bloom_settings_bind_group"),
+            layout: &settings_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.binding(),
+            }],
+        });
+
+        let shader = crate::shader::create_shader_module(
+            device,
+            "Bloom Shader",
+            "bloom.wgsl",
+            BLOOM_SHADER_SOURCE,
+        )?;
+        let layouts = [&texture_bind_group_layout, &settings_bind_group_layout];
+        let builder = PipelineBuilder::with_targets(
+            "Bloom Pipeline",
+            RenderTargets {
+                color_format: HDR_FORMAT,
+                depth_format: None,
+                sample_count: 1,
+            },
+        )
+        .shader_module(&shader)
+        .bind_group_layouts(&layouts);
+        let prefilter_pipeline = builder
+            .clone()
+            .label("Bloom Prefilter Pipeline")
+            .fragment_entry_point("fs_prefilter")
+            .build(device)?;
+        let downsample_pipeline = builder
+            .clone()
+            .label("Bloom Downsample Pipeline")
+            .fragment_entry_point("fs_downsample")
+            .build(device)?;
+        let upsample_pipeline = builder
+            .clone()
+            .label("Bloom Upsample Pipeline")
+            .fragment_entry_point("fs_upsample")
+            .color_target(HDR_FORMAT, Some(ADDITIVE))
+            .build(device)?;
+        let composite_pipeline = builder
+            .label("Bloom Composite Pipeline")
+            .fragment_entry_point("fs_composite")
+            .color_target(HDR_FORMAT, Some(ADDITIVE))
+            .build(device)?;
+
+        let chain = MipChain::new(
+            device,
+            &texture_bind_group_layout,
+            &sampler,
+            config,
+            hdr_view,
+        );
+
+        Ok(Self {
+            texture_bind_group_layout,
+            sampler,
+            settings,
+            settings_buffer,
+            settings_bind_group,
+            prefilter_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            composite_pipeline,
+            chain,
+            enabled: true,
+        })
+    }
+
+    /// Rebuilds the mip chain for the new `hdr_view` of a scene the size of
+    /// `config`.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        hdr_view: &wgpu::TextureView,
+    ) {
+        let chain = MipChain::new(
+            device,
+            &self.texture_bind_group_layout,
+            &self.sampler,
+            config,
+            hdr_view,
+        );
+        // Frees the old chain right away rather than whenever the last
+        // reference to it goes, resizes come in bursts
+        std::mem::replace(&mut self.chain, chain).texture.destroy();
+    }
+
+    /// The size of every mip the bloom goes through, biggest first.
+    pub fn mip_sizes(&self) -> &[(u32, u32)] {
+        &self.chain.sizes
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Disabled, `draw` doesn't record any passes.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn settings(&self) -> BloomSettings {
+        self.settings
+    }
+
+    /// Shows up from the next submit on.
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: BloomSettings) {
+        self.settings = settings;
+        self.settings_buffer.write(queue, &settings.uniform());
+    }
+
+    /// Records adding the bloom onto `hdr_view`, the view it was created or
+    /// last resized with. Two passes per mip: down the chain and back up.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, hdr_view: &wgpu::TextureView) {
+        if !self.enabled {
+            return;
+        }
+
+        let chain = &self.chain;
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+        encode_fullscreen_pass(
+            encoder,
+            "Bloom Prefilter Render Pass",
+            &chain.views[0],
+            clear,
+            &self.prefilter_pipeline,
+            &[&chain.scene_bind_group, &self.settings_bind_group],
+        );
+        for mip in 1..chain.views.len() {
+            encode_fullscreen_pass(
+                encoder,
+                "Bloom Downsample Render Pass",
+                &chain.views[mip],
+                clear,
+                &self.downsample_pipeline,
+                &[&chain.bind_groups[mip - 1], &self.settings_bind_group],
+            );
+        }
+        for mip in (1..chain.views.len()).rev() {
+            encode_fullscreen_pass(
+                encoder,
+                "Bloom Upsample Render Pass",
+                &chain.views[mip - 1],
+                wgpu::LoadOp::Load,
+                &self.upsample_pipeline,
+                &[&chain.bind_groups[mip], &self.settings_bind_group],
+            );
+        }
+        encode_fullscreen_pass(
+            encoder,
+            "Bloom Composite Render Pass",
+            hdr_view,
+            wgpu::LoadOp::Load,
+            &self.composite_pipeline,
+            &[&chain.bind_groups[0], &self.settings_bind_group],
+        );
+    }
+}
+
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("bloom_texture_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
@@ -0,0 +1,456 @@
+use std::time::Duration;
+
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::{
+    app::{App, GpuContext},
+    color,
+    compute::{self, WORKGROUP_SIZE},
+    error::AppError,
+    pipeline::{self, PipelineBuilder, RenderTargets},
+    shader::{self, ShaderError},
+};
+
+pub const BOIDS_SHADER_SOURCE: &str = include_str!("../shaders/boids.wgsl");
+
+pub const DEFAULT_BOID_COUNT: u32 = 20_000;
+
+/// How much a key press changes a parameter by, see `BoidParams::adjust`.
+const ADJUST_FACTOR: f32 = 1.25;
+
+/// A boid as the compute shader sees it. It lives in the -1 to 1 square,
+/// which is stretched over the window.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Boid {
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+}
+
+impl Boid {
+    pub fn new(position: glam::Vec2, velocity: glam::Vec2) -> Self {
+        Self {
+            position: position.into(),
+            velocity: velocity.into(),
+        }
+    }
+
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2];
+
+    /// The boids are drawn straight from the storage buffer, one triangle
+    /// per instance.
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Boid>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// How the boids steer. The radii are how far a boid looks for others for
+/// each of the rules, the weights how strongly it follows them.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BoidParams {
+    /// Boids this close push each other away.
+    pub separation_radius: f32,
+    /// Boids this close match each other's velocity.
+    pub alignment_radius: f32,
+    /// Boids this close steer towards their center.
+    pub cohesion_radius: f32,
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+    pub max_speed: f32,
+    /// Keeps the boids from stopping in a crowd.
+    pub min_speed: f32,
+}
+
+impl BoidParams {
+    /// Q/A, W/S and E/D raise and lower the separation, alignment and
+    /// cohesion weights, R/F grow and shrink all radii and T/G the top
+    /// speed. Returns `false` for any other key.
+    pub fn adjust(&mut self, key: KeyCode) -> bool {
+        let factor = match key {
+            KeyCode::KeyQ | KeyCode::KeyW | KeyCode::KeyE | KeyCode::KeyR | KeyCode::KeyT => {
+                ADJUST_FACTOR
+            }
+            KeyCode::KeyA | KeyCode::KeyS | KeyCode::KeyD | KeyCode::KeyF | KeyCode::KeyG => {
+                1.0 / ADJUST_FACTOR
+            }
+            _ => return false,
+        };
+        match key {
+            KeyCode::KeyQ | KeyCode::KeyA => self.separation_weight *= factor,
+            KeyCode::KeyW | KeyCode::KeyS => self.alignment_weight *= factor,
+            KeyCode::KeyE | KeyCode::KeyD => self.cohesion_weight *= factor,
+            KeyCode::KeyR | KeyCode::KeyF => {
+                self.separation_radius *= factor;
+                self.alignment_radius *= factor;
+                self.cohesion_radius *= factor;
+            }
+            _ => {
+                self.max_speed *= factor;
+                self.min_speed = self.min_speed.min(self.max_speed);
+            }
+        }
+        true
+    }
+}
+
+impl Default for BoidParams {
+    fn default() -> Self {
+        Self {
+            separation_radius: 0.025,
+            alignment_radius: 0.05,
+            cohesion_radius: 0.1,
+            separation_weight: 20.0,
+            alignment_weight: 1.5,
+            cohesion_weight: 1.0,
+            max_speed: 0.4,
+            min_speed: 0.1,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimParams {
+    params: BoidParams,
+    dt: f32,
+    count: u32,
+    _padding: [u32; 2],
+}
+
+/// The most boids the device can simulate, bounded by how many workgroups
+/// can be dispatched and how big the storage buffers can be.
+pub fn max_boid_count(limits: &wgpu::Limits) -> u32 {
+    let by_dispatch = limits
+        .max_compute_workgroups_per_dimension
+        .saturating_mul(WORKGROUP_SIZE);
+    let by_size = limits.max_storage_buffer_binding_size / std::mem::size_of::<Boid>() as u32;
+    by_dispatch.min(by_size)
+}
+
+/// `count` boids spread over the whole square, flying in every direction.
+pub fn scatter(count: u32) -> Vec<Boid> {
+    // A fixed seed keeps every run the same
+    let mut seed = 0x2545_f491_u32;
+    let mut random = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed as f32 / u32::MAX as f32
+    };
+    let defaults = BoidParams::default();
+    (0..count)
+        .map(|_| {
+            let position = glam::Vec2::new(random(), random()) * 2.0 - 1.0;
+            let angle = random() * std::f32::consts::TAU;
+            let speed = defaults.min_speed + random() * (defaults.max_speed - defaults.min_speed);
+            Boid::new(position, glam::Vec2::from_angle(angle) * speed)
+        })
+        .collect()
+}
+
+/// Boids flocking on the GPU. A compute shader steers every boid by the
+/// separation, alignment and cohesion rules each step, then they're drawn
+/// as triangles pointing where they fly.
+pub struct Boids {
+    count: u32,
+    params: BoidParams,
+    params_buffer: wgpu::Buffer,
+    /// Every step reads one of them and writes the other.
+    buffers: [wgpu::Buffer; 2],
+    /// `bind_groups[i]` reads `buffers[i]`.
+    bind_groups: [wgpu::BindGroup; 2],
+    /// The buffer written by the last step.
+    current: usize,
+    compute_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Boids {
+    /// Draws into `color_format` textures. Only the first `max_boid_count`
+    /// of `boids` are simulated.
+    pub fn new(
+        device: &wgpu::Device,
+        boids: &[Boid],
+        color_format: wgpu::TextureFormat,
+        shader_source: &str,
+    ) -> Result<Self, ShaderError> {
+        let boids = &boids[..boids.len().min(max_boid_count(&device.limits()) as usize)];
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Boid Params Buffer"),
+            size: std::mem::size_of::<SimParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // Empty buffers can't be bound, so there's room for one boid at least
+        let mut contents = bytemuck::cast_slice::<_, u8>(boids).to_vec();
+        contents.resize(contents.len().max(std::mem::size_of::<Boid>()), 0);
+        let create_buffer = |label| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: &contents,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::COPY_SRC,
+            })
+        };
+        let buffers = [
+            create_buffer("Boid Buffer A"),
+            create_buffer("Boid Buffer B"),
+        ];
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("boid_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, false),
+            ],
+        });
+        let create_bind_group = |src: usize| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("boid_bind_group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: buffers[src].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: buffers[1 - src].as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let bind_groups = [create_bind_group(0), create_bind_group(1)];
+
+        let shader =
+            shader::create_shader_module(device, "Boid Shader", "boids.wgsl", shader_source)?;
+        let compute_layout = pipeline::create_pipeline_layout(
+            device,
+            "Boid Compute Pipeline Layout",
+            &[&bind_group_layout],
+        );
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Boid Compute Pipeline"),
+            layout: Some(&compute_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+        let render_pipeline = PipelineBuilder::with_targets(
+            "Boid Render Pipeline",
+            RenderTargets {
+                color_format,
+                depth_format: None,
+                sample_count: 1,
+            },
+        )
+        .shader_module(&shader)
+        .vertex_buffer(Boid::desc())
+        .build(device)?;
+
+        Ok(Self {
+            count: boids.len() as u32,
+            params: BoidParams::default(),
+            params_buffer,
+            buffers,
+            bind_groups,
+            current: 0,
+            compute_pipeline,
+            render_pipeline,
+        })
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn params(&self) -> BoidParams {
+        self.params
+    }
+
+    /// Applies from the next step on.
+    pub fn set_params(&mut self, params: BoidParams) {
+        self.params = params;
+    }
+
+    /// The buffer holding the boids as of the last step.
+    pub fn boid_buffer(&self) -> &wgpu::Buffer {
+        &self.buffers[self.current]
+    }
+
+    /// Records moving the boids ahead by `dt`.
+    pub fn step(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, dt: Duration) {
+        let params = SimParams {
+            params: self.params,
+            dt: dt.as_secs_f32(),
+            count: self.count,
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Boid Compute Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+        compute_pass.dispatch_workgroups(
+            compute::workgroup_count(self.count, WORKGROUP_SIZE),
+            1,
+            1,
+        );
+        drop(compute_pass);
+
+        self.current = 1 - self.current;
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.boid_buffer().slice(..));
+        // 3 vertices make up the triangle of each boid
+        render_pass.draw(0..3, 0..self.count);
+    }
+}
+
+/// The boids demo as an `App`, see `examples/boids.rs`. The keys of
+/// `BoidParams::adjust` change how they fly.
+pub struct BoidsApp {
+    boids: Boids,
+}
+
+impl BoidsApp {
+    /// Flies `count` boids, clamped between 1 and `max_boid_count`. Fails
+    /// if the device can't run compute shaders.
+    pub fn new(ctx: &GpuContext, count: u32) -> Result<Self, AppError> {
+        let device = ctx.device();
+        let compute_shaders = ctx
+            .adapter()
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+        if !compute_shaders || device.limits().max_storage_buffers_per_shader_stage < 2 {
+            return Err(AppError::ComputeUnsupported {
+                adapter: ctx.adapter().get_info().name,
+            });
+        }
+
+        let count = count.clamp(1, max_boid_count(&device.limits()));
+        log::info!("Flying {count} boids");
+        let boids = Boids::new(
+            device,
+            &scatter(count),
+            ctx.render_format(),
+            BOIDS_SHADER_SOURCE,
+        )?;
+        Ok(Self { boids })
+    }
+}
+
+impl App for BoidsApp {
+    /// Panics where `new` would fail.
+    fn init(ctx: &GpuContext) -> Self {
+        match Self::new(ctx, DEFAULT_BOID_COUNT) {
+            Ok(app) => app,
+            Err(err) => panic!("Can't run the boids: {err}"),
+        }
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(code),
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return false;
+        };
+        let mut params = self.boids.params();
+        if !params.adjust(*code) {
+            return false;
+        }
+        log::info!("{params:?}");
+        self.boids.set_params(params);
+        true
+    }
+
+    fn update(&mut self, ctx: &GpuContext, dt: Duration) {
+        let mut encoder = ctx
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Boid Compute Encoder"),
+            });
+        self.boids.step(ctx.queue(), &mut encoder, dt);
+        ctx.queue().submit(std::iter::once(encoder.finish()));
+    }
+
+    fn render(
+        &mut self,
+        ctx: &GpuContext,
+        view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Boid Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(color::clear_value_for_format(
+                        wgpu::Color {
+                            r: 0.005,
+                            g: 0.005,
+                            b: 0.02,
+                            a: 1.0,
+                        },
+                        ctx.render_format(),
+                    )),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.boids.draw(&mut render_pass);
+    }
+}
@@ -0,0 +1,247 @@
+//! Buffers that know what's in them. Each owns its `wgpu::Buffer` and how
+//! many elements it holds, so drawing doesn't need the counts and formats
+//! passed around next to it.
+
+use std::{marker::PhantomData, ops::Range};
+
+use wgpu::util::DeviceExt;
+
+use crate::{gpu_memory::GpuAllocation, upload::UploadArena, vertex::IndexType};
+
+/// How much bigger a `GrowableBuffer` gets when data doesn't fit.
+pub const GROWTH_FACTOR: usize = 2;
+
+fn create_buffer_init<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    label: &str,
+    contents: &[T],
+    usage: wgpu::BufferUsages,
+) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: bytemuck::cast_slice(contents),
+        usage,
+    })
+}
+
+/// A vertex buffer of `T`s that doesn't change.
+pub struct VertexBuffer<T> {
+    buffer: wgpu::Buffer,
+    allocation: GpuAllocation,
+    len: u32,
+    _vertex: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> VertexBuffer<T> {
+    pub fn new(device: &wgpu::Device, label: &str, vertices: &[T]) -> Self {
+        let buffer = create_buffer_init(device, label, vertices, wgpu::BufferUsages::VERTEX);
+        Self {
+            allocation: GpuAllocation::buffer(&buffer),
+            buffer,
+            len: vertices.len() as u32,
+            _vertex: PhantomData,
+        }
+    }
+}
+
+impl<T> VertexBuffer<T> {
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn allocated_bytes(&self) -> u64 {
+        self.allocation.bytes()
+    }
+
+    /// Binds the buffer to vertex buffer `slot`.
+    pub fn bind<'a>(&'a self, encoder: &mut impl wgpu::util::RenderEncoder<'a>, slot: u32) {
+        encoder.set_vertex_buffer(slot, self.buffer.slice(..));
+    }
+
+    /// All the vertices, for `draw`.
+    pub fn draw_range(&self) -> Range<u32> {
+        0..self.len
+    }
+}
+
+/// An index buffer of `I`s that doesn't change, never empty.
+pub struct IndexBuffer<I> {
+    buffer: wgpu::Buffer,
+    allocation: GpuAllocation,
+    len: u32,
+    _index: PhantomData<I>,
+}
+
+impl<I: IndexType> IndexBuffer<I> {
+    /// `None` if there is nothing to index, since wgpu doesn't allow binding
+    /// an empty buffer slice.
+    pub fn new(device: &wgpu::Device, label: &str, indices: &[I]) -> Option<Self> {
+        if indices.is_empty() {
+            return None;
+        }
+
+        let buffer = create_buffer_init(device, label, indices, wgpu::BufferUsages::INDEX);
+        Some(Self {
+            allocation: GpuAllocation::buffer(&buffer),
+            buffer,
+            len: indices.len() as u32,
+            _index: PhantomData,
+        })
+    }
+
+    pub fn format(&self) -> wgpu::IndexFormat {
+        I::FORMAT
+    }
+
+    /// Binds the buffer as the index buffer.
+    pub fn bind<'a>(&'a self, encoder: &mut impl wgpu::util::RenderEncoder<'a>) {
+        encoder.set_index_buffer(self.buffer.slice(..), I::FORMAT);
+    }
+}
+
+impl<I> IndexBuffer<I> {
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn allocated_bytes(&self) -> u64 {
+        self.allocation.bytes()
+    }
+
+    /// All the indices, for `draw_indexed`.
+    pub fn draw_range(&self) -> Range<u32> {
+        0..self.len
+    }
+}
+
+/// A buffer of `T`s that can be refilled, with room for more than it holds.
+///
+/// It gets reallocated when new data doesn't fit, which `update` and
+/// `upload` return `true` for. Bind groups and render bundles with the old
+/// buffer in them have to be made again then.
+pub struct GrowableBuffer<T> {
+    buffer: wgpu::Buffer,
+    allocation: GpuAllocation,
+    label: String,
+    usage: wgpu::BufferUsages,
+    len: usize,
+    capacity: usize,
+    _element: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> GrowableBuffer<T> {
+    /// A buffer holding `data` with `usage`, and `COPY_DST` to refill it.
+    pub fn new(device: &wgpu::Device, label: &str, usage: wgpu::BufferUsages, data: &[T]) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        // An empty buffer can't be bound, so always keep room for one
+        let buffer = if data.is_empty() {
+            Self::create_buffer(device, label, usage, 1)
+        } else {
+            create_buffer_init(device, label, data, usage)
+        };
+        Self {
+            allocation: GpuAllocation::buffer(&buffer),
+            buffer,
+            label: label.to_owned(),
+            usage,
+            len: data.len(),
+            capacity: data.len().max(1),
+            _element: PhantomData,
+        }
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        label: &str,
+        usage: wgpu::BufferUsages,
+        capacity: usize,
+    ) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress,
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Writes `data` over what's there, reusing the buffer if it's big
+    /// enough. Returns whether it had to be reallocated.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[T]) -> bool {
+        let reallocated = self.set_len(device, data.len());
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(data));
+        reallocated
+    }
+
+    /// Like `update`, through `uploads` with a copy recorded into `encoder`.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        uploads: &mut UploadArena,
+        data: &[T],
+    ) -> bool {
+        let reallocated = self.set_len(device, data.len());
+        uploads.write(device, encoder, &self.buffer, 0, data);
+        reallocated
+    }
+
+    /// Makes room for `len` elements, growing by at least `GROWTH_FACTOR`.
+    fn set_len(&mut self, device: &wgpu::Device, len: usize) -> bool {
+        self.len = len;
+        if len <= self.capacity {
+            return false;
+        }
+        self.capacity = len.max(self.capacity * GROWTH_FACTOR);
+        self.buffer = Self::create_buffer(device, &self.label, self.usage, self.capacity);
+        self.allocation = GpuAllocation::buffer(&self.buffer);
+        true
+    }
+}
+
+impl<T> GrowableBuffer<T> {
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn allocated_bytes(&self) -> u64 {
+        self.allocation.bytes()
+    }
+
+    /// Binds the buffer to vertex buffer `slot`, the whole of it.
+    pub fn bind<'a>(&'a self, encoder: &mut impl wgpu::util::RenderEncoder<'a>, slot: u32) {
+        encoder.set_vertex_buffer(slot, self.buffer.slice(..));
+    }
+
+    /// The elements in it, for `draw`.
+    pub fn draw_range(&self) -> Range<u32> {
+        0..self.len as u32
+    }
+}
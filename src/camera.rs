@@ -0,0 +1,569 @@
+use std::{mem::offset_of, time::Duration};
+
+use glam::{Mat4, Quat, Vec3};
+
+use crate::{
+    culling::Aabb,
+    uniform::{Uniform, UniformField, WgslType},
+};
+
+/// wgpu's normalized device coordinates have z going from 0 to 1, while
+/// OpenGL-style projection matrices map it to -1..1. Without this correction
+/// half of the depth range would be clipped away.
+#[rustfmt::skip]
+pub const OPENGL_TO_WGPU_MATRIX: Mat4 = Mat4::from_cols_array(&[
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+]);
+
+/// How close `Camera::zoom` gets the eye to the target.
+pub const MIN_ZOOM_DISTANCE: f32 = 0.5;
+
+/// How much closer one line of scrolling brings an `OrbitCamera` to its
+/// focus, as a factor of the distance. Scrolling by the same amount always
+/// zooms by the same factor, however close the focus is.
+pub const ORBIT_ZOOM_PER_LINE: f32 = 0.9;
+
+/// How quickly an `OrbitCamera` catches up with where the mouse moved it,
+/// per second. A frame taking `dt` covers `1 - e^(-ORBIT_SMOOTHING * dt)`
+/// of the way left.
+pub const ORBIT_SMOOTHING: f32 = 20.0;
+
+/// How far up or down `Camera::rotate` lets the camera look, in degrees.
+/// Looking straight up or down would make the view direction parallel to
+/// `up`, and the camera would flip over.
+pub const MAX_PITCH: f32 = 89.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub eye: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    pub aspect: f32,
+    /// Vertical field of view, in degrees.
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl Camera {
+    /// Moves the eye towards the target by `fraction` of the distance
+    /// between them, or away from it for negative values. The eye stops
+    /// short of the target so the view direction stays defined.
+    pub fn zoom(&mut self, fraction: f32) {
+        let offset = self.eye - self.target;
+        let distance = (offset.length() * (1.0 - fraction)).max(MIN_ZOOM_DISTANCE);
+        self.eye = self.target + offset.normalize() * distance;
+    }
+
+    /// Turns the camera around its eye, like turning one's head. Positive
+    /// `yaw` turns left and positive `pitch` looks up, both in degrees. The
+    /// pitch stays within `MAX_PITCH`.
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        let offset = self.target - self.eye;
+        let up = self.up.normalize();
+        let direction = Quat::from_axis_angle(up, yaw.to_radians()) * offset.normalize();
+
+        let current_pitch = direction.dot(up).clamp(-1.0, 1.0).asin().to_degrees();
+        let new_pitch = (current_pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+        let right = direction.cross(up).normalize();
+        let direction =
+            Quat::from_axis_angle(right, (new_pitch - current_pitch).to_radians()) * direction;
+
+        self.target = self.eye + direction * offset.length();
+    }
+
+    /// Moves the eye around the target, which stays where it is. The angles
+    /// are like the ones of `rotate`, but turn the world in front of the
+    /// camera instead of the camera's head.
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        // Turning the view direction around the eye and putting the eye
+        // back in front of the target orbits it
+        let distance = (self.target - self.eye).length();
+        let target = self.target;
+        self.rotate(yaw, pitch);
+        let direction = (self.target - self.eye).normalize();
+        self.eye = target - direction * distance;
+        self.target = target;
+    }
+
+    /// Slides the eye and the target sideways by `right` and up by `up`,
+    /// in world units along the view plane.
+    pub fn pan(&mut self, right: f32, up: f32) {
+        let forward = (self.target - self.eye).normalize();
+        let right_axis = forward.cross(self.up).normalize();
+        let up_axis = right_axis.cross(forward);
+        let offset = right_axis * right + up_axis * up;
+        self.eye += offset;
+        self.target += offset;
+    }
+
+    /// How many world units a pixel covers at the target, for a viewport
+    /// `height` pixels high. Panning by it keeps what's under a finger
+    /// under it.
+    pub fn world_units_per_pixel(&self, height: u32) -> f32 {
+        let distance = (self.target - self.eye).length();
+        2.0 * distance * (self.fovy.to_radians() / 2.0).tan() / height.max(1) as f32
+    }
+
+    pub fn build_view_matrix(&self) -> Mat4 {
+        // Moves the world to be at the position and rotation of the camera
+        Mat4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    pub fn build_projection_matrix(&self) -> Mat4 {
+        // Warps the scene to give the effect of depth
+        let proj =
+            Mat4::perspective_rh_gl(self.fovy.to_radians(), self.aspect, self.znear, self.zfar);
+
+        OPENGL_TO_WGPU_MATRIX * proj
+    }
+
+    pub fn build_view_projection_matrix(&self) -> Mat4 {
+        self.build_projection_matrix() * self.build_view_matrix()
+    }
+}
+
+// We need this for Rust to store our data correctly for the shaders
+#[repr(C)]
+// This is so we can store this in a buffer
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    // The eye position is needed for specular lighting. It's a vec4 as
+    // uniforms require 16 byte alignment.
+    pub view_position: [f32; 4],
+    // A plain 4x4 f32 array keeps the layout obvious from the WGSL side
+    pub view_proj: [[f32; 4]; 4],
+    // Turns clip space positions back into view directions, ignoring the
+    // camera's translation. Used to sample the skybox.
+    pub inv_sky_view_proj: [[f32; 4]; 4],
+    // Turns clip space positions back into world space ones, e.g. to cast
+    // the rays of the ground grid
+    pub inv_view_proj: [[f32; 4]; 4],
+}
+
+impl Uniform for CameraUniform {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "view_pos",
+            offset_of!(CameraUniform, view_position),
+            WgslType::Vec4,
+        ),
+        UniformField::new(
+            "view_proj",
+            offset_of!(CameraUniform, view_proj),
+            WgslType::Mat4,
+        ),
+        UniformField::new(
+            "inv_sky_view_proj",
+            offset_of!(CameraUniform, inv_sky_view_proj),
+            WgslType::Mat4,
+        ),
+        UniformField::new(
+            "inv_view_proj",
+            offset_of!(CameraUniform, inv_view_proj),
+            WgslType::Mat4,
+        ),
+    ];
+}
+
+impl CameraUniform {
+    pub fn new() -> Self {
+        Self {
+            view_position: [0.0; 4],
+            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            inv_sky_view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            inv_view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+        }
+    }
+
+    pub fn update_view_proj(&mut self, camera: &Camera) {
+        self.view_position = camera.eye.extend(1.0).into();
+        let view_proj = camera.build_view_projection_matrix();
+        self.view_proj = view_proj.to_cols_array_2d();
+        self.inv_view_proj = view_proj.inverse().to_cols_array_2d();
+
+        let mut view = camera.build_view_matrix();
+        view.w_axis = glam::Vec4::W;
+        self.inv_sky_view_proj = (camera.build_projection_matrix() * view)
+            .inverse()
+            .to_cols_array_2d();
+    }
+}
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The layout of the bind group every scene pipeline binds at group 0: the
+/// `CameraUniform`, and the `Globals` at `globals::BINDING`. The cameras of
+/// all the views share one buffer, and the camera is bound at the dynamic
+/// offset of its view, see `CameraBinding`.
+pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("camera_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                // The fragment shader needs the eye position for lighting
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<CameraUniform>() as u64
+                    ),
+                },
+                count: None,
+            },
+            crate::globals::layout_entry(),
+        ],
+    })
+}
+
+/// The bind group of `create_bind_group_layout` with the offset of the
+/// camera to draw with.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraBinding<'a> {
+    pub bind_group: &'a wgpu::BindGroup,
+    pub offset: u32,
+}
+
+impl<'a> CameraBinding<'a> {
+    pub fn bind(self, encoder: &mut impl wgpu::util::RenderEncoder<'a>, index: u32) {
+        encoder.set_bind_group(index, self.bind_group, &[self.offset]);
+    }
+}
+
+/// A way the `CameraController` can move the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Forward,
+    Backward,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Moves a `Camera` while movement keys are held, see `set_moving`, or by
+/// analog amounts like the axes of an `InputMap`.
+#[derive(Debug, Clone, Default)]
+pub struct CameraController {
+    /// In units per second.
+    pub speed: f32,
+    is_forward_pressed: bool,
+    is_backward_pressed: bool,
+    is_left_pressed: bool,
+    is_right_pressed: bool,
+    is_up_pressed: bool,
+    is_down_pressed: bool,
+    /// Analog input, e.g. from a gamepad stick, see `set_analog_movement`.
+    analog_forward: f32,
+    analog_right: f32,
+    analog_up: f32,
+}
+
+impl CameraController {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            ..Default::default()
+        }
+    }
+
+    /// Releases every key, e.g. when the window loses focus and we would
+    /// never get the release events.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.speed);
+    }
+
+    /// Moves like the forward/backward and left/right keys, but by a
+    /// fraction of the speed. Both are between -1 and 1.
+    pub fn set_analog_movement(&mut self, forward: f32, right: f32) {
+        self.analog_forward = forward.clamp(-1.0, 1.0);
+        self.analog_right = right.clamp(-1.0, 1.0);
+    }
+
+    /// Like `set_analog_movement`, for the up/down keys.
+    pub fn set_analog_up(&mut self, up: f32) {
+        self.analog_up = up.clamp(-1.0, 1.0);
+    }
+
+    /// Whether `update_camera` would move the camera.
+    pub fn is_moving(&self) -> bool {
+        self.is_forward_pressed
+            || self.is_backward_pressed
+            || self.is_left_pressed
+            || self.is_right_pressed
+            || self.is_up_pressed
+            || self.is_down_pressed
+            || self.analog_forward != 0.0
+            || self.analog_right != 0.0
+            || self.analog_up != 0.0
+    }
+
+    /// Starts or stops moving in `direction`, e.g. when its key is pressed
+    /// or released.
+    pub fn set_moving(&mut self, direction: Direction, moving: bool) {
+        let pressed = match direction {
+            Direction::Forward => &mut self.is_forward_pressed,
+            Direction::Backward => &mut self.is_backward_pressed,
+            Direction::Left => &mut self.is_left_pressed,
+            Direction::Right => &mut self.is_right_pressed,
+            Direction::Up => &mut self.is_up_pressed,
+            Direction::Down => &mut self.is_down_pressed,
+        };
+        *pressed = moving;
+    }
+
+    /// Moves the camera by how far it should have gone in `dt`.
+    pub fn update_camera(&self, camera: &mut Camera, dt: Duration) {
+        let amount = self.speed * dt.as_secs_f32();
+
+        let forward = camera.target - camera.eye;
+        let forward_norm = forward.normalize();
+        let forward_mag = forward.length();
+
+        // Prevents glitching when the camera gets too close to the
+        // center of the scene.
+        if self.is_forward_pressed && forward_mag > amount {
+            camera.eye += forward_norm * amount;
+        }
+        if self.is_backward_pressed {
+            camera.eye -= forward_norm * amount;
+        }
+        let analog_amount = amount * self.analog_forward;
+        if forward_mag > analog_amount {
+            camera.eye += forward_norm * analog_amount;
+        }
+
+        let right = forward_norm.cross(camera.up);
+
+        // Redo radius calc in case the forward/backward is pressed.
+        let forward = camera.target - camera.eye;
+        let forward_mag = forward.length();
+
+        if self.is_right_pressed {
+            // Rescale the distance between the target and the eye so
+            // that it doesn't change. The eye, therefore, still
+            // lies on the circle made by the target and eye.
+            camera.eye = camera.target - (forward + right * amount).normalize() * forward_mag;
+        }
+        if self.is_left_pressed {
+            camera.eye = camera.target - (forward - right * amount).normalize() * forward_mag;
+        }
+        if self.analog_right != 0.0 {
+            camera.eye = camera.target
+                - (forward + right * amount * self.analog_right).normalize() * forward_mag;
+        }
+
+        if self.is_up_pressed {
+            camera.eye += camera.up * amount;
+            camera.target += camera.up * amount;
+        }
+        if self.is_down_pressed {
+            camera.eye -= camera.up * amount;
+            camera.target -= camera.up * amount;
+        }
+        let analog_amount = amount * self.analog_up;
+        camera.eye += camera.up * analog_amount;
+        camera.target += camera.up * analog_amount;
+    }
+}
+
+/// Which controller moves the focused camera, see `State::set_camera_mode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CameraMode {
+    /// The movement keys move the eye, mouse look turns it, see
+    /// `CameraController`.
+    #[default]
+    Fly,
+    /// The mouse turns the camera around a focus point, see `OrbitCamera`.
+    Orbit,
+}
+
+impl CameraMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Fly => Self::Orbit,
+            Self::Orbit => Self::Fly,
+        }
+    }
+}
+
+/// Where an `OrbitCamera` puts the eye: `distance` away from `focus`, at
+/// `yaw` degrees around the Y axis from +Z and `pitch` degrees above the
+/// plane through the focus. Cameras are expected to have Y up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitPose {
+    pub focus: Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl OrbitPose {
+    /// The pose that sees what `camera` sees, focused on its target.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let offset = camera.eye - camera.target;
+        let distance = offset.length().max(MIN_ZOOM_DISTANCE);
+        let direction = offset / offset.length().max(f32::EPSILON);
+        Self {
+            focus: camera.target,
+            distance,
+            yaw: direction.x.atan2(direction.z).to_degrees(),
+            pitch: direction
+                .y
+                .clamp(-1.0, 1.0)
+                .asin()
+                .to_degrees()
+                .clamp(-MAX_PITCH, MAX_PITCH),
+        }
+    }
+
+    pub fn eye(&self) -> Vec3 {
+        let (yaw_sin, yaw_cos) = self.yaw.to_radians().sin_cos();
+        let (pitch_sin, pitch_cos) = self.pitch.to_radians().sin_cos();
+        self.focus + Vec3::new(pitch_cos * yaw_sin, pitch_sin, pitch_cos * yaw_cos) * self.distance
+    }
+
+    /// Looking at `bounds` from the same direction, close enough for the
+    /// sphere around them to fill a vertical field of view of `fovy`
+    /// degrees.
+    pub fn framing(self, bounds: Aabb, fovy: f32) -> Self {
+        let radius = bounds.extents().length();
+        let half_fovy = (fovy.to_radians() / 2.0).max(f32::EPSILON);
+        Self {
+            focus: bounds.center(),
+            distance: (radius / half_fovy.sin()).max(MIN_ZOOM_DISTANCE),
+            ..self
+        }
+    }
+
+    /// Moves `camera` to the pose, looking at the focus.
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.eye = self.eye();
+        camera.target = self.focus;
+    }
+
+    /// Part of the way to `goal`, `t` from 0 for none to 1 for all of it.
+    fn lerp(self, goal: Self, t: f32) -> Self {
+        Self {
+            focus: self.focus.lerp(goal.focus, t),
+            // Zooming in and out by a factor takes as long either way
+            distance: self.distance * (goal.distance / self.distance).powf(t),
+            yaw: self.yaw + (goal.yaw - self.yaw) * t,
+            pitch: self.pitch + (goal.pitch - self.pitch) * t,
+        }
+    }
+}
+
+/// Turns a camera around a focus point: dragging orbits it and pans the
+/// focus, scrolling zooms. The mouse moves where the camera is headed, and
+/// `update_camera` moves it there smoothly by the frame's delta time, so
+/// dragging feels the same at any frame rate.
+///
+/// Other changes to the camera, e.g. touch gestures or mouse look, are
+/// picked up by the next `update_camera`.
+#[derive(Debug, Clone)]
+pub struct OrbitCamera {
+    /// How far a one pixel drag orbits, in degrees.
+    pub sensitivity: f32,
+    pose: OrbitPose,
+    goal: OrbitPose,
+    /// The eye and target `update_camera` last left the camera with.
+    applied: Option<(Vec3, Vec3)>,
+}
+
+impl OrbitCamera {
+    /// Orbits what `camera` is looking at, from where it is.
+    pub fn new(camera: &Camera) -> Self {
+        let pose = OrbitPose::from_camera(camera);
+        Self {
+            sensitivity: 0.3,
+            pose,
+            goal: pose,
+            applied: None,
+        }
+    }
+
+    /// Where the camera is.
+    pub fn pose(&self) -> OrbitPose {
+        self.pose
+    }
+
+    /// Where the camera is headed.
+    pub fn goal(&self) -> OrbitPose {
+        self.goal
+    }
+
+    /// Whether `update_camera` would still move the camera.
+    pub fn is_moving(&self) -> bool {
+        self.pose != self.goal
+    }
+
+    /// Orbits by a drag of `dx` pixels right and `dy` down, turning the
+    /// world the way the mouse goes. The pitch stays within `MAX_PITCH`, so
+    /// the camera never goes over the top and flips.
+    pub fn drag_orbit(&mut self, dx: f32, dy: f32) {
+        self.goal.yaw -= dx * self.sensitivity;
+        self.goal.pitch = (self.goal.pitch + dy * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Moves the focus along the view plane by a drag of `dx` pixels right
+    /// and `dy` down, `units_per_pixel` each, so what's under the mouse
+    /// stays under it. See `Camera::world_units_per_pixel`.
+    pub fn drag_pan(&mut self, dx: f32, dy: f32, units_per_pixel: f32) {
+        let forward = (self.goal.focus - self.goal.eye()).normalize();
+        let right = forward.cross(Vec3::Y).normalize();
+        let up = right.cross(forward);
+        self.goal.focus += (up * dy - right * dx) * units_per_pixel;
+    }
+
+    /// Zooms in by `lines` of scrolling, out for negative ones, see
+    /// `ORBIT_ZOOM_PER_LINE`.
+    pub fn zoom(&mut self, lines: f32) {
+        self.goal.distance =
+            (self.goal.distance * ORBIT_ZOOM_PER_LINE.powf(lines)).max(MIN_ZOOM_DISTANCE);
+    }
+
+    /// Heads for `bounds`, see `OrbitPose::framing`.
+    pub fn focus_on(&mut self, bounds: Aabb, fovy: f32) {
+        self.goal = self.goal.framing(bounds, fovy);
+    }
+
+    /// Moves `camera` towards the goal by how far it should have gone in
+    /// `dt`, first catching up with whatever else moved it.
+    pub fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        if self.applied != Some((camera.eye, camera.target)) {
+            // Where the mouse was taking it is kept, from where it is now
+            let pose = OrbitPose::from_camera(camera);
+            self.goal = OrbitPose {
+                focus: pose.focus + (self.goal.focus - self.pose.focus),
+                distance: pose.distance * self.goal.distance / self.pose.distance,
+                yaw: pose.yaw + (self.goal.yaw - self.pose.yaw),
+                pitch: (pose.pitch + (self.goal.pitch - self.pose.pitch))
+                    .clamp(-MAX_PITCH, MAX_PITCH),
+            };
+            self.pose = pose;
+        }
+
+        let t = 1.0 - (-ORBIT_SMOOTHING * dt.as_secs_f32()).exp();
+        self.pose = self.pose.lerp(self.goal, t);
+        // Close enough, and it stops asking for frames
+        if self.pose.focus.abs_diff_eq(self.goal.focus, 1e-4)
+            && (self.pose.distance - self.goal.distance).abs() < 1e-4
+            && (self.pose.yaw - self.goal.yaw).abs() < 1e-3
+            && (self.pose.pitch - self.goal.pitch).abs() < 1e-3
+        {
+            self.pose = self.goal;
+        }
+        self.pose.apply(camera);
+        self.applied = Some((camera.eye, camera.target));
+    }
+}
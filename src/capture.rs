@@ -0,0 +1,204 @@
+use std::{
+    path::PathBuf,
+    sync::mpsc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::texture::padded_bytes_per_row;
+
+/// Copies a texture into a buffer and reads it back on the CPU once the GPU
+/// is done with it.
+pub struct TextureReadback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    format: wgpu::TextureFormat,
+    receiver: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+impl TextureReadback {
+    /// Records the copy of `texture` into `encoder`. The texture must be a
+    /// 4 bytes per pixel RGBA or BGRA texture with `COPY_SRC` usage.
+    ///
+    /// Call `map` once the encoder has been submitted.
+    pub fn new(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) -> Self {
+        let (width, height) = (texture.width(), texture.height());
+        // copy_texture_to_buffer requires rows aligned to 256 bytes
+        let padded_bytes_per_row = padded_bytes_per_row(4 * width);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            format: texture.format(),
+            receiver: None,
+        }
+    }
+
+    /// Starts mapping the buffer. Must be called after the copy was
+    /// submitted, the result is then available through `try_read` once the
+    /// device has been polled.
+    pub fn map(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                // The receiver is gone if the readback was dropped, which is fine
+                let _ = sender.send(result);
+            });
+        self.receiver = Some(receiver);
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the tightly packed RGBA8 pixels if the buffer has been mapped,
+    /// or `None` if the GPU isn't done yet.
+    pub fn try_read(&self) -> Option<Result<Vec<u8>, wgpu::BufferAsyncError>> {
+        let result = self.receiver.as_ref()?.try_recv().ok()?;
+        Some(result.map(|()| self.read_mapped()))
+    }
+
+    /// Blocks until the buffer is mapped and returns its pixels, see
+    /// `try_read`.
+    pub fn read(&self, device: &wgpu::Device) -> Result<Vec<u8>, wgpu::BufferAsyncError> {
+        device.poll(wgpu::Maintain::Wait);
+        match self.receiver.as_ref().map(|receiver| receiver.recv()) {
+            Some(Ok(result)) => result.map(|()| self.read_mapped()),
+            _ => Err(wgpu::BufferAsyncError),
+        }
+    }
+
+    fn read_mapped(&self) -> Vec<u8> {
+        let mut pixels = {
+            let data = self.buffer.slice(..).get_mapped_range();
+            unpad_rows(
+                &data,
+                self.padded_bytes_per_row as usize,
+                4 * self.width as usize,
+            )
+        };
+        self.buffer.unmap();
+
+        if matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            bgra_to_rgba(&mut pixels);
+        }
+        pixels
+    }
+}
+
+/// Removes the padding at the end of each row.
+pub fn unpad_rows(data: &[u8], padded_row: usize, unpadded_row: usize) -> Vec<u8> {
+    data.chunks_exact(padded_row)
+        .flat_map(|row| &row[..unpadded_row])
+        .copied()
+        .collect()
+}
+
+pub fn bgra_to_rgba(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+}
+
+/// A screenshot waiting for its readback to finish before being saved.
+pub struct PendingScreenshot {
+    pub readback: TextureReadback,
+    pub path: PathBuf,
+}
+
+impl PendingScreenshot {
+    /// Saves the screenshot if the readback is done. Returns `true` once the
+    /// screenshot is finished, whether saving it worked or not.
+    pub fn try_save(&self) -> bool {
+        let Some(result) = self.readback.try_read() else {
+            return false;
+        };
+
+        let saved = result.map_err(|err| err.to_string()).and_then(|pixels| {
+            image::save_buffer(
+                &self.path,
+                &pixels,
+                self.readback.width(),
+                self.readback.height(),
+                image::ColorType::Rgba8,
+            )
+            .map_err(|err| err.to_string())
+        });
+        match saved {
+            Ok(()) => log::info!("Saved screenshot to {}", self.path.display()),
+            Err(err) => log::error!("Failed to save screenshot {}: {err}", self.path.display()),
+        }
+        true
+    }
+}
+
+/// `screenshot-YYYY-MM-DD_HH-MM-SS.png`, in UTC.
+pub fn screenshot_file_name() -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    PathBuf::from(format!(
+        "screenshot-{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}.png",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    ))
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date, see
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
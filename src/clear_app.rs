@@ -0,0 +1,108 @@
+use winit::{
+    dpi::PhysicalSize,
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::ModifiersState,
+};
+
+use crate::{
+    app::{App, GpuContext},
+    color,
+    key_bindings::{Action, KeyBindings},
+};
+
+/// The smallest `App` there is, it only clears the screen. The cursor picks
+/// the red and green amounts, the number keys 1 to 5 the color presets,
+/// just like the clear color of the bundled scene.
+pub struct ClearApp {
+    clear_color: wgpu::Color,
+    size: PhysicalSize<u32>,
+    key_bindings: KeyBindings,
+    modifiers: ModifiersState,
+}
+
+impl ClearApp {
+    /// In linear space.
+    pub fn clear_color(&self) -> wgpu::Color {
+        self.clear_color
+    }
+}
+
+impl App for ClearApp {
+    fn init(ctx: &GpuContext) -> Self {
+        Self {
+            clear_color: color::PRESETS[0],
+            size: ctx.size(),
+            key_bindings: KeyBindings::default(),
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.clear_color.r = (position.x / self.size.width as f64).clamp(0.0, 1.0);
+                self.clear_color.g = (position.y / self.size.height as f64).clamp(0.0, 1.0);
+                true
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                false
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key,
+                        logical_key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                let action = self
+                    .key_bindings
+                    .action(physical_key, logical_key, self.modifiers);
+                let Some(Action::ClearColorPreset(preset)) = action else {
+                    return false;
+                };
+                match color::PRESETS.get(preset) {
+                    Some(&color) => {
+                        self.clear_color = color;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn resize(&mut self, _ctx: &GpuContext, size: PhysicalSize<u32>) {
+        self.size = size;
+    }
+
+    fn render(
+        &mut self,
+        ctx: &GpuContext,
+        view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        // Clearing is all the render pass does
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Clear Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(color::clear_value_for_format(
+                        self.clear_color,
+                        ctx.render_format(),
+                    )),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+    }
+}
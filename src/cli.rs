@@ -0,0 +1,426 @@
+//! The command line of the binary, parsed by hand. `Cli::parse` finds out
+//! what to do and `Cli::run_config` the options to do it with.
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    adapter::AdapterSelection, present_mode::PresentModePreference, run_config::RunConfig,
+};
+
+/// What `--help` prints.
+pub const USAGE: &str = "\
+Usage: wgpu-learning [OPTIONS]
+
+Opens the scene in a window, unless one of these says otherwise:
+  --headless --frames <N>   Renders N frames offscreen and prints how long they took
+  --bench <N>               Same as --headless --frames N
+  --json                    Also prints the --headless report as JSON
+  --bench-uploads <N>       Times N frames of small uploads
+  --shadertoy <FILE>        Draws the main_image of a WGSL file, reloading it on save
+  --list-adapters           Prints the adapters and exits
+  -h, --help                Prints this
+
+Options:
+  --width <W> --height <H>  The size of the window, or of the frames with --headless
+  --fullscreen              Opens the window borderless fullscreen
+  --backend <API>           vulkan, dx12, metal or gl, WGPU_BACKEND otherwise
+  --adapter <INDEX|NAME>    An index from --list-adapters, or part of a name
+  --present-mode <MODE>     vsync, immediate, mailbox or auto
+  --msaa <1|2|4|8>          The MSAA sample count";
+
+/// The size frames get rendered at with `--headless` when there's no
+/// `--width` and `--height`.
+pub const HEADLESS_SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 720);
+
+/// What the binary was asked to do.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Opens the scene in a window.
+    Run,
+    /// Renders `frames` frames offscreen and prints how long they took,
+    /// also as JSON with `json`.
+    Headless {
+        frames: u32,
+        json: bool,
+    },
+    /// Times `frames` frames of small uploads.
+    BenchUploads {
+        frames: u32,
+    },
+    /// Runs a shader playground, see `shadertoy`.
+    Shadertoy {
+        path: PathBuf,
+    },
+    ListAdapters,
+    Help,
+}
+
+/// Arguments that don't make sense. The errors say which flag is at fault.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum CliError {
+    #[error("Unknown argument {0:?}, --help lists them")]
+    UnknownArgument(String),
+
+    #[error("{flag} needs a value, e.g. {flag} {example}")]
+    MissingValue {
+        flag: &'static str,
+        example: &'static str,
+    },
+
+    #[error("{flag} can't be {value:?}, it takes {expected}")]
+    InvalidValue {
+        flag: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+
+    #[error("{flag} needs {required} too")]
+    Requires {
+        flag: &'static str,
+        required: &'static str,
+    },
+
+    #[error("{flag} can't be used with {other}")]
+    Conflict {
+        flag: &'static str,
+        other: &'static str,
+    },
+}
+
+/// The parsed command line. Options that weren't given are `None`, they
+/// keep the defaults of `RunConfig`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cli {
+    pub command: Command,
+    pub size: Option<PhysicalSize<u32>>,
+    pub fullscreen: bool,
+    pub backends: Option<wgpu::Backends>,
+    pub adapter: Option<AdapterSelection>,
+    pub present_mode: Option<PresentModePreference>,
+    pub sample_count: Option<u32>,
+}
+
+impl Cli {
+    /// Parses `args`, without the name of the program.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut args = args.into_iter();
+        let mut width = None;
+        let mut height = None;
+        let mut fullscreen = false;
+        let mut backends = None;
+        let mut adapter = None;
+        let mut present_mode = None;
+        let mut sample_count = None;
+        let mut frames = None;
+        let mut json = false;
+        // The flag that picked the command, for the errors
+        let mut command: Option<(&'static str, Command)> = None;
+
+        while let Some(arg) = args.next() {
+            let picked = match arg.as_str() {
+                "-h" | "--help" => return Ok(Self::new(Command::Help)),
+                "--width" => {
+                    width = Some(parse_size(
+                        "--width",
+                        &value(&mut args, "--width", "1280")?,
+                    )?);
+                    None
+                }
+                "--height" => {
+                    height = Some(parse_size(
+                        "--height",
+                        &value(&mut args, "--height", "720")?,
+                    )?);
+                    None
+                }
+                "--fullscreen" => {
+                    fullscreen = true;
+                    None
+                }
+                "--backend" => {
+                    backends = Some(parse_backend(&value(&mut args, "--backend", "vulkan")?)?);
+                    None
+                }
+                "--adapter" => {
+                    adapter = Some(parse_adapter(value(&mut args, "--adapter", "0")?));
+                    None
+                }
+                "--present-mode" => {
+                    let mode = value(&mut args, "--present-mode", "mailbox")?;
+                    present_mode = Some(parse_present_mode(&mode)?);
+                    None
+                }
+                "--msaa" => {
+                    sample_count = Some(parse_sample_count(&value(&mut args, "--msaa", "4")?)?);
+                    None
+                }
+                "--frames" => {
+                    frames = Some(parse_frames(
+                        "--frames",
+                        &value(&mut args, "--frames", "100")?,
+                    )?);
+                    None
+                }
+                "--json" => {
+                    json = true;
+                    None
+                }
+                // The frames may come later, they're filled in below
+                "--headless" => Some((
+                    "--headless",
+                    Command::Headless {
+                        frames: 0,
+                        json: false,
+                    },
+                )),
+                "--bench" => {
+                    let frames = parse_frames("--bench", &value(&mut args, "--bench", "1000")?)?;
+                    Some((
+                        "--bench",
+                        Command::Headless {
+                            frames,
+                            json: false,
+                        },
+                    ))
+                }
+                "--bench-uploads" => {
+                    let value = value(&mut args, "--bench-uploads", "1000")?;
+                    let frames = parse_frames("--bench-uploads", &value)?;
+                    Some(("--bench-uploads", Command::BenchUploads { frames }))
+                }
+                "--shadertoy" => {
+                    let path = value(&mut args, "--shadertoy", "toy.wgsl")?;
+                    Some((
+                        "--shadertoy",
+                        Command::Shadertoy {
+                            path: PathBuf::from(path),
+                        },
+                    ))
+                }
+                "--list-adapters" => Some(("--list-adapters", Command::ListAdapters)),
+                _ => return Err(CliError::UnknownArgument(arg)),
+            };
+            if let Some(picked) = picked {
+                if let Some((other, _)) = command {
+                    return Err(CliError::Conflict {
+                        flag: picked.0,
+                        other,
+                    });
+                }
+                command = Some(picked);
+            }
+        }
+
+        let (flag, mut command) = command.unwrap_or(("", Command::Run));
+        if flag == "--headless" {
+            let Some(frames) = frames else {
+                return Err(CliError::Requires {
+                    flag: "--headless",
+                    required: "--frames",
+                });
+            };
+            command = Command::Headless {
+                frames,
+                json: false,
+            };
+        } else if frames.is_some() {
+            return Err(CliError::Requires {
+                flag: "--frames",
+                required: "--headless",
+            });
+        }
+        match &mut command {
+            Command::Headless {
+                json: report_json, ..
+            } => *report_json = json,
+            _ if json => {
+                return Err(CliError::Requires {
+                    flag: "--json",
+                    required: "--headless",
+                })
+            }
+            _ => {}
+        }
+
+        let size = match (width, height) {
+            (Some(width), Some(height)) => Some(PhysicalSize::new(width, height)),
+            (None, None) => None,
+            (Some(_), None) => {
+                return Err(CliError::Requires {
+                    flag: "--width",
+                    required: "--height",
+                })
+            }
+            (None, Some(_)) => {
+                return Err(CliError::Requires {
+                    flag: "--height",
+                    required: "--width",
+                })
+            }
+        };
+
+        // Options for what the command doesn't do are mistakes
+        let windowed = matches!(command, Command::Run | Command::Shadertoy { .. });
+        let draws_scene = matches!(command, Command::Run | Command::Headless { .. });
+        let sizeless = matches!(
+            command,
+            Command::BenchUploads { .. } | Command::ListAdapters
+        );
+        let unused = [
+            ("--fullscreen", fullscreen && !windowed),
+            ("--present-mode", present_mode.is_some() && !windowed),
+            ("--msaa", sample_count.is_some() && !draws_scene),
+            ("--width", size.is_some() && sizeless),
+            (
+                "--adapter",
+                adapter.is_some() && command == Command::ListAdapters,
+            ),
+        ];
+        if let Some((unused, _)) = unused.into_iter().find(|(_, unused)| *unused) {
+            return Err(CliError::Conflict {
+                flag: unused,
+                other: flag,
+            });
+        }
+        if fullscreen && size.is_some() {
+            return Err(CliError::Conflict {
+                flag: "--fullscreen",
+                other: "--width",
+            });
+        }
+
+        Ok(Self {
+            command,
+            size,
+            fullscreen,
+            backends,
+            adapter,
+            present_mode,
+            sample_count,
+        })
+    }
+
+    /// `command` with all the options left at their defaults.
+    fn new(command: Command) -> Self {
+        Self {
+            command,
+            size: None,
+            fullscreen: false,
+            backends: None,
+            adapter: None,
+            present_mode: None,
+            sample_count: None,
+        }
+    }
+
+    /// The defaults with the options that were given.
+    pub fn run_config(&self) -> RunConfig {
+        let mut config = RunConfig::default();
+        self.apply(&mut config);
+        config
+    }
+
+    /// Overrides what's in `config` with the options that were given, e.g.
+    /// the saved settings.
+    pub fn apply(&self, config: &mut RunConfig) {
+        if let Some(size) = self.size {
+            // A size asks for a window
+            config.window.inner_size = Some(size.into());
+            config.window.fullscreen = false;
+        }
+        config.window.fullscreen |= self.fullscreen;
+        if let Some(backends) = self.backends {
+            config.device.backends = backends;
+        }
+        if let Some(adapter) = &self.adapter {
+            config.adapter = adapter.clone();
+        }
+        if let Some(present_mode) = self.present_mode {
+            config.present_mode = present_mode;
+        }
+        if let Some(sample_count) = self.sample_count {
+            config.sample_count = sample_count;
+        }
+    }
+
+    /// The size of the frames `Command::Headless` renders.
+    pub fn headless_size(&self) -> PhysicalSize<u32> {
+        self.size.unwrap_or(HEADLESS_SIZE)
+    }
+}
+
+fn value(
+    args: &mut impl Iterator<Item = String>,
+    flag: &'static str,
+    example: &'static str,
+) -> Result<String, CliError> {
+    args.next()
+        .filter(|value| !value.starts_with("--"))
+        .ok_or(CliError::MissingValue { flag, example })
+}
+
+fn invalid(flag: &'static str, value: &str, expected: &'static str) -> CliError {
+    CliError::InvalidValue {
+        flag,
+        value: value.to_owned(),
+        expected,
+    }
+}
+
+fn parse_size(flag: &'static str, value: &str) -> Result<u32, CliError> {
+    value
+        .parse()
+        .ok()
+        .filter(|&size| size > 0)
+        .ok_or_else(|| invalid(flag, value, "a number of pixels"))
+}
+
+fn parse_frames(flag: &'static str, value: &str) -> Result<u32, CliError> {
+    value
+        .parse()
+        .ok()
+        .filter(|&frames| frames > 0)
+        .ok_or_else(|| invalid(flag, value, "a number of frames"))
+}
+
+fn parse_backend(value: &str) -> Result<wgpu::Backends, CliError> {
+    match value.to_lowercase().as_str() {
+        "vulkan" => Ok(wgpu::Backends::VULKAN),
+        "dx12" => Ok(wgpu::Backends::DX12),
+        "metal" => Ok(wgpu::Backends::METAL),
+        "gl" => Ok(wgpu::Backends::GL),
+        _ => Err(invalid("--backend", value, "vulkan, dx12, metal or gl")),
+    }
+}
+
+/// Numbers are indices, anything else part of a name.
+fn parse_adapter(value: String) -> AdapterSelection {
+    match value.parse() {
+        Ok(index) => AdapterSelection::ByIndex(index),
+        Err(_) => AdapterSelection::ByNameSubstring(value),
+    }
+}
+
+fn parse_present_mode(value: &str) -> Result<PresentModePreference, CliError> {
+    match value.to_lowercase().as_str() {
+        "vsync" => Ok(PresentModePreference::Vsync),
+        "immediate" => Ok(PresentModePreference::Immediate),
+        "mailbox" => Ok(PresentModePreference::Mailbox),
+        "auto" => Ok(PresentModePreference::Auto),
+        _ => Err(invalid(
+            "--present-mode",
+            value,
+            "vsync, immediate, mailbox or auto",
+        )),
+    }
+}
+
+fn parse_sample_count(value: &str) -> Result<u32, CliError> {
+    match value.parse() {
+        Ok(count @ (1 | 2 | 4 | 8)) => Ok(count),
+        _ => Err(invalid("--msaa", value, "1, 2, 4 or 8")),
+    }
+}
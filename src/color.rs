@@ -0,0 +1,156 @@
+/// Clear colors selectable with the number keys 1 to 5, in linear space.
+pub const PRESETS: [wgpu::Color; 5] = [
+    // The tutorial's original blue
+    wgpu::Color {
+        r: 0.1,
+        g: 0.2,
+        b: 0.3,
+        a: 1.0,
+    },
+    wgpu::Color {
+        r: 0.3,
+        g: 0.2,
+        b: 0.1,
+        a: 1.0,
+    },
+    wgpu::Color::BLACK,
+    wgpu::Color::WHITE,
+    // Cornflower blue, the XNA classic
+    wgpu::Color {
+        r: 0.127,
+        g: 0.301,
+        b: 0.846,
+        a: 1.0,
+    },
+];
+
+/// Encodes a linear color channel with the sRGB transfer function, same as
+/// `linear_to_srgb` in the shaders.
+pub fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decodes an sRGB encoded color channel, the inverse of `linear_to_srgb`.
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Moves `color` towards white by `amount`, 0 keeps it and 1 makes it white.
+/// Alpha stays as it is.
+pub fn lighten(color: wgpu::Color, amount: f64) -> wgpu::Color {
+    let lighten = |c: f64| c + (1.0 - c) * amount;
+    wgpu::Color {
+        r: lighten(color.r),
+        g: lighten(color.g),
+        b: lighten(color.b),
+        a: color.a,
+    }
+}
+
+/// Whether linear colors can be written to a `format` texture as is. sRGB
+/// formats do the encoding when writing and float formats keep the linear
+/// values, only the other formats need the colors encoded beforehand.
+pub fn stores_linear(format: wgpu::TextureFormat) -> bool {
+    format.is_srgb()
+        || matches!(
+            format,
+            wgpu::TextureFormat::Rgba16Float
+                | wgpu::TextureFormat::Rgba32Float
+                | wgpu::TextureFormat::Rg11b10Float
+        )
+}
+
+/// The value to clear a `format` texture with so it shows the linear color
+/// `color`. Where the format doesn't store it linearly we do the encoding,
+/// just like the fragment shaders' `fs_main_encode_srgb`.
+pub fn clear_value_for_format(color: wgpu::Color, format: wgpu::TextureFormat) -> wgpu::Color {
+    if stores_linear(format) {
+        return color;
+    }
+
+    wgpu::Color {
+        r: linear_to_srgb(color.r),
+        g: linear_to_srgb(color.g),
+        b: linear_to_srgb(color.b),
+        // Alpha is always linear
+        a: color.a,
+    }
+}
+
+/// How the linear colors we draw end up sRGB encoded on a surface, see
+/// `select_surface_format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SrgbEncoding {
+    /// The surface format encodes them, or stores them linearly.
+    Format,
+    /// The surface format doesn't, so we draw to it through a view with the
+    /// sRGB variant of its format, which does.
+    View,
+    /// Neither works, the shaders encode the colors themselves before
+    /// writing them.
+    Shader,
+}
+
+impl SrgbEncoding {
+    /// The `view_formats` to configure a `format` surface with.
+    pub fn view_formats(self, format: wgpu::TextureFormat) -> Vec<wgpu::TextureFormat> {
+        match self {
+            Self::View => vec![format.add_srgb_suffix()],
+            Self::Format | Self::Shader => Vec::new(),
+        }
+    }
+}
+
+/// Picks a surface format out of `formats`, the ones the surface supports,
+/// in order of preference. sRGB formats come first. Otherwise the preferred
+/// format is used with an sRGB view when `srgb_views` says the device
+/// supports views of surfaces with a different format
+/// (`DownlevelFlags::SURFACE_VIEW_FORMATS`), and with shaders doing the
+/// encoding when it doesn't.
+pub fn select_surface_format(
+    formats: &[wgpu::TextureFormat],
+    srgb_views: bool,
+) -> Option<(wgpu::TextureFormat, SrgbEncoding)> {
+    if let Some(&format) = formats.iter().find(|format| format.is_srgb()) {
+        return Some((format, SrgbEncoding::Format));
+    }
+
+    let format = *formats.first()?;
+    let encoding = if stores_linear(format) {
+        SrgbEncoding::Format
+    } else if srgb_views && format.add_srgb_suffix() != format {
+        SrgbEncoding::View
+    } else {
+        SrgbEncoding::Shader
+    };
+    Some((format, encoding))
+}
+
+/// The format to create the views of a surface configured with `config`
+/// with, and so the format pipelines drawing to it target.
+pub fn render_format(config: &wgpu::SurfaceConfiguration) -> wgpu::TextureFormat {
+    config
+        .view_formats
+        .first()
+        .copied()
+        .unwrap_or(config.format)
+}
+
+/// How colors get encoded on a surface configured with `config`.
+pub fn srgb_encoding(config: &wgpu::SurfaceConfiguration) -> SrgbEncoding {
+    if !config.view_formats.is_empty() {
+        SrgbEncoding::View
+    } else if stores_linear(config.format) {
+        SrgbEncoding::Format
+    } else {
+        SrgbEncoding::Shader
+    }
+}
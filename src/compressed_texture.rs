@@ -0,0 +1,291 @@
+//! Reading block compressed textures out of KTX2 and DDS files. The levels
+//! are kept as they're stored, `Texture::from_compressed` uploads them.
+
+use thiserror::Error;
+
+/// A 2D texture with its mipmaps, as stored in a KTX2 or DDS file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedImage {
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    /// The data of each mip level, largest first. A level is
+    /// ceil(width / block width) blocks wide, even when the level is
+    /// smaller than a block.
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CompressedImage {
+    /// The size of mip `level` in texels, not rounded up to whole blocks.
+    pub fn level_size(&self, level: u32) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        }
+        .mip_level_size(level, wgpu::TextureDimension::D2)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CompressedTextureError {
+    #[error("not a {0} file")]
+    BadMagic(&'static str),
+    #[error("the file ends before its {0}")]
+    Truncated(&'static str),
+    #[error("unsupported texture: {0}")]
+    Unsupported(String),
+    #[error("{format:?} can't be sampled by this adapter, or decoded in software")]
+    CantDecode { format: wgpu::TextureFormat },
+}
+
+const KTX2_MAGIC: [u8; 12] = [
+    0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a,
+];
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// The wgpu format of a Vulkan `VkFormat`, for the formats KTX2 files
+/// commonly hold.
+fn vk_format(vk_format: u32) -> Option<wgpu::TextureFormat> {
+    use wgpu::{AstcBlock, AstcChannel, TextureFormat as F};
+
+    let format = match vk_format {
+        37 => F::Rgba8Unorm,
+        43 => F::Rgba8UnormSrgb,
+        // BC1 RGB and RGBA, the RGB variant just ignores alpha
+        131 | 133 => F::Bc1RgbaUnorm,
+        132 | 134 => F::Bc1RgbaUnormSrgb,
+        135 => F::Bc2RgbaUnorm,
+        136 => F::Bc2RgbaUnormSrgb,
+        137 => F::Bc3RgbaUnorm,
+        138 => F::Bc3RgbaUnormSrgb,
+        145 => F::Bc7RgbaUnorm,
+        146 => F::Bc7RgbaUnormSrgb,
+        147 => F::Etc2Rgb8Unorm,
+        148 => F::Etc2Rgb8UnormSrgb,
+        149 => F::Etc2Rgb8A1Unorm,
+        150 => F::Etc2Rgb8A1UnormSrgb,
+        151 => F::Etc2Rgba8Unorm,
+        152 => F::Etc2Rgba8UnormSrgb,
+        // The ASTC formats come in UNORM / SRGB pairs, in this block order
+        157..=184 => {
+            let blocks = [
+                AstcBlock::B4x4,
+                AstcBlock::B5x4,
+                AstcBlock::B5x5,
+                AstcBlock::B6x5,
+                AstcBlock::B6x6,
+                AstcBlock::B8x5,
+                AstcBlock::B8x6,
+                AstcBlock::B8x8,
+                AstcBlock::B10x5,
+                AstcBlock::B10x6,
+                AstcBlock::B10x8,
+                AstcBlock::B10x10,
+                AstcBlock::B12x10,
+                AstcBlock::B12x12,
+            ];
+            let index = (vk_format - 157) as usize;
+            F::Astc {
+                block: blocks[index / 2],
+                channel: if index.is_multiple_of(2) {
+                    AstcChannel::Unorm
+                } else {
+                    AstcChannel::UnormSrgb
+                },
+            }
+        }
+        _ => return None,
+    };
+    Some(format)
+}
+
+/// Reads a KTX2 file holding a single 2D texture. Supercompressed files,
+/// including Basis Universal ones, aren't supported.
+pub fn parse_ktx2(bytes: &[u8]) -> Result<CompressedImage, CompressedTextureError> {
+    if !bytes.starts_with(&KTX2_MAGIC) {
+        return Err(CompressedTextureError::BadMagic("KTX2"));
+    }
+    // The header is followed by the offsets of the data format descriptor,
+    // the key/value data and the supercompression data.
+    const LEVEL_INDEX: usize = 80;
+    if bytes.len() < LEVEL_INDEX {
+        return Err(CompressedTextureError::Truncated("header"));
+    }
+
+    let format = read_u32(bytes, 12);
+    let width = read_u32(bytes, 20);
+    let height = read_u32(bytes, 24);
+    let depth = read_u32(bytes, 28);
+    let layers = read_u32(bytes, 32);
+    let faces = read_u32(bytes, 36);
+    // 0 asks the loader to generate the mipmaps, only the first level is
+    // stored then
+    let level_count = read_u32(bytes, 40).max(1);
+    let supercompression = read_u32(bytes, 44);
+
+    if supercompression != 0 {
+        return Err(CompressedTextureError::Unsupported(format!(
+            "supercompression scheme {supercompression}"
+        )));
+    }
+    if height == 0 || depth != 0 || layers > 1 || faces != 1 {
+        return Err(CompressedTextureError::Unsupported(
+            "only single 2D textures are supported".to_owned(),
+        ));
+    }
+    let format = vk_format(format)
+        .ok_or_else(|| CompressedTextureError::Unsupported(format!("VkFormat {format}")))?;
+
+    let index_end = LEVEL_INDEX + level_count as usize * 24;
+    if bytes.len() < index_end {
+        return Err(CompressedTextureError::Truncated("level index"));
+    }
+    let levels = (0..level_count as usize)
+        .map(|level| {
+            let entry = LEVEL_INDEX + level * 24;
+            let offset = read_u64(bytes, entry) as usize;
+            let length = read_u64(bytes, entry + 8) as usize;
+            bytes
+                .get(offset..offset.saturating_add(length))
+                .map(<[u8]>::to_vec)
+                .ok_or(CompressedTextureError::Truncated("mip levels"))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(CompressedImage {
+        format,
+        width,
+        height,
+        levels,
+    })
+}
+
+/// The wgpu format of a `DXGI_FORMAT`, from DDS files with a DX10 header.
+fn dxgi_format(dxgi_format: u32) -> Option<wgpu::TextureFormat> {
+    use wgpu::TextureFormat as F;
+
+    let format = match dxgi_format {
+        28 => F::Rgba8Unorm,
+        29 => F::Rgba8UnormSrgb,
+        71 => F::Bc1RgbaUnorm,
+        72 => F::Bc1RgbaUnormSrgb,
+        74 => F::Bc2RgbaUnorm,
+        75 => F::Bc2RgbaUnormSrgb,
+        77 => F::Bc3RgbaUnorm,
+        78 => F::Bc3RgbaUnormSrgb,
+        98 => F::Bc7RgbaUnorm,
+        99 => F::Bc7RgbaUnormSrgb,
+        _ => return None,
+    };
+    Some(format)
+}
+
+/// How many bytes a `width`x`height` level of `format` takes.
+fn level_byte_size(format: wgpu::TextureFormat, width: u32, height: u32) -> usize {
+    let (block_width, block_height) = format.block_dimensions();
+    let block_bytes = format.block_copy_size(None).unwrap_or(0);
+    (width.div_ceil(block_width) * height.div_ceil(block_height) * block_bytes) as usize
+}
+
+/// Reads a DDS file holding a single 2D texture. The files written before
+/// DX10 headers existed don't say whether they're sRGB, their BC1-3 data is
+/// assumed to be, like images loaded with `Texture::from_image`.
+pub fn parse_dds(bytes: &[u8]) -> Result<CompressedImage, CompressedTextureError> {
+    use wgpu::TextureFormat as F;
+
+    if !bytes.starts_with(b"DDS ") {
+        return Err(CompressedTextureError::BadMagic("DDS"));
+    }
+    const HEADER_END: usize = 128;
+    if bytes.len() < HEADER_END {
+        return Err(CompressedTextureError::Truncated("header"));
+    }
+
+    const MIPMAP_COUNT_FLAG: u32 = 0x20000;
+    const FOURCC_FLAG: u32 = 0x4;
+    const CUBEMAP_FLAG: u32 = 0x200;
+    const VOLUME_FLAG: u32 = 0x200000;
+    let flags = read_u32(bytes, 8);
+    let height = read_u32(bytes, 12);
+    let width = read_u32(bytes, 16);
+    let level_count = if flags & MIPMAP_COUNT_FLAG != 0 {
+        read_u32(bytes, 28).max(1)
+    } else {
+        1
+    };
+    let pixel_format_flags = read_u32(bytes, 80);
+    let four_cc = &bytes[84..88];
+    let caps2 = read_u32(bytes, 112);
+
+    if width == 0 || height == 0 || caps2 & (CUBEMAP_FLAG | VOLUME_FLAG) != 0 {
+        return Err(CompressedTextureError::Unsupported(
+            "only single 2D textures are supported".to_owned(),
+        ));
+    }
+    // Checked before the levels get sized or allocated
+    if level_count > width.max(height).ilog2() + 1 {
+        return Err(CompressedTextureError::Unsupported(format!(
+            "{level_count} mip levels for a {width}x{height} texture"
+        )));
+    }
+    if pixel_format_flags & FOURCC_FLAG == 0 {
+        return Err(CompressedTextureError::Unsupported(
+            "uncompressed DDS pixel formats".to_owned(),
+        ));
+    }
+
+    let (format, mut offset) = match four_cc {
+        b"DXT1" => (F::Bc1RgbaUnormSrgb, HEADER_END),
+        b"DXT2" | b"DXT3" => (F::Bc2RgbaUnormSrgb, HEADER_END),
+        b"DXT4" | b"DXT5" => (F::Bc3RgbaUnormSrgb, HEADER_END),
+        b"DX10" => {
+            const DX10_HEADER_END: usize = HEADER_END + 20;
+            if bytes.len() < DX10_HEADER_END {
+                return Err(CompressedTextureError::Truncated("DX10 header"));
+            }
+            let dxgi = read_u32(bytes, HEADER_END);
+            let array_size = read_u32(bytes, HEADER_END + 12);
+            if array_size > 1 {
+                return Err(CompressedTextureError::Unsupported(
+                    "texture arrays".to_owned(),
+                ));
+            }
+            let format = dxgi_format(dxgi).ok_or_else(|| {
+                CompressedTextureError::Unsupported(format!("DXGI_FORMAT {dxgi}"))
+            })?;
+            (format, DX10_HEADER_END)
+        }
+        _ => {
+            return Err(CompressedTextureError::Unsupported(format!(
+                "FourCC {}",
+                String::from_utf8_lossy(four_cc)
+            )))
+        }
+    };
+
+    // Unlike KTX2, DDS stores the levels one after the other without sizes
+    let mut image = CompressedImage {
+        format,
+        width,
+        height,
+        levels: Vec::with_capacity(level_count as usize),
+    };
+    for level in 0..level_count {
+        let size = image.level_size(level);
+        let length = level_byte_size(format, size.width, size.height);
+        let data = bytes
+            .get(offset..offset + length)
+            .ok_or(CompressedTextureError::Truncated("mip levels"))?;
+        image.levels.push(data.to_vec());
+        offset += length;
+    }
+    Ok(image)
+}
@@ -0,0 +1,743 @@
+use std::{sync::mpsc, time::Duration};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::CameraBinding,
+    pipeline::{self, PipelineBuilder, RenderTargets},
+    shader::{self, ShaderError},
+};
+
+pub const PARTICLE_SHADER_SOURCE: &str = include_str!("../shaders/particles.wgsl");
+
+pub const DEFAULT_PARTICLE_COUNT: u32 = 10_000;
+
+/// How many particles one workgroup simulates, has to match
+/// `@workgroup_size` in the shader.
+pub const WORKGROUP_SIZE: u32 = 64;
+
+/// The most particles the emitters can spawn in one frame, the rest are
+/// dropped.
+pub const MAX_SPAWNS_PER_FRAME: u32 = 4096;
+
+/// A particle as the compute shader sees it. vec3s are 16-byte aligned in
+/// storage buffers too, the age and lifetime fill the gaps.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Particle {
+    pub position: [f32; 3],
+    /// Seconds since the particle was spawned.
+    pub age: f32,
+    pub velocity: [f32; 3],
+    /// The particle dies once its age reaches this, in seconds.
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// A particle that was just spawned.
+    pub fn new(position: glam::Vec3, velocity: glam::Vec3, lifetime: f32) -> Self {
+        Self {
+            position: position.into(),
+            age: 0.0,
+            velocity: velocity.into(),
+            lifetime,
+        }
+    }
+
+    const ATTRIBS: [wgpu::VertexAttribute; 2] = [
+        wgpu::VertexAttribute {
+            offset: 0,
+            shader_location: 0,
+            format: wgpu::VertexFormat::Float32x4,
+        },
+        wgpu::VertexAttribute {
+            offset: std::mem::offset_of!(Particle, velocity) as wgpu::BufferAddress,
+            shader_location: 1,
+            format: wgpu::VertexFormat::Float32x4,
+        },
+    ];
+
+    /// The particles are drawn straight from the storage buffer, one quad
+    /// per instance. The age and lifetime come along in the w components.
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Particle>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// How the particles are blended over the scene.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ParticleBlend {
+    /// Adds up the particles, which doesn't depend on the order they're
+    /// drawn in, so they don't need sorting.
+    #[default]
+    Additive,
+    /// Blends each particle over what's behind it. They get sorted back to
+    /// front on the GPU every frame for that.
+    Alpha,
+}
+
+/// Spawns particles from a point into a cone. All of it can be changed
+/// between frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emitter {
+    pub position: glam::Vec3,
+    /// The axis of the cone, doesn't have to be normalized.
+    pub direction: glam::Vec3,
+    /// The angle between the axis and the side of the cone, in radians.
+    pub spread: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Particles per second.
+    pub rate: f32,
+    /// How long the particles live, in seconds.
+    pub lifetime: f32,
+    /// The fraction of a particle left over from the last frames.
+    pending: f32,
+    seed: u32,
+}
+
+impl Emitter {
+    /// A fountain shooting up from `position`.
+    pub fn new(position: glam::Vec3) -> Self {
+        Self {
+            position,
+            direction: glam::Vec3::Y,
+            spread: 0.2,
+            min_speed: 6.0,
+            max_speed: 9.0,
+            rate: 3000.0,
+            lifetime: 3.0,
+            pending: 0.0,
+            // A fixed seed keeps every run the same
+            seed: 0x2545_f491,
+        }
+    }
+
+    /// Appends the particles spawned over `dt` to `spawns`, at most
+    /// `MAX_SPAWNS_PER_FRAME` of them.
+    pub fn emit(&mut self, dt: Duration, spawns: &mut Vec<Particle>) {
+        self.pending += self.rate.max(0.0) * dt.as_secs_f32();
+        let count = self.pending.floor();
+        self.pending -= count;
+        let count = (count as u32).min(MAX_SPAWNS_PER_FRAME);
+
+        let direction = self.direction.try_normalize().unwrap_or(glam::Vec3::Y);
+        let rotation = glam::Quat::from_rotation_arc(glam::Vec3::Y, direction);
+        let cos_spread = self.spread.clamp(0.0, std::f32::consts::PI).cos();
+        for _ in 0..count {
+            // Evenly spread over the part of the sphere inside the cone
+            let cos_theta = 1.0 - self.random() * (1.0 - cos_spread);
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = self.random() * std::f32::consts::TAU;
+            let local = glam::Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin());
+            let speed = self.min_speed + self.random() * (self.max_speed - self.min_speed);
+            spawns.push(Particle::new(
+                self.position,
+                rotation * local * speed,
+                self.lifetime,
+            ));
+        }
+    }
+
+    /// A xorshift between 0 and 1.
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f32 / u32::MAX as f32
+    }
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self::new(glam::Vec3::ZERO)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimParams {
+    gravity: [f32; 3],
+    dt: f32,
+    eye: [f32; 3],
+    capacity: u32,
+    /// Which of the two particle buffers is read this frame.
+    src: u32,
+    spawn_count: u32,
+    _padding: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SortStep {
+    k: u32,
+    j: u32,
+    _padding: [u32; 2],
+}
+
+/// The `(k, j)` of each step of the bitonic sort of `count` particles: `k`
+/// is the size of the blocks being merged, `j` the distance between the
+/// particles compared. Padded up to a power of two, the particles past the
+/// end are simply never swapped.
+pub fn sort_steps(count: u32) -> Vec<(u32, u32)> {
+    let size = count.max(1).next_power_of_two();
+    let mut steps = Vec::new();
+    let mut k = 2;
+    while k <= size {
+        let mut j = k / 2;
+        while j > 0 {
+            steps.push((k, j));
+            j /= 2;
+        }
+        k *= 2;
+    }
+    steps
+}
+
+/// How many workgroups simulate `count` particles. The last one is only
+/// partly used when `count` isn't a multiple of `workgroup_size`, the shader
+/// skips the invocations past the end.
+pub fn workgroup_count(count: u32, workgroup_size: u32) -> u32 {
+    count.div_ceil(workgroup_size)
+}
+
+/// Whether the device can run compute shaders and draw with counts they
+/// wrote. WebGL2 can't, and its limits don't allow any storage buffers.
+pub fn is_supported(adapter: &wgpu::Adapter, device: &wgpu::Device) -> bool {
+    let limits = device.limits();
+    let flags = adapter.get_downlevel_capabilities().flags;
+    flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS | wgpu::DownlevelFlags::INDIRECT_EXECUTION)
+        && limits.max_storage_buffers_per_shader_stage >= STORAGE_BUFFER_COUNT
+        && limits.max_compute_workgroups_per_dimension > 0
+}
+
+/// The storage buffers the compute shader binds at once.
+const STORAGE_BUFFER_COUNT: u32 = 4;
+
+/// The most particles the device can simulate, bounded by how many
+/// workgroups can be dispatched and how big the storage buffer can be.
+pub fn max_particle_count(limits: &wgpu::Limits) -> u32 {
+    let by_dispatch = limits
+        .max_compute_workgroups_per_dimension
+        .saturating_mul(WORKGROUP_SIZE);
+    let by_size = limits.max_storage_buffer_binding_size / std::mem::size_of::<Particle>() as u32;
+    by_dispatch.min(by_size)
+}
+
+/// The pipelines of a `ParticleSystem`, built separately so they can be
+/// swapped out when the shader changes.
+pub struct ParticlePipelines {
+    simulate: wgpu::ComputePipeline,
+    emit: wgpu::ComputePipeline,
+    sort: wgpu::ComputePipeline,
+    additive: wgpu::RenderPipeline,
+    alpha: wgpu::RenderPipeline,
+}
+
+/// What has to be recreated when the particle count changes.
+struct ParticleBuffers {
+    /// Every frame the compute shader reads one of them and writes the
+    /// particles still alive and the new ones into the other.
+    particles: [wgpu::Buffer; 2],
+    /// The draw arguments of each of the particle buffers. The compute
+    /// shader counts the particles it writes in their instance counts.
+    draws: wgpu::Buffer,
+    sort_step_count: u32,
+    sort_step_stride: u32,
+    /// `bind_groups[i]` reads `particles[i]` and writes the other one.
+    bind_groups: [wgpu::BindGroup; 2],
+}
+
+const DRAW_ARGS_SIZE: wgpu::BufferAddress = std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress;
+
+/// The draw arguments of an empty particle buffer, 6 vertices make up the
+/// quad of each particle.
+const NO_PARTICLES: wgpu::util::DrawIndirectArgs = wgpu::util::DrawIndirectArgs {
+    vertex_count: 6,
+    instance_count: 0,
+    first_vertex: 0,
+    first_instance: 0,
+};
+
+impl ParticleBuffers {
+    fn new(
+        device: &wgpu::Device,
+        count: u32,
+        layout: &wgpu::BindGroupLayout,
+        params_buffer: &wgpu::Buffer,
+        spawn_buffer: &wgpu::Buffer,
+    ) -> Self {
+        let create_particle_buffer = |label| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: u64::from(count) * std::mem::size_of::<Particle>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+                mapped_at_creation: false,
+            })
+        };
+        let particles = [
+            create_particle_buffer("Particle Buffer A"),
+            create_particle_buffer("Particle Buffer B"),
+        ];
+
+        let mut draw_args = NO_PARTICLES.as_bytes().to_vec();
+        draw_args.extend_from_slice(NO_PARTICLES.as_bytes());
+        let draws = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Draw Buffer"),
+            contents: &draw_args,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+        });
+
+        // Each step gets its own slice of the buffer, picked with a dynamic
+        // offset, so all of them fit in one compute pass
+        let sort_step_stride = device
+            .limits()
+            .min_uniform_buffer_offset_alignment
+            .max(std::mem::size_of::<SortStep>() as u32);
+        let steps = sort_steps(count);
+        // Never empty, the bind group needs at least one step to point at
+        let mut contents = vec![0; steps.len().max(1) * sort_step_stride as usize];
+        for (chunk, &(k, j)) in contents
+            .chunks_exact_mut(sort_step_stride as usize)
+            .zip(&steps)
+        {
+            let step = SortStep {
+                k,
+                j,
+                _padding: [0; 2],
+            };
+            chunk[..std::mem::size_of::<SortStep>()].copy_from_slice(bytemuck::bytes_of(&step));
+        }
+        let sort_steps = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Sort Steps Buffer"),
+            contents: &contents,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let create_bind_group = |src: usize| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("particle_compute_bind_group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: particles[src].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: particles[1 - src].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: draws.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: spawn_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &sort_steps,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(std::mem::size_of::<SortStep>() as u64),
+                        }),
+                    },
+                ],
+            })
+        };
+        let bind_groups = [create_bind_group(0), create_bind_group(1)];
+
+        Self {
+            particles,
+            draws,
+            sort_step_count: steps.len() as u32,
+            sort_step_stride,
+            bind_groups,
+        }
+    }
+}
+
+/// Particles simulated on the GPU by compute shaders, then drawn as small
+/// quads facing the camera.
+///
+/// The emitters spawn them on the CPU, the GPU moves them and throws away
+/// the expired ones.
+pub struct ParticleSystem {
+    count: u32,
+    blend: ParticleBlend,
+    gravity: glam::Vec3,
+    emitters: Vec<Emitter>,
+    /// Reused every frame for what the emitters spawn.
+    spawns: Vec<Particle>,
+    params_buffer: wgpu::Buffer,
+    spawn_buffer: wgpu::Buffer,
+    buffers: ParticleBuffers,
+    /// The particle buffer written by the last update.
+    current: usize,
+    compute_bind_group_layout: wgpu::BindGroupLayout,
+    pipelines: ParticlePipelines,
+}
+
+impl ParticleSystem {
+    /// `count` is how many particles can be alive at once, clamped between
+    /// 1 and `max_particle_count`. `camera_bind_group_layout` is the layout
+    /// shared with the other pipelines. Starts with a single
+    /// `Emitter::default()`.
+    pub fn new(
+        device: &wgpu::Device,
+        count: u32,
+        targets: RenderTargets,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        shader_source: &str,
+    ) -> Result<Self, ShaderError> {
+        let count = count.clamp(1, max_particle_count(&device.limits()));
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Params Buffer"),
+            size: std::mem::size_of::<SimParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let spawn_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Spawn Buffer"),
+            size: u64::from(MAX_SPAWNS_PER_FRAME)
+                * std::mem::size_of::<Particle>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("particle_compute_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    storage_entry(1, true),
+                    storage_entry(2, false),
+                    storage_entry(3, false),
+                    storage_entry(4, true),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: wgpu::BufferSize::new(
+                                std::mem::size_of::<SortStep>() as u64,
+                            ),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let buffers = ParticleBuffers::new(
+            device,
+            count,
+            &compute_bind_group_layout,
+            &params_buffer,
+            &spawn_buffer,
+        );
+
+        let pipelines = create_pipelines(
+            device,
+            targets,
+            camera_bind_group_layout,
+            &compute_bind_group_layout,
+            shader_source,
+        )?;
+
+        Ok(Self {
+            count,
+            blend: ParticleBlend::default(),
+            gravity: glam::Vec3::new(0.0, -9.81, 0.0),
+            emitters: vec![Emitter::default()],
+            spawns: Vec::new(),
+            params_buffer,
+            spawn_buffer,
+            buffers,
+            current: 0,
+            compute_bind_group_layout,
+            pipelines,
+        })
+    }
+
+    /// How many particles can be alive at once.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Starts over without any particles and room for `count` of them,
+    /// clamped like in `new`.
+    pub fn set_count(&mut self, device: &wgpu::Device, count: u32) {
+        self.count = count.clamp(1, max_particle_count(&device.limits()));
+        self.buffers = ParticleBuffers::new(
+            device,
+            self.count,
+            &self.compute_bind_group_layout,
+            &self.params_buffer,
+            &self.spawn_buffer,
+        );
+        self.current = 0;
+    }
+
+    pub fn blend(&self) -> ParticleBlend {
+        self.blend
+    }
+
+    pub fn set_blend(&mut self, blend: ParticleBlend) {
+        self.blend = blend;
+    }
+
+    pub fn gravity(&self) -> glam::Vec3 {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: glam::Vec3) {
+        self.gravity = gravity;
+    }
+
+    pub fn emitters(&self) -> &[Emitter] {
+        &self.emitters
+    }
+
+    /// The emitters can be changed, added or removed between frames.
+    pub fn emitters_mut(&mut self) -> &mut Vec<Emitter> {
+        &mut self.emitters
+    }
+
+    /// Builds pipelines for these particles without using them yet, see
+    /// `set_pipelines`.
+    pub fn create_pipelines(
+        &self,
+        device: &wgpu::Device,
+        targets: RenderTargets,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        shader_source: &str,
+    ) -> Result<ParticlePipelines, ShaderError> {
+        create_pipelines(
+            device,
+            targets,
+            camera_bind_group_layout,
+            &self.compute_bind_group_layout,
+            shader_source,
+        )
+    }
+
+    pub fn set_pipelines(&mut self, pipelines: ParticlePipelines) {
+        self.pipelines = pipelines;
+    }
+
+    /// Records advancing the simulation by `dt`: ages and moves the
+    /// particles, drops the expired ones and adds what the emitters spawn.
+    /// With alpha blending the particles then get sorted by their distance
+    /// to `eye`.
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        dt: Duration,
+        eye: glam::Vec3,
+        timestamp_writes: Option<wgpu::ComputePassTimestampWrites>,
+    ) {
+        self.spawns.clear();
+        for emitter in &mut self.emitters {
+            emitter.emit(dt, &mut self.spawns);
+        }
+        self.spawns.truncate(MAX_SPAWNS_PER_FRAME as usize);
+        if !self.spawns.is_empty() {
+            queue.write_buffer(&self.spawn_buffer, 0, bytemuck::cast_slice(&self.spawns));
+        }
+
+        let src = self.current;
+        let dst = 1 - src;
+        let params = SimParams {
+            gravity: self.gravity.into(),
+            dt: dt.as_secs_f32(),
+            eye: eye.into(),
+            capacity: self.count,
+            src: src as u32,
+            spawn_count: self.spawns.len() as u32,
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+        // The shader counts the particles it writes from 0
+        queue.write_buffer(
+            &self.buffers.draws,
+            dst as wgpu::BufferAddress * DRAW_ARGS_SIZE,
+            NO_PARTICLES.as_bytes(),
+        );
+
+        let bind_group = &self.buffers.bind_groups[src];
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Particle Compute Pass"),
+            timestamp_writes,
+        });
+        compute_pass.set_bind_group(0, bind_group, &[0]);
+        compute_pass.set_pipeline(&self.pipelines.simulate);
+        compute_pass.dispatch_workgroups(workgroup_count(self.count, WORKGROUP_SIZE), 1, 1);
+        if !self.spawns.is_empty() {
+            compute_pass.set_pipeline(&self.pipelines.emit);
+            compute_pass.dispatch_workgroups(
+                workgroup_count(self.spawns.len() as u32, WORKGROUP_SIZE),
+                1,
+                1,
+            );
+        }
+        if self.blend == ParticleBlend::Alpha {
+            compute_pass.set_pipeline(&self.pipelines.sort);
+            // Every step compares half of the padded particles with the other half
+            let pairs = self.count.next_power_of_two() / 2;
+            for step in 0..self.buffers.sort_step_count {
+                compute_pass.set_bind_group(0, bind_group, &[step * self.buffers.sort_step_stride]);
+                compute_pass.dispatch_workgroups(workgroup_count(pairs, WORKGROUP_SIZE), 1, 1);
+            }
+        }
+        drop(compute_pass);
+
+        self.current = dst;
+    }
+
+    /// Copies the number of particles alive after the last update back from
+    /// the GPU and waits for it. `None` if it couldn't be read, which is
+    /// always the case on the web where waiting isn't possible.
+    pub fn read_alive_count(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Option<u32> {
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Count Readback Buffer"),
+            size: DRAW_ARGS_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Particle Count Readback Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(
+            &self.buffers.draws,
+            self.current as wgpu::BufferAddress * DRAW_ARGS_SIZE,
+            &readback,
+            0,
+            DRAW_ARGS_SIZE,
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.try_recv().ok()?.ok()?;
+        let data = readback.slice(..).get_mapped_range();
+        let args: &[u32] = bytemuck::cast_slice(&data);
+        Some(args[1])
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera: CameraBinding<'a>) {
+        let pipeline = match self.blend {
+            ParticleBlend::Additive => &self.pipelines.additive,
+            ParticleBlend::Alpha => &self.pipelines.alpha,
+        };
+        render_pass.set_pipeline(pipeline);
+        camera.bind(render_pass, 0);
+        render_pass.set_vertex_buffer(0, self.buffers.particles[self.current].slice(..));
+        // Only as many instances as the compute shader counted
+        render_pass.draw_indirect(
+            &self.buffers.draws,
+            self.current as wgpu::BufferAddress * DRAW_ARGS_SIZE,
+        );
+    }
+}
+
+/// Adds the particles up, each weighted by its alpha. The alpha of the
+/// target stays as it is.
+const ADDITIVE_BLENDING: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::SrcAlpha,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+};
+
+fn create_pipelines(
+    device: &wgpu::Device,
+    targets: RenderTargets,
+    camera_bind_group_layout: &wgpu::BindGroupLayout,
+    compute_bind_group_layout: &wgpu::BindGroupLayout,
+    shader_source: &str,
+) -> Result<ParticlePipelines, ShaderError> {
+    let shader =
+        shader::create_shader_module(device, "Particle Shader", "particles.wgsl", shader_source)?;
+
+    let compute_layout = pipeline::create_pipeline_layout(
+        device,
+        "Particle Compute Pipeline Layout",
+        &[compute_bind_group_layout],
+    );
+    let create_compute_pipeline = |label, entry_point| {
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(label),
+            layout: Some(&compute_layout),
+            module: &shader,
+            entry_point,
+        })
+    };
+
+    // Blended particles still hide behind the scene, but not behind each
+    // other
+    let render_layouts = [camera_bind_group_layout];
+    let render = PipelineBuilder::with_targets("Particle Render Pipeline", targets)
+        .shader_module(&shader)
+        .bind_group_layouts(&render_layouts)
+        .vertex_buffer(Particle::desc())
+        .depth_test(wgpu::CompareFunction::Less, false);
+
+    Ok(ParticlePipelines {
+        simulate: create_compute_pipeline("Particle Simulate Pipeline", "cs_simulate"),
+        emit: create_compute_pipeline("Particle Emit Pipeline", "cs_emit"),
+        sort: create_compute_pipeline("Particle Sort Pipeline", "cs_sort"),
+        additive: render
+            .clone()
+            .label("Additive Particle Render Pipeline")
+            .color_target(targets.color_format, Some(ADDITIVE_BLENDING))
+            .build(device)?,
+        alpha: render
+            .label("Alpha Particle Render Pipeline")
+            .color_target(targets.color_format, Some(wgpu::BlendState::ALPHA_BLENDING))
+            .build(device)?,
+    })
+}
@@ -0,0 +1,147 @@
+use std::ops::Range;
+
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::instance::Instance;
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// The smallest box around `points`, `None` if there aren't any.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |aabb, point| Self {
+                min: aabb.min.min(point),
+                max: aabb.max.max(point),
+            },
+        ))
+    }
+
+    /// The smallest box around both.
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn center(self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Half the size of the box.
+    pub fn extents(self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// A box around this one transformed by `matrix`. With a rotation it's
+    /// bigger than the box really needs to be, never smaller.
+    pub fn transformed(self, matrix: Mat4) -> Self {
+        let center = matrix.transform_point3(self.center());
+        // Each axis of the result gets the extents projected onto it
+        let rotation = glam::Mat3::from_mat4(matrix);
+        let extents = glam::Mat3::from_cols(
+            rotation.x_axis.abs(),
+            rotation.y_axis.abs(),
+            rotation.z_axis.abs(),
+        ) * self.extents();
+        Self {
+            min: center - extents,
+            max: center + extents,
+        }
+    }
+}
+
+/// The six planes around what a camera sees, pointing inwards.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum {
+    /// `xyz` is the normal and `w` the distance, so a point `p` is inside
+    /// a plane when `plane.dot(p.extend(1.0)) >= 0`.
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Reads the planes out of a view-projection matrix that maps depth to
+    /// 0 to 1, like the one of `Camera`.
+    pub fn from_view_proj(view_proj: Mat4) -> Self {
+        let (x, y, z, w) = (
+            view_proj.row(0),
+            view_proj.row(1),
+            view_proj.row(2),
+            view_proj.row(3),
+        );
+        let planes = [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+            // Normalized, so `w` is a real distance
+            plane / plane.truncate().length()
+        });
+        Self { planes }
+    }
+
+    /// Whether any part of `aabb` may be inside. Boxes just outside a corner
+    /// of the frustum count as inside too, which only costs drawing them.
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner of the box furthest along the normal
+            let normal = plane.truncate();
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            normal.dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+/// How many meshes got drawn and left out by the last culling, counting
+/// every instance of every mesh separately.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+/// The instances that are visible, mesh by mesh.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Culled {
+    /// The visible instances of all the meshes one after the other, as
+    /// indices into the instances that were culled.
+    pub instances: Vec<u32>,
+    /// The range of `instances` of each mesh.
+    pub ranges: Vec<Range<u32>>,
+    pub stats: CullStats,
+}
+
+/// Finds out which `instances` of each mesh with `mesh_bounds` are inside
+/// any of `frustums`, one for each view. `None` bounds are never culled.
+pub fn cull(frustums: &[Frustum], mesh_bounds: &[Option<Aabb>], instances: &[Instance]) -> Culled {
+    let matrices: Vec<Mat4> = instances
+        .iter()
+        .map(|instance| Mat4::from_rotation_translation(instance.rotation, instance.position))
+        .collect();
+
+    let mut culled = Culled::default();
+    for bounds in mesh_bounds {
+        let start = culled.instances.len() as u32;
+        for (index, &matrix) in matrices.iter().enumerate() {
+            let visible = bounds.is_none_or(|bounds| {
+                let bounds = bounds.transformed(matrix);
+                frustums.iter().any(|frustum| frustum.intersects(&bounds))
+            });
+            if visible {
+                culled.instances.push(index as u32);
+            }
+        }
+        culled.ranges.push(start..culled.instances.len() as u32);
+    }
+    culled.stats.drawn = culled.instances.len();
+    culled.stats.culled = mesh_bounds.len() * instances.len() - culled.stats.drawn;
+    culled
+}
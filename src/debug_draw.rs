@@ -0,0 +1,235 @@
+//! Lines drawn from anywhere in `State::update` without touching the
+//! render code, to see bounds, directions and positions. Everything drawn
+//! during an update shows in the frames rendered after it and is cleared
+//! by the next one, so shapes have to be drawn again every update to stay.
+//!
+//! The lines go into one vertex buffer, which only grows when an update
+//! draws more than it has room for, and are drawn at the end of the scene's
+//! render pass without writing depth.
+
+use glam::{Mat4, Vec3};
+
+use crate::{
+    buffer::GrowableBuffer,
+    camera::CameraBinding,
+    pipeline::{BlendMode, PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+};
+
+pub const DEBUG_DRAW_SHADER_SOURCE: &str = include_str!("../shaders/debug_draw.wgsl");
+
+/// The segments `DebugDraw::sphere` approximates each of its circles with.
+pub const SPHERE_SEGMENTS: usize = 24;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    /// Linear, in the HDR range like the scene.
+    pub color: [f32; 4],
+}
+
+impl DebugVertex {
+    pub const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<DebugVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// The lines with and without the depth test, see
+/// `DebugDraw::create_pipelines`.
+pub struct DebugDrawPipelines {
+    depth_tested: wgpu::RenderPipeline,
+    on_top: wgpu::RenderPipeline,
+}
+
+/// Collects the lines of an update and draws them, see the module docs.
+pub struct DebugDraw {
+    vertices: Vec<DebugVertex>,
+    buffer: GrowableBuffer<DebugVertex>,
+    depth_test: bool,
+    pipelines: DebugDrawPipelines,
+}
+
+impl DebugDraw {
+    /// `camera_bind_group_layout` is the one shared with the scene's
+    /// pipelines.
+    pub fn new(
+        device: &wgpu::Device,
+        targets: RenderTargets,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<Self, ShaderError> {
+        Ok(Self {
+            vertices: Vec::new(),
+            buffer: GrowableBuffer::new(
+                device,
+                "Debug Draw Vertex Buffer",
+                wgpu::BufferUsages::VERTEX,
+                &[],
+            ),
+            depth_test: true,
+            pipelines: create_pipelines(device, targets, camera_bind_group_layout)?,
+        })
+    }
+
+    /// Builds the pipelines for new render targets without using them yet,
+    /// see `set_pipelines`.
+    pub fn create_pipelines(
+        &self,
+        device: &wgpu::Device,
+        targets: RenderTargets,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<DebugDrawPipelines, ShaderError> {
+        create_pipelines(device, targets, camera_bind_group_layout)
+    }
+
+    pub fn set_pipelines(&mut self, pipelines: DebugDrawPipelines) {
+        self.pipelines = pipelines;
+    }
+
+    /// Whether the lines get hidden behind the scene, or show through it.
+    /// On by default.
+    pub fn depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    pub fn set_depth_test(&mut self, depth_test: bool) {
+        self.depth_test = depth_test;
+    }
+
+    /// What's been drawn since the last `clear`, two vertices per line.
+    pub fn vertices(&self) -> &[DebugVertex] {
+        &self.vertices
+    }
+
+    /// How many vertices fit in the buffer before it has to grow.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// A line from `a` to `b`, in world space.
+    pub fn line(&mut self, a: Vec3, b: Vec3, color: [f32; 4]) {
+        self.vertices.extend([
+            DebugVertex {
+                position: a.into(),
+                color,
+            },
+            DebugVertex {
+                position: b.into(),
+                color,
+            },
+        ]);
+    }
+
+    /// The edges of the box from `min` to `max`, along the axes.
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: [f32; 4]) {
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        // The corners differing in one coordinate, one bit of their index
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    /// A circle of `radius` around each axis, through `center`.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: [f32; 4]) {
+        let point = |axis: usize, segment: usize| {
+            let angle = segment as f32 / SPHERE_SEGMENTS as f32 * std::f32::consts::TAU;
+            let (sin, cos) = angle.sin_cos();
+            let offset = match axis {
+                0 => Vec3::new(0.0, cos, sin),
+                1 => Vec3::new(cos, 0.0, sin),
+                _ => Vec3::new(cos, sin, 0.0),
+            };
+            center + offset * radius
+        };
+        for axis in 0..3 {
+            for segment in 0..SPHERE_SEGMENTS {
+                self.line(point(axis, segment), point(axis, segment + 1), color);
+            }
+        }
+    }
+
+    /// The axes of `transform`, `size` long before it scales them: X in
+    /// red, Y in green and Z in blue.
+    pub fn axes(&mut self, transform: Mat4, size: f32) {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        for (axis, color) in [
+            (Vec3::X, [1.0, 0.0, 0.0, 1.0]),
+            (Vec3::Y, [0.0, 1.0, 0.0, 1.0]),
+            (Vec3::Z, [0.0, 0.0, 1.0, 1.0]),
+        ] {
+            self.line(origin, transform.transform_point3(axis * size), color);
+        }
+    }
+
+    /// Forgets the lines, `State::update` does it first thing.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Writes the lines into the buffer, growing it if they don't fit.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.buffer.update(device, queue, &self.vertices);
+    }
+
+    /// Records drawing what was uploaded with `camera`. Draw it after the
+    /// scene the lines go over.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera: CameraBinding<'a>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(if self.depth_test {
+            &self.pipelines.depth_tested
+        } else {
+            &self.pipelines.on_top
+        });
+        camera.bind(render_pass, 0);
+        self.buffer.bind(render_pass, 0);
+        render_pass.draw(self.buffer.draw_range(), 0..1);
+    }
+}
+
+fn create_pipelines(
+    device: &wgpu::Device,
+    targets: RenderTargets,
+    camera_bind_group_layout: &wgpu::BindGroupLayout,
+) -> Result<DebugDrawPipelines, ShaderError> {
+    let layouts = [camera_bind_group_layout];
+    // The lines never hide each other or what's drawn after them
+    let lines = PipelineBuilder::with_targets("Debug Draw Pipeline", targets)
+        .shader("debug_draw.wgsl", DEBUG_DRAW_SHADER_SOURCE)
+        .fragment_entry_point("fs_main")
+        .bind_group_layouts(&layouts)
+        .vertex_buffer(DebugVertex::desc())
+        .topology(wgpu::PrimitiveTopology::LineList)
+        .cull_mode(None)
+        .blend_mode(BlendMode::AlphaBlend);
+    let depth_tested = lines
+        .clone()
+        .depth_test(wgpu::CompareFunction::LessEqual, false)
+        .build(device)?;
+    let on_top = lines
+        .label("Debug Draw On Top Pipeline")
+        .depth_test(wgpu::CompareFunction::Always, false)
+        .build(device)?;
+    Ok(DebugDrawPipelines {
+        depth_tested,
+        on_top,
+    })
+}
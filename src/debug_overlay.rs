@@ -0,0 +1,104 @@
+use winit::{event::WindowEvent, window::Window};
+
+/// An egui overlay drawn on top of the scene, used for debug panels.
+pub struct DebugOverlay {
+    winit_state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    pub visible: bool,
+}
+
+impl DebugOverlay {
+    /// `format` is the format of the texture the overlay gets drawn into.
+    pub fn new(device: &wgpu::Device, window: &Window, format: wgpu::TextureFormat) -> Self {
+        let winit_state = egui_winit::State::new(
+            egui::Context::default(),
+            egui::ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        // The overlay is drawn after the MSAA resolve, straight into the
+        // final texture, so it has no depth buffer and is always 1x
+        let renderer = egui_wgpu::Renderer::new(device, format, None, 1);
+
+        Self {
+            winit_state,
+            renderer,
+            visible: true,
+        }
+    }
+
+    /// Returns `true` if the overlay consumed the event, e.g. a click on a
+    /// slider, which the rest of the app should then ignore.
+    pub fn handle_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        // egui always gets the events, so it keeps track of the scale factor
+        // and which buttons are held while hidden
+        let response = self.winit_state.on_window_event(window, event);
+        self.visible && response.consumed
+    }
+
+    /// Runs `run_ui` and records a render pass drawing the result on top of
+    /// what is already in `target`.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        window: &Window,
+        target: &wgpu::Texture,
+        run_ui: impl FnOnce(&egui::Context),
+    ) {
+        // Taken even when hidden, otherwise the events pile up until the
+        // overlay is shown again
+        let raw_input = self.winit_state.take_egui_input(window);
+        if !self.visible {
+            return;
+        }
+
+        let context = self.winit_state.egui_ctx().clone();
+        let full_output = context.run(raw_input, run_ui);
+        self.winit_state
+            .handle_platform_output(window, full_output.platform_output);
+
+        let paint_jobs = context.tessellate(full_output.shapes, full_output.pixels_per_point);
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [target.width(), target.height()],
+            pixels_per_point: full_output.pixels_per_point,
+        };
+
+        for (id, image_delta) in &full_output.textures_delta.set {
+            self.renderer
+                .update_texture(device, queue, *id, image_delta);
+        }
+        // Only paint callbacks produce command buffers, and we don't use any
+        let callback_buffers =
+            self.renderer
+                .update_buffers(device, queue, encoder, &paint_jobs, &screen_descriptor);
+        queue.submit(callback_buffers);
+
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Debug Overlay Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // Keep the scene we're drawing on top of
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.renderer
+                .render(&mut render_pass, &paint_jobs, &screen_descriptor);
+        }
+
+        for id in &full_output.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Views of what the passes draw rather than of the shaded scene, F4
+//! cycles through them. The normals and the UVs come from the scene shader
+//! itself, which reads the view from the globals, and the overdraw from
+//! pipelines counting the fragments. The debug view node then shows them
+//! or the depth buffer over the whole frame, or the shadow map in a corner,
+//! reading the targets from the frame resources.
+
+use std::mem::offset_of;
+
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    camera::Camera,
+    pipeline::{PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+    split_screen::Viewport,
+    uniform::{Uniform, UniformBuffer, UniformField, WgslType},
+};
+
+pub const DEBUG_VIEW_SHADER_SOURCE: &str = include_str!("../shaders/debug_view.wgsl");
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DebugView {
+    /// The scene as it's meant to look.
+    #[default]
+    None,
+    /// The distance from the camera, from black at the near plane to white
+    /// at the far one. Needs single-sampled depth, so not with MSAA.
+    Depth,
+    /// The normals the lighting uses, in world space and mapped to 0..1.
+    Normals,
+    Uvs,
+    /// How many fragments of the model each pixel gets, hidden or not.
+    Overdraw,
+    /// The scene with the shadow map in the bottom right corner.
+    ShadowMap,
+    /// The scene with the edges of the model's triangles on top. Needs
+    /// `Features::POLYGON_MODE_LINE`.
+    WireframeOverlay,
+}
+
+impl DebugView {
+    pub const ALL: [Self; 7] = [
+        Self::None,
+        Self::Depth,
+        Self::Normals,
+        Self::Uvs,
+        Self::Overdraw,
+        Self::ShadowMap,
+        Self::WireframeOverlay,
+    ];
+
+    /// In the order of `ALL`, back to `None` after the last one.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&view| view == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// What the shaders get, the position in `ALL`.
+    pub fn index(self) -> u32 {
+        Self::ALL.iter().position(|&view| view == self).unwrap_or(0) as u32
+    }
+
+    /// For the HUD.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Depth => "Depth",
+            Self::Normals => "Normals",
+            Self::Uvs => "UVs",
+            Self::Overdraw => "Overdraw",
+            Self::ShadowMap => "Shadow map",
+            Self::WireframeOverlay => "Wireframe overlay",
+        }
+    }
+
+    /// Whether the debug view node draws over the whole frame, rather than
+    /// over a corner of it or not at all.
+    pub fn replaces_frame(self) -> bool {
+        matches!(
+            self,
+            Self::Depth | Self::Normals | Self::Uvs | Self::Overdraw
+        )
+    }
+}
+
+/// The distance from the camera of a value in the depth buffer, for a
+/// camera with the planes at `near` and `far`. The perspective divide
+/// leaves most of the depth range to what's close to the camera, this
+/// spreads it out evenly again.
+pub fn linearize_depth(depth: f32, near: f32, far: f32) -> f32 {
+    near * far / (far - depth * (far - near))
+}
+
+/// Where `DebugView::ShadowMap` draws the map in a frame of `size`: a
+/// square a third of its smaller side, in the bottom right corner. Empty in
+/// frames too small for it.
+pub fn shadow_map_inset(size: PhysicalSize<u32>) -> Viewport {
+    let side = size.width.min(size.height) / 3;
+    let margin = side / 16;
+    Viewport {
+        x: size.width.saturating_sub(side + margin),
+        y: size.height.saturating_sub(side + margin),
+        width: side,
+        height: side,
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugViewUniform {
+    pub near: f32,
+    pub far: f32,
+    /// See `DebugView::index`.
+    pub view: u32,
+    _padding: u32,
+}
+
+impl DebugViewUniform {
+    pub fn new(view: DebugView, near: f32, far: f32) -> Self {
+        Self {
+            near,
+            far,
+            view: view.index(),
+            _padding: 0,
+        }
+    }
+}
+
+impl Uniform for DebugViewUniform {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new("near", offset_of!(DebugViewUniform, near), WgslType::F32),
+        UniformField::new("far", offset_of!(DebugViewUniform, far), WgslType::F32),
+        UniformField::new("view", offset_of!(DebugViewUniform, view), WgslType::U32),
+    ];
+}
+
+/// The targets a debug view reads.
+pub struct DebugSources<'a> {
+    /// What the scene pass drew into, `render_graph::HDR`.
+    pub scene: &'a wgpu::TextureView,
+    /// The depth aspect of `render_graph::DEPTH`, `None` while it's
+    /// multisampled.
+    pub depth: Option<&'a wgpu::TextureView>,
+    pub shadow_map: &'a wgpu::TextureView,
+}
+
+/// Draws the debug views that show a target, see the module docs.
+pub struct DebugViewRenderer {
+    layout: wgpu::BindGroupLayout,
+    uniform_buffer: UniformBuffer<DebugViewUniform>,
+    /// Bound instead of the depth buffer while it can't be.
+    placeholder_depth: wgpu::TextureView,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl DebugViewRenderer {
+    /// Draws into frames with `format`.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Result<Self, ShaderError> {
+        let texture_entry = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("debug_view_bind_group_layout"),
+            entries: &[
+                // Only ever loaded, never filtered. The depth targets too, as
+                // GLSL can't load from depth textures
+                texture_entry(0, wgpu::TextureSampleType::Float { filterable: false }),
+                texture_entry(1, wgpu::TextureSampleType::Float { filterable: false }),
+                texture_entry(2, wgpu::TextureSampleType::Float { filterable: false }),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Debug View Buffer",
+            &DebugViewUniform::new(DebugView::None, 0.1, 100.0),
+        );
+        let placeholder_depth = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("debug_view_placeholder_depth"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Depth32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&Default::default());
+        let pipeline = PipelineBuilder::with_targets(
+            "Debug View Pipeline",
+            RenderTargets {
+                color_format: format,
+                depth_format: None,
+                sample_count: 1,
+            },
+        )
+        .shader("debug_view.wgsl", DEBUG_VIEW_SHADER_SOURCE)
+        .bind_group_layouts(&[&layout])
+        .build(device)?;
+        Ok(Self {
+            layout,
+            uniform_buffer,
+            placeholder_depth,
+            pipeline,
+        })
+    }
+
+    /// Records drawing `view` into `target`, which is `size` big: over all
+    /// of it for the views that replace the frame, over the inset for the
+    /// shadow map, and nothing for the others. The depth is linearized with
+    /// `camera`'s planes. The targets get recreated with the frame, so
+    /// they're bound anew every time.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        size: PhysicalSize<u32>,
+        sources: &DebugSources,
+        view: DebugView,
+        camera: &Camera,
+    ) {
+        let viewport = match view {
+            _ if view.replaces_frame() => Viewport {
+                x: 0,
+                y: 0,
+                width: size.width,
+                height: size.height,
+            },
+            DebugView::ShadowMap => shadow_map_inset(size),
+            _ => return,
+        };
+        if viewport.is_empty() {
+            return;
+        }
+
+        self.uniform_buffer.write(
+            queue,
+            &DebugViewUniform::new(view, camera.znear, camera.zfar),
+        );
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("debug_view_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(sources.scene),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        sources.depth.unwrap_or(&self.placeholder_depth),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(sources.shadow_map),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.uniform_buffer.binding(),
+                },
+            ],
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Debug View Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // The inset goes over the frame
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        viewport.apply(&mut render_pass);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
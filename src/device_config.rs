@@ -0,0 +1,85 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use wgpu::Features;
+
+/// Where API traces go when `DeviceConfig::trace_path` isn't set.
+pub const TRACE_ENV_VAR: &str = "WGPU_TRACE";
+
+/// Which features and limits the device gets requested with, and which
+/// graphics APIs its adapter may use.
+#[derive(Clone, Debug)]
+pub struct DeviceConfig {
+    /// Adapters are only looked for on these backends.
+    pub backends: wgpu::Backends,
+    /// Creating the state fails if the adapter lacks any of these.
+    pub required_features: Features,
+    /// Turned on where the adapter has them. `State::features` tells which
+    /// ones were.
+    pub optional_features: Features,
+    pub limits: wgpu::Limits,
+    /// Records every wgpu call into this directory, which can be replayed
+    /// to reproduce bugs. Only works when wgpu is built with its `trace`
+    /// feature, and not on the web.
+    pub trace_path: Option<PathBuf>,
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        Self {
+            // WGPU_BACKEND=vulkan and the like still work without a CLI flag
+            backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all()),
+            required_features: Features::empty(),
+            // Lets us use the sample counts the adapter supports beyond the 1
+            // and 4 WebGPU guarantees, time the passes, draw wireframes and
+            // sample compressed textures where possible.
+            optional_features: Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                | Features::TIMESTAMP_QUERY
+                | Features::POLYGON_MODE_LINE
+                | Features::TEXTURE_COMPRESSION_BC
+                | Features::TEXTURE_COMPRESSION_ETC2
+                | Features::TEXTURE_COMPRESSION_ASTC,
+            // WebGL doesn't support all of wgpu's features, so if we're
+            // building for the web we'll have to disable some.
+            limits: if cfg!(target_arch = "wasm32") {
+                wgpu::Limits::downlevel_webgl2_defaults()
+            } else {
+                wgpu::Limits::default()
+            },
+            trace_path: None,
+        }
+    }
+}
+
+impl DeviceConfig {
+    /// The features to request from an adapter with `adapter_features`, or
+    /// the required ones it lacks.
+    pub fn features_for(&self, adapter_features: Features) -> Result<Features, Features> {
+        let missing = self.required_features - adapter_features;
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        Ok(self.required_features | (self.optional_features & adapter_features))
+    }
+
+    /// `trace_path`, or the `WGPU_TRACE` environment variable if it isn't
+    /// set.
+    pub fn trace_dir(&self) -> Option<PathBuf> {
+        self.trace_path.clone().or_else(|| {
+            std::env::var_os(TRACE_ENV_VAR)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+        })
+    }
+}
+
+/// Creates `dir` if it's missing and checks that files can be written into
+/// it, wgpu would only fail later on.
+pub fn prepare_trace_dir(dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(".write_test");
+    std::fs::write(&probe, [])?;
+    std::fs::remove_file(probe)
+}
@@ -0,0 +1,84 @@
+use thiserror::Error;
+
+/// Everything that can go wrong while setting up or running the app.
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("Failed to create the event loop: {0}")]
+    EventLoop(#[from] winit::error::EventLoopError),
+
+    #[error("Failed to create the window: {0}")]
+    CreateWindow(#[from] winit::error::OsError),
+
+    #[error("Failed to decode the window icon: {0}")]
+    DecodeIcon(#[from] image::ImageError),
+
+    #[error("Invalid window icon: {0}")]
+    BadIcon(#[from] winit::window::BadIcon),
+
+    #[error("Failed to create a surface for the window: {0}")]
+    CreateSurface(#[from] wgpu::CreateSurfaceError),
+
+    /// `available` describes every adapter there is, see
+    /// `adapter::describe_adapter`.
+    #[error("{reason}{}", format_available(.available))]
+    NoCompatibleAdapter {
+        reason: String,
+        available: Vec<String>,
+    },
+
+    #[error("Failed to request a device from {adapter}: {source}")]
+    RequestDevice {
+        adapter: String,
+        source: wgpu::RequestDeviceError,
+    },
+
+    #[error("{adapter} lacks the required features {}", format_features(*.missing))]
+    MissingFeatures {
+        adapter: String,
+        missing: wgpu::Features,
+    },
+
+    #[error("{adapter} can't run compute shaders")]
+    ComputeUnsupported { adapter: String },
+
+    #[error("Failed to create a texture array: {0}")]
+    TextureArray(#[from] crate::texture::TextureArrayError),
+
+    #[error("Failed to compile a shader:\n{0}")]
+    Shader(#[from] crate::shader::ShaderError),
+
+    #[error("Failed to read the shader {path}: {source}")]
+    ReadShader {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("Can't configure the surface for {adapter}: {reason}")]
+    SurfaceConfig { adapter: String, reason: String },
+
+    #[error("Can't render to a {width}x{height} texture")]
+    InvalidSize { width: u32, height: u32 },
+
+    #[error("Failed to read the rendered frame back: {0}")]
+    Readback(#[from] wgpu::BufferAsyncError),
+}
+
+fn format_available(available: &[String]) -> String {
+    if available.is_empty() {
+        return ", no adapters are available".to_owned();
+    }
+
+    let mut list = ", available adapters:".to_owned();
+    for (i, adapter) in available.iter().enumerate() {
+        list += &format!("\n  {i}: {adapter}");
+    }
+    list
+}
+
+fn format_features(features: wgpu::Features) -> String {
+    features
+        .iter_names()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,193 @@
+//! Files dropped onto the window: models replace the model of the scene, and
+//! images the diffuse texture of a material. `DropLoader` reads and decodes
+//! them on another thread, so the old scene keeps being drawn until they're
+//! ready and only the upload happens on the main thread.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use crate::{
+    asset_source::FsSource,
+    gltf,
+    model::{Model, ObjData},
+};
+
+/// What a dropped file is used for, by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropKind {
+    /// `.obj`, `.gltf` or `.glb`.
+    Model,
+    /// `.png`, `.jpg` or `.jpeg`, the formats `image` is built with.
+    Image,
+}
+
+impl DropKind {
+    /// `None` for files that can't be dropped.
+    pub fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "obj" | "gltf" | "glb" => Some(Self::Model),
+            "png" | "jpg" | "jpeg" => Some(Self::Image),
+            _ => None,
+        }
+    }
+}
+
+/// A dropped file, read and decoded.
+pub enum DroppedAsset {
+    /// With the textures of the materials that could be decoded. The others
+    /// are loaded while uploading, like compressed ones.
+    Obj {
+        data: ObjData,
+        images: HashMap<PathBuf, image::DynamicImage>,
+    },
+    Gltf(gltf::Document),
+    Image(image::DynamicImage),
+}
+
+/// A dropped file that finished loading, or why it couldn't be loaded.
+pub type LoadedDrop = (PathBuf, Result<DroppedAsset, String>);
+
+/// Reads a dropped file, blocking until it's decoded. Dropped files are
+/// anywhere, so they're read relative to the working directory.
+pub fn read(path: &Path) -> Result<DroppedAsset, String> {
+    let source = FsSource::new("");
+    let failed = |err: &dyn std::fmt::Display| format!("{}: {err}", path.display());
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    match (DropKind::of(path), extension.as_deref()) {
+        (Some(DropKind::Model), Some("obj")) => {
+            let data = Model::read_obj(&source, path).map_err(|err| failed(&err))?;
+            let images = data
+                .texture_paths()
+                .filter_map(|(_, path)| {
+                    let bytes = std::fs::read(&path).ok()?;
+                    let image = image::load_from_memory(&bytes).ok()?;
+                    Some((path, image))
+                })
+                .collect();
+            Ok(DroppedAsset::Obj { data, images })
+        }
+        (Some(DropKind::Model), _) => gltf::read_from(&source, path)
+            .map(DroppedAsset::Gltf)
+            .map_err(|err| failed(&err)),
+        (Some(DropKind::Image), _) => {
+            let bytes = std::fs::read(path).map_err(|err| failed(&err))?;
+            image::load_from_memory(&bytes)
+                .map(DroppedAsset::Image)
+                .map_err(|err| failed(&err))
+        }
+        (None, _) => Err(failed(&"unsupported file")),
+    }
+}
+
+/// Loads dropped files in the background and hands them back when they're
+/// done, in the order they were dropped.
+pub struct DropLoader {
+    next_id: u64,
+    sender: mpsc::Sender<(u64, LoadedDrop)>,
+    receiver: mpsc::Receiver<(u64, LoadedDrop)>,
+    /// In the order they were dropped, with the ones that are done.
+    loading: Vec<(u64, PathBuf, Option<LoadedDrop>)>,
+}
+
+impl Default for DropLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DropLoader {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            next_id: 0,
+            sender,
+            receiver,
+            loading: Vec::new(),
+        }
+    }
+
+    /// Starts loading a dropped file, `None` if it's not one that can be
+    /// dropped. There are no threads on the web, but winit doesn't send
+    /// dropped files there anyway.
+    pub fn load(&mut self, path: &Path) -> Option<DropKind> {
+        let kind = DropKind::of(path)?;
+        self.next_id += 1;
+        let id = self.next_id;
+        let path = path.to_owned();
+        self.loading.push((id, path.clone(), None));
+
+        let sender = self.sender.clone();
+        let load = move || {
+            // A reader that panics still has to hand back its drop, the
+            // ones dropped after it wait for it
+            let asset = std::panic::catch_unwind(|| read(&path))
+                .unwrap_or_else(|_| Err(format!("{}: reading it panicked", path.display())));
+            let _ = sender.send((id, (path, asset)));
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(err) = std::thread::Builder::new()
+            .name("file drop".to_owned())
+            .spawn(load)
+        {
+            log::error!("Failed to start loading the dropped file: {err}");
+            self.loading.pop();
+            return None;
+        }
+        #[cfg(target_arch = "wasm32")]
+        load();
+        Some(kind)
+    }
+
+    /// The dropped files that are still loading.
+    pub fn loading(&self) -> impl Iterator<Item = &Path> {
+        self.loading.iter().map(|(_, path, _)| path.as_path())
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !self.loading.is_empty()
+    }
+
+    /// The files that finished since the last call, without waiting for the
+    /// others. A file that finished before one dropped earlier waits for it,
+    /// so the last drop wins.
+    pub fn finished(&mut self) -> Vec<LoadedDrop> {
+        let finished: Vec<_> = self.receiver.try_iter().collect();
+        for loaded in finished {
+            self.complete(loaded);
+        }
+        let done = self
+            .loading
+            .iter()
+            .take_while(|(_, _, loaded)| loaded.is_some())
+            .count();
+        self.loading
+            .drain(..done)
+            .filter_map(|(_, _, loaded)| loaded)
+            .collect()
+    }
+
+    /// Like `finished`, but waits until nothing is loading anymore.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait(&mut self) -> Vec<LoadedDrop> {
+        while self.loading.iter().any(|(_, _, loaded)| loaded.is_none()) {
+            let Ok(loaded) = self.receiver.recv() else {
+                break;
+            };
+            self.complete(loaded);
+        }
+        self.finished()
+    }
+
+    fn complete(&mut self, (id, loaded): (u64, LoadedDrop)) {
+        if let Some((_, _, slot)) = self.loading.iter_mut().find(|(other, _, _)| *other == id) {
+            *slot = Some(loaded);
+        }
+    }
+}
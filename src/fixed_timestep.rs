@@ -0,0 +1,73 @@
+//! Steps the simulation at a constant rate, however fast the frames come.
+//! Each frame adds the time that passed and takes as many fixed steps out as
+//! fit, the rest carries over. What's left is also how far the frame is
+//! into the next step, see `alpha`, so moving things can be drawn between
+//! where they were at the last two steps instead of stuttering.
+
+use std::time::Duration;
+
+/// The simulation rate of the bundled scene, in steps per second.
+pub const DEFAULT_FIXED_UPDATE_RATE: u32 = 60;
+
+/// The most steps a frame takes. A frame that is slower than the steps it
+/// has to run would need even more steps the next time, this drops the time
+/// instead and the simulation slows down.
+pub const MAX_FIXED_STEPS: u32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedTimestep {
+    rate: u32,
+    step: Duration,
+    accumulated: Duration,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(DEFAULT_FIXED_UPDATE_RATE)
+    }
+}
+
+impl FixedTimestep {
+    /// Steps `rate` times per second, at least once.
+    pub fn new(rate: u32) -> Self {
+        let rate = rate.max(1);
+        Self {
+            rate,
+            step: Duration::from_secs(1) / rate,
+            accumulated: Duration::ZERO,
+        }
+    }
+
+    /// How long each step is.
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// The steps per second.
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// Adds `dt` and returns how many steps to run for it, at most
+    /// `MAX_FIXED_STEPS`.
+    pub fn advance(&mut self, dt: Duration) -> u32 {
+        self.accumulated += dt;
+        let mut steps = 0;
+        while self.accumulated >= self.step {
+            if steps == MAX_FIXED_STEPS {
+                // Only less than a step is left, so alpha stays below 1
+                self.accumulated = Duration::ZERO;
+                break;
+            }
+            self.accumulated -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+
+    /// How far the time is between the last step and the next one, from 0
+    /// to 1.
+    pub fn alpha(&self) -> f32 {
+        self.accumulated.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
@@ -0,0 +1,199 @@
+use std::{collections::VecDeque, time::Duration};
+
+use web_time::Instant;
+
+/// Frame statistics over one measuring interval.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameStats {
+    pub fps: f32,
+    pub avg_frame_time: Duration,
+    pub min_frame_time: Duration,
+    pub max_frame_time: Duration,
+    /// CPU time spent recording the scene's commands, see
+    /// `FrameCounter::record_encode_time`.
+    pub avg_encode_time: Duration,
+}
+
+/// Counts frames and computes `FrameStats` once per interval, so whatever
+/// displays them (e.g. the window title) doesn't have to change every frame.
+#[derive(Clone, Debug)]
+pub struct FrameCounter {
+    interval: Duration,
+    /// When the current interval started and when the last frame was.
+    ticks: Option<(Instant, Instant)>,
+    frames: u32,
+    total_frame_time: Duration,
+    min_frame_time: Duration,
+    max_frame_time: Duration,
+    encodes: u32,
+    total_encode_time: Duration,
+    stats: Option<FrameStats>,
+}
+
+impl FrameCounter {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            ticks: None,
+            frames: 0,
+            total_frame_time: Duration::ZERO,
+            min_frame_time: Duration::MAX,
+            max_frame_time: Duration::ZERO,
+            encodes: 0,
+            total_encode_time: Duration::ZERO,
+            stats: None,
+        }
+    }
+
+    /// Call once per frame. Returns the new stats when an interval is over.
+    pub fn tick(&mut self) -> Option<FrameStats> {
+        self.tick_at(Instant::now())
+    }
+
+    /// Same as `tick`, with the frame happening at `now`.
+    pub fn tick_at(&mut self, now: Instant) -> Option<FrameStats> {
+        let Some((interval_start, last_tick)) = self.ticks else {
+            // The first frame only starts the clock
+            self.ticks = Some((now, now));
+            return None;
+        };
+
+        let frame_time = now.saturating_duration_since(last_tick);
+        self.frames += 1;
+        self.total_frame_time += frame_time;
+        self.min_frame_time = self.min_frame_time.min(frame_time);
+        self.max_frame_time = self.max_frame_time.max(frame_time);
+
+        let interval_time = now.saturating_duration_since(interval_start);
+        if interval_time < self.interval {
+            self.ticks = Some((interval_start, now));
+            return None;
+        }
+
+        let stats = FrameStats {
+            fps: self.frames as f32 / interval_time.as_secs_f32(),
+            avg_frame_time: self.total_frame_time / self.frames,
+            min_frame_time: self.min_frame_time,
+            max_frame_time: self.max_frame_time,
+            avg_encode_time: self.total_encode_time / self.encodes.max(1),
+        };
+        *self = Self {
+            ticks: Some((now, now)),
+            stats: Some(stats),
+            ..Self::new(self.interval)
+        };
+        Some(stats)
+    }
+
+    /// Counts the time it took to record a frame's commands, for
+    /// `FrameStats::avg_encode_time`.
+    pub fn record_encode_time(&mut self, time: Duration) {
+        self.encodes += 1;
+        self.total_encode_time += time;
+    }
+
+    /// The stats of the last finished interval, `None` until one is over.
+    pub fn stats(&self) -> Option<FrameStats> {
+        self.stats
+    }
+}
+
+impl Default for FrameCounter {
+    /// Updates once per second.
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1))
+    }
+}
+
+/// How many frames `FrameTimeHistory` keeps by default, 5 seconds at 60 FPS.
+pub const FRAME_HISTORY_LEN: usize = 300;
+
+/// How long one frame took.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameTime {
+    /// Spent updating the scene and recording its commands.
+    pub cpu: Duration,
+    /// Spent on the GPU, `None` without timestamp queries.
+    pub gpu: Option<Duration>,
+}
+
+impl FrameTime {
+    /// Whether the frame took more than twice the `median` CPU time, see
+    /// `FrameTimeHistory::median_cpu`.
+    pub fn is_spike(&self, median: Duration) -> bool {
+        self.cpu > median * 2
+    }
+}
+
+/// The times of the last frames, oldest first. Once full every new frame
+/// pushes out the oldest one.
+#[derive(Clone, Debug)]
+pub struct FrameTimeHistory {
+    frames: VecDeque<FrameTime>,
+    capacity: usize,
+}
+
+impl Default for FrameTimeHistory {
+    fn default() -> Self {
+        Self::new(FRAME_HISTORY_LEN)
+    }
+}
+
+impl FrameTimeHistory {
+    /// Keeps the last `capacity` frames, at least one.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, frame: FrameTime) {
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &FrameTime> {
+        self.frames.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// The median CPU time, zero while empty.
+    pub fn median_cpu(&self) -> Duration {
+        let mut times: Vec<_> = self.frames.iter().map(|frame| frame.cpu).collect();
+        times.sort_unstable();
+        times.get(times.len() / 2).copied().unwrap_or_default()
+    }
+
+    /// The top of a graph of the frames: the longest CPU or GPU time
+    /// rounded up to the next whole millisecond, at least 1 ms.
+    pub fn scale(&self) -> Duration {
+        let longest = self
+            .frames
+            .iter()
+            .flat_map(|frame| [Some(frame.cpu), frame.gpu])
+            .flatten()
+            .max()
+            .unwrap_or_default();
+        let millis = longest.as_secs_f64() * 1000.0;
+        Duration::from_millis((millis.ceil() as u64).max(1))
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use web_time::Instant;
+
+/// The last bit of the wait before a frame is spent spinning, sleeping
+/// usually overshoots by about this much.
+pub const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// The caps the L key cycles through.
+pub const MAX_FPS_PRESETS: [Option<u32>; 4] = [None, Some(30), Some(60), Some(120)];
+
+/// Caps the frame rate by telling the event loop when the next frame is
+/// due. Independent of the present mode, see `is_needed`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameLimiter {
+    max_fps: Option<u32>,
+    next_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: Option<u32>) -> Self {
+        Self {
+            max_fps: max_fps.filter(|&fps| fps > 0),
+            next_frame: None,
+        }
+    }
+
+    pub fn max_fps(&self) -> Option<u32> {
+        self.max_fps
+    }
+
+    /// `None` or 0 removes the cap. Takes effect with the next frame.
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        *self = Self::new(max_fps);
+    }
+
+    /// How long each frame should take at most, `None` without a cap.
+    pub fn frame_time(&self) -> Option<Duration> {
+        self.max_fps
+            .map(|fps| Duration::from_secs_f64(1.0 / f64::from(fps)))
+    }
+
+    /// Call it right after presenting a frame.
+    pub fn frame_presented(&mut self, now: Instant) {
+        let Some(frame_time) = self.frame_time() else {
+            return;
+        };
+        // Scheduling from the last target rather than from now keeps the
+        // average on the cap, unless we fell behind and would have to catch up
+        let next_frame = self.next_frame.unwrap_or(now) + frame_time;
+        self.next_frame = Some(next_frame.max(now));
+    }
+
+    /// When the next frame should start, `None` if it can start right away.
+    pub fn next_frame(&self) -> Option<Instant> {
+        self.max_fps.and(self.next_frame)
+    }
+}
+
+/// Whether a frame rate cap does anything with `present_mode`. Vsync already
+/// holds the frame rate to the monitor's refresh rate, a cap at or above it
+/// would only add latency.
+pub fn is_needed(
+    max_fps: u32,
+    present_mode: wgpu::PresentMode,
+    refresh_rate_millihertz: Option<u32>,
+) -> bool {
+    let vsync = matches!(
+        present_mode,
+        wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed
+    );
+    match refresh_rate_millihertz {
+        Some(refresh_rate) if vsync => u64::from(max_fps) * 1000 < u64::from(refresh_rate),
+        _ => true,
+    }
+}
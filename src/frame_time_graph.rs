@@ -0,0 +1,210 @@
+//! A bar graph of the last frame times in the top right corner, F3 shows and
+//! hides it. Each bar is the CPU time of a frame, red for the spikes above
+//! twice the median, with a blue mark at its GPU time where there are
+//! timestamp queries. The graph goes up to the longest time in it, the line
+//! across it is the median.
+//!
+//! Hidden, there is no graph at all: nothing gets recorded or drawn, and the
+//! pipeline only gets created when it's shown.
+
+use std::time::Duration;
+
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    frame_counter::{FrameTime, FrameTimeHistory},
+    pipeline::{PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+};
+
+pub const FRAME_TIME_GRAPH_SHADER_SOURCE: &str = include_str!("../shaders/frame_time_graph.wgsl");
+
+/// The size of the graph in logical pixels, one pixel per frame of the
+/// default history.
+pub const GRAPH_SIZE: (f32, f32) = (300.0, 80.0);
+
+/// Between the graph and the edges of the window, in logical pixels.
+const GRAPH_MARGIN: f32 = 8.0;
+
+/// How thick the GPU marks and the median line are, in physical pixels.
+const LINE_WIDTH: f32 = 2.0;
+
+// In linear space, the bars are see-through enough to make out the scene
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+const CPU_COLOR: [f32; 4] = [0.1, 0.6, 0.1, 0.9];
+const SPIKE_COLOR: [f32; 4] = [0.9, 0.1, 0.05, 1.0];
+const GPU_COLOR: [f32; 4] = [0.2, 0.4, 1.0, 1.0];
+const MEDIAN_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.6];
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GraphVertex {
+    /// In clip space of the graph's viewport.
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+impl GraphVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// The two triangles of the rectangle from `min` to `max`.
+fn push_rect(vertices: &mut Vec<GraphVertex>, min: [f32; 2], max: [f32; 2], color: [f32; 4]) {
+    let corners = [
+        [min[0], min[1]],
+        [max[0], min[1]],
+        [max[0], max[1]],
+        [min[0], min[1]],
+        [max[0], max[1]],
+        [min[0], max[1]],
+    ];
+    vertices.extend(corners.map(|position| GraphVertex { position, color }));
+}
+
+/// The most rectangles a graph of `capacity` frames has: a bar and a GPU
+/// mark each, the background and the median line.
+fn max_vertices(capacity: usize) -> usize {
+    (2 * capacity + 2) * 6
+}
+
+/// The graph of `history`, `height` physical pixels tall.
+fn graph_vertices(history: &FrameTimeHistory, height: f32) -> Vec<GraphVertex> {
+    let mut vertices = Vec::with_capacity(max_vertices(history.len()));
+    push_rect(&mut vertices, [-1.0, -1.0], [1.0, 1.0], BACKGROUND_COLOR);
+
+    let scale = history.scale().as_secs_f32();
+    let median = history.median_cpu();
+    let y = |time: Duration| -1.0 + 2.0 * (time.as_secs_f32() / scale).min(1.0);
+    let half_line = LINE_WIDTH / height;
+    // The newest frame is on the right, an empty history fills up from there
+    let bar_width = 2.0 / history.capacity() as f32;
+    let first = history.capacity() - history.len();
+    for (i, frame) in history.iter().enumerate() {
+        let left = -1.0 + (first + i) as f32 * bar_width;
+        let right = left + bar_width;
+        let color = if frame.is_spike(median) {
+            SPIKE_COLOR
+        } else {
+            CPU_COLOR
+        };
+        push_rect(&mut vertices, [left, -1.0], [right, y(frame.cpu)], color);
+        if let Some(gpu) = frame.gpu {
+            let top = y(gpu);
+            push_rect(
+                &mut vertices,
+                [left, top - half_line],
+                [right, top + half_line],
+                GPU_COLOR,
+            );
+        }
+    }
+
+    let median = y(median);
+    push_rect(
+        &mut vertices,
+        [-1.0, median - half_line / 2.0],
+        [1.0, median + half_line / 2.0],
+        MEDIAN_COLOR,
+    );
+    vertices
+}
+
+/// Records the frame times and draws them, see the module docs.
+pub struct FrameTimeGraph {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    history: FrameTimeHistory,
+}
+
+impl FrameTimeGraph {
+    /// `format` is the format of the texture the graph gets drawn into.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Result<Self, ShaderError> {
+        // Drawn after the MSAA resolve like the HUD, so 1x and no depth
+        // buffer
+        let pipeline = PipelineBuilder::with_targets(
+            "Frame Time Graph Pipeline",
+            RenderTargets {
+                color_format: format,
+                depth_format: None,
+                sample_count: 1,
+            },
+        )
+        .shader("frame_time_graph.wgsl", FRAME_TIME_GRAPH_SHADER_SOURCE)
+        .vertex_buffer(GraphVertex::desc())
+        .color_target(format, Some(wgpu::BlendState::ALPHA_BLENDING))
+        .cull_mode(None)
+        .build(device)?;
+
+        let history = FrameTimeHistory::default();
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Time Graph Vertex Buffer"),
+            size: (max_vertices(history.capacity()) * std::mem::size_of::<GraphVertex>())
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            pipeline,
+            vertex_buffer,
+            history,
+        })
+    }
+
+    pub fn history(&self) -> &FrameTimeHistory {
+        &self.history
+    }
+
+    pub fn record(&mut self, frame: FrameTime) {
+        self.history.push(frame);
+    }
+
+    /// Records a render pass drawing the graph into the top right corner of
+    /// `view`, which is `size` big. Windows too small for it get none.
+    pub fn draw(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: PhysicalSize<u32>,
+        scale_factor: f64,
+    ) {
+        let scale_factor = scale_factor as f32;
+        let (width, height) = (GRAPH_SIZE.0 * scale_factor, GRAPH_SIZE.1 * scale_factor);
+        let margin = GRAPH_MARGIN * scale_factor;
+        let x = size.width as f32 - width - margin;
+        if x < 0.0 || height + margin > size.height as f32 {
+            return;
+        }
+
+        let vertices = graph_vertices(&self.history, height);
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Frame Time Graph Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_viewport(x, margin, width, height, 0.0, 1.0);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..vertices.len() as u32, 0..1);
+    }
+}
@@ -0,0 +1,81 @@
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    monitor::{MonitorHandle, VideoMode},
+    window::{Fullscreen, Window},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// A borderless window covering the monitor, quick to switch to.
+    Borderless,
+    /// Takes over the monitor with its best video mode.
+    Exclusive,
+}
+
+/// Where the window was before going fullscreen.
+#[derive(Clone, Copy, Debug)]
+struct WindowedPlacement {
+    size: PhysicalSize<u32>,
+    position: Option<PhysicalPosition<i32>>,
+}
+
+/// Switches a window between windowed and fullscreen, putting it back where
+/// it was when leaving fullscreen.
+#[derive(Clone, Debug, Default)]
+pub struct FullscreenToggle {
+    windowed: Option<WindowedPlacement>,
+}
+
+impl FullscreenToggle {
+    /// Leaves fullscreen if the window is fullscreen, or else goes fullscreen
+    /// in `mode` on the monitor the window is on. The window gets resized
+    /// events for its new size either way.
+    pub fn toggle(&mut self, window: &Window, mode: FullscreenMode) {
+        if window.fullscreen().is_some() {
+            self.exit(window);
+            return;
+        }
+
+        self.windowed = Some(WindowedPlacement {
+            size: window.inner_size(),
+            position: window.outer_position().ok(),
+        });
+
+        let monitor = window.current_monitor();
+        let fullscreen = match mode {
+            FullscreenMode::Borderless => Fullscreen::Borderless(monitor),
+            FullscreenMode::Exclusive => match monitor.as_ref().and_then(best_video_mode) {
+                Some(video_mode) => Fullscreen::Exclusive(video_mode),
+                None => {
+                    log::warn!("No video mode for exclusive fullscreen, going borderless");
+                    Fullscreen::Borderless(monitor)
+                }
+            },
+        };
+        window.set_fullscreen(Some(fullscreen));
+    }
+
+    fn exit(&mut self, window: &Window) {
+        window.set_fullscreen(None);
+        if let Some(windowed) = self.windowed.take() {
+            let _ = window.request_inner_size(windowed.size);
+            if let Some(position) = windowed.position {
+                window.set_outer_position(position);
+            }
+        }
+    }
+}
+
+/// The video mode with the highest resolution, then refresh rate, then bit
+/// depth. `None` if the monitor doesn't allow exclusive fullscreen, like on
+/// the web.
+pub fn best_video_mode(monitor: &MonitorHandle) -> Option<VideoMode> {
+    monitor.video_modes().max_by_key(|mode| {
+        let size = mode.size();
+        (
+            u64::from(size.width) * u64::from(size.height),
+            mode.refresh_rate_millihertz(),
+            mode.bit_depth(),
+        )
+    })
+}
@@ -0,0 +1,272 @@
+//! Fast approximate antialiasing, a cheaper alternative to MSAA. Instead
+//! of rendering more samples it blurs the edges it finds in the finished
+//! frame, after the tonemapping, see `shaders/fxaa.wgsl`. The scene then
+//! renders with a single sample, the two are never used together.
+
+use std::mem::offset_of;
+
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    pipeline::{PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+    uniform::{Uniform, UniformBuffer, UniformField, WgslType},
+};
+
+pub const FXAA_SHADER_SOURCE: &str = include_str!("../shaders/fxaa.wgsl");
+
+/// The samples `Antialiasing::Msaa` renders with.
+pub const MSAA_SAMPLES: u32 = 4;
+
+/// How the frame's edges get smoothed, see `State::set_antialiasing`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Antialiasing {
+    None,
+    Fxaa,
+    /// With `MSAA_SAMPLES` samples.
+    Msaa,
+}
+
+impl Antialiasing {
+    /// From the cheapest to the best looking and back, which is what
+    /// `Action::CycleAntialiasing` goes through.
+    pub fn next(self) -> Self {
+        match self {
+            Self::None => Self::Fxaa,
+            Self::Fxaa => Self::Msaa,
+            Self::Msaa => Self::None,
+        }
+    }
+}
+
+/// What FXAA 3.11 calls its quality settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FxaaSettings {
+    /// How much pixels thinner than an edge get blurred, from 0 for not at
+    /// all to 1 for the softest.
+    pub subpixel: f32,
+    /// How much contrast an edge needs, relative to the brightest luma
+    /// around it. 0.063 gets the most edges, 0.333 only the strongest.
+    pub edge_threshold: f32,
+    /// The contrast an edge needs at least, which leaves dark areas alone.
+    pub edge_threshold_min: f32,
+}
+
+impl Default for FxaaSettings {
+    /// FXAA's own defaults.
+    fn default() -> Self {
+        Self {
+            subpixel: 0.75,
+            edge_threshold: 0.166,
+            edge_threshold_min: 0.0833,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FxaaUniform {
+    /// 1 over the size of the source, in pixels.
+    pub texel_size: [f32; 2],
+    pub subpixel: f32,
+    pub edge_threshold: f32,
+    pub edge_threshold_min: f32,
+    /// Non-zero when reading the source gives linear colors, which need
+    /// encoding before their luma means anything.
+    pub linear_input: u32,
+    _padding: [u32; 2],
+}
+
+impl FxaaUniform {
+    pub fn new(settings: FxaaSettings, size: PhysicalSize<u32>, linear_input: bool) -> Self {
+        Self {
+            texel_size: [
+                1.0 / size.width.max(1) as f32,
+                1.0 / size.height.max(1) as f32,
+            ],
+            subpixel: settings.subpixel,
+            edge_threshold: settings.edge_threshold,
+            edge_threshold_min: settings.edge_threshold_min,
+            linear_input: linear_input.into(),
+            _padding: [0; 2],
+        }
+    }
+}
+
+impl Uniform for FxaaUniform {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "texel_size",
+            offset_of!(FxaaUniform, texel_size),
+            WgslType::Vec2,
+        ),
+        UniformField::new("subpixel", offset_of!(FxaaUniform, subpixel), WgslType::F32),
+        UniformField::new(
+            "edge_threshold",
+            offset_of!(FxaaUniform, edge_threshold),
+            WgslType::F32,
+        ),
+        UniformField::new(
+            "edge_threshold_min",
+            offset_of!(FxaaUniform, edge_threshold_min),
+            WgslType::F32,
+        ),
+        UniformField::new(
+            "linear_input",
+            offset_of!(FxaaUniform, linear_input),
+            WgslType::U32,
+        ),
+    ];
+}
+
+/// Draws the tonemapped frame into the real one with FXAA, see the module
+/// docs.
+pub struct Fxaa {
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    settings: FxaaSettings,
+    size: PhysicalSize<u32>,
+    linear_input: bool,
+    uniform_buffer: UniformBuffer<FxaaUniform>,
+    /// `None` until `set_source`.
+    bind_group: Option<wgpu::BindGroup>,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Fxaa {
+    /// Draws into frames with `format`, from a source with the same format.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Result<Self, ShaderError> {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("fxaa_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        // The blend across an edge comes from sampling between two texels
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("fxaa_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let settings = FxaaSettings::default();
+        let size = PhysicalSize::new(1, 1);
+        // sRGB views decode on read, the others hold the encoded colors
+        let linear_input = format.is_srgb();
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "FXAA Buffer",
+            &FxaaUniform::new(settings, size, linear_input),
+        );
+        let pipeline = PipelineBuilder::with_targets(
+            "FXAA Pipeline",
+            RenderTargets {
+                color_format: format,
+                depth_format: None,
+                sample_count: 1,
+            },
+        )
+        .shader("fxaa.wgsl", FXAA_SHADER_SOURCE)
+        .fragment_entry_point("fs_main")
+        .bind_group_layouts(&[&layout])
+        .build(device)?;
+        Ok(Self {
+            layout,
+            sampler,
+            settings,
+            size,
+            linear_input,
+            uniform_buffer,
+            bind_group: None,
+            pipeline,
+        })
+    }
+
+    pub fn settings(&self) -> FxaaSettings {
+        self.settings
+    }
+
+    /// Shows up from the next submit on.
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: FxaaSettings) {
+        self.settings = settings;
+        self.write_uniform(queue);
+    }
+
+    /// Smooths `source`, of `size`, from the next submit on.
+    pub fn set_source(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &wgpu::TextureView,
+        size: PhysicalSize<u32>,
+    ) {
+        self.size = size;
+        self.write_uniform(queue);
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("fxaa_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.uniform_buffer.binding(),
+                },
+            ],
+        }));
+    }
+
+    /// Records drawing the source into `view`, nothing before `set_source`.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        if let Some(bind_group) = &self.bind_group {
+            crate::post_process::encode_fullscreen_pass(
+                encoder,
+                "FXAA Render Pass",
+                view,
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                &self.pipeline,
+                &[bind_group],
+            );
+        }
+    }
+
+    fn write_uniform(&self, queue: &wgpu::Queue) {
+        self.uniform_buffer.write(
+            queue,
+            &FxaaUniform::new(self.settings, self.size, self.linear_input),
+        );
+    }
+}
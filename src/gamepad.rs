@@ -0,0 +1,86 @@
+use gilrs::{Axis, Button, EventType, Gilrs};
+use glam::Vec2;
+
+/// Stick values closer to the center than this count as centered, as worn
+/// sticks rarely rest at exactly 0.
+pub const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+/// What the gamepads asked for during a frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GamepadFrame {
+    /// The left stick, x to the right and y forward.
+    pub movement: Vec2,
+    /// The right stick, x to the right and y up.
+    pub look: Vec2,
+    /// Start was pressed.
+    pub exit: bool,
+}
+
+/// Reads every connected gamepad. Gamepads can be plugged in and out while
+/// the app runs.
+pub struct GamepadInput {
+    gilrs: Gilrs,
+    pub dead_zone: f32,
+}
+
+impl GamepadInput {
+    /// `None` if the platform's gamepad API isn't available.
+    pub fn new() -> Option<Self> {
+        match Gilrs::new() {
+            Ok(gilrs) => Some(Self {
+                gilrs,
+                dead_zone: DEFAULT_DEAD_ZONE,
+            }),
+            Err(err) => {
+                log::warn!("Gamepads aren't supported: {err}");
+                None
+            }
+        }
+    }
+
+    /// Handles the events since the last call and reads the sticks. Call it
+    /// once per frame.
+    pub fn poll(&mut self) -> GamepadFrame {
+        let mut frame = GamepadFrame::default();
+
+        // The events also keep the state of the gamepads up to date
+        while let Some(event) = self.gilrs.next_event() {
+            match event.event {
+                EventType::Connected => {
+                    let name = self.gilrs.gamepad(event.id).name().to_owned();
+                    log::info!("Gamepad connected: {name}");
+                }
+                EventType::Disconnected => log::info!("Gamepad disconnected"),
+                EventType::ButtonPressed(Button::Start, _) => frame.exit = true,
+                _ => {}
+            }
+        }
+
+        // Disconnected gamepads aren't listed, so unplugging one mid-frame
+        // just stops its input
+        for (_, gamepad) in self.gilrs.gamepads() {
+            let stick = |x, y| {
+                Vec2::new(
+                    apply_dead_zone(gamepad.value(x), self.dead_zone),
+                    apply_dead_zone(gamepad.value(y), self.dead_zone),
+                )
+            };
+            frame.movement += stick(Axis::LeftStickX, Axis::LeftStickY);
+            frame.look += stick(Axis::RightStickX, Axis::RightStickY);
+        }
+        frame.movement = frame.movement.clamp(Vec2::NEG_ONE, Vec2::ONE);
+        frame.look = frame.look.clamp(Vec2::NEG_ONE, Vec2::ONE);
+
+        frame
+    }
+}
+
+/// Zeroes `value` inside the dead zone and rescales the rest so the output
+/// still goes smoothly from 0 to 1.
+pub fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    let magnitude = value.abs();
+    if magnitude <= dead_zone {
+        return 0.0;
+    }
+    value.signum() * ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0)
+}
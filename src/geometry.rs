@@ -0,0 +1,178 @@
+//! Meshes generated in code instead of loaded from files. They all fit in
+//! the unit cube around the origin, face outwards with counter-clockwise
+//! triangles like the pipelines expect, and have the origin of their texture
+//! coordinates at the top left.
+
+use std::f32::consts::{PI, TAU};
+
+use glam::{Vec2, Vec3};
+
+use crate::model::{compute_tangents, ModelVertex};
+
+/// Vertices and the triangles between them, ready for `Mesh::from_geometry`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Geometry {
+    pub vertices: Vec<ModelVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Geometry {
+    /// Fills in the tangents from the texture coordinates.
+    fn new(mut vertices: Vec<ModelVertex>, indices: Vec<u32>) -> Self {
+        compute_tangents(&mut vertices, &indices);
+        Self { vertices, indices }
+    }
+}
+
+fn vertex(position: Vec3, tex_coords: Vec2, normal: Vec3) -> ModelVertex {
+    ModelVertex {
+        position: position.into(),
+        tex_coords: tex_coords.into(),
+        normal: normal.into(),
+        // Filled in by compute_tangents
+        tangent: [0.0; 3],
+        bitangent: [0.0; 3],
+    }
+}
+
+/// A cube with sides of 1. Each face has vertices of its own, so the
+/// normals stay sharp, and the whole texture.
+pub fn cube() -> Geometry {
+    // The normal and which way is up on each face, seen from the outside
+    let faces = [
+        (Vec3::X, Vec3::Y),
+        (Vec3::NEG_X, Vec3::Y),
+        (Vec3::Y, Vec3::NEG_Z),
+        (Vec3::NEG_Y, Vec3::Z),
+        (Vec3::Z, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::Y),
+    ];
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for (normal, up) in faces {
+        let right = up.cross(normal);
+        let first = vertices.len() as u32;
+        // Bottom left, bottom right, top right, top left
+        for (x, y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            let position = normal * 0.5 + right * (x - 0.5) + up * (y - 0.5);
+            vertices.push(vertex(position, Vec2::new(x, 1.0 - y), normal));
+        }
+        indices.extend([0, 1, 2, 0, 2, 3].map(|i| first + i));
+    }
+    Geometry::new(vertices, indices)
+}
+
+/// A sphere with a diameter of 1, made of `rings` rows from pole to pole
+/// and `segments` columns around it. The texture wraps around it once.
+/// There are at least 2 rings and 3 segments.
+pub fn uv_sphere(rings: u32, segments: u32) -> Geometry {
+    let (rings, segments) = (rings.max(2), segments.max(3));
+    // The first and last column are at the same place, with different
+    // texture coordinates, and so are the vertices at the poles
+    let mut vertices = Vec::with_capacity(((rings + 1) * (segments + 1)) as usize);
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let theta = v * PI;
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let normal = around_y(u, theta.sin(), theta.cos());
+            vertices.push(vertex(normal * 0.5, Vec2::new(u, v), normal));
+        }
+    }
+
+    let columns = segments + 1;
+    let mut indices = Vec::with_capacity((segments * (rings - 1) * 6) as usize);
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let top = ring * columns + segment;
+            let bottom = top + columns;
+            // The quads touching the poles are only triangles
+            if ring != 0 {
+                indices.extend([top, bottom, top + 1]);
+            }
+            if ring != rings - 1 {
+                indices.extend([top + 1, bottom, bottom + 1]);
+            }
+        }
+    }
+    Geometry::new(vertices, indices)
+}
+
+/// A square with sides of 1 lying flat at y = 0 and facing up, split into
+/// `subdivisions` by `subdivisions` quads. There's at least one.
+pub fn plane(subdivisions: u32) -> Geometry {
+    let subdivisions = subdivisions.max(1);
+    let columns = subdivisions + 1;
+    let mut vertices = Vec::with_capacity((columns * columns) as usize);
+    for row in 0..columns {
+        let v = row as f32 / subdivisions as f32;
+        for column in 0..columns {
+            let u = column as f32 / subdivisions as f32;
+            let position = Vec3::new(u - 0.5, 0.0, v - 0.5);
+            vertices.push(vertex(position, Vec2::new(u, v), Vec3::Y));
+        }
+    }
+
+    let mut indices = Vec::with_capacity((subdivisions * subdivisions * 6) as usize);
+    for row in 0..subdivisions {
+        for column in 0..subdivisions {
+            let near = row * columns + column;
+            let far = near + columns;
+            indices.extend([near, far, near + 1, near + 1, far, far + 1]);
+        }
+    }
+    Geometry::new(vertices, indices)
+}
+
+/// A cylinder with a diameter and a height of 1 standing on the y axis,
+/// with `segments` sides around it and closed at both ends. There are at
+/// least 3.
+pub fn cylinder(segments: u32) -> Geometry {
+    let segments = segments.max(3);
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    // The side, with the texture wrapped around once
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let normal = around_y(u, 1.0, 0.0);
+        for (y, v) in [(0.5, 0.0), (-0.5, 1.0)] {
+            let position = normal * 0.5 + Vec3::Y * y;
+            vertices.push(vertex(position, Vec2::new(u, v), normal));
+        }
+    }
+    for segment in 0..segments {
+        let top = segment * 2;
+        let bottom = top + 1;
+        indices.extend([top, bottom, top + 2, top + 2, bottom, bottom + 2]);
+    }
+
+    // The ends, with the texture laid over them
+    for (y, normal) in [(0.5, Vec3::Y), (-0.5, Vec3::NEG_Y)] {
+        let center = vertices.len() as u32;
+        vertices.push(vertex(Vec3::Y * y, Vec2::splat(0.5), normal));
+        for segment in 0..segments {
+            let u = segment as f32 / segments as f32;
+            let direction = around_y(u, 1.0, 0.0);
+            let tex_coords = Vec2::new(0.5 + direction.x * 0.5, 0.5 - direction.z * 0.5);
+            vertices.push(vertex(direction * 0.5 + Vec3::Y * y, tex_coords, normal));
+        }
+        for segment in 0..segments {
+            let current = center + 1 + segment;
+            let next = center + 1 + (segment + 1) % segments;
+            if normal == Vec3::Y {
+                indices.extend([center, current, next]);
+            } else {
+                indices.extend([center, next, current]);
+            }
+        }
+    }
+    Geometry::new(vertices, indices)
+}
+
+/// The point `u` of the way around the y axis, counter-clockwise seen from
+/// above starting at +X, at `radius` from it and at `height`.
+fn around_y(u: f32, radius: f32, height: f32) -> Vec3 {
+    let phi = u * TAU;
+    Vec3::new(radius * phi.cos(), height, -radius * phi.sin())
+}
@@ -0,0 +1,142 @@
+//! Values any shader can animate with, like the uniforms of ShaderToy. They
+//! are bound next to the camera at group 0 in every scene pipeline:
+//!
+//! ```wgsl
+//! struct Globals {
+//!     resolution: vec2<f32>,
+//!     cursor: vec2<f32>,
+//!     time: f32,
+//!     delta_time: f32,
+//!     frame: u32,
+//!     debug_view: u32,
+//! };
+//! @group(0) @binding(1)
+//! var<uniform> globals: Globals;
+//! ```
+
+use std::{mem::offset_of, time::Duration};
+
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use crate::{
+    debug_view::DebugView,
+    uniform::{Uniform, UniformField, WgslType},
+};
+
+/// Where the globals are in their bind group.
+pub const BINDING: u32 = 1;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlobalsUniform {
+    /// Of the surface, in pixels.
+    pub resolution: [f32; 2],
+    /// In pixels from the top left of the window.
+    pub cursor: [f32; 2],
+    /// Seconds since the start, not counting the time spent paused.
+    pub time: f32,
+    /// Seconds since the last frame, 0 while paused.
+    pub delta_time: f32,
+    /// Counts every frame, paused or not.
+    pub frame: u32,
+    /// What the scene shader shows instead of the shaded surfaces, see
+    /// `DebugView::index`.
+    pub debug_view: u32,
+}
+
+impl Uniform for GlobalsUniform {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "resolution",
+            offset_of!(GlobalsUniform, resolution),
+            WgslType::Vec2,
+        ),
+        UniformField::new("cursor", offset_of!(GlobalsUniform, cursor), WgslType::Vec2),
+        UniformField::new("time", offset_of!(GlobalsUniform, time), WgslType::F32),
+        UniformField::new(
+            "delta_time",
+            offset_of!(GlobalsUniform, delta_time),
+            WgslType::F32,
+        ),
+        UniformField::new("frame", offset_of!(GlobalsUniform, frame), WgslType::U32),
+        UniformField::new(
+            "debug_view",
+            offset_of!(GlobalsUniform, debug_view),
+            WgslType::U32,
+        ),
+    ];
+}
+
+/// Keeps the time, the resolution and the cursor up to date for the
+/// shaders, `uniform` is what gets uploaded.
+#[derive(Debug, Clone)]
+pub struct Globals {
+    time: Duration,
+    paused: bool,
+    uniform: GlobalsUniform,
+}
+
+impl Globals {
+    pub fn new(resolution: PhysicalSize<u32>) -> Self {
+        let mut globals = Self {
+            time: Duration::ZERO,
+            paused: false,
+            uniform: bytemuck::Zeroable::zeroed(),
+        };
+        globals.set_resolution(resolution);
+        globals
+    }
+
+    /// Moves on to the next frame, `dt` after the last one. The time stands
+    /// still while paused.
+    pub fn advance(&mut self, dt: Duration) {
+        let dt = if self.paused { Duration::ZERO } else { dt };
+        self.time += dt;
+        self.uniform.time = self.time.as_secs_f32();
+        self.uniform.delta_time = dt.as_secs_f32();
+        self.uniform.frame = self.uniform.frame.wrapping_add(1);
+    }
+
+    pub fn set_resolution(&mut self, resolution: PhysicalSize<u32>) {
+        self.uniform.resolution = [resolution.width as f32, resolution.height as f32];
+    }
+
+    pub fn set_cursor(&mut self, position: PhysicalPosition<f64>) {
+        self.uniform.cursor = [position.x as f32, position.y as f32];
+    }
+
+    pub fn set_debug_view(&mut self, view: DebugView) {
+        self.uniform.debug_view = view.index();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// The time the shaders see.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn uniform(&self) -> &GlobalsUniform {
+        &self.uniform
+    }
+}
+
+/// The entry of the globals in the layout of the bind group they're in.
+pub fn layout_entry() -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: BINDING,
+        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
@@ -0,0 +1,1238 @@
+//! Reads glTF 2.0 models, both `.gltf` files (JSON, with the buffers and
+//! images in other files or embedded as data URIs) and `.glb` files (the
+//! JSON and a binary chunk in one). `read` gets the triangles and materials
+//! on the CPU, `Model::load_gltf` uploads them.
+//!
+//! The node hierarchy is flattened: every primitive comes out with its
+//! node's world transform applied to the vertices, so it draws with the
+//! instance transforms like an OBJ mesh does. Files with skins also come
+//! with a `Skeleton` to animate them with, see `animation`.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use glam::{Mat3, Mat4, Quat, Vec3};
+use image::RgbaImage;
+use thiserror::Error;
+
+use crate::{
+    animation::{
+        AnimationError, Channel, Clip, Interpolation, Keyframes, Node, Skeleton, Skin, SkinVertex,
+        Transform,
+    },
+    asset_source::{AssetSource, FsSource},
+    color,
+    json::{self, Value},
+    model::{compute_tangents, ModelVertex},
+};
+
+#[derive(Debug, Error)]
+pub enum GltfError {
+    #[error("failed to read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid JSON: {0}")]
+    Json(#[from] json::JsonError),
+    #[error("invalid GLB container: {0}")]
+    Glb(&'static str),
+    #[error("invalid glTF: {0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Animation(#[from] AnimationError),
+}
+
+fn invalid(message: impl Into<String>) -> GltfError {
+    GltfError::Invalid(message.into())
+}
+
+/// A triangle list with one material.
+#[derive(Debug, Clone)]
+pub struct Primitive {
+    pub name: String,
+    pub vertices: Vec<ModelVertex>,
+    pub indices: Vec<u32>,
+    /// An index into `Document::materials`, `None` for the default material.
+    pub material: Option<usize>,
+    /// One for every vertex when the document has a skeleton, empty when
+    /// it doesn't. Primitives that aren't skinned follow their node, as if
+    /// it was their only joint.
+    pub skin_vertices: Vec<SkinVertex>,
+}
+
+/// The textures of a material, with its factors multiplied in. A material
+/// with a factor but no texture gets a 1x1 texture of the factor. `None`
+/// where it has neither, the defaults are the same as for OBJ materials.
+#[derive(Debug, Clone)]
+pub struct MaterialData {
+    pub name: String,
+    /// sRGB encoded.
+    pub base_color: Option<RgbaImage>,
+    /// Roughness in green, metallic in blue, both linear.
+    pub metallic_roughness: Option<RgbaImage>,
+    pub normal: Option<RgbaImage>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub primitives: Vec<Primitive>,
+    pub materials: Vec<MaterialData>,
+    /// `None` unless the file has skins.
+    pub skeleton: Option<Skeleton>,
+}
+
+/// Reads a `.gltf` or `.glb` file, and the files it refers to relative to
+/// it.
+pub fn read(path: impl AsRef<Path>) -> Result<Document, GltfError> {
+    read_from(&FsSource::new(""), path)
+}
+
+/// Like `read`, with the files in `source`.
+pub fn read_from(source: &dyn AssetSource, path: impl AsRef<Path>) -> Result<Document, GltfError> {
+    let path = path.as_ref();
+    let bytes = read_file(source, path)?;
+    parse_from(
+        &bytes,
+        source,
+        path.parent().unwrap_or_else(|| Path::new("")),
+    )
+}
+
+/// Parses a glTF document from memory, GLB or JSON. URIs of external files
+/// are resolved relative to `base_dir`.
+pub fn parse(bytes: &[u8], base_dir: &Path) -> Result<Document, GltfError> {
+    parse_from(bytes, &FsSource::new(""), base_dir)
+}
+
+/// Like `parse`, with the external files in `source`.
+pub fn parse_from(
+    bytes: &[u8],
+    source: &dyn AssetSource,
+    base_dir: &Path,
+) -> Result<Document, GltfError> {
+    let (json, binary_chunk) = if bytes.starts_with(GLB_MAGIC) {
+        split_glb(bytes)?
+    } else {
+        let json = std::str::from_utf8(bytes).map_err(|_| invalid("the JSON isn't UTF-8"))?;
+        (json, None)
+    };
+    let root = json::parse(json)?;
+
+    let version = root
+        .get("asset")
+        .and_then(|asset| asset.get("version"))
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("missing asset.version"))?;
+    if !version.starts_with("2.") {
+        return Err(invalid(format!("unsupported version {version}")));
+    }
+
+    let buffers = array(&root, "buffers")
+        .iter()
+        .enumerate()
+        .map(|(index, buffer)| load_buffer(index, buffer, binary_chunk, source, base_dir))
+        .collect::<Result<_, _>>()?;
+    let gltf = Gltf {
+        root: &root,
+        buffers,
+        source,
+        base_dir,
+    };
+
+    let mut images = HashMap::new();
+    let materials = array(&root, "materials")
+        .iter()
+        .enumerate()
+        .map(|(index, material)| gltf.material(index, material, &mut images))
+        .collect();
+
+    let nodes = gltf.nodes()?;
+    let mut skins = gltf.skins(nodes.len())?;
+    let file_skins = skins.len();
+    let mut joint_count: usize = skins.iter().map(|skin| skin.joints.len()).sum();
+    let skin_offsets: Vec<usize> = skins
+        .iter()
+        .scan(0, |offset, skin| {
+            let start = *offset;
+            *offset += skin.joints.len();
+            Some(start)
+        })
+        .collect();
+
+    let mut primitives = Vec::new();
+    for (node_index, node, transform) in gltf.mesh_nodes()? {
+        let mesh_index = usize_member(node, "mesh").expect("mesh_nodes only has meshes");
+        let mesh = array(&root, "meshes")
+            .get(mesh_index)
+            .ok_or_else(|| invalid(format!("node mesh {mesh_index} doesn't exist")))?;
+        // In skinned documents every mesh node gets a joint of its own, for
+        // the primitives that aren't skinned. Its inverse bind matrix undoes
+        // the transform that's already applied to their vertices.
+        let skinning = (file_skins > 0).then(|| {
+            skins.push(Skin {
+                joints: vec![node_index],
+                inverse_bind_matrices: vec![transform.inverse()],
+            });
+            joint_count += 1;
+            NodeSkinning {
+                skin_offset: usize_member(node, "skin")
+                    .and_then(|skin| skin_offsets.get(skin))
+                    .map(|&offset| offset as u32),
+                node_joint: (joint_count - 1) as u32,
+            }
+        });
+        gltf.primitives(mesh_index, mesh, transform, skinning, &mut primitives)?;
+    }
+    let material_count = array(&root, "materials").len();
+    for primitive in &mut primitives {
+        if primitive
+            .material
+            .is_some_and(|index| index >= material_count)
+        {
+            log::warn!(
+                "{} has no valid material, using the default",
+                primitive.name
+            );
+            primitive.material = None;
+        }
+    }
+
+    let skeleton = if file_skins > 0 {
+        Some(Skeleton {
+            nodes,
+            skins,
+            clips: gltf.clips()?,
+        })
+    } else {
+        None
+    };
+
+    Ok(Document {
+        primitives,
+        materials,
+        skeleton,
+    })
+}
+
+/// Which of the skeleton's joints the primitives of a node follow.
+#[derive(Debug, Clone, Copy)]
+struct NodeSkinning {
+    /// Where the node's skin starts in the joint matrices, if it has one.
+    skin_offset: Option<u32>,
+    /// The joint of the node itself.
+    node_joint: u32,
+}
+
+fn read_file(source: &dyn AssetSource, path: &Path) -> Result<Vec<u8>, GltfError> {
+    source.read(path).map_err(|err| GltfError::Io {
+        path: path.to_owned(),
+        source: err,
+    })
+}
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F534A;
+const CHUNK_BIN: u32 = 0x004E4942;
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// The JSON chunk and the binary chunk if there is one.
+fn split_glb(bytes: &[u8]) -> Result<(&str, Option<&[u8]>), GltfError> {
+    let version = u32_at(bytes, 4).ok_or(GltfError::Glb("truncated header"))?;
+    if version != 2 {
+        return Err(GltfError::Glb("not version 2"));
+    }
+    let length = u32_at(bytes, 8).ok_or(GltfError::Glb("truncated header"))? as usize;
+    let bytes = bytes
+        .get(..length)
+        .ok_or(GltfError::Glb("shorter than its header says"))?;
+
+    let mut json = None;
+    let mut binary = None;
+    let mut offset = 12;
+    while offset < bytes.len() {
+        let chunk_length = u32_at(bytes, offset).ok_or(GltfError::Glb("truncated chunk"))? as usize;
+        let chunk_type = u32_at(bytes, offset + 4).ok_or(GltfError::Glb("truncated chunk"))?;
+        let data = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or(GltfError::Glb("truncated chunk"))?;
+        match chunk_type {
+            CHUNK_JSON if json.is_none() => json = Some(data),
+            CHUNK_BIN if binary.is_none() => binary = Some(data),
+            // Unknown chunks are to be ignored
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+
+    let json = json.ok_or(GltfError::Glb("no JSON chunk"))?;
+    let json = std::str::from_utf8(json).map_err(|_| GltfError::Glb("the JSON isn't UTF-8"))?;
+    Ok((json, binary))
+}
+
+/// The elements of the array `key` of `value`, none if it's missing.
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value.get(key).and_then(Value::as_array).unwrap_or(&[])
+}
+
+fn usize_member(value: &Value, key: &str) -> Option<usize> {
+    value.get(key).and_then(Value::as_usize)
+}
+
+fn f32_member(value: &Value, key: &str) -> Option<f32> {
+    value
+        .get(key)
+        .and_then(Value::as_f64)
+        .map(|number| number as f32)
+}
+
+/// An array of exactly `N` numbers.
+fn f32_array<const N: usize>(value: &Value, key: &str) -> Option<[f32; N]> {
+    let values = value.get(key)?.as_array()?;
+    if values.len() != N {
+        return None;
+    }
+    let mut array = [0.0; N];
+    for (element, value) in array.iter_mut().zip(values) {
+        *element = value.as_f64()? as f32;
+    }
+    Some(array)
+}
+
+fn load_buffer(
+    index: usize,
+    buffer: &Value,
+    binary_chunk: Option<&[u8]>,
+    source: &dyn AssetSource,
+    base_dir: &Path,
+) -> Result<Vec<u8>, GltfError> {
+    let length = usize_member(buffer, "byteLength")
+        .ok_or_else(|| invalid(format!("buffer {index} has no byteLength")))?;
+    let data = match buffer.get("uri").and_then(Value::as_str) {
+        Some(uri) => load_uri(uri, source, base_dir)?,
+        // Only the first buffer of a GLB file may be its binary chunk
+        None if index == 0 => binary_chunk
+            .ok_or_else(|| invalid("buffer 0 has no URI and there's no binary chunk"))?
+            .to_vec(),
+        None => return Err(invalid(format!("buffer {index} has no URI"))),
+    };
+    // The binary chunk may be padded, but never shorter
+    if data.len() < length {
+        return Err(invalid(format!(
+            "buffer {index} has {} bytes instead of {length}",
+            data.len()
+        )));
+    }
+    Ok(data)
+}
+
+/// Data URIs are decoded, other URIs are paths relative to `base_dir`.
+fn load_uri(uri: &str, source: &dyn AssetSource, base_dir: &Path) -> Result<Vec<u8>, GltfError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data
+            .split_once(";base64,")
+            .ok_or_else(|| invalid("only base64 data URIs are supported"))?;
+        return decode_base64(encoded).ok_or_else(|| invalid("invalid base64 in a data URI"));
+    }
+    read_file(source, &base_dir.join(decode_percent(uri)))
+}
+
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3);
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+    for byte in encoded.bytes().take_while(|&byte| byte != b'=') {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6) | u32::from(value);
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            decoded.push((bits >> bit_count) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+/// URIs escape spaces and the like, e.g. "my%20texture.png".
+fn decode_percent(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// How a node transforms its children and meshes.
+fn local_transform(node: &Value) -> Mat4 {
+    if let Some(matrix) = f32_array::<16>(node, "matrix") {
+        // Column-major, like glam
+        return Mat4::from_cols_array(&matrix);
+    }
+    node_transform(node).matrix()
+}
+
+/// `local_transform` taken apart, for animating it.
+fn node_transform(node: &Value) -> Transform {
+    if let Some(matrix) = f32_array::<16>(node, "matrix") {
+        let (scale, rotation, translation) =
+            Mat4::from_cols_array(&matrix).to_scale_rotation_translation();
+        return Transform {
+            translation,
+            rotation,
+            scale,
+        };
+    }
+    Transform {
+        translation: f32_array(node, "translation").map_or(Vec3::ZERO, Vec3::from),
+        rotation: f32_array(node, "rotation").map_or(Quat::IDENTITY, |[x, y, z, w]| {
+            Quat::from_xyzw(x, y, z, w).normalize()
+        }),
+        scale: f32_array(node, "scale").map_or(Vec3::ONE, Vec3::from),
+    }
+}
+
+const COMPONENT_I8: usize = 5120;
+const COMPONENT_U8: usize = 5121;
+const COMPONENT_I16: usize = 5122;
+const COMPONENT_U16: usize = 5123;
+const COMPONENT_U32: usize = 5125;
+const COMPONENT_F32: usize = 5126;
+
+fn component_size(component_type: usize) -> Option<usize> {
+    match component_type {
+        COMPONENT_I8 | COMPONENT_U8 => Some(1),
+        COMPONENT_I16 | COMPONENT_U16 => Some(2),
+        COMPONENT_U32 | COMPONENT_F32 => Some(4),
+        _ => None,
+    }
+}
+
+fn component_count(accessor_type: &str) -> Option<usize> {
+    match accessor_type {
+        "SCALAR" => Some(1),
+        "VEC2" => Some(2),
+        "VEC3" => Some(3),
+        "VEC4" | "MAT2" => Some(4),
+        "MAT3" => Some(9),
+        "MAT4" => Some(16),
+        _ => None,
+    }
+}
+
+/// Reads one component. Normalized integers map to 0 to 1, or -1 to 1 when
+/// signed.
+fn read_component(bytes: &[u8], component_type: usize, normalized: bool) -> f64 {
+    let value = match component_type {
+        COMPONENT_I8 => f64::from(bytes[0] as i8),
+        COMPONENT_U8 => f64::from(bytes[0]),
+        COMPONENT_I16 => f64::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+        COMPONENT_U16 => f64::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+        COMPONENT_U32 => f64::from(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        _ => f64::from(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+    };
+    if !normalized {
+        return value;
+    }
+    match component_type {
+        COMPONENT_I8 => (value / 127.0).max(-1.0),
+        COMPONENT_U8 => value / 255.0,
+        COMPONENT_I16 => (value / 32767.0).max(-1.0),
+        COMPONENT_U16 => value / 65535.0,
+        _ => value,
+    }
+}
+
+/// The elements of an accessor, `components` numbers each.
+struct AccessorData {
+    components: usize,
+    values: Vec<f64>,
+}
+
+impl AccessorData {
+    fn len(&self) -> usize {
+        self.values.len() / self.components
+    }
+
+    fn vec2(&self, index: usize) -> [f32; 2] {
+        let i = index * self.components;
+        [self.values[i] as f32, self.values[i + 1] as f32]
+    }
+
+    fn vec3(&self, index: usize) -> [f32; 3] {
+        let i = index * self.components;
+        [
+            self.values[i] as f32,
+            self.values[i + 1] as f32,
+            self.values[i + 2] as f32,
+        ]
+    }
+
+    fn vec4(&self, index: usize) -> [f32; 4] {
+        let i = index * self.components;
+        [
+            self.values[i] as f32,
+            self.values[i + 1] as f32,
+            self.values[i + 2] as f32,
+            self.values[i + 3] as f32,
+        ]
+    }
+}
+
+/// Where the buffers come from, for reading what the JSON points into.
+struct Gltf<'a> {
+    root: &'a Value,
+    buffers: Vec<Vec<u8>>,
+    source: &'a dyn AssetSource,
+    base_dir: &'a Path,
+}
+
+impl Gltf<'_> {
+    /// The bytes of a buffer view.
+    fn view(&self, index: usize) -> Result<(&[u8], Option<usize>), GltfError> {
+        let view = array(self.root, "bufferViews")
+            .get(index)
+            .ok_or_else(|| invalid(format!("buffer view {index} doesn't exist")))?;
+        let buffer = usize_member(view, "buffer")
+            .and_then(|buffer| self.buffers.get(buffer))
+            .ok_or_else(|| invalid(format!("buffer view {index} has no valid buffer")))?;
+        let offset = usize_member(view, "byteOffset").unwrap_or(0);
+        let length = usize_member(view, "byteLength")
+            .ok_or_else(|| invalid(format!("buffer view {index} has no byteLength")))?;
+        let bytes = offset
+            .checked_add(length)
+            .and_then(|end| buffer.get(offset..end))
+            .ok_or_else(|| invalid(format!("buffer view {index} is out of its buffer")))?;
+        Ok((bytes, usize_member(view, "byteStride")))
+    }
+
+    /// `count` elements of `components` components from a buffer view.
+    fn read_elements(
+        &self,
+        view_index: usize,
+        offset: usize,
+        count: usize,
+        component_type: usize,
+        components: usize,
+        normalized: bool,
+    ) -> Result<Vec<f64>, GltfError> {
+        let (bytes, stride) = self.view(view_index)?;
+        let size = component_size(component_type)
+            .ok_or_else(|| invalid(format!("unknown component type {component_type}")))?;
+        let element_size = size * components;
+        let stride = stride.unwrap_or(element_size);
+        if count > 0 && offset + (count - 1) * stride + element_size > bytes.len() {
+            return Err(invalid(format!(
+                "{count} elements don't fit into buffer view {view_index}"
+            )));
+        }
+
+        let mut values = Vec::with_capacity(count * components);
+        for element in 0..count {
+            let start = offset + element * stride;
+            for component in 0..components {
+                let at = start + component * size;
+                values.push(read_component(
+                    &bytes[at..at + size],
+                    component_type,
+                    normalized,
+                ));
+            }
+        }
+        Ok(values)
+    }
+
+    /// Reads a whole accessor, with its sparse values swapped in.
+    fn accessor(&self, index: usize) -> Result<AccessorData, GltfError> {
+        let accessor = array(self.root, "accessors")
+            .get(index)
+            .ok_or_else(|| invalid(format!("accessor {index} doesn't exist")))?;
+        let count = usize_member(accessor, "count")
+            .ok_or_else(|| invalid(format!("accessor {index} has no count")))?;
+        let component_type = usize_member(accessor, "componentType")
+            .ok_or_else(|| invalid(format!("accessor {index} has no componentType")))?;
+        let components = accessor
+            .get("type")
+            .and_then(Value::as_str)
+            .and_then(component_count)
+            .ok_or_else(|| invalid(format!("accessor {index} has no valid type")))?;
+        let normalized = accessor
+            .get("normalized")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        // Without a buffer view everything is 0, unless sparse says otherwise
+        let mut values = match usize_member(accessor, "bufferView") {
+            Some(view) => self.read_elements(
+                view,
+                usize_member(accessor, "byteOffset").unwrap_or(0),
+                count,
+                component_type,
+                components,
+                normalized,
+            )?,
+            None => vec![0.0; count * components],
+        };
+
+        if let Some(sparse) = accessor.get("sparse") {
+            let sparse_count = usize_member(sparse, "count")
+                .ok_or_else(|| invalid(format!("sparse accessor {index} has no count")))?;
+            let (indices, sparse_values) = sparse
+                .get("indices")
+                .zip(sparse.get("values"))
+                .ok_or_else(|| invalid(format!("sparse accessor {index} is incomplete")))?;
+            let index_view = usize_member(indices, "bufferView")
+                .ok_or_else(|| invalid(format!("sparse accessor {index} has no indices")))?;
+            let value_view = usize_member(sparse_values, "bufferView")
+                .ok_or_else(|| invalid(format!("sparse accessor {index} has no values")))?;
+            let indices = self.read_elements(
+                index_view,
+                usize_member(indices, "byteOffset").unwrap_or(0),
+                sparse_count,
+                usize_member(indices, "componentType").unwrap_or(COMPONENT_U32),
+                1,
+                false,
+            )?;
+            let replacements = self.read_elements(
+                value_view,
+                usize_member(sparse_values, "byteOffset").unwrap_or(0),
+                sparse_count,
+                component_type,
+                components,
+                normalized,
+            )?;
+            for (element, replacement) in indices
+                .into_iter()
+                .zip(replacements.chunks_exact(components))
+            {
+                let start = element as usize * components;
+                values
+                    .get_mut(start..start + components)
+                    .ok_or_else(|| invalid(format!("sparse accessor {index} is out of range")))?
+                    .copy_from_slice(replacement);
+            }
+        }
+
+        Ok(AccessorData { components, values })
+    }
+
+    /// Every node with its parent and rest transform. A node can only have
+    /// one parent, and never be its own ancestor.
+    fn nodes(&self) -> Result<Vec<Node>, GltfError> {
+        let nodes = array(self.root, "nodes");
+        let mut parents = vec![None; nodes.len()];
+        for (index, node) in nodes.iter().enumerate() {
+            for child in array(node, "children") {
+                let parent = child
+                    .as_usize()
+                    .and_then(|child| parents.get_mut(child))
+                    .ok_or_else(|| invalid(format!("node {index} has an invalid child")))?;
+                if parent.replace(index).is_some() {
+                    return Err(invalid(format!("node {index} shares a child")));
+                }
+            }
+        }
+        for start in 0..nodes.len() {
+            let mut node = start;
+            for _ in 0..=nodes.len() {
+                match parents[node] {
+                    Some(parent) => node = parent,
+                    None => break,
+                }
+            }
+            if parents[node].is_some() {
+                return Err(invalid(format!("node {start} is its own ancestor")));
+            }
+        }
+
+        Ok(nodes
+            .iter()
+            .zip(parents)
+            .enumerate()
+            .map(|(index, (node, parent))| Node {
+                name: node
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .map_or_else(|| format!("node{index}"), str::to_owned),
+                parent,
+                rest: node_transform(node),
+            })
+            .collect())
+    }
+
+    fn skins(&self, node_count: usize) -> Result<Vec<Skin>, GltfError> {
+        array(self.root, "skins")
+            .iter()
+            .enumerate()
+            .map(|(index, skin)| {
+                let joints = array(skin, "joints")
+                    .iter()
+                    .map(|joint| joint.as_usize().filter(|&joint| joint < node_count))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| invalid(format!("skin {index} has an invalid joint")))?;
+                // Without them the joints were modeled where they are
+                let inverse_bind_matrices = match usize_member(skin, "inverseBindMatrices") {
+                    Some(accessor) => {
+                        let matrices = self.accessor(accessor)?;
+                        if matrices.components != 16 || matrices.len() < joints.len() {
+                            return Err(invalid(format!(
+                                "skin {index} has too few inverse bind matrices"
+                            )));
+                        }
+                        matrices
+                            .values
+                            .chunks_exact(16)
+                            .map(|matrix| {
+                                Mat4::from_cols_array(&std::array::from_fn(|i| matrix[i] as f32))
+                            })
+                            .collect()
+                    }
+                    None => vec![Mat4::IDENTITY; joints.len()],
+                };
+                Ok(Skin {
+                    joints,
+                    inverse_bind_matrices,
+                })
+            })
+            .collect()
+    }
+
+    /// The animations, only the ones of nodes' transforms. Morph target
+    /// weights get skipped, cubic splines are played linearly.
+    fn clips(&self) -> Result<Vec<Clip>, GltfError> {
+        let node_count = array(self.root, "nodes").len();
+        let mut clips = Vec::new();
+        for (index, animation) in array(self.root, "animations").iter().enumerate() {
+            let name = animation
+                .get("name")
+                .and_then(Value::as_str)
+                .map_or_else(|| format!("animation{index}"), str::to_owned);
+            let samplers = array(animation, "samplers");
+            let mut channels = Vec::new();
+            for channel in array(animation, "channels") {
+                let target = channel
+                    .get("target")
+                    .ok_or_else(|| invalid(format!("{name} has a channel without a target")))?;
+                let Some(node) = usize_member(target, "node").filter(|&node| node < node_count)
+                else {
+                    log::warn!("Skipping a channel of {name} without a valid node");
+                    continue;
+                };
+                let path = target.get("path").and_then(Value::as_str).unwrap_or("");
+                if !matches!(path, "translation" | "rotation" | "scale") {
+                    log::warn!("Skipping the {path:?} channel of {name}");
+                    continue;
+                }
+                let sampler = usize_member(channel, "sampler")
+                    .and_then(|sampler| samplers.get(sampler))
+                    .ok_or_else(|| invalid(format!("{name} has a channel without a sampler")))?;
+                let (input, output) = usize_member(sampler, "input")
+                    .zip(usize_member(sampler, "output"))
+                    .ok_or_else(|| invalid(format!("{name} has an incomplete sampler")))?;
+                let times: Vec<f32> = self
+                    .accessor(input)?
+                    .values
+                    .into_iter()
+                    .map(|time| time as f32)
+                    .collect();
+                let output = self.accessor(output)?;
+
+                let interpolation = sampler.get("interpolation").and_then(Value::as_str);
+                let (interpolation, stride, skip) = match interpolation {
+                    Some("STEP") => (Interpolation::Step, 1, 0),
+                    // In-tangent, value and out-tangent for every keyframe,
+                    // only the values are kept
+                    Some("CUBICSPLINE") => {
+                        log::warn!("{name} has cubic splines, playing them linearly");
+                        (Interpolation::Linear, 3, 1)
+                    }
+                    _ => (Interpolation::Linear, 1, 0),
+                };
+                if output.len() != times.len() * stride {
+                    return Err(invalid(format!(
+                        "{name} has {} keyframes for {} times",
+                        output.len() / stride,
+                        times.len()
+                    )));
+                }
+                let keys = (0..times.len()).map(|i| i * stride + skip);
+                let keyframes = match path {
+                    "translation" if output.components == 3 => {
+                        Keyframes::Translation(keys.map(|i| Vec3::from(output.vec3(i))).collect())
+                    }
+                    "scale" if output.components == 3 => {
+                        Keyframes::Scale(keys.map(|i| Vec3::from(output.vec3(i))).collect())
+                    }
+                    "rotation" if output.components == 4 => Keyframes::Rotation(
+                        keys.map(|i| Quat::from_array(output.vec4(i)).normalize())
+                            .collect(),
+                    ),
+                    _ => {
+                        return Err(invalid(format!(
+                            "{name} animates {path} with the wrong type"
+                        )))
+                    }
+                };
+                channels.push(Channel {
+                    node,
+                    times,
+                    keyframes,
+                    interpolation,
+                });
+            }
+            clips.push(Clip { name, channels });
+        }
+        Ok(clips)
+    }
+
+    /// Every node with a mesh, with its world transform. Only the nodes of
+    /// the default scene count, or of the first one if there's no default.
+    /// Without scenes every node that isn't a child is a root.
+    fn mesh_nodes(&self) -> Result<Vec<(usize, &Value, Mat4)>, GltfError> {
+        let nodes = array(self.root, "nodes");
+        let scenes = array(self.root, "scenes");
+        let roots: Vec<usize> = if scenes.is_empty() {
+            let children: Vec<usize> = nodes
+                .iter()
+                .flat_map(|node| array(node, "children"))
+                .filter_map(Value::as_usize)
+                .collect();
+            (0..nodes.len())
+                .filter(|node| !children.contains(node))
+                .collect()
+        } else {
+            let scene = usize_member(self.root, "scene").unwrap_or(0);
+            let scene = scenes
+                .get(scene)
+                .ok_or_else(|| invalid(format!("scene {scene} doesn't exist")))?;
+            array(scene, "nodes")
+                .iter()
+                .filter_map(Value::as_usize)
+                .collect()
+        };
+
+        let mut mesh_nodes = Vec::new();
+        // Only ever visited once, so a cycle can't hang us
+        let mut visited = vec![false; nodes.len()];
+        let mut stack: Vec<(usize, Mat4)> = roots
+            .into_iter()
+            .rev()
+            .map(|root| (root, Mat4::IDENTITY))
+            .collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = nodes
+                .get(index)
+                .ok_or_else(|| invalid(format!("node {index} doesn't exist")))?;
+            if std::mem::replace(&mut visited[index], true) {
+                return Err(invalid(format!("node {index} is in the hierarchy twice")));
+            }
+            let transform = parent * local_transform(node);
+            if node.get("mesh").is_some() {
+                mesh_nodes.push((index, node, transform));
+            }
+            for child in array(node, "children").iter().rev() {
+                let child = child
+                    .as_usize()
+                    .ok_or_else(|| invalid(format!("node {index} has an invalid child")))?;
+                stack.push((child, transform));
+            }
+        }
+        Ok(mesh_nodes)
+    }
+
+    /// Adds the primitives of a mesh drawn with `transform` to `primitives`.
+    fn primitives(
+        &self,
+        mesh_index: usize,
+        mesh: &Value,
+        transform: Mat4,
+        skinning: Option<NodeSkinning>,
+        primitives: &mut Vec<Primitive>,
+    ) -> Result<(), GltfError> {
+        let mesh_name = mesh
+            .get("name")
+            .and_then(Value::as_str)
+            .map_or_else(|| format!("mesh{mesh_index}"), str::to_owned);
+        let mesh_primitives = array(mesh, "primitives");
+        for (index, primitive) in mesh_primitives.iter().enumerate() {
+            let name = if mesh_primitives.len() == 1 {
+                mesh_name.clone()
+            } else {
+                format!("{mesh_name}.{index}")
+            };
+            match self.primitive(&name, primitive, transform, skinning)? {
+                Some(primitive) => primitives.push(primitive),
+                None => continue,
+            }
+        }
+        Ok(())
+    }
+
+    /// `None` for the primitives we can't draw, like points and lines.
+    fn primitive(
+        &self,
+        name: &str,
+        primitive: &Value,
+        transform: Mat4,
+        skinning: Option<NodeSkinning>,
+    ) -> Result<Option<Primitive>, GltfError> {
+        const TRIANGLES: usize = 4;
+        const TRIANGLE_STRIP: usize = 5;
+        const TRIANGLE_FAN: usize = 6;
+        let mode = usize_member(primitive, "mode").unwrap_or(TRIANGLES);
+        if !matches!(mode, TRIANGLES | TRIANGLE_STRIP | TRIANGLE_FAN) {
+            log::warn!("Skipping {name}, only triangles are supported");
+            return Ok(None);
+        }
+
+        let attributes = primitive
+            .get("attributes")
+            .ok_or_else(|| invalid(format!("{name} has no attributes")))?;
+        // The type is checked, reading the vertices relies on it
+        let attribute = |key, expected_type| {
+            usize_member(attributes, key)
+                .map(|accessor| {
+                    let accessor_type = array(self.root, "accessors")
+                        .get(accessor)
+                        .and_then(|accessor| accessor.get("type"))
+                        .and_then(Value::as_str);
+                    if accessor_type.is_some_and(|accessor_type| accessor_type != expected_type) {
+                        return Err(invalid(format!("{name}'s {key} isn't a {expected_type}")));
+                    }
+                    self.accessor(accessor)
+                })
+                .transpose()
+        };
+        let Some(positions) = attribute("POSITION", "VEC3")? else {
+            log::warn!("Skipping {name}, it has no positions");
+            return Ok(None);
+        };
+        let normals = attribute("NORMAL", "VEC3")?;
+        let tex_coords = attribute("TEXCOORD_0", "VEC2")?;
+        // Skinned primitives are moved by their joints alone, their node's
+        // transform doesn't count
+        let skin = match skinning.and_then(|skinning| skinning.skin_offset) {
+            Some(offset) => attribute("JOINTS_0", "VEC4")?
+                .zip(attribute("WEIGHTS_0", "VEC4")?)
+                .map(|skin| (offset, skin)),
+            None => None,
+        };
+        let transform = if skin.is_some() {
+            Mat4::IDENTITY
+        } else {
+            transform
+        };
+        let skin_data = skin
+            .as_ref()
+            .map(|(_, (joints, weights))| [joints, weights]);
+        for (attribute, data) in [
+            ("NORMAL", normals.as_ref()),
+            ("TEXCOORD_0", tex_coords.as_ref()),
+            ("JOINTS_0", skin_data.map(|[joints, _]| joints)),
+            ("WEIGHTS_0", skin_data.map(|[_, weights]| weights)),
+        ] {
+            if data.is_some_and(|data| data.len() != positions.len()) {
+                return Err(invalid(format!("{name} has too few or many {attribute}")));
+            }
+        }
+
+        let indices: Vec<u32> = match usize_member(primitive, "indices") {
+            Some(accessor) => self
+                .accessor(accessor)?
+                .values
+                .into_iter()
+                .map(|index| index as u32)
+                .collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        if indices
+            .iter()
+            .any(|&index| index as usize >= positions.len())
+        {
+            return Err(invalid(format!("{name} has an index out of range")));
+        }
+        let mut indices = match mode {
+            TRIANGLE_STRIP => strip_to_list(&indices),
+            TRIANGLE_FAN => fan_to_list(&indices),
+            _ => indices,
+        };
+        indices.truncate(indices.len() / 3 * 3);
+
+        // Normals need the inverse transpose to stay perpendicular to their
+        // surface when the transform scales unevenly
+        let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+        let mut vertices: Vec<ModelVertex> = (0..positions.len())
+            .map(|i| ModelVertex {
+                position: transform
+                    .transform_point3(Vec3::from(positions.vec3(i)))
+                    .into(),
+                // glTF has the origin of the texture coordinates at the top
+                // left like wgpu, no need to flip them
+                tex_coords: tex_coords.as_ref().map_or([0.0; 2], |uvs| uvs.vec2(i)),
+                normal: normals.as_ref().map_or([0.0; 3], |normals| {
+                    (normal_matrix * Vec3::from(normals.vec3(i)))
+                        .normalize_or_zero()
+                        .into()
+                }),
+                tangent: [0.0; 3],
+                bitangent: [0.0; 3],
+            })
+            .collect();
+
+        let mut skin_vertices = match (skinning, &skin) {
+            (_, Some((offset, (joints, weights)))) => (0..positions.len())
+                .map(|i| skin_vertex(*offset, joints, weights, i))
+                .collect::<Result<_, _>>()
+                .map_err(|joint| invalid(format!("{name} has joint {joint} out of range")))?,
+            (Some(skinning), None) => vec![
+                SkinVertex {
+                    joints: [skinning.node_joint, 0, 0, 0],
+                    weights: [1.0, 0.0, 0.0, 0.0],
+                };
+                positions.len()
+            ],
+            (None, None) => Vec::new(),
+        };
+
+        // Mirroring transforms turn the triangles inside out
+        if transform.determinant() < 0.0 {
+            for triangle in indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+        if normals.is_none() {
+            vertices = unweld(&vertices, &indices);
+            if !skin_vertices.is_empty() {
+                skin_vertices = unweld(&skin_vertices, &indices);
+            }
+            indices = (0..vertices.len() as u32).collect();
+            set_flat_normals(&mut vertices);
+        }
+        compute_tangents(&mut vertices, &indices);
+
+        Ok(Some(Primitive {
+            name: name.to_owned(),
+            vertices,
+            indices,
+            material: usize_member(primitive, "material"),
+            skin_vertices,
+        }))
+    }
+
+    fn material(
+        &self,
+        index: usize,
+        material: &Value,
+        images: &mut HashMap<usize, Option<RgbaImage>>,
+    ) -> MaterialData {
+        let name = material
+            .get("name")
+            .and_then(Value::as_str)
+            .map_or_else(|| format!("material{index}"), str::to_owned);
+        let texture = |info: Option<&Value>, images: &mut HashMap<_, _>| {
+            let texture = usize_member(info?, "index")?;
+            self.texture_image(texture, images)
+        };
+
+        let pbr = material.get("pbrMetallicRoughness");
+        let base_color_factor = pbr
+            .and_then(|pbr| f32_array::<4>(pbr, "baseColorFactor"))
+            .unwrap_or([1.0; 4]);
+        let base_color = texture(pbr.and_then(|pbr| pbr.get("baseColorTexture")), images);
+        let base_color = apply_factor(base_color, base_color_factor, |pixel, factor| {
+            for channel in 0..3 {
+                let linear = color::srgb_to_linear(f64::from(pixel[channel]) / 255.0);
+                pixel[channel] = to_u8(color::linear_to_srgb(linear * f64::from(factor[channel])));
+            }
+            pixel[3] = to_u8(f64::from(pixel[3]) / 255.0 * f64::from(factor[3]));
+        });
+
+        // No pbrMetallicRoughness at all leaves the default smooth
+        // dielectric, the factors only default to 1 inside of it
+        let metallic_roughness = pbr.and_then(|pbr| {
+            let metallic = f32_member(pbr, "metallicFactor").unwrap_or(1.0);
+            let roughness = f32_member(pbr, "roughnessFactor").unwrap_or(1.0);
+            let image = texture(pbr.get("metallicRoughnessTexture"), images)
+                .unwrap_or_else(|| RgbaImage::from_pixel(1, 1, image::Rgba([0, 255, 255, 255])));
+            apply_factor(
+                Some(image),
+                [1.0, roughness, metallic, 1.0],
+                |pixel, factor| {
+                    for channel in 1..3 {
+                        pixel[channel] =
+                            to_u8(f64::from(pixel[channel]) / 255.0 * f64::from(factor[channel]));
+                    }
+                },
+            )
+        });
+
+        let normal = texture(material.get("normalTexture"), images);
+
+        MaterialData {
+            name,
+            base_color,
+            metallic_roughness,
+            normal,
+        }
+    }
+
+    /// The decoded image of a texture, each image only gets decoded once.
+    /// Images that can't be loaded are left out with a warning, like the
+    /// textures of OBJ materials.
+    fn texture_image(
+        &self,
+        texture: usize,
+        images: &mut HashMap<usize, Option<RgbaImage>>,
+    ) -> Option<RgbaImage> {
+        let source = array(self.root, "textures")
+            .get(texture)
+            .and_then(|texture| usize_member(texture, "source"))?;
+        images
+            .entry(source)
+            .or_insert_with(|| {
+                self.load_image(source)
+                    .inspect_err(|err| {
+                        log::warn!("Failed to load glTF image {source}, using a placeholder: {err}")
+                    })
+                    .ok()
+            })
+            .clone()
+    }
+
+    fn load_image(&self, index: usize) -> Result<RgbaImage, Box<dyn std::error::Error>> {
+        let image = array(self.root, "images")
+            .get(index)
+            .ok_or_else(|| invalid("it doesn't exist"))?;
+        let bytes = match (
+            image.get("uri").and_then(Value::as_str),
+            usize_member(image, "bufferView"),
+        ) {
+            (Some(uri), _) => load_uri(uri, self.source, self.base_dir)?,
+            (None, Some(view)) => self.view(view)?.0.to_vec(),
+            (None, None) => return Err(invalid("it has neither a URI nor a buffer view").into()),
+        };
+        Ok(image::load_from_memory(&bytes)?.to_rgba8())
+    }
+}
+
+fn to_u8(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Multiplies `factor` into every pixel of `image` with `apply`. Without an
+/// image a factor other than 1 gets a 1x1 image of its own.
+fn apply_factor(
+    image: Option<RgbaImage>,
+    factor: [f32; 4],
+    apply: impl Fn(&mut image::Rgba<u8>, [f32; 4]),
+) -> Option<RgbaImage> {
+    let image = match image {
+        Some(image) => image,
+        None if factor == [1.0; 4] => return None,
+        None => RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])),
+    };
+    if factor == [1.0; 4] {
+        return Some(image);
+    }
+    let mut image = image;
+    for pixel in image.pixels_mut() {
+        apply(pixel, factor);
+    }
+    Some(image)
+}
+
+fn strip_to_list(strip: &[u32]) -> Vec<u32> {
+    (2..strip.len())
+        .flat_map(|i| {
+            // Every other triangle is wound the other way
+            if i % 2 == 0 {
+                [strip[i - 2], strip[i - 1], strip[i]]
+            } else {
+                [strip[i - 1], strip[i - 2], strip[i]]
+            }
+        })
+        .collect()
+}
+
+fn fan_to_list(fan: &[u32]) -> Vec<u32> {
+    (2..fan.len())
+        .flat_map(|i| [fan[0], fan[i - 1], fan[i]])
+        .collect()
+}
+
+/// The skin of vertex `i`, with the joints moved to where the skin starts
+/// in the joint matrices. Errors with the joint when it's out of range.
+fn skin_vertex(
+    offset: u32,
+    joints: &AccessorData,
+    weights: &AccessorData,
+    i: usize,
+) -> Result<SkinVertex, usize> {
+    let joint_indices = joints.vec4(i).map(|joint| joint as usize);
+    let mut weights = weights.vec4(i);
+    // Exporters don't always get them to add up to exactly 1
+    let sum: f32 = weights.iter().sum();
+    if sum > 0.0 {
+        weights = weights.map(|weight| weight / sum);
+    } else {
+        weights = [1.0, 0.0, 0.0, 0.0];
+    }
+    let mut joints = [0; 4];
+    for ((joint, index), weight) in joints.iter_mut().zip(joint_indices).zip(weights) {
+        // Joints without weight may point anywhere
+        *joint = if weight > 0.0 {
+            offset + u32::try_from(index).map_err(|_| index)?
+        } else {
+            offset
+        };
+    }
+    Ok(SkinVertex { joints, weights })
+}
+
+/// Gives every triangle its own copy of its vertices.
+fn unweld<T: Copy>(vertices: &[T], indices: &[u32]) -> Vec<T> {
+    indices
+        .iter()
+        .map(|&index| vertices[index as usize])
+        .collect()
+}
+
+/// Gives the vertices of every triangle of an unwelded triangle list the
+/// normal of the triangle, for meshes that come without normals.
+fn set_flat_normals(vertices: &mut [ModelVertex]) {
+    for triangle in vertices.chunks_exact_mut(3) {
+        let [p0, p1, p2] = [0, 1, 2].map(|i| Vec3::from(triangle[i].position));
+        // Counter-clockwise triangles face us, like the pipelines expect
+        let normal = (p1 - p0).cross(p2 - p0).normalize_or_zero();
+        for vertex in triangle {
+            vertex.normal = normal.into();
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Reporting GPU errors no error scope caught, along with what the app was
+//! doing when they happened, and noticing when the device gets lost.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+#[derive(Debug, Default)]
+struct Progress {
+    frame: u64,
+    scope: Option<&'static str>,
+}
+
+/// What the app is doing, shared with the uncaptured error handler so its
+/// messages can say which frame and which part of it went wrong.
+#[derive(Clone, Debug, Default)]
+pub struct ErrorContext(Arc<Mutex<Progress>>);
+
+impl ErrorContext {
+    pub fn set_frame(&self, frame: u64) {
+        self.0.lock().unwrap().frame = frame;
+    }
+
+    /// Labels what happens until the guard is dropped, e.g. "render pass".
+    /// The label from before comes back then, so scopes can be nested.
+    pub fn scope(&self, label: &'static str) -> ScopeGuard {
+        let previous = self.0.lock().unwrap().scope.replace(label);
+        ScopeGuard {
+            context: self.clone(),
+            previous,
+        }
+    }
+
+    /// The current scope label, `None` outside of scopes.
+    pub fn current_scope(&self) -> Option<&'static str> {
+        self.0.lock().unwrap().scope
+    }
+
+    /// Where an error happening now would have happened, like
+    /// "in frame 12 during render pass".
+    pub fn describe(&self) -> String {
+        let progress = self.0.lock().unwrap();
+        match progress.scope {
+            Some(scope) => format!("in frame {} during {scope}", progress.frame),
+            None => format!("in frame {}", progress.frame),
+        }
+    }
+}
+
+/// Restores the previous scope label of an `ErrorContext` when dropped.
+#[must_use = "the scope ends when the guard is dropped"]
+pub struct ScopeGuard {
+    context: ErrorContext,
+    previous: Option<&'static str>,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        self.context.0.lock().unwrap().scope = self.previous;
+    }
+}
+
+/// The uncaptured error and device lost handlers of a device, see `install`.
+pub struct GpuErrorHandlers {
+    context: ErrorContext,
+    error_count: Arc<AtomicU64>,
+    /// What wgpu said when it lost the device.
+    lost: Arc<Mutex<Option<String>>>,
+}
+
+impl GpuErrorHandlers {
+    /// Replaces wgpu's default handler, which panics without saying where
+    /// the error came from. Uncaptured errors get logged with their context
+    /// instead, or still panic with the `panic-on-gpu-error` feature so tests
+    /// fail on them.
+    pub fn install(device: &wgpu::Device) -> Self {
+        let context = ErrorContext::default();
+        let error_count = Arc::new(AtomicU64::new(0));
+        let lost = Arc::new(Mutex::new(None));
+
+        let handler_context = context.clone();
+        let handler_count = Arc::clone(&error_count);
+        device.on_uncaptured_error(Box::new(move |error| {
+            handler_count.fetch_add(1, Ordering::Relaxed);
+            let message = format!(
+                "Uncaptured GPU error {}: {error}",
+                handler_context.describe()
+            );
+            if cfg!(feature = "panic-on-gpu-error") {
+                panic!("{message}");
+            }
+            log::error!("{message}");
+        }));
+
+        // wgpu also calls this when the device gets dropped, which is why
+        // it's only logged once somebody checks
+        let lost_message = Arc::clone(&lost);
+        device.set_device_lost_callback(move |reason, message| {
+            *lost_message.lock().unwrap() = Some(format!("{message} ({reason:?})"));
+        });
+
+        Self {
+            context,
+            error_count,
+            lost,
+        }
+    }
+
+    pub fn context(&self) -> &ErrorContext {
+        &self.context
+    }
+
+    /// How many uncaptured errors there were so far.
+    pub fn error_count(&self) -> u64 {
+        self.error_count.load(Ordering::Relaxed)
+    }
+
+    /// Why the device was lost, `None` while it's fine.
+    pub fn device_lost(&self) -> Option<String> {
+        self.lost.lock().unwrap().clone()
+    }
+}
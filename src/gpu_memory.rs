@@ -0,0 +1,126 @@
+//! Roughly how much GPU memory this crate's buffers and textures take. Each
+//! one holds a `GpuAllocation` with its size, which adds it to the total
+//! when it's created and takes it off again when it's dropped. Drivers add
+//! padding and keep memory of their own, so the actual use is higher.
+//!
+//! The total is for the whole process, every window's resources count.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+
+/// The bytes of every `GpuAllocation` that's alive.
+pub fn allocated() -> u64 {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+/// Counts `bytes` towards `allocated` for as long as it's alive. Keep it
+/// next to the buffer or texture it's for.
+#[derive(Debug)]
+pub struct GpuAllocation {
+    bytes: u64,
+}
+
+impl GpuAllocation {
+    pub fn new(bytes: u64) -> Self {
+        ALLOCATED.fetch_add(bytes, Ordering::Relaxed);
+        Self { bytes }
+    }
+
+    pub fn buffer(buffer: &wgpu::Buffer) -> Self {
+        Self::new(buffer.size())
+    }
+
+    pub fn texture(texture: &wgpu::Texture) -> Self {
+        Self::new(texture_size(texture))
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl Drop for GpuAllocation {
+    fn drop(&mut self) {
+        ALLOCATED.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+/// How many bytes `texture` takes with all its mip levels, layers and
+/// samples.
+pub fn texture_size(texture: &wgpu::Texture) -> u64 {
+    let format = texture.format();
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = u64::from(texel_size(format));
+    let size = texture.size();
+    (0..texture.mip_level_count())
+        .map(|level| {
+            let extent = size.mip_level_size(level, texture.dimension());
+            let blocks_wide = u64::from(extent.width.div_ceil(block_width));
+            let blocks_high = u64::from(extent.height.div_ceil(block_height));
+            blocks_wide * blocks_high * u64::from(extent.depth_or_array_layers) * block_size
+        })
+        .sum::<u64>()
+        * u64::from(texture.sample_count())
+}
+
+/// The bytes of a block of `format`. Depth formats without a set size are
+/// counted as 32 bits, which is what they usually are.
+fn texel_size(format: wgpu::TextureFormat) -> u32 {
+    if let Some(size) = format.block_copy_size(None) {
+        return size;
+    }
+    let aspect = |aspect| format.block_copy_size(Some(aspect));
+    let depth = aspect(wgpu::TextureAspect::DepthOnly).unwrap_or(4);
+    let stencil = if format.has_stencil_aspect() {
+        aspect(wgpu::TextureAspect::StencilOnly).unwrap_or(1)
+    } else {
+        0
+    };
+    depth + stencil
+}
+
+/// `bytes` in binary units, e.g. "34 MiB".
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.0} {}", UNITS[unit])
+    }
+}
+
+/// The default view of a texture and its allocation, for the render targets
+/// that only keep the view around. Derefs to the view.
+#[derive(Debug)]
+pub struct TrackedView {
+    view: wgpu::TextureView,
+    allocation: GpuAllocation,
+}
+
+impl TrackedView {
+    pub fn new(texture: &wgpu::Texture) -> Self {
+        Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            allocation: GpuAllocation::texture(texture),
+        }
+    }
+
+    pub fn allocation(&self) -> &GpuAllocation {
+        &self.allocation
+    }
+}
+
+impl std::ops::Deref for TrackedView {
+    type Target = wgpu::TextureView;
+
+    fn deref(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+}
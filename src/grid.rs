@@ -0,0 +1,43 @@
+//! A ground grid on the y = 0 plane that seems to go on forever, with the
+//! origin's axes. There's no geometry: a fullscreen triangle finds where
+//! each pixel's ray hits the plane and draws the lines there analytically,
+//! see `shaders/grid.wgsl`. It writes the depth of the hit so the scene
+//! stands on the grid, and doesn't need a perspective camera to work.
+//!
+//! G toggles it, along with the debug draw's axes at the origin.
+
+use crate::{
+    pipeline::{BlendMode, PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+};
+
+pub const GRID_SHADER_SOURCE: &str = include_str!("../shaders/grid.wgsl");
+
+/// The finest the lines get, however close the camera is to the ground.
+pub const MIN_HEIGHT: f32 = 0.01;
+
+/// The lines seen from a camera `height` above or below the ground: the
+/// spacing of the fine ones, a power of ten, and how far they've faded
+/// towards the next power, from 0 to 1. The shader does the same.
+pub fn grid_spacing(height: f32) -> (f32, f32) {
+    let level = height.abs().max(MIN_HEIGHT).log10();
+    (10f32.powf(level.floor()), level.fract())
+}
+
+/// Draws the grid over the background of the scene and under whatever is
+/// blended after it. Binds the camera at group 0.
+pub fn create_pipeline(
+    device: &wgpu::Device,
+    targets: RenderTargets,
+    camera_bind_group_layout: &wgpu::BindGroupLayout,
+) -> Result<wgpu::RenderPipeline, ShaderError> {
+    PipelineBuilder::with_targets("Grid Pipeline", targets)
+        .shader("grid.wgsl", GRID_SHADER_SOURCE)
+        // Only ever drawn into the HDR target, which stays linear
+        .fragment_entry_point("fs_main")
+        .bind_group_layouts(&[camera_bind_group_layout])
+        .cull_mode(None)
+        .blend_mode(BlendMode::AlphaBlend)
+        .depth_test(wgpu::CompareFunction::LessEqual, false)
+        .build(device)
+}
@@ -0,0 +1,222 @@
+use glam::Vec2;
+use glyphon::{
+    Attrs, Buffer, Color, ColorMode, Family, FontSystem, Metrics, PrepareError, Resolution,
+    Shaping, SwashCache, TextArea, TextAtlas, TextBounds, TextRenderer,
+};
+use winit::dpi::PhysicalSize;
+
+/// Line height relative to the font size.
+pub const LINE_HEIGHT: f32 = 1.2;
+
+struct QueuedText {
+    position: Vec2,
+    text: String,
+    size: f32,
+    color: [u8; 4],
+}
+
+/// Text drawn straight onto the frame, e.g. for debug info. Queue the text
+/// for a frame with `queue_text`, then `draw` it on top of the scene.
+pub struct Hud {
+    font_system: FontSystem,
+    swash_cache: SwashCache,
+    atlas: TextAtlas,
+    renderer: TextRenderer,
+    queued: Vec<QueuedText>,
+    /// Kept between frames so their allocations get reused.
+    buffers: Vec<Buffer>,
+    size: PhysicalSize<u32>,
+    scale_factor: f64,
+}
+
+impl Hud {
+    /// `format` is the format of the texture the HUD gets drawn into.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+        scale_factor: f64,
+    ) -> Self {
+        // The font ships with egui, so there's one on every platform
+        // without loading the system fonts
+        let mut db = glyphon::fontdb::Database::new();
+        let font = egui::FontDefinitions::default()
+            .font_data
+            .remove("Hack")
+            .expect("egui has a monospace font");
+        db.load_font_data(font.font.into_owned());
+        let font_system = FontSystem::new_with_locale_and_db("en-US".to_owned(), db);
+
+        // Like the shaders, glyphon has to do the sRGB encoding itself for
+        // other formats
+        let color_mode = if format.is_srgb() {
+            ColorMode::Accurate
+        } else {
+            ColorMode::Web
+        };
+        let mut atlas = TextAtlas::with_color_mode(device, queue, format, color_mode);
+        // Drawn after the MSAA resolve like the debug overlay, so 1x and no
+        // depth buffer
+        let renderer =
+            TextRenderer::new(&mut atlas, device, wgpu::MultisampleState::default(), None);
+
+        Self {
+            font_system,
+            swash_cache: SwashCache::new(),
+            atlas,
+            renderer,
+            queued: Vec::new(),
+            buffers: Vec::new(),
+            size,
+            scale_factor,
+        }
+    }
+
+    /// Call it whenever the target is resized or the scale factor changes.
+    pub fn resize(&mut self, size: PhysicalSize<u32>, scale_factor: f64) {
+        self.size = size;
+        self.scale_factor = scale_factor;
+    }
+
+    /// The size of the target in logical pixels, the unit text gets placed
+    /// in.
+    pub fn logical_size(&self) -> Vec2 {
+        Vec2::new(self.size.width as f32, self.size.height as f32) / self.scale_factor as f32
+    }
+
+    /// Draws `text` this frame with its top left corner at `position`.
+    /// `position` and `size` are in logical pixels, so the text keeps its
+    /// size on high DPI screens. `color` is sRGB RGBA.
+    pub fn queue_text(
+        &mut self,
+        position: Vec2,
+        text: impl Into<String>,
+        size: f32,
+        color: [u8; 4],
+    ) {
+        self.queued.push(QueuedText {
+            position,
+            text: text.into(),
+            size,
+            color,
+        });
+    }
+
+    /// Records a render pass drawing the queued text on top of what's
+    /// already in `view`, then clears the queue.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let queued = std::mem::take(&mut self.queued);
+        if queued.is_empty() {
+            return;
+        }
+
+        // Shaping at the physical size rasterizes the glyphs at the size
+        // they end up on screen, which keeps them sharp at any scale factor
+        let scale = self.scale_factor as f32;
+        while self.buffers.len() < queued.len() {
+            self.buffers
+                .push(Buffer::new(&mut self.font_system, Metrics::new(1.0, 1.0)));
+        }
+        for (buffer, text) in self.buffers.iter_mut().zip(&queued) {
+            let font_size = text.size * scale;
+            buffer.set_metrics(
+                &mut self.font_system,
+                Metrics::new(font_size, font_size * LINE_HEIGHT),
+            );
+            buffer.set_size(
+                &mut self.font_system,
+                self.size.width as f32,
+                self.size.height as f32,
+            );
+            buffer.set_text(
+                &mut self.font_system,
+                &text.text,
+                Attrs::new().family(Family::Monospace),
+                Shaping::Advanced,
+            );
+            buffer.shape_until_scroll(&mut self.font_system);
+        }
+
+        let resolution = Resolution {
+            width: self.size.width,
+            height: self.size.height,
+        };
+        let text_areas = || {
+            self.buffers
+                .iter()
+                .zip(&queued)
+                .map(|(buffer, text)| TextArea {
+                    buffer,
+                    left: text.position.x * scale,
+                    top: text.position.y * scale,
+                    scale: 1.0,
+                    bounds: TextBounds::default(),
+                    default_color: Color::rgba(
+                        text.color[0],
+                        text.color[1],
+                        text.color[2],
+                        text.color[3],
+                    ),
+                })
+        };
+
+        // The atlas grows by itself, it's only full once it reached the
+        // largest texture size. Dropping the glyphs that weren't used
+        // recently makes room again.
+        let mut prepared = self.renderer.prepare(
+            device,
+            queue,
+            &mut self.font_system,
+            &mut self.atlas,
+            resolution,
+            text_areas(),
+            &mut self.swash_cache,
+        );
+        if prepared == Err(PrepareError::AtlasFull) {
+            self.atlas.trim();
+            prepared = self.renderer.prepare(
+                device,
+                queue,
+                &mut self.font_system,
+                &mut self.atlas,
+                resolution,
+                text_areas(),
+                &mut self.swash_cache,
+            );
+        }
+        if let Err(err) = prepared {
+            log::error!("Failed to prepare the HUD text: {err}");
+            return;
+        }
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("HUD Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // Keep the scene we're drawing on top of
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            if let Err(err) = self.renderer.render(&self.atlas, &mut render_pass) {
+                log::error!("Failed to draw the HUD text: {err}");
+            }
+        }
+
+        self.atlas.trim();
+    }
+}
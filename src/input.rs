@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+use glam::Vec2;
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+};
+
+/// How many pixels of a touchpad scroll count as one line of a mouse wheel.
+/// Roughly what browsers scroll per wheel notch.
+pub const PIXELS_PER_LINE: f32 = 20.0;
+
+/// The state of the mouse, built up from window events.
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+    cursor_position: Option<PhysicalPosition<f64>>,
+    pressed_buttons: HashSet<MouseButton>,
+    scroll_delta: Vec2,
+}
+
+impl InputState {
+    /// Returns `true` for the mouse events, which are all tracked.
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(*position);
+                true
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_position = None;
+                true
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                match state {
+                    ElementState::Pressed => self.pressed_buttons.insert(*button),
+                    ElementState::Released => self.pressed_buttons.remove(button),
+                };
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll_delta += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(*x, *y),
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        Vec2::new(delta.x as f32, delta.y as f32) / PIXELS_PER_LINE
+                    }
+                };
+                true
+            }
+            // The release events go to whichever window has focus
+            WindowEvent::Focused(false) => {
+                self.pressed_buttons.clear();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// In physical pixels from the top left of the window, `None` while the
+    /// cursor is outside of it.
+    pub fn cursor_position(&self) -> Option<PhysicalPosition<f64>> {
+        self.cursor_position
+    }
+
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    /// How far the wheel scrolled this frame, in lines. Positive y scrolls
+    /// up, away from the user.
+    pub fn scroll_delta(&self) -> Vec2 {
+        self.scroll_delta
+    }
+
+    /// Clears what only applies to a single frame, call it at the end of
+    /// each update.
+    pub fn end_frame(&mut self) {
+        self.scroll_delta = Vec2::ZERO;
+    }
+}
@@ -0,0 +1,504 @@
+//! Named actions and axes bound to keys, mouse buttons and gamepads, so the
+//! code moving the camera asks for `"move_forward"` instead of checking W,
+//! the up arrow and the left stick. `InputBindings` says what's bound to
+//! what, and `InputMap` keeps track of what's held during a frame.
+//!
+//! Inputs are written by their names, which is how serde (de)serializes
+//! them and how the settings file has them:
+//!
+//! ```toml
+//! [input.actions]
+//! exit = ["logical:Escape", "gamepad:Start"]
+//! toggle_fullscreen = ["F11"]
+//!
+//! [input.axes]
+//! move_forward = ["KeyS/KeyW", "ArrowDown/ArrowUp", "gamepad:LeftStickY"]
+//! look_y = ["-gamepad:RightStickY"]
+//! ```
+//!
+//! Keys are `KeyCode`s, where they are on the keyboard whatever the layout,
+//! or with `logical:` the `NamedKey` the layout puts somewhere. Mouse buttons start with `mouse:`, and an axis can be two keys for its
+//! negative and positive ends or a gamepad axis, `-` in front inverts it.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use serde::{de::IntoDeserializer, Deserialize, Serialize};
+use thiserror::Error;
+use winit::{
+    event::{ElementState, KeyEvent, MouseButton, WindowEvent},
+    keyboard::{Key, KeyCode, NamedKey, PhysicalKey},
+};
+
+/// The actions and axes the bundled scene reads, see `InputBindings::default`.
+pub mod names {
+    pub const EXIT: &str = "exit";
+    pub const TOGGLE_FULLSCREEN: &str = "toggle_fullscreen";
+    /// Positive forward.
+    pub const MOVE_FORWARD: &str = "move_forward";
+    pub const MOVE_RIGHT: &str = "move_right";
+    pub const MOVE_UP: &str = "move_up";
+    /// Positive to the right.
+    pub const LOOK_X: &str = "look_x";
+    /// Positive up.
+    pub const LOOK_Y: &str = "look_y";
+}
+
+/// A gamepad button, by where it is: `South` is A on an Xbox pad and the
+/// cross on a PlayStation one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    Start,
+    Select,
+}
+
+/// The sticks of a gamepad, from -1 to 1. Y is up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+
+/// A name in a binding that isn't an input.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("unknown input {0:?}")]
+pub struct UnknownInput(pub String);
+
+/// Something that is pressed or not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Input {
+    Key(KeyCode),
+    /// A named key by what it does, wherever the layout has it.
+    Logical(NamedKey),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl Input {
+    /// What a key is as an input: where it is, and what it does if it's a
+    /// named key.
+    pub fn of_key(physical_key: &PhysicalKey, logical_key: &Key) -> impl Iterator<Item = Input> {
+        let code = match physical_key {
+            PhysicalKey::Code(code) => Some(Self::Key(*code)),
+            PhysicalKey::Unidentified(_) => None,
+        };
+        let named = match logical_key {
+            Key::Named(key) => Some(Self::Logical(*key)),
+            _ => None,
+        };
+        code.into_iter().chain(named)
+    }
+}
+
+/// Something that goes from -1 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum AxisInput {
+    /// -1 while `negative` is held, 1 while `positive` is, 0 for both.
+    Keys {
+        negative: KeyCode,
+        positive: KeyCode,
+    },
+    Gamepad {
+        axis: GamepadAxis,
+        inverted: bool,
+    },
+}
+
+/// Parses a unit variant of `T` by its name, the way serde would.
+fn parse_variant<'de, T: Deserialize<'de>>(name: &'de str) -> Option<T> {
+    T::deserialize(IntoDeserializer::<serde::de::value::Error>::into_deserializer(name)).ok()
+}
+
+const LOGICAL_PREFIX: &str = "logical:";
+const MOUSE_PREFIX: &str = "mouse:";
+const GAMEPAD_PREFIX: &str = "gamepad:";
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The derived Debug is the variant name
+        match self {
+            Self::Key(code) => write!(f, "{code:?}"),
+            Self::Logical(key) => write!(f, "{LOGICAL_PREFIX}{key:?}"),
+            Self::Mouse(MouseButton::Other(button)) => write!(f, "{MOUSE_PREFIX}{button}"),
+            Self::Mouse(button) => write!(f, "{MOUSE_PREFIX}{button:?}"),
+            Self::Gamepad(button) => write!(f, "{GAMEPAD_PREFIX}{button:?}"),
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = UnknownInput;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let input = if let Some(key) = name.strip_prefix(LOGICAL_PREFIX) {
+            parse_variant(key).map(Self::Logical)
+        } else if let Some(button) = name.strip_prefix(MOUSE_PREFIX) {
+            match button.parse() {
+                Ok(other) => Some(Self::Mouse(MouseButton::Other(other))),
+                Err(_) => parse_variant(button).map(Self::Mouse),
+            }
+        } else if let Some(button) = name.strip_prefix(GAMEPAD_PREFIX) {
+            parse_variant(button).map(Self::Gamepad)
+        } else {
+            parse_variant(name).map(Self::Key)
+        };
+        input.ok_or_else(|| UnknownInput(name.to_owned()))
+    }
+}
+
+impl fmt::Display for AxisInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Keys { negative, positive } => write!(f, "{negative:?}/{positive:?}"),
+            Self::Gamepad { axis, inverted } => {
+                let sign = if *inverted { "-" } else { "" };
+                write!(f, "{sign}{GAMEPAD_PREFIX}{axis:?}")
+            }
+        }
+    }
+}
+
+impl FromStr for AxisInput {
+    type Err = UnknownInput;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let (inverted, unsigned) = match name.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, name),
+        };
+        let input = if let Some(axis) = unsigned.strip_prefix(GAMEPAD_PREFIX) {
+            parse_variant(axis).map(|axis| Self::Gamepad { axis, inverted })
+        } else {
+            unsigned.split_once('/').and_then(|(negative, positive)| {
+                let (negative, positive) = (parse_variant(negative)?, parse_variant(positive)?);
+                // Swapping the keys inverts them
+                Some(if inverted {
+                    Self::Keys {
+                        negative: positive,
+                        positive: negative,
+                    }
+                } else {
+                    Self::Keys { negative, positive }
+                })
+            })
+        };
+        input.ok_or_else(|| UnknownInput(name.to_owned()))
+    }
+}
+
+// For `#[serde(into, try_from)]`
+impl From<Input> for String {
+    fn from(input: Input) -> Self {
+        input.to_string()
+    }
+}
+
+impl TryFrom<String> for Input {
+    type Error = UnknownInput;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl From<AxisInput> for String {
+    fn from(input: AxisInput) -> Self {
+        input.to_string()
+    }
+}
+
+impl TryFrom<String> for AxisInput {
+    type Error = UnknownInput;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+/// Which inputs the actions and axes have. Any number of inputs can be
+/// bound to one action or axis, and the same input to several.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputBindings {
+    #[serde(default)]
+    actions: BTreeMap<String, Vec<Input>>,
+    #[serde(default)]
+    axes: BTreeMap<String, Vec<AxisInput>>,
+}
+
+impl InputBindings {
+    /// No bindings at all.
+    pub fn empty() -> Self {
+        Self {
+            actions: BTreeMap::new(),
+            axes: BTreeMap::new(),
+        }
+    }
+
+    /// Binds `input` to `action`, on top of what's already bound to it.
+    pub fn bind(mut self, action: &str, input: Input) -> Self {
+        let inputs = self.actions.entry(action.to_owned()).or_default();
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+        self
+    }
+
+    /// Like `bind`, for an axis.
+    pub fn bind_axis(mut self, axis: &str, input: AxisInput) -> Self {
+        let inputs = self.axes.entry(axis.to_owned()).or_default();
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+        self
+    }
+
+    /// Replaces whatever was bound to `action` with `inputs`, none unbinds
+    /// it.
+    pub fn set_action(&mut self, action: &str, inputs: Vec<Input>) {
+        self.actions.insert(action.to_owned(), inputs);
+    }
+
+    /// Like `set_action`, for an axis.
+    pub fn set_axis(&mut self, axis: &str, inputs: Vec<AxisInput>) {
+        self.axes.insert(axis.to_owned(), inputs);
+    }
+
+    /// The actions and their inputs, by name.
+    pub fn actions(&self) -> impl Iterator<Item = (&str, &[Input])> {
+        self.actions
+            .iter()
+            .map(|(name, inputs)| (name.as_str(), inputs.as_slice()))
+    }
+
+    /// The axes and their inputs, by name.
+    pub fn axes(&self) -> impl Iterator<Item = (&str, &[AxisInput])> {
+        self.axes
+            .iter()
+            .map(|(name, inputs)| (name.as_str(), inputs.as_slice()))
+    }
+
+    /// What's bound to `action`, nothing for unknown actions.
+    pub fn inputs(&self, action: &str) -> &[Input] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// What's bound to `axis`, nothing for unknown axes.
+    pub fn axis_inputs(&self, axis: &str) -> &[AxisInput] {
+        self.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether anything uses the key, as an action or an end of an axis.
+    fn binds_key(&self, code: KeyCode) -> bool {
+        self.actions
+            .values()
+            .flatten()
+            .any(|input| *input == Input::Key(code))
+            || self.axes.values().flatten().any(|input| match input {
+                AxisInput::Keys { negative, positive } => *negative == code || *positive == code,
+                AxisInput::Gamepad { .. } => false,
+            })
+    }
+
+    fn binds(&self, input: Input) -> bool {
+        match input {
+            Input::Key(code) => self.binds_key(code),
+            _ => self.actions.values().flatten().any(|bound| *bound == input),
+        }
+    }
+}
+
+impl Default for InputBindings {
+    /// Escape and F11 like the key bindings, WASD and the arrows to move, E
+    /// and Shift to go up and down, and the sticks of a gamepad.
+    fn default() -> Self {
+        use names::*;
+
+        let keys = |negative, positive| AxisInput::Keys { negative, positive };
+        let stick = |axis| AxisInput::Gamepad {
+            axis,
+            inverted: false,
+        };
+        Self::empty()
+            .bind(EXIT, Input::Logical(NamedKey::Escape))
+            .bind(EXIT, Input::Gamepad(GamepadButton::Start))
+            .bind(TOGGLE_FULLSCREEN, Input::Key(KeyCode::F11))
+            .bind_axis(MOVE_FORWARD, keys(KeyCode::KeyS, KeyCode::KeyW))
+            .bind_axis(MOVE_FORWARD, keys(KeyCode::ArrowDown, KeyCode::ArrowUp))
+            .bind_axis(MOVE_FORWARD, stick(GamepadAxis::LeftStickY))
+            .bind_axis(MOVE_RIGHT, keys(KeyCode::KeyA, KeyCode::KeyD))
+            .bind_axis(MOVE_RIGHT, keys(KeyCode::ArrowLeft, KeyCode::ArrowRight))
+            .bind_axis(MOVE_RIGHT, stick(GamepadAxis::LeftStickX))
+            .bind_axis(MOVE_UP, keys(KeyCode::ShiftLeft, KeyCode::KeyE))
+            .bind_axis(MOVE_UP, keys(KeyCode::ShiftRight, KeyCode::KeyE))
+            .bind_axis(LOOK_X, stick(GamepadAxis::RightStickX))
+            .bind_axis(LOOK_Y, stick(GamepadAxis::RightStickY))
+    }
+}
+
+/// What the bound inputs are doing, built up from the events of a frame.
+/// `just_pressed` lasts until `end_frame`.
+#[derive(Debug, Clone, Default)]
+pub struct InputMap {
+    bindings: InputBindings,
+    pressed: HashSet<Input>,
+    just_pressed: HashSet<Input>,
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+}
+
+impl InputMap {
+    pub fn new(bindings: InputBindings) -> Self {
+        Self {
+            bindings,
+            ..Self::default()
+        }
+    }
+
+    pub fn bindings(&self) -> &InputBindings {
+        &self.bindings
+    }
+
+    /// Takes effect right away, inputs held since keep counting.
+    pub fn set_bindings(&mut self, bindings: InputBindings) {
+        self.bindings = bindings;
+    }
+
+    /// Returns `true` for the key and mouse button events of bound inputs,
+    /// which nothing else should handle. Losing the focus releases
+    /// everything, the releases go to another window.
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        let (input, state) = match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key,
+                        logical_key,
+                        state,
+                        ..
+                    },
+                ..
+            } => return self.process_key(physical_key, logical_key, *state),
+            WindowEvent::MouseInput { state, button, .. } => (Input::Mouse(*button), *state),
+            WindowEvent::Focused(false) => {
+                self.reset();
+                return false;
+            }
+            _ => return false,
+        };
+        if !self.bindings.binds(input) {
+            return false;
+        }
+        self.set_pressed(input, state == ElementState::Pressed);
+        true
+    }
+
+    /// Like `process_event`, for a key by where it is and what it does.
+    /// Either can be bound.
+    pub fn process_key(
+        &mut self,
+        physical_key: &PhysicalKey,
+        logical_key: &Key,
+        state: ElementState,
+    ) -> bool {
+        let mut bound = false;
+        for input in Input::of_key(physical_key, logical_key) {
+            if self.bindings.binds(input) {
+                self.set_pressed(input, state == ElementState::Pressed);
+                bound = true;
+            }
+        }
+        bound
+    }
+
+    /// Presses or releases a gamepad button, e.g. from gilrs' events.
+    pub fn set_gamepad_button(&mut self, button: GamepadButton, pressed: bool) {
+        self.set_pressed(Input::Gamepad(button), pressed);
+    }
+
+    /// Where a gamepad stick is, from -1 to 1 and after the dead zone.
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.gamepad_axes.insert(axis, value.clamp(-1.0, 1.0));
+    }
+
+    fn set_pressed(&mut self, input: Input, pressed: bool) {
+        if !pressed {
+            self.pressed.remove(&input);
+        } else if self.pressed.insert(input) {
+            // Held keys repeat, but they were only pressed once
+            self.just_pressed.insert(input);
+        }
+    }
+
+    /// Whether one of the inputs of `action` is held.
+    pub fn is_pressed(&self, action: &str) -> bool {
+        self.bindings
+            .inputs(action)
+            .iter()
+            .any(|input| self.pressed.contains(input))
+    }
+
+    /// Whether one of the inputs of `action` went down this frame, even if
+    /// it's up again.
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.bindings
+            .inputs(action)
+            .iter()
+            .any(|input| self.just_pressed.contains(input))
+    }
+
+    /// Where `axis` is, from -1 to 1. Of several inputs moving it, the one
+    /// furthest from the center wins, so a key and a stick don't add up to
+    /// more than either.
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.bindings
+            .axis_inputs(axis)
+            .iter()
+            .map(|input| self.axis_value(input))
+            .fold(0.0, |strongest, value| {
+                if value.abs() > strongest.abs() {
+                    value
+                } else {
+                    strongest
+                }
+            })
+    }
+
+    fn axis_value(&self, input: &AxisInput) -> f32 {
+        match *input {
+            AxisInput::Keys { negative, positive } => {
+                let held = |code| f32::from(u8::from(self.pressed.contains(&Input::Key(code))));
+                held(positive) - held(negative)
+            }
+            AxisInput::Gamepad { axis, inverted } => {
+                let value = self.gamepad_axes.get(&axis).copied().unwrap_or(0.0);
+                if inverted {
+                    -value
+                } else {
+                    value
+                }
+            }
+        }
+    }
+
+    /// Forgets what was just pressed, call it at the end of each update.
+    pub fn end_frame(&mut self) {
+        self.just_pressed.clear();
+    }
+
+    /// Releases everything.
+    pub fn reset(&mut self) {
+        self.pressed.clear();
+        self.just_pressed.clear();
+        self.gamepad_axes.clear();
+    }
+}
@@ -0,0 +1,294 @@
+use glam::{Mat3, Mat4, Quat, Vec3};
+
+use crate::{buffer::GrowableBuffer, upload::UploadArena};
+
+/// Where one copy of a mesh is placed in the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instance {
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+impl Instance {
+    pub fn to_raw(&self) -> InstanceRaw {
+        InstanceRaw {
+            model: Mat4::from_rotation_translation(self.rotation, self.position).to_cols_array_2d(),
+            // Instances are only rotated and translated, so the rotation
+            // is all we need to transform the normals.
+            normal: Mat3::from_quat(self.rotation).to_cols_array_2d(),
+        }
+    }
+
+    /// `alpha` of the way from `self` to `other`.
+    pub fn lerp(&self, other: &Instance, alpha: f32) -> Instance {
+        Instance {
+            position: self.position.lerp(other.position, alpha),
+            rotation: self.rotation.slerp(other.rotation, alpha),
+        }
+    }
+}
+
+/// Instances moved by fixed steps, with where they were at the step before
+/// to draw them in between.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SteppedInstances {
+    previous: Vec<Instance>,
+    current: Vec<Instance>,
+    /// Where `move_to` moves them with the next step.
+    next: Option<Vec<Instance>>,
+}
+
+impl SteppedInstances {
+    pub fn new(instances: Vec<Instance>) -> Self {
+        Self {
+            previous: instances.clone(),
+            current: instances,
+            next: None,
+        }
+    }
+
+    /// Where they are as of the last step.
+    pub fn current(&self) -> &[Instance] {
+        &self.current
+    }
+
+    /// Puts them somewhere else right away, without moving there.
+    pub fn set(&mut self, instances: Vec<Instance>) {
+        *self = Self::new(instances);
+    }
+
+    /// Moves them to `instances` with the next step, they're drawn moving
+    /// there over the step after. A different number of instances can't
+    /// move and is `set` then instead.
+    pub fn move_to(&mut self, instances: Vec<Instance>) {
+        self.next = Some(instances);
+    }
+
+    /// Call it before each fixed step: where they are now becomes where
+    /// they were at the step before.
+    pub fn begin_step(&mut self) {
+        let next = self.next.take();
+        self.previous.clone_from(&self.current);
+        if let Some(next) = next {
+            if next.len() != self.current.len() {
+                self.previous.clone_from(&next);
+            }
+            self.current = next;
+        }
+    }
+
+    /// Whether they moved in the last step.
+    pub fn is_moving(&self) -> bool {
+        self.previous != self.current
+    }
+
+    /// `alpha` of the way from where they were at the step before the last
+    /// one to where they are now.
+    pub fn interpolated(&self, alpha: f32) -> Vec<Instance> {
+        self.previous
+            .iter()
+            .zip(&self.current)
+            .map(|(previous, current)| previous.lerp(current, alpha))
+            .collect()
+    }
+}
+
+/// The per-instance data as the vertex shader sees it.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceRaw {
+    pub model: [[f32; 4]; 4],
+    pub normal: [[f32; 3]; 3],
+}
+
+impl InstanceRaw {
+    // A mat4 takes up 4 vertex slots as it is technically 4 vec4s. We need
+    // to define a slot for each vec4 and reassemble the mat4 in the shader.
+    // The vertex attributes use the first few locations, so we start at 5
+    // to leave them some room to grow.
+    pub const ATTRIBS: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        // The normal matrix, a mat3, takes up three more slots.
+        9 => Float32x3,
+        10 => Float32x3,
+        11 => Float32x3,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            // We need to switch from using a step mode of Vertex to Instance
+            // This means that our shaders will only change to use the next
+            // instance when the shader starts processing a new instance
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// An instance that also picks the layer of a texture array it's drawn
+/// with, so instances with different textures share one draw call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TexturedInstance {
+    pub instance: Instance,
+    pub layer: u32,
+}
+
+impl TexturedInstance {
+    pub fn to_raw(&self) -> TexturedInstanceRaw {
+        let InstanceRaw { model, normal } = self.instance.to_raw();
+        TexturedInstanceRaw {
+            model,
+            normal,
+            layer: self.layer,
+        }
+    }
+}
+
+/// `InstanceRaw` followed by the texture array layer.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TexturedInstanceRaw {
+    pub model: [[f32; 4]; 4],
+    pub normal: [[f32; 3]; 3],
+    pub layer: u32,
+}
+
+impl TexturedInstanceRaw {
+    // The same slots as `InstanceRaw`, the layer goes after the ones the
+    // skinned vertices use
+    pub const ATTRIBS: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x3,
+        10 => Float32x3,
+        11 => Float32x3,
+        14 => Uint32,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TexturedInstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// An instance drawn in a color of its own, alpha included.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TintedInstance {
+    pub instance: Instance,
+    pub color: [f32; 4],
+}
+
+impl TintedInstance {
+    pub fn to_raw(&self) -> TintedInstanceRaw {
+        let InstanceRaw { model, normal } = self.instance.to_raw();
+        TintedInstanceRaw {
+            model,
+            normal,
+            color: self.color,
+        }
+    }
+}
+
+/// `InstanceRaw` followed by the color.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TintedInstanceRaw {
+    pub model: [[f32; 4]; 4],
+    pub normal: [[f32; 3]; 3],
+    pub color: [f32; 4],
+}
+
+impl TintedInstanceRaw {
+    // Like `TexturedInstanceRaw`, with the color where the layer goes
+    pub const ATTRIBS: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x3,
+        10 => Float32x3,
+        11 => Float32x3,
+        14 => Float32x4,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TintedInstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// A vertex buffer of `InstanceRaw` that can be refilled at runtime.
+pub struct InstanceBuffer {
+    buffer: GrowableBuffer<InstanceRaw>,
+}
+
+impl InstanceBuffer {
+    pub fn new(device: &wgpu::Device, instances: &[Instance]) -> Self {
+        let raw = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        Self {
+            buffer: GrowableBuffer::new(
+                device,
+                "Instance Buffer",
+                wgpu::BufferUsages::VERTEX,
+                &raw,
+            ),
+        }
+    }
+
+    /// Uploads `instances`, reusing the current buffer if it is big enough
+    /// and reallocating it otherwise. Returns whether it was reallocated.
+    pub fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[Instance],
+    ) -> bool {
+        let raw = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        self.buffer.update(device, queue, &raw)
+    }
+
+    /// Like `write`, through `uploads` with a copy recorded into `encoder`.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        uploads: &mut UploadArena,
+        instances: &[Instance],
+    ) -> bool {
+        let raw = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        self.buffer.upload(device, encoder, uploads, &raw)
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        self.buffer.buffer()
+    }
+
+    /// Binds the instances to vertex buffer `slot`.
+    pub fn bind<'a>(&'a self, encoder: &mut impl wgpu::util::RenderEncoder<'a>, slot: u32) {
+        self.buffer.bind(encoder, slot);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+}
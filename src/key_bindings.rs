@@ -0,0 +1,253 @@
+use winit::keyboard::{Key, KeyCode, ModifiersState, NamedKey, PhysicalKey};
+
+/// Something the app can do, independent of the key it's bound to. See
+/// `State::handle_action`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Closes the window, the app exits with the last one. Triggered by
+    /// `input_map::names::EXIT`.
+    Exit,
+    /// Opens another window, native only.
+    OpenWindow,
+    /// Borderless fullscreen, triggered by
+    /// `input_map::names::TOGGLE_FULLSCREEN`.
+    ToggleFullscreen,
+    /// Exclusive fullscreen, with the video mode of the monitor.
+    ToggleExclusiveFullscreen,
+    Screenshot,
+    ToggleVsync,
+    CycleFrameRateCap,
+    ToggleRedrawMode,
+    NextPostEffect,
+    ToggleOverlay,
+    /// The graph of the last frame times, see `frame_time_graph`.
+    ToggleFrameTimeGraph,
+    /// Native only, on the web the shaders are built in.
+    ReloadShaders,
+    ToggleMouseLook,
+    /// Draws the model as a wireframe where supported.
+    ToggleWireframe,
+    /// Shadows cost a pass over the scene, turning them off shows how much.
+    ToggleShadows,
+    /// Keeps culling with the current camera, so one can fly out and see
+    /// what gets left out.
+    FreezeCulling,
+    /// One view, then the frame split by a horizontal line, then by a
+    /// vertical one, see `split_screen::SplitMode`.
+    CycleSplitScreen,
+    /// Moves the camera of the other view of a split frame.
+    ToggleViewFocus,
+    /// Flies the camera around or orbits it, see `camera::CameraMode`.
+    ToggleCameraMode,
+    /// Points the camera at the whole scene, see `State::focus_scene`.
+    FocusScene,
+    /// Replays the recorded draws of the model instead of encoding them
+    /// every frame.
+    ToggleRenderBundles,
+    /// Outlines the first instance, or takes the outline off whatever is
+    /// selected. Clicks select what they hit.
+    ToggleSelection,
+    /// Samples the model's first material with nearest, bilinear,
+    /// trilinear and then anisotropic filtering, see
+    /// `sampler::TextureFiltering`.
+    CycleTextureFiltering,
+    /// Goes from no antialiasing to FXAA to MSAA, see
+    /// `State::set_antialiasing`.
+    CycleAntialiasing,
+    /// Shows the depth, the normals and the other debug views in turn, see
+    /// `debug_view::DebugView`.
+    CycleDebugView,
+    ToggleBloom,
+    /// The ground grid and the origin's axes, see `grid`.
+    ToggleGrid,
+    /// Draws the animated plasma instead of the skybox.
+    TogglePlasma,
+    /// Stops the time the shaders see, see `Globals`.
+    TogglePause,
+    /// Cycles through the tonemapping operators.
+    NextTonemap,
+    IncreaseExposure,
+    DecreaseExposure,
+    /// Renders the scene bigger or smaller than the window, see
+    /// `State::set_render_scale`.
+    IncreaseRenderScale,
+    DecreaseRenderScale,
+    /// An index into `color::PRESETS`.
+    ClearColorPreset(usize),
+}
+
+/// A key, either by where it is on the keyboard or by what it types.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KeyTrigger {
+    /// Stays in the same place whatever the layout, best for movement.
+    Physical(KeyCode),
+    /// Follows the layout, e.g. for keys named after a letter.
+    Logical(Key),
+}
+
+impl From<KeyCode> for KeyTrigger {
+    fn from(code: KeyCode) -> Self {
+        Self::Physical(code)
+    }
+}
+
+impl From<Key> for KeyTrigger {
+    fn from(key: Key) -> Self {
+        Self::Logical(key)
+    }
+}
+
+impl From<NamedKey> for KeyTrigger {
+    fn from(key: NamedKey) -> Self {
+        Self::Logical(Key::Named(key))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Binding {
+    trigger: KeyTrigger,
+    modifiers: ModifiersState,
+    action: Action,
+}
+
+/// Which keys trigger which actions. An action can have any number of keys.
+/// `State` leaves exiting and borderless fullscreen to its
+/// `input_map::InputBindings` instead, which the settings file changes, and
+/// moving the camera is only bound there.
+///
+/// Start from `KeyBindings::default()` to change a few bindings, e.g.
+/// `.unbind(Action::Screenshot).bind(KeyCode::KeyP, Action::Screenshot)`,
+/// or from `KeyBindings::empty()` to define all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    bindings: Vec<Binding>,
+}
+
+impl KeyBindings {
+    /// No bindings at all.
+    pub fn empty() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Binds `key` to `action`, on top of the keys already bound to it.
+    /// Binding a key again replaces the action it had.
+    pub fn bind(self, key: impl Into<KeyTrigger>, action: Action) -> Self {
+        self.bind_with_modifiers(key, ModifiersState::empty(), action)
+    }
+
+    /// Like `bind`, but only while at least `modifiers` are held. A key can
+    /// have a different action for each combination of modifiers, the one
+    /// needing the most modifiers wins.
+    pub fn bind_with_modifiers(
+        mut self,
+        key: impl Into<KeyTrigger>,
+        modifiers: ModifiersState,
+        action: Action,
+    ) -> Self {
+        let trigger = key.into();
+        self.bindings
+            .retain(|binding| binding.trigger != trigger || binding.modifiers != modifiers);
+        self.bindings.push(Binding {
+            trigger,
+            modifiers,
+            action,
+        });
+        self
+    }
+
+    /// Removes every key bound to `action`.
+    pub fn unbind(mut self, action: Action) -> Self {
+        self.bindings.retain(|binding| binding.action != action);
+        self
+    }
+
+    /// The keys bound to `action` and the modifiers they need.
+    pub fn keys_for(&self, action: Action) -> impl Iterator<Item = (&KeyTrigger, ModifiersState)> {
+        self.bindings
+            .iter()
+            .filter(move |binding| binding.action == action)
+            .map(|binding| (&binding.trigger, binding.modifiers))
+    }
+
+    /// The action for a key event, `None` if the key isn't bound. Bindings
+    /// match when their modifiers are held, extra modifiers don't matter,
+    /// so e.g. Alt+Shift+C still freezes the culling.
+    pub fn action(
+        &self,
+        physical_key: &PhysicalKey,
+        logical_key: &Key,
+        modifiers: ModifiersState,
+    ) -> Option<Action> {
+        self.bindings
+            .iter()
+            .filter(|binding| match &binding.trigger {
+                KeyTrigger::Physical(code) => *physical_key == PhysicalKey::Code(*code),
+                KeyTrigger::Logical(key) => key == logical_key,
+            })
+            .filter(|binding| modifiers.contains(binding.modifiers))
+            .max_by_key(|binding| binding.modifiers.bits().count_ones())
+            .map(|binding| binding.action)
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let bindings = Self::empty()
+            .bind(NamedKey::Escape, Action::Exit)
+            .bind(KeyCode::F2, Action::OpenWindow)
+            .bind(KeyCode::F11, Action::ToggleFullscreen)
+            .bind_with_modifiers(
+                KeyCode::Enter,
+                ModifiersState::ALT,
+                Action::ToggleExclusiveFullscreen,
+            )
+            .bind(KeyCode::F12, Action::Screenshot)
+            .bind(KeyCode::KeyV, Action::ToggleVsync)
+            .bind(KeyCode::KeyL, Action::CycleFrameRateCap)
+            .bind(KeyCode::KeyO, Action::ToggleRedrawMode)
+            .bind(KeyCode::KeyP, Action::NextPostEffect)
+            .bind(KeyCode::F1, Action::ToggleOverlay)
+            .bind(KeyCode::F3, Action::ToggleFrameTimeGraph)
+            .bind(KeyCode::F4, Action::CycleDebugView)
+            .bind(KeyCode::KeyR, Action::ReloadShaders)
+            .bind(KeyCode::Tab, Action::ToggleMouseLook)
+            .bind(KeyCode::KeyZ, Action::ToggleWireframe)
+            .bind(KeyCode::KeyH, Action::ToggleShadows)
+            .bind(KeyCode::KeyC, Action::ToggleCameraMode)
+            .bind_with_modifiers(KeyCode::KeyC, ModifiersState::ALT, Action::FreezeCulling)
+            .bind(KeyCode::KeyX, Action::CycleSplitScreen)
+            .bind(KeyCode::KeyF, Action::FocusScene)
+            .bind_with_modifiers(KeyCode::KeyF, ModifiersState::ALT, Action::ToggleViewFocus)
+            .bind(KeyCode::KeyN, Action::ToggleRenderBundles)
+            .bind(KeyCode::KeyI, Action::ToggleSelection)
+            .bind(KeyCode::KeyK, Action::CycleTextureFiltering)
+            .bind(KeyCode::KeyM, Action::CycleAntialiasing)
+            .bind(KeyCode::KeyB, Action::ToggleBloom)
+            .bind(KeyCode::KeyT, Action::NextTonemap)
+            .bind(KeyCode::KeyG, Action::ToggleGrid)
+            .bind(KeyCode::KeyY, Action::TogglePlasma)
+            .bind(KeyCode::Space, Action::TogglePause)
+            // + shares its key with = on most layouts
+            .bind(KeyCode::Equal, Action::IncreaseExposure)
+            .bind(KeyCode::NumpadAdd, Action::IncreaseExposure)
+            .bind(KeyCode::Minus, Action::DecreaseExposure)
+            .bind(KeyCode::NumpadSubtract, Action::DecreaseExposure)
+            .bind(KeyCode::BracketRight, Action::IncreaseRenderScale)
+            .bind(KeyCode::BracketLeft, Action::DecreaseRenderScale);
+
+        [
+            KeyCode::Digit1,
+            KeyCode::Digit2,
+            KeyCode::Digit3,
+            KeyCode::Digit4,
+            KeyCode::Digit5,
+        ]
+        .into_iter()
+        .enumerate()
+        .fold(bindings, |bindings, (preset, key)| {
+            bindings.bind(key, Action::ClearColorPreset(preset))
+        })
+    }
+}
@@ -1,260 +1,4776 @@
-use std::error::Error;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
+
+use web_time::Instant;
 use winit::{
     dpi,
     event::*,
     event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
-    keyboard::{Key, NamedKey},
-    window::{Window, WindowBuilder},
+    window::{CursorGrabMode, Window},
+};
+
+pub mod adapter;
+pub mod alpha_mode;
+pub mod animation;
+pub mod app;
+pub mod asset_source;
+pub mod assets;
+pub mod bench;
+pub mod block_compression;
+pub mod bloom;
+pub mod boids;
+pub mod buffer;
+pub mod camera;
+pub mod capture;
+pub mod clear_app;
+pub mod cli;
+pub mod color;
+pub mod compressed_texture;
+pub mod compute;
+pub mod culling;
+pub mod debug_draw;
+pub mod debug_overlay;
+pub mod debug_view;
+pub mod device_config;
+pub mod error;
+pub mod file_drop;
+pub mod fixed_timestep;
+pub mod frame_counter;
+pub mod frame_limiter;
+pub mod frame_time_graph;
+pub mod fullscreen;
+pub mod fxaa;
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+pub mod gamepad;
+pub mod geometry;
+pub mod globals;
+pub mod gltf;
+pub mod gpu_errors;
+pub mod gpu_memory;
+pub mod grid;
+pub mod hud;
+pub mod input;
+pub mod input_map;
+pub mod instance;
+pub mod json;
+pub mod key_bindings;
+pub mod light;
+pub mod minimize;
+pub mod mipmap;
+pub mod model;
+pub mod msaa;
+pub mod outline;
+pub mod picking;
+pub mod pipeline;
+pub mod post_process;
+pub mod present_mode;
+pub mod procedural;
+pub mod profiler;
+pub mod redraw_mode;
+pub mod render_bundle;
+pub mod render_graph;
+pub mod render_scale;
+pub mod run_config;
+pub mod sampler;
+pub mod settings;
+pub mod shader;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shader_watcher;
+pub mod shadertoy;
+pub mod shadow;
+pub mod skybox;
+pub mod split_screen;
+pub mod storage;
+pub mod texture;
+pub mod texture_array;
+pub mod tonemap;
+pub mod touch;
+pub mod transparency;
+pub mod uniform;
+pub mod upload;
+pub mod vertex;
+pub mod window_config;
+
+use adapter::{AdapterSelection, GpuInfo};
+use animation::{AnimationError, JointBinding, SkinVertex};
+use asset_source::{AssetSource, FsSource};
+use assets::{AssetLoader, Assets, LoadedImage};
+use bloom::BloomSettings;
+use camera::{
+    Camera, CameraBinding, CameraController, CameraMode, CameraUniform, OrbitCamera, OrbitPose,
 };
+use capture::{PendingScreenshot, TextureReadback};
+use color::SrgbEncoding;
+use compute::{ParticlePipelines, ParticleSystem};
+use culling::{Aabb, CullStats, Culled, Frustum};
+use debug_draw::{DebugDraw, DebugDrawPipelines};
+use debug_overlay::DebugOverlay;
+use debug_view::{DebugView, DebugViewRenderer};
+use device_config::DeviceConfig;
+use error::AppError;
+use file_drop::{DropLoader, DroppedAsset, LoadedDrop};
+use fixed_timestep::FixedTimestep;
+use frame_counter::{FrameCounter, FrameStats, FrameTime};
+use frame_limiter::FrameLimiter;
+use frame_time_graph::FrameTimeGraph;
+use fullscreen::{FullscreenMode, FullscreenToggle};
+use fxaa::{Antialiasing, Fxaa, FxaaSettings};
+use geometry::Geometry;
+use globals::{Globals, GlobalsUniform};
+use gltf::GltfError;
+use gpu_errors::{ErrorContext, GpuErrorHandlers};
+use hud::Hud;
+use input::InputState;
+use input_map::{InputBindings, InputMap};
+use instance::{Instance, InstanceBuffer, InstanceRaw, SteppedInstances};
+use key_bindings::{Action, KeyBindings};
+use light::{LightBinding, LightBuffers, Lights, PointLight};
+use minimize::{FrameAction, MinimizeTracker};
+use model::{DrawLight, DrawModel, MaterialLayouts, Model, ModelVertex, TextureSlot};
+use outline::{Outline, OutlinePipelines, OutlineSettings};
+use picking::{PickScene, Picked, Picking};
+use pipeline::{BlendMode, PipelineBuilder, RenderTargets, ShaderSources};
+use post_process::{PostProcessor, HDR_FORMAT};
+use present_mode::PresentModePreference;
+use profiler::{GpuPass, GpuProfiler, GpuTimings};
+use redraw_mode::RedrawMode;
+use render_bundle::{BundleKey, SceneBundle};
+use render_graph::{Frame, FrameResources, RenderGraph, ResourceDesc};
+use render_scale::Upscaler;
+use run_config::{FrameSchedule, RunConfig};
+use sampler::{SamplerCache, SamplerDesc, TextureFiltering};
+use settings::{Debounce, SettingsFile};
+use shader::ShaderError;
+use shadow::ShadowMap;
+use skybox::Skybox;
+use split_screen::{SplitMode, Viewport, MAX_VIEWS};
+use storage::StorageSupport;
+use texture::Texture;
+use tonemap::Tonemap;
+use touch::TouchGestures;
+use uniform::{UniformBuffer, UniformSlots};
+use upload::UploadArena;
+
+const NUM_INSTANCES_PER_ROW: u32 = 10;
+const INSTANCE_DISPLACEMENT: glam::Vec3 = glam::Vec3::new(
+    NUM_INSTANCES_PER_ROW as f32 * 0.5,
+    0.0,
+    NUM_INSTANCES_PER_ROW as f32 * 0.5,
+);
+
+/// A grid of models, each rotated a bit differently.
+fn create_instance_grid() -> Vec<Instance> {
+    (0..NUM_INSTANCES_PER_ROW)
+        .flat_map(|z| {
+            (0..NUM_INSTANCES_PER_ROW).map(move |x| {
+                let position = glam::Vec3::new(x as f32, 0.0, z as f32) - INSTANCE_DISPLACEMENT;
+
+                let rotation = if position == glam::Vec3::ZERO {
+                    // this is needed so an object at (0, 0, 0) won't get scaled to zero
+                    // as Quats can affect scale if they're not created correctly
+                    glam::Quat::IDENTITY
+                } else {
+                    glam::Quat::from_axis_angle(position.normalize(), 45.0_f32.to_radians())
+                };
+
+                Instance { position, rotation }
+            })
+        })
+        .collect()
+}
+
+/// A white light casting the shadows, with a dimmer red and blue one.
+fn create_lights(max_lights: Option<usize>) -> Lights {
+    let mut lights = Lights::new(max_lights);
+    lights.add_light(PointLight::new(
+        glam::Vec3::new(2.0, 2.0, 2.0),
+        glam::Vec3::ONE,
+    ));
+    for (position, color) in [
+        (
+            glam::Vec3::new(-3.0, 1.5, -1.0),
+            glam::Vec3::new(1.0, 0.2, 0.2),
+        ),
+        (
+            glam::Vec3::new(1.0, 1.5, -3.0),
+            glam::Vec3::new(0.2, 0.4, 1.0),
+        ),
+    ] {
+        lights.add_light(PointLight {
+            intensity: 0.8,
+            radius: 8.0,
+            ..PointLight::new(position, color)
+        });
+    }
+    lights
+}
+
+/// How fast the lights orbit around the scene, in degrees per second.
+const LIGHT_ORBIT_SPEED: f32 = 60.0;
+
+/// The longest frame `State::update` simulates. Longer frames, e.g. the first
+/// one after the window was minimized, are treated as if they took this long
+/// so things don't jump around.
+pub const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// 4x MSAA is guaranteed to be supported by every WebGPU implementation.
+const DEFAULT_SAMPLE_COUNT: u32 = 4;
+
+/// How much closer one line of scrolling brings the camera, as a fraction of
+/// its distance to the target.
+const ZOOM_PER_LINE: f32 = 0.1;
+
+/// How far the camera turns per unit of raw mouse motion, in degrees. The
+/// unit depends on the platform but is usually close to a pixel.
+const MOUSE_SENSITIVITY: f32 = 0.1;
+
+/// How far the camera orbits per pixel a finger drags, in degrees.
+const TOUCH_ORBIT_SENSITIVITY: f32 = 0.3;
+
+/// How fast the look axes turn the camera when fully pushed, e.g. by a
+/// gamepad's right stick, in degrees per second.
+const LOOK_SPEED: f32 = 120.0;
+
+/// How far around the target the second view's camera starts out, in
+/// degrees.
+const SIDE_VIEW_YAW: f32 = 90.0;
+
+/// The bind group layouts shared between the pipelines, kept around so the
+/// pipelines can be rebuilt.
+struct BindGroupLayouts {
+    materials: MaterialLayouts,
+    camera: wgpu::BindGroupLayout,
+    light: wgpu::BindGroupLayout,
+    /// How `light` binds the lights, the shaders are adapted to it.
+    light_binding: LightBinding,
+    shadow: wgpu::BindGroupLayout,
+}
+
+/// Every pipeline the scene is drawn with, see `State::create_pipelines`.
+struct Pipelines {
+    scene: ScenePipelines,
+    skybox: Option<wgpu::RenderPipeline>,
+    particles: Option<ParticlePipelines>,
+    outline: OutlinePipelines,
+    debug_draw: DebugDrawPipelines,
+}
+
+struct ScenePipelines {
+    render: wgpu::RenderPipeline,
+    /// `None` if the device can't draw lines instead of triangles.
+    wireframe: Option<wgpu::RenderPipeline>,
+    /// The same two for skinned models.
+    skinned: wgpu::RenderPipeline,
+    skinned_wireframe: Option<wgpu::RenderPipeline>,
+    light: wgpu::RenderPipeline,
+    plasma: wgpu::RenderPipeline,
+    grid: wgpu::RenderPipeline,
+    debug: DebugPipelines,
+}
+
+/// What the debug views draw the model with, see `debug_view`.
+struct DebugPipelines {
+    overdraw: wgpu::RenderPipeline,
+    skinned_overdraw: wgpu::RenderPipeline,
+    /// `None` like the wireframe pipelines.
+    wireframe_overlay: Option<wgpu::RenderPipeline>,
+    skinned_wireframe_overlay: Option<wgpu::RenderPipeline>,
+}
+
+impl DebugPipelines {
+    fn overdraw(&self, model: &Model) -> &wgpu::RenderPipeline {
+        if model.is_skinned() {
+            &self.skinned_overdraw
+        } else {
+            &self.overdraw
+        }
+    }
+
+    fn wireframe_overlay(&self, model: &Model) -> Option<&wgpu::RenderPipeline> {
+        if model.is_skinned() {
+            self.skinned_wireframe_overlay.as_ref()
+        } else {
+            self.wireframe_overlay.as_ref()
+        }
+    }
+}
+
+/// Creates the main render pipeline, its wireframe variant, the skinned
+/// variants of both, the one drawing the light source, the plasma
+/// background, the ground grid and the debug views' pipelines.
+fn create_scene_pipelines(
+    device: &wgpu::Device,
+    layouts: &BindGroupLayouts,
+    targets: RenderTargets,
+    blend: wgpu::BlendState,
+    sources: &ShaderSources,
+) -> Result<ScenePipelines, ShaderError> {
+    // The wireframe and skinned pipelines only differ in their polygon mode
+    // and vertex shader, so the shader is shared
+    let main_source = light::shader_source(&sources.main, layouts.light_binding);
+    let main_source = animation::shader_source(&main_source, layouts.materials.joint_binding);
+    let shader = shader::create_shader_module(device, "Shader", "shader.wgsl", &main_source)?;
+    let scene_layouts = [
+        &layouts.camera,
+        &layouts.materials.material,
+        &layouts.light,
+        &layouts.shadow,
+    ];
+    let skinned_layouts = [
+        &layouts.camera,
+        &layouts.materials.skinned,
+        &layouts.light,
+        &layouts.shadow,
+    ];
+    let render = PipelineBuilder::with_targets("Render Pipeline", targets)
+        .shader_module(&shader)
+        .bind_group_layouts(&scene_layouts)
+        .color_target(targets.color_format, Some(blend))
+        .vertex_buffer(ModelVertex::desc())
+        .vertex_buffer(InstanceRaw::desc());
+    let render_pipeline = render.build(device)?;
+    let skinned = render
+        .clone()
+        .label("Skinned Render Pipeline")
+        .vertex_entry_point("vs_skinned")
+        .bind_group_layouts(&skinned_layouts)
+        .vertex_buffer(SkinVertex::desc());
+    let skinned_pipeline = skinned.build(device)?;
+    let wireframe = |pipeline: &PipelineBuilder, label| {
+        device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+            .then(|| {
+                pipeline
+                    .clone()
+                    .label(label)
+                    .polygon_mode(wgpu::PolygonMode::Line)
+                    .build(device)
+            })
+            .transpose()
+    };
+    let wireframe_pipeline = wireframe(&render, "Wireframe Render Pipeline")?;
+    let skinned_wireframe_pipeline = wireframe(&skinned, "Skinned Wireframe Render Pipeline")?;
+
+    // Every fragment adds 1, hidden or not
+    let overdraw = |pipeline: &PipelineBuilder, label| {
+        pipeline
+            .clone()
+            .label(label)
+            .fragment_entry_point("fs_overdraw")
+            .color_target(
+                targets.color_format,
+                Some(BlendMode::Additive.blend_state()),
+            )
+            .depth_test(wgpu::CompareFunction::Always, false)
+            .build(device)
+    };
+    // The lines go on top of the filled model, pulled towards the camera so
+    // they don't fight with its triangles
+    let wireframe_overlay = |pipeline: &PipelineBuilder, label| {
+        wireframe(
+            &pipeline
+                .clone()
+                .fragment_entry_point("fs_wireframe")
+                .depth_test(wgpu::CompareFunction::LessEqual, false)
+                .depth_bias(wgpu::DepthBiasState {
+                    constant: -2,
+                    slope_scale: -1.0,
+                    clamp: 0.0,
+                }),
+            label,
+        )
+    };
+    let debug = DebugPipelines {
+        overdraw: overdraw(&render, "Overdraw Render Pipeline")?,
+        skinned_overdraw: overdraw(&skinned, "Skinned Overdraw Render Pipeline")?,
+        wireframe_overlay: wireframe_overlay(&render, "Wireframe Overlay Render Pipeline")?,
+        skinned_wireframe_overlay: wireframe_overlay(
+            &skinned,
+            "Skinned Wireframe Overlay Render Pipeline",
+        )?,
+    };
+
+    // The light pipeline shares the camera bind group layout with the
+    // main pipeline.
+    let light_render_pipeline = PipelineBuilder::with_targets("Light Render Pipeline", targets)
+        .shader(
+            "light.wgsl",
+            &light::shader_source(&sources.light, layouts.light_binding),
+        )
+        .bind_group_layouts(&[&layouts.camera, &layouts.light])
+        .color_target(targets.color_format, Some(blend))
+        .vertex_buffer(ModelVertex::desc())
+        .build(device)?;
+
+    // Only reads the globals next to the camera
+    let plasma_pipeline = PipelineBuilder::with_targets("Plasma Pipeline", targets)
+        .shader("plasma.wgsl", &sources.plasma)
+        .bind_group_layouts(&[&layouts.camera])
+        // In the background like the skybox
+        .depth_test(wgpu::CompareFunction::LessEqual, false)
+        .build(device)?;
+    let grid_pipeline = grid::create_pipeline(device, targets, &layouts.camera)?;
+
+    Ok(ScenePipelines {
+        render: render_pipeline,
+        wireframe: wireframe_pipeline,
+        skinned: skinned_pipeline,
+        skinned_wireframe: skinned_wireframe_pipeline,
+        light: light_render_pipeline,
+        plasma: plasma_pipeline,
+        grid: grid_pipeline,
+        debug,
+    })
+}
+
+/// Where the first light is, which casts the shadows. Without lights there
+/// aren't any shadows to see anyway.
+fn shadow_light_position(lights: &Lights) -> glam::Vec3 {
+    lights
+        .iter()
+        .next()
+        .map_or(glam::Vec3::Y, |(_, light)| light.position)
+}
+
+/// Creates the depth buffer with `sample_count` samples, and the MSAA target
+/// if there is more than one.
+fn insert_scene_targets(resources: &mut FrameResources, device: &wgpu::Device, sample_count: u32) {
+    resources.insert(
+        device,
+        render_graph::DEPTH,
+        // Only the depth debug view samples it, and the GL backend can't
+        // create multisampled depth-stencil textures that could be
+        ResourceDesc::frame(
+            Texture::DEPTH_FORMAT,
+            if sample_count > 1 {
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+            } else {
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+            },
+        )
+        .with_sample_count(sample_count),
+    );
+    if sample_count > 1 {
+        resources.insert(
+            device,
+            render_graph::MSAA,
+            msaa::target_desc(HDR_FORMAT, sample_count),
+        );
+    } else {
+        resources.remove(render_graph::MSAA);
+    }
+}
+
+/// How much lighter the background gets while a file is dragged over the
+/// window.
+const DROP_HINT_AMOUNT: f64 = 0.15;
+
+/// The name of a dropped file without the directories, for the HUD.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+const HUD_FONT_SIZE: f32 = 14.0;
+const HUD_MARGIN: f32 = 8.0;
+
+/// Puts the debug info in the bottom left corner, out of the way of the
+/// debug overlay, with how the dropped files are doing above it.
+#[allow(clippy::too_many_arguments)]
+fn queue_hud_text(
+    hud: &mut Hud,
+    stats: Option<FrameStats>,
+    gpu_info: &GpuInfo,
+    camera_eye: glam::Vec3,
+    tonemap: Tonemap,
+    cull_stats: CullStats,
+    culling_frozen: bool,
+    debug_view: DebugView,
+    drop_status: Option<String>,
+) {
+    let fps = match stats {
+        Some(stats) => format!(
+            "{:.0} FPS, {:.2} ms encoding",
+            stats.fps,
+            stats.avg_encode_time.as_secs_f64() * 1000.0
+        ),
+        None => "-- FPS".to_owned(),
+    };
+    let mut text = format!(
+        "{fps}\n{}\nApp GPU memory: {}\nCamera: ({:.2}, {:.2}, {:.2})\nTonemap: {}\nMeshes: {} drawn, {} culled{}",
+        gpu_info.describe(),
+        gpu_memory::format_bytes(gpu_memory::allocated()),
+        camera_eye.x,
+        camera_eye.y,
+        camera_eye.z,
+        tonemap.describe(),
+        cull_stats.drawn,
+        cull_stats.culled,
+        if culling_frozen { " (frozen)" } else { "" }
+    );
+    if debug_view != DebugView::None {
+        text.push_str(&format!("\nDebug view: {}", debug_view.name()));
+    }
+    let line_count = text.lines().count() as f32;
+    let height = line_count * HUD_FONT_SIZE * hud::LINE_HEIGHT;
+    let position = glam::Vec2::new(HUD_MARGIN, hud.logical_size().y - height - HUD_MARGIN);
+    hud.queue_text(position, text, HUD_FONT_SIZE, [255, 255, 255, 255]);
+
+    if let Some(drop_status) = drop_status {
+        let height = drop_status.lines().count() as f32 * HUD_FONT_SIZE * hud::LINE_HEIGHT;
+        let position = position - glam::Vec2::new(0.0, height);
+        hud.queue_text(position, drop_status, HUD_FONT_SIZE, [255, 200, 80, 255]);
+    }
+}
 
-struct State {
-    surface: wgpu::Surface,
+/// The instance is a handle to our GPU
+/// Backends::all => Vulkan + Metal + DX12 + Browser WebGPU
+/// `DeviceConfig::backends` limits it to some of them.
+fn create_instance(backends: wgpu::Backends) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    })
+}
+
+/// Requests a device with the features and limits of `config`. Fails with
+/// `AppError::MissingFeatures` if the adapter lacks required features, wgpu
+/// would only tell us that the request failed.
+async fn request_device(
+    adapter: &wgpu::Adapter,
+    config: &DeviceConfig,
+) -> Result<(wgpu::Device, wgpu::Queue), AppError> {
+    let features =
+        config
+            .features_for(adapter.features())
+            .map_err(|missing| AppError::MissingFeatures {
+                adapter: adapter::describe_adapter(&adapter.get_info()),
+                missing,
+            })?;
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                required_features: features,
+                required_limits: config.limits.clone(),
+                label: None,
+            },
+            trace_dir(config).as_deref(),
+        )
+        .await
+        .map_err(|source| AppError::RequestDevice {
+            adapter: adapter::describe_adapter(&adapter.get_info()),
+            source,
+        })
+}
+
+/// Where the API trace of a new device goes, `None` without one or if the
+/// directory can't be written to.
+fn trace_dir(config: &DeviceConfig) -> Option<PathBuf> {
+    let dir = config.trace_dir()?;
+    if cfg!(target_arch = "wasm32") {
+        log::warn!("API traces aren't supported on the web, not tracing");
+        return None;
+    }
+    match device_config::prepare_trace_dir(&dir) {
+        Ok(()) => {
+            log::info!("Writing a wgpu API trace to {}", dir.display());
+            Some(dir)
+        }
+        Err(err) => {
+            log::warn!(
+                "Can't write a wgpu API trace to {}, continuing without one: {err}",
+                dir.display()
+            );
+            None
+        }
+    }
+}
+
+/// Picks the format and present mode of a window surface and describes it
+/// at `size`, without configuring it yet.
+fn surface_config(
+    surface: &wgpu::Surface,
+    adapter: &wgpu::Adapter,
+    size: dpi::PhysicalSize<u32>,
+    present_mode: PresentModePreference,
+) -> Result<wgpu::SurfaceConfiguration, AppError> {
+    let surface_caps = surface.get_capabilities(adapter);
+    // Our shaders output linear colors, which have to be sRGB encoded on the
+    // way to the screen or they come out too dark. Non-sRGB surfaces get an
+    // sRGB view if possible, and shaders that encode the colors otherwise.
+    let srgb_views = adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::SURFACE_VIEW_FORMATS);
+    let Some((surface_format, encoding)) =
+        color::select_surface_format(&surface_caps.formats, srgb_views)
+    else {
+        return Err(AppError::SurfaceConfig {
+            adapter: adapter::describe_adapter(&adapter.get_info()),
+            reason: "the surface supports no formats".to_owned(),
+        });
+    };
+
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: surface_format,
+        // The surface can't be 0 sized, which the window may be before
+        // it's shown (e.g. a canvas that isn't laid out yet on the web)
+        width: size.width.max(1),
+        height: size.height.max(1),
+        present_mode: present_mode::select_present_mode(present_mode, &surface_caps.present_modes),
+        // Transparency is turned on afterwards, see `State::set_transparent`
+        alpha_mode: alpha_mode::select_alpha_mode(false, &surface_caps.alpha_modes),
+        view_formats: encoding.view_formats(surface_format),
+        desired_maximum_frame_latency: 2,
+    };
+    Ok(config)
+}
+
+/// What the render loop should do after `State::render` failed, see
+/// `State::handle_surface_error`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceErrorAction {
+    /// The surface was reconfigured, the next frame should work.
+    Reconfigured,
+    /// Nothing to fix, try again next frame.
+    SkipFrame,
+    /// The error can't be recovered from.
+    Exit,
+}
+
+/// The GPU objects a `State` gets built from.
+struct GpuParts {
+    instance: Arc<wgpu::Instance>,
+    adapter: Arc<wgpu::Adapter>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    /// What `device` was requested with.
+    device_config: DeviceConfig,
+}
+
+/// Runs before the app exits, see `State::set_on_exit`.
+pub type ExitHook = Box<dyn FnMut(&mut State)>;
+
+fn log_surface_error(err: wgpu::SurfaceError, action: SurfaceErrorAction) {
+    // Outdated happens all the time while resizing or moving the window
+    // to another monitor, so it's not worth more than a debug message
+    let level = match err {
+        wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Timeout => log::Level::Debug,
+        wgpu::SurfaceError::Lost => log::Level::Warn,
+        wgpu::SurfaceError::OutOfMemory => log::Level::Error,
+    };
+    log::log!(level, "Surface error: {err}, action taken: {action:?}");
+}
+
+pub struct State {
+    /// `None` for headless states, and while the app is suspended.
+    surface: Option<wgpu::Surface<'static>>,
+    // Shared with the state `recreate_device` builds
+    instance: Arc<wgpu::Instance>,
+    adapter: Arc<wgpu::Adapter>,
     device: wgpu::Device,
     queue: wgpu::Queue,
+    /// What the device was requested with, for `recreate_device`.
+    device_config: DeviceConfig,
+    gpu_errors: GpuErrorHandlers,
+    /// How many frames were rendered, for the GPU error messages.
+    frame_index: u64,
     config: wgpu::SurfaceConfiguration,
     size: dpi::PhysicalSize<u32>,
-    // The window must be declared after the surface so
-    // it gets dropped after it as the surface contains
-    // unsafe references to the window's resources.
-    window: Window,
+    /// Nothing gets rendered while the window is minimized.
+    minimized: MinimizeTracker,
+    redraw_mode: RedrawMode,
+    frame_limiter: FrameLimiter,
+    /// Steps the lights and animations, see `fixed_update`.
+    fixed_timestep: FixedTimestep,
+    // The surface keeps its own reference to the window, which is why
+    // it's shared.
+    window: Option<Arc<Window>>,
+    /// Set once the shutdown started, exiting again quits right away.
     exiting: bool,
+    /// Runs once before exiting, see `set_on_exit`.
+    on_exit: Option<ExitHook>,
     clear_color: wgpu::Color,
+    render_pipeline: wgpu::RenderPipeline,
+    /// `None` without `Features::POLYGON_MODE_LINE`.
+    wireframe_pipeline: Option<wgpu::RenderPipeline>,
+    /// Whether the model is drawn with `wireframe_pipeline`.
+    wireframe: bool,
+    skinned_pipeline: wgpu::RenderPipeline,
+    skinned_wireframe_pipeline: Option<wgpu::RenderPipeline>,
+    debug_pipelines: DebugPipelines,
+    /// See `set_debug_view`.
+    debug_view: DebugView,
+    /// Shows the targets for the debug views that need them.
+    debug_view_renderer: DebugViewRenderer,
+    obj_model: Option<Model>,
+    /// Where the model, its textures, the skybox and the reloaded shaders
+    /// are read from.
+    source: Arc<dyn AssetSource>,
+    /// Loads the model's textures in the background, see `receive_assets`.
+    asset_loader: AssetLoader,
+    /// The textures of the models, see `Model::load_obj`.
+    assets: Assets,
+    /// Where the model came from if `load_gltf` or a dropped file replaced
+    /// the cube, so `recreate_device` can load it again.
+    model_path: Option<PathBuf>,
+    /// Reads the files dropped onto the window, see `drop_file`.
+    drop_loader: DropLoader,
+    /// Why the last dropped file couldn't be used, shown in the HUD.
+    drop_error: Option<String>,
+    /// Whether a file is dragged over the window, which lightens the
+    /// background.
+    file_hovered: bool,
+    /// The camera of each view, the first one is the only one without a
+    /// split screen.
+    cameras: [Camera; MAX_VIEWS],
+    camera_uniforms: [CameraUniform; MAX_VIEWS],
+    /// A slot for each view's camera, see `camera_binding`.
+    camera_buffer: UniformSlots<CameraUniform>,
+    split_mode: SplitMode,
+    /// Which view's camera the keys, the mouse and the fingers move, see
+    /// `focused_view`.
+    focused_view: usize,
+    /// The camera, the lights and the instances are uploaded through it
+    /// every frame.
+    uploads: UploadArena,
+    /// Advanced every update, see `globals`.
+    globals: Globals,
+    globals_buffer: UniformBuffer<GlobalsUniform>,
+    /// Binds the camera and the globals at group 0.
+    camera_bind_group: wgpu::BindGroup,
+    camera_controller: CameraController,
+    input: InputState,
+    /// The named actions and axes the camera, exiting and fullscreen go by.
+    input_map: InputMap,
+    /// One finger orbits, two pinch to zoom and drag to pan.
+    touch: TouchGestures,
+    /// Whether the cursor is grabbed and mouse motion turns the camera.
+    mouse_look: bool,
+    camera_mode: CameraMode,
+    /// Moves the focused camera in `CameraMode::Orbit`.
+    orbit_camera: OrbitCamera,
+    /// `None` if gamepads aren't supported on this platform.
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    gamepad: Option<gamepad::GamepadInput>,
+    /// Set when something other than a window event asks to exit, see
+    /// `exit_requested`.
+    exit_requested: bool,
+    /// Set by `Action::OpenWindow`, see `take_open_window_request`.
+    open_window_requested: bool,
+    key_bindings: KeyBindings,
+    instances: SteppedInstances,
+    /// The instances where they're drawn this frame, between the last two
+    /// steps.
+    drawn_instances: Vec<Instance>,
+    /// All the instances, for the passes that can't be culled by the
+    /// camera: shadows are cast from outside the view too.
+    instance_buffer: InstanceBuffer,
+    /// The instances inside the view, mesh by mesh, see `culled`.
+    visible_instance_buffer: InstanceBuffer,
+    culled: Culled,
+    /// Culling keeps using this camera while it's set, see
+    /// `Action::FreezeCulling`.
+    culling_camera: Option<Camera>,
+    /// Replays the draws of the model while they stay the same, see
+    /// `set_render_bundles`.
+    render_bundles: bool,
+    scene_bundle: Option<SceneBundle>,
+    /// Left clicks read back what's under the cursor through it.
+    picking: Picking,
+    /// The instance with the outline, see `set_selection`.
+    selection: Option<u32>,
+    outline: Outline,
+    /// Lines drawn during `update`, see `debug_draw`.
+    debug_draw: DebugDraw,
+    /// Where `texture_filtering` gets its samplers.
+    sampler_cache: SamplerCache,
+    /// How the model's first material is sampled, see
+    /// `set_texture_filtering`.
+    texture_filtering: Option<TextureFiltering>,
+    lights: Lights,
+    light_buffers: LightBuffers,
+    light_render_pipeline: wgpu::RenderPipeline,
+    plasma_pipeline: wgpu::RenderPipeline,
+    /// Whether `plasma_pipeline` draws the background instead of the skybox.
+    plasma: bool,
+    grid_pipeline: wgpu::RenderPipeline,
+    /// Whether `grid_pipeline` draws the ground grid, see `grid`.
+    grid: bool,
+    shadow_map: ShadowMap,
+    /// When there is no skybox the background is just the clear color.
+    skybox: Option<Skybox>,
+    /// `None` where compute shaders aren't supported, like on WebGL2.
+    particles: Option<ParticleSystem>,
+    /// `None` without timestamp queries.
+    profiler: Option<GpuProfiler>,
+    bind_group_layouts: BindGroupLayouts,
+    sample_count: u32,
+    /// The passes of every frame, see `render_graph`.
+    render_graph: RenderGraph,
+    /// The depth, HDR, MSAA and shadow map textures the passes share.
+    frame_resources: FrameResources,
+    /// Draws the HDR resource the scene is rendered into to the frame.
+    post_processor: PostProcessor,
+    /// The size the scene renders at compared to the window's, see
+    /// `set_render_scale`.
+    render_scale: f32,
+    /// Draws the scaled frame into the real one.
+    upscaler: Upscaler,
+    /// Smooths `render_graph::LDR` into the frame while the graph has the
+    /// FXAA node.
+    fxaa: Fxaa,
+    pending_screenshots: Vec<PendingScreenshot>,
+    elapsed: Duration,
+    frame_counter: FrameCounter,
+    fullscreen: FullscreenToggle,
+    modifiers: winit::keyboard::ModifiersState,
+    /// `None` for headless states.
+    debug_overlay: Option<DebugOverlay>,
+    /// `None` for headless states.
+    hud: Option<Hud>,
+    /// `None` while hidden, see `set_frame_time_graph`.
+    frame_time_graph: Option<FrameTimeGraph>,
+    gpu_info: GpuInfo,
+    storage_support: StorageSupport,
+    /// How long the last `update` took, only measured for the graph.
+    update_time: Duration,
+    /// The sources the pipelines were last built from.
+    shader_sources: ShaderSources,
+    /// Only watches for changes with a window, `None` if watching failed.
+    #[cfg(not(target_arch = "wasm32"))]
+    shader_watcher: Option<shader_watcher::ShaderWatcher>,
 }
 
 impl State {
-    async fn new(window: Window) -> Result<Self, Box<dyn Error>> {
+    /// `sample_count` is the requested MSAA sample count (1, 2, 4 or 8). If
+    /// the surface format doesn't support it the highest supported count
+    /// below it is used instead.
+    ///
+    /// `present_mode` is mapped to a present mode the surface supports, see
+    /// `present_mode::select_present_mode`.
+    ///
+    /// The device gets the features and limits of `device_config`.
+    pub async fn new(
+        window: Window,
+        sample_count: u32,
+        present_mode: PresentModePreference,
+        adapter_selection: AdapterSelection,
+        device_config: &DeviceConfig,
+    ) -> Result<Self, AppError> {
+        let window = Arc::new(window);
         let size = window.inner_size();
 
-        // The instance is a handle to our GPU
-        // Backends::all => Vulkan + Metal + DX12 + Browser WebGPU
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: wgpu::Dx12Compiler::default(),
-        });
+        let instance = create_instance(device_config.backends);
 
-        // # Safety
-        //
-        // The surface needs to live as long as the window that created it.
-        // State owns the window so this should be safe.
-        let surface = unsafe { instance.create_surface(&window) }?;
-
-        let adapter: Result<wgpu::Adapter, String> = match instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::LowPower,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-        {
-            Some(adapter) => Ok(adapter),
-            None => Err("No adapter found for that surface".into()),
+        // The surface needs to live as long as the window that created it,
+        // giving it a reference to the window makes sure it does.
+        let surface = instance.create_surface(Arc::clone(&window))?;
+
+        let adapter =
+            adapter::select_adapter(&instance, &adapter_selection, Some(&surface)).await?;
+
+        let (device, queue) = request_device(&adapter, device_config).await?;
+
+        let config = surface_config(&surface, &adapter, size, present_mode)?;
+        surface.configure(&device, &config);
+        log::info!(
+            "Surface format: {:?} (sRGB encoding: {:?}), present mode: {:?}",
+            config.format,
+            color::srgb_encoding(&config),
+            config.present_mode
+        );
+
+        let gpu = GpuParts {
+            instance: Arc::new(instance),
+            adapter: Arc::new(adapter),
+            device,
+            queue,
+            device_config: device_config.clone(),
         };
-        let adapter = adapter?;
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
-                    // WebGL doesn't support all of wgpu's features, so if
-                    // we're building for the web we'll have to disable some.
-                    limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                    label: None,
-                },
-                None,
-            )
-            .await?;
-
-        let surface_caps = surface.get_capabilities(&adapter);
-        // Shader code in this tutorial assumes an sRGB surface texture. Using a different
-        // one will result all the colors coming out darker. If you want to support non
-        // sRGB surfaces, you'll need to account for that when drawing to the frame.
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+        Self::from_parts(Some(window), Some(surface), gpu, config, sample_count)
+    }
+
+    /// Creates a state without a window or surface, rendering into an
+    /// offscreen `Rgba8UnormSrgb` texture of the given size. Use
+    /// `render_to_vec` to get the rendered pixels.
+    pub async fn new_headless(
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Result<Self, AppError> {
+        Self::new_headless_with_encoding(width, height, sample_count, SrgbEncoding::Format).await
+    }
+
+    /// Like `new_headless`, with the features and limits of `device_config`.
+    pub async fn new_headless_with_device(
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        device_config: &DeviceConfig,
+    ) -> Result<Self, AppError> {
+        Self::headless(
+            width,
+            height,
+            sample_count,
+            SrgbEncoding::Format,
+            &AdapterSelection::default(),
+            device_config,
+        )
+        .await
+    }
+
+    /// Like `new_headless`, with the adapter, sample count and device of
+    /// `config`.
+    pub async fn new_headless_with_config(
+        width: u32,
+        height: u32,
+        config: &RunConfig,
+    ) -> Result<Self, AppError> {
+        Self::headless(
+            width,
+            height,
+            config.sample_count,
+            SrgbEncoding::Format,
+            &config.adapter,
+            &config.device,
+        )
+        .await
+    }
 
+    /// Like `new_headless`, but the colors are sRGB encoded the way they
+    /// would be on a surface needing `encoding`. The texture is
+    /// `Rgba8Unorm` unless `encoding` is `SrgbEncoding::Format`.
+    ///
+    /// `SrgbEncoding::View` fails on adapters that can't view textures with
+    /// another format, like WebGL and most other GL ones.
+    pub async fn new_headless_with_encoding(
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        encoding: SrgbEncoding,
+    ) -> Result<Self, AppError> {
+        Self::headless(
+            width,
+            height,
+            sample_count,
+            encoding,
+            &AdapterSelection::default(),
+            &DeviceConfig::default(),
+        )
+        .await
+    }
+
+    async fn headless(
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        encoding: SrgbEncoding,
+        adapter_selection: &AdapterSelection,
+        device_config: &DeviceConfig,
+    ) -> Result<Self, AppError> {
+        if width == 0 || height == 0 {
+            return Err(AppError::InvalidSize { width, height });
+        }
+
+        let instance = create_instance(device_config.backends);
+        let adapter = adapter::select_adapter(&instance, adapter_selection, None).await?;
+
+        let view_formats = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::VIEW_FORMATS);
+        if encoding == SrgbEncoding::View && !view_formats {
+            return Err(AppError::SurfaceConfig {
+                adapter: adapter::describe_adapter(&adapter.get_info()),
+                reason: "the adapter can't create sRGB views of other textures".to_owned(),
+            });
+        }
+
+        let (device, queue) = request_device(&adapter, device_config).await?;
+
+        // There is no surface, but the rest of the state only cares about
+        // the size and format of the target, so we still describe it this way.
+        let format = match encoding {
+            SrgbEncoding::Format => wgpu::TextureFormat::Rgba8UnormSrgb,
+            SrgbEncoding::View | SrgbEncoding::Shader => wgpu::TextureFormat::Rgba8Unorm,
+        };
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: encoding.view_formats(format),
+            desired_maximum_frame_latency: 2,
         };
-        surface.configure(&device, &config);
 
-        Ok(Self {
+        let gpu = GpuParts {
+            instance: Arc::new(instance),
+            adapter: Arc::new(adapter),
+            device,
+            queue,
+            device_config: device_config.clone(),
+        };
+        Self::from_parts(None, None, gpu, config, sample_count)
+    }
+
+    fn from_parts(
+        window: Option<Arc<Window>>,
+        surface: Option<wgpu::Surface<'static>>,
+        gpu: GpuParts,
+        config: wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> Result<Self, AppError> {
+        let GpuParts {
+            instance,
+            adapter,
+            device,
+            queue,
+            device_config,
+        } = gpu;
+        let size = dpi::PhysicalSize::new(config.width, config.height);
+        let gpu_info = GpuInfo::new(&adapter);
+        log::info!(
+            "Using {}, driver: {} {}",
+            adapter::describe_adapter(&gpu_info.info),
+            gpu_info.info.driver,
+            gpu_info.info.driver_info
+        );
+        log::info!(
+            "Max 2D texture size: {}, max buffer size: {}, features: {:?}",
+            gpu_info.limits.max_texture_dimension_2d,
+            gpu_info.limits.max_buffer_size,
+            gpu_info.features
+        );
+        let gpu_errors = GpuErrorHandlers::install(&device);
+
+        let camera = Camera {
+            // position the camera 5 units up and 10 units back
+            // +z is out of the screen
+            eye: (0.0, 5.0, 10.0).into(),
+            // have it look at the origin
+            target: (0.0, 0.0, 0.0).into(),
+            // which way is "up"
+            up: glam::Vec3::Y,
+            aspect: config.width as f32 / config.height as f32,
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+        };
+
+        // The second view looks at the scene from the side
+        let mut side_camera = camera;
+        side_camera.orbit(SIDE_VIEW_YAW, 0.0);
+        let cameras = [camera, side_camera];
+        let camera_uniforms = cameras.map(|camera| {
+            let mut uniform = CameraUniform::new();
+            uniform.update_view_proj(&camera);
+            uniform
+        });
+
+        let camera_buffer = UniformSlots::new(&device, "Camera Buffer", &camera_uniforms);
+        let globals = Globals::new(size);
+        let globals_buffer = UniformBuffer::new(&device, "Globals Buffer", globals.uniform());
+
+        let camera_bind_group_layout = camera::create_bind_group_layout(&device);
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera_bind_group"),
+            layout: &camera_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: globals::BINDING,
+                    resource: globals_buffer.binding(),
+                },
+            ],
+        });
+
+        let supported_sample_counts = msaa::supported_sample_counts(
+            &adapter,
+            device.features(),
+            &[HDR_FORMAT, Texture::DEPTH_FORMAT],
+        );
+        let sample_count = msaa::select_sample_count(sample_count, &supported_sample_counts);
+        let mut frame_resources =
+            FrameResources::new(dpi::PhysicalSize::new(config.width, config.height));
+        frame_resources.insert(
+            &device,
+            render_graph::HDR,
+            ResourceDesc::frame(
+                HDR_FORMAT,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            ),
+        );
+        frame_resources.insert(
+            &device,
+            render_graph::SHADOW_MAP,
+            shadow::resource_desc(shadow::DEFAULT_SHADOW_MAP_SIZE),
+        );
+        insert_scene_targets(&mut frame_resources, &device, sample_count);
+        let post_processor = PostProcessor::new(
+            &device,
+            &config,
+            frame_resources.view(render_graph::HDR),
+            vec![Box::new(post_process::Vignette)],
+        )?;
+        let targets = RenderTargets {
+            color_format: HDR_FORMAT,
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count,
+        };
+
+        let storage_support = StorageSupport::probe(&adapter, &device);
+        let light_binding = LightBinding::for_support(storage_support);
+        log::info!("Lights are bound as {light_binding:?} buffers");
+        let lights = create_lights(light_binding.max_lights());
+        let light_bind_group_layout = light::create_bind_group_layout(&device, light_binding);
+        let light_buffers = LightBuffers::new(
+            &device,
+            &queue,
+            &light_bind_group_layout,
+            light_binding,
+            &lights,
+        );
+
+        let joint_binding = JointBinding::for_support(storage_support);
+        let bind_group_layouts = BindGroupLayouts {
+            materials: MaterialLayouts::new(&device, joint_binding),
+            camera: camera_bind_group_layout,
+            light: light_bind_group_layout,
+            light_binding,
+            shadow: shadow::create_bind_group_layout(&device),
+        };
+        let shader_sources = ShaderSources::embedded();
+        let pipeline_scope = gpu_errors.context().scope("pipeline creation");
+        let ScenePipelines {
+            render: render_pipeline,
+            wireframe: wireframe_pipeline,
+            skinned: skinned_pipeline,
+            skinned_wireframe: skinned_wireframe_pipeline,
+            light: light_render_pipeline,
+            plasma: plasma_pipeline,
+            grid: grid_pipeline,
+            debug: debug_pipelines,
+        } = create_scene_pipelines(
+            &device,
+            &bind_group_layouts,
+            targets,
+            alpha_mode::blend_state(config.alpha_mode),
+            &shader_sources,
+        )?;
+
+        let shadow_map = ShadowMap::new(
+            &device,
+            &bind_group_layouts.shadow,
+            frame_resources.view(render_graph::SHADOW_MAP),
+            shadow_light_position(&lights),
+        )?;
+        drop(pipeline_scope);
+
+        let source = asset_source::default_source();
+        let skybox_faces =
+            ["px", "nx", "py", "ny", "pz", "nz"].map(|face| format!("assets/skybox/{face}.png"));
+        let upload_scope = gpu_errors.context().scope("texture upload");
+        let skybox = match Texture::cubemap_from_files(
+            &device,
+            &queue,
+            source.as_ref(),
+            &skybox_faces,
+            "skybox",
+        ) {
+            Ok(texture) => Some(Skybox::new(
+                &device,
+                targets,
+                &bind_group_layouts.camera,
+                texture,
+                &shader_sources.skybox,
+            )?),
+            Err(err) => {
+                log::warn!("Failed to load the skybox, using the clear color instead: {err}");
+                None
+            }
+        };
+        drop(upload_scope);
+
+        let particles = if compute::is_supported(&adapter, &device) {
+            Some(ParticleSystem::new(
+                &device,
+                compute::DEFAULT_PARTICLE_COUNT,
+                targets,
+                &bind_group_layouts.camera,
+                &shader_sources.particles,
+            )?)
+        } else {
+            log::info!("Compute shaders aren't supported, not simulating particles");
+            None
+        };
+
+        let profiler = GpuProfiler::new(&device, &queue);
+
+        let instances = create_instance_grid();
+        let instance_buffer = InstanceBuffer::new(&device, &instances);
+        // Filled in by update_culling
+        let visible_instance_buffer = InstanceBuffer::new(&device, &[]);
+
+        let upload_scope = gpu_errors.context().scope("texture upload");
+        let mut asset_loader = AssetLoader::new(Arc::clone(&source));
+        let mut assets = Assets::new();
+        let obj_model = match Model::load_obj_async(
+            source.as_ref(),
+            "assets/cube/cube.obj",
+            &device,
+            &queue,
+            &bind_group_layouts.materials.material,
+            &mut asset_loader,
+            &mut assets,
+        ) {
+            Ok(model) => model,
+            Err(err) => {
+                log::warn!("Failed to load the model, drawing a generated cube: {err}");
+                Model::from_geometry(
+                    &device,
+                    &queue,
+                    &bind_group_layouts.materials.material,
+                    "cube",
+                    &geometry::cube(),
+                )
+            }
+        };
+        drop(upload_scope);
+
+        let pipeline_scope = gpu_errors.context().scope("pipeline creation");
+        let picking = Picking::new(&device, &config, &bind_group_layouts.camera)?;
+        let outline = Outline::new(&device, targets, &bind_group_layouts.camera)?;
+        let debug_draw = DebugDraw::new(&device, targets, &bind_group_layouts.camera)?;
+        drop(pipeline_scope);
+
+        let debug_overlay = window
+            .as_deref()
+            .map(|window| DebugOverlay::new(&device, window, color::render_format(&config)));
+        let hud = window.as_deref().map(|window| {
+            Hud::new(
+                &device,
+                &queue,
+                color::render_format(&config),
+                size,
+                window.scale_factor(),
+            )
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let shader_watcher = window
+            .as_ref()
+            .and_then(|_| source.file_path(Path::new("shaders")))
+            .and_then(|dir| {
+                shader_watcher::ShaderWatcher::new(&dir)
+                    .map_err(|err| log::warn!("Not watching the shaders for changes: {err}"))
+                    .ok()
+            });
+
+        let sampler_cache = SamplerCache::new(sampler::max_anisotropy(&adapter));
+        let upscaler = Upscaler::new(&device, color::render_format(&config))?;
+        let fxaa = Fxaa::new(&device, color::render_format(&config))?;
+        let debug_view_renderer = DebugViewRenderer::new(&device, color::render_format(&config))?;
+
+        let mut state = Self {
             window,
             surface,
+            instance,
+            adapter,
             device,
             queue,
+            device_config,
+            gpu_errors,
+            frame_index: 0,
             config,
             size,
+            minimized: MinimizeTracker::default(),
+            redraw_mode: RedrawMode::default(),
+            frame_limiter: FrameLimiter::default(),
+            fixed_timestep: FixedTimestep::default(),
             exiting: false,
-            clear_color: wgpu::Color {
-                r: 0.1,
-                g: 0.2,
-                b: 0.3,
-                a: 1.0,
-            },
-        })
+            on_exit: None,
+            clear_color: color::PRESETS[0],
+            render_pipeline,
+            wireframe_pipeline,
+            wireframe: false,
+            skinned_pipeline,
+            skinned_wireframe_pipeline,
+            debug_pipelines,
+            debug_view: DebugView::None,
+            debug_view_renderer,
+            obj_model: Some(obj_model),
+            source,
+            asset_loader,
+            assets,
+            model_path: None,
+            drop_loader: DropLoader::new(),
+            drop_error: None,
+            file_hovered: false,
+            cameras,
+            camera_uniforms,
+            camera_buffer,
+            split_mode: SplitMode::Single,
+            focused_view: 0,
+            uploads: UploadArena::default(),
+            globals,
+            globals_buffer,
+            camera_bind_group,
+            camera_controller: CameraController::new(settings::DEFAULT_CAMERA_SPEED),
+            input: InputState::default(),
+            input_map: InputMap::default(),
+            touch: TouchGestures::default(),
+            mouse_look: false,
+            camera_mode: CameraMode::Fly,
+            orbit_camera: OrbitCamera::new(&cameras[0]),
+            #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+            gamepad: gamepad::GamepadInput::new(),
+            exit_requested: false,
+            open_window_requested: false,
+            key_bindings: KeyBindings::default(),
+            drawn_instances: instances.clone(),
+            instances: SteppedInstances::new(instances),
+            instance_buffer,
+            visible_instance_buffer,
+            culled: Culled::default(),
+            culling_camera: None,
+            render_bundles: true,
+            scene_bundle: None,
+            picking,
+            selection: None,
+            outline,
+            debug_draw,
+            sampler_cache,
+            texture_filtering: None,
+            lights,
+            light_buffers,
+            light_render_pipeline,
+            plasma_pipeline,
+            plasma: false,
+            grid_pipeline,
+            grid: false,
+            shadow_map,
+            skybox,
+            particles,
+            profiler,
+            bind_group_layouts,
+            sample_count,
+            render_graph: RenderGraph::with_default_nodes(),
+            frame_resources,
+            post_processor,
+            render_scale: 1.0,
+            upscaler,
+            fxaa,
+            pending_screenshots: Vec::new(),
+            elapsed: Duration::ZERO,
+            frame_counter: FrameCounter::default(),
+            fullscreen: FullscreenToggle::default(),
+            modifiers: winit::keyboard::ModifiersState::empty(),
+            debug_overlay,
+            hud,
+            frame_time_graph: None,
+            gpu_info,
+            storage_support,
+            update_time: Duration::ZERO,
+            shader_sources,
+            #[cfg(not(target_arch = "wasm32"))]
+            shader_watcher,
+        };
+        let mut encoder = state.create_upload_encoder();
+        state.update_culling(&mut encoder);
+        state.submit_uploads(encoder);
+        // Headless frames should look the same however fast the textures
+        // load
+        #[cfg(not(target_arch = "wasm32"))]
+        if state.window.is_none() {
+            state.wait_for_assets();
+        }
+        Ok(state)
+    }
+
+    /// The window we render to, `None` for headless states.
+    pub fn window(&self) -> Option<&Window> {
+        self.window.as_deref()
+    }
+
+    /// The background color, in linear space whatever the surface format.
+    pub fn clear_color(&self) -> wgpu::Color {
+        self.clear_color
+    }
+
+    pub fn set_clear_color(&mut self, clear_color: wgpu::Color) {
+        self.clear_color = clear_color;
+    }
+
+    /// The camera of the first view.
+    pub fn camera(&self) -> &Camera {
+        &self.cameras[0]
+    }
+
+    /// The camera can be moved freely, the uniform buffer is refreshed in
+    /// `update()` whenever it changed.
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.cameras[0]
+    }
+
+    /// The camera of `view`, which is there even while the frame isn't
+    /// split.
+    ///
+    /// # Panics
+    ///
+    /// If `view` isn't below `split_screen::MAX_VIEWS`.
+    pub fn view_camera(&self, view: usize) -> &Camera {
+        &self.cameras[view]
+    }
+
+    /// Like `camera_mut`, for the camera of `view`.
+    pub fn view_camera_mut(&mut self, view: usize) -> &mut Camera {
+        &mut self.cameras[view]
+    }
+
+    pub fn split_mode(&self) -> SplitMode {
+        self.split_mode
+    }
+
+    /// Splits the frame between the views, or gives it all to the first
+    /// one. The cameras get the aspect ratios of their new viewports.
+    pub fn set_split_mode(&mut self, mode: SplitMode) {
+        self.split_mode = mode;
+        self.update_view_aspects();
+        self.request_redraw();
+    }
+
+    /// The parts of the frame each view is drawn into, in order.
+    pub fn viewports(&self) -> Vec<Viewport> {
+        self.split_mode.viewports(self.size)
+    }
+
+    fn update_view_aspects(&mut self) {
+        let viewports = self.viewports();
+        for (camera, viewport) in self.cameras.iter_mut().zip(viewports) {
+            camera.aspect = viewport.aspect();
+        }
+    }
+
+    /// The view whose camera the keys, the mouse and the fingers move. It's
+    /// always the first one while the frame isn't split.
+    pub fn focused_view(&self) -> usize {
+        if self.focused_view < self.split_mode.view_count() {
+            self.focused_view
+        } else {
+            0
+        }
+    }
+
+    /// Moves the camera of `view` from now on, if the frame has such a view.
+    /// Returns whether it does.
+    pub fn set_focused_view(&mut self, view: usize) -> bool {
+        if view >= self.split_mode.view_count() {
+            return false;
+        }
+        if view != self.focused_view() {
+            // Keys held for the old camera would keep moving the new one
+            self.camera_controller.reset();
+            self.orbit_camera = OrbitCamera::new(&self.cameras[view]);
+        }
+        self.focused_view = view;
+        true
+    }
+
+    pub fn camera_mode(&self) -> CameraMode {
+        self.camera_mode
+    }
+
+    /// Switches the controller of the focused camera. The camera stays
+    /// where it is: the orbit starts around what it's looking at, and
+    /// flying starts from where the orbit left it.
+    pub fn set_camera_mode(&mut self, mode: CameraMode) {
+        if mode == CameraMode::Orbit && self.camera_mode != mode {
+            self.orbit_camera = OrbitCamera::new(&self.cameras[self.focused_view()]);
+        }
+        self.camera_controller.reset();
+        self.camera_mode = mode;
+        self.request_redraw();
+    }
+
+    pub fn orbit_camera(&self) -> &OrbitCamera {
+        &self.orbit_camera
+    }
+
+    /// Orbits, pans and zooms the focused camera from the next `update`,
+    /// in `CameraMode::Orbit`.
+    pub fn orbit_camera_mut(&mut self) -> &mut OrbitCamera {
+        &mut self.orbit_camera
+    }
+
+    /// The box around every instance of the model, `None` without either.
+    pub fn scene_bounds(&self) -> Option<Aabb> {
+        let model = self.obj_model.as_ref()?;
+        let bounds = model
+            .meshes
+            .iter()
+            .filter_map(|mesh| mesh.bounds)
+            .reduce(Aabb::union)?;
+        self.drawn_instances
+            .iter()
+            .map(|instance| {
+                let matrix =
+                    glam::Mat4::from_rotation_translation(instance.rotation, instance.position);
+                bounds.transformed(matrix)
+            })
+            .reduce(Aabb::union)
+    }
+
+    /// Points the focused camera at the whole scene from where it's looking
+    /// from: smoothly when orbiting, right away when flying. Returns whether
+    /// there's a scene to look at.
+    pub fn focus_scene(&mut self) -> bool {
+        let Some(bounds) = self.scene_bounds() else {
+            return false;
+        };
+        let view = self.focused_view();
+        let camera = &mut self.cameras[view];
+        match self.camera_mode {
+            CameraMode::Orbit => self.orbit_camera.focus_on(bounds, camera.fovy),
+            CameraMode::Fly => OrbitPose::from_camera(camera)
+                .framing(bounds, camera.fovy)
+                .apply(camera),
+        }
+        self.request_redraw();
+        true
+    }
+
+    /// The camera bind group with the offset of the camera of `view`.
+    fn camera_binding(&self, view: usize) -> CameraBinding<'_> {
+        CameraBinding {
+            bind_group: &self.camera_bind_group,
+            offset: self.camera_buffer.offset(view),
+        }
+    }
+
+    /// The mouse as of the last `input` calls.
+    pub fn input_state(&self) -> &InputState {
+        &self.input
+    }
+
+    pub fn mouse_look(&self) -> bool {
+        self.mouse_look
+    }
+
+    /// Grabs and hides the cursor so mouse motion turns the camera, or
+    /// releases it. Stays off if the cursor can't be grabbed, or without a
+    /// window.
+    pub fn set_mouse_look(&mut self, enabled: bool) {
+        let Some(window) = &self.window else {
+            return;
+        };
+
+        if enabled {
+            // Not every platform can lock the cursor in place, but keeping
+            // it inside the window works as well with raw motion
+            let grabbed = window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+            if let Err(err) = grabbed {
+                log::warn!("Can't grab the cursor for mouse look: {err}");
+                return;
+            }
+        } else if let Err(err) = window.set_cursor_grab(CursorGrabMode::None) {
+            log::warn!("Failed to release the cursor: {err}");
+        }
+        window.set_cursor_visible(!enabled);
+        self.mouse_look = enabled;
+    }
+
+    /// Turns the camera by raw mouse motion while mouse look is on. Unlike
+    /// `CursorMoved` the motion doesn't stop at the edges of the window.
+    pub fn mouse_motion(&mut self, dx: f64, dy: f64) {
+        if self.mouse_look {
+            let view = self.focused_view();
+            self.cameras[view].rotate(
+                -dx as f32 * MOUSE_SENSITIVITY,
+                -dy as f32 * MOUSE_SENSITIVITY,
+            );
+            self.request_redraw();
+        }
+    }
+
+    pub fn redraw_mode(&self) -> RedrawMode {
+        self.redraw_mode
+    }
+
+    pub fn set_redraw_mode(&mut self, redraw_mode: RedrawMode) {
+        self.redraw_mode = redraw_mode;
+        self.request_redraw();
+    }
+
+    /// The frame rate cap, `None` if there is none.
+    pub fn max_fps(&self) -> Option<u32> {
+        self.frame_limiter.max_fps()
+    }
+
+    /// Makes the window transparent where nothing gets drawn, or opaque
+    /// again. The surface needs an alpha mode the compositor blends with,
+    /// if it has none the window stays opaque and a warning is logged.
+    /// Headless states render into a texture, which keeps the alpha either
+    /// way.
+    pub fn set_transparent(&mut self, transparent: bool) {
+        let supported = match &self.surface {
+            Some(surface) => surface.get_capabilities(&self.adapter).alpha_modes,
+            None => vec![
+                wgpu::CompositeAlphaMode::Opaque,
+                wgpu::CompositeAlphaMode::PreMultiplied,
+            ],
+        };
+        let alpha_mode = alpha_mode::select_alpha_mode(transparent, &supported);
+        if transparent && !alpha_mode::is_transparent(alpha_mode) {
+            log::warn!(
+                "The surface has no transparent alpha mode (only {supported:?}), \
+                 the window stays opaque"
+            );
+        }
+        if alpha_mode == self.config.alpha_mode {
+            return;
+        }
+        self.config.alpha_mode = alpha_mode;
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+
+        // The scene pipelines blend differently with premultiplied alpha
+        match self.create_pipelines(&self.shader_sources) {
+            Ok(pipelines) => self.set_pipelines(pipelines),
+            Err(err) => log::error!("Failed to rebuild the pipelines: {err}"),
+        }
+    }
+
+    /// Whether the window is transparent, see `set_transparent`.
+    pub fn is_transparent(&self) -> bool {
+        alpha_mode::is_transparent(self.config.alpha_mode)
+    }
+
+    /// How many times a second `update` steps the scene.
+    pub fn fixed_update_rate(&self) -> u32 {
+        self.fixed_timestep.rate()
+    }
+
+    /// Steps the scene `rate` times a second from now on, at least once.
+    /// Time already passed towards the next step is dropped.
+    pub fn set_fixed_update_rate(&mut self, rate: u32) {
+        self.fixed_timestep = FixedTimestep::new(rate);
+    }
+
+    /// How far the frame is between the last two fixed steps, which moving
+    /// things are drawn at. From 0 to 1.
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.fixed_timestep.alpha()
+    }
+
+    /// Caps the frame rate at `max_fps`, `None` or 0 removes the cap. With
+    /// vsync the cap only applies if it's below the refresh rate.
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        self.frame_limiter.set_max_fps(max_fps);
+    }
+
+    /// When the frame rate cap allows the next frame to start, `None` if it
+    /// can start right away.
+    pub fn next_frame_due(&self) -> Option<Instant> {
+        let max_fps = self.frame_limiter.max_fps()?;
+        let refresh_rate = self
+            .window()
+            .and_then(Window::current_monitor)
+            .and_then(|monitor| monitor.refresh_rate_millihertz());
+        frame_limiter::is_needed(max_fps, self.config.present_mode, refresh_rate)
+            .then(|| self.frame_limiter.next_frame())
+            .flatten()
+    }
+
+    /// Asks for the window to be redrawn, which `RedrawMode::OnDemand`
+    /// otherwise only does after input. Does nothing for headless states.
+    pub fn request_redraw(&self) {
+        if let Some(window) = self.window() {
+            window.request_redraw();
+        }
+    }
+
+    /// Whether the window has to be redrawn again right after this frame:
+    /// always in `RedrawMode::Continuous`, and while the camera moves in
+    /// `RedrawMode::OnDemand` so the movement still animates. Dropped files
+    /// are picked up by the updates, so they keep going while one loads.
+    pub fn wants_continuous_redraw(&self) -> bool {
+        self.redraw_mode == RedrawMode::Continuous
+            || self.camera_controller.is_moving()
+            || (self.camera_mode == CameraMode::Orbit && self.orbit_camera.is_moving())
+            || self.drop_loader.is_loading()
+    }
+
+    /// Sets how far the gamepad sticks have to be pushed before they do
+    /// anything, from 0 to 1.
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    pub fn set_gamepad_dead_zone(&mut self, dead_zone: f32) {
+        if let Some(gamepad) = &mut self.gamepad {
+            gamepad.dead_zone = dead_zone.clamp(0.0, 0.99);
+        }
+    }
+
+    pub fn camera_controller(&self) -> &CameraController {
+        &self.camera_controller
+    }
+
+    pub fn camera_controller_mut(&mut self) -> &mut CameraController {
+        &mut self.camera_controller
+    }
+
+    pub fn skybox(&self) -> Option<&Skybox> {
+        self.skybox.as_ref()
+    }
+
+    /// Replaces the skybox, `None` goes back to only clearing the background.
+    pub fn set_skybox(&mut self, skybox: Option<Skybox>) -> Option<Skybox> {
+        std::mem::replace(&mut self.skybox, skybox)
+    }
+
+    /// The name of the post-processing effect the frame is drawn with.
+    pub fn post_effect(&self) -> &str {
+        self.post_processor.active_effect().name()
+    }
+
+    /// Switches to the next post-processing effect and returns its name.
+    /// After the last one the frame is drawn unchanged again.
+    pub fn next_post_effect(&mut self) -> &str {
+        self.post_processor.next_effect().name()
+    }
+
+    /// How the HDR scene gets mapped to the frame.
+    pub fn tonemap(&self) -> Tonemap {
+        self.post_processor.tonemap()
+    }
+
+    pub fn set_tonemap(&mut self, tonemap: Tonemap) {
+        self.post_processor.set_tonemap(&self.queue, tonemap);
+    }
+
+    /// Whether the plasma is drawn instead of the skybox.
+    pub fn plasma(&self) -> bool {
+        self.plasma
+    }
+
+    /// Whether the ground grid and the origin's axes are drawn.
+    pub fn grid(&self) -> bool {
+        self.grid
+    }
+
+    pub fn set_grid(&mut self, grid: bool) {
+        self.grid = grid;
+        self.request_redraw();
+    }
+
+    pub fn set_plasma(&mut self, plasma: bool) {
+        self.plasma = plasma;
+        self.request_redraw();
+    }
+
+    /// Whether the brightest parts of the scene bloom, i.e. the render
+    /// graph has the bloom node.
+    pub fn bloom(&self) -> bool {
+        self.render_graph.contains(render_graph::BLOOM_NODE)
+    }
+
+    /// Turned off, the bloom node is taken out of the render graph and
+    /// doesn't cost any passes. Turned on, it goes back in right before the
+    /// post-processing.
+    pub fn set_bloom(&mut self, enabled: bool) {
+        if enabled == self.bloom() {
+            return;
+        }
+        if enabled {
+            self.render_graph.insert_before(
+                render_graph::POST_PROCESS_NODE,
+                Box::new(render_graph::BloomPass),
+            );
+        } else {
+            self.render_graph.remove(render_graph::BLOOM_NODE);
+        }
+    }
+
+    pub fn bloom_settings(&self) -> BloomSettings {
+        self.post_processor.bloom().settings()
+    }
+
+    pub fn set_bloom_settings(&mut self, settings: BloomSettings) {
+        self.post_processor
+            .bloom_mut()
+            .set_settings(&self.queue, settings);
+    }
+
+    /// The sizes of the mips the bloom is blurred through, they follow the
+    /// size of the frame.
+    pub fn bloom_mip_sizes(&self) -> &[(u32, u32)] {
+        self.post_processor.bloom().mip_sizes()
+    }
+
+    /// `None` when compute shaders aren't supported.
+    pub fn particle_count(&self) -> Option<u32> {
+        self.particles.as_ref().map(ParticleSystem::count)
+    }
+
+    /// Restarts the particle simulation with `count` particles, see
+    /// `ParticleSystem::set_count`. Does nothing without compute shaders.
+    pub fn set_particle_count(&mut self, count: u32) {
+        if let Some(particles) = &mut self.particles {
+            particles.set_count(&self.device, count);
+        }
+    }
+
+    /// `None` when compute shaders aren't supported.
+    pub fn particles(&self) -> Option<&ParticleSystem> {
+        self.particles.as_ref()
+    }
+
+    /// For changing the emitters, gravity and blending of the particles.
+    pub fn particles_mut(&mut self) -> Option<&mut ParticleSystem> {
+        self.particles.as_mut()
+    }
+
+    /// Blocks until the GPU has counted the particles alive, see
+    /// `ParticleSystem::read_alive_count`.
+    pub fn alive_particle_count(&self) -> Option<u32> {
+        self.particles
+            .as_ref()?
+            .read_alive_count(&self.device, &self.queue)
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        self.instances.current()
+    }
+
+    /// Replaces the drawn instances. The instance buffer is only reallocated
+    /// when it is too small to hold them.
+    pub fn set_instances(&mut self, instances: &[Instance]) {
+        self.instances.set(instances.to_vec());
+        let mut encoder = self.create_upload_encoder();
+        self.upload_instances(&mut encoder, instances.to_vec());
+        self.submit_uploads(encoder);
+    }
+
+    /// Like `set_instances`, but they get there with the next fixed step
+    /// and are drawn moving there, see `SteppedInstances::move_to`.
+    pub fn move_instances(&mut self, instances: &[Instance]) {
+        self.instances.move_to(instances.to_vec());
+    }
+
+    /// Draws the instances at `drawn` from now on.
+    fn upload_instances(&mut self, encoder: &mut wgpu::CommandEncoder, drawn: Vec<Instance>) {
+        self.drawn_instances = drawn;
+        // Only bound each time it's drawn, reallocating it needs nothing else
+        self.instance_buffer.upload(
+            &self.device,
+            encoder,
+            &mut self.uploads,
+            &self.drawn_instances,
+        );
+        // The same indices are different instances now
+        self.culled = Culled::default();
+        self.update_culling(encoder);
+    }
+
+    fn create_upload_encoder(&self) -> wgpu::CommandEncoder {
+        self.device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Upload Encoder"),
+            })
+    }
+
+    /// Submits the uploads recorded into `encoder`.
+    fn submit_uploads(&mut self, encoder: wgpu::CommandEncoder) {
+        self.uploads.finish();
+        self.queue.submit(std::iter::once(encoder.finish()));
+        self.uploads.recall();
+    }
+
+    /// How many meshes the last culling drew and left out.
+    pub fn cull_stats(&self) -> CullStats {
+        self.culled.stats
+    }
+
+    /// Where the assets and the reloaded shaders are read from, see
+    /// `asset_source::default_source`.
+    pub fn asset_source(&self) -> &dyn AssetSource {
+        self.source.as_ref()
+    }
+
+    /// The textures loaded so far.
+    pub fn assets(&self) -> &Assets {
+        &self.assets
+    }
+
+    /// How many assets are still loading in the background.
+    pub fn assets_loading(&self) -> usize {
+        self.asset_loader.loading()
+    }
+
+    /// Blocks until the assets loading in the background are there, and
+    /// swaps them in.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait_for_assets(&mut self) {
+        let dropped = self.drop_loader.wait();
+        self.receive_drops(dropped);
+        let finished = self.asset_loader.wait();
+        self.receive_assets(&finished);
+    }
+
+    /// Uploads the assets that finished loading and swaps them in for their
+    /// placeholders.
+    fn receive_assets(&mut self, finished: &[LoadedImage]) {
+        if finished.is_empty() {
+            return;
+        }
+        let received = self.obj_model.as_mut().is_some_and(|model| {
+            model.receive_textures(
+                &self.device,
+                &self.queue,
+                &self.bind_group_layouts.materials.material,
+                &mut self.assets,
+                finished,
+            )
+        });
+        if received {
+            self.request_redraw();
+        }
+    }
+
+    /// Loads a file dropped onto the window in the background: models
+    /// replace the model, and images the diffuse texture of the picked mesh's
+    /// material, or of the first one. The old scene is drawn until it's
+    /// there, and stays if the file can't be loaded. Other files are only
+    /// warned about.
+    pub fn drop_file(&mut self, path: &Path) {
+        match self.drop_loader.load(path) {
+            Some(kind) => {
+                log::info!("Loading the dropped {kind:?} {}", path.display());
+                self.drop_error = None;
+            }
+            None => log::warn!(
+                "Unsupported file {}, drop an .obj, .gltf or .glb model or a PNG or JPEG image",
+                path.display()
+            ),
+        }
+        self.request_redraw();
+    }
+
+    /// What the HUD says about the dropped files: which are loading, or why
+    /// the last one couldn't be used. `None` when there's nothing to say.
+    pub fn drop_status(&self) -> Option<String> {
+        let loading: Vec<_> = self.drop_loader.loading().map(file_name).collect();
+        if !loading.is_empty() {
+            return Some(format!("Loading {}...", loading.join(", ")));
+        }
+        self.drop_error.clone()
+    }
+
+    pub fn file_hovered(&self) -> bool {
+        self.file_hovered
+    }
+
+    fn receive_drops(&mut self, dropped: Vec<LoadedDrop>) {
+        for (path, asset) in dropped {
+            if let Err(err) = asset.and_then(|asset| self.receive_drop(&path, asset)) {
+                log::warn!("Can't use the dropped {}: {err}", path.display());
+                self.drop_error = Some(format!("Can't use {}: {err}", file_name(&path)));
+            }
+            self.request_redraw();
+        }
+    }
+
+    /// Uploads a dropped file that finished loading.
+    fn receive_drop(&mut self, path: &Path, asset: DroppedAsset) -> Result<(), String> {
+        let scope = self.gpu_errors.context().scope("texture upload");
+        match asset {
+            DroppedAsset::Obj { data, images } => {
+                let model = Model::from_obj_data(
+                    data,
+                    &self.device,
+                    &self.queue,
+                    &self.bind_group_layouts.materials.material,
+                    &mut self.assets,
+                    &images,
+                );
+                drop(scope);
+                self.replace_model(model, Some(path));
+            }
+            DroppedAsset::Gltf(document) => {
+                let model = Model::from_gltf(
+                    document,
+                    path,
+                    &self.device,
+                    &self.queue,
+                    &self.bind_group_layouts.materials,
+                    &mut self.assets,
+                )
+                .map_err(|err| err.to_string())?;
+                drop(scope);
+                self.replace_model(model, Some(path));
+            }
+            DroppedAsset::Image(image) => {
+                let model = self
+                    .obj_model
+                    .as_mut()
+                    .ok_or("there's no model to put the texture on")?;
+                let material = self
+                    .picking
+                    .picked()
+                    .and_then(|picked| model.meshes.get(picked.mesh as usize))
+                    .map_or(0, |mesh| mesh.material);
+                let slot = TextureSlot::Diffuse;
+                let handle = self.assets.insert_image(
+                    &self.device,
+                    &self.queue,
+                    path,
+                    slot.format(),
+                    &image,
+                );
+                let texture = self
+                    .assets
+                    .shared(handle)
+                    .ok_or("the texture was removed")?;
+                model.set_texture(
+                    &self.device,
+                    &self.bind_group_layouts.materials,
+                    material,
+                    slot,
+                    texture,
+                );
+                drop(scope);
+                self.assets.remove_unused();
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws `model` at the instances from now on. `path` is the file it
+    /// was loaded from, `None` for generated ones.
+    fn replace_model(&mut self, model: Model, path: Option<&Path>) {
+        self.obj_model = Some(model);
+        if self.texture_filtering.is_some() {
+            self.apply_texture_filtering();
+        }
+        // The old model's textures are only in the cache now
+        self.assets.remove_unused();
+        self.model_path = path.map(Path::to_owned);
+        let mut encoder = self.create_upload_encoder();
+        self.update_culling(&mut encoder);
+        self.submit_uploads(encoder);
+        self.request_redraw();
+    }
+
+    pub fn culling_frozen(&self) -> bool {
+        self.culling_camera.is_some()
+    }
+
+    /// Frozen, culling keeps using the camera as it is now while the camera
+    /// moves on.
+    pub fn set_culling_frozen(&mut self, frozen: bool) {
+        self.culling_camera = frozen.then_some(self.culling_camera.unwrap_or(self.cameras[0]));
+    }
+
+    /// Finds the instances inside the view of the culling camera, or of any
+    /// of the views, and records uploading them into `encoder`, if they
+    /// changed.
+    fn update_culling(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let cameras = match &self.culling_camera {
+            Some(camera) => std::slice::from_ref(camera),
+            None => &self.cameras[..self.split_mode.view_count()],
+        };
+        let frustums: Vec<_> = cameras
+            .iter()
+            .map(|camera| Frustum::from_view_proj(camera.build_view_projection_matrix()))
+            .collect();
+        let bounds: Vec<_> = self
+            .obj_model
+            .iter()
+            .flat_map(|model| &model.meshes)
+            .map(|mesh| mesh.bounds)
+            .collect();
+        let culled = culling::cull(&frustums, &bounds, &self.drawn_instances);
+        if culled != self.culled {
+            let visible: Vec<Instance> = culled
+                .instances
+                .iter()
+                .map(|&index| self.drawn_instances[index as usize])
+                .collect();
+            let reallocated = self.visible_instance_buffer.upload(
+                &self.device,
+                encoder,
+                &mut self.uploads,
+                &visible,
+            );
+            if reallocated {
+                // The bundle draws from the old buffer
+                self.scene_bundle = None;
+            }
+            self.culled = culled;
+        }
+    }
+
+    pub fn render_bundles(&self) -> bool {
+        self.render_bundles
+    }
+
+    /// With render bundles the draws of the model are recorded once and
+    /// replayed each frame until something they use changes, which saves
+    /// encoding them again. The frame stats show the difference.
+    pub fn set_render_bundles(&mut self, enabled: bool) {
+        self.render_bundles = enabled;
+        if !enabled {
+            self.scene_bundle = None;
+        }
+    }
+
+    /// The pipeline the model is drawn with, fill or wireframe.
+    fn model_pipeline(&self, model: &Model) -> &wgpu::RenderPipeline {
+        let (fill_pipeline, wireframe_pipeline) = if model.is_skinned() {
+            (&self.skinned_pipeline, &self.skinned_wireframe_pipeline)
+        } else {
+            (&self.render_pipeline, &self.wireframe_pipeline)
+        };
+        match wireframe_pipeline {
+            Some(wireframe_pipeline) if self.wireframe => wireframe_pipeline,
+            _ => fill_pipeline,
+        }
+    }
+
+    /// What the bundle of the model would be recorded with right now, `None`
+    /// without a model.
+    fn scene_bundle_key(&self) -> Option<BundleKey> {
+        let model = self.obj_model.as_ref()?;
+        let mut bind_groups = vec![
+            self.camera_bind_group.global_id(),
+            self.light_buffers.bind_group().global_id(),
+            self.shadow_map.bind_group().global_id(),
+        ];
+        bind_groups.extend(
+            model
+                .materials
+                .iter()
+                .map(|material| material.bind_group.global_id()),
+        );
+        let mut buffers = vec![self.visible_instance_buffer.buffer().global_id()];
+        for mesh in &model.meshes {
+            buffers.push(mesh.vertex_buffer.buffer().global_id());
+            buffers.extend(mesh.index_buffer.as_ref().map(|b| b.buffer().global_id()));
+            buffers.extend(mesh.skin_buffer.as_ref().map(|b| b.buffer().global_id()));
+        }
+        Some(BundleKey {
+            targets: self.render_targets(),
+            pipeline: self.model_pipeline(model).global_id(),
+            bind_groups,
+            buffers,
+            ranges: self.culled.ranges.clone(),
+        })
+    }
+
+    /// Records the bundle of the model again if it's out of date.
+    fn update_scene_bundle(&mut self) {
+        if !self.render_bundles {
+            return;
+        }
+        let key = self.scene_bundle_key();
+        if self.scene_bundle.as_ref().map(SceneBundle::key) == key.as_ref() {
+            return;
+        }
+        self.scene_bundle = key.map(|key| {
+            SceneBundle::record(&self.device, "Scene Bundle", key, |encoder| {
+                self.encode_model_draws(encoder, 0);
+            })
+        });
+    }
+
+    /// Draws the visible instances of the model with the camera of `view`,
+    /// straight into a render pass or into a bundle.
+    fn encode_model_draws<'a>(
+        &'a self,
+        encoder: &mut impl wgpu::util::RenderEncoder<'a>,
+        view: usize,
+    ) {
+        if let Some(obj_model) = &self.obj_model {
+            self.encode_model_draws_with(encoder, view, self.model_pipeline(obj_model));
+        }
+    }
+
+    /// Like `encode_model_draws`, with `pipeline` instead of the model's.
+    fn encode_model_draws_with<'a>(
+        &'a self,
+        encoder: &mut impl wgpu::util::RenderEncoder<'a>,
+        view: usize,
+        pipeline: &'a wgpu::RenderPipeline,
+    ) {
+        let Some(obj_model) = &self.obj_model else {
+            return;
+        };
+        encoder.set_pipeline(pipeline);
+        encoder.set_bind_group(3, self.shadow_map.bind_group(), &[]);
+        self.visible_instance_buffer.bind(encoder, 1);
+        encoder.draw_model_culled(
+            obj_model,
+            &self.culled.ranges,
+            self.camera_binding(view),
+            self.light_buffers.bind_group(),
+        );
+    }
+
+    /// A 0x0 size means the window was minimized, the surface keeps its
+    /// size until the window is restored.
+    pub fn resize(&mut self, new_size: dpi::PhysicalSize<u32>) {
+        if self.minimized.resized(new_size) {
+            self.size = new_size;
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+            // The depth texture has to match the size of the surface
+            self.recreate_render_targets();
+            self.update_view_aspects();
+            if let (Some(hud), Some(window)) = (&mut self.hud, &self.window) {
+                hud.resize(new_size, window.scale_factor());
+            }
+            self.request_redraw();
+        }
+    }
+
+    /// Call it for `WindowEvent::Occluded`, occluded windows aren't rendered.
+    pub fn set_occluded(&mut self, occluded: bool) {
+        self.minimized.set_occluded(occluded);
+        if !occluded {
+            self.request_redraw();
+        }
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.minimized.is_minimized()
+    }
+
+    /// Call it for `Event::Suspended`. Drops the surface, which Android
+    /// destroys while the app is in the background. Nothing gets rendered
+    /// until `resume`.
+    pub fn suspend(&mut self) {
+        if self.surface.take().is_some() {
+            log::info!("Suspended, dropped the surface");
+        }
+    }
+
+    /// Call it for `Event::Resumed`. Creates the surface again after
+    /// `suspend`, with the same format as before as the pipelines were
+    /// built for it. Does nothing for headless states.
+    pub fn resume(&mut self) -> Result<(), AppError> {
+        let Some(window) = &self.window else {
+            return Ok(());
+        };
+        if self.surface.is_some() {
+            return Ok(());
+        }
+
+        let surface = self.instance.create_surface(Arc::clone(window))?;
+        let caps = surface.get_capabilities(&self.adapter);
+        if !caps.formats.contains(&self.config.format) {
+            return Err(AppError::SurfaceConfig {
+                adapter: adapter::describe_adapter(&self.adapter.get_info()),
+                reason: format!("the new surface doesn't support {:?}", self.config.format),
+            });
+        }
+        surface.configure(&self.device, &self.config);
+        self.surface = Some(surface);
+        log::info!("Resumed, recreated the surface");
+
+        // The window may have been resized in the meantime
+        let size = window.inner_size();
+        self.resize(size);
+        self.request_redraw();
+        Ok(())
+    }
+
+    /// Whether the app is suspended and there's no surface to render to.
+    pub fn is_suspended(&self) -> bool {
+        self.window.is_some() && self.surface.is_none()
+    }
+
+    /// Recovers from a `render` error where possible.
+    pub fn handle_surface_error(&mut self, err: wgpu::SurfaceError) -> SurfaceErrorAction {
+        let action = match err {
+            wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated => {
+                // A minimized window has a 0 size, which the surface can't be
+                // configured with. It gets resized when it's restored.
+                let minimized = self
+                    .window()
+                    .map(Window::inner_size)
+                    .is_some_and(|size| size.width == 0 || size.height == 0);
+                if minimized {
+                    SurfaceErrorAction::SkipFrame
+                } else {
+                    self.resize(self.size);
+                    SurfaceErrorAction::Reconfigured
+                }
+            }
+            wgpu::SurfaceError::Timeout => SurfaceErrorAction::SkipFrame,
+            wgpu::SurfaceError::OutOfMemory => SurfaceErrorAction::Exit,
+        };
+
+        log_surface_error(err, action);
+        action
+    }
+
+    /// Recreates the frame resources and the other textures that follow the
+    /// surface size.
+    fn recreate_render_targets(&mut self) {
+        let size = self.render_size();
+        self.frame_resources.resize(&self.device, size);
+        // The post-processing draws at the scene's size too
+        let render_config = wgpu::SurfaceConfiguration {
+            width: size.width,
+            height: size.height,
+            ..self.config.clone()
+        };
+        self.post_processor.resize(
+            &self.device,
+            &render_config,
+            self.frame_resources.view(render_graph::HDR),
+        );
+        let window_size = dpi::PhysicalSize::new(self.config.width, self.config.height);
+        if size == window_size {
+            self.frame_resources.remove(render_graph::SCALED);
+        } else {
+            self.frame_resources.insert(
+                &self.device,
+                render_graph::SCALED,
+                ResourceDesc::frame(
+                    color::render_format(&self.config),
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                ),
+            );
+            self.upscaler.set_source(
+                &self.device,
+                &self.queue,
+                self.frame_resources.view(render_graph::SCALED),
+                size,
+                window_size,
+            );
+        }
+        if self.fxaa() {
+            self.bind_ldr_target();
+        }
+        self.globals.set_resolution(size);
+        // Picking draws at the window's size, clicks are in its pixels
+        self.picking.resize(&self.device, &self.config);
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// Renders the scene at `scale` times the size of the window, within
+    /// `render_scale::MIN_RENDER_SCALE` and `MAX_RENDER_SCALE`, and draws
+    /// it stretched or shrunk into the frame. Returns the scale used.
+    pub fn set_render_scale(&mut self, scale: f32) -> f32 {
+        let scale = render_scale::clamp_render_scale(scale);
+        if scale != self.render_scale {
+            self.render_scale = scale;
+            self.recreate_render_targets();
+            self.request_redraw();
+        }
+        scale
+    }
+
+    /// The size the scene renders at, the window's times the render scale
+    /// and never 0.
+    pub fn render_size(&self) -> dpi::PhysicalSize<u32> {
+        render_scale::scaled_size(
+            dpi::PhysicalSize::new(self.config.width, self.config.height),
+            self.render_scale,
+            self.device.limits().max_texture_dimension_2d,
+        )
+    }
+
+    pub fn debug_view(&self) -> DebugView {
+        self.debug_view
+    }
+
+    /// Whether `view` can be shown: the depth needs MSAA off and the
+    /// wireframe overlay `Features::POLYGON_MODE_LINE`.
+    pub fn supports_debug_view(&self, view: DebugView) -> bool {
+        match view {
+            DebugView::Depth => self.sample_count == 1,
+            DebugView::WireframeOverlay => self.debug_pipelines.wireframe_overlay.is_some(),
+            _ => true,
+        }
+    }
+
+    /// Shows `view` instead of the scene or on top of it, see `debug_view`.
+    /// The render graph has the debug view node while there's one. Views
+    /// that aren't supported turn it off, returns the view shown.
+    pub fn set_debug_view(&mut self, view: DebugView) -> DebugView {
+        let view = if self.supports_debug_view(view) {
+            view
+        } else {
+            log::warn!("The {} debug view isn't supported", view.name());
+            DebugView::None
+        };
+        self.debug_view = view;
+        self.globals.set_debug_view(view);
+        let has_node = self.render_graph.contains(render_graph::DEBUG_VIEW_NODE);
+        if view == DebugView::None {
+            self.render_graph.remove(render_graph::DEBUG_VIEW_NODE);
+        } else if !has_node {
+            self.render_graph
+                .push(Box::new(render_graph::DebugViewPass));
+        }
+        self.request_redraw();
+        view
+    }
+
+    /// Whether FXAA smooths the frame, i.e. the render graph has the FXAA
+    /// node.
+    pub fn fxaa(&self) -> bool {
+        self.render_graph.contains(render_graph::FXAA_NODE)
+    }
+
+    /// Turned on, the tonemapping draws into `render_graph::LDR` and the
+    /// FXAA node right after it smooths that into the frame. FXAA replaces
+    /// MSAA, so the sample count drops to 1.
+    pub fn set_fxaa(&mut self, enabled: bool) {
+        if enabled == self.fxaa() {
+            return;
+        }
+        if enabled {
+            if self.sample_count > 1 {
+                log::warn!(
+                    "FXAA replaces MSAA, rendering with 1 sample instead of {}",
+                    self.sample_count
+                );
+                self.set_sample_count(1);
+            }
+            self.frame_resources.insert(
+                &self.device,
+                render_graph::LDR,
+                ResourceDesc::frame(
+                    color::render_format(&self.config),
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                ),
+            );
+            self.bind_ldr_target();
+            self.render_graph.insert_after(
+                render_graph::POST_PROCESS_NODE,
+                Box::new(render_graph::FxaaPass),
+            );
+        } else {
+            self.render_graph.remove(render_graph::FXAA_NODE);
+            self.frame_resources.remove(render_graph::LDR);
+        }
+        self.request_redraw();
+    }
+
+    /// Points the FXAA at `render_graph::LDR`, which the frame resources
+    /// recreate with the render size.
+    fn bind_ldr_target(&mut self) {
+        self.fxaa.set_source(
+            &self.device,
+            &self.queue,
+            self.frame_resources.view(render_graph::LDR),
+            self.frame_resources.size(),
+        );
+    }
+
+    pub fn fxaa_settings(&self) -> FxaaSettings {
+        self.fxaa.settings()
+    }
+
+    pub fn set_fxaa_settings(&mut self, settings: FxaaSettings) {
+        self.fxaa.set_settings(&self.queue, settings);
+    }
+
+    pub fn antialiasing(&self) -> Antialiasing {
+        if self.fxaa() {
+            Antialiasing::Fxaa
+        } else if self.sample_count > 1 {
+            Antialiasing::Msaa
+        } else {
+            Antialiasing::None
+        }
+    }
+
+    /// Turns on FXAA or `fxaa::MSAA_SAMPLES` times MSAA, or neither. MSAA
+    /// falls back like `set_sample_count` does, down to none at all where
+    /// there's no multisampling. Returns what's used.
+    pub fn set_antialiasing(&mut self, antialiasing: Antialiasing) -> Antialiasing {
+        // Turning one off before the other goes on, they'd turn each other
+        // off with a warning
+        if antialiasing != Antialiasing::Fxaa {
+            self.set_fxaa(false);
+        }
+        self.set_sample_count(if antialiasing == Antialiasing::Msaa {
+            fxaa::MSAA_SAMPLES
+        } else {
+            1
+        });
+        if antialiasing == Antialiasing::Fxaa {
+            self.set_fxaa(true);
+        }
+        self.antialiasing()
+    }
+
+    /// The targets of the scene pipelines, the post-processing effects draw
+    /// to the frame itself.
+    fn render_targets(&self) -> RenderTargets {
+        RenderTargets {
+            color_format: HDR_FORMAT,
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count: self.sample_count,
+        }
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// The features the device got, the required ones of the
+    /// `DeviceConfig` and the optional ones the adapter has.
+    pub fn features(&self) -> wgpu::Features {
+        self.device.features()
+    }
+
+    /// Labels the GPU errors no error scope catches, see
+    /// `ErrorContext::scope`.
+    pub fn gpu_error_context(&self) -> &ErrorContext {
+        self.gpu_errors.context()
+    }
+
+    /// How many GPU errors weren't caught by an error scope so far. They
+    /// get logged, or panic with the `panic-on-gpu-error` feature.
+    pub fn gpu_error_count(&self) -> u64 {
+        self.gpu_errors.error_count()
+    }
+
+    /// Whether the device was lost, e.g. because the driver crashed or got
+    /// updated. Nothing renders until `recreate_device` is done.
+    pub fn device_lost(&self) -> bool {
+        self.gpu_errors.device_lost().is_some()
+    }
+
+    /// Requests a new device from the adapter and rebuilds everything on
+    /// the GPU with it. The camera and the settings changed since the state
+    /// was created are kept, the rest starts over. Fails if the adapter
+    /// can't give us a device anymore, the state is unusable then.
+    pub async fn recreate_device(&mut self) -> Result<(), AppError> {
+        if let Some(reason) = self.gpu_errors.device_lost() {
+            log::warn!("Lost the device: {reason}, recreating it");
+        }
+        let (device, queue) = request_device(&self.adapter, &self.device_config).await?;
+        if let Some(surface) = &self.surface {
+            surface.configure(&device, &self.config);
+        }
+        let gpu = GpuParts {
+            instance: Arc::clone(&self.instance),
+            adapter: Arc::clone(&self.adapter),
+            device,
+            queue,
+            device_config: self.device_config.clone(),
+        };
+        let state = Self::from_parts(
+            self.window.clone(),
+            self.surface.take(),
+            gpu,
+            self.config.clone(),
+            self.sample_count,
+        )?;
+
+        let old = std::mem::replace(self, state);
+        self.set_shadows(old.shadows());
+        self.set_shadow_map_size(old.shadow_map_size());
+        self.set_tonemap(old.tonemap());
+        self.set_bloom(old.bloom());
+        self.set_bloom_settings(old.bloom_settings());
+        self.frame_index = old.frame_index;
+        self.gpu_errors.context().set_frame(old.frame_index);
+        self.redraw_mode = old.redraw_mode;
+        self.frame_limiter = old.frame_limiter;
+        self.set_frame_time_graph(old.frame_time_graph.is_some());
+        self.fixed_timestep = old.fixed_timestep;
+        self.on_exit = old.on_exit;
+        self.drop_loader = old.drop_loader;
+        self.drop_error = old.drop_error;
+        self.clear_color = old.clear_color;
+        self.cameras = old.cameras;
+        self.split_mode = old.split_mode;
+        self.focused_view = old.focused_view;
+        self.camera_controller = old.camera_controller;
+        self.camera_mode = old.camera_mode;
+        self.orbit_camera = old.orbit_camera;
+        self.key_bindings = old.key_bindings;
+        self.input_map = old.input_map;
+        self.elapsed = old.elapsed;
+        self.globals = old.globals;
+        self.plasma = old.plasma;
+        self.grid = old.grid;
+        self.frame_counter = old.frame_counter;
+        self.set_wireframe(old.wireframe);
+        self.lights = old.lights;
+        self.culling_camera = old.culling_camera;
+        self.render_bundles = old.render_bundles;
+        if let (Some(particles), Some(old_particles)) = (&mut self.particles, &old.particles) {
+            particles.set_count(&self.device, old_particles.count());
+            particles.set_blend(old_particles.blend());
+            particles.set_gravity(old_particles.gravity());
+            *particles.emitters_mut() = old_particles.emitters().to_vec();
+        }
+        if let Some(path) = &old.model_path {
+            let animator = old
+                .obj_model
+                .as_ref()
+                .and_then(|model| model.skinning.as_ref())
+                .map(|skinning| &skinning.animator);
+            let playing =
+                animator.and_then(|animator| Some((animator.playing()?, animator.looping())));
+            let reloaded = file_drop::read(path)
+                .and_then(|asset| self.receive_drop(path, asset))
+                .and_then(|()| {
+                    playing.map_or(Ok(()), |(clip, looping)| {
+                        self.play_animation(clip, looping)
+                            .map_err(|err| err.to_string())
+                    })
+                });
+            if let Err(err) = reloaded {
+                log::warn!("Failed to reload {}: {err}", path.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// How the rendered colors get sRGB encoded on the surface.
+    pub fn srgb_encoding(&self) -> SrgbEncoding {
+        color::srgb_encoding(&self.config)
+    }
+
+    /// The present mode the surface is configured with. Always `Fifo` for
+    /// headless states.
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    /// Switches to the best supported present mode for `preference` and
+    /// returns it. Does nothing for headless states.
+    pub fn set_present_mode(&mut self, preference: PresentModePreference) -> wgpu::PresentMode {
+        if let Some(surface) = &self.surface {
+            let supported = surface.get_capabilities(&self.adapter).present_modes;
+            let present_mode = present_mode::select_present_mode(preference, &supported);
+            if present_mode != self.config.present_mode {
+                self.config.present_mode = present_mode;
+                surface.configure(&self.device, &self.config);
+            }
+        }
+        self.config.present_mode
+    }
+
+    /// The MSAA sample counts that can be passed to `set_sample_count`.
+    pub fn supported_sample_counts(&self) -> Vec<u32> {
+        msaa::supported_sample_counts(
+            &self.adapter,
+            self.device.features(),
+            &[HDR_FORMAT, Texture::DEPTH_FORMAT],
+        )
+    }
+
+    /// Changes the MSAA sample count, falling back to the highest supported
+    /// count below `sample_count`. Returns the count actually used. More
+    /// than 1 turns FXAA off, see `set_fxaa`.
+    pub fn set_sample_count(&mut self, sample_count: u32) -> u32 {
+        let sample_count = msaa::select_sample_count(sample_count, &self.supported_sample_counts());
+        if sample_count > 1 && self.fxaa() {
+            log::warn!("MSAA replaces FXAA, turning it off");
+            self.set_fxaa(false);
+        }
+        if sample_count > 1 && self.debug_view == DebugView::Depth {
+            log::warn!("The depth debug view doesn't work with MSAA, turning it off");
+            self.set_debug_view(DebugView::None);
+        }
+        if sample_count != self.sample_count {
+            self.sample_count = sample_count;
+            insert_scene_targets(&mut self.frame_resources, &self.device, sample_count);
+
+            // The current shaders already compiled once, so they still do
+            let pipelines = self
+                .create_pipelines(&self.shader_sources)
+                .expect("the current shaders compile");
+            self.set_pipelines(pipelines);
+        }
+        sample_count
+    }
+
+    /// Builds every pipeline for the current render targets from `sources`,
+    /// without using them yet. The shaders are checked before anything is
+    /// created, so nothing changes if one of them doesn't compile.
+    fn create_pipelines(&self, sources: &ShaderSources) -> Result<Pipelines, ShaderError> {
+        let _scope = self.gpu_errors.context().scope("pipeline creation");
+        let targets = self.render_targets();
+        let scene = create_scene_pipelines(
+            &self.device,
+            &self.bind_group_layouts,
+            targets,
+            alpha_mode::blend_state(self.config.alpha_mode),
+            sources,
+        )?;
+        let skybox = self
+            .skybox
+            .as_ref()
+            .map(|skybox| {
+                skybox.create_pipeline(
+                    &self.device,
+                    targets,
+                    &self.bind_group_layouts.camera,
+                    &sources.skybox,
+                )
+            })
+            .transpose()?;
+        let particles = self
+            .particles
+            .as_ref()
+            .map(|particles| {
+                particles.create_pipelines(
+                    &self.device,
+                    targets,
+                    &self.bind_group_layouts.camera,
+                    &sources.particles,
+                )
+            })
+            .transpose()?;
+        let outline = self.outline.create_pipelines(
+            &self.device,
+            targets,
+            &self.bind_group_layouts.camera,
+        )?;
+        let debug_draw = self.debug_draw.create_pipelines(
+            &self.device,
+            targets,
+            &self.bind_group_layouts.camera,
+        )?;
+        Ok(Pipelines {
+            scene,
+            skybox,
+            particles,
+            outline,
+            debug_draw,
+        })
+    }
+
+    fn set_pipelines(&mut self, pipelines: Pipelines) {
+        self.render_pipeline = pipelines.scene.render;
+        self.wireframe_pipeline = pipelines.scene.wireframe;
+        self.skinned_pipeline = pipelines.scene.skinned;
+        self.skinned_wireframe_pipeline = pipelines.scene.skinned_wireframe;
+        self.debug_pipelines = pipelines.scene.debug;
+        self.light_render_pipeline = pipelines.scene.light;
+        self.plasma_pipeline = pipelines.scene.plasma;
+        self.grid_pipeline = pipelines.scene.grid;
+        if let (Some(skybox), Some(pipeline)) = (&mut self.skybox, pipelines.skybox) {
+            skybox.set_pipeline(pipeline);
+        }
+        if let (Some(particles), Some(pipelines)) = (&mut self.particles, pipelines.particles) {
+            particles.set_pipelines(pipelines);
+        }
+        self.outline.set_pipelines(pipelines.outline);
+        self.debug_draw.set_pipelines(pipelines.debug_draw);
+    }
+
+    /// Reads the shaders from the asset source again and rebuilds the
+    /// pipelines with them. If a shader doesn't compile the error is logged
+    /// and the current pipelines are kept.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_pipelines(&mut self) {
+        let sources = match ShaderSources::load(self.source.as_ref()) {
+            Ok(sources) => sources,
+            Err(err) => {
+                log::error!("Failed to read the shaders: {err}");
+                return;
+            }
+        };
+
+        let pipelines = match self.create_pipelines(&sources) {
+            Ok(pipelines) => pipelines,
+            Err(_) => {
+                // The error itself was already logged with the source
+                log::error!("Failed to reload the shaders, keeping the old ones");
+                return;
+            }
+        };
+        self.set_pipelines(pipelines);
+        self.shader_sources = sources;
+        log::info!("Reloaded the shaders");
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        // The overlay goes first so the camera doesn't move while dragging a
+        // slider
+        if let (Some(debug_overlay), Some(window)) = (&mut self.debug_overlay, &self.window) {
+            if debug_overlay.handle_event(window, event) {
+                return true;
+            }
+        }
+
+        // Tracked whatever else the event does, the drags need where the
+        // cursor was
+        let previous_cursor = self.input.cursor_position();
+        let is_mouse_event = self.input.process_event(event);
+        // Bound keys and buttons are only the input map's
+        if self.touch.process_event(event) || self.input_map.process_event(event) {
+            self.request_redraw();
+            return true;
+        }
+
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key,
+                        logical_key,
+                        state,
+                        repeat,
+                        ..
+                    },
+                ..
+            } => {
+                // Exiting and fullscreen are the input map's, whatever the
+                // key bindings say, so the settings can rebind them
+                let Some(action) = self
+                    .key_bindings
+                    .action(physical_key, logical_key, self.modifiers)
+                    .filter(|action| !matches!(action, Action::Exit | Action::ToggleFullscreen))
+                else {
+                    return false;
+                };
+                // Held keys repeat, but the action already happened
+                *repeat || self.handle_action(action, *state)
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Right,
+                ..
+            } => self.handle_action(Action::ToggleMouseLook, ElementState::Pressed),
+            // While looking around the cursor is hidden, there's nothing to
+            // point at
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if !self.mouse_look => {
+                if let Some(position) = self.input.cursor_position() {
+                    self.pick(position);
+                }
+                true
+            }
+            // Another window has the mouse now, and we would never get the
+            // release events of the keys that are held
+            WindowEvent::Focused(false) => {
+                if self.mouse_look {
+                    self.set_mouse_look(false);
+                }
+                self.camera_controller.reset();
+                false
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                false
+            }
+            WindowEvent::HoveredFile(_) => {
+                self.file_hovered = true;
+                self.request_redraw();
+                true
+            }
+            WindowEvent::HoveredFileCancelled => {
+                self.file_hovered = false;
+                self.request_redraw();
+                true
+            }
+            WindowEvent::DroppedFile(path) => {
+                self.file_hovered = false;
+                self.drop_file(path);
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(previous) = previous_cursor {
+                    self.drag_orbit_camera(
+                        (position.x - previous.x) as f32,
+                        (position.y - previous.y) as f32,
+                    );
+                }
+                // In the pixels the scene renders at, like the resolution
+                let render_size = self.render_size();
+                self.globals.set_cursor(dpi::PhysicalPosition::new(
+                    position.x * render_size.width as f64 / self.size.width.max(1) as f64,
+                    position.y * render_size.height as f64 / self.size.height.max(1) as f64,
+                ));
+                // The cursor position picks the red and green amounts
+                self.clear_color.r = (position.x / self.size.width as f64).clamp(0.0, 1.0);
+                self.clear_color.g = (position.y / self.size.height as f64).clamp(0.0, 1.0);
+                true
+            }
+            _ => is_mouse_event,
+        }
+    }
+
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    /// The fingers on the window and what they're doing.
+    pub fn touch_gestures(&self) -> &TouchGestures {
+        &self.touch
+    }
+
+    /// Moves the camera and exits or toggles fullscreen by the actions and
+    /// axes of this frame.
+    fn apply_input_map(&mut self, dt: Duration) {
+        use input_map::names;
+
+        let map = &self.input_map;
+        self.camera_controller
+            .set_analog_movement(map.axis(names::MOVE_FORWARD), map.axis(names::MOVE_RIGHT));
+        self.camera_controller
+            .set_analog_up(map.axis(names::MOVE_UP));
+        let look = glam::Vec2::new(map.axis(names::LOOK_X), map.axis(names::LOOK_Y))
+            * LOOK_SPEED
+            * dt.as_secs_f32();
+        if look != glam::Vec2::ZERO {
+            let view = self.focused_view();
+            self.cameras[view].rotate(-look.x, look.y);
+        }
+
+        let (exit, fullscreen) = (
+            map.just_pressed(names::EXIT),
+            map.just_pressed(names::TOGGLE_FULLSCREEN),
+        );
+        if exit {
+            self.handle_action(Action::Exit, ElementState::Pressed);
+        }
+        if fullscreen {
+            self.handle_action(Action::ToggleFullscreen, ElementState::Pressed);
+        }
+        self.input_map.end_frame();
+    }
+
+    /// Orbits the camera by a drag of the left button and pans it by one of
+    /// the middle button, in `CameraMode::Orbit`.
+    fn drag_orbit_camera(&mut self, dx: f32, dy: f32) {
+        if self.camera_mode != CameraMode::Orbit || self.mouse_look {
+            return;
+        }
+        if self.input.is_button_pressed(MouseButton::Left) {
+            self.orbit_camera.drag_orbit(dx, dy);
+            self.request_redraw();
+        }
+        if self.input.is_button_pressed(MouseButton::Middle) {
+            let view = self.focused_view();
+            let height = self.viewports()[view].height;
+            let units_per_pixel = self.cameras[view].world_units_per_pixel(height);
+            self.orbit_camera.drag_pan(dx, dy, units_per_pixel);
+            self.request_redraw();
+        }
+    }
+
+    /// Moves the camera by what the fingers did since the last update.
+    fn apply_touch_gesture(&mut self) {
+        let gesture = self.touch.take_gesture();
+        if gesture.is_empty() {
+            return;
+        }
+        // Dragging turns the world the way the finger goes
+        let view = self.focused_view();
+        let height = self.viewports()[view].height;
+        let camera = &mut self.cameras[view];
+        let orbit = gesture.orbit * TOUCH_ORBIT_SENSITIVITY;
+        camera.orbit(-orbit.x, -orbit.y);
+        // Spreading the fingers brings the target closer, by as much as
+        // they spread
+        camera.zoom(1.0 - 1.0 / gesture.pinch);
+        // What's under the fingers stays under them
+        let pan = gesture.pan * camera.world_units_per_pixel(height);
+        camera.pan(-pan.x, pan.y);
+    }
+
+    /// Whether the device can draw wireframes, see `set_wireframe`.
+    pub fn supports_wireframe(&self) -> bool {
+        self.wireframe_pipeline.is_some()
+    }
+
+    /// Draws the model as a wireframe or filled. Returns whether it's a
+    /// wireframe now, which it can't be without `Features::POLYGON_MODE_LINE`,
+    /// e.g. on WebGL2.
+    pub fn set_wireframe(&mut self, wireframe: bool) -> bool {
+        if wireframe && !self.supports_wireframe() {
+            log::warn!("Wireframes aren't supported by this adapter, staying in fill mode");
+        }
+        self.wireframe = wireframe && self.supports_wireframe();
+        self.request_redraw();
+        self.wireframe
+    }
+
+    /// Replaces the model drawn at the instances with a glTF model. The old
+    /// one stays if it fails to load. `path` is the user's file, not one of
+    /// the assets, so it's read relative to the working directory.
+    pub fn load_gltf(&mut self, path: impl AsRef<Path>) -> Result<(), GltfError> {
+        let path = path.as_ref();
+        let scope = self.gpu_errors.context().scope("texture upload");
+        let model = Model::load_gltf(
+            &FsSource::new(""),
+            path,
+            &self.device,
+            &self.queue,
+            &self.bind_group_layouts.materials,
+            &mut self.assets,
+        )?;
+        drop(scope);
+        self.replace_model(model, Some(path));
+        Ok(())
+    }
+
+    /// Replaces the model drawn at the instances with one generated mesh,
+    /// with the checkerboard texture.
+    pub fn set_geometry(&mut self, name: &str, geometry: &Geometry) {
+        let model = Model::from_geometry(
+            &self.device,
+            &self.queue,
+            &self.bind_group_layouts.materials.material,
+            name,
+            geometry,
+        );
+        self.replace_model(model, None);
+    }
+
+    /// The model drawn at every instance, `None` if it failed to load.
+    pub fn model(&self) -> Option<&Model> {
+        self.obj_model.as_ref()
+    }
+
+    /// Plays the animation `name` of the model, from the start. Only skinned
+    /// glTF models have animations.
+    pub fn play_animation(&mut self, name: &str, looping: bool) -> Result<(), AnimationError> {
+        let model = self.obj_model.as_mut().ok_or(AnimationError::NotSkinned)?;
+        model.play(name, looping)?;
+        if let Some(skinning) = &model.skinning {
+            skinning.write(&self.queue);
+        }
+        self.request_redraw();
+        Ok(())
+    }
+
+    /// The point lights, which orbit the scene. Changes show up after the
+    /// next `update`.
+    pub fn lights(&self) -> &Lights {
+        &self.lights
+    }
+
+    pub fn lights_mut(&mut self) -> &mut Lights {
+        &mut self.lights
+    }
+
+    /// Whether the model casts shadows, which takes a pass over the scene
+    /// from the light's point of view.
+    pub fn shadows(&self) -> bool {
+        self.shadow_map.enabled()
+    }
+
+    pub fn set_shadows(&mut self, enabled: bool) {
+        self.shadow_map.set_enabled(&self.queue, enabled);
+    }
+
+    /// The width and height of the shadow map in texels.
+    pub fn shadow_map_size(&self) -> u32 {
+        match self.frame_resources.desc(render_graph::SHADOW_MAP) {
+            Some(ResourceDesc {
+                size: render_graph::ResourceSize::Fixed { width, .. },
+                ..
+            }) => width,
+            _ => 0,
+        }
+    }
+
+    /// Recreates the shadow map with `size` by `size` texels, as far as the
+    /// device allows. Returns the size that's used.
+    pub fn set_shadow_map_size(&mut self, size: u32) -> u32 {
+        let size = size.clamp(1, self.device.limits().max_texture_dimension_2d);
+        if size != self.shadow_map_size() {
+            self.frame_resources.insert(
+                &self.device,
+                render_graph::SHADOW_MAP,
+                shadow::resource_desc(size),
+            );
+            self.shadow_map.set_view(
+                &self.device,
+                &self.bind_group_layouts.shadow,
+                self.frame_resources.view(render_graph::SHADOW_MAP),
+            );
+        }
+        size
+    }
+
+    pub fn key_bindings(&self) -> &KeyBindings {
+        &self.key_bindings
+    }
+
+    pub fn set_key_bindings(&mut self, key_bindings: KeyBindings) {
+        self.key_bindings = key_bindings;
+    }
+
+    /// What the named actions and axes are bound to, exiting, fullscreen
+    /// and the camera's movement among them. The keys they use take
+    /// precedence over the `KeyBindings`.
+    pub fn input_bindings(&self) -> &InputBindings {
+        self.input_map.bindings()
+    }
+
+    pub fn set_input_bindings(&mut self, bindings: InputBindings) {
+        self.input_map.set_bindings(bindings);
+    }
+
+    /// What the bound inputs are doing this frame.
+    pub fn input_map(&self) -> &InputMap {
+        &self.input_map
+    }
+
+    /// E.g. to move the sticks of a gamepad the state doesn't poll itself.
+    pub fn input_map_mut(&mut self) -> &mut InputMap {
+        &mut self.input_map
+    }
+
+    /// Does what `action` stands for, as if its key changed to `state`.
+    /// Actions only happen when pressed. Returns `false` if it did nothing,
+    /// e.g. toggling the debug overlay of a headless state.
+    pub fn handle_action(&mut self, action: Action, state: ElementState) -> bool {
+        if state != ElementState::Pressed {
+            return false;
+        }
+
+        match action {
+            Action::Exit => {
+                self.exit_requested = true;
+                true
+            }
+            Action::OpenWindow => {
+                self.open_window_requested = true;
+                true
+            }
+            Action::ToggleFullscreen | Action::ToggleExclusiveFullscreen => {
+                let mode = if action == Action::ToggleFullscreen {
+                    FullscreenMode::Borderless
+                } else {
+                    FullscreenMode::Exclusive
+                };
+                match &self.window {
+                    Some(window) => {
+                        // The resize that follows reconfigures the surface
+                        self.fullscreen.toggle(window, mode);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Action::Screenshot => {
+                self.capture_frame();
+                true
+            }
+            Action::ToggleVsync => {
+                let preference = if self.present_mode() == wgpu::PresentMode::Fifo {
+                    PresentModePreference::Auto
+                } else {
+                    PresentModePreference::Vsync
+                };
+                let present_mode = self.set_present_mode(preference);
+                log::info!("Present mode: {present_mode:?}");
+                true
+            }
+            Action::CycleFrameRateCap => {
+                let presets = frame_limiter::MAX_FPS_PRESETS;
+                let next = presets
+                    .iter()
+                    .position(|&max_fps| max_fps == self.max_fps())
+                    .map_or(0, |i| (i + 1) % presets.len());
+                self.set_max_fps(presets[next]);
+                log::info!("Frame rate cap: {:?}", self.max_fps());
+                true
+            }
+            Action::ToggleRedrawMode => {
+                self.set_redraw_mode(self.redraw_mode.toggled());
+                log::info!("Redraw mode: {:?}", self.redraw_mode);
+                true
+            }
+            Action::NextPostEffect => {
+                let effect = self.next_post_effect();
+                log::info!("Post-processing effect: {effect}");
+                true
+            }
+            Action::ToggleFrameTimeGraph => {
+                let visible = self.frame_time_graph.is_none();
+                self.set_frame_time_graph(visible)
+            }
+            Action::ToggleOverlay => match &mut self.debug_overlay {
+                Some(debug_overlay) => {
+                    debug_overlay.visible = !debug_overlay.visible;
+                    true
+                }
+                None => false,
+            },
+            // Manual reload, for when the file watcher misses a change
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::ReloadShaders => {
+                self.reload_pipelines();
+                true
+            }
+            #[cfg(target_arch = "wasm32")]
+            Action::ReloadShaders => false,
+            Action::ToggleMouseLook => {
+                self.set_mouse_look(!self.mouse_look);
+                true
+            }
+            Action::ToggleWireframe => {
+                let wireframe = self.set_wireframe(!self.wireframe);
+                log::info!("Wireframe: {wireframe}");
+                true
+            }
+            Action::ToggleShadows => {
+                self.set_shadows(!self.shadows());
+                log::info!("Shadows: {}", self.shadows());
+                true
+            }
+            Action::FreezeCulling => {
+                self.set_culling_frozen(!self.culling_frozen());
+                log::info!("Culling frozen: {}", self.culling_frozen());
+                true
+            }
+            Action::CycleSplitScreen => {
+                self.set_split_mode(self.split_mode.next());
+                log::info!("Split screen: {:?}", self.split_mode);
+                true
+            }
+            Action::ToggleCameraMode => {
+                self.set_camera_mode(self.camera_mode.toggled());
+                log::info!("Camera mode: {:?}", self.camera_mode);
+                true
+            }
+            Action::FocusScene => self.focus_scene(),
+            Action::ToggleViewFocus => {
+                let view = (self.focused_view() + 1) % self.split_mode.view_count();
+                let changed = view != self.focused_view();
+                self.set_focused_view(view);
+                if changed {
+                    log::info!("Moving the camera of view {}", view + 1);
+                }
+                changed
+            }
+            Action::IncreaseRenderScale | Action::DecreaseRenderScale => {
+                let step = if action == Action::IncreaseRenderScale {
+                    render_scale::RENDER_SCALE_STEP
+                } else {
+                    -render_scale::RENDER_SCALE_STEP
+                };
+                let scale = self.set_render_scale(self.render_scale + step);
+                log::info!("Render scale: {scale}");
+                true
+            }
+            Action::CycleDebugView => {
+                // Skipping the ones that can't be shown
+                let mut view = self.debug_view.next();
+                while !self.supports_debug_view(view) {
+                    view = view.next();
+                }
+                let view = self.set_debug_view(view);
+                log::info!("Debug view: {}", view.name());
+                true
+            }
+            Action::CycleAntialiasing => {
+                let antialiasing = self.set_antialiasing(self.antialiasing().next());
+                log::info!("Antialiasing: {antialiasing:?}");
+                true
+            }
+            Action::CycleTextureFiltering => {
+                let filtering = self
+                    .texture_filtering
+                    .map_or(TextureFiltering::Nearest, TextureFiltering::next);
+                self.set_texture_filtering(Some(filtering));
+                log::info!("Texture filtering: {filtering:?}");
+                true
+            }
+            Action::ToggleSelection => {
+                let selection = match self.selection {
+                    Some(_) => None,
+                    None => Some(0),
+                };
+                self.set_selection(selection);
+                log::info!("Selected instance: {selection:?}");
+                true
+            }
+            Action::ToggleRenderBundles => {
+                self.set_render_bundles(!self.render_bundles());
+                log::info!("Render bundles: {}", self.render_bundles());
+                true
+            }
+            Action::ToggleBloom => {
+                self.set_bloom(!self.bloom());
+                log::info!("Bloom: {}", self.bloom());
+                true
+            }
+            Action::ToggleGrid => {
+                self.set_grid(!self.grid());
+                log::info!("Grid: {}", self.grid());
+                true
+            }
+            Action::TogglePlasma => {
+                self.set_plasma(!self.plasma());
+                log::info!("Plasma background: {}", self.plasma());
+                true
+            }
+            Action::TogglePause => {
+                self.set_paused(!self.is_paused());
+                log::info!("Shader time paused: {}", self.is_paused());
+                true
+            }
+            Action::NextTonemap => {
+                let mut tonemap = self.tonemap();
+                tonemap.operator = tonemap.operator.next();
+                self.set_tonemap(tonemap);
+                log::info!("Tonemap: {}", tonemap.describe());
+                true
+            }
+            Action::IncreaseExposure | Action::DecreaseExposure => {
+                let mut tonemap = self.tonemap();
+                tonemap.adjust_exposure(if action == Action::IncreaseExposure {
+                    1
+                } else {
+                    -1
+                });
+                self.set_tonemap(tonemap);
+                log::info!("Tonemap: {}", tonemap.describe());
+                true
+            }
+            Action::ClearColorPreset(preset) => match color::PRESETS.get(preset) {
+                Some(&color) => {
+                    self.clear_color = color;
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    /// Total time simulated by `update` so far, e.g. for animating shaders.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The time, resolution and cursor the shaders see.
+    pub fn globals(&self) -> &Globals {
+        &self.globals
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.globals.is_paused()
+    }
+
+    /// Stops the time of `globals` while the rest of the app keeps running.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.globals.set_paused(paused);
+    }
+
+    /// Counts a frame, returning the new stats once per second.
+    pub fn tick_frame_counter(&mut self) -> Option<FrameStats> {
+        self.frame_counter.tick()
+    }
+
+    /// How long the GPU took for the passes of a recent frame, `None` without
+    /// timestamp queries or before the first timings came back.
+    pub fn last_gpu_timings(&self) -> Option<GpuTimings> {
+        self.profiler.as_ref().and_then(GpuProfiler::last_timings)
+    }
+
+    /// The adapter rendering, its limits and features.
+    pub fn gpu_info(&self) -> &GpuInfo {
+        &self.gpu_info
+    }
+
+    /// Which shader stages can read storage buffers. The lights and joints
+    /// fall back to uniform arrays where the ones they're read in can't.
+    pub fn storage_support(&self) -> StorageSupport {
+        self.storage_support
+    }
+
+    /// For creating what the render nodes need.
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// The passes every frame runs, in order.
+    pub fn render_graph(&self) -> &RenderGraph {
+        &self.render_graph
+    }
+
+    /// Nodes can be added and taken out between frames. `recreate_device`
+    /// starts over with the default ones, as the others have resources of
+    /// the old device.
+    pub fn render_graph_mut(&mut self) -> &mut RenderGraph {
+        &mut self.render_graph
+    }
+
+    /// The textures the render nodes share, see `render_graph` for the
+    /// names of the built-in ones.
+    pub fn frame_resources(&self) -> &FrameResources {
+        &self.frame_resources
+    }
+
+    /// Creates the frame resource `name` for the render nodes, replacing
+    /// the one there was. The ones the size of the frame follow it. Returns
+    /// `false` for the built-in ones, which the state has bound and manages
+    /// itself.
+    pub fn insert_frame_resource(&mut self, name: &str, desc: ResourceDesc) -> bool {
+        if render_graph::BUILT_IN_RESOURCES.contains(&name) {
+            return false;
+        }
+        self.frame_resources.insert(&self.device, name, desc);
+        true
+    }
+
+    /// The frame time graph, `None` while it's hidden.
+    pub fn frame_time_graph(&self) -> Option<&FrameTimeGraph> {
+        self.frame_time_graph.as_ref()
+    }
+
+    /// Shows or hides the frame time graph in the corner of the window.
+    /// Shown again, it starts over. Returns `false` if there's no window to
+    /// show it in or its pipeline couldn't be built.
+    pub fn set_frame_time_graph(&mut self, visible: bool) -> bool {
+        if !visible {
+            self.frame_time_graph = None;
+            return true;
+        }
+        if self.window.is_none() {
+            return false;
+        }
+        if self.frame_time_graph.is_none() {
+            let format = color::render_format(&self.config);
+            match FrameTimeGraph::new(&self.device, format) {
+                Ok(graph) => self.frame_time_graph = Some(graph),
+                Err(err) => {
+                    log::error!("Can't show the frame time graph: {err}");
+                    return false;
+                }
+            }
+        }
+        true
     }
 
-    pub fn window(&self) -> &Window {
-        &self.window
+    /// Frame statistics over the last second, `None` during the first one.
+    pub fn frame_stats(&self) -> Option<FrameStats> {
+        self.frame_counter.stats()
     }
 
-    pub fn resize(&mut self, new_size: dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+    /// Moves the simulated part of the scene, the lights and the animations,
+    /// by one step of `dt`. `update` calls it at the fixed update rate, so
+    /// they move as fast whatever the frame rate.
+    pub fn fixed_update(&mut self, dt: Duration) {
+        self.lights.begin_step();
+        self.instances.begin_step();
+        self.lights.orbit(LIGHT_ORBIT_SPEED * dt.as_secs_f32());
+        if let Some(model) = &mut self.obj_model {
+            model.update_animation(&self.queue, dt);
         }
     }
 
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
-        return match event {
-            WindowEvent::CursorMoved { position, .. } => {
-                if position.x < ((self.size.width / 2) as f64) {
-                    self.clear_color = wgpu::Color {
-                        r: 0.1,
-                        g: 0.2,
-                        b: 0.3,
-                        a: 1.0,
-                    }
-                } else {
-                    self.clear_color = wgpu::Color {
-                        r: 0.3,
-                        g: 0.2,
-                        b: 0.1,
-                        a: 1.0,
-                    }
+    /// Advances the scene by `dt`, the time since the last update, clamped
+    /// to `MAX_FRAME_TIME`. The camera and particles move by `dt`, the rest
+    /// in as many fixed steps as fit, see `fixed_update`.
+    pub fn update(&mut self, dt: Duration) {
+        let update_start = self.frame_time_graph.is_some().then(Instant::now);
+        let dt = dt.min(MAX_FRAME_TIME);
+        self.elapsed += dt;
+        self.globals.advance(dt);
+        // The last update's lines have been shown
+        self.debug_draw.clear();
+        if self.grid {
+            self.debug_draw.axes(glam::Mat4::IDENTITY, 1.0);
+        }
+
+        let dropped = self.drop_loader.finished();
+        self.receive_drops(dropped);
+        let finished = self.asset_loader.finished();
+        self.receive_assets(&finished);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self
+            .shader_watcher
+            .as_ref()
+            .is_some_and(|watcher| watcher.changed())
+        {
+            self.reload_pipelines();
+        }
+
+        // Save the screenshots whose readback is done
+        self.device.poll(wgpu::Maintain::Poll);
+        self.pending_screenshots
+            .retain(|screenshot| !screenshot.try_save());
+        if self.picking.collect() {
+            self.selection = self.picking.picked().map(|picked| picked.instance);
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.collect();
+        }
+
+        #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+        if let Some(gamepad) = &mut self.gamepad {
+            use input_map::{GamepadAxis, GamepadButton};
+
+            let frame = gamepad.poll();
+            let map = &mut self.input_map;
+            map.set_gamepad_axis(GamepadAxis::LeftStickX, frame.movement.x);
+            map.set_gamepad_axis(GamepadAxis::LeftStickY, frame.movement.y);
+            map.set_gamepad_axis(GamepadAxis::RightStickX, frame.look.x);
+            map.set_gamepad_axis(GamepadAxis::RightStickY, frame.look.y);
+            map.set_gamepad_button(GamepadButton::Start, frame.exit);
+        }
+        self.apply_input_map(dt);
+
+        let view = self.focused_view();
+        let scroll = self.input.scroll_delta().y;
+        match self.camera_mode {
+            CameraMode::Fly => {
+                self.camera_controller
+                    .update_camera(&mut self.cameras[view], dt);
+                if scroll != 0.0 {
+                    self.cameras[view].zoom(scroll * ZOOM_PER_LINE);
                 }
-                true
+                self.apply_touch_gesture();
             }
-            _ => false,
-        };
-    }
+            CameraMode::Orbit => {
+                self.orbit_camera.zoom(scroll);
+                // The orbit picks up what the fingers did
+                self.apply_touch_gesture();
+                self.orbit_camera.update_camera(&mut self.cameras[view], dt);
+            }
+        }
+
+        let steps = self.fixed_timestep.advance(dt);
+        for _ in 0..steps {
+            self.fixed_update(self.fixed_timestep.step());
+        }
+
+        let mut uploads = self.create_upload_encoder();
+        let alpha = self.fixed_timestep.alpha();
+        let lights = self.lights.interpolated(alpha);
+        self.light_buffers.upload(
+            &self.device,
+            &mut uploads,
+            &mut self.uploads,
+            &self.bind_group_layouts.light,
+            &lights,
+        );
+        self.shadow_map
+            .update(&self.queue, shadow_light_position(&lights));
+        // Instances that stand still were uploaded when they were set
+        if self.instances.is_moving() || self.drawn_instances != self.instances() {
+            let drawn = self.instances.interpolated(alpha);
+            self.upload_instances(&mut uploads, drawn);
+        }
+
+        if let Some(particles) = &mut self.particles {
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Compute Encoder"),
+                });
+            let timestamp_writes = self
+                .profiler
+                .as_ref()
+                .map(GpuProfiler::compute_timestamp_writes);
+            particles.update(
+                &self.queue,
+                &mut encoder,
+                dt,
+                self.cameras[0].eye,
+                timestamp_writes,
+            );
+            if let Some(profiler) = &mut self.profiler {
+                profiler.resolve(&mut encoder, GpuPass::Compute);
+            }
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
 
-    pub fn update(&mut self) {}
+        for (view, camera) in self.cameras.iter().enumerate() {
+            let mut camera_uniform = self.camera_uniforms[view];
+            camera_uniform.update_view_proj(camera);
+            if camera_uniform != self.camera_uniforms[view] {
+                self.camera_uniforms[view] = camera_uniform;
+                self.camera_buffer.upload(
+                    &self.device,
+                    &mut uploads,
+                    &mut self.uploads,
+                    view,
+                    &camera_uniform,
+                );
+            }
+        }
+        self.globals_buffer.upload(
+            &self.device,
+            &mut uploads,
+            &mut self.uploads,
+            self.globals.uniform(),
+        );
+        self.update_culling(&mut uploads);
+        self.submit_uploads(uploads);
+
+        self.input.end_frame();
+        if let Some(update_start) = update_start {
+            self.update_time = update_start.elapsed();
+        }
+    }
 
+    /// Renders a frame and presents it. Frames are skipped while the app is
+    /// suspended. Headless states have nothing to present to, use
+    /// `render_to_vec` for them instead.
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.begin_frame();
+        // Getting the surface texture of a minimized window fails, and the
+        // size may have changed while it was
+        let inner_size = self.window().map_or(self.size, Window::inner_size);
+        match self.minimized.begin_frame(inner_size) {
+            FrameAction::Render => {}
+            FrameAction::Reconfigure(size) => self.resize(size),
+            FrameAction::Skip => return Ok(()),
+        }
+
+        let Some(surface) = &self.surface else {
+            return Ok(());
+        };
+
         /*
             The get_current_texture function will wait for the surface to provide a
             new SurfaceTexture that we will render to.
             We'll store this in output for later.
         */
-        let output = self.surface.get_current_texture()?;
+        let output = surface.get_current_texture()?;
+        let render_start = self.frame_time_graph.is_some().then(Instant::now);
+        self.render_pick();
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        // Non-sRGB surfaces may be drawn to through an sRGB view
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(color::render_format(&self.config)),
+            ..Default::default()
+        });
 
-        let mut encoder = self
+        let encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.clear_color),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
+        let encode_start = Instant::now();
+        self.update_scene_bundle();
+        let (mut encoder, view) = self.encode_frame(encoder, view, true);
+        self.frame_counter
+            .record_encode_time(encode_start.elapsed());
+        if let Some(profiler) = &mut self.profiler {
+            profiler.resolve(&mut encoder, GpuPass::Render);
+            profiler.end_frame(&mut encoder);
+        }
+
+        if let (Some(graph), Some(render_start)) = (&mut self.frame_time_graph, render_start) {
+            let gpu = self
+                .profiler
+                .as_ref()
+                .and_then(GpuProfiler::last_timings)
+                .map(|timings| timings.render + timings.compute.unwrap_or_default());
+            graph.record(FrameTime {
+                cpu: self.update_time + render_start.elapsed(),
+                gpu,
             });
+            let scale_factor = self
+                .window
+                .as_ref()
+                .map_or(1.0, |window| window.scale_factor());
+            graph.draw(&self.queue, &mut encoder, &view, self.size, scale_factor);
+        }
+
+        // Like the overlay below, the HUD isn't part of the scene
+        let drop_status = self.drop_status();
+        let eye = self.cameras[self.focused_view()].eye;
+        if let Some(hud) = &mut self.hud {
+            queue_hud_text(
+                hud,
+                self.frame_counter.stats(),
+                &self.gpu_info,
+                eye,
+                self.post_processor.tonemap(),
+                self.culled.stats,
+                self.culling_camera.is_some(),
+                self.debug_view,
+                drop_status,
+            );
+            hud.draw(&self.device, &self.queue, &mut encoder, &view);
+        }
+
+        // The overlay isn't part of the scene, so it's drawn here rather than
+        // in the render graph and doesn't end up in screenshots
+        let render_size = self.render_size();
+        let render_scale = self.render_scale;
+        let antialiasing = self.antialiasing();
+        if let (Some(debug_overlay), Some(window)) = (&mut self.debug_overlay, &self.window) {
+            let clear_color = &mut self.clear_color;
+            let camera_speed = &mut self.camera_controller.speed;
+            let present_mode = self.config.present_mode;
+            let tonemap = self.post_processor.tonemap();
+            let mut bloom_settings = self.post_processor.bloom().settings();
+            let mut outline_settings = self.outline.settings();
+            let gpu_timings = self.profiler.as_ref().and_then(GpuProfiler::last_timings);
+            debug_overlay.draw(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                window,
+                &output.texture,
+                |ctx| {
+                    egui::Window::new("Debug").show(ctx, |ui| {
+                        ui.label("Clear color");
+                        ui.add(egui::Slider::new(&mut clear_color.r, 0.0..=1.0).text("r"));
+                        ui.add(egui::Slider::new(&mut clear_color.g, 0.0..=1.0).text("g"));
+                        ui.add(egui::Slider::new(&mut clear_color.b, 0.0..=1.0).text("b"));
+                        ui.separator();
+                        ui.add(egui::Slider::new(camera_speed, 0.0..=10.0).text("Camera speed"));
+                        ui.separator();
+                        ui.label(format!(
+                            "Present mode: {present_mode:?} (V to toggle vsync)"
+                        ));
+                        ui.label(format!(
+                            "Tonemap: {} (T to switch, +/- for exposure)",
+                            tonemap.describe()
+                        ));
+                        ui.label(format!(
+                            "Render scale: {render_scale} ({}x{}, [ and ] to change)",
+                            render_size.width, render_size.height
+                        ));
+                        ui.label(format!("Antialiasing: {antialiasing:?}"));
+                        ui.label("Bloom (B to toggle)");
+                        ui.add(
+                            egui::Slider::new(&mut bloom_settings.threshold, 0.0..=4.0)
+                                .text("Threshold"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut bloom_settings.intensity, 0.0..=2.0)
+                                .text("Intensity"),
+                        );
+                        ui.label("Outline (click an instance, I for the first one)");
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_rgba_unmultiplied(&mut outline_settings.color);
+                            ui.add(
+                                egui::Slider::new(&mut outline_settings.thickness, 0.0..=0.3)
+                                    .text("Thickness"),
+                            );
+                        });
+                        if let Some(gpu_timings) = gpu_timings {
+                            ui.separator();
+                            ui.label(format!(
+                                "GPU render pass: {:.3} ms",
+                                gpu_timings.render.as_secs_f64() * 1000.0
+                            ));
+                            if let Some(compute) = gpu_timings.compute {
+                                ui.label(format!(
+                                    "GPU compute pass: {:.3} ms",
+                                    compute.as_secs_f64() * 1000.0
+                                ));
+                            }
+                        }
+                    });
+                },
+            );
+            // Used from the next frame on, this one's bloom is encoded already
+            if bloom_settings != self.post_processor.bloom().settings() {
+                self.set_bloom_settings(bloom_settings);
+            }
+            if outline_settings != self.outline.settings() {
+                self.set_outline_settings(outline_settings);
+            }
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
+        if let Some(profiler) = &mut self.profiler {
+            profiler.map();
+        }
         output.present();
+        self.frame_limiter.frame_presented(Instant::now());
 
         Ok(())
     }
 
+    /// Runs the render graph on a frame recorded into `encoder`, drawing
+    /// into `view`. Only `render` times the passes with `profile`.
+    fn encode_frame(
+        &mut self,
+        encoder: wgpu::CommandEncoder,
+        view: wgpu::TextureView,
+        profile: bool,
+    ) -> (wgpu::CommandEncoder, wgpu::TextureView) {
+        self.debug_draw.upload(&self.device, &self.queue);
+        // The nodes can't be borrowed from the state the frame borrows
+        let mut graph = std::mem::take(&mut self.render_graph);
+        let _scope = self.gpu_errors.context().scope("render pass");
+        let timestamp_writes = self
+            .profiler
+            .as_ref()
+            .filter(|_| profile)
+            .map(GpuProfiler::render_timestamp_writes);
+        let mut frame = Frame::new(self, encoder, view, timestamp_writes);
+        graph.run(&mut frame, &self.frame_resources);
+        let parts = frame.finish();
+        drop(_scope);
+        self.render_graph = graph;
+        parts
+    }
+
+    /// Records the draws of the scene's render pass, which draws into the
+    /// HDR texture, for each view into its own viewport. See
+    /// `render_graph::ScenePass`.
+    fn encode_scene<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, transparent: bool) {
+        // The targets have the render scale, the viewports follow them
+        let viewports = self.split_mode.viewports(self.frame_resources.size());
+        for (view, viewport) in viewports.into_iter().enumerate() {
+            if viewport.is_empty() {
+                continue;
+            }
+            viewport.apply(render_pass);
+            self.encode_view(render_pass, view, transparent);
+        }
+    }
+
+    /// Records the draws of the scene as the camera of `view` sees it.
+    fn encode_view<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        view: usize,
+        transparent: bool,
+    ) {
+        let camera = self.camera_binding(view);
+        // Only the model's fragments count, see `DebugView::Overdraw`
+        if self.debug_view == DebugView::Overdraw {
+            if let Some(obj_model) = &self.obj_model {
+                self.encode_model_draws_with(
+                    render_pass,
+                    view,
+                    self.debug_pipelines.overdraw(obj_model),
+                );
+            }
+            return;
+        }
+        // A bundle recorded with anything that changed since is left out,
+        // and it only has the first camera
+        let scene_bundle = self.scene_bundle.as_ref().filter(|bundle| {
+            self.render_bundles
+                && view == 0
+                && Some(bundle.key()) == self.scene_bundle_key().as_ref()
+        });
+        match scene_bundle {
+            Some(scene_bundle) => {
+                render_pass.execute_bundles(std::iter::once(scene_bundle.bundle()));
+            }
+            None => self.encode_model_draws(render_pass, view),
+        }
+        if let Some(obj_model) = &self.obj_model {
+            let overlay = self.debug_pipelines.wireframe_overlay(obj_model);
+            if let (DebugView::WireframeOverlay, Some(pipeline)) = (self.debug_view, overlay) {
+                self.encode_model_draws_with(render_pass, view, pipeline);
+            }
+        }
+
+        // The light gizmos move with the lights, so they're never bundled
+        if let Some(obj_model) = &self.obj_model {
+            render_pass.set_pipeline(&self.light_render_pipeline);
+            render_pass.draw_light_model_instanced(
+                obj_model,
+                0..self.lights.len() as u32,
+                camera,
+                self.light_buffers.bind_group(),
+            );
+        }
+
+        // The background would cover the transparent one
+        if !transparent {
+            if self.plasma {
+                render_pass.set_pipeline(&self.plasma_pipeline);
+                camera.bind(render_pass, 0);
+                render_pass.draw(0..3, 0..1);
+            } else if let Some(skybox) = &self.skybox {
+                skybox.draw(render_pass, camera);
+            }
+        }
+
+        // Blended over the background, the scene hides it by its depth
+        if self.grid {
+            render_pass.set_pipeline(&self.grid_pipeline);
+            camera.bind(render_pass, 0);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        // Over the background, which would cover it where the outline
+        // sticks out past the instance
+        if let (Some(obj_model), Some(instance)) = (&self.obj_model, self.selection) {
+            if (instance as usize) < self.instance_buffer.len() {
+                self.outline.draw(
+                    render_pass,
+                    camera,
+                    obj_model,
+                    self.instance_buffer.buffer(),
+                    instance..instance + 1,
+                );
+            }
+        }
+
+        // Blended over everything else, so they have to come last
+        if let Some(particles) = &self.particles {
+            particles.draw(render_pass, camera);
+        }
+
+        // Over everything, they're there to be seen
+        self.debug_draw.draw(render_pass, camera);
+    }
+
+    /// Counts the frame for the GPU error messages.
+    fn begin_frame(&mut self) {
+        self.frame_index += 1;
+        self.gpu_errors.context().set_frame(self.frame_index);
+    }
+
+    /// A texture like the surface's textures, but one we can copy from.
+    fn create_offscreen_target(&self) -> wgpu::Texture {
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: wgpu::Extent3d {
+                width: self.config.width,
+                height: self.config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &self.config.view_formats,
+        })
+    }
+
+    /// The view of an offscreen target drawn into, with the format of the
+    /// surface's views.
+    fn offscreen_view(&self, texture: &wgpu::Texture) -> wgpu::TextureView {
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(color::render_format(&self.config)),
+            ..Default::default()
+        })
+    }
+
+    /// Renders a frame into a texture we can copy from and starts reading it
+    /// back.
+    fn render_offscreen(&mut self) -> TextureReadback {
+        let texture = self.create_offscreen_target();
+        let encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Offscreen Encoder"),
+            });
+        let (mut encoder, _) = self.encode_frame(encoder, self.offscreen_view(&texture), false);
+        let mut readback = TextureReadback::new(&self.device, &mut encoder, &texture);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        readback.map();
+        readback
+    }
+
+    /// Renders a frame into `texture` and waits until the GPU is done with
+    /// it, so frames can be timed.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn render_and_wait(&mut self, texture: &wgpu::Texture) {
+        self.begin_frame();
+        let encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Benchmark Encoder"),
+            });
+        self.update_scene_bundle();
+        let (encoder, _) = self.encode_frame(encoder, self.offscreen_view(texture), false);
+        let submission = self.queue.submit(std::iter::once(encoder.finish()));
+        self.device
+            .poll(wgpu::Maintain::WaitForSubmissionIndex(submission));
+    }
+
+    /// Renders a frame offscreen and returns its tightly packed RGBA8 pixels,
+    /// row by row from the top left corner. Waits for the GPU to finish.
+    pub fn render_to_vec(&mut self) -> Result<Vec<u8>, wgpu::BufferAsyncError> {
+        self.begin_frame();
+        self.render_pick();
+        self.update_scene_bundle();
+        self.render_offscreen().read(&self.device)
+    }
+
+    /// Answers the click waiting for this frame, if there is one.
+    fn render_pick(&mut self) {
+        let scene = PickScene {
+            model: self.obj_model.as_ref(),
+            instance_buffer: self.instance_buffer.buffer(),
+            instance_count: self.instance_buffer.len() as u32,
+            views: (0..MAX_VIEWS)
+                .zip(self.viewports())
+                .map(|(view, viewport)| {
+                    let camera = CameraBinding {
+                        bind_group: &self.camera_bind_group,
+                        offset: self.camera_buffer.offset(view),
+                    };
+                    (viewport, camera)
+                })
+                .collect(),
+        };
+        self.picking.render(&self.device, &self.queue, scene);
+    }
+
+    /// Finds out what's at `position`, in physical pixels from the top left
+    /// of the window, with the next frame. Left clicks do this at the cursor.
+    /// `picked` has the result a frame or two later.
+    pub fn pick(&mut self, position: dpi::PhysicalPosition<f64>) {
+        self.picking.request(position, self.size);
+    }
+
+    /// What the last click or `pick` hit, `None` if it was the background.
+    pub fn picked(&self) -> Option<Picked> {
+        self.picking.picked()
+    }
+
+    /// The lines drawn since the last `update`.
+    pub fn debug_draw(&self) -> &DebugDraw {
+        &self.debug_draw
+    }
+
+    /// Draws lines into the frames until the next `update`, see
+    /// `debug_draw`.
+    pub fn debug_draw_mut(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
+
+    /// The index into `instances` of the instance with the outline.
+    pub fn selection(&self) -> Option<u32> {
+        self.selection
+    }
+
+    /// Outlines the instance `selection` indexes into `instances`, or none.
+    /// Clicks replace it with what they hit.
+    pub fn set_selection(&mut self, selection: Option<u32>) {
+        self.selection = selection;
+        self.request_redraw();
+    }
+
+    pub fn outline_settings(&self) -> OutlineSettings {
+        self.outline.settings()
+    }
+
+    pub fn set_outline_settings(&mut self, settings: OutlineSettings) {
+        self.outline.set_settings(&self.queue, settings);
+        self.request_redraw();
+    }
+
+    /// The sampler for `desc`, shared with everything else asking for the
+    /// same one.
+    pub fn sampler(&mut self, desc: &SamplerDesc) -> Arc<wgpu::Sampler> {
+        self.sampler_cache.get(&self.device, desc)
+    }
+
+    pub fn sampler_cache(&self) -> &SamplerCache {
+        &self.sampler_cache
+    }
+
+    pub fn texture_filtering(&self) -> Option<TextureFiltering> {
+        self.texture_filtering
+    }
+
+    /// Samples the textures of the model's first material with
+    /// `filtering`, or with the textures' own samplers for `None`. Models
+    /// loaded later get it too.
+    pub fn set_texture_filtering(&mut self, filtering: Option<TextureFiltering>) {
+        self.texture_filtering = filtering;
+        self.apply_texture_filtering();
+        self.request_redraw();
+    }
+
+    fn apply_texture_filtering(&mut self) {
+        let sampler = self
+            .texture_filtering
+            .map(|filtering| self.sampler_cache.get(&self.device, &filtering.desc()));
+        if let Some(model) = &mut self.obj_model {
+            model.set_sampler(&self.device, &self.bind_group_layouts.materials, 0, sampler);
+        }
+    }
+
+    /// Renders the current frame again into a texture we can copy from, and
+    /// saves it as a PNG file in the working directory once the GPU is done.
+    ///
+    /// The surface texture can't be used directly as it usually doesn't
+    /// support `COPY_SRC`.
+    pub fn capture_frame(&mut self) {
+        let readback = self.render_offscreen();
+        self.pending_screenshots.push(PendingScreenshot {
+            readback,
+            path: capture::screenshot_file_name(),
+        });
+    }
+
+    /// Whether the app should exit, e.g. because Start was pressed on a
+    /// gamepad or `Action::Exit` happened. The event loop checks it after
+    /// input and after each update.
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
+    /// Whether `Action::OpenWindow` happened since the last call.
+    pub fn take_open_window_request(&mut self) -> bool {
+        std::mem::take(&mut self.open_window_requested)
+    }
+
+    /// Registers `on_exit` to run before the app exits, e.g. to save
+    /// something. It replaces the one registered before.
+    pub fn set_on_exit(&mut self, on_exit: impl FnMut(&mut State) + 'static) {
+        self.on_exit = Some(Box::new(on_exit));
+    }
+
+    /// Whether `exit` or `shut_down` was called.
+    pub fn is_exiting(&self) -> bool {
+        self.exiting
+    }
+
+    /// Starts shutting down: runs the `on_exit` hook, without waiting for
+    /// the GPU, see `poll_shut_down`. Only does anything the first time,
+    /// returns `false` after that.
+    pub fn shut_down(&mut self) -> bool {
+        if self.exiting {
+            return false;
+        }
+        self.exiting = true;
+
+        if let Some(mut on_exit) = self.on_exit.take() {
+            on_exit(self);
+        }
+        true
+    }
+
+    /// Saves the pending screenshots whose readback is done, without
+    /// blocking. Returns whether they all are, so it's fine to go.
+    pub fn poll_shut_down(&mut self) -> bool {
+        // Mapping callbacks only run when the device is polled, the
+        // readbacks would be dropped unfinished otherwise
+        self.device.poll(wgpu::Maintain::Poll);
+        self.pending_screenshots
+            .retain(|screenshot| !screenshot.try_save());
+        self.pending_screenshots.is_empty()
+    }
+
+    /// Waits for the GPU to finish what was submitted, then saves the
+    /// pending screenshots. For windows that close while the app goes on,
+    /// as nothing can force quit them.
+    pub fn finish_shut_down(&mut self) {
+        self.device.poll(wgpu::Maintain::Wait);
+        for screenshot in std::mem::take(&mut self.pending_screenshots) {
+            if !screenshot.try_save() {
+                log::warn!(
+                    "Screenshot {} wasn't finished in time",
+                    screenshot.path.display()
+                );
+            }
+        }
+    }
+
+    /// Shuts down, see `shut_down`, and stops the event loop once
+    /// `poll_shut_down` says the readbacks are done, which the event loop
+    /// checks before waiting for events. Exiting again before that, e.g.
+    /// with a second Escape, stops the event loop right away.
     pub fn exit(&mut self, event_loop_window_target: &EventLoopWindowTarget<()>) {
+        // Another request has to come in for the force quit
+        self.exit_requested = false;
         if self.exiting {
+            log::warn!("Exiting right away");
+            event_loop_window_target.exit();
             return;
         }
-        self.exiting = true;
 
-        println!("Exiting the program...");
-        event_loop_window_target.exit();
+        log::info!("Exiting the program...");
+        self.shut_down();
+        if self.poll_shut_down() {
+            event_loop_window_target.exit();
+        }
     }
 }
 
-pub async fn run() -> Result<(), Box<dyn Error>> {
-    env_logger::init();
+/// Runs the app in a default window, see `run_with`.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run() -> Result<(), AppError> {
+    run_with(RunConfig::default()).await
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_with(config: RunConfig) -> Result<(), AppError> {
+    init_logger(&config);
+    run_with_logger(config).await
+}
+
+/// Like `run_with`, for callers that already set up logging, e.g. to see
+/// what happens before the config is complete.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_with_logger(config: RunConfig) -> Result<(), AppError> {
     let evt_loop = EventLoop::new()?;
+    // The window gets opened once the loop is resumed
+    run_event_loop(evt_loop, None, config)
+}
+
+/// Sets up logging with the verbosity of `config`. The runners call it, it
+/// does nothing if the logger is already there, e.g. for the second
+/// benchmark of a test.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init_logger(config: &RunConfig) {
+    // RUST_LOG still wins when it's set
+    let _ = env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(config.default_log_filter()),
+    )
+    .try_init();
+}
+
+/// The size the canvas starts with on the web, where the window has no size
+/// of its own until the canvas is laid out.
+#[cfg(target_arch = "wasm32")]
+const CANVAS_SIZE: dpi::PhysicalSize<u32> = dpi::PhysicalSize::new(800, 600);
+
+/// Web entry point. The canvas is appended to the element with the id
+/// `canvas_parent_id`.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+pub async fn run_web(canvas_parent_id: String) -> Result<(), wasm_bindgen::JsValue> {
+    use winit::platform::web::WindowExtWebSys;
+
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    let config = RunConfig::default();
+    // Fails if run_web is called twice, the logger is already there then.
+    // There's no RUST_LOG on the web, so the verbosity applies to every crate.
+    if let Some(level) = config.verbosity.to_level() {
+        let _ = console_log::init_with_level(level);
+    }
+
+    let to_js_error =
+        |err: &dyn std::fmt::Display| wasm_bindgen::JsValue::from_str(&err.to_string());
+
+    let evt_loop = EventLoop::new().map_err(|err| to_js_error(&err))?;
+    let window = config
+        .build_window(&evt_loop)
+        .map_err(|err| to_js_error(&err))?;
+    // inner_size() is 0x0 until the canvas is given a size
+    let _ = window.request_inner_size(CANVAS_SIZE);
+
+    let canvas = window.canvas().ok_or("The window has no canvas")?;
+    web_sys::window()
+        .and_then(|win| win.document())
+        .and_then(|doc| doc.get_element_by_id(&canvas_parent_id))
+        .ok_or_else(|| format!("No element with id {canvas_parent_id:?}"))?
+        .append_child(&canvas)?;
+
+    // The event loop can't block on creating the state on the web, so
+    // unlike natively it's created before the loop starts
+    let app_window = AppWindow::new(window, &config)
+        .await
+        .map_err(|err| to_js_error(&err))?;
+    run_event_loop(evt_loop, Some(app_window), config).map_err(|err| to_js_error(&err))
+}
+
+/// A window of the app and the state rendering to it.
+struct AppWindow {
+    state: State,
+    last_frame: Instant,
+    /// Whether the last frame was followed right away by another one. If
+    /// not, the window sat idle waiting for input since.
+    animating: bool,
+    focused: bool,
+    /// Only the first window has them, see `open_window`. Shared with the
+    /// exit hook saving them.
+    settings: Option<Rc<RefCell<SettingsFile>>>,
+    /// Saves the placement once the window stopped moving and resizing.
+    placement_save: Debounce,
+}
+
+impl AppWindow {
+    async fn new(window: Window, config: &RunConfig) -> Result<Self, AppError> {
+        // state now owns the window
+        let mut state = State::new(
+            window,
+            config.sample_count,
+            config.present_mode,
+            config.adapter.clone(),
+            &config.device,
+        )
+        .await?;
+        state.set_max_fps(config.max_fps);
+        state.set_fixed_update_rate(config.fixed_update_rate);
+        state.set_transparent(config.transparent);
+        state.set_shadow_map_size(config.shadow_map_size);
+        Ok(Self {
+            state,
+            last_frame: Instant::now(),
+            animating: true,
+            focused: true,
+            settings: None,
+            placement_save: Debounce::new(settings::PLACEMENT_SAVE_DELAY),
+        })
+    }
+
+    /// Call it when the window was moved or resized.
+    fn placement_changed(&mut self, now: Instant) {
+        if let (Some(settings), Some(window)) = (&self.settings, self.state.window()) {
+            settings.borrow_mut().settings.update_placement(window);
+            self.placement_save.changed(now);
+        }
+    }
+
+    /// Saves a placement change that settled by `now`.
+    fn save_placement(&mut self, now: Instant) {
+        if let Some(settings) = &self.settings {
+            if self.placement_save.settled(now) {
+                settings.borrow().save();
+            }
+        }
+    }
+}
+
+/// Opens a window with `config`, showing the scene with its own camera.
+/// The first one gets `config.settings` and saves them when it closes.
+#[cfg(not(target_arch = "wasm32"))]
+fn open_window(
+    windows: &mut HashMap<winit::window::WindowId, AppWindow>,
+    config: &RunConfig,
+    event_loop_window_target: &EventLoopWindowTarget<()>,
+) -> Result<(), AppError> {
+    let settings = config.settings.clone().filter(|_| windows.is_empty());
+    let window = if settings.is_none() && config.settings.is_some() {
+        // The others would cover the first one
+        let mut config = config.clone();
+        config.window.position = None;
+        config.window.fullscreen = false;
+        config.build_window(event_loop_window_target)?
+    } else {
+        config.build_window(event_loop_window_target)?
+    };
+    let window_id = window.id();
+    // The event loop can't await, but creating the state only takes a moment
+    let mut app_window = pollster::block_on(AppWindow::new(window, config))?;
+    if let Some(settings) = settings {
+        settings.settings.apply_to_state(&mut app_window.state);
+        let settings = Rc::new(RefCell::new(settings));
+        let exit_settings = Rc::clone(&settings);
+        app_window
+            .state
+            .set_on_exit(move |state| exit_settings.borrow_mut().save_from_state(state));
+        app_window.settings = Some(settings);
+    }
+    windows.insert(window_id, app_window);
+    Ok(())
+}
+
+/// Every window has its own `State`, events are routed to it by window id.
+/// Closing a window drops its state, the app exits with the last one.
+/// `config.window.title` is the title of the windows, which the frame stats
+/// get appended to.
+///
+/// Without `first_window` the first window gets opened when the loop is
+/// resumed, which is the earliest Android allows creating a surface.
+fn run_event_loop(
+    evt_loop: EventLoop<()>,
+    first_window: Option<AppWindow>,
+    config: RunConfig,
+) -> Result<(), AppError> {
     evt_loop.set_control_flow(ControlFlow::Poll);
 
-    let window = WindowBuilder::new().build(&evt_loop)?;
-    // state now owns the window
-    let mut state = State::new(window).await?;
+    let mut windows = HashMap::new();
+    if let Some(app_window) = first_window {
+        let window_id = app_window
+            .state
+            .window()
+            .expect("app windows have a window")
+            .id();
+        windows.insert(window_id, app_window);
+    }
+    // Set if the first window can't be opened, the loop exits then
+    #[cfg(not(target_arch = "wasm32"))]
+    let startup_error = std::rc::Rc::new(std::cell::RefCell::new(None));
+    #[cfg(not(target_arch = "wasm32"))]
+    let handler_startup_error = std::rc::Rc::clone(&startup_error);
+
+    let event_handler = move |event, event_loop_window_target: &EventLoopWindowTarget<()>| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } => {
+                let is_last_window = windows.len() == 1;
+                // Events can still arrive for a window that was just closed
+                let Some(app_window) = windows.get_mut(&window_id) else {
+                    return;
+                };
+                let state = &mut app_window.state;
+                if let WindowEvent::Focused(focused) = event {
+                    app_window.focused = *focused;
+                    if *focused {
+                        // Don't make up for the time spent in the background
+                        app_window.animating = false;
+                        state.request_redraw();
+                    }
+                }
+                let consumed = state.input(event);
+                if consumed {
+                    // Input usually changes what's on screen
+                    state.request_redraw();
+                    // There's only the one canvas on the web
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if state.take_open_window_request() {
+                        // The other windows keep going if it fails
+                        if let Err(err) =
+                            open_window(&mut windows, &config, event_loop_window_target)
+                        {
+                            log::error!("Failed to open a window: {err}");
+                        }
+                        return;
+                    }
+                }
+                // Closing the window, `Action::Exit` does the same
+                if matches!(event, WindowEvent::CloseRequested) || state.exit_requested() {
+                    // The last window stays until the loop is done, so it
+                    // can still be force quit
+                    if is_last_window {
+                        state.exit(event_loop_window_target);
+                    } else {
+                        state.shut_down();
+                        state.finish_shut_down();
+                        windows.remove(&window_id);
+                    }
+                    return;
+                }
+                if consumed {
+                    return;
+                }
 
-    evt_loop.run(move |event, event_loop_window_target| match event {
-        Event::WindowEvent {
-            ref event,
-            window_id,
-        } if window_id == state.window().id() => {
-            if !state.input(event) {
                 match event {
-                    WindowEvent::CloseRequested
-                    | WindowEvent::KeyboardInput {
-                        event:
-                            KeyEvent {
-                                state: ElementState::Pressed,
-                                logical_key: Key::Named(NamedKey::Escape),
-                                ..
-                            },
-                        ..
-                        // Exiting the program
-                    } => state.exit(event_loop_window_target),
                     WindowEvent::Resized(physical_size) => {
                         state.resize(*physical_size);
+                        app_window.placement_changed(Instant::now());
                     }
+                    WindowEvent::Moved(_) => app_window.placement_changed(Instant::now()),
+                    WindowEvent::Occluded(occluded) => state.set_occluded(*occluded),
                     WindowEvent::ScaleFactorChanged { .. } => {
-                        let inner_size = state.window().inner_size();
-                        state.resize(inner_size);
+                        if let Some(inner_size) = state.window().map(Window::inner_size) {
+                            state.resize(inner_size);
+                        }
                     }
-                    WindowEvent::RedrawRequested {} => {
-                        state.update();
-                        match state.render() {
-                            Ok(_) => {}
-                            Err(err) => {
-                                eprintln!("{:?}", err);
-                                match err {
-                                    wgpu::SurfaceError::Lost => state.resize(state.size),
-                                    wgpu::SurfaceError::OutOfMemory => {
-                                        state.exit(event_loop_window_target);
-                                    }
-                                    _ => {}
-                                }
+                    // Nothing new gets drawn while the last readbacks finish
+                    WindowEvent::RedrawRequested if state.is_exiting() => {}
+                    WindowEvent::RedrawRequested => {
+                        if state.device_lost() {
+                            // The event loop can't await, creating the device
+                            // only takes a moment though
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if let Err(err) = pollster::block_on(state.recreate_device()) {
+                                log::error!("Failed to recreate the device: {err}");
+                                state.exit(event_loop_window_target);
+                                return;
+                            }
+                            // Blocking isn't possible on the web
+                            #[cfg(target_arch = "wasm32")]
+                            {
+                                log::error!("Lost the device, reload the page to start over");
+                                state.exit(event_loop_window_target);
+                                return;
                             }
+                        }
+                        if let Some(stats) = state.tick_frame_counter() {
+                            // Not done every frame, set_title can be slow
+                            if let Some(window) = state.window() {
+                                let cap = match state.max_fps() {
+                                    Some(max_fps) => format!(" — capped at {max_fps}"),
+                                    None => String::new(),
+                                };
+                                let bundles = if state.render_bundles() {
+                                    ", bundled"
+                                } else {
+                                    ""
+                                };
+                                window.set_title(&format!(
+                                    "{} — {:.0} fps ({:.2} ms, {:.2} ms encoding{bundles}) — {:?}{cap}",
+                                    config.window.title,
+                                    stats.fps,
+                                    stats.avg_frame_time.as_secs_f64() * 1000.0,
+                                    stats.avg_encode_time.as_secs_f64() * 1000.0,
+                                    state.present_mode()
+                                ));
+                            }
+                        }
+
+                        let now = Instant::now();
+                        // Time spent idle in on-demand mode or in the
+                        // background didn't pass for the scene, the camera
+                        // would jump otherwise
+                        let animates =
+                            app_window.focused || config.background.animates_in_background();
+                        let dt = if app_window.animating && animates {
+                            now - app_window.last_frame
+                        } else {
+                            Duration::ZERO
                         };
+                        log::trace!("Redrawing {window_id:?} after {dt:?}");
+                        state.update(dt);
+                        app_window.last_frame = now;
+                        app_window.animating = state.wants_continuous_redraw();
+                        if state.exit_requested() {
+                            state.exit(event_loop_window_target);
+                            return;
+                        }
+                        if let Err(err) = state.render() {
+                            if state.handle_surface_error(err) == SurfaceErrorAction::Exit {
+                                state.exit(event_loop_window_target);
+                            }
+                        }
                     }
                     _ => {}
                 }
             }
+            Event::Resumed => {
+                #[cfg(not(target_arch = "wasm32"))]
+                if windows.is_empty() {
+                    if let Err(err) = open_window(&mut windows, &config, event_loop_window_target) {
+                        *handler_startup_error.borrow_mut() = Some(err);
+                        event_loop_window_target.exit();
+                    }
+                    return;
+                }
+                for app_window in windows.values_mut() {
+                    if let Err(err) = app_window.state.resume() {
+                        log::error!("Failed to resume: {err}");
+                        app_window.state.exit(event_loop_window_target);
+                    }
+                }
+            }
+            // Android destroys the surfaces while the app is in the background
+            Event::Suspended => {
+                for app_window in windows.values_mut() {
+                    app_window.state.suspend();
+                }
+            }
+            // Only the window with mouse look enabled turns its camera
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta: (dx, dy) },
+                ..
+            } => {
+                for app_window in windows.values_mut() {
+                    app_window.state.mouse_motion(dx, dy);
+                }
+            }
+            Event::AboutToWait => {
+                let now = Instant::now();
+                // The last window is shutting down, the loop keeps going
+                // until its readbacks are done or it's exited again
+                let shutting_down = windows
+                    .values_mut()
+                    .find(|app_window| app_window.state.is_exiting());
+                if let Some(app_window) = shutting_down {
+                    if app_window.state.poll_shut_down() {
+                        event_loop_window_target.exit();
+                    } else {
+                        event_loop_window_target.set_control_flow(ControlFlow::Poll);
+                    }
+                    return;
+                }
+                // The earliest another frame or a save is due, `None` to wait
+                // for events
+                let mut wake_up: Option<Instant> = None;
+                for app_window in windows.values_mut() {
+                    app_window.save_placement(now);
+                    wake_up = match (wake_up, app_window.placement_save.deadline()) {
+                        (Some(wake_up), Some(deadline)) => Some(wake_up.min(deadline)),
+                        (wake_up, deadline) => wake_up.or(deadline),
+                    };
+                }
+                for app_window in windows.values() {
+                    if !app_window.state.wants_continuous_redraw() {
+                        continue;
+                    }
+                    let schedule =
+                        config
+                            .background
+                            .schedule(app_window.focused, app_window.last_frame, now);
+                    let due = match schedule {
+                        FrameSchedule::Now => app_window.state.next_frame_due().unwrap_or(now),
+                        FrameSchedule::At(next_frame) => next_frame,
+                        FrameSchedule::Idle => continue,
+                    };
+
+                    // Sleeping tends to overshoot, so the last moment before
+                    // the frame is spent spinning instead
+                    let wake_up_at = if due <= now + frame_limiter::SPIN_THRESHOLD {
+                        while Instant::now() < due {
+                            std::hint::spin_loop();
+                        }
+                        app_window.state.request_redraw();
+                        now
+                    } else {
+                        due - frame_limiter::SPIN_THRESHOLD
+                    };
+                    wake_up = Some(wake_up.map_or(wake_up_at, |other| other.min(wake_up_at)));
+                }
+                event_loop_window_target.set_control_flow(match wake_up {
+                    None => ControlFlow::Wait,
+                    Some(wake_up) if wake_up <= now => ControlFlow::Poll,
+                    Some(wake_up) => ControlFlow::WaitUntil(wake_up),
+                });
+            }
+            _ => {}
         }
-        Event::AboutToWait => {
-            state.window().request_redraw();
+    };
+
+    // The browser owns the event loop on the web, we can't block until it
+    // exits. spawn returns right away and lets the handler run from the
+    // browser's event loop.
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::EventLoopExtWebSys;
+        evt_loop.spawn(event_handler);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        evt_loop.run(event_handler)?;
+        if let Some(err) = startup_error.take() {
+            return Err(err);
         }
-        _ => {}
-    })?;
+    }
 
     Ok(())
 }
+
+/// Untimed frames `run_benchmark` renders first, so compiling shaders and
+/// the like on the first frames doesn't count.
+pub const BENCH_WARMUP_FRAMES: u32 = 10;
+
+/// Renders `frames` frames of the scene without a window as fast as possible
+/// and reports how long they took. `WGPU_BACKEND` picks the backend to
+/// measure.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_benchmark(
+    frames: u32,
+    width: u32,
+    height: u32,
+) -> Result<bench::BenchReport, AppError> {
+    run_benchmark_with(&RunConfig::default(), frames, width, height).await
+}
+
+/// Like `run_benchmark`, on the adapter and with the sample count and
+/// device of `config`.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_benchmark_with(
+    config: &RunConfig,
+    frames: u32,
+    width: u32,
+    height: u32,
+) -> Result<bench::BenchReport, AppError> {
+    init_logger(config);
+
+    let mut state = State::new_headless_with_config(width, height, config).await?;
+    let texture = state.create_offscreen_target();
+    // The scene moves on as much as it would at 60 FPS each frame
+    let dt = Duration::from_secs(1) / 60;
+    for _ in 0..BENCH_WARMUP_FRAMES {
+        state.update(dt);
+        state.render_and_wait(&texture);
+    }
+
+    let mut frame_times = Vec::with_capacity(frames as usize);
+    let start = Instant::now();
+    for _ in 0..frames {
+        let frame_start = Instant::now();
+        state.update(dt);
+        state.render_and_wait(&texture);
+        frame_times.push(frame_start.elapsed());
+    }
+    let total = start.elapsed();
+
+    Ok(bench::BenchReport::new(
+        state.adapter.get_info(),
+        width,
+        height,
+        &frame_times,
+        total,
+    ))
+}
+
+/// Compares uploading `writes_per_frame` small writes per frame through
+/// `queue.write_buffer` with uploading them through an `UploadArena`, see
+/// `upload::benchmark`.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_upload_benchmark(
+    frames: u32,
+    writes_per_frame: u32,
+) -> Result<upload::UploadBenchReport, AppError> {
+    run_upload_benchmark_with(&RunConfig::default(), frames, writes_per_frame).await
+}
+
+/// Like `run_upload_benchmark`, on the adapter and with the device of
+/// `config`.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_upload_benchmark_with(
+    config: &RunConfig,
+    frames: u32,
+    writes_per_frame: u32,
+) -> Result<upload::UploadBenchReport, AppError> {
+    init_logger(config);
+
+    // Nothing gets drawn, the sample count doesn't matter
+    let state = State::headless(
+        1,
+        1,
+        1,
+        SrgbEncoding::Format,
+        &config.adapter,
+        &config.device,
+    )
+    .await?;
+    Ok(upload::benchmark(
+        &state.device,
+        &state.queue,
+        frames,
+        writes_per_frame,
+    ))
+}
+
+/// Renders a single frame of the scene without a window and returns its
+/// RGBA8 pixels, e.g. to check the rendering on a machine without a display.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_headless(width: u32, height: u32) -> Result<Vec<u8>, AppError> {
+    // Tests may call this several times, only the first call can init the logger
+    let _ = env_logger::try_init();
+
+    let mut state = State::new_headless(width, height, DEFAULT_SAMPLE_COUNT).await?;
+    state.update(Duration::ZERO);
+    Ok(state.render_to_vec()?)
+}
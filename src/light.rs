@@ -0,0 +1,394 @@
+use std::{borrow::Cow, collections::HashMap, mem::offset_of};
+
+use glam::{Quat, Vec3};
+
+use crate::{
+    storage::{ArrayBinding, StorageBuffer, StorageSupport},
+    uniform::{Uniform, UniformBuffer, UniformField, WgslType},
+    upload::UploadArena,
+};
+
+/// How far a light reaches unless changed, further than the scene goes.
+pub const DEFAULT_LIGHT_RADIUS: f32 = 20.0;
+
+/// How many lights the shaders can see where the lights are in a uniform
+/// array instead of a storage buffer.
+pub const MAX_UNIFORM_LIGHTS: usize = 16;
+
+/// The vertex shaders draw the lights, the fragment shaders shade with them.
+const LIGHT_STAGES: wgpu::ShaderStages =
+    wgpu::ShaderStages::VERTEX.union(wgpu::ShaderStages::FRAGMENT);
+
+/// How the lights get to the shaders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightBinding {
+    /// A read-only storage buffer, as big as it needs to be.
+    Storage,
+    /// An array of `MAX_UNIFORM_LIGHTS` in a uniform buffer, for WebGL2 and
+    /// other downlevel targets whose shaders can't read storage buffers.
+    Uniform,
+}
+
+impl LightBinding {
+    /// Storage buffers where both the vertex shaders, which draw the lights,
+    /// and the fragment shaders, which shade with them, can read them.
+    pub fn for_support(support: StorageSupport) -> Self {
+        match support.binding(LIGHT_STAGES, MAX_UNIFORM_LIGHTS) {
+            ArrayBinding::Storage => Self::Storage,
+            ArrayBinding::Uniform { .. } => Self::Uniform,
+        }
+    }
+
+    pub fn array_binding(self) -> ArrayBinding {
+        match self {
+            Self::Storage => ArrayBinding::Storage,
+            Self::Uniform => ArrayBinding::Uniform {
+                len: MAX_UNIFORM_LIGHTS,
+            },
+        }
+    }
+
+    /// The most lights the shaders can see, `None` if only the size of the
+    /// buffer limits them.
+    pub fn max_lights(self) -> Option<usize> {
+        self.array_binding().max_len()
+    }
+}
+
+/// Adapts the WGSL `source` of a shader reading the lights to `binding`. The
+/// shaders declare them as a storage buffer, the uniform fallback replaces
+/// that with a fixed-size array.
+pub fn shader_source(source: &str, binding: LightBinding) -> Cow<'_, str> {
+    binding
+        .array_binding()
+        .shader_source(source, "lights", "Light")
+}
+
+/// A point light as the shaders see it. The scalars fill the padding after
+/// the vec3s, which keeps it at 32 bytes, a multiple of 16 as uniform arrays
+/// require.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightRaw {
+    pub position: [f32; 3],
+    pub intensity: f32,
+    pub color: [f32; 3],
+    pub radius: f32,
+}
+
+impl Uniform for LightRaw {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new("position", offset_of!(LightRaw, position), WgslType::Vec3),
+        UniformField::new("intensity", offset_of!(LightRaw, intensity), WgslType::F32),
+        UniformField::new("color", offset_of!(LightRaw, color), WgslType::Vec3),
+        UniformField::new("radius", offset_of!(LightRaw, radius), WgslType::F32),
+    ];
+}
+
+/// What the shaders need to know about the lights besides each light.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightsUniform {
+    /// Added once, however many lights there are.
+    pub ambient: [f32; 3],
+    /// How many of the lights are used.
+    pub count: u32,
+}
+
+impl Uniform for LightsUniform {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "ambient",
+            offset_of!(LightsUniform, ambient),
+            WgslType::Vec3,
+        ),
+        UniformField::new("count", offset_of!(LightsUniform, count), WgslType::U32),
+    ];
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: Vec3,
+    /// Scales the color, 1 is as bright as the color itself.
+    pub intensity: f32,
+    /// The light fades out towards this distance and lights nothing beyond.
+    pub radius: f32,
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, color: Vec3) -> Self {
+        Self {
+            position,
+            color,
+            intensity: 1.0,
+            radius: DEFAULT_LIGHT_RADIUS,
+        }
+    }
+
+    pub fn to_raw(&self) -> LightRaw {
+        LightRaw {
+            position: self.position.into(),
+            intensity: self.intensity,
+            color: self.color.into(),
+            radius: self.radius,
+        }
+    }
+
+    /// Rotates the light around the y axis going through the origin.
+    pub fn orbit(&mut self, angle_degrees: f32) {
+        self.position = Quat::from_rotation_y(angle_degrees.to_radians()) * self.position;
+    }
+}
+
+/// Identifies a light in `Lights`, it stays the same when other lights are
+/// removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightId(u64);
+
+/// The point lights of the scene. The first one casts the shadows.
+#[derive(Debug, Clone)]
+pub struct Lights {
+    lights: Vec<(LightId, PointLight)>,
+    /// Where the lights were at the fixed step before the last one, see
+    /// `interpolated`.
+    previous_positions: HashMap<LightId, Vec3>,
+    next_id: u64,
+    /// `None` without a limit.
+    max_lights: Option<usize>,
+    /// The light reaching everything, even the shadows.
+    pub ambient: Vec3,
+}
+
+impl Lights {
+    /// No lights yet. At most `max_lights` can be added, see
+    /// `LightBinding::max_lights`.
+    pub fn new(max_lights: Option<usize>) -> Self {
+        Self {
+            lights: Vec::new(),
+            previous_positions: HashMap::new(),
+            next_id: 0,
+            max_lights,
+            // We don't need (or want) much ambient light, so 0.1 is fine
+            ambient: Vec3::splat(0.1),
+        }
+    }
+
+    /// `None` if there are as many lights as the shaders can see already.
+    pub fn add_light(&mut self, light: PointLight) -> Option<LightId> {
+        if self.max_lights.is_some_and(|max| self.lights.len() >= max) {
+            return None;
+        }
+        let id = LightId(self.next_id);
+        self.next_id += 1;
+        self.lights.push((id, light));
+        Some(id)
+    }
+
+    pub fn remove_light(&mut self, id: LightId) -> Option<PointLight> {
+        let index = self
+            .lights
+            .iter()
+            .position(|(light_id, _)| *light_id == id)?;
+        self.previous_positions.remove(&id);
+        Some(self.lights.remove(index).1)
+    }
+
+    pub fn light(&self, id: LightId) -> Option<&PointLight> {
+        self.iter()
+            .find(|(light_id, _)| *light_id == id)
+            .map(|(_, light)| light)
+    }
+
+    /// For changing the color, intensity, radius or position of a light.
+    pub fn light_mut(&mut self, id: LightId) -> Option<&mut PointLight> {
+        self.lights
+            .iter_mut()
+            .find(|(light_id, _)| *light_id == id)
+            .map(|(_, light)| light)
+    }
+
+    /// In the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (LightId, &PointLight)> {
+        self.lights.iter().map(|(id, light)| (*id, light))
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    pub fn max_lights(&self) -> Option<usize> {
+        self.max_lights
+    }
+
+    /// Rotates every light around the y axis going through the origin.
+    pub fn orbit(&mut self, angle_degrees: f32) {
+        for (_, light) in &mut self.lights {
+            light.orbit(angle_degrees);
+        }
+    }
+
+    pub fn uniform(&self) -> LightsUniform {
+        LightsUniform {
+            ambient: self.ambient.into(),
+            count: self.lights.len() as u32,
+        }
+    }
+
+    /// Call it before each fixed step moves the lights: where they are now
+    /// becomes where they were at the step before.
+    pub fn begin_step(&mut self) {
+        self.previous_positions = self
+            .lights
+            .iter()
+            .map(|(id, light)| (*id, light.position))
+            .collect();
+    }
+
+    /// The lights `alpha` of the way from where they were at the step before
+    /// the last one to where they are now, for drawing them between steps.
+    /// Lights added since are where they are.
+    pub fn interpolated(&self, alpha: f32) -> Lights {
+        let mut lights = self.clone();
+        for (id, light) in &mut lights.lights {
+            if let Some(previous) = self.previous_positions.get(id) {
+                light.position = previous.lerp(light.position, alpha);
+            }
+        }
+        lights
+    }
+
+    pub fn to_raw(&self) -> Vec<LightRaw> {
+        self.lights
+            .iter()
+            .map(|(_, light)| light.to_raw())
+            .collect()
+    }
+}
+
+/// The layout of the lights' bind group: the `LightsUniform` and the lights
+/// themselves, bound as `binding` says.
+pub fn create_bind_group_layout(
+    device: &wgpu::Device,
+    binding: LightBinding,
+) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("light_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: LIGHT_STAGES,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            binding.array_binding().layout_entry(1, LIGHT_STAGES),
+        ],
+    })
+}
+
+/// The buffers the shaders read the lights from, and their bind group.
+pub struct LightBuffers {
+    uniform: UniformBuffer<LightsUniform>,
+    lights: StorageBuffer<LightRaw>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl LightBuffers {
+    /// Uploads `lights`. `layout` comes from `create_bind_group_layout`
+    /// with the same `binding`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        binding: LightBinding,
+        lights: &Lights,
+    ) -> Self {
+        let uniform = UniformBuffer::new(device, "Lights Buffer", &lights.uniform());
+        let light_buffer = StorageBuffer::new(
+            device,
+            "Light Array Buffer",
+            binding.array_binding(),
+            lights.len(),
+        );
+        let bind_group = create_bind_group(device, layout, &uniform, &light_buffer);
+        light_buffer.write(queue, &lights.to_raw());
+        Self {
+            uniform,
+            lights: light_buffer,
+            bind_group,
+        }
+    }
+
+    /// Uploads `lights`. The storage buffer grows when they don't fit
+    /// anymore, `layout` is the one they were created with.
+    pub fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        lights: &Lights,
+    ) {
+        self.uniform.write(queue, &lights.uniform());
+        if self.lights.update(device, queue, &lights.to_raw()) {
+            self.recreate_bind_group(device, layout);
+        }
+    }
+
+    /// Like `write`, through `uploads` with copies recorded into `encoder`.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        uploads: &mut UploadArena,
+        layout: &wgpu::BindGroupLayout,
+        lights: &Lights,
+    ) {
+        self.uniform
+            .upload(device, encoder, uploads, &lights.uniform());
+        if self
+            .lights
+            .upload(device, encoder, uploads, &lights.to_raw())
+        {
+            self.recreate_bind_group(device, layout);
+        }
+    }
+
+    /// After the storage buffer grew, the bind group still has the old one.
+    fn recreate_bind_group(&mut self, device: &wgpu::Device, layout: &wgpu::BindGroupLayout) {
+        self.bind_group = create_bind_group(device, layout, &self.uniform, &self.lights);
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform: &UniformBuffer<LightsUniform>,
+    lights: &StorageBuffer<LightRaw>,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("light_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: lights.as_entire_binding(),
+            },
+        ],
+    })
+}
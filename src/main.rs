@@ -1,9 +1,91 @@
 use std::error::Error;
 
-use wgpu_learning::run;
+/// How many writes `--bench-uploads` makes per frame.
+#[cfg(not(target_arch = "wasm32"))]
+const BENCH_UPLOADS_PER_FRAME: u32 = 4000;
 
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<(), Box<dyn Error>> {
-    pollster::block_on(run())?;
+    use wgpu_learning::{
+        adapter,
+        cli::{Cli, Command, USAGE},
+        run_config::RunConfig,
+        settings::{self, SettingsFile},
+    };
 
+    // The Debug output `?` would print is no help here
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(2);
+        }
+    };
+    match &cli.command {
+        // The options win over the saved settings, and get saved with them
+        Command::Run => {
+            // Before the settings, so what loading them logs shows up
+            wgpu_learning::init_logger(&cli.run_config());
+            let mut config = RunConfig::default();
+            let settings = settings::default_path().map(SettingsFile::load);
+            if let Some(settings) = &settings {
+                settings.settings.apply_to_config(&mut config);
+            }
+            cli.apply(&mut config);
+            config.settings = settings;
+            pollster::block_on(wgpu_learning::run_with_logger(config))?
+        }
+        // `--headless --frames 1000` renders 1000 frames offscreen, prints
+        // how long they took and exits
+        Command::Headless { frames, json } => {
+            let size = cli.headless_size();
+            let report = pollster::block_on(wgpu_learning::run_benchmark_with(
+                &cli.run_config(),
+                *frames,
+                size.width,
+                size.height,
+            ))?;
+            println!("{report}");
+            if *json {
+                println!("{}", report.to_json());
+            }
+        }
+        // `--bench-uploads 1000` times 1000 frames of small uploads through
+        // both upload paths
+        Command::BenchUploads { frames } => {
+            let report = pollster::block_on(wgpu_learning::run_upload_benchmark_with(
+                &cli.run_config(),
+                *frames,
+                BENCH_UPLOADS_PER_FRAME,
+            ))?;
+            println!("{report}");
+        }
+        // `--shadertoy toy.wgsl` draws the `main_image` of toy.wgsl over the
+        // window instead of the scene, reloading it when it's saved
+        Command::Shadertoy { path } => pollster::block_on(
+            wgpu_learning::shadertoy::run_shadertoy_with(cli.run_config(), path),
+        )?,
+        Command::ListAdapters => {
+            let adapters = adapter::list_adapters_on(cli.run_config().device.backends);
+            if adapters.is_empty() {
+                println!("No adapters found");
+            }
+            for (index, info) in adapters.iter().enumerate() {
+                let driver = format!("{} {}", info.driver, info.driver_info);
+                match driver.trim() {
+                    "" => println!("{index}: {}", adapter::describe_adapter(info)),
+                    driver => println!("{index}: {}, {driver}", adapter::describe_adapter(info)),
+                }
+            }
+        }
+        Command::Help => println!("{USAGE}"),
+    }
+
+    Ok(())
+}
+
+// On the web the page calls `run_web` from the library instead
+#[cfg(target_arch = "wasm32")]
+fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
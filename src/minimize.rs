@@ -0,0 +1,70 @@
+use winit::dpi::PhysicalSize;
+
+/// What to do about the next frame, see `MinimizeTracker::begin_frame`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameAction {
+    Render,
+    /// The window came back, configure the surface with this size first.
+    Reconfigure(PhysicalSize<u32>),
+    /// The window can't be seen, don't render at all.
+    Skip,
+}
+
+/// Tracks whether a window is minimized from its resize and occlusion
+/// events. Minimizing on Windows resizes the window to 0x0, which the
+/// surface can't be configured with, other platforms only report it as
+/// occluded.
+///
+/// Kept apart from `State` so it can be tested without a window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MinimizeTracker {
+    zero_sized: bool,
+    occluded: bool,
+    /// The window came back without a resize, so the surface may still have
+    /// the size from before.
+    restored: bool,
+}
+
+impl MinimizeTracker {
+    pub fn is_minimized(&self) -> bool {
+        self.zero_sized || self.occluded
+    }
+
+    /// Call it for every resize. Returns whether the surface can be
+    /// configured with `size`.
+    pub fn resized(&mut self, size: PhysicalSize<u32>) -> bool {
+        self.zero_sized = size.width == 0 || size.height == 0;
+        if !self.zero_sized {
+            // The surface gets this fresh size right away
+            self.restored = false;
+        }
+        !self.zero_sized
+    }
+
+    pub fn set_occluded(&mut self, occluded: bool) {
+        let was_minimized = self.is_minimized();
+        self.occluded = occluded;
+        if was_minimized && !self.is_minimized() {
+            self.restored = true;
+        }
+    }
+
+    /// Call it before each frame with the current size of the window. A
+    /// window that was 0x0 is checked again, in case it was restored without
+    /// a resize event.
+    pub fn begin_frame(&mut self, inner_size: PhysicalSize<u32>) -> FrameAction {
+        if self.occluded {
+            return FrameAction::Skip;
+        }
+        if !self.zero_sized && !self.restored {
+            return FrameAction::Render;
+        }
+
+        self.restored = false;
+        if self.resized(inner_size) {
+            FrameAction::Reconfigure(inner_size)
+        } else {
+            FrameAction::Skip
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use crate::{
+    pipeline::{PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+};
+
+pub const MIPMAP_SHADER_SOURCE: &str = include_str!("../shaders/mipmap.wgsl");
+
+/// How many mip levels a full chain has for a `width`x`height` texture,
+/// down to a 1x1 level.
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    }
+    .max_mips(wgpu::TextureDimension::D2)
+}
+
+/// Fills the mip levels of 2D textures from their first level, one render
+/// pass per level. Keep one around when generating mipmaps for several
+/// textures, the pipelines are created once per format.
+pub struct MipmapGenerator {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipelines: HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("mipmap_bind_group_layout"),
+            // The shader weighs the texels itself, nothing gets filtered
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                },
+                count: None,
+            }],
+        });
+
+        Self {
+            bind_group_layout,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Records rendering every mip level of `texture` after the first one
+    /// from the level before. The texture needs `RENDER_ATTACHMENT` and
+    /// `TEXTURE_BINDING` usage, and a float format that can be rendered
+    /// to.
+    pub fn generate(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) -> Result<(), ShaderError> {
+        let format = texture.format();
+        if !self.pipelines.contains_key(&format) {
+            let targets = RenderTargets {
+                color_format: format,
+                depth_format: None,
+                sample_count: 1,
+            };
+            let pipeline = PipelineBuilder::with_targets("Mipmap Pipeline", targets)
+                .shader("mipmap.wgsl", MIPMAP_SHADER_SOURCE)
+                .fragment_entry_point("fs_main")
+                .bind_group_layouts(&[&self.bind_group_layout])
+                .build(device)?;
+            self.pipelines.insert(format, pipeline);
+        }
+        let pipeline = &self.pipelines[&format];
+
+        // Each level gets its own view, a view can't be read from and
+        // rendered to at once
+        let level_view = |level| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("mip_level_view"),
+                base_mip_level: level,
+                mip_level_count: Some(1),
+                ..Default::default()
+            })
+        };
+        for level in 1..texture.mip_level_count() {
+            let source = level_view(level - 1);
+            let target = level_view(level);
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("mipmap_bind_group"),
+                layout: &self.bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source),
+                }],
+            });
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Mipmap Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // Every texel gets overwritten
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        Ok(())
+    }
+}
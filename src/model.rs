@@ -0,0 +1,1356 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use glam::{Vec2, Vec3};
+
+use crate::{
+    animation::{AnimationError, Animator, JointBinding, SkinVertex, Skinning},
+    asset_source::{AssetSource, FsSource},
+    assets::{AssetLoader, Assets, Handle, LoadedImage},
+    buffer::{IndexBuffer, VertexBuffer},
+    camera::CameraBinding,
+    culling::Aabb,
+    geometry::Geometry,
+    gltf::{self, GltfError},
+    texture::Texture,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ModelVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 3],
+    pub bitangent: [f32; 3],
+}
+
+impl ModelVertex {
+    pub const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x2,
+        2 => Float32x3,
+        3 => Float32x3,
+        4 => Float32x3,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ModelVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// The textures are shared with the other materials using the same files,
+/// see `Assets`.
+pub struct Material {
+    pub name: String,
+    pub diffuse_texture: Arc<Texture>,
+    pub normal_texture: Arc<Texture>,
+    pub metallic_roughness_texture: Arc<Texture>,
+    pub bind_group: wgpu::BindGroup,
+    /// Samples all three textures instead of their own samplers, see
+    /// `set_sampler`.
+    sampler: Option<Arc<wgpu::Sampler>>,
+}
+
+impl Material {
+    /// Materials without a normal map should pass `Texture::flat_normal`,
+    /// and without a metallic-roughness map `Texture::smooth_dielectric`.
+    pub fn new(
+        device: &wgpu::Device,
+        name: &str,
+        diffuse_texture: impl Into<Arc<Texture>>,
+        normal_texture: impl Into<Arc<Texture>>,
+        metallic_roughness_texture: impl Into<Arc<Texture>>,
+        layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        Self::create(
+            device,
+            name,
+            diffuse_texture,
+            normal_texture,
+            metallic_roughness_texture,
+            layout,
+            None,
+        )
+    }
+
+    /// A material of a skinned mesh, which also binds the model's joint
+    /// matrices. `layout` comes from `create_skinned_bind_group_layout`.
+    pub fn new_skinned(
+        device: &wgpu::Device,
+        name: &str,
+        diffuse_texture: impl Into<Arc<Texture>>,
+        normal_texture: impl Into<Arc<Texture>>,
+        metallic_roughness_texture: impl Into<Arc<Texture>>,
+        layout: &wgpu::BindGroupLayout,
+        joint_buffer: &wgpu::Buffer,
+    ) -> Self {
+        Self::create(
+            device,
+            name,
+            diffuse_texture,
+            normal_texture,
+            metallic_roughness_texture,
+            layout,
+            Some(joint_buffer),
+        )
+    }
+
+    fn create(
+        device: &wgpu::Device,
+        name: &str,
+        diffuse_texture: impl Into<Arc<Texture>>,
+        normal_texture: impl Into<Arc<Texture>>,
+        metallic_roughness_texture: impl Into<Arc<Texture>>,
+        layout: &wgpu::BindGroupLayout,
+        joint_buffer: Option<&wgpu::Buffer>,
+    ) -> Self {
+        let (diffuse_texture, normal_texture, metallic_roughness_texture) = (
+            diffuse_texture.into(),
+            normal_texture.into(),
+            metallic_roughness_texture.into(),
+        );
+        let bind_group = Self::create_bind_group(
+            device,
+            name,
+            &diffuse_texture,
+            &normal_texture,
+            &metallic_roughness_texture,
+            None,
+            layout,
+            joint_buffer,
+        );
+
+        Self {
+            name: name.to_string(),
+            diffuse_texture,
+            normal_texture,
+            metallic_roughness_texture,
+            bind_group,
+            sampler: None,
+        }
+    }
+
+    /// Swaps one of the textures and rebuilds the bind group with `layout`,
+    /// which comes from `create_bind_group_layout`. Skinned materials would
+    /// lose their joint matrices this way.
+    pub fn set_texture(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        slot: TextureSlot,
+        texture: impl Into<Arc<Texture>>,
+    ) {
+        self.replace_texture(device, layout, slot, texture.into(), None);
+    }
+
+    fn replace_texture(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        slot: TextureSlot,
+        texture: Arc<Texture>,
+        joint_buffer: Option<&wgpu::Buffer>,
+    ) {
+        match slot {
+            TextureSlot::Diffuse => self.diffuse_texture = texture,
+            TextureSlot::Normal => self.normal_texture = texture,
+        }
+        self.recreate_bind_group(device, layout, joint_buffer);
+    }
+
+    /// The sampler set with `set_sampler`, `None` if each texture is
+    /// sampled with its own.
+    pub fn sampler(&self) -> Option<&Arc<wgpu::Sampler>> {
+        self.sampler.as_ref()
+    }
+
+    /// Samples every texture with `sampler`, e.g. one from a
+    /// `SamplerCache`, or with their own samplers again for `None`. Like
+    /// `set_texture`, it rebuilds the bind group with `layout`.
+    pub fn set_sampler(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: Option<Arc<wgpu::Sampler>>,
+    ) {
+        self.replace_sampler(device, layout, sampler, None);
+    }
+
+    fn replace_sampler(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: Option<Arc<wgpu::Sampler>>,
+        joint_buffer: Option<&wgpu::Buffer>,
+    ) {
+        self.sampler = sampler;
+        self.recreate_bind_group(device, layout, joint_buffer);
+    }
+
+    fn recreate_bind_group(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        joint_buffer: Option<&wgpu::Buffer>,
+    ) {
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.name,
+            &self.diffuse_texture,
+            &self.normal_texture,
+            &self.metallic_roughness_texture,
+            self.sampler.as_deref(),
+            layout,
+            joint_buffer,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_bind_group(
+        device: &wgpu::Device,
+        name: &str,
+        diffuse_texture: &Texture,
+        normal_texture: &Texture,
+        metallic_roughness_texture: &Texture,
+        sampler: Option<&wgpu::Sampler>,
+        layout: &wgpu::BindGroupLayout,
+        joint_buffer: Option<&wgpu::Buffer>,
+    ) -> wgpu::BindGroup {
+        let joint_entry = joint_buffer.map(|buffer| wgpu::BindGroupEntry {
+            binding: 6,
+            resource: buffer.as_entire_binding(),
+        });
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(name),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(
+                        sampler.unwrap_or(&diffuse_texture.sampler),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&normal_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(
+                        sampler.unwrap_or(&normal_texture.sampler),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&metallic_roughness_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Sampler(
+                        sampler.unwrap_or(&metallic_roughness_texture.sampler),
+                    ),
+                },
+            ]
+            .into_iter()
+            .chain(joint_entry)
+            .collect::<Vec<_>>(),
+        })
+    }
+
+    /// The diffuse texture and its sampler at bindings 0 and 1, the normal
+    /// map and its sampler at bindings 2 and 3, the metallic-roughness map
+    /// and its sampler at bindings 4 and 5.
+    pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        Self::create_layout(device, "material_bind_group_layout", None)
+    }
+
+    /// Like `create_bind_group_layout`, with the joint matrices bound as
+    /// `binding` says at binding 6.
+    pub fn create_skinned_bind_group_layout(
+        device: &wgpu::Device,
+        binding: JointBinding,
+    ) -> wgpu::BindGroupLayout {
+        Self::create_layout(
+            device,
+            "skinned_material_bind_group_layout",
+            Some(binding.layout_entry(6)),
+        )
+    }
+
+    fn create_layout(
+        device: &wgpu::Device,
+        label: &str,
+        joint_entry: Option<wgpu::BindGroupLayoutEntry>,
+    ) -> wgpu::BindGroupLayout {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let sampler_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &[
+                texture_entry(0),
+                sampler_entry(1),
+                texture_entry(2),
+                sampler_entry(3),
+                texture_entry(4),
+                sampler_entry(5),
+            ]
+            .into_iter()
+            .chain(joint_entry)
+            .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Which texture of a material, see `Material::set_texture`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureSlot {
+    Diffuse,
+    Normal,
+}
+
+impl TextureSlot {
+    /// Colors are stored as sRGB, normal maps aren't.
+    pub fn format(self) -> wgpu::TextureFormat {
+        match self {
+            Self::Diffuse => wgpu::TextureFormat::Rgba8UnormSrgb,
+            Self::Normal => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+
+    /// What's drawn while the texture is loading.
+    pub fn placeholder(self, device: &wgpu::Device, queue: &wgpu::Queue) -> Texture {
+        match self {
+            Self::Diffuse => Texture::placeholder(device, queue),
+            Self::Normal => Texture::flat_normal(device, queue),
+        }
+    }
+
+    /// What's drawn when the texture failed to load. Magenta for colors, so
+    /// it's easy to spot.
+    pub fn fallback(self, device: &wgpu::Device, queue: &wgpu::Queue) -> Texture {
+        match self {
+            Self::Diffuse => Texture::missing(device, queue),
+            Self::Normal => Texture::flat_normal(device, queue),
+        }
+    }
+}
+
+/// The layouts of the materials' bind groups, static and skinned.
+pub struct MaterialLayouts {
+    /// From `Material::create_bind_group_layout`.
+    pub material: wgpu::BindGroupLayout,
+    /// From `Material::create_skinned_bind_group_layout`.
+    pub skinned: wgpu::BindGroupLayout,
+    pub joint_binding: JointBinding,
+}
+
+impl MaterialLayouts {
+    pub fn new(device: &wgpu::Device, joint_binding: JointBinding) -> Self {
+        Self {
+            material: Material::create_bind_group_layout(device),
+            skinned: Material::create_skinned_bind_group_layout(device, joint_binding),
+            joint_binding,
+        }
+    }
+}
+
+pub struct Mesh {
+    pub name: String,
+    pub vertex_buffer: VertexBuffer<ModelVertex>,
+    /// `None` when the mesh has no indices, it is then drawn with `draw`.
+    pub index_buffer: Option<IndexBuffer<u32>>,
+    pub material: usize,
+    /// The `SkinVertex`es of skinned models' meshes, drawn from vertex
+    /// buffer slot 2.
+    pub skin_buffer: Option<VertexBuffer<SkinVertex>>,
+    /// Around the vertices, in model space. `None` for empty and skinned
+    /// meshes, whose vertices move away from where they were loaded, so
+    /// they never get culled.
+    pub bounds: Option<Aabb>,
+}
+
+impl Mesh {
+    /// Uploads generated vertices and indices, drawn with `material` of the
+    /// model the mesh goes into.
+    pub fn from_geometry(
+        device: &wgpu::Device,
+        name: &str,
+        geometry: &Geometry,
+        material: usize,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            vertex_buffer: VertexBuffer::new(
+                device,
+                &format!("{name} Vertex Buffer"),
+                &geometry.vertices,
+            ),
+            index_buffer: IndexBuffer::new(
+                device,
+                &format!("{name} Index Buffer"),
+                &geometry.indices,
+            ),
+            material,
+            skin_buffer: None,
+            bounds: vertex_bounds(&geometry.vertices),
+        }
+    }
+
+    /// Binds the vertices to slot 0 and the indices, and draws `instances`
+    /// of them. The skin and instance buffers are up to the caller.
+    pub fn draw<'a>(
+        &'a self,
+        encoder: &mut impl wgpu::util::RenderEncoder<'a>,
+        instances: Range<u32>,
+    ) {
+        self.vertex_buffer.bind(encoder, 0);
+        match &self.index_buffer {
+            Some(index_buffer) => {
+                index_buffer.bind(encoder);
+                encoder.draw_indexed(index_buffer.draw_range(), 0, instances);
+            }
+            None => encoder.draw(self.vertex_buffer.draw_range(), instances),
+        }
+    }
+
+    /// The bytes of its vertex, index and skin buffers.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.vertex_buffer.allocated_bytes()
+            + self
+                .index_buffer
+                .as_ref()
+                .map_or(0, IndexBuffer::allocated_bytes)
+            + self
+                .skin_buffer
+                .as_ref()
+                .map_or(0, VertexBuffer::allocated_bytes)
+    }
+}
+
+/// An OBJ file and its MTL library, parsed but not uploaded yet, see
+/// `Model::read_obj`. It can be read on another thread.
+#[derive(Debug, Clone)]
+pub struct ObjData {
+    path: PathBuf,
+    models: Vec<tobj::Model>,
+    materials: Vec<tobj::Material>,
+}
+
+impl ObjData {
+    /// The texture files the materials refer to, relative to the working
+    /// directory like the OBJ file.
+    pub fn texture_paths(&self) -> impl Iterator<Item = (TextureSlot, PathBuf)> + '_ {
+        let base_dir = obj_base_dir(&self.path);
+        self.materials.iter().flat_map(move |m| {
+            let diffuse = m
+                .diffuse_texture
+                .as_ref()
+                .map(|file_name| (TextureSlot::Diffuse, base_dir.join(file_name)));
+            let normal = m
+                .normal_texture
+                .as_ref()
+                .map(|file_name| (TextureSlot::Normal, base_dir.join(file_name)));
+            diffuse.into_iter().chain(normal)
+        })
+    }
+}
+
+fn obj_base_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or_else(|| Path::new(""))
+}
+
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    /// `None` for models without skins, which are drawn with the static
+    /// pipeline.
+    pub skinning: Option<Skinning>,
+    /// The textures of `load_obj_async` that aren't there yet.
+    pending_textures: Vec<PendingTexture>,
+}
+
+/// A texture being loaded by an `AssetLoader`, the material has a
+/// placeholder in its slot until then.
+struct PendingTexture {
+    handle: Handle<image::DynamicImage>,
+    material: usize,
+    slot: TextureSlot,
+    path: PathBuf,
+}
+
+impl Model {
+    /// What the mesh buffers count towards `gpu_memory::allocated`.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.meshes.iter().map(Mesh::allocated_bytes).sum()
+    }
+
+    /// A model of one generated mesh with a checkerboard material, for scenes
+    /// without model files.
+    pub fn from_geometry(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        name: &str,
+        geometry: &Geometry,
+    ) -> Self {
+        let material = Material::new(
+            device,
+            name,
+            Texture::uv_checker(device, queue),
+            Texture::flat_normal(device, queue),
+            Texture::smooth_dielectric(device, queue),
+            layout,
+        );
+        Self {
+            meshes: vec![Mesh::from_geometry(device, name, geometry, 0)],
+            materials: vec![material],
+            skinning: None,
+            pending_textures: Vec::new(),
+        }
+    }
+
+    /// Loads an OBJ file and its MTL library from `source`. The MTL and
+    /// texture paths are resolved relative to the OBJ file. Textures already
+    /// in `assets` aren't loaded again, the others are added to it.
+    ///
+    /// Materials whose diffuse texture is missing or can't be decoded get
+    /// `Texture::uv_checker` instead, and a flat normal map when they don't
+    /// have a valid one. Meshes without a material use a default one with the
+    /// checkerboard.
+    pub fn load_obj(
+        source: &dyn AssetSource,
+        path: impl AsRef<Path>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        assets: &mut Assets,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_obj(
+            source,
+            path.as_ref(),
+            device,
+            queue,
+            layout,
+            |_, slot, path| load_texture(assets, source, path, slot.format(), device, queue),
+        )
+    }
+
+    /// Like `load_obj`, but the textures that aren't in `assets` yet are
+    /// loaded by `loader` while the materials draw with placeholders. Pass
+    /// what `loader` finished to `receive_textures` to swap them in.
+    /// Compressed textures are still loaded right away. `loader` should read
+    /// from the same source.
+    pub fn load_obj_async(
+        source: &dyn AssetSource,
+        path: impl AsRef<Path>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        loader: &mut AssetLoader,
+        assets: &mut Assets,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut pending = Vec::new();
+        let mut model = Self::from_obj(
+            source,
+            path.as_ref(),
+            device,
+            queue,
+            layout,
+            |material, slot, path| {
+                let compressed = matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("ktx2" | "dds")
+                );
+                if compressed || assets.find(path, slot.format()).is_some() {
+                    return load_texture(assets, source, path, slot.format(), device, queue);
+                }
+                let handle = match loader.load_image(path) {
+                    Ok(handle) => handle,
+                    Err(err) => {
+                        log::warn!("{err}, loading {} right away", path.display());
+                        return load_texture(assets, source, path, slot.format(), device, queue);
+                    }
+                };
+                pending.push(PendingTexture {
+                    handle,
+                    material,
+                    slot,
+                    path: path.to_owned(),
+                });
+                Some(Arc::new(slot.placeholder(device, queue)))
+            },
+        )?;
+        model.pending_textures = pending;
+        Ok(model)
+    }
+
+    /// Reads and parses the OBJ file at `path` in `source` and its MTL
+    /// library, without touching the GPU. `from_obj_data` uploads it.
+    pub fn read_obj(
+        source: &dyn AssetSource,
+        path: impl AsRef<Path>,
+    ) -> Result<ObjData, Box<dyn Error>> {
+        let path = path.as_ref();
+        let base_dir = obj_base_dir(path);
+
+        let obj = source.read(path)?;
+        let (models, obj_materials) = tobj::load_obj_buf(
+            &mut obj.as_slice(),
+            &tobj::LoadOptions {
+                triangulate: true,
+                single_index: true,
+                ..Default::default()
+            },
+            |mtl_path| {
+                let mtl = source.read(&base_dir.join(mtl_path)).map_err(|err| {
+                    log::warn!("Failed to read {}: {err}", mtl_path.display());
+                    tobj::LoadError::OpenFileFailed
+                })?;
+                tobj::load_mtl_buf(&mut mtl.as_slice())
+            },
+        )?;
+        let obj_materials = obj_materials.unwrap_or_else(|err| {
+            log::warn!("Failed to load the materials of {}: {err}", path.display());
+            Vec::new()
+        });
+        Ok(ObjData {
+            path: path.to_owned(),
+            models,
+            materials: obj_materials,
+        })
+    }
+
+    /// Uploads a parsed OBJ file. The textures of the materials come from
+    /// `images` when they're in it, e.g. because they were decoded with the
+    /// OBJ on another thread, and are loaded from the files otherwise.
+    pub fn from_obj_data(
+        data: ObjData,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        assets: &mut Assets,
+        images: &HashMap<PathBuf, image::DynamicImage>,
+    ) -> Self {
+        let source = FsSource::new("");
+        Self::build_obj(data, device, queue, layout, |_, slot, path| {
+            match images.get(path) {
+                Some(image) => {
+                    let handle = assets.insert_image(device, queue, path, slot.format(), image);
+                    assets.shared(handle)
+                }
+                None => load_texture(assets, &source, path, slot.format(), device, queue),
+            }
+        })
+    }
+
+    /// Loads the OBJ, with `load_texture` giving the textures of each
+    /// material, `None` when they can't be loaded.
+    fn from_obj(
+        source: &dyn AssetSource,
+        path: &Path,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        load_texture: impl FnMut(usize, TextureSlot, &Path) -> Option<Arc<Texture>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let data = Self::read_obj(source, path)?;
+        Ok(Self::build_obj(data, device, queue, layout, load_texture))
+    }
+
+    fn build_obj(
+        data: ObjData,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        mut load_texture: impl FnMut(usize, TextureSlot, &Path) -> Option<Arc<Texture>>,
+    ) -> Self {
+        let ObjData {
+            path,
+            models,
+            materials: obj_materials,
+        } = data;
+        let base_dir = obj_base_dir(&path);
+
+        let mut materials = obj_materials
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let diffuse_texture = m
+                    .diffuse_texture
+                    .as_ref()
+                    .and_then(|file_name| {
+                        load_texture(i, TextureSlot::Diffuse, &base_dir.join(file_name))
+                    })
+                    .unwrap_or_else(|| Arc::new(Texture::uv_checker(device, queue)));
+                let normal_texture = m
+                    .normal_texture
+                    .as_ref()
+                    .and_then(|file_name| {
+                        load_texture(i, TextureSlot::Normal, &base_dir.join(file_name))
+                    })
+                    .unwrap_or_else(|| Arc::new(Texture::flat_normal(device, queue)));
+                Material::new(
+                    device,
+                    &m.name,
+                    diffuse_texture,
+                    normal_texture,
+                    Texture::smooth_dielectric(device, queue),
+                    layout,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Meshes without a (valid) material point at this one.
+        let default_material = materials.len();
+        if models
+            .iter()
+            .any(|m| m.mesh.material_id.is_none_or(|id| id >= default_material))
+        {
+            materials.push(Material::new(
+                device,
+                "default",
+                Texture::uv_checker(device, queue),
+                Texture::flat_normal(device, queue),
+                Texture::smooth_dielectric(device, queue),
+                layout,
+            ));
+        }
+
+        let meshes = models
+            .into_iter()
+            .map(|m| {
+                let mut vertices = (0..m.mesh.positions.len() / 3)
+                    .map(|i| ModelVertex {
+                        position: [
+                            m.mesh.positions[i * 3],
+                            m.mesh.positions[i * 3 + 1],
+                            m.mesh.positions[i * 3 + 2],
+                        ],
+                        // OBJ has the origin of the texture coordinates at the
+                        // bottom left, wgpu at the top left.
+                        tex_coords: if m.mesh.texcoords.is_empty() {
+                            [0.0, 0.0]
+                        } else {
+                            [m.mesh.texcoords[i * 2], 1.0 - m.mesh.texcoords[i * 2 + 1]]
+                        },
+                        normal: if m.mesh.normals.is_empty() {
+                            [0.0, 0.0, 0.0]
+                        } else {
+                            [
+                                m.mesh.normals[i * 3],
+                                m.mesh.normals[i * 3 + 1],
+                                m.mesh.normals[i * 3 + 2],
+                            ]
+                        },
+                        // Filled in by compute_tangents below
+                        tangent: [0.0; 3],
+                        bitangent: [0.0; 3],
+                    })
+                    .collect::<Vec<_>>();
+                compute_tangents(&mut vertices, &m.mesh.indices);
+
+                let vertex_buffer =
+                    VertexBuffer::new(device, &format!("{} Vertex Buffer", m.name), &vertices);
+                let index_buffer =
+                    IndexBuffer::new(device, &format!("{} Index Buffer", m.name), &m.mesh.indices);
+
+                Mesh {
+                    bounds: vertex_bounds(&vertices),
+                    name: m.name,
+                    vertex_buffer,
+                    index_buffer,
+                    material: m
+                        .mesh
+                        .material_id
+                        .filter(|&id| id < default_material)
+                        .unwrap_or(default_material),
+                    skin_buffer: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            meshes,
+            materials,
+            skinning: None,
+            pending_textures: Vec::new(),
+        }
+    }
+
+    /// Loads a glTF 2.0 model from `source`, a `.gltf` file or a `.glb` one.
+    /// External buffers and images are resolved relative to it.
+    ///
+    /// Each primitive becomes a mesh, with its node's transform already
+    /// applied to the vertices. Like with `load_obj`, missing textures get
+    /// the defaults and primitives without a material use a checkerboard.
+    ///
+    /// Models with skins get `skinning` and skinned materials, and fail to
+    /// load when they have more joints than `layouts.joint_binding` allows.
+    ///
+    /// The textures go into `assets` under the path of the model, with the
+    /// index of the material and the texture's name after a `#`.
+    pub fn load_gltf(
+        source: &dyn AssetSource,
+        path: impl AsRef<Path>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &MaterialLayouts,
+        assets: &mut Assets,
+    ) -> Result<Self, GltfError> {
+        let path = path.as_ref();
+        let document = gltf::read_from(source, path)?;
+        Self::from_gltf(document, path, device, queue, layouts, assets)
+    }
+
+    /// Uploads a glTF document read from `path`, e.g. on another thread.
+    /// `path` only names its textures in `assets`.
+    pub fn from_gltf(
+        document: gltf::Document,
+        path: impl AsRef<Path>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &MaterialLayouts,
+        assets: &mut Assets,
+    ) -> Result<Self, GltfError> {
+        let path = path.as_ref();
+        let skinning = document
+            .skeleton
+            .map(|skeleton| Skinning::new(device, Animator::new(skeleton), layouts.joint_binding))
+            .transpose()?;
+        if let Some(skinning) = &skinning {
+            skinning.write(queue);
+        }
+        let create_material =
+            |name: &str,
+             diffuse: Arc<Texture>,
+             normal: Arc<Texture>,
+             metallic_roughness: Arc<Texture>| match &skinning {
+                Some(skinning) => Material::new_skinned(
+                    device,
+                    name,
+                    diffuse,
+                    normal,
+                    metallic_roughness,
+                    &layouts.skinned,
+                    skinning.joint_buffer(),
+                ),
+                None => Material::new(
+                    device,
+                    name,
+                    diffuse,
+                    normal,
+                    metallic_roughness,
+                    &layouts.material,
+                ),
+            };
+
+        // The factors are already multiplied into the images, so they are
+        // the material's own. Loading the model again still reuses them.
+        let mut upload = |image: Option<image::RgbaImage>, key: String, format| {
+            image.and_then(|image| {
+                let image = image::DynamicImage::ImageRgba8(image);
+                let key = format!("{}#{key}", path.display());
+                let handle = assets.insert_image(device, queue, key, format, &image);
+                assets.shared(handle)
+            })
+        };
+        let mut materials = document
+            .materials
+            .into_iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let diffuse_texture = upload(
+                    m.base_color,
+                    format!("{i} base color"),
+                    wgpu::TextureFormat::Rgba8UnormSrgb,
+                )
+                .unwrap_or_else(|| Arc::new(Texture::uv_checker(device, queue)));
+                let normal_texture = upload(
+                    m.normal,
+                    format!("{i} normal"),
+                    wgpu::TextureFormat::Rgba8Unorm,
+                )
+                .unwrap_or_else(|| Arc::new(Texture::flat_normal(device, queue)));
+                let metallic_roughness_texture = upload(
+                    m.metallic_roughness,
+                    format!("{i} metallic-roughness"),
+                    wgpu::TextureFormat::Rgba8Unorm,
+                )
+                .unwrap_or_else(|| Arc::new(Texture::smooth_dielectric(device, queue)));
+                create_material(
+                    &m.name,
+                    diffuse_texture,
+                    normal_texture,
+                    metallic_roughness_texture,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let default_material = materials.len();
+        if document.primitives.iter().any(|p| p.material.is_none()) {
+            materials.push(create_material(
+                "default",
+                Arc::new(Texture::uv_checker(device, queue)),
+                Arc::new(Texture::flat_normal(device, queue)),
+                Arc::new(Texture::smooth_dielectric(device, queue)),
+            ));
+        }
+
+        let meshes = document
+            .primitives
+            .into_iter()
+            .map(|p| {
+                let vertex_buffer =
+                    VertexBuffer::new(device, &format!("{} Vertex Buffer", p.name), &p.vertices);
+                Mesh {
+                    bounds: if p.skin_vertices.is_empty() {
+                        vertex_bounds(&p.vertices)
+                    } else {
+                        None
+                    },
+                    vertex_buffer,
+                    index_buffer: IndexBuffer::new(
+                        device,
+                        &format!("{} Index Buffer", p.name),
+                        &p.indices,
+                    ),
+                    material: p.material.unwrap_or(default_material),
+                    skin_buffer: (!p.skin_vertices.is_empty()).then(|| {
+                        VertexBuffer::new(
+                            device,
+                            &format!("{} Skin Buffer", p.name),
+                            &p.skin_vertices,
+                        )
+                    }),
+                    name: p.name,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            meshes,
+            materials,
+            skinning,
+            pending_textures: Vec::new(),
+        })
+    }
+
+    /// Uploads the textures in `finished` the model was waiting for into
+    /// `assets`, the ones that failed to load get `TextureSlot::fallback`.
+    /// `layout` is the one the model was loaded with. Returns whether any
+    /// textures changed.
+    pub fn receive_textures(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        assets: &mut Assets,
+        finished: &[LoadedImage],
+    ) -> bool {
+        let mut received = false;
+        self.pending_textures.retain(|pending| {
+            let Some((_, image)) = finished
+                .iter()
+                .find(|(handle, _)| *handle == pending.handle)
+            else {
+                return true;
+            };
+            let texture = match image {
+                // Another model may have been waiting for the same file
+                Ok(img) => {
+                    let format = pending.slot.format();
+                    let handle = assets.insert_image(device, queue, &pending.path, format, img);
+                    assets.shared(handle)
+                }
+                Err(err) => {
+                    log::warn!("Failed to load texture, drawing it magenta: {err}");
+                    None
+                }
+            }
+            .unwrap_or_else(|| Arc::new(pending.slot.fallback(device, queue)));
+            self.materials[pending.material].set_texture(device, layout, pending.slot, texture);
+            received = true;
+            false
+        });
+        received
+    }
+
+    /// Swaps a texture of the material at `index`, keeping the joint
+    /// matrices bound if the model is skinned. Does nothing for materials it
+    /// doesn't have.
+    pub fn set_texture(
+        &mut self,
+        device: &wgpu::Device,
+        layouts: &MaterialLayouts,
+        index: usize,
+        slot: TextureSlot,
+        texture: impl Into<Arc<Texture>>,
+    ) {
+        let Some(material) = self.materials.get_mut(index) else {
+            return;
+        };
+        match &self.skinning {
+            Some(skinning) => material.replace_texture(
+                device,
+                &layouts.skinned,
+                slot,
+                texture.into(),
+                Some(skinning.joint_buffer()),
+            ),
+            None => material.replace_texture(device, &layouts.material, slot, texture.into(), None),
+        }
+    }
+
+    /// Samples the textures of the material at `index` with `sampler`, see
+    /// `Material::set_sampler`. Keeps the joint matrices of skinned models.
+    pub fn set_sampler(
+        &mut self,
+        device: &wgpu::Device,
+        layouts: &MaterialLayouts,
+        index: usize,
+        sampler: Option<Arc<wgpu::Sampler>>,
+    ) {
+        let Some(material) = self.materials.get_mut(index) else {
+            return;
+        };
+        match &self.skinning {
+            Some(skinning) => material.replace_sampler(
+                device,
+                &layouts.skinned,
+                sampler,
+                Some(skinning.joint_buffer()),
+            ),
+            None => material.replace_sampler(device, &layouts.material, sampler, None),
+        }
+    }
+
+    /// Whether some textures of `load_obj_async` are still placeholders.
+    pub fn is_loading(&self) -> bool {
+        !self.pending_textures.is_empty()
+    }
+
+    /// Whether it has to be drawn with the skinned pipeline.
+    pub fn is_skinned(&self) -> bool {
+        self.skinning.is_some()
+    }
+
+    /// Starts the animation `name`, switching straight from the one that's
+    /// playing.
+    pub fn play(&mut self, name: &str, looping: bool) -> Result<(), AnimationError> {
+        self.skinning
+            .as_mut()
+            .ok_or(AnimationError::NotSkinned)?
+            .animator
+            .play(name, looping)
+    }
+
+    /// Moves the animation forward by `dt` and uploads the joint matrices.
+    /// Does nothing for models without skins.
+    pub fn update_animation(&mut self, queue: &wgpu::Queue, dt: Duration) {
+        if let Some(skinning) = &mut self.skinning {
+            if skinning.animator.playing().is_some() {
+                skinning.animator.update(dt);
+                skinning.write(queue);
+            }
+        }
+    }
+}
+
+/// Computes per-vertex tangents and bitangents from the triangles' UVs.
+///
+/// Each vertex gets the average of the tangents of the triangles using it.
+/// Triangles with zero UV area don't define a tangent and are skipped, and
+/// vertices left without one get an arbitrary tangent perpendicular to
+/// their normal, so no NaN ends up in the vertex buffer.
+pub fn compute_tangents(vertices: &mut [ModelVertex], indices: &[u32]) {
+    let mut tangents = vec![Vec3::ZERO; vertices.len()];
+    let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [i0, i1, i2] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let (v0, v1, v2) = (vertices[i0], vertices[i1], vertices[i2]);
+
+        let pos0 = Vec3::from(v0.position);
+        let delta_pos1 = Vec3::from(v1.position) - pos0;
+        let delta_pos2 = Vec3::from(v2.position) - pos0;
+
+        let uv0 = Vec2::from(v0.tex_coords);
+        let delta_uv1 = Vec2::from(v1.tex_coords) - uv0;
+        let delta_uv2 = Vec2::from(v2.tex_coords) - uv0;
+
+        let denominator = delta_uv1.x * delta_uv2.y - delta_uv1.y * delta_uv2.x;
+        if denominator.abs() < f32::EPSILON {
+            continue;
+        }
+        let r = 1.0 / denominator;
+        let tangent = (delta_pos1 * delta_uv2.y - delta_pos2 * delta_uv1.y) * r;
+        // The texture coordinates were flipped on load, so the bitangent has
+        // to be flipped as well to keep the normal maps' green channel up.
+        let bitangent = (delta_pos2 * delta_uv1.x - delta_pos1 * delta_uv2.x) * -r;
+
+        for i in [i0, i1, i2] {
+            tangents[i] += tangent;
+            bitangents[i] += bitangent;
+        }
+    }
+
+    for ((vertex, tangent), bitangent) in vertices.iter_mut().zip(tangents).zip(bitangents) {
+        let normal = Vec3::from(vertex.normal).try_normalize().unwrap_or(Vec3::Z);
+        // Gram-Schmidt: make the tangent perpendicular to the normal again
+        // after averaging.
+        let tangent = (tangent - normal * normal.dot(tangent))
+            .try_normalize()
+            .unwrap_or_else(|| normal.any_orthonormal_vector());
+        let mut bitangent_out = normal.cross(tangent);
+        if bitangent.dot(bitangent_out) < 0.0 {
+            bitangent_out = -bitangent_out;
+        }
+
+        vertex.tangent = tangent.into();
+        vertex.bitangent = bitangent_out.into();
+    }
+}
+
+fn vertex_bounds(vertices: &[ModelVertex]) -> Option<Aabb> {
+    Aabb::from_points(vertices.iter().map(|vertex| Vec3::from(vertex.position)))
+}
+
+/// The texture at `path` from `assets`, loading it if it isn't there yet.
+fn load_texture(
+    assets: &mut Assets,
+    source: &dyn AssetSource,
+    path: &Path,
+    format: wgpu::TextureFormat,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> Option<Arc<Texture>> {
+    match assets.load_texture(device, queue, source, path, format) {
+        Ok(handle) => assets.shared(handle),
+        Err(err) => {
+            log::warn!("Failed to load texture, using a placeholder: {err}");
+            None
+        }
+    }
+}
+
+pub trait DrawModel<'a> {
+    fn draw_mesh(
+        &mut self,
+        mesh: &'a Mesh,
+        material: &'a Material,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
+    fn draw_mesh_instanced(
+        &mut self,
+        mesh: &'a Mesh,
+        material: &'a Material,
+        instances: Range<u32>,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
+
+    fn draw_model(
+        &mut self,
+        model: &'a Model,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
+    fn draw_model_instanced(
+        &mut self,
+        model: &'a Model,
+        instances: Range<u32>,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
+    /// Draws each mesh with its own range of instances, see
+    /// `culling::Culled`.
+    fn draw_model_culled(
+        &mut self,
+        model: &'a Model,
+        ranges: &[Range<u32>],
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
+}
+
+// Render passes and render bundles alike
+impl<'a, T> DrawModel<'a> for T
+where
+    T: wgpu::util::RenderEncoder<'a>,
+{
+    fn draw_mesh(
+        &mut self,
+        mesh: &'a Mesh,
+        material: &'a Material,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    ) {
+        self.draw_mesh_instanced(mesh, material, 0..1, camera, light_bind_group);
+    }
+
+    fn draw_mesh_instanced(
+        &mut self,
+        mesh: &'a Mesh,
+        material: &'a Material,
+        instances: Range<u32>,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    ) {
+        if let Some(skin_buffer) = &mesh.skin_buffer {
+            skin_buffer.bind(self, 2);
+        }
+        camera.bind(self, 0);
+        self.set_bind_group(1, &material.bind_group, &[]);
+        self.set_bind_group(2, light_bind_group, &[]);
+        mesh.draw(self, instances);
+    }
+
+    fn draw_model(
+        &mut self,
+        model: &'a Model,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    ) {
+        self.draw_model_instanced(model, 0..1, camera, light_bind_group);
+    }
+
+    fn draw_model_instanced(
+        &mut self,
+        model: &'a Model,
+        instances: Range<u32>,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    ) {
+        for mesh in &model.meshes {
+            let material = &model.materials[mesh.material];
+            self.draw_mesh_instanced(mesh, material, instances.clone(), camera, light_bind_group);
+        }
+    }
+
+    fn draw_model_culled(
+        &mut self,
+        model: &'a Model,
+        ranges: &[Range<u32>],
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    ) {
+        // Meshes culled for every instance cost nothing at all
+        for (mesh, instances) in model.meshes.iter().zip(ranges) {
+            if instances.is_empty() {
+                continue;
+            }
+            let material = &model.materials[mesh.material];
+            self.draw_mesh_instanced(mesh, material, instances.clone(), camera, light_bind_group);
+        }
+    }
+}
+
+/// Draws a model with the light pipeline, which only needs the geometry.
+/// Instance `i` is drawn at light `i`.
+pub trait DrawLight<'a> {
+    fn draw_light_mesh(
+        &mut self,
+        mesh: &'a Mesh,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
+    fn draw_light_mesh_instanced(
+        &mut self,
+        mesh: &'a Mesh,
+        instances: Range<u32>,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
+
+    fn draw_light_model(
+        &mut self,
+        model: &'a Model,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
+    fn draw_light_model_instanced(
+        &mut self,
+        model: &'a Model,
+        instances: Range<u32>,
+        camera: CameraBinding<'a>,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
+}
+
+impl<'a, 'b> DrawLight<'b> for wgpu::RenderPass<'a>
+where
+    'b: 'a,
+{
+    fn draw_light_mesh(
+        &mut self,
+        mesh: &'b Mesh,
+        camera: CameraBinding<'b>,
+        light_bind_group: &'b wgpu::BindGroup,
+    ) {
+        self.draw_light_mesh_instanced(mesh, 0..1, camera, light_bind_group);
+    }
+
+    fn draw_light_mesh_instanced(
+        &mut self,
+        mesh: &'b Mesh,
+        instances: Range<u32>,
+        camera: CameraBinding<'b>,
+        light_bind_group: &'b wgpu::BindGroup,
+    ) {
+        camera.bind(self, 0);
+        self.set_bind_group(1, light_bind_group, &[]);
+        mesh.draw(self, instances);
+    }
+
+    fn draw_light_model(
+        &mut self,
+        model: &'b Model,
+        camera: CameraBinding<'b>,
+        light_bind_group: &'b wgpu::BindGroup,
+    ) {
+        self.draw_light_model_instanced(model, 0..1, camera, light_bind_group);
+    }
+
+    fn draw_light_model_instanced(
+        &mut self,
+        model: &'b Model,
+        instances: Range<u32>,
+        camera: CameraBinding<'b>,
+        light_bind_group: &'b wgpu::BindGroup,
+    ) {
+        for mesh in &model.meshes {
+            self.draw_light_mesh_instanced(mesh, instances.clone(), camera, light_bind_group);
+        }
+    }
+}
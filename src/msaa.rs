@@ -0,0 +1,50 @@
+use crate::render_graph::ResourceDesc;
+
+/// The sample counts we know how to ask for, highest first.
+pub const SAMPLE_COUNTS: [u32; 4] = [8, 4, 2, 1];
+
+/// Returns the sample counts usable for render targets of all of `formats`.
+///
+/// Without `Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES` only the
+/// counts WebGPU guarantees (1 and 4) may be used, whatever the adapter
+/// supports.
+pub fn supported_sample_counts(
+    adapter: &wgpu::Adapter,
+    device_features: wgpu::Features,
+    formats: &[wgpu::TextureFormat],
+) -> Vec<u32> {
+    SAMPLE_COUNTS
+        .into_iter()
+        .filter(|&count| {
+            formats.iter().all(|&format| {
+                let flags = if device_features
+                    .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+                {
+                    adapter.get_texture_format_features(format).flags
+                } else {
+                    format.guaranteed_format_features(device_features).flags
+                };
+                count == 1 || flags.sample_count_supported(count)
+            })
+        })
+        .collect()
+}
+
+/// Picks `requested` if it is supported, or else the highest supported count
+/// below it. A sample count of 1 is always supported.
+pub fn select_sample_count(requested: u32, supported: &[u32]) -> u32 {
+    supported
+        .iter()
+        .copied()
+        .filter(|&count| count <= requested)
+        .max()
+        .unwrap_or(1)
+}
+
+/// The multisampled color target the scene is rendered into before being
+/// resolved into a `format` texture the size of the frame, the
+/// `render_graph::MSAA` resource.
+pub fn target_desc(format: wgpu::TextureFormat, sample_count: u32) -> ResourceDesc {
+    ResourceDesc::frame(format, wgpu::TextureUsages::RENDER_ATTACHMENT)
+        .with_sample_count(sample_count)
+}
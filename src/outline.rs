@@ -0,0 +1,222 @@
+//! An outline around the selected instance, drawn with the stencil buffer.
+//! The instance is drawn once more as it is, only marking its pixels in the
+//! stencil buffer, and then a little bigger with a flat color wherever it
+//! isn't marked. What's left is the rim the bigger one sticks out by.
+//!
+//! Both draws skip the depth test, so the outline shows through whatever is
+//! in front of the instance. Skinned models are outlined in their bind pose.
+
+use std::{mem::offset_of, ops::Range};
+
+use crate::{
+    camera::CameraBinding,
+    instance::InstanceRaw,
+    model::{Model, ModelVertex},
+    pipeline::{PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+    uniform::{Uniform, UniformBuffer, UniformField, WgslType},
+};
+
+pub const OUTLINE_SHADER_SOURCE: &str = include_str!("../shaders/outline.wgsl");
+
+/// What the outlined instance leaves in the stencil buffer. The scene's
+/// render pass clears it to 0.
+pub const STENCIL_MARK: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlineSettings {
+    /// Linear, in the HDR range like the scene.
+    pub color: [f32; 4],
+    /// How much bigger the outline is than the instance, as a fraction of
+    /// its size.
+    pub thickness: f32,
+}
+
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 0.55, 0.1, 1.0],
+            thickness: 0.06,
+        }
+    }
+}
+
+impl OutlineSettings {
+    pub fn uniform(&self) -> OutlineUniform {
+        OutlineUniform {
+            color: self.color,
+            thickness: self.thickness,
+            _padding: [0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OutlineUniform {
+    pub color: [f32; 4],
+    pub thickness: f32,
+    _padding: [u32; 3],
+}
+
+impl Uniform for OutlineUniform {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new("color", offset_of!(OutlineUniform, color), WgslType::Vec4),
+        UniformField::new(
+            "thickness",
+            offset_of!(OutlineUniform, thickness),
+            WgslType::F32,
+        ),
+    ];
+}
+
+/// The two pipelines of an outline, see `Outline::create_pipelines`.
+pub struct OutlinePipelines {
+    mask: wgpu::RenderPipeline,
+    outline: wgpu::RenderPipeline,
+}
+
+/// Draws the outline of one instance into the scene's render pass.
+pub struct Outline {
+    settings: OutlineSettings,
+    settings_buffer: UniformBuffer<OutlineUniform>,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipelines: OutlinePipelines,
+}
+
+impl Outline {
+    /// `targets` need a depth format with a stencil aspect.
+    /// `camera_bind_group_layout` is the one shared with the scene's
+    /// pipelines.
+    pub fn new(
+        device: &wgpu::Device,
+        targets: RenderTargets,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<Self, ShaderError> {
+        let settings = OutlineSettings::default();
+        let settings_buffer =
+            UniformBuffer::new(device, "Outline Settings Buffer", &settings.uniform());
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("outline_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("outline_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.binding(),
+            }],
+        });
+        let pipelines = create_pipelines(device, targets, camera_bind_group_layout, &layout)?;
+        Ok(Self {
+            settings,
+            settings_buffer,
+            layout,
+            bind_group,
+            pipelines,
+        })
+    }
+
+    /// Builds the pipelines for new render targets without using them yet,
+    /// see `set_pipelines`.
+    pub fn create_pipelines(
+        &self,
+        device: &wgpu::Device,
+        targets: RenderTargets,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<OutlinePipelines, ShaderError> {
+        create_pipelines(device, targets, camera_bind_group_layout, &self.layout)
+    }
+
+    pub fn set_pipelines(&mut self, pipelines: OutlinePipelines) {
+        self.pipelines = pipelines;
+    }
+
+    pub fn settings(&self) -> OutlineSettings {
+        self.settings
+    }
+
+    /// Shows up from the next submit on.
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: OutlineSettings) {
+        self.settings = settings;
+        self.settings_buffer.write(queue, &settings.uniform());
+    }
+
+    /// Outlines `instances` of `model`, drawn from `instance_buffer`. Draw
+    /// it after everything the outline goes over.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera: CameraBinding<'a>,
+        model: &'a Model,
+        instance_buffer: &'a wgpu::Buffer,
+        instances: Range<u32>,
+    ) {
+        render_pass.set_stencil_reference(STENCIL_MARK);
+        camera.bind(render_pass, 0);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        for pipeline in [&self.pipelines.mask, &self.pipelines.outline] {
+            render_pass.set_pipeline(pipeline);
+            for mesh in &model.meshes {
+                mesh.draw(render_pass, instances.clone());
+            }
+        }
+    }
+}
+
+fn create_pipelines(
+    device: &wgpu::Device,
+    targets: RenderTargets,
+    camera_bind_group_layout: &wgpu::BindGroupLayout,
+    outline_bind_group_layout: &wgpu::BindGroupLayout,
+) -> Result<OutlinePipelines, ShaderError> {
+    let stencil = |face| wgpu::StencilState {
+        front: face,
+        back: face,
+        read_mask: !0,
+        write_mask: !0,
+    };
+    let layouts = [camera_bind_group_layout, outline_bind_group_layout];
+    let outline = PipelineBuilder::with_targets("Outline Pipeline", targets)
+        .shader("outline.wgsl", OUTLINE_SHADER_SOURCE)
+        .bind_group_layouts(&layouts)
+        .vertex_buffer(ModelVertex::desc())
+        .vertex_buffer(InstanceRaw::desc())
+        .depth_test(wgpu::CompareFunction::Always, false);
+
+    // Marks every pixel of the instance, hidden or not, without drawing it
+    let mask = outline
+        .clone()
+        .label("Outline Mask Pipeline")
+        .vertex_entry_point("vs_mask")
+        .color_writes(wgpu::ColorWrites::empty())
+        .stencil(stencil(wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Always,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Replace,
+        }))
+        .build(device)?;
+    // Only where the mask isn't
+    let outline = outline
+        .stencil(stencil(wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::NotEqual,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Keep,
+        }))
+        .build(device)?;
+    Ok(OutlinePipelines { mask, outline })
+}
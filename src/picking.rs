@@ -0,0 +1,385 @@
+//! Finding out what's under the cursor. The instances get drawn again with
+//! their IDs instead of colors into an integer texture, and the texel under
+//! the cursor gets copied back once the GPU is done.
+
+use std::{mem::offset_of, sync::mpsc};
+
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use crate::{
+    camera::CameraBinding,
+    instance::InstanceRaw,
+    model::{Model, ModelVertex},
+    pipeline::{PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+    split_screen::Viewport,
+    texture::Texture,
+    uniform::{DynamicUniform, Uniform, UniformField, WgslType},
+};
+
+pub const PICKING_SHADER_SOURCE: &str = include_str!("../shaders/picking.wgsl");
+
+pub const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+/// What the ID texture is cleared to, where no instance gets drawn.
+pub const BACKGROUND_ID: u32 = 0;
+
+/// How many of the low bits of an ID hold the instance index, the mesh index
+/// is in the bits above them.
+pub const INSTANCE_BITS: u32 = 16;
+
+/// Which mesh is drawn, one per mesh at its own dynamic offset.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PickMesh {
+    index: u32,
+    // Uniform buffers need 16 bytes on every backend
+    _padding: [u32; 3],
+}
+
+impl Uniform for PickMesh {
+    const FIELDS: &'static [UniformField] = &[UniformField::new(
+        "index",
+        offset_of!(PickMesh, index),
+        WgslType::U32,
+    )];
+}
+
+/// What a click hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Picked {
+    /// The index into `State::instances`.
+    pub instance: u32,
+    /// The index into the model's meshes.
+    pub mesh: u32,
+}
+
+impl Picked {
+    /// What `picking.wgsl` writes into the ID texture for it.
+    pub fn id(self) -> u32 {
+        ((self.mesh << INSTANCE_BITS) | self.instance) + 1
+    }
+
+    /// `None` for `BACKGROUND_ID`.
+    pub fn from_id(id: u32) -> Option<Self> {
+        let id = id.checked_sub(1)?;
+        Some(Self {
+            instance: id & ((1 << INSTANCE_BITS) - 1),
+            mesh: id >> INSTANCE_BITS,
+        })
+    }
+}
+
+/// The texel of a texture with `size` that `position` is on, `None` if it's
+/// outside of the texture.
+pub fn texel_at(position: PhysicalPosition<f64>, size: PhysicalSize<u32>) -> Option<(u32, u32)> {
+    let (x, y) = (position.x.floor(), position.y.floor());
+    let inside = (0.0..size.width as f64).contains(&x) && (0.0..size.height as f64).contains(&y);
+    inside.then_some((x as u32, y as u32))
+}
+
+/// A click waiting for the next frame.
+#[derive(Debug, Clone, Copy)]
+struct PickRequest {
+    position: PhysicalPosition<f64>,
+    /// The size of the window when it was clicked.
+    size: PhysicalSize<u32>,
+}
+
+/// Copies one texel of the ID texture into a buffer and reads it back.
+struct PickReadback {
+    buffer: wgpu::Buffer,
+    receiver: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+impl PickReadback {
+    fn new(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        (x, y): (u32, u32),
+    ) -> Self {
+        // Buffer rows have to be aligned to 256 bytes even when copying a
+        // single texel, which ends up at the start of the row
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Pick Readback Buffer"),
+            size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+                    rows_per_image: Some(1),
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Self {
+            buffer,
+            receiver: None,
+        }
+    }
+
+    /// Must be called after the copy was submitted.
+    fn map(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                // The receiver is gone if another click replaced this one
+                let _ = sender.send(result);
+            });
+        self.receiver = Some(receiver);
+    }
+
+    /// The ID that was under the cursor, or `None` if the GPU isn't done yet.
+    fn try_read(&self) -> Option<Result<u32, wgpu::BufferAsyncError>> {
+        let result = self.receiver.as_ref()?.try_recv().ok()?;
+        Some(result.map(|()| {
+            let id = {
+                let data = self.buffer.slice(..).get_mapped_range();
+                u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+            };
+            self.buffer.unmap();
+            id
+        }))
+    }
+}
+
+/// What the scene gets drawn with into the ID texture.
+pub struct PickScene<'a> {
+    pub model: Option<&'a Model>,
+    pub instance_buffer: &'a wgpu::Buffer,
+    pub instance_count: u32,
+    /// Each part of the frame with the camera it's drawn with.
+    pub views: Vec<(Viewport, CameraBinding<'a>)>,
+}
+
+/// Answers clicks with what's under the cursor, see `request`.
+///
+/// The IDs get their own pass rather than a second attachment of the scene's
+/// render pass, as integer textures can't be multisampled or resolved. It's
+/// only drawn in frames where somebody clicked.
+pub struct Picking {
+    pipeline: wgpu::RenderPipeline,
+    meshes: DynamicUniform<PickMesh>,
+    id_texture: wgpu::Texture,
+    id_view: wgpu::TextureView,
+    /// The scene's depth texture may be multisampled, this one never is.
+    depth_texture: Texture,
+    pending: Option<PickRequest>,
+    readback: Option<PickReadback>,
+    picked: Option<Picked>,
+}
+
+impl Picking {
+    /// `config` describes the frame to pick from.
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        camera_layout: &wgpu::BindGroupLayout,
+    ) -> Result<Self, ShaderError> {
+        let meshes = DynamicUniform::new(device, "Pick Mesh Buffer", wgpu::ShaderStages::VERTEX);
+
+        let targets = RenderTargets {
+            color_format: ID_FORMAT,
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count: 1,
+        };
+        let pipeline = PipelineBuilder::with_targets("Picking Pipeline", targets)
+            .shader("picking.wgsl", PICKING_SHADER_SOURCE)
+            .fragment_entry_point("fs_main")
+            .bind_group_layouts(&[camera_layout, meshes.layout()])
+            // Integer formats can't be blended
+            .color_target(ID_FORMAT, None)
+            .vertex_buffer(ModelVertex::desc())
+            .vertex_buffer(InstanceRaw::desc())
+            .build(device)?;
+
+        let (id_texture, id_view) = create_id_texture(device, config);
+        Ok(Self {
+            pipeline,
+            meshes,
+            id_texture,
+            id_view,
+            depth_texture: Texture::create_depth_texture(device, config, 1, "pick_depth_texture"),
+            pending: None,
+            readback: None,
+            picked: None,
+        })
+    }
+
+    /// Recreates the textures to match the new size of the frame.
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        (self.id_texture, self.id_view) = create_id_texture(device, config);
+        self.depth_texture = Texture::create_depth_texture(device, config, 1, "pick_depth_texture");
+    }
+
+    /// Picks what's at `position` in the next frame, in physical pixels from
+    /// the top left of a frame with `size`. Replaces a click that's still
+    /// waiting.
+    pub fn request(&mut self, position: PhysicalPosition<f64>, size: PhysicalSize<u32>) {
+        self.pending = Some(PickRequest { position, size });
+    }
+
+    /// Draws the IDs and starts reading back the one under the waiting click.
+    /// Clicks made before the frame was resized would pick whatever is now
+    /// where the cursor was, so they are dropped, as are clicks outside of
+    /// the frame. Both pick nothing.
+    pub fn render(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, scene: PickScene) {
+        let Some(request) = self.pending.take() else {
+            return;
+        };
+        let size = PhysicalSize::new(self.id_texture.width(), self.id_texture.height());
+        if request.size != size {
+            log::debug!(
+                "Dropped a click on a {}x{} frame after it was resized to {}x{}",
+                request.size.width,
+                request.size.height,
+                size.width,
+                size.height
+            );
+            self.picked = None;
+            return;
+        }
+        let Some(texel) = texel_at(request.position, size) else {
+            self.picked = None;
+            return;
+        };
+
+        // Each mesh's index at its own offset, there's room for any model
+        self.meshes.clear();
+        let offsets: Vec<u32> = (0..scene.model.map_or(0, |model| model.meshes.len()))
+            .map(|index| {
+                self.meshes.push(&PickMesh {
+                    index: index as u32,
+                    _padding: [0; 3],
+                })
+            })
+            .collect();
+        self.meshes.upload(device, queue);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Picking Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Picking Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.id_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: BACKGROUND_ID as f64,
+                            g: 0.0,
+                            b: 0.0,
+                            a: 0.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            if let Some(model) = scene.model {
+                render_pass.set_pipeline(&self.pipeline);
+                render_pass.set_vertex_buffer(1, scene.instance_buffer.slice(..));
+                let instances = 0..scene.instance_count;
+                for &(viewport, camera) in &scene.views {
+                    if viewport.is_empty() {
+                        continue;
+                    }
+                    viewport.apply(&mut render_pass);
+                    camera.bind(&mut render_pass, 0);
+                    for (mesh, &offset) in model.meshes.iter().zip(&offsets) {
+                        render_pass.set_bind_group(1, self.meshes.bind_group(), &[offset]);
+                        mesh.draw(&mut render_pass, instances.clone());
+                    }
+                }
+            }
+        }
+
+        let mut readback = PickReadback::new(device, &mut encoder, &self.id_texture, texel);
+        queue.submit(std::iter::once(encoder.finish()));
+        readback.map();
+        self.readback = Some(readback);
+    }
+
+    /// Takes the result of the last click if its readback is done, returns
+    /// whether it was. Call it after polling the device.
+    pub fn collect(&mut self) -> bool {
+        let Some(result) = self.readback.as_ref().and_then(PickReadback::try_read) else {
+            return false;
+        };
+        self.readback = None;
+        self.picked = match result {
+            Ok(id) => Picked::from_id(id),
+            Err(err) => {
+                log::error!("Failed to read back the picked object: {err}");
+                None
+            }
+        };
+        match self.picked {
+            Some(picked) => log::info!(
+                "Picked mesh {} of instance {}",
+                picked.mesh,
+                picked.instance
+            ),
+            None => log::info!("Picked the background"),
+        }
+        true
+    }
+
+    /// What the last click hit, `None` for the background or before the
+    /// first click was answered.
+    pub fn picked(&self) -> Option<Picked> {
+        self.picked
+    }
+}
+
+fn create_id_texture(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Pick ID Texture"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ID_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
@@ -0,0 +1,472 @@
+use std::{
+    future::Future,
+    io,
+    path::Path,
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    asset_source::AssetSource,
+    shader::{self, ShaderError},
+};
+
+/// The WGSL source of the main shader. The shaders are embedded in the
+/// binary so the examples don't depend on the current working directory.
+pub const SHADER_SOURCE: &str = include_str!("../shaders/shader.wgsl");
+/// Draws the light source itself.
+pub const LIGHT_SHADER_SOURCE: &str = include_str!("../shaders/light.wgsl");
+/// The animated background, see `State::set_plasma`.
+pub const PLASMA_SHADER_SOURCE: &str = include_str!("../shaders/plasma.wgsl");
+
+pub const VERTEX_ENTRY_POINT: &str = "vs_main";
+
+/// The WGSL sources of every pipeline, either the embedded ones or loaded
+/// from disk to pick up edits while the app runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderSources {
+    pub main: String,
+    pub light: String,
+    pub skybox: String,
+    pub particles: String,
+    pub plasma: String,
+}
+
+impl ShaderSources {
+    pub fn embedded() -> Self {
+        Self {
+            main: SHADER_SOURCE.to_owned(),
+            light: LIGHT_SHADER_SOURCE.to_owned(),
+            skybox: crate::skybox::SKYBOX_SHADER_SOURCE.to_owned(),
+            particles: crate::compute::PARTICLE_SHADER_SOURCE.to_owned(),
+            plasma: PLASMA_SHADER_SOURCE.to_owned(),
+        }
+    }
+
+    /// Reads the shaders from `shaders/` in `source`, with the same file
+    /// names as the embedded ones.
+    pub fn load(source: &dyn AssetSource) -> io::Result<Self> {
+        let read = |name: &str| {
+            let path = Path::new("shaders").join(name);
+            let bytes = source
+                .read(&path)
+                .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))?;
+            String::from_utf8(bytes).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {err}", path.display()),
+                )
+            })
+        };
+        Ok(Self {
+            main: read("shader.wgsl")?,
+            light: read("light.wgsl")?,
+            skybox: read("skybox.wgsl")?,
+            particles: read("particles.wgsl")?,
+            plasma: read("plasma.wgsl")?,
+        })
+    }
+}
+
+/// The attachments a render pipeline draws into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderTargets {
+    pub color_format: wgpu::TextureFormat,
+    pub depth_format: Option<wgpu::TextureFormat>,
+    pub sample_count: u32,
+}
+
+/// How a material's colors end up in the target. Everything but `Opaque`
+/// is transparent: drawn after the opaque objects, from back to front,
+/// testing depth without writing it, see `PipelineBuilder::blend_mode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Replaces what's behind.
+    #[default]
+    Opaque,
+    /// Mixes with what's behind by the alpha, like tinted glass.
+    AlphaBlend,
+    /// Adds the color times the alpha to what's behind, which only gets
+    /// lighter, like fire or light beams.
+    Additive,
+    /// Like `AlphaBlend` for colors already multiplied by their alpha, so
+    /// a color with alpha 0 is added like `Additive`.
+    Premultiplied,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 4] = [
+        BlendMode::Opaque,
+        BlendMode::AlphaBlend,
+        BlendMode::Additive,
+        BlendMode::Premultiplied,
+    ];
+
+    pub fn is_transparent(self) -> bool {
+        self != BlendMode::Opaque
+    }
+
+    pub fn blend_state(self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Opaque => wgpu::BlendState::REPLACE,
+            BlendMode::AlphaBlend => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                // Lighter, not any more opaque
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+            BlendMode::Premultiplied => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+        }
+    }
+}
+
+pub fn create_pipeline_layout(
+    device: &wgpu::Device,
+    label: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+) -> wgpu::PipelineLayout {
+    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    })
+}
+
+/// Picks the fragment entry point for the given target format.
+///
+/// sRGB formats encode the linear shader output on write and float formats
+/// keep it linear. Other formats store it as is, so for those the shader has
+/// to do the encoding itself.
+pub fn fragment_entry_point(format: wgpu::TextureFormat) -> &'static str {
+    if crate::color::stores_linear(format) {
+        "fs_main"
+    } else {
+        "fs_main_encode_srgb"
+    }
+}
+
+/// Where a `PipelineBuilder` gets its shader from.
+#[derive(Clone, Copy)]
+enum ShaderInput<'a> {
+    Source { file: &'a str, source: &'a str },
+    Module(&'a wgpu::ShaderModule),
+}
+
+/// Builds a render pipeline without spelling out the whole descriptor. By
+/// default it draws filled triangles with back faces culled, replacing what
+/// was in the target. The shader needs the
+/// `vs_main` entry point and the fragment entry points picked by
+/// `fragment_entry_point`, unless they're overridden.
+///
+/// `depth_only` pipelines have no fragment shader and only write depth,
+/// e.g. for shadow maps.
+#[derive(Clone)]
+pub struct PipelineBuilder<'a> {
+    label: &'a str,
+    shader: Option<ShaderInput<'a>>,
+    vertex_entry_point: &'a str,
+    /// `None` picks one with `fragment_entry_point`.
+    fragment_entry_point: Option<&'a str>,
+    bind_group_layouts: &'a [&'a wgpu::BindGroupLayout],
+    vertex_buffers: Vec<wgpu::VertexBufferLayout<'a>>,
+    /// `None` for depth-only pipelines.
+    color_format: Option<wgpu::TextureFormat>,
+    blend: Option<wgpu::BlendState>,
+    color_writes: wgpu::ColorWrites,
+    depth_format: Option<wgpu::TextureFormat>,
+    depth_write: bool,
+    depth_compare: wgpu::CompareFunction,
+    depth_bias: wgpu::DepthBiasState,
+    stencil: wgpu::StencilState,
+    cull_mode: Option<wgpu::Face>,
+    topology: wgpu::PrimitiveTopology,
+    polygon_mode: wgpu::PolygonMode,
+    sample_count: u32,
+}
+
+impl<'a> PipelineBuilder<'a> {
+    /// A pipeline drawing to surfaces configured with `config`.
+    pub fn new(label: &'a str, config: &wgpu::SurfaceConfiguration) -> Self {
+        Self::with_targets(
+            label,
+            RenderTargets {
+                color_format: crate::color::render_format(config),
+                depth_format: None,
+                sample_count: 1,
+            },
+        )
+    }
+
+    /// A pipeline drawing into `targets`.
+    pub fn with_targets(label: &'a str, targets: RenderTargets) -> Self {
+        Self {
+            label,
+            shader: None,
+            vertex_entry_point: VERTEX_ENTRY_POINT,
+            fragment_entry_point: None,
+            bind_group_layouts: &[],
+            vertex_buffers: Vec::new(),
+            color_format: Some(targets.color_format),
+            blend: Some(wgpu::BlendState::REPLACE),
+            color_writes: wgpu::ColorWrites::ALL,
+            depth_format: targets.depth_format,
+            depth_write: true,
+            // Draw a fragment only if it is closer than what's already there
+            depth_compare: wgpu::CompareFunction::Less,
+            depth_bias: wgpu::DepthBiasState::default(),
+            // Leaves the stencil buffer alone
+            stencil: wgpu::StencilState::default(),
+            cull_mode: Some(wgpu::Face::Back),
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            sample_count: targets.sample_count,
+        }
+    }
+
+    /// A pipeline that only draws into a depth texture with `depth_format`.
+    /// The shader only needs the `vs_main` entry point.
+    pub fn depth_only(label: &'a str, depth_format: wgpu::TextureFormat) -> Self {
+        Self {
+            color_format: None,
+            blend: None,
+            ..Self::with_targets(
+                label,
+                RenderTargets {
+                    color_format: depth_format,
+                    depth_format: Some(depth_format),
+                    sample_count: 1,
+                },
+            )
+        }
+    }
+
+    /// Labels the pipeline and what gets created for it.
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.label = label;
+        self
+    }
+
+    /// Compiles the WGSL `source` when building, `file` names it in the
+    /// errors.
+    pub fn shader(mut self, file: &'a str, source: &'a str) -> Self {
+        self.shader = Some(ShaderInput::Source { file, source });
+        self
+    }
+
+    /// Uses a shader module that's already compiled, e.g. one shared with
+    /// other pipelines.
+    pub fn shader_module(mut self, module: &'a wgpu::ShaderModule) -> Self {
+        self.shader = Some(ShaderInput::Module(module));
+        self
+    }
+
+    /// Uses `entry_point` instead of `vs_main`.
+    pub fn vertex_entry_point(mut self, entry_point: &'a str) -> Self {
+        self.vertex_entry_point = entry_point;
+        self
+    }
+
+    /// Uses `entry_point` whatever the target format, for shaders whose
+    /// output must never be sRGB encoded.
+    pub fn fragment_entry_point(mut self, entry_point: &'a str) -> Self {
+        self.fragment_entry_point = Some(entry_point);
+        self
+    }
+
+    pub fn bind_group_layouts(mut self, layouts: &'a [&'a wgpu::BindGroupLayout]) -> Self {
+        self.bind_group_layouts = layouts;
+        self
+    }
+
+    /// Adds the layout of the next vertex buffer slot.
+    pub fn vertex_buffer(mut self, layout: wgpu::VertexBufferLayout<'a>) -> Self {
+        self.vertex_buffers.push(layout);
+        self
+    }
+
+    /// `None` overwrites what's in the target instead of blending.
+    pub fn color_target(
+        mut self,
+        format: wgpu::TextureFormat,
+        blend: Option<wgpu::BlendState>,
+    ) -> Self {
+        self.color_format = Some(format);
+        self.blend = blend;
+        self
+    }
+
+    /// Blends with `mode`. Transparent modes keep testing depth but stop
+    /// writing it, so whatever is drawn behind them afterwards still shows.
+    pub fn blend_mode(mut self, mode: BlendMode) -> Self {
+        self.blend = Some(mode.blend_state());
+        self.depth_write = !mode.is_transparent();
+        self
+    }
+
+    /// Which channels of the color target get written, `ColorWrites::empty()`
+    /// for pipelines that only write depth or stencil.
+    pub fn color_writes(mut self, color_writes: wgpu::ColorWrites) -> Self {
+        self.color_writes = color_writes;
+        self
+    }
+
+    pub fn depth_format(mut self, format: Option<wgpu::TextureFormat>) -> Self {
+        self.depth_format = format;
+        self
+    }
+
+    /// How fragments are tested against the depth buffer, and whether they
+    /// write to it when they pass.
+    pub fn depth_test(mut self, compare: wgpu::CompareFunction, write: bool) -> Self {
+        self.depth_compare = compare;
+        self.depth_write = write;
+        self
+    }
+
+    /// Pushes the depth of each triangle away from the viewer, more the
+    /// steeper it is. Keeps shadow maps from shadowing the surfaces they
+    /// were drawn from.
+    pub fn depth_bias(mut self, bias: wgpu::DepthBiasState) -> Self {
+        self.depth_bias = bias;
+        self
+    }
+
+    /// How fragments are tested against the stencil buffer and what they
+    /// write to it. The depth format needs a stencil aspect, like
+    /// `Texture::DEPTH_FORMAT`, and the reference value is set on the render
+    /// pass.
+    pub fn stencil(mut self, stencil: wgpu::StencilState) -> Self {
+        self.stencil = stencil;
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: Option<wgpu::Face>) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    /// What the vertices make, `LineList` for lines that need no
+    /// `Features::POLYGON_MODE_LINE`.
+    pub fn topology(mut self, topology: wgpu::PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Modes other than `Fill` need their feature enabled on the device,
+    /// e.g. `Features::POLYGON_MODE_LINE`.
+    pub fn polygon_mode(mut self, polygon_mode: wgpu::PolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    /// Compiles the shader if needed and creates the pipeline. Problems
+    /// wgpu finds with the pipeline come back as a `ShaderErrorKind::Pipeline`
+    /// error instead of a panic.
+    pub fn build(&self, device: &wgpu::Device) -> Result<wgpu::RenderPipeline, ShaderError> {
+        let compiled;
+        let (file, module) = match self.shader {
+            Some(ShaderInput::Source { file, source }) => {
+                compiled = shader::create_shader_module(device, self.label, file, source)?;
+                (file, &compiled)
+            }
+            Some(ShaderInput::Module(module)) => (self.label, module),
+            None => {
+                return Err(ShaderError::pipeline(
+                    self.label,
+                    "the pipeline has no shader".to_owned(),
+                ))
+            }
+        };
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let layout = create_pipeline_layout(
+            device,
+            &format!("{} Layout", self.label),
+            self.bind_group_layouts,
+        );
+        let color_targets = self.color_format.map(|format| {
+            [Some(wgpu::ColorTargetState {
+                format,
+                blend: self.blend,
+                write_mask: self.color_writes,
+            })]
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(self.label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module,
+                entry_point: self.vertex_entry_point,
+                buffers: &self.vertex_buffers,
+            },
+            fragment: color_targets
+                .as_ref()
+                .zip(self.color_format)
+                .map(|(targets, format)| wgpu::FragmentState {
+                    module,
+                    entry_point: self
+                        .fragment_entry_point
+                        .unwrap_or_else(|| fragment_entry_point(format)),
+                    targets,
+                }),
+            primitive: wgpu::PrimitiveState {
+                topology: self.topology,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: self.cull_mode,
+                polygon_mode: self.polygon_mode,
+                // Requires Features::DEPTH_CLIP_CONTROL
+                unclipped_depth: false,
+                // Requires Features::CONSERVATIVE_RASTERIZATION
+                conservative: false,
+            },
+            depth_stencil: self.depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: self.depth_write,
+                depth_compare: self.depth_compare,
+                stencil: self.stencil.clone(),
+                bias: self.depth_bias,
+            }),
+            multisample: wgpu::MultisampleState {
+                count: self.sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        match pop_error_scope(device) {
+            Some(err) => {
+                let err = ShaderError::pipeline(file, err.to_string());
+                log::error!("{err}");
+                Err(err)
+            }
+            None => Ok(pipeline),
+        }
+    }
+}
+
+/// The error caught by the innermost error scope, if any. Natively the
+/// result is there right away. On the web it only arrives later, so errors
+/// aren't caught there and get reported by the device's error handler
+/// instead.
+fn pop_error_scope(device: &wgpu::Device) -> Option<wgpu::Error> {
+    let mut future = std::pin::pin!(device.pop_error_scope());
+    let mut context = Context::from_waker(Waker::noop());
+    match future.as_mut().poll(&mut context) {
+        Poll::Ready(err) => err,
+        Poll::Pending => None,
+    }
+}
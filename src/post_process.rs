@@ -0,0 +1,346 @@
+use crate::{
+    bloom::Bloom,
+    gpu_memory::TrackedView,
+    pipeline::{PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+    tonemap::{Tonemap, TonemapUniform, TONEMAP_SHADER_SOURCE},
+    uniform::UniformBuffer,
+};
+
+/// Copies the scene to the frame as is.
+pub const PASS_THROUGH_SHADER_SOURCE: &str = include_str!("../shaders/post.wgsl");
+pub const VIGNETTE_SHADER_SOURCE: &str = include_str!("../shaders/vignette.wgsl");
+
+/// The format the scene is rendered in before post-processing. Floats keep
+/// the colors linear and allow values above 1.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// An effect applied to the whole frame. Its shader reads the scene from
+/// `t_scene` and `s_scene` in group 0 and draws a fullscreen triangle, with
+/// the same entry points as the other shaders. Effects draw in
+/// `HDR_FORMAT`, the tonemapping comes after them.
+pub trait PostProcess {
+    fn name(&self) -> &str;
+
+    /// The file name shown in shader errors.
+    fn shader_file(&self) -> &str {
+        "post.wgsl"
+    }
+
+    /// Passes the scene through unchanged unless overridden.
+    fn shader_source(&self) -> &str {
+        PASS_THROUGH_SHADER_SOURCE
+    }
+}
+
+/// No effect at all.
+pub struct PassThrough;
+
+impl PostProcess for PassThrough {
+    fn name(&self) -> &str {
+        "None"
+    }
+}
+
+/// Darkens the corners of the frame.
+pub struct Vignette;
+
+impl PostProcess for Vignette {
+    fn name(&self) -> &str {
+        "Vignette"
+    }
+
+    fn shader_file(&self) -> &str {
+        "vignette.wgsl"
+    }
+
+    fn shader_source(&self) -> &str {
+        VIGNETTE_SHADER_SOURCE
+    }
+}
+
+struct Effect {
+    effect: Box<dyn PostProcess>,
+    pipeline: wgpu::RenderPipeline,
+}
+
+/// Draws the HDR texture the scene gets rendered into to the frame, through
+/// one of its effects and then the tonemapping. It also has the bloom, which
+/// is drawn onto the HDR texture before.
+pub struct PostProcessor {
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    bind_group: wgpu::BindGroup,
+    bloom: Bloom,
+    /// What the active effect draws into, still HDR.
+    effect_view: TrackedView,
+    effect_bind_group: wgpu::BindGroup,
+    /// Pass-through first, then the effects it was created with.
+    effects: Vec<Effect>,
+    active: usize,
+    tonemap: Tonemap,
+    tonemap_buffer: UniformBuffer<TonemapUniform>,
+    tonemap_bind_group: wgpu::BindGroup,
+    tonemap_pipeline: wgpu::RenderPipeline,
+}
+
+impl PostProcessor {
+    /// `config` describes the frame the tonemapping draws into, and
+    /// `hdr_view` is the `HDR_FORMAT` texture of its size the scene is
+    /// drawn into. The pass-through effect is added in front of `effects`
+    /// and starts out active.
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        hdr_view: &wgpu::TextureView,
+        effects: Vec<Box<dyn PostProcess>>,
+    ) -> Result<Self, ShaderError> {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post_process_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        // The texture has the size of the frame, so every fragment samples
+        // exactly one texel
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("post_process_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let bind_group = create_bind_group(device, &bind_group_layout, hdr_view, &sampler);
+        let bloom = Bloom::new(device, config, hdr_view)?;
+        let effect_view = create_hdr_view(device, config);
+        let effect_bind_group =
+            create_bind_group(device, &bind_group_layout, &effect_view, &sampler);
+
+        let tonemap = Tonemap::default();
+        let tonemap_buffer = UniformBuffer::new(device, "Tonemap Buffer", &tonemap.uniform());
+        let tonemap_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("tonemap_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let tonemap_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tonemap_bind_group"),
+            layout: &tonemap_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tonemap_buffer.binding(),
+            }],
+        });
+        // Only the tonemapping draws to the frame, and takes care of the
+        // sRGB encoding where the frame's format doesn't
+        let tonemap_pipeline = PipelineBuilder::new("Tonemap Pipeline", config)
+            .shader("tonemap.wgsl", TONEMAP_SHADER_SOURCE)
+            .bind_group_layouts(&[&bind_group_layout, &tonemap_bind_group_layout])
+            .build(device)?;
+
+        let targets = RenderTargets {
+            color_format: HDR_FORMAT,
+            depth_format: None,
+            sample_count: 1,
+        };
+        let effects = std::iter::once(Box::new(PassThrough) as Box<dyn PostProcess>)
+            .chain(effects)
+            .map(|effect| {
+                let pipeline = PipelineBuilder::with_targets("Post Process Pipeline", targets)
+                    .shader(effect.shader_file(), effect.shader_source())
+                    .bind_group_layouts(&[&bind_group_layout])
+                    .build(device)?;
+                Ok(Effect { effect, pipeline })
+            })
+            .collect::<Result<_, ShaderError>>()?;
+
+        Ok(Self {
+            bind_group_layout,
+            sampler,
+            bind_group,
+            bloom,
+            effect_view,
+            effect_bind_group,
+            effects,
+            active: 0,
+            tonemap,
+            tonemap_buffer,
+            tonemap_bind_group,
+            tonemap_pipeline,
+        })
+    }
+
+    /// Recreates its textures to match the new size of the frame, and
+    /// draws `hdr_view` from now on.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        hdr_view: &wgpu::TextureView,
+    ) {
+        self.bind_group =
+            create_bind_group(device, &self.bind_group_layout, hdr_view, &self.sampler);
+        self.bloom.resize(device, config, hdr_view);
+        self.effect_view = create_hdr_view(device, config);
+        self.effect_bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.effect_view,
+            &self.sampler,
+        );
+    }
+
+    pub fn active_effect(&self) -> &dyn PostProcess {
+        self.effects[self.active].effect.as_ref()
+    }
+
+    /// Switches to the next effect, going back to pass-through after the
+    /// last one.
+    pub fn next_effect(&mut self) -> &dyn PostProcess {
+        self.active = (self.active + 1) % self.effects.len();
+        self.active_effect()
+    }
+
+    pub fn bloom(&self) -> &Bloom {
+        &self.bloom
+    }
+
+    pub fn bloom_mut(&mut self) -> &mut Bloom {
+        &mut self.bloom
+    }
+
+    pub fn tonemap(&self) -> Tonemap {
+        self.tonemap
+    }
+
+    /// Shows up from the next submit on.
+    pub fn set_tonemap(&mut self, queue: &wgpu::Queue, tonemap: Tonemap) {
+        self.tonemap = tonemap;
+        self.tonemap_buffer.write(queue, &tonemap.uniform());
+    }
+
+    /// Records drawing the HDR texture into `view` through the active
+    /// effect and the tonemapping. The bloom is drawn separately, see
+    /// `Bloom::draw`.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        // Passing through would only copy the scene, the tonemapping can
+        // just as well read it directly
+        let source = if self.active == 0 {
+            &self.bind_group
+        } else {
+            encode_fullscreen_pass(
+                encoder,
+                "Post Process Render Pass",
+                &self.effect_view,
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                &self.effects[self.active].pipeline,
+                &[&self.bind_group],
+            );
+            &self.effect_bind_group
+        };
+        encode_fullscreen_pass(
+            encoder,
+            "Tonemap Render Pass",
+            view,
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            &self.tonemap_pipeline,
+            &[source, &self.tonemap_bind_group],
+        );
+    }
+}
+
+/// Records a pass drawing the fullscreen triangle into `view`, with one bind
+/// group per group of the pipeline's shader.
+pub(crate) fn encode_fullscreen_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    view: &wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+    pipeline: &wgpu::RenderPipeline,
+    bind_groups: &[&wgpu::BindGroup],
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    render_pass.set_pipeline(pipeline);
+    for (index, bind_group) in bind_groups.iter().enumerate() {
+        render_pass.set_bind_group(index as u32, bind_group, &[]);
+    }
+    render_pass.draw(0..3, 0..1);
+}
+
+fn create_hdr_view(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> TrackedView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("hdr_texture"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: HDR_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    TrackedView::new(&texture)
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    hdr_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("post_process_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(hdr_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
@@ -0,0 +1,52 @@
+use wgpu::PresentMode;
+
+/// How frames should be presented, mapped to one of the present modes the
+/// surface supports by `select_present_mode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentModePreference {
+    /// Waits for the vertical blank, no tearing. Supported everywhere.
+    #[default]
+    Vsync,
+    /// Presents right away, which may tear.
+    Immediate,
+    /// Replaces the queued frame with the newest one, no tearing.
+    Mailbox,
+    /// The lowest latency mode available.
+    Auto,
+}
+
+impl PresentModePreference {
+    /// The present modes to try, best first. `Fifo` is always last as it's the
+    /// only one guaranteed to be supported.
+    fn candidates(self) -> &'static [PresentMode] {
+        match self {
+            Self::Vsync => &[PresentMode::Fifo],
+            Self::Immediate => &[
+                PresentMode::Immediate,
+                PresentMode::Mailbox,
+                PresentMode::Fifo,
+            ],
+            // Falling back to Immediate would tear, which Mailbox never does
+            Self::Mailbox => &[PresentMode::Mailbox, PresentMode::Fifo],
+            Self::Auto => &[
+                PresentMode::Mailbox,
+                PresentMode::Immediate,
+                PresentMode::Fifo,
+            ],
+        }
+    }
+}
+
+/// Picks the best of `supported`, the surface's present modes, for
+/// `preference`.
+pub fn select_present_mode(
+    preference: PresentModePreference,
+    supported: &[PresentMode],
+) -> PresentMode {
+    preference
+        .candidates()
+        .iter()
+        .copied()
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(PresentMode::Fifo)
+}
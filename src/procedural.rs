@@ -0,0 +1,79 @@
+//! Images generated in code, so the samples have textures without any image
+//! files. `Texture::checkerboard`, `Texture::gradient` and
+//! `Texture::value_noise` upload them. Colors are sRGB encoded RGBA.
+
+use image::{Rgba, RgbaImage};
+
+/// Squares of `cell` pixels alternating between `color_a` and `color_b`,
+/// starting with `color_a` at the top left.
+pub fn checkerboard(size: (u32, u32), cell: u32, color_a: [u8; 4], color_b: [u8; 4]) -> RgbaImage {
+    let cell = cell.max(1);
+    RgbaImage::from_fn(size.0, size.1, |x, y| {
+        if (x / cell + y / cell).is_multiple_of(2) {
+            Rgba(color_a)
+        } else {
+            Rgba(color_b)
+        }
+    })
+}
+
+/// Goes from `top` in the first row to `bottom` in the last one.
+pub fn gradient(size: (u32, u32), top: [u8; 4], bottom: [u8; 4]) -> RgbaImage {
+    let last_row = size.1.saturating_sub(1).max(1) as f32;
+    RgbaImage::from_fn(size.0, size.1, |_, y| {
+        let t = y as f32 / last_row;
+        Rgba(std::array::from_fn(|i| {
+            (f32::from(top[i]) + (f32::from(bottom[i]) - f32::from(top[i])) * t).round() as u8
+        }))
+    })
+}
+
+/// Gray value noise that tiles, the same for the same `seed`. Each of the
+/// `octaves` (1 to 16) has twice the detail of the one before and
+/// half its weight, starting with 4 cells across.
+pub fn value_noise(size: (u32, u32), seed: u32, octaves: u32) -> RgbaImage {
+    let octaves = octaves.clamp(1, 16);
+    let total_weight: f32 = (0..octaves).map(|octave| 0.5f32.powi(octave as i32)).sum();
+    RgbaImage::from_fn(size.0, size.1, |x, y| {
+        let (u, v) = (x as f32 / size.0 as f32, y as f32 / size.1 as f32);
+        let mut value = 0.0;
+        for octave in 0..octaves {
+            let cells = 4 << octave;
+            let weight = 0.5f32.powi(octave as i32);
+            value +=
+                lattice_noise(u * cells as f32, v * cells as f32, cells, seed ^ octave) * weight;
+        }
+        let gray = (value / total_weight * 255.0).round() as u8;
+        Rgba([gray, gray, gray, 255])
+    })
+}
+
+/// Smoothly interpolated random values at the corners of a grid of `cells`
+/// by `cells`, which wraps around.
+fn lattice_noise(x: f32, y: f32, cells: u32, seed: u32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+    let corner = |dx: u32, dy: u32| {
+        let cx = (x0 as u32 + dx) % cells;
+        let cy = (y0 as u32 + dy) % cells;
+        hash(cx, cy, seed) as f32 / u32::MAX as f32
+    };
+    let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * tx;
+    let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * tx;
+    top + (bottom - top) * ty
+}
+
+/// Mixes the bits of the coordinates and the seed, the same on every
+/// platform.
+fn hash(x: u32, y: u32, seed: u32) -> u32 {
+    let mut h = seed
+        .wrapping_mul(0x9E37_79B9)
+        .wrapping_add(x.wrapping_mul(0x85EB_CA6B))
+        .wrapping_add(y.wrapping_mul(0xC2B2_AE35));
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^ (h >> 16)
+}
@@ -0,0 +1,251 @@
+use std::{sync::mpsc, time::Duration};
+
+/// The passes that get timed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpuPass {
+    Render,
+    Compute,
+}
+
+impl GpuPass {
+    const COUNT: usize = 2;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The queries holding the start and end of the pass.
+    fn first_query(self) -> u32 {
+        2 * self as u32
+    }
+
+    /// `resolve_query_set` needs 256-byte aligned offsets, so each pass gets
+    /// its own slot of the resolve buffer.
+    fn resolve_offset(self) -> wgpu::BufferAddress {
+        self as wgpu::BufferAddress * wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT
+    }
+}
+
+/// How long the GPU spent on each pass of a frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuTimings {
+    pub render: Duration,
+    /// `None` if nothing was simulated that frame.
+    pub compute: Option<Duration>,
+}
+
+/// How many frames can be waiting for their timings at once. When they're
+/// all in flight the timings of the new frames are skipped rather than
+/// waiting for the GPU.
+const READBACK_COUNT: usize = 3;
+
+/// Two timestamps of 8 bytes per pass.
+const TIMESTAMPS_SIZE: wgpu::BufferAddress = 16;
+
+struct Readback {
+    buffer: wgpu::Buffer,
+    /// Which passes the buffer holds timestamps for.
+    passes: [bool; GpuPass::COUNT],
+    /// `Some` while the buffer is being mapped.
+    receiver: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+    /// Filled this frame, to be mapped once the frame is submitted.
+    filled: bool,
+    /// The frame the timestamps are from, buffers can finish mapping out of
+    /// order.
+    frame: u64,
+}
+
+impl Readback {
+    fn is_free(&self) -> bool {
+        self.receiver.is_none() && !self.filled
+    }
+}
+
+/// Times the passes of a frame with timestamp queries. The timings arrive a
+/// frame or two after the frame was submitted.
+pub struct GpuProfiler {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readbacks: Vec<Readback>,
+    /// Nanoseconds per timestamp tick.
+    timestamp_period: f32,
+    /// The passes timed since the last `end_frame`.
+    written: [bool; GpuPass::COUNT],
+    frame: u64,
+    /// The timings and the frame they're from.
+    last_timings: Option<(u64, GpuTimings)>,
+}
+
+impl GpuProfiler {
+    /// `None` if the device wasn't created with `Features::TIMESTAMP_QUERY`.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timestamp Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2 * GpuPass::COUNT as u32,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size: GpuPass::COUNT as wgpu::BufferAddress * wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readbacks = (0..READBACK_COUNT)
+            .map(|_| Readback {
+                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Timestamp Readback Buffer"),
+                    size: GpuPass::COUNT as wgpu::BufferAddress * TIMESTAMPS_SIZE,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                passes: [false; GpuPass::COUNT],
+                receiver: None,
+                filled: false,
+                frame: 0,
+            })
+            .collect();
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readbacks,
+            timestamp_period: queue.get_timestamp_period(),
+            written: [false; GpuPass::COUNT],
+            frame: 0,
+            last_timings: None,
+        })
+    }
+
+    pub fn render_timestamp_writes(&self) -> wgpu::RenderPassTimestampWrites<'_> {
+        wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(GpuPass::Render.first_query()),
+            end_of_pass_write_index: Some(GpuPass::Render.first_query() + 1),
+        }
+    }
+
+    pub fn compute_timestamp_writes(&self) -> wgpu::ComputePassTimestampWrites<'_> {
+        wgpu::ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(GpuPass::Compute.first_query()),
+            end_of_pass_write_index: Some(GpuPass::Compute.first_query() + 1),
+        }
+    }
+
+    /// Records resolving the timestamps of `pass`, after the pass in the
+    /// same encoder.
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder, pass: GpuPass) {
+        let first_query = pass.first_query();
+        encoder.resolve_query_set(
+            &self.query_set,
+            first_query..first_query + 2,
+            &self.resolve_buffer,
+            pass.resolve_offset(),
+        );
+        self.written[pass.index()] = true;
+    }
+
+    /// Records copying the timestamps resolved this frame into a free
+    /// readback buffer. Call `map` once the encoder has been submitted.
+    pub fn end_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let written = std::mem::take(&mut self.written);
+        self.frame += 1;
+        if !written[GpuPass::Render.index()] {
+            return;
+        }
+        let Some(readback) = self
+            .readbacks
+            .iter_mut()
+            .find(|readback| readback.is_free())
+        else {
+            return;
+        };
+
+        for pass in [GpuPass::Render, GpuPass::Compute] {
+            if written[pass.index()] {
+                encoder.copy_buffer_to_buffer(
+                    &self.resolve_buffer,
+                    pass.resolve_offset(),
+                    &readback.buffer,
+                    pass.index() as wgpu::BufferAddress * TIMESTAMPS_SIZE,
+                    TIMESTAMPS_SIZE,
+                );
+            }
+        }
+        readback.passes = written;
+        readback.filled = true;
+        readback.frame = self.frame;
+    }
+
+    /// Starts mapping the buffer filled by `end_frame`.
+    pub fn map(&mut self) {
+        for readback in self.readbacks.iter_mut().filter(|readback| readback.filled) {
+            let (sender, receiver) = mpsc::channel();
+            readback
+                .buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    // The receiver is gone if the profiler was dropped, which is fine
+                    let _ = sender.send(result);
+                });
+            readback.receiver = Some(receiver);
+            readback.filled = false;
+        }
+    }
+
+    /// Picks up the timings of the frames the GPU is done with. Only checks,
+    /// the device has to be polled for the buffers to get mapped.
+    pub fn collect(&mut self) {
+        for readback in &mut self.readbacks {
+            let Some(result) = readback
+                .receiver
+                .as_ref()
+                .and_then(|receiver| receiver.try_recv().ok())
+            else {
+                continue;
+            };
+            readback.receiver = None;
+            if result.is_err() {
+                continue;
+            }
+
+            let timestamps: Vec<u64> = {
+                let data = readback.buffer.slice(..).get_mapped_range();
+                bytemuck::cast_slice(&data).to_vec()
+            };
+            readback.buffer.unmap();
+
+            let duration = |pass: GpuPass| {
+                readback.passes[pass.index()].then(|| {
+                    let [start, end] = [0, 1].map(|i| timestamps[2 * pass.index() + i]);
+                    ticks_to_duration(end.saturating_sub(start), self.timestamp_period)
+                })
+            };
+            let is_newer = self
+                .last_timings
+                .is_none_or(|(frame, _)| readback.frame > frame);
+            if let (true, Some(render)) = (is_newer, duration(GpuPass::Render)) {
+                let timings = GpuTimings {
+                    render,
+                    compute: duration(GpuPass::Compute),
+                };
+                self.last_timings = Some((readback.frame, timings));
+            }
+        }
+    }
+
+    /// The timings of the most recent frame that finished on the GPU.
+    pub fn last_timings(&self) -> Option<GpuTimings> {
+        self.last_timings.map(|(_, timings)| timings)
+    }
+}
+
+/// `timestamp_period` is in nanoseconds per tick, see
+/// `wgpu::Queue::get_timestamp_period`.
+pub fn ticks_to_duration(ticks: u64, timestamp_period: f32) -> Duration {
+    Duration::from_nanos((ticks as f64 * f64::from(timestamp_period)) as u64)
+}
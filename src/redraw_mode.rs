@@ -0,0 +1,20 @@
+/// When the event loop redraws the windows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RedrawMode {
+    /// Every iteration of the event loop, which keeps everything animating.
+    #[default]
+    Continuous,
+    /// Only after resizes, input or `State::request_redraw`. The loop waits
+    /// for events in between, so an idle window uses no CPU or GPU time.
+    OnDemand,
+}
+
+impl RedrawMode {
+    /// The other mode.
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Continuous => Self::OnDemand,
+            Self::OnDemand => Self::Continuous,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::ops::Range;
+
+use crate::pipeline::RenderTargets;
+
+/// Everything a bundle was recorded with. GPU objects are compared by id, so
+/// a rebuilt pipeline or a reallocated buffer needs a new bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleKey {
+    pub targets: RenderTargets,
+    pub pipeline: wgpu::Id<wgpu::RenderPipeline>,
+    pub bind_groups: Vec<wgpu::Id<wgpu::BindGroup>>,
+    pub buffers: Vec<wgpu::Id<wgpu::Buffer>>,
+    /// The instances of each mesh, see `culling::Culled`.
+    pub ranges: Vec<Range<u32>>,
+}
+
+/// Draw commands recorded once and replayed with `execute_bundles` every
+/// frame, which saves encoding them again when nothing changed.
+#[derive(Debug)]
+pub struct SceneBundle {
+    key: BundleKey,
+    bundle: wgpu::RenderBundle,
+}
+
+impl SceneBundle {
+    /// Records what `draw` encodes for render passes into `key.targets`.
+    pub fn record<'a>(
+        device: &'a wgpu::Device,
+        label: &str,
+        key: BundleKey,
+        draw: impl FnOnce(&mut wgpu::RenderBundleEncoder<'a>),
+    ) -> Self {
+        let targets = key.targets;
+        let mut encoder =
+            device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                label: Some(label),
+                color_formats: &[Some(targets.color_format)],
+                depth_stencil: targets
+                    .depth_format
+                    .map(|format| wgpu::RenderBundleDepthStencil {
+                        format,
+                        depth_read_only: false,
+                        stencil_read_only: true,
+                    }),
+                sample_count: targets.sample_count,
+                multiview: None,
+            });
+        draw(&mut encoder);
+        let bundle = encoder.finish(&wgpu::RenderBundleDescriptor { label: Some(label) });
+        Self { key, bundle }
+    }
+
+    pub fn key(&self) -> &BundleKey {
+        &self.key
+    }
+
+    pub fn bundle(&self) -> &wgpu::RenderBundle {
+        &self.bundle
+    }
+}
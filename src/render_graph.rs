@@ -0,0 +1,516 @@
+//! The passes of a frame as a list of nodes, run in order. `State` starts
+//! out with the scene, the bloom and the post-processing, and more can be
+//! put in between or taken out while it runs: turning off the bloom just
+//! removes its node, and turning on FXAA adds one.
+//!
+//! The textures the passes share, like the HDR target and the depth buffer,
+//! live in the `FrameResources` by name. The ones the size of the frame get
+//! recreated with it in `FrameResources::resize`. That's the size the scene
+//! renders at, which is the window's times the render scale.
+
+use std::collections::HashMap;
+
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    debug_view::{DebugSources, DebugView},
+    gpu_memory::TrackedView,
+    State,
+};
+
+/// The scene's depth buffer, with the MSAA sample count.
+pub const DEPTH: &str = "depth";
+/// What the scene gets drawn into, in `post_process::HDR_FORMAT`.
+pub const HDR: &str = "hdr";
+/// The multisampled target resolved into `HDR`, only there with MSAA on.
+pub const MSAA: &str = "msaa";
+/// What the shadow pass draws into, see `shadow::resource_desc`.
+pub const SHADOW_MAP: &str = "shadow_map";
+/// What the post-processing draws into instead of the frame while the
+/// render scale isn't 1, in the frame's format. See `render_scale`.
+pub const SCALED: &str = "scaled";
+/// What the tonemapping draws into for the FXAA node to read, in the
+/// frame's format. Only there with FXAA on, see `fxaa`.
+pub const LDR: &str = "ldr";
+/// The resources `State` creates and binds itself.
+pub const BUILT_IN_RESOURCES: [&str; 6] = [DEPTH, HDR, MSAA, SHADOW_MAP, SCALED, LDR];
+
+/// Shadows, clearing the HDR target and drawing the scene into it.
+pub const SCENE_NODE: &str = "scene";
+pub const BLOOM_NODE: &str = "bloom";
+/// The effects and the tonemapping, which draw to the frame unless FXAA
+/// is on. With a render scale it also draws `SCALED` into the frame.
+pub const POST_PROCESS_NODE: &str = "post_process";
+/// Smooths `LDR` into the frame, right after the post-processing while
+/// FXAA is on.
+pub const FXAA_NODE: &str = "fxaa";
+/// Shows the targets of the debug view over the frame, last while there's
+/// one. See `debug_view`.
+pub const DEBUG_VIEW_NODE: &str = "debug_view";
+
+/// How big a frame resource is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceSize {
+    /// As big as the frame, it gets recreated when that changes.
+    Frame,
+    Fixed {
+        width: u32,
+        height: u32,
+    },
+}
+
+/// What a frame resource is created with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceDesc {
+    pub size: ResourceSize,
+    pub format: wgpu::TextureFormat,
+    pub usage: wgpu::TextureUsages,
+    pub sample_count: u32,
+}
+
+impl ResourceDesc {
+    /// A texture the size of the frame, not multisampled.
+    pub fn frame(format: wgpu::TextureFormat, usage: wgpu::TextureUsages) -> Self {
+        Self {
+            size: ResourceSize::Frame,
+            format,
+            usage,
+            sample_count: 1,
+        }
+    }
+
+    /// A `width` by `height` texture, not multisampled.
+    pub fn fixed(
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
+        Self {
+            size: ResourceSize::Fixed { width, height },
+            ..Self::frame(format, usage)
+        }
+    }
+
+    pub fn with_sample_count(self, sample_count: u32) -> Self {
+        Self {
+            sample_count,
+            ..self
+        }
+    }
+}
+
+struct Resource {
+    desc: ResourceDesc,
+    texture: wgpu::Texture,
+    view: TrackedView,
+}
+
+impl Resource {
+    fn new(
+        device: &wgpu::Device,
+        name: &str,
+        desc: ResourceDesc,
+        frame_size: PhysicalSize<u32>,
+    ) -> Self {
+        let (width, height) = match desc.size {
+            ResourceSize::Frame => (frame_size.width, frame_size.height),
+            ResourceSize::Fixed { width, height } => (width, height),
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(name),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: desc.sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: desc.format,
+            usage: desc.usage,
+            view_formats: &[],
+        });
+        Self {
+            desc,
+            view: TrackedView::new(&texture),
+            texture,
+        }
+    }
+}
+
+/// The textures the nodes share, by name.
+pub struct FrameResources {
+    size: PhysicalSize<u32>,
+    resources: HashMap<String, Resource>,
+}
+
+impl FrameResources {
+    /// No resources yet for frames of `size`, which must not be 0x0.
+    pub fn new(size: PhysicalSize<u32>) -> Self {
+        Self {
+            size,
+            resources: HashMap::new(),
+        }
+    }
+
+    /// The size of the frame.
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    /// Creates the resource `name`, replacing the one there was.
+    pub fn insert(&mut self, device: &wgpu::Device, name: &str, desc: ResourceDesc) {
+        let resource = Resource::new(device, name, desc, self.size);
+        self.resources.insert(name.to_owned(), resource);
+    }
+
+    /// Returns whether there was a resource `name`.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.resources.remove(name).is_some()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.resources.contains_key(name)
+    }
+
+    pub fn desc(&self, name: &str) -> Option<ResourceDesc> {
+        self.resources.get(name).map(|resource| resource.desc)
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&wgpu::Texture> {
+        self.resources.get(name).map(|resource| &resource.texture)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&wgpu::TextureView> {
+        self.resources.get(name).map(|resource| &*resource.view)
+    }
+
+    /// # Panics
+    ///
+    /// If there is no resource `name`.
+    pub fn view(&self, name: &str) -> &wgpu::TextureView {
+        match self.get(name) {
+            Some(view) => view,
+            None => panic!("there is no frame resource named {name:?}"),
+        }
+    }
+
+    /// Recreates every resource the size of the frame for frames of `size`.
+    /// Bind groups with the old textures have to be made again.
+    pub fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        self.size = size;
+        for (name, resource) in &mut self.resources {
+            if resource.desc.size == ResourceSize::Frame {
+                *resource = Resource::new(device, name, resource.desc, size);
+            }
+        }
+    }
+}
+
+/// One frame being recorded. It owns the encoder the nodes record into and
+/// the view of the frame's texture.
+pub struct Frame<'a> {
+    pub encoder: wgpu::CommandEncoder,
+    /// What gets presented, or read back for screenshots.
+    pub view: wgpu::TextureView,
+    state: &'a State,
+    timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'a>>,
+}
+
+impl<'a> Frame<'a> {
+    pub(crate) fn new(
+        state: &'a State,
+        encoder: wgpu::CommandEncoder,
+        view: wgpu::TextureView,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'a>>,
+    ) -> Self {
+        Self {
+            encoder,
+            view,
+            state,
+            timestamp_writes,
+        }
+    }
+
+    /// What's being drawn.
+    pub fn state(&self) -> &'a State {
+        self.state
+    }
+
+    pub fn device(&self) -> &'a wgpu::Device {
+        &self.state.device
+    }
+
+    pub fn queue(&self) -> &'a wgpu::Queue {
+        &self.state.queue
+    }
+
+    /// Where the render pass that gets timed writes its timestamps, `None`
+    /// once a node took them or when nothing is timed.
+    pub fn take_timestamp_writes(&mut self) -> Option<wgpu::RenderPassTimestampWrites<'a>> {
+        self.timestamp_writes.take()
+    }
+
+    pub(crate) fn finish(self) -> (wgpu::CommandEncoder, wgpu::TextureView) {
+        (self.encoder, self.view)
+    }
+}
+
+/// A step of the frame, usually a render pass or a few.
+pub trait RenderNode {
+    /// What the node is found by in the `RenderGraph`.
+    fn name(&self) -> &str;
+
+    fn run(&mut self, frame: &mut Frame, resources: &FrameResources);
+}
+
+/// The nodes of a frame in the order they run.
+#[derive(Default)]
+pub struct RenderGraph {
+    nodes: Vec<Box<dyn RenderNode>>,
+}
+
+impl RenderGraph {
+    /// The scene, the bloom and the post-processing, which is what `State`
+    /// draws without any other nodes.
+    pub fn with_default_nodes() -> Self {
+        let mut graph = Self::default();
+        graph.push(Box::new(ScenePass));
+        graph.push(Box::new(BloomPass));
+        graph.push(Box::new(PostProcessPass));
+        graph
+    }
+
+    /// Runs `node` after all the others.
+    pub fn push(&mut self, node: Box<dyn RenderNode>) {
+        self.nodes.push(node);
+    }
+
+    /// Runs `node` right before the node `before`, or last if there is
+    /// none.
+    pub fn insert_before(&mut self, before: &str, node: Box<dyn RenderNode>) {
+        let index = self.position(before).unwrap_or(self.nodes.len());
+        self.nodes.insert(index, node);
+    }
+
+    /// Runs `node` right after the node `after`, or last if there is none.
+    pub fn insert_after(&mut self, after: &str, node: Box<dyn RenderNode>) {
+        let index = self.position(after).map_or(self.nodes.len(), |i| i + 1);
+        self.nodes.insert(index, node);
+    }
+
+    /// Takes the node `name` out, it can be put back in later.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn RenderNode>> {
+        let index = self.position(name)?;
+        Some(self.nodes.remove(index))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// The names of the nodes in the order they run.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.nodes.iter().map(|node| node.name())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.name() == name)
+    }
+
+    /// Runs every node on `frame`.
+    pub fn run(&mut self, frame: &mut Frame, resources: &FrameResources) {
+        for node in &mut self.nodes {
+            node.run(frame, resources);
+        }
+    }
+}
+
+/// Draws the shadow map, then clears `HDR` and draws the scene into it,
+/// through `MSAA` if it's there.
+pub struct ScenePass;
+
+impl RenderNode for ScenePass {
+    fn name(&self) -> &str {
+        SCENE_NODE
+    }
+
+    fn run(&mut self, frame: &mut Frame, resources: &FrameResources) {
+        let state = frame.state();
+        let timestamp_writes = frame.take_timestamp_writes();
+        let encoder = &mut frame.encoder;
+        let transparent = state.is_transparent();
+        let clear_color = if transparent {
+            // Whatever the compositor puts behind the window shows through
+            // where nothing gets drawn
+            wgpu::Color::TRANSPARENT
+        } else if state.debug_view == DebugView::Overdraw {
+            // The fragments get counted from 0
+            wgpu::Color::TRANSPARENT
+        } else if state.file_hovered {
+            crate::color::lighten(state.clear_color, crate::DROP_HINT_AMOUNT)
+        } else {
+            state.clear_color
+        };
+        let clear_value =
+            crate::color::clear_value_for_format(clear_color, crate::post_process::HDR_FORMAT);
+
+        // With MSAA we draw into the multisampled texture, which then gets
+        // resolved into the HDR one
+        let hdr_view = resources.view(HDR);
+        let color_attachment = match resources.get(MSAA) {
+            Some(msaa_view) => wgpu::RenderPassColorAttachment {
+                view: msaa_view,
+                resolve_target: Some(hdr_view),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_value),
+                    // Only the resolved image is needed afterwards
+                    store: wgpu::StoreOp::Discard,
+                },
+            },
+            None => wgpu::RenderPassColorAttachment {
+                view: hdr_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_value),
+                    store: wgpu::StoreOp::Store,
+                },
+            },
+        };
+
+        // The main pass samples the shadow map, so it's drawn first
+        if let Some(obj_model) = &state.obj_model {
+            state.shadow_map.draw(
+                encoder,
+                resources.view(SHADOW_MAP),
+                obj_model,
+                state.instance_buffer.buffer(),
+                state.instance_buffer.len() as u32,
+            );
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(color_attachment)],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: resources.view(DEPTH),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                // Only the outline uses it, within the pass
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+            }),
+            timestamp_writes,
+            occlusion_query_set: None,
+        });
+        state.encode_scene(&mut render_pass, transparent);
+    }
+}
+
+/// Adds the bloom of the brightest parts onto `HDR`.
+pub struct BloomPass;
+
+impl RenderNode for BloomPass {
+    fn name(&self) -> &str {
+        BLOOM_NODE
+    }
+
+    fn run(&mut self, frame: &mut Frame, resources: &FrameResources) {
+        let bloom = frame.state().post_processor.bloom();
+        bloom.draw(&mut frame.encoder, resources.view(HDR));
+    }
+}
+
+/// Draws `HDR` through the active effect and the tonemapping into `LDR`
+/// if it's there, or else into the frame, see `draw_to_frame`.
+pub struct PostProcessPass;
+
+impl RenderNode for PostProcessPass {
+    fn name(&self) -> &str {
+        POST_PROCESS_NODE
+    }
+
+    fn run(&mut self, frame: &mut Frame, resources: &FrameResources) {
+        let post_processor = &frame.state().post_processor;
+        match resources.get(LDR) {
+            Some(ldr) => post_processor.draw(&mut frame.encoder, ldr),
+            None => draw_to_frame(frame, resources, |encoder, view| {
+                post_processor.draw(encoder, view)
+            }),
+        }
+    }
+}
+
+/// Draws `LDR` into the frame with FXAA, see `draw_to_frame`.
+pub struct FxaaPass;
+
+impl RenderNode for FxaaPass {
+    fn name(&self) -> &str {
+        FXAA_NODE
+    }
+
+    fn run(&mut self, frame: &mut Frame, resources: &FrameResources) {
+        let fxaa = &frame.state().fxaa;
+        draw_to_frame(frame, resources, |encoder, view| fxaa.draw(encoder, view));
+    }
+}
+
+/// Draws `DebugView`s showing `HDR`, `DEPTH` or `SHADOW_MAP` over the
+/// frame, at the window's size.
+pub struct DebugViewPass;
+
+impl RenderNode for DebugViewPass {
+    fn name(&self) -> &str {
+        DEBUG_VIEW_NODE
+    }
+
+    fn run(&mut self, frame: &mut Frame, resources: &FrameResources) {
+        let state = frame.state();
+        // Multisampled depth can't be bound, see `insert_scene_targets`
+        let depth = resources
+            .texture(DEPTH)
+            .filter(|_| state.sample_count == 1)
+            .map(|depth| {
+                depth.create_view(&wgpu::TextureViewDescriptor {
+                    aspect: wgpu::TextureAspect::DepthOnly,
+                    ..Default::default()
+                })
+            });
+        let sources = DebugSources {
+            scene: resources.view(HDR),
+            depth: depth.as_ref(),
+            shadow_map: resources.view(SHADOW_MAP),
+        };
+        state.debug_view_renderer.draw(
+            frame.device(),
+            &state.queue,
+            &mut frame.encoder,
+            &frame.view,
+            state.size,
+            &sources,
+            state.debug_view,
+            state.camera(),
+        );
+    }
+}
+
+/// Records `draw` drawing into the frame, or into `SCALED` and then that
+/// into the frame while there's a render scale.
+fn draw_to_frame(
+    frame: &mut Frame,
+    resources: &FrameResources,
+    draw: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView),
+) {
+    match resources.get(SCALED) {
+        Some(scaled) => {
+            draw(&mut frame.encoder, scaled);
+            frame.state().upscaler.draw(&mut frame.encoder, &frame.view);
+        }
+        None => draw(&mut frame.encoder, &frame.view),
+    }
+}
@@ -0,0 +1,209 @@
+//! Rendering the scene at a fraction or a multiple of the window's size.
+//! The scene and its post-processing draw into targets of the scaled size,
+//! and the `Upscaler` draws the result into the frame. Smaller frames get
+//! stretched with bilinear filtering, bigger ones averaged down over the
+//! texels each pixel covers, which is supersampling. The HUD and the
+//! overlay are drawn at the window's size on top, so text stays sharp.
+
+use std::mem::offset_of;
+
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    pipeline::PipelineBuilder,
+    shader::ShaderError,
+    uniform::{Uniform, UniformBuffer, UniformField, WgslType},
+};
+
+pub const UPSCALE_SHADER_SOURCE: &str = include_str!("../shaders/upscale.wgsl");
+
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+pub const MAX_RENDER_SCALE: f32 = 2.0;
+/// How much `Action::IncreaseRenderScale` and `DecreaseRenderScale` change
+/// the scale by.
+pub const RENDER_SCALE_STEP: f32 = 0.25;
+
+/// `scale` within `MIN_RENDER_SCALE` and `MAX_RENDER_SCALE`, 1 for NaN.
+pub fn clamp_render_scale(scale: f32) -> f32 {
+    if scale.is_nan() {
+        return 1.0;
+    }
+    scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE)
+}
+
+/// `size` times `scale`, rounded. Each side stays between 1, as wgpu
+/// rejects empty textures, and `max_dimension`, the device's
+/// `max_texture_dimension_2d`.
+pub fn scaled_size(size: PhysicalSize<u32>, scale: f32, max_dimension: u32) -> PhysicalSize<u32> {
+    let scale_side = |side: u32| ((side as f32 * scale).round() as u32).clamp(1, max_dimension);
+    PhysicalSize::new(scale_side(size.width), scale_side(size.height))
+}
+
+/// How many samples across the `Upscaler` averages for each pixel, so
+/// every texel a pixel covers counts when the scene is bigger than the
+/// frame. 1 is a single bilinear sample.
+pub fn box_filter_taps(source: PhysicalSize<u32>, target: PhysicalSize<u32>) -> u32 {
+    let ratio = |source: u32, target: u32| source.div_ceil(target.max(1));
+    ratio(source.width, target.width)
+        .max(ratio(source.height, target.height))
+        .max(1)
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct UpscaleUniform {
+    /// Of the frame, in pixels.
+    pub target_size: [f32; 2],
+    /// See `box_filter_taps`.
+    pub taps: u32,
+    _padding: u32,
+}
+
+impl Uniform for UpscaleUniform {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "target_size",
+            offset_of!(UpscaleUniform, target_size),
+            WgslType::Vec2,
+        ),
+        UniformField::new("taps", offset_of!(UpscaleUniform, taps), WgslType::U32),
+    ];
+}
+
+/// Draws the scaled frame into the real one, see the module docs.
+pub struct Upscaler {
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    uniform_buffer: UniformBuffer<UpscaleUniform>,
+    /// `None` until `set_source`.
+    bind_group: Option<wgpu::BindGroup>,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Upscaler {
+    /// Draws into frames with `format`. The source needs the same format,
+    /// the colors are copied as they are, encoded or not.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Result<Self, ShaderError> {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("upscale_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("upscale_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Upscale Buffer",
+            &UpscaleUniform {
+                target_size: [1.0, 1.0],
+                taps: 1,
+                _padding: 0,
+            },
+        );
+        let pipeline = PipelineBuilder::with_targets(
+            "Upscale Pipeline",
+            crate::pipeline::RenderTargets {
+                color_format: format,
+                depth_format: None,
+                sample_count: 1,
+            },
+        )
+        .shader("upscale.wgsl", UPSCALE_SHADER_SOURCE)
+        // sRGB views decode on read and encode on write, other formats
+        // are taken as they are, so it never encodes itself
+        .fragment_entry_point("fs_main")
+        .bind_group_layouts(&[&layout])
+        .build(device)?;
+        Ok(Self {
+            layout,
+            sampler,
+            uniform_buffer,
+            bind_group: None,
+            pipeline,
+        })
+    }
+
+    /// Draws `source`, of `source_size`, into frames of `target_size` from
+    /// the next submit on.
+    pub fn set_source(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &wgpu::TextureView,
+        source_size: PhysicalSize<u32>,
+        target_size: PhysicalSize<u32>,
+    ) {
+        self.uniform_buffer.write(
+            queue,
+            &UpscaleUniform {
+                target_size: [target_size.width as f32, target_size.height as f32],
+                taps: box_filter_taps(source_size, target_size),
+                _padding: 0,
+            },
+        );
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("upscale_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.uniform_buffer.binding(),
+                },
+            ],
+        }));
+    }
+
+    /// Records drawing the source into `view`, nothing before `set_source`.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        if let Some(bind_group) = &self.bind_group {
+            crate::post_process::encode_fullscreen_pass(
+                encoder,
+                "Upscale Render Pass",
+                view,
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                &self.pipeline,
+                &[bind_group],
+            );
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use web_time::Instant;
+use winit::{event_loop::EventLoopWindowTarget, window::Window};
+
+use crate::{
+    adapter::AdapterSelection, device_config::DeviceConfig, error::AppError, fixed_timestep,
+    present_mode::PresentModePreference, settings::SettingsFile, shadow,
+    window_config::WindowConfig,
+};
+
+/// How long throttled windows wait between frames, about 10 updates per
+/// second.
+pub const BACKGROUND_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// What a window does while it doesn't have focus.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackgroundBehavior {
+    /// Keeps going as if it had focus.
+    KeepRunning,
+    /// Draws a frame every `BACKGROUND_FRAME_TIME` and stops the animations,
+    /// which saves a lot of power when nobody's looking.
+    #[default]
+    Throttle,
+    /// Only redraws when the platform asks for it, e.g. after a resize.
+    Pause,
+}
+
+/// When a window should draw its next frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameSchedule {
+    /// Right away.
+    Now,
+    At(Instant),
+    /// Not until something else asks for a redraw.
+    Idle,
+}
+
+impl BackgroundBehavior {
+    /// When the next frame of a window that keeps animating is due, given
+    /// when its last frame was drawn.
+    pub fn schedule(self, focused: bool, last_frame: Instant, now: Instant) -> FrameSchedule {
+        if focused {
+            return FrameSchedule::Now;
+        }
+        match self {
+            Self::KeepRunning => FrameSchedule::Now,
+            Self::Throttle => {
+                let next_frame = last_frame + BACKGROUND_FRAME_TIME;
+                if next_frame <= now {
+                    FrameSchedule::Now
+                } else {
+                    FrameSchedule::At(next_frame)
+                }
+            }
+            Self::Pause => FrameSchedule::Idle,
+        }
+    }
+
+    /// Whether the camera, light and particles keep moving without focus.
+    pub fn animates_in_background(self) -> bool {
+        self == Self::KeepRunning
+    }
+}
+
+/// How `run_with` runs the app.
+#[derive(Clone, Debug)]
+pub struct RunConfig {
+    /// Used for every window the app opens.
+    pub window: WindowConfig,
+    /// The features and limits every window's device is requested with,
+    /// and where its API trace goes.
+    pub device: DeviceConfig,
+    /// The GPU every window renders with.
+    pub adapter: AdapterSelection,
+    pub present_mode: PresentModePreference,
+    /// The MSAA sample count of every window, lowered to one the surface
+    /// supports.
+    pub sample_count: u32,
+    pub background: BackgroundBehavior,
+    /// Caps the frame rate of every window, see `State::set_max_fps`.
+    pub max_fps: Option<u32>,
+    /// How many times a second the scene of every window is stepped, see
+    /// `State::set_fixed_update_rate`.
+    pub fixed_update_rate: u32,
+    /// Lets the desktop show through the background of every window, see
+    /// `State::set_transparent`. There is no skybox then.
+    pub transparent: bool,
+    /// The width and height of every window's shadow map, see
+    /// `State::set_shadow_map_size`.
+    pub shadow_map_size: u32,
+    /// What this crate logs when `RUST_LOG` isn't set. The other crates only
+    /// log warnings and errors then.
+    pub verbosity: log::LevelFilter,
+    /// Given to the first window, which saves them with its changes when it
+    /// closes. The window and graphics settings in them only apply once
+    /// they're in the rest of the config, see `Settings::apply_to_config`.
+    pub settings: Option<SettingsFile>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            window: WindowConfig::default(),
+            device: DeviceConfig::default(),
+            adapter: AdapterSelection::default(),
+            present_mode: PresentModePreference::default(),
+            sample_count: crate::DEFAULT_SAMPLE_COUNT,
+            background: BackgroundBehavior::default(),
+            max_fps: None,
+            fixed_update_rate: fixed_timestep::DEFAULT_FIXED_UPDATE_RATE,
+            transparent: false,
+            shadow_map_size: shadow::DEFAULT_SHADOW_MAP_SIZE,
+            verbosity: log::LevelFilter::Info,
+            settings: None,
+        }
+    }
+}
+
+impl RunConfig {
+    /// Creates a window described by `window`, which is transparent if
+    /// `transparent` is set.
+    pub fn build_window(&self, event_loop: &EventLoopWindowTarget<()>) -> Result<Window, AppError> {
+        let builder = self
+            .window
+            .builder(event_loop)?
+            .with_transparent(self.transparent);
+        Ok(builder.build(event_loop)?)
+    }
+
+    /// The `RUST_LOG` style filter used when the variable isn't set.
+    pub fn default_log_filter(&self) -> String {
+        let verbosity = self.verbosity.to_string().to_lowercase();
+        format!("warn,{}={verbosity}", env!("CARGO_CRATE_NAME"))
+    }
+}
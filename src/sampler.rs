@@ -0,0 +1,235 @@
+//! Samplers described by value, and a cache so that everything asking for
+//! the same one shares a single `wgpu::Sampler`.
+
+use std::{collections::HashMap, sync::Arc};
+
+/// The most anisotropic filtering wgpu does, devices that can't do any
+/// take it as 1.
+pub const MAX_ANISOTROPY: u16 = 16;
+
+/// How a texture gets sampled, like `wgpu::SamplerDescriptor` without the
+/// label and the border color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerDesc {
+    /// Along U, V and W. `ClampToBorder` needs
+    /// `Features::ADDRESS_MODE_CLAMP_TO_BORDER`.
+    pub address_modes: [wgpu::AddressMode; 3],
+    /// When a texel covers more than a pixel.
+    pub mag_filter: wgpu::FilterMode,
+    /// When a pixel covers more than a texel.
+    pub min_filter: wgpu::FilterMode,
+    /// Between mip levels.
+    pub mipmap_filter: wgpu::FilterMode,
+    /// How many samples along the direction the texture is squashed in, 1
+    /// for none. Needs every filter to be linear.
+    pub anisotropy_clamp: u16,
+    /// For comparison samplers like the shadow map's.
+    pub compare: Option<wgpu::CompareFunction>,
+    pub lod_min_clamp: f32,
+    pub lod_max_clamp: f32,
+}
+
+impl Default for SamplerDesc {
+    /// Like `wgpu::SamplerDescriptor::default()`, see `nearest`.
+    fn default() -> Self {
+        Self::nearest()
+    }
+}
+
+impl SamplerDesc {
+    /// The texel closest to the pixel, from the closest mip level. Blocky
+    /// up close and shimmering in the distance.
+    pub fn nearest() -> Self {
+        Self {
+            address_modes: [wgpu::AddressMode::ClampToEdge; 3],
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            anisotropy_clamp: 1,
+            compare: None,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 32.0,
+        }
+    }
+
+    /// Blends the four closest texels, from the closest mip level. The
+    /// seams between the levels show.
+    pub fn bilinear() -> Self {
+        Self {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Self::nearest()
+        }
+    }
+
+    /// `bilinear` blended between the two closest mip levels. Surfaces at
+    /// a steep angle get blurry.
+    pub fn trilinear() -> Self {
+        Self {
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Self::bilinear()
+        }
+    }
+
+    /// `trilinear` with up to `samples` samples, which keeps surfaces at a
+    /// steep angle sharp.
+    pub fn anisotropic(samples: u16) -> Self {
+        Self {
+            anisotropy_clamp: samples,
+            ..Self::trilinear()
+        }
+    }
+
+    pub fn with_address_mode(self, address_mode: wgpu::AddressMode) -> Self {
+        Self {
+            address_modes: [address_mode; 3],
+            ..self
+        }
+    }
+
+    /// What wgpu accepts for a device doing up to `max_anisotropy` samples:
+    /// the anisotropy clamped to it, linear filters wherever there's any
+    /// anisotropy left, and a LOD range that starts at 0 or above and
+    /// doesn't end before it starts.
+    pub fn corrected(self, max_anisotropy: u16) -> Self {
+        let max_anisotropy = max_anisotropy.clamp(1, MAX_ANISOTROPY);
+        let mut desc = self;
+        desc.anisotropy_clamp = self.anisotropy_clamp.clamp(1, max_anisotropy);
+        if desc.anisotropy_clamp > 1 {
+            desc.mag_filter = wgpu::FilterMode::Linear;
+            desc.min_filter = wgpu::FilterMode::Linear;
+            desc.mipmap_filter = wgpu::FilterMode::Linear;
+        }
+        // `max` also turns NaN into 0
+        desc.lod_min_clamp = self.lod_min_clamp.max(0.0);
+        desc.lod_max_clamp = self.lod_max_clamp.max(desc.lod_min_clamp);
+        desc
+    }
+
+    pub fn descriptor<'a>(&self, label: Option<&'a str>) -> wgpu::SamplerDescriptor<'a> {
+        let [address_mode_u, address_mode_v, address_mode_w] = self.address_modes;
+        wgpu::SamplerDescriptor {
+            label,
+            address_mode_u,
+            address_mode_v,
+            address_mode_w,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: self.lod_min_clamp,
+            lod_max_clamp: self.lod_max_clamp,
+            compare: self.compare,
+            anisotropy_clamp: self.anisotropy_clamp,
+            border_color: None,
+        }
+    }
+
+    /// Floats aren't `Eq`, their bits are.
+    fn key(&self) -> SamplerKey {
+        SamplerKey {
+            address_modes: self.address_modes,
+            filters: [self.mag_filter, self.min_filter, self.mipmap_filter],
+            anisotropy_clamp: self.anisotropy_clamp,
+            compare: self.compare,
+            lod_clamp: [self.lod_min_clamp.to_bits(), self.lod_max_clamp.to_bits()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct SamplerKey {
+    address_modes: [wgpu::AddressMode; 3],
+    filters: [wgpu::FilterMode; 3],
+    anisotropy_clamp: u16,
+    compare: Option<wgpu::CompareFunction>,
+    lod_clamp: [u32; 2],
+}
+
+/// How many anisotropic samples `adapter`'s devices do, 1 if they can't
+/// filter anisotropically at all.
+pub fn max_anisotropy(adapter: &wgpu::Adapter) -> u16 {
+    if adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING)
+    {
+        MAX_ANISOTROPY
+    } else {
+        1
+    }
+}
+
+/// Creates each sampler once and hands out the same one for the same
+/// `SamplerDesc`. Descriptions wgpu would reject are corrected first, see
+/// `SamplerDesc::corrected`, so they never fail.
+pub struct SamplerCache {
+    max_anisotropy: u16,
+    samplers: HashMap<SamplerKey, Arc<wgpu::Sampler>>,
+}
+
+impl SamplerCache {
+    /// For a device doing up to `max_anisotropy` samples, see
+    /// `max_anisotropy`.
+    pub fn new(max_anisotropy: u16) -> Self {
+        Self {
+            max_anisotropy: max_anisotropy.clamp(1, MAX_ANISOTROPY),
+            samplers: HashMap::new(),
+        }
+    }
+
+    pub fn max_anisotropy(&self) -> u16 {
+        self.max_anisotropy
+    }
+
+    /// The sampler for `desc`, created if no one asked for it yet.
+    pub fn get(&mut self, device: &wgpu::Device, desc: &SamplerDesc) -> Arc<wgpu::Sampler> {
+        let corrected = desc.corrected(self.max_anisotropy);
+        if corrected != *desc {
+            log::warn!("Sampler {desc:?} isn't supported, using {corrected:?}");
+        }
+        Arc::clone(self.samplers.entry(corrected.key()).or_insert_with(|| {
+            Arc::new(device.create_sampler(&corrected.descriptor(Some("cached_sampler"))))
+        }))
+    }
+
+    /// How many different samplers there are.
+    pub fn len(&self) -> usize {
+        self.samplers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samplers.is_empty()
+    }
+}
+
+/// The filterings `Action::CycleTextureFiltering` goes through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureFiltering {
+    Nearest,
+    Bilinear,
+    Trilinear,
+    /// With `MAX_ANISOTROPY` samples.
+    Anisotropic,
+}
+
+impl TextureFiltering {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Nearest => Self::Bilinear,
+            Self::Bilinear => Self::Trilinear,
+            Self::Trilinear => Self::Anisotropic,
+            Self::Anisotropic => Self::Nearest,
+        }
+    }
+
+    /// Clamped to the edges like the textures' own samplers, so only the
+    /// filtering changes.
+    pub fn desc(self) -> SamplerDesc {
+        match self {
+            Self::Nearest => SamplerDesc::nearest(),
+            Self::Bilinear => SamplerDesc::bilinear(),
+            Self::Trilinear => SamplerDesc::trilinear(),
+            Self::Anisotropic => SamplerDesc::anisotropic(MAX_ANISOTROPY),
+        }
+    }
+}
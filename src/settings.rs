@@ -0,0 +1,558 @@
+//! What the app remembers between runs, kept in a TOML file like
+//!
+//! ```toml
+//! version = 1
+//!
+//! [window]
+//! width = 1280
+//! height = 720
+//! x = 100
+//! y = 80
+//! maximized = false
+//! fullscreen = false
+//!
+//! [graphics]
+//! vsync = true
+//! msaa = 4
+//!
+//! [camera]
+//! speed = 2.0
+//!
+//! [scene]
+//! clear_color = [0.1, 0.2, 0.3, 1.0]
+//!
+//! [input.actions]
+//! exit = ["logical:Escape", "gamepad:Start"]
+//!
+//! [input.axes]
+//! move_forward = ["KeyS/KeyW", "ArrowDown/ArrowUp", "gamepad:LeftStickY"]
+//! ```
+//!
+//! Reading it never fails: missing keys keep their defaults, unknown ones
+//! are ignored and values that can't be used are logged and replaced with
+//! the default. Actions and axes that aren't in the file keep their default
+//! bindings, and inputs that can't be parsed are left out.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use toml_edit::{Document, Item};
+use web_time::Instant;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    window::Window,
+};
+
+use crate::{
+    input_map::{InputBindings, UnknownInput},
+    present_mode::PresentModePreference,
+    run_config::RunConfig,
+    State,
+};
+
+/// The layout of the files written now. Files without a version are from
+/// before there was one, see `migrate`.
+pub const VERSION: i64 = 1;
+
+/// The name of the file in the app's config directory.
+pub const FILE_NAME: &str = "settings.toml";
+
+/// The camera speed of the bundled scene, in units per second.
+pub const DEFAULT_CAMERA_SPEED: f32 = 2.0;
+
+/// How long a window has to stay put before its new placement is saved.
+pub const PLACEMENT_SAVE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// The inner size of the window, `None` lets the platform pick.
+    pub window_size: Option<PhysicalSize<u32>>,
+    /// The outer position of the window, `None` lets the platform pick.
+    pub window_position: Option<PhysicalPosition<i32>>,
+    /// The size and position are for when the window isn't maximized or
+    /// fullscreen.
+    pub maximized: bool,
+    /// Borderless fullscreen.
+    pub fullscreen: bool,
+    /// Fifo if set, the lowest latency mode otherwise, like
+    /// `Action::ToggleVsync`.
+    pub vsync: bool,
+    /// The MSAA sample count, 1, 2, 4 or 8.
+    pub msaa: u32,
+    pub camera_speed: f32,
+    /// In linear space.
+    pub clear_color: wgpu::Color,
+    /// What moves the camera, exits and toggles fullscreen.
+    pub input: InputBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            window_size: None,
+            window_position: None,
+            maximized: false,
+            fullscreen: false,
+            vsync: true,
+            msaa: crate::DEFAULT_SAMPLE_COUNT,
+            camera_speed: DEFAULT_CAMERA_SPEED,
+            clear_color: crate::color::PRESETS[0],
+            input: InputBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings in `path`. They're the defaults if there is no
+    /// such file or it isn't TOML.
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                log::info!("Loading the settings from {}", path.display());
+                Self::from_toml(&text)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                log::info!("No settings in {} yet, using the defaults", path.display());
+                Self::default()
+            }
+            Err(err) => {
+                log::warn!("Can't read the settings in {}: {err}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    /// Reads the settings in a TOML document, see the module docs.
+    pub fn from_toml(text: &str) -> Self {
+        let mut doc = match text.parse::<Document>() {
+            Ok(doc) => doc,
+            Err(err) => {
+                log::warn!("The settings aren't valid TOML, using the defaults: {err}");
+                return Self::default();
+            }
+        };
+        migrate(&mut doc);
+
+        let defaults = Self::default();
+        let size = match (
+            read(&doc, "window", "width", as_size),
+            read(&doc, "window", "height", as_size),
+        ) {
+            (Some(width), Some(height)) => Some(PhysicalSize::new(width, height)),
+            _ => defaults.window_size,
+        };
+        let position = match (
+            read(&doc, "window", "x", as_coordinate),
+            read(&doc, "window", "y", as_coordinate),
+        ) {
+            (Some(x), Some(y)) => Some(PhysicalPosition::new(x, y)),
+            _ => defaults.window_position,
+        };
+
+        Self {
+            window_size: size,
+            window_position: position,
+            maximized: read(&doc, "window", "maximized", Item::as_bool)
+                .unwrap_or(defaults.maximized),
+            fullscreen: read(&doc, "window", "fullscreen", Item::as_bool)
+                .unwrap_or(defaults.fullscreen),
+            vsync: read(&doc, "graphics", "vsync", Item::as_bool).unwrap_or(defaults.vsync),
+            msaa: read(&doc, "graphics", "msaa", as_sample_count).unwrap_or(defaults.msaa),
+            camera_speed: read(&doc, "camera", "speed", as_speed).unwrap_or(defaults.camera_speed),
+            clear_color: read(&doc, "scene", "clear_color", as_color)
+                .unwrap_or(defaults.clear_color),
+            input: read_input(&doc),
+        }
+    }
+
+    /// The settings as a new TOML document.
+    pub fn to_toml(&self) -> String {
+        let mut doc = Document::new();
+        self.write_into(&mut doc);
+        doc.to_string()
+    }
+
+    /// Writes the settings to `path`, creating its directory if needed.
+    /// Comments and keys of the file that aren't settings are kept.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut doc = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| text.parse::<Document>().ok())
+            .unwrap_or_default();
+        self.write_into(&mut doc);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, doc.to_string())
+    }
+
+    fn write_into(&self, doc: &mut Document) {
+        doc["version"] = toml_edit::value(VERSION);
+
+        let window = table(doc, "window");
+        match self.window_size {
+            Some(size) => {
+                window["width"] = toml_edit::value(i64::from(size.width));
+                window["height"] = toml_edit::value(i64::from(size.height));
+            }
+            None => {
+                window.remove("width");
+                window.remove("height");
+            }
+        }
+        match self.window_position {
+            Some(position) => {
+                window["x"] = toml_edit::value(i64::from(position.x));
+                window["y"] = toml_edit::value(i64::from(position.y));
+            }
+            None => {
+                window.remove("x");
+                window.remove("y");
+            }
+        }
+        window["maximized"] = toml_edit::value(self.maximized);
+        window["fullscreen"] = toml_edit::value(self.fullscreen);
+
+        let graphics = table(doc, "graphics");
+        graphics["vsync"] = toml_edit::value(self.vsync);
+        graphics["msaa"] = toml_edit::value(i64::from(self.msaa));
+
+        table(doc, "camera")["speed"] = toml_edit::value(f64::from(self.camera_speed));
+
+        let wgpu::Color { r, g, b, a } = self.clear_color;
+        table(doc, "scene")["clear_color"] =
+            toml_edit::value(toml_edit::Array::from_iter([r, g, b, a]));
+
+        // Only the subtables get headers, there's nothing in [input] itself
+        let input = table(doc, "input");
+        input.set_implicit(true);
+        write_bindings(input, "actions", self.input.actions());
+        write_bindings(input, "axes", self.input.axes());
+    }
+
+    /// Opens windows the way the settings say.
+    pub fn apply_to_config(&self, config: &mut RunConfig) {
+        config.window.inner_size = self.window_size.map(Into::into);
+        config.window.position = self.window_position.map(Into::into);
+        config.window.maximized = self.maximized;
+        config.window.fullscreen = self.fullscreen;
+        config.present_mode = self.present_mode();
+        config.sample_count = self.msaa;
+    }
+
+    /// Sets what `apply_to_config` can't, the state has the rest already.
+    pub fn apply_to_state(&self, state: &mut State) {
+        state.camera_controller_mut().speed = self.camera_speed;
+        state.set_clear_color(self.clear_color);
+        state.set_input_bindings(self.input.clone());
+    }
+
+    /// Takes over what changed while the app ran, e.g. toggling vsync or
+    /// fullscreen.
+    pub fn update_from_state(&mut self, state: &State) {
+        if let Some(window) = state.window() {
+            self.update_placement(window);
+        }
+        self.vsync = state.present_mode() == wgpu::PresentMode::Fifo;
+        self.msaa = state.sample_count();
+        self.camera_speed = state.camera_controller().speed;
+        self.clear_color = state.clear_color();
+        self.input = state.input_bindings().clone();
+    }
+
+    /// Takes over where `window` is and how big it is, e.g. after it was
+    /// moved or resized.
+    pub fn update_placement(&mut self, window: &Window) {
+        self.fullscreen = window.fullscreen().is_some();
+        self.maximized = window.is_maximized();
+        // The normal size and position stay for leaving fullscreen or
+        // unmaximizing, those are restored as states
+        if !self.fullscreen && !self.maximized {
+            self.window_size = Some(window.inner_size());
+            // Not every platform knows, Wayland doesn't
+            if let Ok(position) = window.outer_position() {
+                self.window_position = Some(position);
+            }
+        }
+    }
+
+    pub fn present_mode(&self) -> PresentModePreference {
+        if self.vsync {
+            PresentModePreference::Vsync
+        } else {
+            PresentModePreference::Auto
+        }
+    }
+}
+
+/// The settings of the first window, read when it opens and written back
+/// when it closes.
+#[derive(Clone, Debug)]
+pub struct SettingsFile {
+    pub path: PathBuf,
+    pub settings: Settings,
+}
+
+impl SettingsFile {
+    pub fn load(path: PathBuf) -> Self {
+        let settings = Settings::load(&path);
+        Self { path, settings }
+    }
+
+    /// Saves the settings with the changes made in `state`, see `save`.
+    pub fn save_from_state(&mut self, state: &State) {
+        self.settings.update_from_state(state);
+        self.save();
+    }
+
+    /// Saves the settings, logging whether it worked.
+    pub fn save(&self) {
+        match self.settings.save(&self.path) {
+            Ok(()) => log::info!("Saved the settings to {}", self.path.display()),
+            Err(err) => log::warn!("Can't save the settings to {}: {err}", self.path.display()),
+        }
+    }
+}
+
+/// Holds something off until the changes stopped for `delay`, e.g. saving
+/// the placement at the end of a live resize instead of on each of its
+/// events.
+#[derive(Clone, Debug)]
+pub struct Debounce {
+    delay: Duration,
+    last_change: Option<Instant>,
+}
+
+impl Debounce {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            last_change: None,
+        }
+    }
+
+    pub fn changed(&mut self, now: Instant) {
+        self.last_change = Some(now);
+    }
+
+    /// When the changes so far will have settled, `None` without changes.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.last_change.map(|last_change| last_change + self.delay)
+    }
+
+    /// Whether the changes settled by `now`. Only `true` once for them.
+    pub fn settled(&mut self, now: Instant) -> bool {
+        let settled = self.deadline().is_some_and(|deadline| deadline <= now);
+        if settled {
+            self.last_change = None;
+        }
+        settled
+    }
+}
+
+/// Where the settings go on this platform, e.g. `~/.config/wgpu-learning/`
+/// on Linux. `None` on the web, or if the home directory is unknown.
+pub fn default_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")).join(FILE_NAME))
+}
+
+#[cfg(windows)]
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| Path::new(&home).join("Library/Application Support"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn config_dir() -> Option<PathBuf> {
+    // Relative paths in XDG_CONFIG_HOME are to be ignored
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+}
+
+#[cfg(not(any(windows, unix)))]
+fn config_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Brings documents written by older versions up to `VERSION`.
+fn migrate(doc: &mut Document) {
+    match doc.get("version").and_then(Item::as_integer) {
+        Some(VERSION) => {}
+        // Files from before the version field have the same keys
+        None => {
+            log::info!("The settings have no version, treating them as version {VERSION}");
+            doc["version"] = toml_edit::value(VERSION);
+        }
+        Some(version) => log::warn!(
+            "The settings are version {version}, reading them as version {VERSION} anyway"
+        ),
+    }
+}
+
+/// The `key` of the `section` table, `None` if it's missing or `parse`
+/// can't use it. Only the latter gets logged.
+fn read<T>(
+    doc: &Document,
+    section: &str,
+    key: &str,
+    parse: impl FnOnce(&Item) -> Option<T>,
+) -> Option<T> {
+    let item = doc.get(section)?.get(key)?;
+    let value = parse(item);
+    if value.is_none() {
+        log::warn!(
+            "Ignoring {section}.{key} = {} in the settings, using the default",
+            item.to_string().trim()
+        );
+    }
+    value
+}
+
+/// The table `key` of `doc`, replacing whatever else is there.
+fn table<'a>(doc: &'a mut Document, key: &str) -> &'a mut toml_edit::Table {
+    if !doc.get(key).is_some_and(Item::is_table) {
+        doc[key] = toml_edit::table();
+    }
+    doc[key].as_table_mut().expect("just made it a table")
+}
+
+/// The bindings in `[input.actions]` and `[input.axes]`, over the defaults.
+fn read_input(doc: &Document) -> InputBindings {
+    let mut bindings = InputBindings::default();
+    for (action, inputs) in read_bindings(doc, "actions") {
+        bindings.set_action(&action, inputs);
+    }
+    for (axis, inputs) in read_bindings(doc, "axes") {
+        bindings.set_axis(&axis, inputs);
+    }
+    bindings
+}
+
+/// The lists of inputs in the `kind` table of `[input]`, by name.
+fn read_bindings<T: FromStr<Err = UnknownInput>>(
+    doc: &Document,
+    kind: &str,
+) -> Vec<(String, Vec<T>)> {
+    let Some(table) = doc
+        .get("input")
+        .and_then(|input| input.get(kind))
+        .and_then(Item::as_table_like)
+    else {
+        return Vec::new();
+    };
+    table
+        .iter()
+        .filter_map(|(name, item)| {
+            let Some(array) = item.as_array() else {
+                log::warn!(
+                    "Ignoring input.{kind}.{name} = {} in the settings, it's not a list",
+                    item.to_string().trim()
+                );
+                return None;
+            };
+            let inputs = array
+                .iter()
+                .filter_map(|value| match value.as_str().map(str::parse) {
+                    Some(Ok(input)) => Some(input),
+                    Some(Err(err)) => {
+                        log::warn!("Ignoring the {err} of input.{kind}.{name} in the settings");
+                        None
+                    }
+                    None => {
+                        log::warn!(
+                            "Ignoring {} in input.{kind}.{name} of the settings, inputs are strings",
+                            value.to_string().trim()
+                        );
+                        None
+                    }
+                })
+                .collect();
+            Some((name.to_owned(), inputs))
+        })
+        .collect()
+}
+
+/// Writes each binding as a list of input names into the `kind` table of
+/// `input`, dropping the ones that aren't bound anymore.
+fn write_bindings<'a, T: fmt::Display + 'a>(
+    input: &mut toml_edit::Table,
+    kind: &str,
+    bindings: impl Iterator<Item = (&'a str, &'a [T])>,
+) {
+    if !input.get(kind).is_some_and(Item::is_table) {
+        input[kind] = toml_edit::table();
+    }
+    let table = input[kind].as_table_mut().expect("just made it a table");
+    let bindings: Vec<_> = bindings.collect();
+    let unbound: Vec<_> = table
+        .iter()
+        .map(|(name, _)| name.to_owned())
+        .filter(|name| !bindings.iter().any(|(bound, _)| bound == name))
+        .collect();
+    for name in unbound {
+        table.remove(&name);
+    }
+    for (name, inputs) in bindings {
+        table[name] = toml_edit::value(toml_edit::Array::from_iter(
+            inputs.iter().map(ToString::to_string),
+        ));
+    }
+}
+
+fn as_number(item: &Item) -> Option<f64> {
+    item.as_float()
+        .or_else(|| item.as_integer().map(|value| value as f64))
+}
+
+fn as_size(item: &Item) -> Option<u32> {
+    item.as_integer()
+        .and_then(|size| u32::try_from(size).ok())
+        .filter(|&size| size > 0)
+}
+
+fn as_coordinate(item: &Item) -> Option<i32> {
+    item.as_integer()
+        .and_then(|value| i32::try_from(value).ok())
+}
+
+fn as_sample_count(item: &Item) -> Option<u32> {
+    match item.as_integer()? {
+        count @ (1 | 2 | 4 | 8) => Some(count as u32),
+        _ => None,
+    }
+}
+
+fn as_speed(item: &Item) -> Option<f32> {
+    as_number(item)
+        .filter(|speed| speed.is_finite() && *speed >= 0.0)
+        .map(|speed| speed as f32)
+}
+
+/// Three or four numbers from 0 up, the alpha is 1 when there are three.
+fn as_color(item: &Item) -> Option<wgpu::Color> {
+    let array = item.as_array()?;
+    let channels = array
+        .iter()
+        .map(|value| {
+            value
+                .as_float()
+                .or_else(|| value.as_integer().map(|value| value as f64))
+                .filter(|channel| channel.is_finite() && *channel >= 0.0)
+        })
+        .collect::<Option<Vec<f64>>>()?;
+    match channels[..] {
+        [r, g, b] => Some(wgpu::Color { r, g, b, a: 1.0 }),
+        [r, g, b, a] => Some(wgpu::Color { r, g, b, a }),
+        _ => None,
+    }
+}
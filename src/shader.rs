@@ -0,0 +1,157 @@
+use std::{error::Error as _, fmt::Write as _};
+
+use thiserror::Error;
+
+/// Why a shader failed to compile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderErrorKind {
+    /// The source isn't valid WGSL.
+    Parse,
+    /// The source parses, but breaks one of the rules of the language, like
+    /// a type mismatch.
+    Validation,
+    /// The shader is fine on its own, but wgpu rejected the pipeline using
+    /// it, e.g. because its vertex inputs don't match the vertex buffers.
+    Pipeline,
+}
+
+/// A shader that doesn't compile. Its `Display` is a report pointing at the
+/// offending source, e.g.
+///
+/// ```text
+/// error: expected ';', found '}'
+///   --> shader.wgsl:12:5
+///    |
+/// 12 |     return x
+///    |     ^^^^^^ expected ';'
+/// ```
+#[derive(Clone, Debug, Error)]
+#[error("{report}")]
+pub struct ShaderError {
+    pub kind: ShaderErrorKind,
+    /// The file name the source came from, only used in the report.
+    pub file: String,
+    pub message: String,
+    /// `None` when naga can't tell where in the source the error is.
+    pub location: Option<naga::SourceLocation>,
+    report: String,
+}
+
+impl ShaderError {
+    fn new(
+        kind: ShaderErrorKind,
+        file: &str,
+        message: String,
+        label: Option<(naga::Span, &str)>,
+        source: &str,
+    ) -> Self {
+        let label = label.filter(|(span, _)| span.is_defined());
+        let location = label.map(|(span, _)| span.location(source));
+
+        let mut report = format!("error: {message}");
+        match location {
+            Some(location) => {
+                let line = source
+                    .lines()
+                    .nth(location.line_number as usize - 1)
+                    .unwrap_or_default();
+                let number = location.line_number.to_string();
+                let gutter = " ".repeat(number.len());
+                // The caret stops at the end of the line for spans covering
+                // several lines
+                let column = location.line_position as usize - 1;
+                let remaining = line.chars().count().saturating_sub(column);
+                let span_length = source
+                    .get(location.offset as usize..(location.offset + location.length) as usize)
+                    .map_or(1, |text| text.chars().count());
+                let carets = "^".repeat(span_length.clamp(1, remaining.max(1)));
+                let label = label.map_or("", |(_, label)| label);
+
+                let _ = write!(
+                    report,
+                    "\n{gutter}--> {file}:{}:{}\n{gutter} |\n{number} | {line}\n{gutter} | {}{carets} {label}",
+                    location.line_number,
+                    location.line_position,
+                    " ".repeat(column),
+                );
+                report.truncate(report.trim_end().len());
+            }
+            None => {
+                let _ = write!(report, "\n  --> {file}");
+            }
+        }
+
+        Self {
+            kind,
+            file: file.to_owned(),
+            message,
+            location,
+            report,
+        }
+    }
+}
+
+impl ShaderError {
+    /// An error wgpu reported while creating a pipeline from `file`.
+    pub fn pipeline(file: &str, message: String) -> Self {
+        Self::new(ShaderErrorKind::Pipeline, file, message, None, "")
+    }
+}
+
+/// Parses and validates a WGSL shader, the same way wgpu does when creating
+/// a shader module. `file` names the source in the error.
+pub fn validate(source: &str, file: &str) -> Result<naga::Module, ShaderError> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|err| {
+        ShaderError::new(
+            ShaderErrorKind::Parse,
+            file,
+            err.message().to_owned(),
+            err.labels().next(),
+            source,
+        )
+    })?;
+
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|err| {
+        // The top level error only says which function is invalid, the
+        // reason is further down the chain
+        let mut message = err.to_string();
+        let mut cause = err.source();
+        while let Some(err) = cause {
+            let _ = write!(message, ": {err}");
+            cause = err.source();
+        }
+        // The spans go from the function down to the expression at fault
+        let label = err
+            .spans()
+            .last()
+            .map(|(span, label)| (*span, label.as_str()));
+        ShaderError::new(ShaderErrorKind::Validation, file, message, label, source)
+    })?;
+
+    Ok(module)
+}
+
+/// Creates a shader module, checking the source first. wgpu would only
+/// report an invalid shader when it gets used to create a pipeline, and
+/// then by panicking.
+pub fn create_shader_module(
+    device: &wgpu::Device,
+    label: &str,
+    file: &str,
+    source: &str,
+) -> Result<wgpu::ShaderModule, ShaderError> {
+    if let Err(err) = validate(source, file) {
+        log::error!("{err}");
+        return Err(err);
+    }
+
+    Ok(device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    }))
+}
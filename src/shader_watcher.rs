@@ -0,0 +1,49 @@
+use std::{
+    path::Path,
+    sync::mpsc::{self, Receiver},
+};
+
+use notify::{RecursiveMode, Watcher};
+
+/// Watches a directory for changes to WGSL files.
+pub struct ShaderWatcher {
+    // Stops watching when dropped
+    _watcher: notify::RecommendedWatcher,
+    receiver: Receiver<notify::Result<notify::Event>>,
+}
+
+impl ShaderWatcher {
+    pub fn new(dir: &Path) -> notify::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            // The receiver is gone if the watcher was dropped, which is fine
+            let _ = sender.send(event);
+        })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            receiver,
+        })
+    }
+
+    /// Returns `true` if a shader was written to or created since the last
+    /// call. Editors often save a file in several steps, those all count as
+    /// one change.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.receiver.try_iter() {
+            match event {
+                Ok(event) => {
+                    let is_shader = event
+                        .paths
+                        .iter()
+                        .any(|path| path.extension().is_some_and(|ext| ext == "wgsl"));
+                    changed |= is_shader && (event.kind.is_modify() || event.kind.is_create());
+                }
+                Err(err) => log::warn!("Shader watcher error: {err}"),
+            }
+        }
+        changed
+    }
+}
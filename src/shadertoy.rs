@@ -0,0 +1,387 @@
+//! A playground for fragment shaders, like ShaderToy. `run_shadertoy` draws
+//! a WGSL file over the whole window and reloads it whenever it's saved.
+//!
+//! The file only needs the color of each pixel, `frag_coord` is in pixels
+//! from the top left:
+//!
+//! ```wgsl
+//! fn main_image(frag_coord: vec2<f32>) -> vec4<f32> {
+//!     let uv = frag_coord / globals.resolution;
+//!     return vec4<f32>(uv, 0.5 + 0.5 * sin(globals.time), 1.0);
+//! }
+//! ```
+//!
+//! `globals` are the ones from the `globals` module. They, the vertex shader
+//! and the entry points come from `PRELUDE`, which goes after the file so
+//! errors point at the lines of the file. Colors are linear.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use winit::dpi::PhysicalSize;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    app::{App, GpuContext},
+    error::AppError,
+    key_bindings::{Action, KeyBindings},
+    run_config::RunConfig,
+    shader_watcher::ShaderWatcher,
+};
+use crate::{
+    globals::{self, Globals, GlobalsUniform},
+    pipeline::{PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+    uniform::UniformBuffer,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::ModifiersState,
+};
+
+/// What every playground shader gets on top of its `main_image`.
+pub const PRELUDE: &str = r#"
+// Added after the shader by the playground
+
+struct Globals {
+    resolution: vec2<f32>,
+    cursor: vec2<f32>,
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+};
+@group(0) @binding(1)
+var<uniform> globals: Globals;
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the screen
+    let uv = vec2<f32>(f32((id << 1u) & 2u), f32(id & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn playground_linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return main_image(position.xy);
+}
+
+@fragment
+fn fs_main_encode_srgb(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = main_image(position.xy);
+    return vec4<f32>(
+        playground_linear_to_srgb(color.r),
+        playground_linear_to_srgb(color.g),
+        playground_linear_to_srgb(color.b),
+        color.a,
+    );
+}
+"#;
+
+/// The whole shader for a playground `source`, with `PRELUDE` after it.
+pub fn assemble(source: &str) -> String {
+    format!("{source}\n{PRELUDE}")
+}
+
+/// What the window title says for the shader in `file`, with the first
+/// line of the `error` if it doesn't compile.
+pub fn title(file: &str, error: Option<&ShaderError>) -> String {
+    match error {
+        Some(error) => {
+            let line = error
+                .location
+                .map(|location| format!(" at line {}", location.line_number))
+                .unwrap_or_default();
+            format!("{file}: error{line}: {}", error.message)
+        }
+        None => format!("{file} - shadertoy"),
+    }
+}
+
+/// Draws a fullscreen triangle with the latest playground shader that
+/// compiled.
+pub struct ShaderToy {
+    color_format: wgpu::TextureFormat,
+    globals: Globals,
+    globals_buffer: UniformBuffer<GlobalsUniform>,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    /// `None` until a shader compiled.
+    pipeline: Option<wgpu::RenderPipeline>,
+    error: Option<ShaderError>,
+}
+
+impl ShaderToy {
+    /// Draws nothing but black until `load` succeeds.
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+    ) -> Self {
+        let globals = Globals::new(size);
+        let globals_buffer = UniformBuffer::new(device, "Shadertoy Globals", globals.uniform());
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadertoy Bind Group Layout"),
+            entries: &[globals::layout_entry()],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadertoy Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: globals::BINDING,
+                resource: globals_buffer.binding(),
+            }],
+        });
+
+        Self {
+            color_format,
+            globals,
+            globals_buffer,
+            bind_group_layout,
+            bind_group,
+            pipeline: None,
+            error: None,
+        }
+    }
+
+    /// Compiles the playground `source`, `file` names it in the errors. If
+    /// it doesn't compile, the last shader that did stays on screen.
+    pub fn load(
+        &mut self,
+        device: &wgpu::Device,
+        file: &str,
+        source: &str,
+    ) -> Result<(), ShaderError> {
+        let source = assemble(source);
+        let layouts = [&self.bind_group_layout];
+        let result = PipelineBuilder::with_targets(
+            "Shadertoy Pipeline",
+            RenderTargets {
+                color_format: self.color_format,
+                depth_format: None,
+                sample_count: 1,
+            },
+        )
+        .shader(file, &source)
+        .bind_group_layouts(&layouts)
+        .cull_mode(None)
+        .build(device);
+
+        match result {
+            Ok(pipeline) => {
+                self.pipeline = Some(pipeline);
+                self.error = None;
+                Ok(())
+            }
+            Err(err) => {
+                self.error = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// Whether a shader compiled at some point.
+    pub fn has_shader(&self) -> bool {
+        self.pipeline.is_some()
+    }
+
+    /// Why the last `load` failed, `None` if it didn't.
+    pub fn error(&self) -> Option<&ShaderError> {
+        self.error.as_ref()
+    }
+
+    pub fn globals(&self) -> &Globals {
+        &self.globals
+    }
+
+    pub fn globals_mut(&mut self) -> &mut Globals {
+        &mut self.globals
+    }
+
+    /// Moves the time on by `dt` and uploads the globals.
+    pub fn update(&mut self, queue: &wgpu::Queue, dt: Duration) {
+        self.globals.advance(dt);
+        self.globals_buffer.write(queue, self.globals.uniform());
+    }
+
+    /// Records covering `view` with the shader.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadertoy Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        if let Some(pipeline) = &self.pipeline {
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+/// Runs the playground shader in `path`, see the module docs. Space pauses
+/// the time.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_shadertoy(path: &Path) -> Result<(), AppError> {
+    run_shadertoy_with(RunConfig::default(), path).await
+}
+
+/// Like `run_shadertoy`, in a window opened with `config`.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_shadertoy_with(config: RunConfig, path: &Path) -> Result<(), AppError> {
+    let source = read_shader(path)?;
+    let path = path.to_owned();
+    crate::app::run_app_with_init(config, move |ctx| Ok(ShaderToyApp::new(ctx, path, &source)))
+        .await
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_shader(path: &Path) -> Result<String, AppError> {
+    std::fs::read_to_string(path).map_err(|source| AppError::ReadShader {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+/// Runs a `ShaderToy` for a file, reloading it when it changes.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ShaderToyApp {
+    path: PathBuf,
+    /// How the file is called in the errors and the title.
+    file: String,
+    toy: ShaderToy,
+    watcher: Option<ShaderWatcher>,
+    key_bindings: KeyBindings,
+    modifiers: ModifiersState,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ShaderToyApp {
+    /// Starts with `source`, the contents of `path`. A shader that doesn't
+    /// compile shows its error in the title until it's fixed.
+    pub fn new(ctx: &GpuContext, path: PathBuf, source: &str) -> Self {
+        let file = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        // A file name alone is in the working directory
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let watcher = ShaderWatcher::new(dir)
+            .map_err(|err| log::warn!("Not watching {} for changes: {err}", path.display()))
+            .ok();
+
+        let mut app = Self {
+            toy: ShaderToy::new(ctx.device(), ctx.render_format(), ctx.size()),
+            path,
+            file,
+            watcher,
+            key_bindings: KeyBindings::default(),
+            modifiers: ModifiersState::empty(),
+        };
+        app.load(ctx, source);
+        app
+    }
+
+    pub fn shader_toy(&self) -> &ShaderToy {
+        &self.toy
+    }
+
+    fn load(&mut self, ctx: &GpuContext, source: &str) {
+        // The error was logged with its line while compiling
+        if self.toy.load(ctx.device(), &self.file, source).is_ok() {
+            log::info!("Loaded {}", self.path.display());
+        }
+        ctx.window().set_title(&title(&self.file, self.toy.error()));
+    }
+
+    fn reload(&mut self, ctx: &GpuContext) {
+        match read_shader(&self.path) {
+            Ok(source) => self.load(ctx, &source),
+            // Editors may replace the file instead of writing it, the next
+            // change reloads it
+            Err(err) => log::warn!("{err}"),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl App for ShaderToyApp {
+    /// Panics, the app needs a file, see `run_shadertoy`.
+    fn init(_ctx: &GpuContext) -> Self {
+        panic!("ShaderToyApp needs a shader file, run it with run_shadertoy");
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.toy.globals_mut().set_cursor(*position);
+                true
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                false
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key,
+                        logical_key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                let action = self
+                    .key_bindings
+                    .action(physical_key, logical_key, self.modifiers);
+                if action != Some(Action::TogglePause) {
+                    return false;
+                }
+                let globals = self.toy.globals_mut();
+                globals.set_paused(!globals.is_paused());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn resize(&mut self, _ctx: &GpuContext, size: PhysicalSize<u32>) {
+        self.toy.globals_mut().set_resolution(size);
+    }
+
+    fn update(&mut self, ctx: &GpuContext, dt: Duration) {
+        if self.watcher.as_ref().is_some_and(ShaderWatcher::changed) {
+            self.reload(ctx);
+        }
+        self.toy.update(ctx.queue(), dt);
+    }
+
+    fn render(
+        &mut self,
+        _ctx: &GpuContext,
+        view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        self.toy.render(encoder, view);
+    }
+}
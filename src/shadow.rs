@@ -0,0 +1,317 @@
+//! Shadows of the light. The scene's depth is drawn from the light's point of
+//! view into the shadow map first, then the main shader compares each
+//! fragment's distance to the light with it.
+
+use std::mem::offset_of;
+
+use glam::{Mat4, Vec3};
+
+use crate::{
+    instance::InstanceRaw,
+    model::{Model, ModelVertex},
+    pipeline::PipelineBuilder,
+    render_graph::ResourceDesc,
+    shader::ShaderError,
+    uniform::{Uniform, UniformBuffer, UniformField, WgslType},
+};
+
+pub const SHADOW_SHADER_SOURCE: &str = include_str!("../shaders/shadow.wgsl");
+
+pub const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// The width and height of the shadow map in texels. Bigger maps have
+/// sharper shadows but take longer to draw.
+pub const DEFAULT_SHADOW_MAP_SIZE: u32 = 2048;
+
+/// How far the scene reaches from the origin. Everything within casts and
+/// receives shadows.
+pub const SCENE_RADIUS: f32 = 8.0;
+
+/// How the shadow pass offsets the depth it writes. Without it surfaces
+/// facing the light shadow themselves in stripes ("shadow acne"), and the
+/// more they are tilted away from the light the more offset they need.
+pub const SHADOW_DEPTH_BIAS: wgpu::DepthBiasState = wgpu::DepthBiasState {
+    constant: 4,
+    slope_scale: 3.0,
+    clamp: 0.0,
+};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowUniform {
+    pub light_view_proj: [[f32; 4]; 4],
+    /// 0 lights every fragment fully, WGSL has no bools in uniforms.
+    pub enabled: u32,
+    _padding: [u32; 3],
+}
+
+impl Uniform for ShadowUniform {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "light_view_proj",
+            offset_of!(ShadowUniform, light_view_proj),
+            WgslType::Mat4,
+        ),
+        UniformField::new("enabled", offset_of!(ShadowUniform, enabled), WgslType::U32),
+    ];
+}
+
+impl ShadowUniform {
+    pub fn new(light_position: Vec3, enabled: bool) -> Self {
+        Self {
+            light_view_proj: light_view_proj(light_position).to_cols_array_2d(),
+            enabled: enabled.into(),
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// The view-projection of the light, treated as a directional light shining
+/// from `light_position` towards the origin. The orthographic projection
+/// covers everything within `SCENE_RADIUS`.
+pub fn light_view_proj(light_position: Vec3) -> Mat4 {
+    let direction = light_position.try_normalize().unwrap_or(Vec3::Y);
+    // look_at breaks down when looking straight along the up vector
+    let up = if direction.abs().abs_diff_eq(Vec3::Y, 1e-3) {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    let view = Mat4::look_at_rh(direction * SCENE_RADIUS * 2.0, Vec3::ZERO, up);
+    // Unlike the camera's perspective_rh_gl, this one already maps depth to
+    // 0..1 like wgpu expects
+    let proj = Mat4::orthographic_rh(
+        -SCENE_RADIUS,
+        SCENE_RADIUS,
+        -SCENE_RADIUS,
+        SCENE_RADIUS,
+        SCENE_RADIUS,
+        SCENE_RADIUS * 3.0,
+    );
+    proj * view
+}
+
+/// The layout of the bind group the main shader samples the shadow map
+/// through: the `ShadowUniform`, the shadow map and its comparison sampler.
+pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("shadow_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Depth,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                count: None,
+            },
+        ],
+    })
+}
+
+/// The `render_graph::SHADOW_MAP` resource of a `size` by `size` shadow
+/// map.
+pub fn resource_desc(size: u32) -> ResourceDesc {
+    ResourceDesc::fixed(
+        size,
+        size,
+        SHADOW_FORMAT,
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    )
+}
+
+/// The pipeline drawing the shadow map and what the main shader needs to
+/// sample it. The map itself is a frame resource, see `resource_desc`.
+pub struct ShadowMap {
+    uniform: ShadowUniform,
+    buffer: UniformBuffer<ShadowUniform>,
+    sampler: wgpu::Sampler,
+    /// Group 0 of the shadow pass, which can't bind the map it draws into.
+    pass_bind_group: wgpu::BindGroup,
+    /// Bound by the main pipeline, with the layout of
+    /// `create_bind_group_layout`.
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl ShadowMap {
+    /// Samples the shadow map `view`. `layout` is from
+    /// `create_bind_group_layout`.
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        light_position: Vec3,
+    ) -> Result<Self, ShaderError> {
+        let uniform = ShadowUniform::new(light_position, true);
+        let buffer = UniformBuffer::new(device, "Shadow Buffer", &uniform);
+
+        let pass_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("shadow_pass_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let pass_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shadow_pass_bind_group"),
+            layout: &pass_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.binding(),
+            }],
+        });
+
+        // Only the vertex positions matter, the model's vertex layout is
+        // reused so the same buffers can be drawn
+        let pipeline = PipelineBuilder::depth_only("Shadow Pipeline", SHADOW_FORMAT)
+            .shader("shadow.wgsl", SHADOW_SHADER_SOURCE)
+            .bind_group_layouts(&[&pass_layout])
+            .vertex_buffer(ModelVertex::desc())
+            .vertex_buffer(InstanceRaw::desc())
+            .depth_bias(SHADOW_DEPTH_BIAS)
+            .build(device)?;
+
+        // Linear filtering compares the 4 nearest texels and blends the
+        // results, softening the edges on top of the shader's filtering
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let bind_group = create_bind_group(device, layout, &buffer, view, &sampler);
+        Ok(Self {
+            uniform,
+            buffer,
+            sampler,
+            pass_bind_group,
+            bind_group,
+            pipeline,
+        })
+    }
+
+    /// Samples the shadow map `view` from now on, after it was recreated.
+    /// `layout` is the one it was created with.
+    pub fn set_view(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+    ) {
+        self.bind_group = create_bind_group(device, layout, &self.buffer, view, &self.sampler);
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.uniform.enabled != 0
+    }
+
+    /// Disabled shadow maps aren't drawn and light every fragment.
+    pub fn set_enabled(&mut self, queue: &wgpu::Queue, enabled: bool) {
+        self.uniform.enabled = enabled.into();
+        self.buffer.write(queue, &self.uniform);
+    }
+
+    /// Follows the light to `light_position`.
+    pub fn update(&mut self, queue: &wgpu::Queue, light_position: Vec3) {
+        self.uniform = ShadowUniform::new(light_position, self.enabled());
+        self.buffer.write(queue, &self.uniform);
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Records the shadow pass drawing `model`'s instances into `view`, the
+    /// shadow map, unless shadows are disabled.
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        model: &Model,
+        instance_buffer: &wgpu::Buffer,
+        instance_count: u32,
+    ) {
+        if !self.enabled() {
+            return;
+        }
+
+        let mut shadow_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadow Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        shadow_pass.set_pipeline(&self.pipeline);
+        shadow_pass.set_bind_group(0, &self.pass_bind_group, &[]);
+        shadow_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        for mesh in &model.meshes {
+            mesh.draw(&mut shadow_pass, 0..instance_count);
+        }
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &UniformBuffer<ShadowUniform>,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("shadow_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
@@ -0,0 +1,142 @@
+use crate::{
+    camera::CameraBinding,
+    pipeline::{PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+    texture::Texture,
+};
+
+pub const SKYBOX_SHADER_SOURCE: &str = include_str!("../shaders/skybox.wgsl");
+
+/// A cubemap drawn behind everything else in the scene.
+pub struct Skybox {
+    pub texture: Texture,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Skybox {
+    /// `camera_bind_group_layout` is the layout shared with the other
+    /// pipelines, the skybox only uses it to get its view direction.
+    pub fn new(
+        device: &wgpu::Device,
+        targets: RenderTargets,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        texture: Texture,
+        shader_source: &str,
+    ) -> Result<Self, ShaderError> {
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("skybox_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("skybox_bind_group"),
+            layout: &texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+        });
+
+        let render_pipeline = create_pipeline(
+            device,
+            targets,
+            camera_bind_group_layout,
+            &texture_bind_group_layout,
+            shader_source,
+        )?;
+
+        Ok(Self {
+            texture,
+            texture_bind_group_layout,
+            bind_group,
+            render_pipeline,
+        })
+    }
+
+    /// Rebuilds the pipeline after the render targets or the shader changed.
+    pub fn recreate_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        targets: RenderTargets,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        shader_source: &str,
+    ) -> Result<(), ShaderError> {
+        self.render_pipeline =
+            self.create_pipeline(device, targets, camera_bind_group_layout, shader_source)?;
+        Ok(())
+    }
+
+    /// Builds a pipeline for this skybox without using it yet, see
+    /// `set_pipeline`.
+    pub fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        targets: RenderTargets,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        shader_source: &str,
+    ) -> Result<wgpu::RenderPipeline, ShaderError> {
+        create_pipeline(
+            device,
+            targets,
+            camera_bind_group_layout,
+            &self.texture_bind_group_layout,
+            shader_source,
+        )
+    }
+
+    pub fn set_pipeline(&mut self, render_pipeline: wgpu::RenderPipeline) {
+        self.render_pipeline = render_pipeline;
+    }
+
+    /// Draws the skybox. Draw it after the opaque geometry so the depth test
+    /// discards the hidden fragments early.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera: CameraBinding<'a>) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        camera.bind(render_pass, 0);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    targets: RenderTargets,
+    camera_bind_group_layout: &wgpu::BindGroupLayout,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+    shader_source: &str,
+) -> Result<wgpu::RenderPipeline, ShaderError> {
+    PipelineBuilder::with_targets("Skybox Pipeline", targets)
+        .shader("skybox.wgsl", shader_source)
+        .bind_group_layouts(&[camera_bind_group_layout, texture_bind_group_layout])
+        // Seen from the inside
+        .cull_mode(None)
+        // The skybox sits at the far plane: it must pass where the depth
+        // buffer was only cleared, and never hide anything.
+        .depth_test(wgpu::CompareFunction::LessEqual, false)
+        .build(device)
+}
@@ -0,0 +1,126 @@
+//! Drawing the scene from more than one camera into the same frame, each
+//! into its own part of it. The parts are set with `set_viewport` and
+//! `set_scissor_rect`, and each view binds its own camera, see
+//! `camera::CameraBinding`.
+
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+/// How many views the frame can be split into.
+pub const MAX_VIEWS: usize = 2;
+
+/// How the frame is shared between the views.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SplitMode {
+    /// Only the first view, over the whole frame.
+    #[default]
+    Single,
+    /// Split by a horizontal line, the first view at the top.
+    Horizontal,
+    /// Split by a vertical line, the first view on the left.
+    Vertical,
+}
+
+impl SplitMode {
+    pub fn view_count(self) -> usize {
+        match self {
+            Self::Single => 1,
+            Self::Horizontal | Self::Vertical => 2,
+        }
+    }
+
+    /// The mode after this one, back to `Single` after the last.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Single => Self::Horizontal,
+            Self::Horizontal => Self::Vertical,
+            Self::Vertical => Self::Single,
+        }
+    }
+
+    /// The parts of a frame of `size` each view draws into, in order. They
+    /// cover the whole frame without overlapping, with odd sizes the second
+    /// view gets the extra pixel.
+    pub fn viewports(self, size: PhysicalSize<u32>) -> Vec<Viewport> {
+        let whole = Viewport {
+            x: 0,
+            y: 0,
+            width: size.width,
+            height: size.height,
+        };
+        match self {
+            Self::Single => vec![whole],
+            Self::Horizontal => {
+                let top = size.height / 2;
+                vec![
+                    Viewport {
+                        height: top,
+                        ..whole
+                    },
+                    Viewport {
+                        y: top,
+                        height: size.height - top,
+                        ..whole
+                    },
+                ]
+            }
+            Self::Vertical => {
+                let left = size.width / 2;
+                vec![
+                    Viewport {
+                        width: left,
+                        ..whole
+                    },
+                    Viewport {
+                        x: left,
+                        width: size.width - left,
+                        ..whole
+                    },
+                ]
+            }
+        }
+    }
+}
+
+/// A part of the frame, in physical pixels from the top left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    /// A frame 1 pixel wide has nothing left for one of its halves.
+    pub fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// What the camera of the view needs as `Camera::aspect`.
+    pub fn aspect(self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    pub fn contains(self, position: PhysicalPosition<f64>) -> bool {
+        let (x, y) = (f64::from(self.x), f64::from(self.y));
+        position.x >= x
+            && position.y >= y
+            && position.x < x + f64::from(self.width)
+            && position.y < y + f64::from(self.height)
+    }
+
+    /// Draws only into this part of the frame from now on, with the whole
+    /// depth range. The scissor rect keeps the views from drawing into each
+    /// other.
+    pub fn apply(self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_viewport(
+            self.x as f32,
+            self.y as f32,
+            self.width as f32,
+            self.height as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_scissor_rect(self.x, self.y, self.width, self.height);
+    }
+}
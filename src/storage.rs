@@ -0,0 +1,239 @@
+//! Arrays the shaders read, in a storage buffer where the device allows it
+//! and in a uniform array where it doesn't. WebGL2 and other downlevel
+//! targets have no storage buffers outside compute shaders, or none at all.
+//!
+//! The shaders declare the arrays as storage buffers, and
+//! `ArrayBinding::shader_source` swaps that for a fixed-size uniform array
+//! when falling back.
+
+use std::{borrow::Cow, marker::PhantomData};
+
+use crate::{gpu_memory::GpuAllocation, upload::UploadArena};
+
+/// Which shader stages can read storage buffers on a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageSupport {
+    stages: wgpu::ShaderStages,
+}
+
+impl StorageSupport {
+    /// What the downlevel flags of `adapter` and the limits `device` was
+    /// created with allow.
+    pub fn probe(adapter: &wgpu::Adapter, device: &wgpu::Device) -> Self {
+        Self::new(adapter.get_downlevel_capabilities().flags, &device.limits())
+    }
+
+    pub fn new(flags: wgpu::DownlevelFlags, limits: &wgpu::Limits) -> Self {
+        let mut stages = wgpu::ShaderStages::NONE;
+        if limits.max_storage_buffers_per_shader_stage > 0 {
+            let by_flag = [
+                (
+                    wgpu::DownlevelFlags::VERTEX_STORAGE,
+                    wgpu::ShaderStages::VERTEX,
+                ),
+                (
+                    wgpu::DownlevelFlags::FRAGMENT_STORAGE,
+                    wgpu::ShaderStages::FRAGMENT,
+                ),
+                (
+                    wgpu::DownlevelFlags::COMPUTE_SHADERS,
+                    wgpu::ShaderStages::COMPUTE,
+                ),
+            ];
+            for (flag, stage) in by_flag {
+                if flags.contains(flag) {
+                    stages |= stage;
+                }
+            }
+        }
+        Self { stages }
+    }
+
+    /// Whether read-only storage buffers can be bound in all of `stages`.
+    pub fn read_only(self, stages: wgpu::ShaderStages) -> bool {
+        self.stages.contains(stages)
+    }
+
+    /// A storage buffer if `stages` can read it, or else a uniform array of
+    /// `uniform_len`.
+    pub fn binding(self, stages: wgpu::ShaderStages, uniform_len: usize) -> ArrayBinding {
+        if self.read_only(stages) {
+            ArrayBinding::Storage
+        } else {
+            ArrayBinding::Uniform { len: uniform_len }
+        }
+    }
+}
+
+/// How an array gets to the shaders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayBinding {
+    /// A read-only storage buffer, as big as it needs to be.
+    Storage,
+    /// `len` elements in a uniform buffer, which is all the shaders see.
+    Uniform { len: usize },
+}
+
+impl ArrayBinding {
+    /// The most elements the shaders can see, `None` if only the size of the
+    /// buffer limits them.
+    pub fn max_len(self) -> Option<usize> {
+        match self {
+            Self::Storage => None,
+            Self::Uniform { len } => Some(len),
+        }
+    }
+
+    pub fn binding_type(self) -> wgpu::BufferBindingType {
+        match self {
+            Self::Storage => wgpu::BufferBindingType::Storage { read_only: true },
+            Self::Uniform { .. } => wgpu::BufferBindingType::Uniform,
+        }
+    }
+
+    /// The layout entry for the array at `binding`, read in `visibility`.
+    pub fn layout_entry(
+        self,
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: self.binding_type(),
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    }
+
+    fn usage(self) -> wgpu::BufferUsages {
+        match self {
+            Self::Storage => wgpu::BufferUsages::STORAGE,
+            Self::Uniform { .. } => wgpu::BufferUsages::UNIFORM,
+        }
+    }
+
+    /// Adapts the WGSL `source` to the binding. It declares the array as
+    /// `var<storage, read> name: array<element>;`, which becomes a uniform
+    /// array of `len` for `Uniform`.
+    pub fn shader_source<'a>(self, source: &'a str, name: &str, element: &str) -> Cow<'a, str> {
+        match self {
+            Self::Storage => Cow::Borrowed(source),
+            Self::Uniform { len } => Cow::Owned(source.replace(
+                &format!("var<storage, read> {name}: array<{element}>;"),
+                &format!("var<uniform> {name}: array<{element}, {len}>;"),
+            )),
+        }
+    }
+}
+
+/// An array of `T`s for the shaders, bound as its `ArrayBinding` says.
+///
+/// Storage buffers grow when the data doesn't fit, which `update` and
+/// `upload` return `true` for, and the bind groups with the old buffer have
+/// to be made again. Uniform arrays always have all their elements and
+/// never grow.
+pub struct StorageBuffer<T> {
+    binding: ArrayBinding,
+    buffer: wgpu::Buffer,
+    allocation: GpuAllocation,
+    label: String,
+    capacity: usize,
+    _element: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> StorageBuffer<T> {
+    /// Room for `capacity` elements. Storage buffers can't be empty, so
+    /// there's room for at least one.
+    pub fn new(device: &wgpu::Device, label: &str, binding: ArrayBinding, capacity: usize) -> Self {
+        let capacity = binding.max_len().unwrap_or(capacity.max(1));
+        let buffer = Self::create_buffer(device, label, binding, capacity);
+        Self {
+            binding,
+            allocation: GpuAllocation::buffer(&buffer),
+            buffer,
+            label: label.to_owned(),
+            capacity,
+            _element: PhantomData,
+        }
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        label: &str,
+        binding: ArrayBinding,
+        capacity: usize,
+    ) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress,
+            usage: binding.usage() | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Writes `data` from the start, what doesn't fit is left out.
+    pub fn write(&self, queue: &wgpu::Queue, data: &[T]) {
+        let data = &data[..data.len().min(self.capacity)];
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(data));
+    }
+
+    /// Like `write`, growing a storage buffer to fit first. Returns whether
+    /// it was reallocated.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[T]) -> bool {
+        let reallocated = self.reserve(device, data.len());
+        self.write(queue, data);
+        reallocated
+    }
+
+    /// Like `update`, through `uploads` with a copy recorded into `encoder`.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        uploads: &mut UploadArena,
+        data: &[T],
+    ) -> bool {
+        let reallocated = self.reserve(device, data.len());
+        let data = &data[..data.len().min(self.capacity)];
+        uploads.write(device, encoder, &self.buffer, 0, data);
+        reallocated
+    }
+
+    fn reserve(&mut self, device: &wgpu::Device, len: usize) -> bool {
+        if len <= self.capacity || self.binding != ArrayBinding::Storage {
+            return false;
+        }
+        self.capacity = len.next_power_of_two();
+        self.buffer = Self::create_buffer(device, &self.label, self.binding, self.capacity);
+        self.allocation = GpuAllocation::buffer(&self.buffer);
+        true
+    }
+}
+
+impl<T> StorageBuffer<T> {
+    pub fn binding(&self) -> ArrayBinding {
+        self.binding
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// How many elements fit before a storage buffer has to grow.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn allocated_bytes(&self) -> u64 {
+        self.allocation.bytes()
+    }
+
+    /// The whole buffer, for a bind group entry.
+    pub fn as_entire_binding(&self) -> wgpu::BindingResource<'_> {
+        self.buffer.as_entire_binding()
+    }
+}
@@ -0,0 +1,816 @@
+use std::{error::Error, path::Path};
+
+use image::GenericImageView;
+use thiserror::Error;
+
+use crate::{
+    asset_source::AssetSource,
+    block_compression,
+    compressed_texture::{self, CompressedImage, CompressedTextureError},
+    gpu_memory::GpuAllocation,
+    mipmap::MipmapGenerator,
+    procedural,
+    shader::ShaderError,
+};
+
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    allocation: GpuAllocation,
+}
+
+/// Rounds `unpadded_bytes_per_row` up to the next multiple of
+/// `wgpu::COPY_BYTES_PER_ROW_ALIGNMENT` (256 bytes).
+pub fn padded_bytes_per_row(unpadded_bytes_per_row: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    unpadded_bytes_per_row.div_ceil(align) * align
+}
+
+/// Copies tightly packed rows into a buffer whose rows are `padded_row` bytes
+/// long, so the upload uses an aligned `bytes_per_row`.
+fn pad_rows(data: &[u8], unpadded_row: usize, padded_row: usize) -> Vec<u8> {
+    if unpadded_row == padded_row {
+        return data.to_vec();
+    }
+
+    let rows = data.len() / unpadded_row;
+    let mut padded = vec![0; rows * padded_row];
+    for (src, dst) in data
+        .chunks_exact(unpadded_row)
+        .zip(padded.chunks_exact_mut(padded_row))
+    {
+        dst[..unpadded_row].copy_from_slice(src);
+    }
+    padded
+}
+
+/// Creates a texture with room for `mip_level_count` levels and uploads
+/// `img` to the first one.
+fn upload_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    img: &image::DynamicImage,
+    label: Option<&str>,
+    format: wgpu::TextureFormat,
+    mip_level_count: u32,
+) -> wgpu::Texture {
+    let rgba = img.to_rgba8();
+    let dimensions = img.dimensions();
+
+    let size = wgpu::Extent3d {
+        width: dimensions.0,
+        height: dimensions.1,
+        depth_or_array_layers: 1,
+    };
+    // TEXTURE_BINDING tells wgpu that we want to use this texture in shaders
+    // COPY_DST means that we want to copy data to this texture
+    let mut usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+    if mip_level_count > 1 {
+        // The mip levels are rendered from the one above, COPY_SRC lets
+        // them be read back
+        usage |= wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label,
+        size,
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage,
+        view_formats: &[],
+    });
+
+    // Rows of an RGBA8 image are 4 * width bytes long, which is rarely a
+    // multiple of 256, so each row gets padded before the upload.
+    let unpadded_bytes_per_row = 4 * dimensions.0;
+    let bytes_per_row = padded_bytes_per_row(unpadded_bytes_per_row);
+    let data = pad_rows(
+        &rgba,
+        unpadded_bytes_per_row as usize,
+        bytes_per_row as usize,
+    );
+
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(bytes_per_row),
+            rows_per_image: Some(dimensions.1),
+        },
+        size,
+    );
+
+    texture
+}
+
+/// Uploads each of `layers` into the layer of `texture` with its index. The
+/// images must all be as big as the texture.
+fn write_layers(queue: &wgpu::Queue, texture: &wgpu::Texture, layers: &[image::DynamicImage]) {
+    let wgpu::Extent3d { width, height, .. } = texture.size();
+    let unpadded_bytes_per_row = 4 * width;
+    let bytes_per_row = padded_bytes_per_row(unpadded_bytes_per_row);
+    for (layer, image) in layers.iter().enumerate() {
+        let data = pad_rows(
+            &image.to_rgba8(),
+            unpadded_bytes_per_row as usize,
+            bytes_per_row as usize,
+        );
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer as u32,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+/// Why `Texture::array_from_images` can't make an array of the images.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TextureArrayError {
+    #[error("a texture array needs at least one image")]
+    NoImages,
+    #[error(
+        "the layers of a texture array must all have the same size, layer {layer} is \
+         {}x{} but layer 0 is {}x{}; resize them first with texture::resize_layers",
+        .found.0, .found.1, .expected.0, .expected.1
+    )]
+    SizeMismatch {
+        layer: usize,
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    #[error("{count} layers are more than the {max} a texture array can have on this device")]
+    TooManyLayers { count: usize, max: u32 },
+}
+
+/// `images` scaled to `width`x`height` where they aren't already, so they
+/// fit into one texture array.
+pub fn resize_layers(
+    images: &[image::DynamicImage],
+    width: u32,
+    height: u32,
+) -> Vec<image::DynamicImage> {
+    images
+        .iter()
+        .map(|image| {
+            if image.dimensions() == (width, height) {
+                image.clone()
+            } else {
+                image.resize_exact(width, height, image::imageops::FilterType::Triangle)
+            }
+        })
+        .collect()
+}
+
+impl Texture {
+    /// With a stencil aspect for the outlines, see `outline`.
+    pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
+    /// What the texture counts towards `gpu_memory::allocated`.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.allocation.bytes()
+    }
+
+    /// Creates a depth texture matching the size of the surface, with the
+    /// sample count of the color target it is used with.
+    ///
+    /// The surface must not have a zero dimension, wgpu rejects zero-sized
+    /// textures.
+    pub fn create_depth_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+        label: &str,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        };
+        let desc = wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+            // We need RENDER_ATTACHMENT to render to it, TEXTURE_BINDING
+            // lets us read it back in a shader later on.
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        };
+        let texture = device.create_texture(&desc);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+
+        Self {
+            allocation: GpuAllocation::texture(&texture),
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// A 1x1 white texture, for materials that are only their color.
+    pub fn white(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let img = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+        Self::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some("white_texture"),
+        )
+    }
+
+    /// The diffuse texture of materials without one, a checkerboard that
+    /// shows how the texture coordinates go.
+    pub fn uv_checker(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::checkerboard(
+            device,
+            queue,
+            (64, 64),
+            8,
+            [255, 255, 255, 255],
+            [160, 160, 160, 255],
+        )
+    }
+
+    /// See `procedural::checkerboard`.
+    pub fn checkerboard(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: (u32, u32),
+        cell: u32,
+        color_a: [u8; 4],
+        color_b: [u8; 4],
+    ) -> Self {
+        let img = procedural::checkerboard(size, cell, color_a, color_b);
+        Self::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some("checkerboard_texture"),
+        )
+    }
+
+    /// See `procedural::gradient`.
+    pub fn gradient(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: (u32, u32),
+        top: [u8; 4],
+        bottom: [u8; 4],
+    ) -> Self {
+        let img = procedural::gradient(size, top, bottom);
+        Self::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some("gradient_texture"),
+        )
+    }
+
+    /// See `procedural::value_noise`.
+    pub fn value_noise(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: (u32, u32),
+        seed: u32,
+        octaves: u32,
+    ) -> Self {
+        let img = procedural::value_noise(size, seed, octaves);
+        Self::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some("value_noise_texture"),
+        )
+    }
+
+    /// A 1x1 gray texture, drawn while the real one is loading.
+    pub fn placeholder(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let img = image::RgbaImage::from_pixel(1, 1, image::Rgba([128, 128, 128, 255]));
+        Self::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some("placeholder_texture"),
+        )
+    }
+
+    /// A 1x1 magenta texture, drawn instead of textures that failed to load.
+    pub fn missing(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let img = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 0, 255, 255]));
+        Self::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some("missing_texture"),
+        )
+    }
+
+    /// A 1x1 normal map pointing straight out of the surface, so meshes
+    /// without a normal map can go through the normal mapping pipeline.
+    pub fn flat_normal(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let img = image::RgbaImage::from_pixel(1, 1, image::Rgba([128, 128, 255, 255]));
+        Self::from_image_with_format(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some("flat_normal_texture"),
+            wgpu::TextureFormat::Rgba8Unorm,
+        )
+    }
+
+    /// A 1x1 metallic-roughness map of a dielectric that's as smooth as can
+    /// be, roughness in green and metallic in blue like glTF. Shades the
+    /// same as materials did before there were these maps.
+    pub fn smooth_dielectric(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let img = image::RgbaImage::from_pixel(1, 1, image::Rgba([0, 0, 0, 255]));
+        Self::from_image_with_format(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(img),
+            Some("smooth_dielectric_texture"),
+            wgpu::TextureFormat::Rgba8Unorm,
+        )
+    }
+
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+    ) -> Result<Self, image::ImageError> {
+        let img = image::load_from_memory(bytes)?;
+        Ok(Self::from_image(device, queue, &img, Some(label)))
+    }
+
+    /// Uploads a color image, which is assumed to be stored as sRGB.
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+    ) -> Self {
+        Self::from_image_with_format(
+            device,
+            queue,
+            img,
+            label,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    /// Uploads an image as an RGBA8 texture of the given format. Data that
+    /// isn't a color, like normal maps, must use `Rgba8Unorm` so the values
+    /// aren't converted from sRGB when sampled.
+    pub fn from_image_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = upload_image(device, queue, img, label, format, 1);
+        Self::with_default_view(device, texture)
+    }
+
+    /// Like `from_image_with_format`, with a full mip chain generated on the
+    /// GPU by `generator`, and a trilinear sampler. Pass the same generator
+    /// for all the textures of a model.
+    pub fn from_image_mipmapped(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        generator: &mut MipmapGenerator,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        format: wgpu::TextureFormat,
+    ) -> Result<Self, ShaderError> {
+        let (width, height) = img.dimensions();
+        let mip_level_count = crate::mipmap::mip_level_count(width, height);
+        let texture = upload_image(device, queue, img, label, format, mip_level_count);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+        generator.generate(device, &mut encoder, &texture)?;
+        queue.submit(std::iter::once(encoder.finish()));
+        Ok(Self::with_default_view(device, texture))
+    }
+
+    /// Loads a KTX2 file, see `from_compressed`.
+    pub fn from_ktx2(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> Result<Self, CompressedTextureError> {
+        let image = compressed_texture::parse_ktx2(bytes)?;
+        Self::from_compressed(device, queue, &image, label)
+    }
+
+    /// Loads a DDS file, see `from_compressed`.
+    pub fn from_dds(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> Result<Self, CompressedTextureError> {
+        let image = compressed_texture::parse_dds(bytes)?;
+        Self::from_compressed(device, queue, &image, label)
+    }
+
+    /// Uploads every mip level of a block compressed image. When the device
+    /// can't sample its format, or the size isn't a whole number of blocks,
+    /// the levels are decoded to RGBA8 first, see `block_compression`.
+    pub fn from_compressed(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &CompressedImage,
+        label: Option<&str>,
+    ) -> Result<Self, CompressedTextureError> {
+        let (block_width, block_height) = image.format.block_dimensions();
+        let max_mips = image.level_size(0).max_mips(wgpu::TextureDimension::D2);
+        if image.width == 0 || image.levels.len() as u32 > max_mips {
+            return Err(CompressedTextureError::Unsupported(format!(
+                "{} mip levels for a {}x{} texture",
+                image.levels.len(),
+                image.width,
+                image.height
+            )));
+        }
+
+        let supported = device.features().contains(image.format.required_features())
+            && image.width.is_multiple_of(block_width)
+            && image.height.is_multiple_of(block_height);
+        let (format, levels) = if supported {
+            (image.format, image.levels.clone())
+        } else {
+            if !block_compression::can_decode(image.format) {
+                return Err(CompressedTextureError::CantDecode {
+                    format: image.format,
+                });
+            }
+            log::info!(
+                "Decoding {} from {:?} to RGBA8, the adapter can't sample it",
+                label.unwrap_or("a texture"),
+                image.format
+            );
+            let levels = image
+                .levels
+                .iter()
+                .enumerate()
+                .map(|(level, data)| {
+                    let size = image.level_size(level as u32);
+                    block_compression::decompress(image.format, size.width, size.height, data)
+                        .ok_or(CompressedTextureError::Truncated("mip levels"))
+                })
+                .collect::<Result<_, _>>()?;
+            let format = if image.format.is_srgb() {
+                wgpu::TextureFormat::Rgba8UnormSrgb
+            } else {
+                wgpu::TextureFormat::Rgba8Unorm
+            };
+            (format, levels)
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: image.level_size(0),
+            mip_level_count: levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        // Copies of compressed textures cover whole blocks, even for mips
+        // smaller than a block, and their rows are counted in blocks
+        let (block_width, block_height) = format.block_dimensions();
+        let block_bytes = format.block_copy_size(None).unwrap_or(4);
+        for (level, data) in levels.iter().enumerate() {
+            let size = image.level_size(level as u32).physical_size(format);
+            let bytes_per_row = size.width / block_width * block_bytes;
+            let rows = size.height / block_height;
+            if data.len() < (bytes_per_row * rows) as usize {
+                return Err(CompressedTextureError::Truncated("mip levels"));
+            }
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(rows),
+                },
+                size,
+            );
+        }
+
+        Ok(Self::with_default_view(device, texture))
+    }
+
+    /// A view of the whole texture, and a sampler that blends between mip
+    /// levels if there's more than one.
+    fn with_default_view(device: &wgpu::Device, texture: wgpu::Texture) -> Self {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // Trilinear filtering: linear within a level and between levels
+        let min_filter = if texture.mip_level_count() > 1 {
+            wgpu::FilterMode::Linear
+        } else {
+            wgpu::FilterMode::Nearest
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter,
+            mipmap_filter: min_filter,
+            ..Default::default()
+        });
+
+        Self {
+            allocation: GpuAllocation::texture(&texture),
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Loads the six faces of a cubemap from `source`, in the order wgpu
+    /// expects them: +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cubemap_from_files<P: AsRef<Path>>(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &dyn AssetSource,
+        paths: &[P; 6],
+        label: &str,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut faces = Vec::with_capacity(6);
+        for path in paths {
+            let path = path.as_ref();
+            let bytes = source
+                .read(path)
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+            faces.push(image::load_from_memory(&bytes)?);
+        }
+        Self::cubemap_from_images(device, queue, &faces, label)
+    }
+
+    /// Creates a cubemap from six square images of the same size, in the
+    /// order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cubemap_from_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[image::DynamicImage],
+        label: &str,
+    ) -> Result<Self, Box<dyn Error>> {
+        if faces.len() != 6 {
+            return Err(format!("a cubemap needs 6 faces, got {}", faces.len()).into());
+        }
+        let (width, height) = faces[0].dimensions();
+        if width != height {
+            return Err(format!("cubemap faces must be square, got {width}x{height}").into());
+        }
+        if let Some(face) = faces
+            .iter()
+            .find(|face| face.dimensions() != (width, height))
+        {
+            let (w, h) = face.dimensions();
+            return Err(format!(
+                "cubemap faces must all have the same size, got {w}x{h} and {width}x{height}"
+            )
+            .into());
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                // A cubemap is a 2D array with 6 layers
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        write_layers(queue, &texture, faces);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(label),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Ok(Self {
+            allocation: GpuAllocation::texture(&texture),
+            texture,
+            view,
+            sampler,
+        })
+    }
+
+    /// Creates a 2D array texture with one layer for each of `images`,
+    /// which must all be as big as the first one, see `resize_layers`.
+    /// Shaders pick the layer with the third coordinate of
+    /// `textureSample`, bind it with `create_array_bind_group_layout`.
+    pub fn array_from_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: &[image::DynamicImage],
+        label: Option<&str>,
+    ) -> Result<Self, TextureArrayError> {
+        let Some(first) = images.first() else {
+            return Err(TextureArrayError::NoImages);
+        };
+        let max = device.limits().max_texture_array_layers;
+        if images.len() > max as usize {
+            return Err(TextureArrayError::TooManyLayers {
+                count: images.len(),
+                max,
+            });
+        }
+        let (width, height) = first.dimensions();
+        if let Some((layer, image)) = images
+            .iter()
+            .enumerate()
+            .find(|(_, image)| image.dimensions() != (width, height))
+        {
+            return Err(TextureArrayError::SizeMismatch {
+                layer,
+                expected: (width, height),
+                found: image.dimensions(),
+            });
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: images.len() as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        write_layers(queue, &texture, images);
+
+        // A single layer would get a D2 view by default
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label,
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label,
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Ok(Self {
+            allocation: GpuAllocation::texture(&texture),
+            texture,
+            view,
+            sampler,
+        })
+    }
+
+    /// The layout of the bind group created by `Texture::create_bind_group`:
+    /// the texture at binding 0 and its sampler at binding 1.
+    pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        Self::bind_group_layout(
+            device,
+            "texture_bind_group_layout",
+            wgpu::TextureViewDimension::D2,
+        )
+    }
+
+    /// Like `create_bind_group_layout`, for the textures made by
+    /// `array_from_images`.
+    pub fn create_array_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        Self::bind_group_layout(
+            device,
+            "texture_array_bind_group_layout",
+            wgpu::TextureViewDimension::D2Array,
+        )
+    }
+
+    fn bind_group_layout(
+        device: &wgpu::Device,
+        label: &str,
+        view_dimension: wgpu::TextureViewDimension,
+    ) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    // This should match the filterable field of the
+                    // corresponding Texture entry above.
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    pub fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("texture_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,307 @@
+//! Many differently textured objects in a single draw call. The textures
+//! are the layers of one texture array, and every instance carries the
+//! layer it's drawn with, see `TexturedInstance`.
+
+use std::time::Duration;
+
+use glam::{Quat, Vec3};
+
+use crate::{
+    app::{App, GpuContext},
+    buffer::GrowableBuffer,
+    camera::{Camera, CameraUniform},
+    color,
+    error::AppError,
+    geometry,
+    instance::{Instance, TexturedInstance, TexturedInstanceRaw},
+    model::{Mesh, ModelVertex},
+    pipeline::{PipelineBuilder, RenderTargets},
+    procedural,
+    texture::Texture,
+    uniform::UniformBuffer,
+};
+
+pub const TEXTURE_ARRAY_SHADER_SOURCE: &str = include_str!("../shaders/texture_array.wgsl");
+
+/// How many textures the demo draws, a 4x4 grid of cubes with one each.
+pub const DEMO_LAYER_COUNT: u32 = 16;
+
+/// How far apart the cubes of the demo are.
+const GRID_SPACING: f32 = 1.75;
+
+/// In radians per second.
+const SPIN_SPEED: f32 = 0.6;
+
+/// `count` checkerboards of 64x64, each in a color of its own.
+pub fn demo_layers(count: u32) -> Vec<image::DynamicImage> {
+    (0..count)
+        .map(|layer| {
+            // Around the color wheel, at full saturation
+            let hue = layer as f32 / count.max(1) as f32 * 6.0;
+            let channel = |offset: f32| {
+                let distance = ((hue + offset) % 6.0 - 3.0).abs();
+                ((distance - 1.0).clamp(0.0, 1.0) * 255.0) as u8
+            };
+            let color = [channel(0.0), channel(4.0), channel(2.0), 255];
+            image::DynamicImage::ImageRgba8(procedural::checkerboard(
+                (64, 64),
+                8 + 4 * (layer % 4),
+                color,
+                [255, 255, 255, 255],
+            ))
+        })
+        .collect()
+}
+
+/// `count` cubes in rows of 4 on the XZ plane around the origin, the
+/// `i`th one with layer `i`, spun by `angle` radians.
+pub fn grid_instances(count: u32, angle: f32) -> Vec<TexturedInstance> {
+    let columns = 4;
+    let rows = count.div_ceil(columns);
+    let center = Vec3::new(columns as f32 - 1.0, 0.0, rows as f32 - 1.0) * GRID_SPACING / 2.0;
+    (0..count)
+        .map(|layer| {
+            let position = Vec3::new((layer % columns) as f32, 0.0, (layer / columns) as f32)
+                * GRID_SPACING
+                - center;
+            // Every cube spins a little differently
+            let axis = Vec3::new(0.3, 1.0, 0.2 * (layer % 3) as f32).normalize();
+            TexturedInstance {
+                instance: Instance {
+                    position,
+                    rotation: Quat::from_axis_angle(axis, angle * (1.0 + layer as f32 * 0.1)),
+                },
+                layer,
+            }
+        })
+        .collect()
+}
+
+/// A mesh drawn once for each `TexturedInstance`, with the texture array
+/// layer the instance picks.
+pub struct TextureArrayBatch {
+    pipeline: wgpu::RenderPipeline,
+    layers: Texture,
+    texture_bind_group: wgpu::BindGroup,
+    camera_buffer: UniformBuffer<CameraUniform>,
+    camera_bind_group: wgpu::BindGroup,
+    mesh: Mesh,
+    instances: GrowableBuffer<TexturedInstanceRaw>,
+}
+
+impl TextureArrayBatch {
+    /// Draws `geometry` into `targets`, with `layers` as the texture array.
+    /// Fails if the layers don't make an array, see
+    /// `Texture::array_from_images`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        targets: RenderTargets,
+        geometry: &geometry::Geometry,
+        layers: &[image::DynamicImage],
+    ) -> Result<Self, AppError> {
+        let layers = Texture::array_from_images(device, queue, layers, Some("texture_array"))?;
+        let texture_layout = Texture::create_array_bind_group_layout(device);
+        let texture_bind_group = layers.create_bind_group(device, &texture_layout);
+
+        // Only the vertex shader needs the camera, there's no specular light
+        let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("texture_array_camera_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let camera_buffer =
+            UniformBuffer::new(device, "Texture Array Camera Buffer", &CameraUniform::new());
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("texture_array_camera_bind_group"),
+            layout: &camera_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.binding(),
+            }],
+        });
+
+        let pipeline = PipelineBuilder::with_targets("Texture Array Pipeline", targets)
+            .shader("texture_array.wgsl", TEXTURE_ARRAY_SHADER_SOURCE)
+            .bind_group_layouts(&[&camera_layout, &texture_layout])
+            .vertex_buffer(ModelVertex::desc())
+            .vertex_buffer(TexturedInstanceRaw::desc())
+            .build(device)?;
+
+        Ok(Self {
+            pipeline,
+            layers,
+            texture_bind_group,
+            camera_buffer,
+            camera_bind_group,
+            mesh: Mesh::from_geometry(device, "Texture Array Mesh", geometry, 0),
+            instances: GrowableBuffer::new(
+                device,
+                "Texture Array Instance Buffer",
+                wgpu::BufferUsages::VERTEX,
+                &[],
+            ),
+        })
+    }
+
+    /// How many textures the instances can pick from.
+    pub fn layer_count(&self) -> u32 {
+        self.layers.texture.depth_or_array_layers()
+    }
+
+    pub fn instance_count(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn set_camera(&self, queue: &wgpu::Queue, camera: &Camera) {
+        let mut uniform = CameraUniform::new();
+        uniform.update_view_proj(camera);
+        self.camera_buffer.write(queue, &uniform);
+    }
+
+    /// Replaces the instances drawn. Layers past `layer_count` show the
+    /// last one.
+    pub fn set_instances(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[TexturedInstance],
+    ) {
+        let raw = instances
+            .iter()
+            .map(TexturedInstance::to_raw)
+            .collect::<Vec<_>>();
+        self.instances.update(device, queue, &raw);
+    }
+
+    /// Draws all the instances with one draw call.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.instances.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
+        self.instances.bind(render_pass, 1);
+        self.mesh.draw(render_pass, self.instances.draw_range());
+    }
+}
+
+/// The texture array demo as an `App`, see `examples/texture_array.rs`:
+/// `DEMO_LAYER_COUNT` spinning cubes, each with a texture of its own, in a
+/// single draw call.
+pub struct TextureArrayApp {
+    batch: TextureArrayBatch,
+    depth_texture: Texture,
+    camera: Camera,
+    angle: f32,
+}
+
+impl TextureArrayApp {
+    pub fn new(ctx: &GpuContext) -> Result<Self, AppError> {
+        let device = ctx.device();
+        let batch = TextureArrayBatch::new(
+            device,
+            ctx.queue(),
+            RenderTargets {
+                color_format: ctx.render_format(),
+                depth_format: Some(Texture::DEPTH_FORMAT),
+                sample_count: 1,
+            },
+            &geometry::cube(),
+            &demo_layers(DEMO_LAYER_COUNT),
+        )?;
+        let size = ctx.size();
+        let camera = Camera {
+            eye: Vec3::new(0.0, 5.0, 8.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            aspect: size.width as f32 / size.height as f32,
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+        };
+        log::info!(
+            "Drawing {DEMO_LAYER_COUNT} cubes with {} textures in one draw call",
+            batch.layer_count()
+        );
+        Ok(Self {
+            batch,
+            depth_texture: Texture::create_depth_texture(device, ctx.config(), 1, "depth_texture"),
+            camera,
+            angle: 0.0,
+        })
+    }
+}
+
+impl App for TextureArrayApp {
+    /// Panics where `new` would fail.
+    fn init(ctx: &GpuContext) -> Self {
+        match Self::new(ctx) {
+            Ok(app) => app,
+            Err(err) => panic!("Can't run the texture array demo: {err}"),
+        }
+    }
+
+    fn resize(&mut self, ctx: &GpuContext, size: winit::dpi::PhysicalSize<u32>) {
+        self.camera.aspect = size.width as f32 / size.height as f32;
+        self.depth_texture =
+            Texture::create_depth_texture(ctx.device(), ctx.config(), 1, "depth_texture");
+    }
+
+    fn update(&mut self, ctx: &GpuContext, dt: Duration) {
+        self.angle += SPIN_SPEED * dt.as_secs_f32();
+        self.batch.set_camera(ctx.queue(), &self.camera);
+        self.batch.set_instances(
+            ctx.device(),
+            ctx.queue(),
+            &grid_instances(DEMO_LAYER_COUNT, self.angle),
+        );
+    }
+
+    fn render(
+        &mut self,
+        ctx: &GpuContext,
+        view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Texture Array Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(color::clear_value_for_format(
+                        wgpu::Color {
+                            r: 0.1,
+                            g: 0.1,
+                            b: 0.12,
+                            a: 1.0,
+                        },
+                        ctx.render_format(),
+                    )),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.batch.draw(&mut render_pass);
+    }
+}
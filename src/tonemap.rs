@@ -0,0 +1,139 @@
+//! Maps the HDR scene, where lights can push colors way above 1, to the
+//! 0 to 1 the frame can show. Without it everything brighter than 1 clips to
+//! flat white. The shader is the last pass `PostProcessor` draws.
+
+use std::mem::offset_of;
+
+use glam::Vec3;
+
+use crate::uniform::{Uniform, UniformField, WgslType};
+
+pub const TONEMAP_SHADER_SOURCE: &str = include_str!("../shaders/tonemap.wgsl");
+
+/// How far the exposure keys change the exposure, in stops.
+pub const EXPOSURE_STEP: f32 = 0.25;
+/// The exposure stays within this many stops of 0 either way.
+pub const MAX_EXPOSURE: f32 = 8.0;
+
+/// How colors above 1 get brought down. The numbers must match the ones in
+/// tonemap.wgsl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Operator {
+    /// Only clamps, like before there was tonemapping.
+    None,
+    /// `c / (1 + c)`, simple but it washes out the brightest colors.
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve, with more contrast.
+    #[default]
+    Aces,
+}
+
+impl Operator {
+    pub const ALL: [Self; 3] = [Self::None, Self::Reinhard, Self::Aces];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Reinhard => "Reinhard",
+            Self::Aces => "ACES",
+        }
+    }
+
+    /// The operator after this one, going back to the first after the last.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&op| op == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Same as the shader's, on the CPU.
+    pub fn apply(self, color: Vec3) -> Vec3 {
+        match self {
+            Self::None => color.clamp(Vec3::ZERO, Vec3::ONE),
+            Self::Reinhard => color / (Vec3::ONE + color),
+            Self::Aces => {
+                let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                ((color * (a * color + b)) / (color * (c * color + d) + e))
+                    .clamp(Vec3::ZERO, Vec3::ONE)
+            }
+        }
+    }
+
+    fn index(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Reinhard => 1,
+            Self::Aces => 2,
+        }
+    }
+}
+
+/// What the tonemapping pass does with the scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tonemap {
+    pub operator: Operator,
+    /// In stops, every 1 doubles how bright the scene is before the
+    /// operator. Clamped to `MAX_EXPOSURE` either way.
+    pub exposure: f32,
+}
+
+impl Default for Tonemap {
+    fn default() -> Self {
+        Self {
+            operator: Operator::default(),
+            exposure: 0.0,
+        }
+    }
+}
+
+impl Tonemap {
+    /// Changes the exposure by `steps` times `EXPOSURE_STEP`.
+    pub fn adjust_exposure(&mut self, steps: i32) {
+        self.exposure =
+            (self.exposure + steps as f32 * EXPOSURE_STEP).clamp(-MAX_EXPOSURE, MAX_EXPOSURE);
+    }
+
+    /// A linear `color` of the scene the way the pass shows it, still
+    /// linear.
+    pub fn apply(&self, color: Vec3) -> Vec3 {
+        self.operator
+            .apply(color * self.exposure.clamp(-MAX_EXPOSURE, MAX_EXPOSURE).exp2())
+    }
+
+    pub fn uniform(&self) -> TonemapUniform {
+        TonemapUniform {
+            exposure: self.exposure.clamp(-MAX_EXPOSURE, MAX_EXPOSURE).exp2(),
+            curve: self.operator.index(),
+            _padding: [0; 2],
+        }
+    }
+
+    /// For the HUD, e.g. "ACES, exposure +0.50 EV".
+    pub fn describe(&self) -> String {
+        format!(
+            "{}, exposure {:+.2} EV",
+            self.operator.name(),
+            self.exposure
+        )
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TonemapUniform {
+    /// What the scene gets multiplied by, 2 to the power of the stops.
+    pub exposure: f32,
+    /// `Operator::index`, `operator` is reserved in WGSL.
+    pub curve: u32,
+    _padding: [u32; 2],
+}
+
+impl Uniform for TonemapUniform {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "exposure",
+            offset_of!(TonemapUniform, exposure),
+            WgslType::F32,
+        ),
+        UniformField::new("curve", offset_of!(TonemapUniform, curve), WgslType::U32),
+    ];
+}
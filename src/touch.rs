@@ -0,0 +1,149 @@
+//! Touchscreen gestures for the camera. `TouchGestures` follows the fingers
+//! on the window and sums up what they did since the last frame: one finger
+//! dragging orbits, two fingers pinching zoom and dragging together pan.
+
+use std::{collections::HashMap, time::Duration};
+
+use glam::Vec2;
+use web_time::Instant;
+use winit::event::{TouchPhase, WindowEvent};
+
+/// Touches without any event for this long are dropped when a new finger
+/// comes down. Some platforms lose the `Ended` of a touch, e.g. when the
+/// finger leaves the window, and it would pin a gesture in place forever.
+pub const STALE_TOUCH: Duration = Duration::from_secs(5);
+
+/// What the fingers did since the last `take_gesture`, in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gesture {
+    /// How far a single finger dragged.
+    pub orbit: Vec2,
+    /// How far the middle of two fingers moved.
+    pub pan: Vec2,
+    /// How much further apart two fingers are than they were, 2 for twice
+    /// as far and 1 when they didn't pinch.
+    pub pinch: f32,
+}
+
+impl Default for Gesture {
+    fn default() -> Self {
+        Self {
+            orbit: Vec2::ZERO,
+            pan: Vec2::ZERO,
+            pinch: 1.0,
+        }
+    }
+}
+
+impl Gesture {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Finger {
+    position: Vec2,
+    last_event: Instant,
+}
+
+/// Follows the fingers on the window by their ids, see the module docs.
+/// Three or more fingers don't do anything.
+#[derive(Debug, Clone, Default)]
+pub struct TouchGestures {
+    fingers: HashMap<u64, Finger>,
+    gesture: Gesture,
+}
+
+impl TouchGestures {
+    /// Returns `true` for touch events, which are all tracked. Losing the
+    /// focus lifts every finger, their `Ended` goes to another window.
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::Touch(touch) => {
+                let position = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+                self.touch(touch.id, touch.phase, position, Instant::now());
+                true
+            }
+            WindowEvent::Focused(false) => {
+                self.reset();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Feeds in a touch event of the finger `id` at `position`, which
+    /// happened at `now`.
+    pub fn touch(&mut self, id: u64, phase: TouchPhase, position: Vec2, now: Instant) {
+        match phase {
+            TouchPhase::Started => {
+                self.fingers
+                    .retain(|_, finger| now.duration_since(finger.last_event) < STALE_TOUCH);
+                // A finger that comes down again didn't end the last time,
+                // it starts over
+                self.fingers.insert(
+                    id,
+                    Finger {
+                        position,
+                        last_event: now,
+                    },
+                );
+            }
+            TouchPhase::Moved => self.move_finger(id, position, now),
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                // Where it ended may be further than the last move
+                self.move_finger(id, position, now);
+                self.fingers.remove(&id);
+            }
+        }
+    }
+
+    fn move_finger(&mut self, id: u64, position: Vec2, now: Instant) {
+        let before: Vec<_> = self.positions().collect();
+        let Some(finger) = self.fingers.get_mut(&id) else {
+            // Its start was lost, it only counts from here on
+            self.touch(id, TouchPhase::Started, position, now);
+            return;
+        };
+        finger.position = position;
+        finger.last_event = now;
+        let after: Vec<_> = self.positions().collect();
+
+        match (before.as_slice(), after.as_slice()) {
+            ([before], [after]) => self.gesture.orbit += *after - *before,
+            ([a0, b0], [a1, b1]) => {
+                self.gesture.pan += (*a1 + *b1) / 2.0 - (*a0 + *b0) / 2.0;
+                let (distance_before, distance_after) = (a0.distance(*b0), a1.distance(*b1));
+                // Fingers on top of each other can't pinch
+                if distance_before > 1.0 && distance_after > 1.0 {
+                    self.gesture.pinch *= distance_after / distance_before;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The positions of the fingers, always in the same order.
+    fn positions(&self) -> impl Iterator<Item = Vec2> + '_ {
+        let mut ids: Vec<_> = self.fingers.keys().copied().collect();
+        ids.sort_unstable();
+        ids.into_iter().map(|id| self.fingers[&id].position)
+    }
+
+    /// How many fingers are on the window.
+    pub fn touches(&self) -> usize {
+        self.fingers.len()
+    }
+
+    /// What the fingers did since the last call.
+    pub fn take_gesture(&mut self) -> Gesture {
+        std::mem::take(&mut self.gesture)
+    }
+
+    /// Forgets the fingers and what they did.
+    pub fn reset(&mut self) {
+        self.fingers.clear();
+        self.gesture = Gesture::default();
+    }
+}
@@ -0,0 +1,432 @@
+//! Transparent objects mixed with opaque ones. The opaque objects are drawn
+//! first, writing depth as usual. The transparent ones come after them,
+//! sorted from back to front by how far they are in front of the camera,
+//! so each one blends over everything behind it. They test depth without
+//! writing it, so they don't hide each other when the sort gets it wrong.
+//!
+//! The sort goes by each instance's position, not by triangle, so objects
+//! that go through each other look off from some angles. It's stable, so
+//! they at least look off the same way from one frame to the next.
+
+use std::{ops::Range, time::Duration};
+
+use glam::{Quat, Vec3};
+
+use crate::{
+    app::{App, GpuContext},
+    buffer::GrowableBuffer,
+    camera::{Camera, CameraUniform},
+    color,
+    error::AppError,
+    geometry::{self, Geometry},
+    instance::{Instance, TintedInstance, TintedInstanceRaw},
+    model::{Mesh, ModelVertex},
+    pipeline::{BlendMode, PipelineBuilder, RenderTargets},
+    shader::ShaderError,
+    texture::Texture,
+    uniform::UniformBuffer,
+};
+
+pub const TRANSPARENCY_SHADER_SOURCE: &str = include_str!("../shaders/transparency.wgsl");
+
+/// In degrees per second.
+const ORBIT_SPEED: f32 = 15.0;
+
+/// An instance of one of the scene's meshes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneObject {
+    /// Index into the meshes the scene was created with.
+    pub mesh: usize,
+    pub instance: TintedInstance,
+    /// `Premultiplied` objects need their color multiplied by its alpha.
+    pub blend_mode: BlendMode,
+}
+
+/// Instances drawn with one draw call, see `sort_draws`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrawBatch {
+    pub mesh: usize,
+    pub blend_mode: BlendMode,
+    /// Into the instances `sort_draws` returns.
+    pub instances: Range<u32>,
+}
+
+/// How far in front of `camera` `position` is, along the view direction.
+/// Negative behind it.
+pub fn view_depth(camera: &Camera, position: Vec3) -> f32 {
+    let forward = (camera.target - camera.eye).normalize_or_zero();
+    (position - camera.eye).dot(forward)
+}
+
+/// Sorts the `objects` the way they're drawn: the opaque ones first,
+/// grouped by mesh, then the transparent ones from the farthest to the
+/// closest to `camera`. Neighbours with the same mesh and blend mode share
+/// a batch. Objects as far away as each other keep their order.
+pub fn sort_draws(
+    objects: &[SceneObject],
+    camera: &Camera,
+) -> (Vec<TintedInstance>, Vec<DrawBatch>) {
+    let (mut opaque, mut transparent): (Vec<&SceneObject>, Vec<_>) = objects
+        .iter()
+        .partition(|object| !object.blend_mode.is_transparent());
+    // Both stable
+    opaque.sort_by_key(|object| object.mesh);
+    transparent.sort_by(|a, b| {
+        let depth = |object: &&SceneObject| view_depth(camera, object.instance.instance.position);
+        depth(b).total_cmp(&depth(a))
+    });
+
+    let mut instances = Vec::with_capacity(objects.len());
+    let mut batches: Vec<DrawBatch> = Vec::new();
+    for object in opaque.into_iter().chain(transparent) {
+        let index = instances.len() as u32;
+        instances.push(object.instance);
+        match batches.last_mut() {
+            Some(batch) if batch.mesh == object.mesh && batch.blend_mode == object.blend_mode => {
+                batch.instances.end = index + 1;
+            }
+            _ => batches.push(DrawBatch {
+                mesh: object.mesh,
+                blend_mode: object.blend_mode,
+                instances: index..index + 1,
+            }),
+        }
+    }
+    (instances, batches)
+}
+
+/// A few meshes and the objects made of them, transparent or not.
+pub struct TransparencyScene {
+    /// One for each of `BlendMode::ALL`.
+    pipelines: Vec<wgpu::RenderPipeline>,
+    camera_buffer: UniformBuffer<CameraUniform>,
+    camera_bind_group: wgpu::BindGroup,
+    meshes: Vec<Mesh>,
+    objects: Vec<SceneObject>,
+    /// The draws of the last `update`.
+    batches: Vec<DrawBatch>,
+    instances: GrowableBuffer<TintedInstanceRaw>,
+}
+
+impl TransparencyScene {
+    /// Draws objects made of `meshes` into `targets`, which need a depth
+    /// format.
+    pub fn new(
+        device: &wgpu::Device,
+        targets: RenderTargets,
+        meshes: &[Geometry],
+    ) -> Result<Self, ShaderError> {
+        let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("transparency_camera_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let camera_buffer =
+            UniformBuffer::new(device, "Transparency Camera Buffer", &CameraUniform::new());
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("transparency_camera_bind_group"),
+            layout: &camera_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.binding(),
+            }],
+        });
+
+        let layouts = [&camera_layout];
+        let pipeline = PipelineBuilder::with_targets("Opaque Pipeline", targets)
+            .shader("transparency.wgsl", TRANSPARENCY_SHADER_SOURCE)
+            .bind_group_layouts(&layouts)
+            .vertex_buffer(ModelVertex::desc())
+            .vertex_buffer(TintedInstanceRaw::desc());
+        let pipelines = BlendMode::ALL
+            .into_iter()
+            .map(|mode| {
+                let label = format!("{mode:?} Pipeline");
+                let pipeline = pipeline.clone().label(&label).blend_mode(mode);
+                // Glass can be seen from behind too
+                let pipeline = if mode.is_transparent() {
+                    pipeline.cull_mode(None)
+                } else {
+                    pipeline
+                };
+                pipeline.build(device)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            pipelines,
+            camera_buffer,
+            camera_bind_group,
+            meshes: meshes
+                .iter()
+                .enumerate()
+                .map(|(i, geometry)| {
+                    Mesh::from_geometry(device, &format!("Transparency Mesh {i}"), geometry, 0)
+                })
+                .collect(),
+            objects: Vec::new(),
+            batches: Vec::new(),
+            instances: GrowableBuffer::new(
+                device,
+                "Transparency Instance Buffer",
+                wgpu::BufferUsages::VERTEX,
+                &[],
+            ),
+        })
+    }
+
+    pub fn objects(&self) -> &[SceneObject] {
+        &self.objects
+    }
+
+    /// Shows up after the next `update`. Objects with a mesh the scene
+    /// doesn't have aren't drawn.
+    pub fn set_objects(&mut self, objects: Vec<SceneObject>) {
+        self.objects = objects;
+    }
+
+    /// The draws in the order they're made, see `sort_draws`.
+    pub fn batches(&self) -> &[DrawBatch] {
+        &self.batches
+    }
+
+    /// Sorts the objects for `camera` and uploads them. Call it every frame
+    /// the camera or the objects move.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, camera: &Camera) {
+        let mut uniform = CameraUniform::new();
+        uniform.update_view_proj(camera);
+        self.camera_buffer.write(queue, &uniform);
+
+        let (instances, batches) = sort_draws(&self.objects, camera);
+        let raw = instances
+            .iter()
+            .map(TintedInstance::to_raw)
+            .collect::<Vec<_>>();
+        self.instances.update(device, queue, &raw);
+        self.batches = batches;
+    }
+
+    /// Draws the opaque objects, which go first.
+    pub fn draw_opaque<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.draw(render_pass, false);
+    }
+
+    /// Draws the transparent objects over what's drawn already.
+    pub fn draw_transparent<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.draw(render_pass, true);
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, transparent: bool) {
+        if self.instances.is_empty() {
+            return;
+        }
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        self.instances.bind(render_pass, 1);
+        let batches = self
+            .batches
+            .iter()
+            .filter(|batch| batch.blend_mode.is_transparent() == transparent);
+        for batch in batches {
+            let Some(mesh) = self.meshes.get(batch.mesh) else {
+                continue;
+            };
+            let mode = BlendMode::ALL
+                .iter()
+                .position(|mode| *mode == batch.blend_mode)
+                .unwrap_or_default();
+            render_pass.set_pipeline(&self.pipelines[mode]);
+            mesh.draw(render_pass, batch.instances.clone());
+        }
+    }
+}
+
+/// The meshes of the demo: a cube and an upright quad.
+pub fn demo_meshes() -> Vec<Geometry> {
+    vec![geometry::cube(), geometry::plane(1)]
+}
+
+/// A few opaque cubes behind and between panes of tinted glass, one
+/// glowing additive pane and a premultiplied one, and two panes crossing
+/// each other.
+pub fn demo_objects() -> Vec<SceneObject> {
+    let cube = |x: f32, z: f32, color: [f32; 3]| SceneObject {
+        mesh: 0,
+        instance: TintedInstance {
+            instance: Instance {
+                position: Vec3::new(x, 0.0, z),
+                rotation: Quat::from_rotation_y(0.4 * x),
+            },
+            color: [color[0], color[1], color[2], 1.0],
+        },
+        blend_mode: BlendMode::Opaque,
+    };
+    // The plane lies flat, standing it up makes it face +Z
+    let pane = |position: Vec3, turn: f32, color: [f32; 4], blend_mode| SceneObject {
+        mesh: 1,
+        instance: TintedInstance {
+            instance: Instance {
+                position,
+                rotation: Quat::from_rotation_y(turn)
+                    * Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+            },
+            color,
+        },
+        blend_mode,
+    };
+    vec![
+        cube(-2.0, -2.0, [0.8, 0.8, 0.8]),
+        cube(0.0, -3.0, [0.9, 0.7, 0.2]),
+        cube(2.0, -2.0, [0.3, 0.6, 0.9]),
+        pane(
+            Vec3::new(-1.5, 0.0, 0.0),
+            0.0,
+            [1.0, 0.1, 0.1, 0.4],
+            BlendMode::AlphaBlend,
+        ),
+        pane(
+            Vec3::new(0.0, 0.0, 1.0),
+            0.0,
+            [0.1, 1.0, 0.1, 0.4],
+            BlendMode::AlphaBlend,
+        ),
+        pane(
+            Vec3::new(1.5, 0.0, 2.0),
+            0.0,
+            [0.1, 0.2, 1.0, 0.4],
+            BlendMode::AlphaBlend,
+        ),
+        pane(
+            Vec3::new(0.0, 1.5, -1.0),
+            0.0,
+            [1.0, 0.5, 0.1, 0.6],
+            BlendMode::Additive,
+        ),
+        // 0.5 alpha, the color already multiplied by it
+        pane(
+            Vec3::new(-2.5, 1.0, 2.5),
+            0.6,
+            [0.4, 0.05, 0.5, 0.5],
+            BlendMode::Premultiplied,
+        ),
+        pane(
+            Vec3::new(3.0, 0.5, 0.0),
+            0.5,
+            [0.9, 0.9, 0.1, 0.35],
+            BlendMode::AlphaBlend,
+        ),
+        pane(
+            Vec3::new(3.0, 0.5, 0.0),
+            2.07,
+            [0.1, 0.9, 0.9, 0.35],
+            BlendMode::AlphaBlend,
+        ),
+    ]
+}
+
+/// The transparency demo as an `App`, see `examples/transparency.rs`:
+/// `demo_objects` with the camera going around them, so the order of the
+/// panes keeps changing.
+pub struct TransparencyApp {
+    scene: TransparencyScene,
+    depth_texture: Texture,
+    camera: Camera,
+}
+
+impl TransparencyApp {
+    pub fn new(ctx: &GpuContext) -> Result<Self, AppError> {
+        let device = ctx.device();
+        let mut scene = TransparencyScene::new(
+            device,
+            RenderTargets {
+                color_format: ctx.render_format(),
+                depth_format: Some(Texture::DEPTH_FORMAT),
+                sample_count: 1,
+            },
+            &demo_meshes(),
+        )?;
+        scene.set_objects(demo_objects());
+        let size = ctx.size();
+        let camera = Camera {
+            eye: Vec3::new(0.0, 3.0, 9.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            aspect: size.width as f32 / size.height as f32,
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+        };
+        Ok(Self {
+            scene,
+            depth_texture: Texture::create_depth_texture(device, ctx.config(), 1, "depth_texture"),
+            camera,
+        })
+    }
+}
+
+impl App for TransparencyApp {
+    /// Panics where `new` would fail.
+    fn init(ctx: &GpuContext) -> Self {
+        match Self::new(ctx) {
+            Ok(app) => app,
+            Err(err) => panic!("Can't run the transparency demo: {err}"),
+        }
+    }
+
+    fn resize(&mut self, ctx: &GpuContext, size: winit::dpi::PhysicalSize<u32>) {
+        self.camera.aspect = size.width as f32 / size.height as f32;
+        self.depth_texture =
+            Texture::create_depth_texture(ctx.device(), ctx.config(), 1, "depth_texture");
+    }
+
+    fn update(&mut self, ctx: &GpuContext, dt: Duration) {
+        self.camera.orbit(ORBIT_SPEED * dt.as_secs_f32(), 0.0);
+        self.scene.update(ctx.device(), ctx.queue(), &self.camera);
+    }
+
+    fn render(
+        &mut self,
+        ctx: &GpuContext,
+        view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Transparency Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(color::clear_value_for_format(
+                        wgpu::Color {
+                            r: 0.1,
+                            g: 0.1,
+                            b: 0.12,
+                            a: 1.0,
+                        },
+                        ctx.render_format(),
+                    )),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.scene.draw_opaque(&mut render_pass);
+        self.scene.draw_transparent(&mut render_pass);
+    }
+}
@@ -0,0 +1,436 @@
+use std::marker::PhantomData;
+
+use thiserror::Error;
+use wgpu::util::DeviceExt;
+
+use crate::upload::UploadArena;
+
+/// The WGSL type of a uniform struct field, see `Uniform::FIELDS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WgslType {
+    F32,
+    U32,
+    I32,
+    Vec2,
+    Vec3,
+    Vec4,
+    /// Each column is a vec3, padded to 16 bytes like a vec4.
+    Mat3,
+    Mat4,
+}
+
+impl WgslType {
+    /// Alignment in bytes, from the WGSL spec's alignment table.
+    pub fn align(self) -> usize {
+        match self {
+            Self::F32 | Self::U32 | Self::I32 => 4,
+            Self::Vec2 => 8,
+            Self::Vec3 | Self::Vec4 | Self::Mat3 | Self::Mat4 => 16,
+        }
+    }
+
+    /// Size in bytes. A vec3 only takes 12, a scalar can follow it without
+    /// padding.
+    pub fn size(self) -> usize {
+        match self {
+            Self::F32 | Self::U32 | Self::I32 => 4,
+            Self::Vec2 => 8,
+            Self::Vec3 => 12,
+            Self::Vec4 => 16,
+            Self::Mat3 => 48,
+            Self::Mat4 => 64,
+        }
+    }
+}
+
+/// A field of the WGSL struct a `Uniform` mirrors. Padding fields only
+/// exist on the Rust side and aren't listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniformField {
+    pub name: &'static str,
+    /// Where the field is in the Rust struct, from `std::mem::offset_of!`.
+    pub offset: usize,
+    pub ty: WgslType,
+}
+
+impl UniformField {
+    pub const fn new(name: &'static str, offset: usize, ty: WgslType) -> Self {
+        Self { name, offset, ty }
+    }
+}
+
+/// A Rust struct that's uploaded as a WGSL uniform struct. `FIELDS`
+/// describes the WGSL struct so `check_layout` can compare the two.
+pub trait Uniform: bytemuck::Pod {
+    const FIELDS: &'static [UniformField];
+}
+
+/// Where the Rust and the WGSL layout of a `Uniform` differ.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LayoutError {
+    #[error("field `{field}` is at offset {actual}, WGSL expects it at {expected}")]
+    FieldOffset {
+        field: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("the struct is {actual} bytes, WGSL expects {expected}")]
+    Size { expected: usize, actual: usize },
+}
+
+/// Lays out `T::FIELDS` by the WGSL rules and checks the Rust struct
+/// matches. The size also has to be a multiple of 16, which WebGL2's
+/// uniform blocks need.
+pub fn check_layout<T: Uniform>() -> Result<(), LayoutError> {
+    let mut end = 0_usize;
+    let mut align = 16_usize;
+    for field in T::FIELDS {
+        let expected = end.next_multiple_of(field.ty.align());
+        if field.offset != expected {
+            return Err(LayoutError::FieldOffset {
+                field: field.name,
+                expected,
+                actual: field.offset,
+            });
+        }
+        end = expected + field.ty.size();
+        align = align.max(field.ty.align());
+    }
+
+    let expected = end.next_multiple_of(align);
+    let actual = std::mem::size_of::<T>();
+    if actual != expected {
+        return Err(LayoutError::Size { expected, actual });
+    }
+    Ok(())
+}
+
+/// Owns the buffer of a uniform. Debug builds check the layout of `T`
+/// against `T::FIELDS` when it's created, so padding mistakes show up right
+/// away instead of as garbled values in the shader.
+pub struct UniformBuffer<T> {
+    buffer: wgpu::Buffer,
+    _value: PhantomData<T>,
+}
+
+impl<T: Uniform> UniformBuffer<T> {
+    /// # Panics
+    ///
+    /// In debug builds, if `check_layout` fails for `T`.
+    pub fn new(device: &wgpu::Device, label: &str, value: &T) -> Self {
+        if cfg!(debug_assertions) {
+            if let Err(err) = check_layout::<T>() {
+                panic!("{label} doesn't match its WGSL struct: {err}");
+            }
+        }
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::bytes_of(value),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            buffer,
+            _value: PhantomData,
+        }
+    }
+
+    /// Replaces the contents, the shaders see it from the next submit on.
+    pub fn write(&self, queue: &wgpu::Queue, value: &T) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(value));
+    }
+
+    /// Like `write`, through `uploads` with a copy recorded into `encoder`.
+    pub fn upload(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        uploads: &mut UploadArena,
+        value: &T,
+    ) {
+        uploads.write(
+            device,
+            encoder,
+            &self.buffer,
+            0,
+            std::slice::from_ref(value),
+        );
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// The whole buffer, for bind group entries.
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        self.buffer.as_entire_binding()
+    }
+}
+
+/// A fixed number of values of a uniform in one buffer, each in its own
+/// slot at a dynamic offset. Unlike `DynamicUniform` the bind group is left
+/// to the caller, so the values can share one with other bindings.
+pub struct UniformSlots<T> {
+    buffer: wgpu::Buffer,
+    stride: u32,
+    len: usize,
+    _value: PhantomData<T>,
+}
+
+impl<T: Uniform> UniformSlots<T> {
+    /// A slot for each of `values`, with it in it.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if `check_layout` fails for `T`.
+    pub fn new(device: &wgpu::Device, label: &str, values: &[T]) -> Self {
+        if cfg!(debug_assertions) {
+            if let Err(err) = check_layout::<T>() {
+                panic!("{label} doesn't match its WGSL struct: {err}");
+            }
+        }
+
+        let size = std::mem::size_of::<T>();
+        let stride = dynamic_stride(
+            size as u64,
+            device.limits().min_uniform_buffer_offset_alignment,
+        );
+        let mut contents = vec![0; stride as usize * values.len().max(1)];
+        for (slot, value) in contents.chunks_mut(stride as usize).zip(values) {
+            slot[..size].copy_from_slice(bytemuck::bytes_of(value));
+        }
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: &contents,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            buffer,
+            stride,
+            len: values.len(),
+            _value: PhantomData,
+        }
+    }
+
+    /// The dynamic offset to bind the value in `slot` at.
+    pub fn offset(&self, slot: usize) -> u32 {
+        slot as u32 * self.stride
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Replaces the value in `slot`, the shaders see it from the next submit
+    /// on.
+    pub fn write(&self, queue: &wgpu::Queue, slot: usize, value: &T) {
+        queue.write_buffer(
+            &self.buffer,
+            u64::from(self.offset(slot)),
+            bytemuck::bytes_of(value),
+        );
+    }
+
+    /// Like `write`, through `uploads` with a copy recorded into `encoder`.
+    pub fn upload(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        uploads: &mut UploadArena,
+        slot: usize,
+        value: &T,
+    ) {
+        uploads.write(
+            device,
+            encoder,
+            &self.buffer,
+            u64::from(self.offset(slot)),
+            std::slice::from_ref(value),
+        );
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// One value's worth of the buffer, for a bind group entry with a
+    /// dynamic offset.
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+        })
+    }
+}
+
+/// How far apart values of `size` bytes have to be in a buffer for dynamic
+/// offsets, which must be multiples of `alignment`. That's the device's
+/// `min_uniform_buffer_offset_alignment`, usually 256.
+pub fn dynamic_stride(size: u64, alignment: u32) -> u32 {
+    size.max(1).next_multiple_of(u64::from(alignment)) as u32
+}
+
+/// Many values of a uniform in one buffer, each at its own dynamic offset.
+/// One bind group is enough for all of them, drawing value `i` only takes
+/// `set_bind_group(index, uniform.bind_group(), &[offset])` with the offset
+/// `push` returned for it.
+///
+/// Values are pushed every frame after a `clear` and then uploaded at once.
+/// When they don't fit anymore `upload` moves them to a bigger buffer at the
+/// same offsets, with a new bind group. Passes recorded with the old one
+/// keep it and its buffer alive until the GPU is done with them, so their
+/// offsets stay valid.
+pub struct DynamicUniform<T> {
+    label: String,
+    layout: wgpu::BindGroupLayout,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    stride: u32,
+    /// The pushed values, `stride` bytes apart.
+    contents: Vec<u8>,
+    _value: PhantomData<T>,
+}
+
+impl<T: Uniform> DynamicUniform<T> {
+    /// # Panics
+    ///
+    /// In debug builds, if `check_layout` fails for `T`.
+    pub fn new(device: &wgpu::Device, label: &str, visibility: wgpu::ShaderStages) -> Self {
+        if cfg!(debug_assertions) {
+            if let Err(err) = check_layout::<T>() {
+                panic!("{label} doesn't match its WGSL struct: {err}");
+            }
+        }
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{label} Layout")),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(Self::size()),
+                },
+                count: None,
+            }],
+        });
+        let stride = dynamic_stride(
+            Self::size(),
+            device.limits().min_uniform_buffer_offset_alignment,
+        );
+        let (buffer, bind_group) = Self::create_buffer(device, label, &layout, stride, 1);
+        Self {
+            label: label.to_owned(),
+            layout,
+            buffer,
+            bind_group,
+            stride,
+            contents: Vec::new(),
+            _value: PhantomData,
+        }
+    }
+
+    fn size() -> u64 {
+        std::mem::size_of::<T>() as u64
+    }
+
+    /// A buffer with room for `capacity` values and a bind group for it.
+    fn create_buffer(
+        device: &wgpu::Device,
+        label: &str,
+        layout: &wgpu::BindGroupLayout,
+        stride: u32,
+        capacity: usize,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: u64::from(stride) * capacity as u64,
+            // COPY_SRC isn't needed for drawing, only so that tests can copy
+            // the values out and check where they ended up
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{label} Bind Group")),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(Self::size()),
+                }),
+            }],
+        });
+        (buffer, bind_group)
+    }
+
+    /// How far apart the values are, a multiple of the device's
+    /// `min_uniform_buffer_offset_alignment`.
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// How many values were pushed since the last `clear`.
+    pub fn len(&self) -> usize {
+        self.contents.len() / self.stride as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+
+    /// How many values fit into the buffer before it has to grow.
+    pub fn capacity(&self) -> usize {
+        (self.buffer.size() / u64::from(self.stride)) as usize
+    }
+
+    /// Forgets the values, the offsets handed out start from 0 again.
+    pub fn clear(&mut self) {
+        self.contents.clear();
+    }
+
+    /// Adds a value and returns the dynamic offset to bind it at. The
+    /// shaders only see it after the next `upload`.
+    pub fn push(&mut self, value: &T) -> u32 {
+        let offset = self.contents.len();
+        self.contents.resize(offset + self.stride as usize, 0);
+        self.contents[offset..offset + Self::size() as usize]
+            .copy_from_slice(bytemuck::bytes_of(value));
+        offset as u32
+    }
+
+    /// Writes the pushed values to the buffer, growing it first if they don't
+    /// fit.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.len() > self.capacity() {
+            let capacity = self.len().next_power_of_two();
+            (self.buffer, self.bind_group) =
+                Self::create_buffer(device, &self.label, &self.layout, self.stride, capacity);
+        }
+        if !self.contents.is_empty() {
+            queue.write_buffer(&self.buffer, 0, &self.contents);
+        }
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    /// Changes when the buffer grows, so get it after `upload`.
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+}
@@ -0,0 +1,256 @@
+//! Uploads through staging buffers we keep around, instead of the ones
+//! `queue.write_buffer` allocates inside wgpu for every write. The data is
+//! written into a mapped staging buffer right away and copied to where it
+//! belongs by the encoder it's recorded into.
+
+use std::{fmt, sync::mpsc, time::Duration};
+
+/// How big the staging buffers are, bigger writes get one of their own.
+pub const DEFAULT_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// A staging buffer, mapped while it's written to.
+struct Chunk {
+    id: usize,
+    buffer: wgpu::Buffer,
+    /// Where the next write goes.
+    offset: u64,
+}
+
+/// Hands out room in staging buffers and reuses them once the GPU copied
+/// out of them, like `wgpu::util::StagingBelt`.
+///
+/// After recording the writes call `finish`, then submit the encoder, then
+/// `recall`. The staging buffers come back with a later `device.poll`.
+pub struct UploadArena {
+    chunk_size: u64,
+    /// Mapped and being written to this frame.
+    active: Vec<Chunk>,
+    /// Unmapped, waiting for the encoder to be submitted.
+    closed: Vec<Chunk>,
+    /// Submitted, they send their id once they're mapped again.
+    in_flight: Vec<Chunk>,
+    /// Mapped again and empty.
+    free: Vec<Chunk>,
+    sender: mpsc::Sender<usize>,
+    receiver: mpsc::Receiver<usize>,
+    next_id: usize,
+}
+
+impl UploadArena {
+    pub fn new(chunk_size: u64) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            chunk_size: chunk_size.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+            active: Vec::new(),
+            closed: Vec::new(),
+            in_flight: Vec::new(),
+            free: Vec::new(),
+            sender,
+            receiver,
+            next_id: 0,
+        }
+    }
+
+    /// Writes `data` to `target` at `offset` with a copy recorded into
+    /// `encoder`. `target` needs `BufferUsages::COPY_DST`.
+    ///
+    /// # Panics
+    ///
+    /// If `offset` or the size of `data` isn't a multiple of
+    /// `wgpu::COPY_BUFFER_ALIGNMENT`, copies can't do that.
+    pub fn write<T: bytemuck::Pod>(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[T],
+    ) {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        let size = bytes.len() as u64;
+        assert!(
+            offset.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+                && size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+            "uploads must be aligned to {} bytes, got {size} bytes at {offset}",
+            wgpu::COPY_BUFFER_ALIGNMENT
+        );
+        if size == 0 {
+            return;
+        }
+
+        let chunk = self.chunk_with_room(device, size);
+        chunk
+            .buffer
+            .slice(chunk.offset..chunk.offset + size)
+            .get_mapped_range_mut()
+            .copy_from_slice(bytes);
+        encoder.copy_buffer_to_buffer(&chunk.buffer, chunk.offset, target, offset, size);
+        // Mapped ranges have to start at a multiple of 8
+        chunk.offset = (chunk.offset + size).next_multiple_of(wgpu::MAP_ALIGNMENT);
+    }
+
+    /// An active chunk with `size` bytes left, a free or a new one if none of
+    /// them has.
+    fn chunk_with_room(&mut self, device: &wgpu::Device, size: u64) -> &mut Chunk {
+        let fits = |chunk: &Chunk| chunk.offset + size <= chunk.buffer.size();
+        if let Some(index) = self.active.iter().position(fits) {
+            return &mut self.active[index];
+        }
+        let chunk = match self.free.iter().position(fits) {
+            Some(index) => self.free.swap_remove(index),
+            None => {
+                self.next_id += 1;
+                Chunk {
+                    id: self.next_id,
+                    buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Upload Chunk"),
+                        size: size.max(self.chunk_size),
+                        usage: wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+                        mapped_at_creation: true,
+                    }),
+                    offset: 0,
+                }
+            }
+        };
+        self.active.push(chunk);
+        self.active.last_mut().unwrap()
+    }
+
+    /// Unmaps the staging buffers written to, call it before submitting the
+    /// encoders the writes were recorded into.
+    pub fn finish(&mut self) {
+        for chunk in self.active.drain(..) {
+            chunk.buffer.unmap();
+            self.closed.push(chunk);
+        }
+    }
+
+    /// Maps the staging buffers of the submitted writes again, they'll be
+    /// reused once the GPU is done copying out of them. Call it after
+    /// submitting.
+    pub fn recall(&mut self) {
+        while let Ok(id) = self.receiver.try_recv() {
+            if let Some(index) = self.in_flight.iter().position(|chunk| chunk.id == id) {
+                let mut chunk = self.in_flight.swap_remove(index);
+                chunk.offset = 0;
+                self.free.push(chunk);
+            }
+        }
+
+        for chunk in self.closed.drain(..) {
+            let sender = self.sender.clone();
+            let id = chunk.id;
+            chunk
+                .buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Write, move |result| {
+                    // A failed mapping only means the buffer isn't reused
+                    if result.is_ok() {
+                        let _ = sender.send(id);
+                    }
+                });
+            self.in_flight.push(chunk);
+        }
+    }
+
+    /// How many staging buffers there are, however they're used right now.
+    pub fn chunk_count(&self) -> usize {
+        self.active.len() + self.closed.len() + self.in_flight.len() + self.free.len()
+    }
+}
+
+impl Default for UploadArena {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHUNK_SIZE)
+    }
+}
+
+/// How long uploading took per frame through either path, see
+/// `benchmark`.
+#[derive(Clone, Debug)]
+pub struct UploadBenchReport {
+    pub frames: u32,
+    pub writes_per_frame: u32,
+    pub write_buffer: Duration,
+    pub arena: Duration,
+}
+
+impl fmt::Display for UploadBenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        writeln!(
+            f,
+            "Frames        {} with {} writes of 64 bytes each",
+            self.frames, self.writes_per_frame
+        )?;
+        writeln!(
+            f,
+            "write_buffer  {:>9.3} ms per frame",
+            ms(self.write_buffer)
+        )?;
+        write!(f, "Upload arena  {:>9.3} ms per frame", ms(self.arena))
+    }
+}
+
+/// Uploads `writes_per_frame` matrices into a buffer each frame, once
+/// through `queue.write_buffer` and once through an `UploadArena`, waiting
+/// for the GPU after every frame.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn benchmark(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    frames: u32,
+    writes_per_frame: u32,
+) -> UploadBenchReport {
+    const WRITE_SIZE: u64 = std::mem::size_of::<[[f32; 4]; 4]>() as u64;
+
+    let target = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Upload Bench Target"),
+        size: WRITE_SIZE * u64::from(writes_per_frame.max(1)),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let matrix = |frame: u32, write: u32| {
+        glam::Mat4::from_translation(glam::Vec3::new(frame as f32, write as f32, 0.0))
+            .to_cols_array_2d()
+    };
+    let offsets = (0..writes_per_frame).map(|write| (write, u64::from(write) * WRITE_SIZE));
+    let frame = |record: &mut dyn FnMut(&mut wgpu::CommandEncoder)| {
+        let start = web_time::Instant::now();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Upload Bench Encoder"),
+        });
+        record(&mut encoder);
+        let submission = queue.submit(std::iter::once(encoder.finish()));
+        device.poll(wgpu::Maintain::WaitForSubmissionIndex(submission));
+        start.elapsed()
+    };
+
+    let mut write_buffer = Duration::ZERO;
+    for i in 0..frames {
+        write_buffer += frame(&mut |_| {
+            for (write, offset) in offsets.clone() {
+                queue.write_buffer(&target, offset, bytemuck::cast_slice(&matrix(i, write)));
+            }
+        });
+    }
+
+    let mut uploads = UploadArena::default();
+    let mut arena = Duration::ZERO;
+    for i in 0..frames {
+        arena += frame(&mut |encoder| {
+            for (write, offset) in offsets.clone() {
+                uploads.write(device, encoder, &target, offset, &matrix(i, write));
+            }
+            uploads.finish();
+        });
+        uploads.recall();
+    }
+
+    UploadBenchReport {
+        frames,
+        writes_per_frame,
+        write_buffer: write_buffer / frames.max(1),
+        arena: arena / frames.max(1),
+    }
+}
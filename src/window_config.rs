@@ -0,0 +1,167 @@
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize, Position, Size},
+    event_loop::EventLoopWindowTarget,
+    monitor::MonitorHandle,
+    window::{Fullscreen, Icon, Window, WindowBuilder},
+};
+
+use crate::error::AppError;
+
+/// How the window the app renders to gets created.
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    pub title: String,
+    /// `None` lets the platform pick.
+    pub inner_size: Option<Size>,
+    /// Of the top left corner of the window, `None` lets the platform pick.
+    pub position: Option<Position>,
+    pub min_inner_size: Option<Size>,
+    pub max_inner_size: Option<Size>,
+    pub resizable: bool,
+    pub decorations: bool,
+    pub maximized: bool,
+    /// Opens the window borderless fullscreen on the current monitor.
+    pub fullscreen: bool,
+    /// A PNG encoded image.
+    pub icon: Option<Vec<u8>>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "wgpu-learning".to_owned(),
+            inner_size: None,
+            position: None,
+            min_inner_size: None,
+            max_inner_size: None,
+            resizable: true,
+            decorations: true,
+            maximized: false,
+            fullscreen: false,
+            icon: None,
+        }
+    }
+}
+
+impl WindowConfig {
+    /// Creates the window. It has its requested size right away, so the
+    /// surface can be configured with it.
+    /// `event_loop` can also be the target passed to the event handler, to
+    /// open windows while the loop runs.
+    pub fn build(&self, event_loop: &EventLoopWindowTarget<()>) -> Result<Window, AppError> {
+        Ok(self.builder(event_loop)?.build(event_loop)?)
+    }
+
+    /// The builder `build` creates the window with, for settings this config
+    /// doesn't have. A `position` off every monitor of `event_loop` is
+    /// replaced by one centering the window on the primary monitor.
+    pub fn builder(
+        &self,
+        event_loop: &EventLoopWindowTarget<()>,
+    ) -> Result<WindowBuilder, AppError> {
+        let icon = self.icon.as_deref().map(load_icon).transpose()?;
+
+        let mut builder = WindowBuilder::new()
+            .with_title(&self.title)
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations)
+            .with_maximized(self.maximized)
+            .with_fullscreen(self.fullscreen.then_some(Fullscreen::Borderless(None)))
+            .with_window_icon(icon);
+        if let Some(size) = self.inner_size {
+            builder = builder.with_inner_size(size);
+        }
+        if let Some(position) = self.position {
+            let monitors: Vec<_> = event_loop
+                .available_monitors()
+                .map(|monitor| MonitorArea::of(&monitor))
+                .collect();
+            let primary = event_loop.primary_monitor();
+            // Settings have physical positions, logical ones are a guess
+            let scale_factor = primary.as_ref().map_or(1.0, MonitorHandle::scale_factor);
+            let size = self
+                .inner_size
+                .map_or(FALLBACK_SIZE, |size| size.to_physical(scale_factor));
+            let position = visible_position(
+                position.to_physical(scale_factor),
+                size,
+                &monitors,
+                primary.as_ref().map(MonitorArea::of),
+            );
+            builder = builder.with_position(position);
+        }
+        if let Some(size) = self.min_inner_size {
+            builder = builder.with_min_inner_size(size);
+        }
+        if let Some(size) = self.max_inner_size {
+            builder = builder.with_max_inner_size(size);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// How big `builder` assumes windows without an `inner_size` are, what
+/// winit usually picks.
+const FALLBACK_SIZE: PhysicalSize<u32> = PhysicalSize::new(800, 600);
+
+/// Where a monitor is on the desktop, in physical pixels. winit doesn't know
+/// the work areas without the task bars and docks, so it's the whole
+/// monitor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MonitorArea {
+    pub position: PhysicalPosition<i32>,
+    pub size: PhysicalSize<u32>,
+}
+
+impl MonitorArea {
+    pub fn of(monitor: &MonitorHandle) -> Self {
+        Self {
+            position: monitor.position(),
+            size: monitor.size(),
+        }
+    }
+
+    pub fn contains(&self, point: PhysicalPosition<i32>) -> bool {
+        let (x, y) = (i64::from(point.x), i64::from(point.y));
+        let (left, top) = (i64::from(self.position.x), i64::from(self.position.y));
+        (left..left + i64::from(self.size.width)).contains(&x)
+            && (top..top + i64::from(self.size.height)).contains(&y)
+    }
+
+    /// The position of a window of `size` in the middle, or the top left
+    /// corner if it's bigger than the monitor.
+    pub fn center(&self, size: PhysicalSize<u32>) -> PhysicalPosition<i32> {
+        let offset = |monitor: u32, window: u32| (monitor.saturating_sub(window) / 2) as i32;
+        PhysicalPosition::new(
+            self.position.x + offset(self.size.width, size.width),
+            self.position.y + offset(self.size.height, size.height),
+        )
+    }
+}
+
+/// `position` if the top left corner of a window there is on one of the
+/// `monitors`, so it can be grabbed. Otherwise, e.g. when the monitor it
+/// was on is gone, the window of `size` gets centered on `primary`, or on
+/// the first monitor if there's no primary one. Positions are kept if
+/// there are no monitors to check against.
+pub fn visible_position(
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+    monitors: &[MonitorArea],
+    primary: Option<MonitorArea>,
+) -> PhysicalPosition<i32> {
+    if monitors.is_empty() || monitors.iter().any(|monitor| monitor.contains(position)) {
+        return position;
+    }
+    log::info!("The window was off every monitor, centering it");
+    primary.unwrap_or(monitors[0]).center(size)
+}
+
+/// Decodes a PNG into a window icon. winit rejects icons whose dimensions
+/// don't match their pixel data, which we pass on as an error.
+pub fn load_icon(png: &[u8]) -> Result<Icon, AppError> {
+    let image = image::load_from_memory_with_format(png, image::ImageFormat::Png)?.to_rgba8();
+    let (width, height) = image.dimensions();
+    Ok(Icon::from_rgba(image.into_raw(), width, height)?)
+}
@@ -0,0 +1,34 @@
+// Adapters can't be enumerated on the web
+#![cfg(not(target_arch = "wasm32"))]
+
+use wgpu_learning::adapter::{list_adapters, select_adapter, AdapterSelection};
+
+#[test]
+fn missing_adapters_are_descriptive_errors() {
+    let instance = wgpu::Instance::default();
+
+    let err = pollster::block_on(select_adapter(
+        &instance,
+        &AdapterSelection::ByIndex(usize::MAX),
+        None,
+    ))
+    .unwrap_err()
+    .to_string();
+    assert!(
+        err.starts_with(&format!("No adapter at index {}", usize::MAX)),
+        "{err}"
+    );
+
+    let err = pollster::block_on(select_adapter(
+        &instance,
+        &AdapterSelection::ByNameSubstring("no such gpu".to_owned()),
+        None,
+    ))
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("\"no such gpu\""), "{err}");
+    // Every available adapter is listed
+    for info in list_adapters() {
+        assert!(err.contains(&info.name), "{err}");
+    }
+}
@@ -0,0 +1,36 @@
+use wgpu::CompositeAlphaMode;
+use wgpu_learning::alpha_mode::{is_transparent, select_alpha_mode};
+
+#[test]
+fn transparent_windows_prefer_premultiplied_alpha() {
+    let all = [
+        CompositeAlphaMode::Opaque,
+        CompositeAlphaMode::PostMultiplied,
+        CompositeAlphaMode::PreMultiplied,
+    ];
+    assert_eq!(
+        select_alpha_mode(true, &all),
+        CompositeAlphaMode::PreMultiplied
+    );
+    assert_eq!(
+        select_alpha_mode(true, &all[..2]),
+        CompositeAlphaMode::PostMultiplied
+    );
+    // Opaque windows ignore the surface's default
+    assert_eq!(
+        select_alpha_mode(false, &all[1..]),
+        CompositeAlphaMode::PostMultiplied
+    );
+    assert_eq!(select_alpha_mode(false, &all), CompositeAlphaMode::Opaque);
+}
+
+#[test]
+fn without_transparent_modes_windows_stay_opaque() {
+    let mode = select_alpha_mode(true, &[CompositeAlphaMode::Opaque]);
+    assert_eq!(mode, CompositeAlphaMode::Opaque);
+    assert!(!is_transparent(mode));
+
+    let mode = select_alpha_mode(true, &[CompositeAlphaMode::Inherit]);
+    assert_eq!(mode, CompositeAlphaMode::Inherit);
+    assert!(!is_transparent(mode));
+}
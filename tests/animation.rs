@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use glam::{Mat4, Quat, Vec3};
+use wgpu_learning::{
+    animation::{
+        self, AnimationError, Animator, Channel, Clip, Interpolation, JointBinding, Keyframes,
+        Node, Skeleton, Skin, Transform, MAX_UNIFORM_JOINTS,
+    },
+    light::{self, LightBinding},
+    pipeline::SHADER_SOURCE,
+    shader,
+};
+
+fn node(name: &str, parent: Option<usize>, translation: Vec3) -> Node {
+    Node {
+        name: name.to_owned(),
+        parent,
+        rest: Transform {
+            translation,
+            ..Transform::default()
+        },
+    }
+}
+
+/// An arm: the elbow sits 1 up from the shoulder, which moves along x from
+/// 0 to 2 over a second.
+fn arm(interpolation: Interpolation) -> Skeleton {
+    Skeleton {
+        nodes: vec![
+            // The child comes first, the animator has to sort them
+            node("elbow", Some(1), Vec3::Y),
+            node("shoulder", None, Vec3::ZERO),
+        ],
+        skins: vec![Skin {
+            joints: vec![1, 0],
+            inverse_bind_matrices: vec![Mat4::IDENTITY, Mat4::from_translation(-Vec3::Y)],
+        }],
+        clips: vec![
+            Clip {
+                name: "Swing".to_owned(),
+                channels: vec![Channel {
+                    node: 1,
+                    times: vec![0.0, 1.0],
+                    keyframes: Keyframes::Translation(vec![Vec3::ZERO, Vec3::new(2.0, 0.0, 0.0)]),
+                    interpolation,
+                }],
+            },
+            Clip {
+                name: "Twist".to_owned(),
+                channels: vec![Channel {
+                    node: 0,
+                    times: vec![0.0, 2.0],
+                    keyframes: Keyframes::Rotation(vec![
+                        Quat::IDENTITY,
+                        Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+                    ]),
+                    interpolation,
+                }],
+            },
+        ],
+    }
+}
+
+fn assert_translation(matrix: Mat4, expected: Vec3) {
+    let translation = matrix.w_axis.truncate();
+    assert!(
+        (translation - expected).length() < 1e-5,
+        "{translation} != {expected}"
+    );
+}
+
+#[test]
+fn the_rest_pose_has_identity_joints() {
+    let animator = Animator::new(arm(Interpolation::Linear));
+    assert_eq!(animator.playing(), None);
+    assert_eq!(animator.joint_matrices().len(), 2);
+    for &matrix in animator.joint_matrices() {
+        assert!(matrix.abs_diff_eq(Mat4::IDENTITY, 1e-6), "{matrix}");
+    }
+}
+
+#[test]
+fn linear_keyframes_are_interpolated_and_children_follow() {
+    let mut animator = Animator::new(arm(Interpolation::Linear));
+    animator.play("Swing", false).unwrap();
+    animator.update(Duration::from_millis(250));
+    assert_eq!(animator.playing(), Some("Swing"));
+    // The shoulder moved a quarter of the way, and the elbow with it
+    assert_translation(animator.joint_matrices()[0], Vec3::new(0.5, 0.0, 0.0));
+    assert_translation(animator.joint_matrices()[1], Vec3::new(0.5, 0.0, 0.0));
+
+    // Without looping the last keyframe holds
+    animator.update(Duration::from_secs(3));
+    assert_eq!(animator.time(), 1.0);
+    assert_translation(animator.joint_matrices()[0], Vec3::new(2.0, 0.0, 0.0));
+
+    animator.stop();
+    assert_translation(animator.joint_matrices()[0], Vec3::ZERO);
+}
+
+#[test]
+fn step_keyframes_hold_until_the_next_one() {
+    let mut animator = Animator::new(arm(Interpolation::Step));
+    animator.play("Swing", true).unwrap();
+    animator.update(Duration::from_millis(900));
+    assert_translation(animator.joint_matrices()[0], Vec3::ZERO);
+
+    // Looping wraps around to the start
+    animator.update(Duration::from_millis(300));
+    assert!((animator.time() - 0.2).abs() < 1e-5, "{}", animator.time());
+}
+
+#[test]
+fn switching_clips_starts_from_the_rest_pose() {
+    let mut animator = Animator::new(arm(Interpolation::Linear));
+    animator.play("Swing", false).unwrap();
+    animator.update(Duration::from_secs(1));
+    animator.play("Twist", false).unwrap();
+    assert_eq!(animator.time(), 0.0);
+    assert_translation(animator.joint_matrices()[0], Vec3::ZERO);
+
+    // Halfway the elbow has turned by 45 degrees
+    animator.update(Duration::from_secs(1));
+    let elbow = animator.joint_matrices()[1];
+    let halfway = Vec3::new(1.0, 0.0, -1.0).normalize();
+    assert!(
+        elbow.x_axis.truncate().abs_diff_eq(halfway, 1e-5),
+        "{elbow}"
+    );
+
+    assert_eq!(
+        animator.play("Jump", true),
+        Err(AnimationError::UnknownClip("Jump".to_owned()))
+    );
+    assert_eq!(animator.playing(), Some("Twist"));
+}
+
+#[test]
+fn the_uniform_fallback_limits_the_joints() {
+    assert!(JointBinding::Storage.check_joint_count(10_000).is_ok());
+    assert!(JointBinding::Uniform
+        .check_joint_count(MAX_UNIFORM_JOINTS)
+        .is_ok());
+    let err = JointBinding::Uniform
+        .check_joint_count(MAX_UNIFORM_JOINTS + 1)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        AnimationError::TooManyJoints {
+            count: MAX_UNIFORM_JOINTS + 1,
+            max: MAX_UNIFORM_JOINTS
+        }
+    );
+    assert!(err.to_string().contains("129 joints"), "{err}");
+}
+
+#[test]
+fn the_skinned_shader_validates_with_both_joint_bindings() {
+    for joints in [JointBinding::Storage, JointBinding::Uniform] {
+        for lights in [LightBinding::Storage, LightBinding::Uniform] {
+            let source = light::shader_source(SHADER_SOURCE, lights);
+            let source = animation::shader_source(&source, joints);
+            if let Err(err) = shader::validate(&source, "shader.wgsl") {
+                panic!("shader.wgsl with {joints:?} joints and {lights:?} lights: {err}");
+            }
+        }
+    }
+    // The fallback must actually replace the storage buffer
+    assert!(
+        !animation::shader_source(SHADER_SOURCE, JointBinding::Uniform)
+            .contains("var<storage, read> joints")
+    );
+}
@@ -0,0 +1,71 @@
+use std::{io, path::Path};
+
+use wgpu_learning::{
+    asset_source::{AssetSource, EmbeddedSource, FsSource},
+    pipeline::ShaderSources,
+};
+
+fn crate_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn the_embedded_files_are_the_ones_in_the_crate() {
+    let files = FsSource::new(crate_dir());
+    let embedded = EmbeddedSource;
+    assert!(embedded.paths().count() > 0);
+    for path in embedded.paths() {
+        let path = Path::new(path);
+        assert_eq!(
+            embedded.read(path).unwrap(),
+            files.read(path).unwrap(),
+            "{}",
+            path.display()
+        );
+    }
+    assert_eq!(embedded.file_path(Path::new("shaders")), None);
+
+    // The shaders reloaded from the binary are the ones it was built with
+    assert_eq!(
+        ShaderSources::load(&embedded).unwrap(),
+        ShaderSources::embedded()
+    );
+}
+
+#[test]
+fn embedded_paths_are_normalized() {
+    let embedded = EmbeddedSource;
+    let obj = embedded.read(Path::new("assets/cube/cube.obj")).unwrap();
+    assert_eq!(
+        embedded
+            .read(Path::new("assets/skybox/../cube/./cube.obj"))
+            .unwrap(),
+        obj
+    );
+    let err = embedded
+        .read(Path::new("assets/cube/missing.png"))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn files_are_read_relative_to_the_base_directory() {
+    let files = FsSource::new(crate_dir().join("assets"));
+    assert_eq!(
+        files.read(Path::new("cube/cube.mtl")).unwrap(),
+        std::fs::read(crate_dir().join("assets/cube/cube.mtl")).unwrap()
+    );
+    assert_eq!(
+        files.file_path(Path::new("cube")),
+        Some(crate_dir().join("assets/cube"))
+    );
+    // Absolute paths stay what they are
+    let absolute = crate_dir().join("Cargo.toml");
+    assert!(files.read(&absolute).is_ok());
+    assert_eq!(
+        files.read(Path::new("missing.png")).unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+    // Tests don't run next to the assets, so it falls back to the crate
+    assert_eq!(FsSource::locate().base_dir(), crate_dir());
+}
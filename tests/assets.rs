@@ -0,0 +1,211 @@
+// The web loads through fetch, which needs a page
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use wgpu_learning::{
+    asset_source::{AssetSource, FsSource},
+    assets::{AssetError, AssetLoader, AssetState, Assets},
+    model::{Material, Model},
+};
+
+fn cube_texture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/cube/cube-diffuse.png")
+}
+
+/// A directory with `found.png` and an OBJ of two triangles for each of
+/// `names`, the first one with the diffuse texture `found.png` and the
+/// second one with one that doesn't exist.
+fn write_objs(test: &str, names: &[&str]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wgpu_learning_{test}_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(cube_texture(), dir.join("found.png")).unwrap();
+    std::fs::write(
+        dir.join("two.mtl"),
+        "newmtl found\nmap_Kd found.png\nnewmtl lost\nmap_Kd lost.png\n",
+    )
+    .unwrap();
+    for name in names {
+        std::fs::write(
+            dir.join(format!("{name}.obj")),
+            "mtllib two.mtl\n\
+             v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\n\
+             o found\nusemtl found\nf 1/1/1 2/2/1 3/3/1\n\
+             o lost\nusemtl lost\nf 1/1/1 2/2/1 3/3/1\n",
+        )
+        .unwrap();
+    }
+    dir
+}
+
+fn diffuse_size(model: &Model, material: usize) -> (u32, u32) {
+    let texture = &model.materials[material].diffuse_texture.texture;
+    (texture.width(), texture.height())
+}
+
+#[test]
+fn images_load_in_the_background_or_fail() {
+    let mut loader = AssetLoader::new(Arc::new(FsSource::new("")));
+    let found = loader.load_image(cube_texture()).unwrap();
+    let lost = loader.load_image("there/is/no/such/texture.png").unwrap();
+    assert_ne!(found, lost);
+    // Nothing finishes before the main thread took the results
+    assert_eq!(loader.state(found), AssetState::Loading);
+    assert_eq!(loader.loading(), 2);
+
+    let finished = loader.wait();
+    assert_eq!(finished.len(), 2);
+    assert_eq!(loader.loading(), 0);
+    assert_eq!(loader.state(found), AssetState::Loaded);
+    match loader.state(lost) {
+        AssetState::Failed(reason) => assert!(reason.contains("texture.png"), "{reason}"),
+        state => panic!("expected the missing texture to fail, got {state:?}"),
+    }
+
+    let (_, image) = finished
+        .iter()
+        .find(|(handle, _)| *handle == found)
+        .unwrap();
+    let (width, height) = image::image_dimensions(cube_texture()).unwrap();
+    let image = image.as_ref().unwrap();
+    assert_eq!((image.width(), image.height()), (width, height));
+    assert!(loader.finished().is_empty());
+}
+
+/// Panics on every read, which takes the loader thread down with it.
+struct PanickingSource;
+
+impl AssetSource for PanickingSource {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        panic!("can't read {}", path.display());
+    }
+}
+
+#[test]
+fn a_stopped_loader_refuses_new_images() {
+    let mut loader = AssetLoader::new(Arc::new(PanickingSource));
+    let first = loader.load_image("first.png").unwrap();
+    // Returns once the thread is gone, without the image
+    assert!(loader.wait().is_empty());
+    assert_eq!(loader.state(first), AssetState::Loading);
+
+    // The jobs may still be taken until the thread finished unwinding
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let err = loop {
+        match loader.load_image("second.png") {
+            Err(err) => break err,
+            Ok(_) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(10)),
+            Ok(_) => panic!("the loader still takes images"),
+        }
+    };
+    assert!(matches!(err, AssetError::LoaderStopped), "{err}");
+}
+
+#[test]
+fn models_draw_placeholders_until_their_textures_arrive() {
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+    let dir = write_objs("placeholders", &["two"]);
+
+    let layout = Material::create_bind_group_layout(&device);
+    let source = Arc::new(FsSource::new(&dir));
+    let mut loader = AssetLoader::new(source.clone());
+    let mut assets = Assets::new();
+    let mut model = Model::load_obj_async(
+        source.as_ref(),
+        "two.obj",
+        &device,
+        &queue,
+        &layout,
+        &mut loader,
+        &mut assets,
+    )
+    .unwrap();
+    assert!(model.is_loading());
+    assert_eq!(diffuse_size(&model, 0), (1, 1));
+    assert_eq!(diffuse_size(&model, 1), (1, 1));
+    let placeholder = model.materials[0].bind_group.global_id();
+
+    let finished = loader.wait();
+    assert!(model.receive_textures(&device, &queue, &layout, &mut assets, &finished));
+    assert!(!model.is_loading());
+    assert_ne!(model.materials[0].bind_group.global_id(), placeholder);
+    assert_eq!(
+        diffuse_size(&model, 0),
+        image::image_dimensions(cube_texture()).unwrap()
+    );
+    // The missing one is drawn with the magenta 1x1 texture
+    assert_eq!(diffuse_size(&model, 1), (1, 1));
+    assert_eq!(loader.state(finished[0].0), AssetState::Loaded);
+    // Only the one that loaded is cached
+    assert_eq!(assets.len(), 1);
+
+    // Nothing left to receive
+    assert!(!model.receive_textures(&device, &queue, &layout, &mut assets, &finished));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn models_sharing_a_texture_upload_it_once() {
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+    let dir = write_objs("shared", &["first", "second"]);
+
+    let layout = Material::create_bind_group_layout(&device);
+    let source = FsSource::new(&dir);
+    let mut assets = Assets::new();
+    let first =
+        Model::load_obj(&source, "first.obj", &device, &queue, &layout, &mut assets).unwrap();
+    let second =
+        Model::load_obj(&source, "second.obj", &device, &queue, &layout, &mut assets).unwrap();
+    assert!(Arc::ptr_eq(
+        &first.materials[0].diffuse_texture,
+        &second.materials[0].diffuse_texture
+    ));
+    assert_eq!(assets.len(), 1);
+
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let handle = assets
+        .load_texture(&device, &queue, &source, "found.png", format)
+        .unwrap();
+    assert_eq!(assets.find(Path::new("found.png"), format), Some(handle));
+    assert_eq!(
+        assets
+            .load_texture(&device, &queue, &source, "found.png", format)
+            .unwrap(),
+        handle
+    );
+    // Another format is another texture
+    let linear = assets
+        .load_texture(
+            &device,
+            &queue,
+            &source,
+            "found.png",
+            wgpu::TextureFormat::Rgba8Unorm,
+        )
+        .unwrap();
+    assert_ne!(linear, handle);
+    assert!(assets
+        .load_texture(&device, &queue, &source, "lost.png", format)
+        .is_err());
+
+    // Only the linear one isn't used by a material
+    assert_eq!(assets.remove_unused(), 1);
+    assert!(assets.get(linear).is_none());
+    drop(first);
+    assert_eq!(assets.remove_unused(), 0);
+    drop(second);
+    assert_eq!(assets.remove_unused(), 1);
+    assert!(assets.get(handle).is_none());
+    assert!(assets.is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
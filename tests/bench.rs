@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use wgpu_learning::bench::{percentile, BenchReport};
+
+fn adapter_info() -> wgpu::AdapterInfo {
+    wgpu::AdapterInfo {
+        name: "Test \"GPU\"".to_owned(),
+        vendor: 0,
+        device: 0,
+        device_type: wgpu::DeviceType::Cpu,
+        driver: "llvmpipe".to_owned(),
+        driver_info: String::new(),
+        backend: wgpu::Backend::Gl,
+    }
+}
+
+#[test]
+fn percentiles_use_the_nearest_rank() {
+    let sorted: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+    assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(50));
+    assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(99));
+    assert_eq!(percentile(&sorted, 100.0), Duration::from_millis(100));
+    assert_eq!(percentile(&sorted, 0.0), Duration::from_millis(1));
+
+    let one = [Duration::from_millis(7)];
+    assert_eq!(percentile(&one, 95.0), Duration::from_millis(7));
+    assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+}
+
+#[test]
+fn reports_sort_the_frame_times() {
+    let frame_times = [4, 1, 3, 2].map(Duration::from_millis);
+    let report = BenchReport::new(
+        adapter_info(),
+        64,
+        32,
+        &frame_times,
+        Duration::from_millis(10),
+    );
+    assert_eq!(report.frames, 4);
+    assert_eq!(report.p50, Duration::from_millis(2));
+    assert_eq!(report.p95, Duration::from_millis(4));
+
+    assert_eq!(
+        report.to_json(),
+        concat!(
+            r#"{"frames":4,"width":64,"height":32,"#,
+            r#""adapter":{"name":"Test \"GPU\"","backend":"Gl","device_type":"Cpu","driver":"llvmpipe"},"#,
+            r#""total_ms":10.000,"p50_ms":2.000,"p95_ms":4.000,"p99_ms":4.000}"#
+        )
+    );
+    assert!(report.to_string().contains("4 at 64x32"));
+}
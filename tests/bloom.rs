@@ -0,0 +1,14 @@
+use wgpu_learning::bloom::{mip_sizes, MAX_BLOOM_MIPS};
+
+#[test]
+fn mips_halve_the_scene_down_to_1x1() {
+    assert_eq!(
+        mip_sizes(64, 48),
+        [(32, 24), (16, 12), (8, 6), (4, 3), (2, 1), (1, 1)]
+    );
+    assert_eq!(mip_sizes(1920, 1080).len(), MAX_BLOOM_MIPS);
+    assert_eq!(mip_sizes(1920, 1080)[0], (960, 540));
+    // Odd sizes round down, thin ones stay at least a texel wide
+    assert_eq!(mip_sizes(5, 1), [(2, 1), (1, 1)]);
+    assert_eq!(mip_sizes(1, 1), [(1, 1)]);
+}
@@ -0,0 +1,136 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use wgpu_learning::{
+    boids::{self, max_boid_count, BoidParams},
+    compute::{workgroup_count, WORKGROUP_SIZE},
+};
+use winit::keyboard::KeyCode;
+
+#[test]
+fn boids_start_inside_the_square() {
+    let boids = boids::scatter(1000);
+    assert_eq!(boids.len(), 1000);
+    let params = BoidParams::default();
+    for boid in boids {
+        let position = glam::Vec2::from(boid.position);
+        assert!(position.abs().max_element() <= 1.0, "{position}");
+        let speed = glam::Vec2::from(boid.velocity).length();
+        assert!(
+            (params.min_speed - 1e-5..=params.max_speed + 1e-5).contains(&speed),
+            "{speed}"
+        );
+    }
+}
+
+#[test]
+fn keys_raise_and_lower_the_params() {
+    let mut params = BoidParams::default();
+    assert!(params.adjust(KeyCode::KeyQ));
+    assert_eq!(
+        params.separation_weight,
+        BoidParams::default().separation_weight * 1.25
+    );
+    assert!(params.adjust(KeyCode::KeyA));
+    assert!((params.separation_weight - BoidParams::default().separation_weight).abs() < 1e-5);
+
+    // The radii all change together
+    assert!(params.adjust(KeyCode::KeyF));
+    assert!((params.cohesion_radius - 0.1 / 1.25).abs() < 1e-6);
+    assert!((params.separation_radius - 0.025 / 1.25).abs() < 1e-6);
+
+    // Slowing down far enough drags the lowest speed down too
+    for _ in 0..10 {
+        params.adjust(KeyCode::KeyG);
+    }
+    assert_eq!(params.min_speed, params.max_speed);
+
+    assert!(!params.adjust(KeyCode::KeyZ));
+}
+
+#[test]
+fn the_boid_count_fits_the_limits() {
+    let limits = wgpu::Limits::default();
+    let max = max_boid_count(&limits);
+    assert!(workgroup_count(max, WORKGROUP_SIZE) <= limits.max_compute_workgroups_per_dimension);
+    assert!(u64::from(max) * 16 <= u64::from(limits.max_storage_buffer_binding_size));
+    assert_eq!(
+        max_boid_count(&wgpu::Limits::downlevel_webgl2_defaults()),
+        0
+    );
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn close_boids_push_each_other_apart() {
+    use std::time::Duration;
+
+    use glam::Vec2;
+    use wgpu_learning::boids::{Boid, Boids, BOIDS_SHADER_SOURCE};
+
+    let Some(adapter) = common::adapter() else {
+        return;
+    };
+    if !adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+    {
+        eprintln!("Skipping boids test: compute shaders aren't supported");
+        return;
+    }
+    let (device, queue) = common::device_on(&adapter);
+
+    // Still rows of boids too far apart to notice each other, with the
+    // first and the last one right next to each other. They're in
+    // different tiles of the shader.
+    let mut agents: Vec<Boid> = (0..100)
+        .map(|i| {
+            let (column, row) = (i % 50, i / 50);
+            let position = Vec2::new(-0.9 + column as f32 * 0.03, 0.3 + row as f32 * 0.3);
+            Boid::new(position, Vec2::ZERO)
+        })
+        .collect();
+    agents[0] = Boid::new(Vec2::new(-0.005, 0.0), Vec2::ZERO);
+    agents[99] = Boid::new(Vec2::new(0.005, 0.0), Vec2::ZERO);
+
+    let mut boids = Boids::new(
+        &device,
+        &agents,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+        BOIDS_SHADER_SOURCE,
+    )
+    .unwrap();
+    assert_eq!(boids.count(), 100);
+    boids.set_params(BoidParams {
+        alignment_weight: 0.0,
+        cohesion_weight: 0.0,
+        min_speed: 0.0,
+        ..BoidParams::default()
+    });
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    boids.step(&queue, &mut encoder, Duration::from_millis(100));
+    let size = boids.boid_buffer().size();
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("boid_readback"),
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_buffer_to_buffer(boids.boid_buffer(), 0, &readback, 0, size);
+    queue.submit(std::iter::once(encoder.finish()));
+
+    readback.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::Maintain::Wait);
+    let data = readback.slice(..).get_mapped_range();
+    let moved: &[Boid] = bytemuck::cast_slice(&data);
+
+    assert!(moved[0].position[0] < -0.005, "{:?}", moved[0]);
+    assert!(moved[99].position[0] > 0.005, "{:?}", moved[99]);
+    assert!(moved[0].velocity[0] < 0.0 && moved[99].velocity[0] > 0.0);
+    for boid in &moved[1..99] {
+        assert_eq!(boid.velocity, [0.0, 0.0]);
+    }
+    assert_eq!(moved[50].position, agents[50].position);
+}
@@ -0,0 +1,50 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use wgpu_learning::buffer::{GrowableBuffer, IndexBuffer, VertexBuffer, GROWTH_FACTOR};
+
+#[test]
+fn buffers_remember_what_they_hold() {
+    let Some((device, _queue)) = common::device() else {
+        return;
+    };
+
+    let vertices = VertexBuffer::new(&device, "vertices", &[[0.0f32; 3]; 5]);
+    assert_eq!(vertices.len(), 5);
+    assert_eq!(vertices.draw_range(), 0..5);
+    assert_eq!(vertices.buffer().size(), 5 * 12);
+
+    let indices = IndexBuffer::new(&device, "indices", &[0u16, 1, 2]).unwrap();
+    assert_eq!(indices.format(), wgpu::IndexFormat::Uint16);
+    assert_eq!(indices.draw_range(), 0..3);
+    assert!(IndexBuffer::<u32>::new(&device, "no indices", &[]).is_none());
+}
+
+#[test]
+fn growable_buffers_only_reallocate_when_the_data_does_not_fit() {
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+
+    // Empty, there's still room for one to bind
+    let mut buffer =
+        GrowableBuffer::<[f32; 4]>::new(&device, "growable", wgpu::BufferUsages::VERTEX, &[]);
+    assert!(buffer.is_empty());
+    assert_eq!(buffer.capacity(), 1);
+
+    assert!(!buffer.update(&device, &queue, &[[1.0; 4]]));
+    let first = buffer.buffer().global_id();
+    assert!(buffer.update(&device, &queue, &[[1.0; 4]; 2]));
+    assert_ne!(buffer.buffer().global_id(), first);
+    assert_eq!(buffer.capacity(), GROWTH_FACTOR);
+    assert_eq!(buffer.draw_range(), 0..2);
+
+    // Way past the growth it takes the size it needs, and shrinking keeps
+    // the buffer
+    assert!(buffer.update(&device, &queue, &[[1.0; 4]; 100]));
+    assert_eq!(buffer.capacity(), 100);
+    assert_eq!(buffer.buffer().size(), 100 * 16);
+    assert!(!buffer.update(&device, &queue, &[[1.0; 4]; 3]));
+    assert_eq!((buffer.len(), buffer.capacity()), (3, 100));
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use glam::Vec3;
+use wgpu_learning::{
+    camera::{
+        Camera, CameraController, CameraMode, OrbitCamera, OrbitPose, MAX_PITCH, MIN_ZOOM_DISTANCE,
+        ORBIT_ZOOM_PER_LINE,
+    },
+    culling::Aabb,
+};
+
+fn camera_looking_down_z() -> Camera {
+    Camera {
+        eye: Vec3::new(0.0, 0.0, 10.0),
+        target: Vec3::ZERO,
+        up: Vec3::Y,
+        aspect: 1.0,
+        fovy: 45.0,
+        znear: 0.1,
+        zfar: 100.0,
+    }
+}
+
+#[test]
+fn zooming_never_reaches_the_target() {
+    let mut camera = camera_looking_down_z();
+
+    camera.zoom(0.5);
+    assert_eq!(camera.eye, Vec3::new(0.0, 0.0, 5.0));
+    camera.zoom(-1.0);
+    assert_eq!(camera.eye, Vec3::new(0.0, 0.0, 10.0));
+    camera.zoom(2.0);
+    assert_eq!(camera.eye, Vec3::new(0.0, 0.0, MIN_ZOOM_DISTANCE));
+}
+
+#[test]
+fn rotating_turns_around_the_eye() {
+    let mut camera = camera_looking_down_z();
+
+    camera.rotate(90.0, 0.0);
+    assert_eq!(camera.eye, Vec3::new(0.0, 0.0, 10.0));
+    assert!(camera.target.abs_diff_eq(Vec3::new(-10.0, 0.0, 10.0), 1e-4));
+
+    camera.rotate(0.0, 45.0);
+    let direction = (camera.target - camera.eye).normalize();
+    assert!((direction.y - 45f32.to_radians().sin()).abs() < 1e-4);
+}
+
+#[test]
+fn pitch_is_clamped() {
+    let mut camera = camera_looking_down_z();
+
+    for _ in 0..10 {
+        camera.rotate(0.0, 30.0);
+    }
+    let direction = (camera.target - camera.eye).normalize();
+    let pitch = direction.y.asin().to_degrees();
+    assert!((pitch - MAX_PITCH).abs() < 1e-2, "{pitch}");
+    // Still looking the same way, not flipped over
+    assert!(direction.z < 0.0);
+}
+
+#[test]
+fn analog_movement_counts_as_moving() {
+    let mut controller = CameraController::new(2.0);
+    assert!(!controller.is_moving());
+
+    controller.set_analog_movement(0.5, 0.0);
+    assert!(controller.is_moving());
+    controller.reset();
+    assert!(!controller.is_moving());
+}
+
+#[test]
+fn orbiting_and_panning_keep_the_distance() {
+    let mut camera = camera_looking_down_z();
+
+    camera.orbit(90.0, 0.0);
+    assert_eq!(camera.target, Vec3::ZERO);
+    assert!(camera.eye.abs_diff_eq(Vec3::new(10.0, 0.0, 0.0), 1e-4));
+
+    camera.pan(1.0, 2.0);
+    assert!(camera.target.abs_diff_eq(Vec3::new(0.0, 2.0, -1.0), 1e-4));
+    assert!((camera.eye.distance(camera.target) - 10.0).abs() < 1e-4);
+    // A 45 degree view 10 units away is about 8.3 units high
+    let units = camera.world_units_per_pixel(100);
+    assert!((units - 0.0828).abs() < 1e-3, "{units}");
+}
+
+#[test]
+fn orbit_poses_see_what_the_camera_sees() {
+    let mut camera = camera_looking_down_z();
+    camera.orbit(30.0, -20.0);
+    let pose = OrbitPose::from_camera(&camera);
+    assert_eq!(pose.focus, Vec3::ZERO);
+    assert!((pose.distance - 10.0).abs() < 1e-4);
+    assert!(pose.eye().abs_diff_eq(camera.eye, 1e-4));
+
+    // Framing a box looks at it from the same side
+    let bounds = Aabb {
+        min: Vec3::splat(4.0),
+        max: Vec3::splat(6.0),
+    };
+    let framed = pose.framing(bounds, 90.0);
+    assert_eq!(framed.focus, Vec3::splat(5.0));
+    assert!((framed.distance - 3f32.sqrt() / 45f32.to_radians().sin()).abs() < 1e-4);
+    assert_eq!((framed.yaw, framed.pitch), (pose.yaw, pose.pitch));
+    assert_eq!(CameraMode::Fly.toggled(), CameraMode::Orbit);
+}
+
+#[test]
+fn orbit_drags_stop_short_of_the_poles() {
+    let camera = camera_looking_down_z();
+    let mut orbit = OrbitCamera::new(&camera);
+    orbit.drag_orbit(0.0, 10_000.0);
+    assert_eq!(orbit.goal().pitch, MAX_PITCH);
+    orbit.drag_orbit(0.0, -100_000.0);
+    assert_eq!(orbit.goal().pitch, -MAX_PITCH);
+
+    // Each line zooms by the same factor
+    let mut orbit = OrbitCamera::new(&camera);
+    orbit.zoom(2.0);
+    let distance = 10.0 * ORBIT_ZOOM_PER_LINE * ORBIT_ZOOM_PER_LINE;
+    assert!((orbit.goal().distance - distance).abs() < 1e-4);
+    orbit.zoom(1000.0);
+    assert_eq!(orbit.goal().distance, MIN_ZOOM_DISTANCE);
+}
+
+#[test]
+fn orbiting_moves_the_same_at_any_frame_rate() {
+    let start = camera_looking_down_z();
+    let pose_after = |frames: u32| {
+        let mut camera = start;
+        let mut orbit = OrbitCamera::new(&camera);
+        orbit.drag_orbit(100.0, 50.0);
+        orbit.drag_pan(10.0, 0.0, 0.1);
+        for _ in 0..frames {
+            orbit.update_camera(&mut camera, Duration::from_secs_f32(0.1 / frames as f32));
+        }
+        orbit.pose()
+    };
+    let slow = pose_after(2);
+    let fast = pose_after(20);
+    assert!(slow.focus.abs_diff_eq(fast.focus, 1e-4));
+    assert!((slow.yaw - fast.yaw).abs() < 1e-3);
+    assert!((slow.pitch - fast.pitch).abs() < 1e-3);
+    // Most of the way there, panned left by the drag to the right
+    assert!(slow.yaw < -20.0 && slow.focus.x < -0.5, "{slow:?}");
+}
+
+#[test]
+fn orbiting_picks_up_other_camera_moves() {
+    let mut camera = camera_looking_down_z();
+    let mut orbit = OrbitCamera::new(&camera);
+    orbit.update_camera(&mut camera, Duration::ZERO);
+    assert!(!orbit.is_moving());
+
+    camera.pan(0.0, 3.0);
+    orbit.update_camera(&mut camera, Duration::from_millis(16));
+    assert_eq!(orbit.pose().focus, Vec3::new(0.0, 3.0, 0.0));
+    assert!(camera.eye.abs_diff_eq(Vec3::new(0.0, 3.0, 10.0), 1e-4));
+}
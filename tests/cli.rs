@@ -0,0 +1,172 @@
+use std::path::PathBuf;
+
+use wgpu_learning::{
+    adapter::AdapterSelection,
+    cli::{Cli, CliError, Command, HEADLESS_SIZE},
+    present_mode::PresentModePreference,
+};
+use winit::dpi::{PhysicalSize, Size};
+
+fn parse(args: &[&str]) -> Result<Cli, CliError> {
+    Cli::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn no_arguments_open_the_scene_with_the_defaults() {
+    let cli = parse(&[]).unwrap();
+    assert_eq!(cli.command, Command::Run);
+    let config = cli.run_config();
+    assert_eq!(config.window.inner_size, None);
+    assert!(!config.window.fullscreen);
+    assert_eq!(config.adapter, AdapterSelection::default());
+    assert_eq!(cli.headless_size(), HEADLESS_SIZE);
+}
+
+#[test]
+fn options_end_up_in_the_run_config() {
+    let cli = parse(&[
+        "--width",
+        "640",
+        "--height",
+        "480",
+        "--backend",
+        "Vulkan",
+        "--adapter",
+        "1",
+        "--present-mode",
+        "mailbox",
+        "--msaa",
+        "2",
+    ])
+    .unwrap();
+    let config = cli.run_config();
+    assert_eq!(
+        config.window.inner_size,
+        Some(Size::Physical(PhysicalSize::new(640, 480)))
+    );
+    assert_eq!(config.device.backends, wgpu::Backends::VULKAN);
+    assert_eq!(config.adapter, AdapterSelection::ByIndex(1));
+    assert_eq!(config.present_mode, PresentModePreference::Mailbox);
+    assert_eq!(config.sample_count, 2);
+
+    let cli = parse(&["--adapter", "llvmpipe", "--fullscreen"]).unwrap();
+    assert_eq!(
+        cli.adapter,
+        Some(AdapterSelection::ByNameSubstring("llvmpipe".to_owned()))
+    );
+    assert!(cli.run_config().window.fullscreen);
+}
+
+#[test]
+fn commands_take_their_values() {
+    let headless = Command::Headless {
+        frames: 10,
+        json: true,
+    };
+    assert_eq!(
+        parse(&["--frames", "10", "--json", "--headless"])
+            .unwrap()
+            .command,
+        headless
+    );
+    assert_eq!(
+        parse(&["--bench", "10", "--json"]).unwrap().command,
+        headless
+    );
+    let cli = parse(&[
+        "--headless",
+        "--frames",
+        "1",
+        "--width",
+        "8",
+        "--height",
+        "4",
+    ])
+    .unwrap();
+    assert_eq!(cli.headless_size(), PhysicalSize::new(8, 4));
+
+    assert_eq!(
+        parse(&["--bench-uploads", "5"]).unwrap().command,
+        Command::BenchUploads { frames: 5 }
+    );
+    assert_eq!(
+        parse(&["--shadertoy", "toy.wgsl"]).unwrap().command,
+        Command::Shadertoy {
+            path: PathBuf::from("toy.wgsl")
+        }
+    );
+    assert_eq!(
+        parse(&["--backend", "gl", "--list-adapters"])
+            .unwrap()
+            .command,
+        Command::ListAdapters
+    );
+    // Parsing stops at the help, mistakes before it are still errors
+    assert_eq!(
+        parse(&["--msaa", "3", "-h"]).unwrap_err().to_string(),
+        "--msaa can't be \"3\", it takes 1, 2, 4 or 8"
+    );
+    assert_eq!(
+        parse(&["--bogus", "--help"]).unwrap_err(),
+        CliError::UnknownArgument("--bogus".to_owned())
+    );
+    assert_eq!(
+        parse(&["--help", "--bogus"]).unwrap().command,
+        Command::Help
+    );
+}
+
+#[test]
+fn mistakes_say_which_flag_is_wrong() {
+    let error = |args: &[&str]| parse(args).unwrap_err().to_string();
+    assert_eq!(error(&["--frames", "5"]), "--frames needs --headless too");
+    assert_eq!(error(&["--headless"]), "--headless needs --frames too");
+    assert_eq!(error(&["--json"]), "--json needs --headless too");
+    assert_eq!(error(&["--width", "5"]), "--width needs --height too");
+    // No frames make no report
+    assert_eq!(
+        error(&["--headless", "--frames", "0"]),
+        "--frames can't be \"0\", it takes a number of frames"
+    );
+    assert_eq!(
+        error(&["--bench", "0"]),
+        "--bench can't be \"0\", it takes a number of frames"
+    );
+    assert_eq!(
+        error(&["--bench-uploads", "0"]),
+        "--bench-uploads can't be \"0\", it takes a number of frames"
+    );
+    assert_eq!(
+        error(&["--backend", "opengl"]),
+        "--backend can't be \"opengl\", it takes vulkan, dx12, metal or gl"
+    );
+    assert_eq!(
+        error(&["--width", "0", "--height", "5"]),
+        "--width can't be \"0\", it takes a number of pixels"
+    );
+    assert_eq!(error(&["--msaa"]), "--msaa needs a value, e.g. --msaa 4");
+    assert_eq!(
+        error(&["--shadertoy", "--fullscreen"]),
+        "--shadertoy needs a value, e.g. --shadertoy toy.wgsl"
+    );
+    assert_eq!(
+        error(&["--bench", "5", "--shadertoy", "toy.wgsl"]),
+        "--shadertoy can't be used with --bench"
+    );
+    assert_eq!(
+        error(&["--headless", "--frames", "5", "--present-mode", "vsync"]),
+        "--present-mode can't be used with --headless"
+    );
+    assert_eq!(
+        error(&["--list-adapters", "--adapter", "0"]),
+        "--adapter can't be used with --list-adapters"
+    );
+    assert_eq!(
+        error(&["--shadertoy", "toy.wgsl", "--msaa", "4"]),
+        "--msaa can't be used with --shadertoy"
+    );
+    assert_eq!(
+        error(&["--fullscreen", "--width", "5", "--height", "5"]),
+        "--fullscreen can't be used with --width"
+    );
+}
@@ -0,0 +1,76 @@
+use wgpu_learning::color;
+
+#[test]
+fn srgb_formats_get_the_linear_color() {
+    for preset in color::PRESETS {
+        assert_eq!(
+            color::clear_value_for_format(preset, wgpu::TextureFormat::Bgra8UnormSrgb),
+            preset
+        );
+    }
+}
+
+#[test]
+fn other_formats_get_the_encoded_color() {
+    let color = wgpu::Color {
+        r: 0.0,
+        g: 0.2,
+        b: 1.0,
+        a: 0.5,
+    };
+    let value = color::clear_value_for_format(color, wgpu::TextureFormat::Bgra8Unorm);
+
+    assert_eq!(value.r, 0.0);
+    // 0.2 linear is about 124/255 in sRGB
+    assert!((value.g - 124.0 / 255.0).abs() < 0.5 / 255.0, "{value:?}");
+    assert!((value.b - 1.0).abs() < 1e-9, "{value:?}");
+    assert_eq!(value.a, 0.5);
+}
+
+#[test]
+fn float_formats_get_the_linear_color() {
+    assert!(color::stores_linear(wgpu::TextureFormat::Rgba16Float));
+    assert!(color::stores_linear(wgpu::TextureFormat::Rgba8UnormSrgb));
+    assert!(!color::stores_linear(wgpu::TextureFormat::Rgba8Unorm));
+
+    let color = color::PRESETS[0];
+    assert_eq!(
+        color::clear_value_for_format(color, wgpu::TextureFormat::Rgba16Float),
+        color
+    );
+}
+
+#[test]
+fn surfaces_prefer_srgb_formats_then_srgb_views() {
+    use color::SrgbEncoding;
+    use wgpu::TextureFormat::{Bgra8Unorm, Bgra8UnormSrgb, Rgb10a2Unorm, Rgba16Float};
+
+    assert_eq!(
+        color::select_surface_format(&[Bgra8Unorm, Bgra8UnormSrgb], false),
+        Some((Bgra8UnormSrgb, SrgbEncoding::Format))
+    );
+    assert_eq!(
+        color::select_surface_format(&[Bgra8Unorm], true),
+        Some((Bgra8Unorm, SrgbEncoding::View))
+    );
+    assert_eq!(
+        color::select_surface_format(&[Bgra8Unorm], false),
+        Some((Bgra8Unorm, SrgbEncoding::Shader))
+    );
+    // No sRGB variant to view it as
+    assert_eq!(
+        color::select_surface_format(&[Rgb10a2Unorm], true),
+        Some((Rgb10a2Unorm, SrgbEncoding::Shader))
+    );
+    assert_eq!(
+        color::select_surface_format(&[Rgba16Float], false),
+        Some((Rgba16Float, SrgbEncoding::Format))
+    );
+    assert_eq!(color::select_surface_format(&[], true), None);
+
+    assert_eq!(
+        SrgbEncoding::View.view_formats(Bgra8Unorm),
+        [Bgra8UnormSrgb]
+    );
+    assert!(SrgbEncoding::Shader.view_formats(Bgra8Unorm).is_empty());
+}
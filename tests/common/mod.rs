@@ -0,0 +1,28 @@
+//! Setup for the tests that need a GPU. Creating a device blocks on the
+//! GPU, which the web doesn't allow, so those tests are native only.
+
+// Each test file uses a part of it
+#![allow(dead_code)]
+
+/// The default adapter, `None` without one, e.g. on CI runners without a
+/// GPU. The tests return early then instead of failing.
+pub fn adapter() -> Option<wgpu::Adapter> {
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()));
+    if adapter.is_none() {
+        eprintln!("Skipping GPU test: no adapter");
+    }
+    adapter
+}
+
+/// A device with the default features and limits on `adapter()`.
+pub fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    Some(device_on(&adapter()?))
+}
+
+/// A device with the default features and limits, for tests that look at
+/// the adapter too.
+pub fn device_on(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+    pollster::block_on(adapter.request_device(&Default::default(), None))
+        .expect("the adapter has no device with the default limits")
+}
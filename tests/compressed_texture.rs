@@ -0,0 +1,336 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use wgpu::TextureFormat;
+use wgpu_learning::{
+    block_compression::{decode_block, decompress},
+    compressed_texture::{parse_dds, parse_ktx2, CompressedTextureError},
+};
+
+/// A KTX2 file holding one 2D texture with the given mip levels.
+fn ktx2(
+    vk_format: u32,
+    width: u32,
+    height: u32,
+    levels: &[&[u8]],
+    supercompression: u32,
+) -> Vec<u8> {
+    let mut file = vec![
+        0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a,
+    ];
+    for field in [
+        vk_format,
+        1,
+        width,
+        height,
+        0,
+        0,
+        1,
+        levels.len() as u32,
+        supercompression,
+    ] {
+        file.extend(field.to_le_bytes());
+    }
+    // No data format descriptor, key/value or supercompression data
+    file.resize(80, 0);
+
+    let mut offset = (80 + levels.len() * 24) as u64;
+    for level in levels {
+        let length = level.len() as u64;
+        for field in [offset, length, length] {
+            file.extend(field.to_le_bytes());
+        }
+        offset += length;
+    }
+    for level in levels {
+        file.extend_from_slice(level);
+    }
+    file
+}
+
+/// A DDS file with a FourCC pixel format, and a DX10 header with `dxgi` if
+/// there is one.
+fn dds(
+    four_cc: &[u8; 4],
+    dxgi: Option<u32>,
+    width: u32,
+    height: u32,
+    mips: u32,
+    data: &[u8],
+) -> Vec<u8> {
+    let mut file = vec![0; 128];
+    file[..4].copy_from_slice(b"DDS ");
+    let mut write = |offset: usize, value: u32| {
+        file[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    };
+    write(4, 124);
+    write(8, 0x1 | 0x2 | 0x4 | 0x1000 | 0x20000);
+    write(12, height);
+    write(16, width);
+    write(28, mips);
+    write(76, 32);
+    write(80, 0x4);
+    file[84..88].copy_from_slice(four_cc);
+    if let Some(dxgi) = dxgi {
+        for field in [dxgi, 3, 0, 1, 0] {
+            file.extend(field.to_le_bytes());
+        }
+    }
+    file.extend_from_slice(data);
+    file
+}
+
+/// A red to blue BC1 block using all four colors in the first four texels.
+const BC1_BLOCK: [u8; 8] = [0x00, 0xf8, 0x1f, 0x00, 0xe4, 0x00, 0x00, 0x00];
+
+#[test]
+fn bc1_interpolates_between_its_endpoints() {
+    let texels = decode_block(TextureFormat::Bc1RgbaUnorm, &BC1_BLOCK).unwrap();
+    assert_eq!(texels[0], [255, 0, 0, 255]);
+    assert_eq!(texels[1], [0, 0, 255, 255]);
+    assert_eq!(texels[2], [170, 0, 85, 255]);
+    assert_eq!(texels[3], [85, 0, 170, 255]);
+    assert_eq!(texels[4], [255, 0, 0, 255]);
+
+    // Swapped endpoints select the three color mode with transparent black
+    let block = [0x1f, 0x00, 0x00, 0xf8, 0xe4, 0x00, 0x00, 0x00];
+    let texels = decode_block(TextureFormat::Bc1RgbaUnormSrgb, &block).unwrap();
+    assert_eq!(texels[2], [127, 0, 127, 255]);
+    assert_eq!(texels[3], [0, 0, 0, 0]);
+}
+
+#[test]
+fn bc3_alpha_uses_eight_steps() {
+    let mut block = [0; 16];
+    block[0] = 255;
+    block[2] = 1 << 3 | 2 << 6;
+    block[8..12].copy_from_slice(&[0xff; 4]);
+    let texels = decode_block(TextureFormat::Bc3RgbaUnorm, &block).unwrap();
+
+    assert_eq!(texels[0], [255, 255, 255, 255]);
+    assert_eq!(texels[1], [255, 255, 255, 0]);
+    assert_eq!(texels[2], [255, 255, 255, 218]);
+}
+
+#[test]
+fn bc7_mode_6_blocks_decode() {
+    // Written from the lowest bit up, like the decoder reads it
+    let mut bits = 0u128;
+    let mut position = 0;
+    let mut write = |value: u128, count: u32| {
+        bits |= value << position;
+        position += count;
+    };
+    write(1 << 6, 7);
+    for _ in 0..4 {
+        write(127, 7);
+        write(0, 7);
+    }
+    write(1, 1);
+    write(0, 1);
+    // Texel 0 is the anchor, its index has a bit less
+    write(0, 3);
+    write(8, 4);
+    for _ in 2..16 {
+        write(15, 4);
+    }
+    assert_eq!(position, 128);
+
+    let texels = decode_block(TextureFormat::Bc7RgbaUnorm, &bits.to_le_bytes()).unwrap();
+    assert_eq!(texels[0], [255; 4]);
+    assert_eq!(texels[1], [120; 4]);
+    assert!(texels[2..].iter().all(|&texel| texel == [0; 4]));
+}
+
+/// Gray ETC2 halves side by side, with modifier tables 0 and 1. The first
+/// texel has the largest negative modifier, the others the smallest
+/// positive one.
+const ETC2_BLOCK: [u8; 8] = [0x88, 0x88, 0x88, 0x04, 0x00, 0x01, 0x00, 0x01];
+
+#[test]
+fn etc2_blocks_have_two_halves() {
+    let texels = decode_block(TextureFormat::Etc2Rgb8Unorm, &ETC2_BLOCK).unwrap();
+    assert_eq!(texels[0], [128, 128, 128, 255]);
+    assert_eq!(texels[1], [138, 138, 138, 255]);
+    assert_eq!(texels[2], [141, 141, 141, 255]);
+    assert_eq!(texels[15], [141, 141, 141, 255]);
+}
+
+#[test]
+fn etc2_punchthrough_blocks_can_be_transparent() {
+    let block = [0x80, 0x80, 0x80, 0x00, 0x00, 0x01, 0x00, 0x00];
+    let texels = decode_block(TextureFormat::Etc2Rgb8A1Unorm, &block).unwrap();
+    assert_eq!(texels[0], [0, 0, 0, 0]);
+    assert_eq!(texels[1], [132, 132, 132, 255]);
+}
+
+#[test]
+fn eac_alpha_is_scaled_by_the_multiplier() {
+    let mut indices = 7 << 45;
+    for i in 1..16 {
+        indices |= 4 << (45 - 3 * i);
+    }
+    let alpha = (100 << 56) | (0x20 << 48) | indices;
+    let mut block = [0; 16];
+    block[..8].copy_from_slice(&u64::to_be_bytes(alpha));
+    block[8..].copy_from_slice(&ETC2_BLOCK);
+
+    let texels = decode_block(TextureFormat::Etc2Rgba8UnormSrgb, &block).unwrap();
+    assert_eq!(texels[0][3], 128);
+    assert!(texels[1..].iter().all(|texel| texel[3] == 104));
+}
+
+#[test]
+fn decompressing_crops_partial_blocks() {
+    // The same endpoints, with every texel blue
+    let blue_block = [0x00, 0xf8, 0x1f, 0x00, 0x55, 0x55, 0x55, 0x55];
+    let data = [BC1_BLOCK, blue_block].concat();
+    let rgba = decompress(TextureFormat::Bc1RgbaUnorm, 5, 3, &data).unwrap();
+    assert_eq!(rgba.len(), 5 * 3 * 4);
+    // Only the first column of the second block is in the image
+    let texel = |x: usize, y: usize| &rgba[(y * 5 + x) * 4..][..4];
+    assert_eq!(texel(4, 0), [0, 0, 255, 255]);
+    assert_eq!(texel(0, 1), [255, 0, 0, 255]);
+    assert_eq!(texel(4, 2), [0, 0, 255, 255]);
+
+    assert_eq!(
+        decompress(TextureFormat::Bc1RgbaUnorm, 5, 3, &BC1_BLOCK),
+        None
+    );
+    assert_eq!(
+        decompress(
+            TextureFormat::Astc {
+                block: wgpu::AstcBlock::B4x4,
+                channel: wgpu::AstcChannel::Unorm
+            },
+            4,
+            4,
+            &[0; 16]
+        ),
+        None
+    );
+}
+
+#[test]
+fn ktx2_files_are_read_level_by_level() {
+    let levels: [&[u8]; 2] = [&[1; 16], &[2; 4]];
+    let image = parse_ktx2(&ktx2(43, 2, 2, &levels, 0)).unwrap();
+    assert_eq!(image.format, TextureFormat::Rgba8UnormSrgb);
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(image.levels, [vec![1; 16], vec![2; 4]]);
+
+    let image = parse_ktx2(&ktx2(157, 4, 4, &[&[0; 16]], 0)).unwrap();
+    assert_eq!(
+        image.format,
+        TextureFormat::Astc {
+            block: wgpu::AstcBlock::B4x4,
+            channel: wgpu::AstcChannel::Unorm
+        }
+    );
+
+    assert!(matches!(
+        parse_ktx2(&ktx2(43, 2, 2, &levels, 2)),
+        Err(CompressedTextureError::Unsupported(_))
+    ));
+    assert_eq!(
+        parse_ktx2(b"DDS definitely not a KTX2 file"),
+        Err(CompressedTextureError::BadMagic("KTX2"))
+    );
+    let mut truncated = ktx2(43, 2, 2, &levels, 0);
+    truncated.pop();
+    assert_eq!(
+        parse_ktx2(&truncated),
+        Err(CompressedTextureError::Truncated("mip levels"))
+    );
+}
+
+#[test]
+fn dds_levels_are_sized_in_whole_blocks() {
+    // 8x8, 4x4, 2x2 and 1x1, the last two still take a whole block
+    let data = [BC1_BLOCK; 7].concat();
+    let image = parse_dds(&dds(b"DXT1", None, 8, 8, 4, &data)).unwrap();
+    assert_eq!(image.format, TextureFormat::Bc1RgbaUnormSrgb);
+    let lengths: Vec<_> = image.levels.iter().map(Vec::len).collect();
+    assert_eq!(lengths, [32, 8, 8, 8]);
+
+    let image = parse_dds(&dds(b"DX10", Some(98), 4, 4, 1, &[0; 16])).unwrap();
+    assert_eq!(image.format, TextureFormat::Bc7RgbaUnorm);
+
+    assert_eq!(
+        parse_dds(&dds(b"DXT1", None, 8, 8, 4, &data[..48])),
+        Err(CompressedTextureError::Truncated("mip levels"))
+    );
+    assert!(matches!(
+        parse_dds(&dds(b"ATI2", None, 4, 4, 1, &[0; 16])),
+        Err(CompressedTextureError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn malformed_dds_headers_are_errors() {
+    // A 4x4 texture has 3 levels at most
+    assert_eq!(
+        parse_dds(&dds(b"DXT1", None, 4, 4, 40, &BC1_BLOCK)),
+        Err(CompressedTextureError::Unsupported(
+            "40 mip levels for a 4x4 texture".to_owned()
+        ))
+    );
+    assert!(matches!(
+        parse_dds(&dds(b"DXT1", None, 4, 4, u32::MAX, &BC1_BLOCK)),
+        Err(CompressedTextureError::Unsupported(_))
+    ));
+    assert!(matches!(
+        parse_dds(&dds(b"DXT1", None, 4, 0, 1, &BC1_BLOCK)),
+        Err(CompressedTextureError::Unsupported(_))
+    ));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn unsupported_formats_fall_back_to_rgba8() {
+    use wgpu_learning::texture::Texture;
+
+    let Some(adapter) = common::adapter() else {
+        return;
+    };
+    let device = |required_features| {
+        let descriptor = wgpu::DeviceDescriptor {
+            required_features,
+            ..Default::default()
+        };
+        pollster::block_on(adapter.request_device(&descriptor, None)).unwrap()
+    };
+    let file = dds(b"DXT1", None, 8, 8, 4, &[BC1_BLOCK; 7].concat());
+
+    // Devices only sample BC textures when asked to
+    let (device_without_bc, queue) = device(wgpu::Features::empty());
+    let texture = Texture::from_dds(&device_without_bc, &queue, &file, None).unwrap();
+    assert_eq!(texture.texture.format(), TextureFormat::Rgba8UnormSrgb);
+    assert_eq!(texture.texture.mip_level_count(), 4);
+
+    // Odd sizes can't be uploaded compressed at all
+    let odd = dds(b"DXT1", None, 5, 3, 1, &[BC1_BLOCK; 2].concat());
+    let texture = Texture::from_dds(&device_without_bc, &queue, &odd, None).unwrap();
+    assert_eq!(texture.texture.format(), TextureFormat::Rgba8UnormSrgb);
+
+    let astc = ktx2(157, 4, 4, &[&[0; 16]], 0);
+    if !adapter
+        .features()
+        .contains(wgpu::Features::TEXTURE_COMPRESSION_ASTC)
+    {
+        assert!(matches!(
+            Texture::from_ktx2(&device_without_bc, &queue, &astc, None),
+            Err(CompressedTextureError::CantDecode { .. })
+        ));
+    }
+
+    if adapter
+        .features()
+        .contains(wgpu::Features::TEXTURE_COMPRESSION_BC)
+    {
+        let (device, queue) = device(wgpu::Features::TEXTURE_COMPRESSION_BC);
+        let texture = Texture::from_dds(&device, &queue, &file, None).unwrap();
+        assert_eq!(texture.texture.format(), TextureFormat::Bc1RgbaUnormSrgb);
+        assert_eq!(texture.texture.mip_level_count(), 4);
+    }
+}
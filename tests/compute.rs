@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use glam::Vec3;
+use wgpu_learning::compute::{
+    max_particle_count, sort_steps, workgroup_count, Emitter, MAX_SPAWNS_PER_FRAME, WORKGROUP_SIZE,
+};
+
+#[test]
+fn workgroups_cover_every_particle() {
+    assert_eq!(workgroup_count(0, WORKGROUP_SIZE), 0);
+    assert_eq!(workgroup_count(1, WORKGROUP_SIZE), 1);
+    assert_eq!(workgroup_count(64, 64), 1);
+    assert_eq!(workgroup_count(65, 64), 2);
+    assert_eq!(workgroup_count(10_000, 64), 157);
+}
+
+#[test]
+fn particle_count_fits_the_limits() {
+    let limits = wgpu::Limits::default();
+    let max = max_particle_count(&limits);
+    assert!(workgroup_count(max, WORKGROUP_SIZE) <= limits.max_compute_workgroups_per_dimension);
+    assert!(u64::from(max) * 32 <= u64::from(limits.max_storage_buffer_binding_size));
+
+    // WebGL2 has no storage buffers at all
+    assert_eq!(
+        max_particle_count(&wgpu::Limits::downlevel_webgl2_defaults()),
+        0
+    );
+}
+
+#[test]
+fn emitters_carry_fractions_of_particles_over() {
+    let mut emitter = Emitter::new(Vec3::ZERO);
+    emitter.rate = 10.0;
+    let mut spawns = Vec::new();
+    // 0.5 particles a frame
+    emitter.emit(Duration::from_millis(50), &mut spawns);
+    assert!(spawns.is_empty());
+    for _ in 0..19 {
+        emitter.emit(Duration::from_millis(50), &mut spawns);
+    }
+    assert_eq!(spawns.len(), 10);
+
+    // A long hitch can't spawn more than fits in a frame
+    emitter.rate = 1e9;
+    spawns.clear();
+    emitter.emit(Duration::from_secs(1), &mut spawns);
+    assert_eq!(spawns.len(), MAX_SPAWNS_PER_FRAME as usize);
+}
+
+#[test]
+fn emitted_particles_leave_inside_the_cone() {
+    let mut emitter = Emitter::new(Vec3::new(1.0, 2.0, 3.0));
+    emitter.direction = Vec3::new(0.0, 0.0, -2.0);
+    emitter.spread = 0.3;
+    emitter.min_speed = 2.0;
+    emitter.max_speed = 4.0;
+    emitter.lifetime = 1.5;
+    emitter.rate = 1000.0;
+    let mut spawns = Vec::new();
+    emitter.emit(Duration::from_secs(1), &mut spawns);
+    assert_eq!(spawns.len(), 1000);
+
+    for particle in spawns {
+        assert_eq!(particle.position, [1.0, 2.0, 3.0]);
+        assert_eq!(particle.age, 0.0);
+        assert_eq!(particle.lifetime, 1.5);
+        let velocity = Vec3::from(particle.velocity);
+        let speed = velocity.length();
+        assert!((2.0 - 1e-4..=4.0 + 1e-4).contains(&speed), "{speed}");
+        let angle = velocity.angle_between(Vec3::NEG_Z);
+        assert!(angle <= 0.3 + 1e-3, "{angle}");
+    }
+}
+
+#[test]
+fn the_bitonic_sort_merges_every_block_size() {
+    assert!(sort_steps(1).is_empty());
+    assert_eq!(sort_steps(2), [(2, 1)]);
+    // Padded up to 8
+    assert_eq!(
+        sort_steps(5),
+        [(2, 1), (4, 2), (4, 1), (8, 4), (8, 2), (8, 1)]
+    );
+    // log2(n) * (log2(n) + 1) / 2 steps for 16384
+    assert_eq!(sort_steps(10_000).len(), 14 * 15 / 2);
+}
@@ -0,0 +1,120 @@
+use glam::{Mat4, Quat, Vec3};
+use wgpu_learning::{
+    camera::Camera,
+    culling::{self, Aabb, CullStats, Frustum},
+    instance::Instance,
+};
+
+fn unit_cube() -> Aabb {
+    Aabb {
+        min: Vec3::splat(-1.0),
+        max: Vec3::splat(1.0),
+    }
+}
+
+fn camera_looking_down_z() -> Camera {
+    Camera {
+        eye: Vec3::new(0.0, 0.0, 10.0),
+        target: Vec3::ZERO,
+        up: Vec3::Y,
+        aspect: 1.0,
+        fovy: 45.0,
+        znear: 0.1,
+        zfar: 100.0,
+    }
+}
+
+fn instance(position: Vec3) -> Instance {
+    Instance {
+        position,
+        rotation: Quat::IDENTITY,
+    }
+}
+
+#[test]
+fn boxes_fit_their_points() {
+    assert_eq!(Aabb::from_points([]), None);
+    let aabb = Aabb::from_points([
+        Vec3::new(1.0, -2.0, 0.0),
+        Vec3::new(-1.0, 3.0, 0.5),
+        Vec3::new(0.0, 0.0, -4.0),
+    ])
+    .unwrap();
+    assert_eq!(aabb.min, Vec3::new(-1.0, -2.0, -4.0));
+    assert_eq!(aabb.max, Vec3::new(1.0, 3.0, 0.5));
+    assert_eq!(aabb.center(), Vec3::new(0.0, 0.5, -1.75));
+    assert_eq!(aabb.union(unit_cube()).max, Vec3::new(1.0, 3.0, 1.0));
+}
+
+#[test]
+fn transformed_boxes_still_hold_every_corner() {
+    let matrix = Mat4::from_rotation_translation(
+        Quat::from_rotation_y(0.7) * Quat::from_rotation_x(0.3),
+        Vec3::new(5.0, 0.0, -2.0),
+    );
+    let aabb = unit_cube().transformed(matrix);
+    for i in 0..8 {
+        let corner = Vec3::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { -1.0 } else { 1.0 },
+        );
+        let point = matrix.transform_point3(corner);
+        assert!(
+            point.cmpge(aabb.min - 1e-5).all() && point.cmple(aabb.max + 1e-5).all(),
+            "{point} is outside {aabb:?}"
+        );
+    }
+
+    // Just moving keeps the size
+    let moved = unit_cube().transformed(Mat4::from_translation(Vec3::X));
+    assert!(moved.extents().abs_diff_eq(Vec3::ONE, 1e-6));
+    assert!(moved.center().abs_diff_eq(Vec3::X, 1e-6));
+}
+
+#[test]
+fn the_frustum_only_holds_what_the_camera_sees() {
+    let frustum = Frustum::from_view_proj(camera_looking_down_z().build_view_projection_matrix());
+    let at = |position: Vec3| unit_cube().transformed(Mat4::from_translation(position));
+
+    assert!(frustum.intersects(&at(Vec3::ZERO)));
+    // Partly inside the left edge
+    assert!(frustum.intersects(&at(Vec3::new(-4.5, 0.0, 0.0))));
+    // Behind the camera, off to the side, and past the far plane
+    assert!(!frustum.intersects(&at(Vec3::new(0.0, 0.0, 20.0))));
+    assert!(!frustum.intersects(&at(Vec3::new(20.0, 0.0, 0.0))));
+    assert!(!frustum.intersects(&at(Vec3::new(0.0, -20.0, 0.0))));
+    assert!(!frustum.intersects(&at(Vec3::new(0.0, 0.0, -200.0))));
+}
+
+#[test]
+fn culling_keeps_the_visible_instances_of_each_mesh() {
+    let frustum = Frustum::from_view_proj(camera_looking_down_z().build_view_projection_matrix());
+    let instances = [
+        instance(Vec3::new(20.0, 0.0, 0.0)),
+        instance(Vec3::ZERO),
+        instance(Vec3::new(0.0, 0.0, 20.0)),
+        instance(Vec3::new(1.0, 1.0, 0.0)),
+    ];
+    // The second mesh sits far off to the side of its instances, and the
+    // last one has no bounds, so it's always drawn
+    let offset = Aabb {
+        min: Vec3::new(19.0, -1.0, -1.0),
+        max: Vec3::new(21.0, 1.0, 1.0),
+    };
+    let culled = culling::cull(
+        &[frustum],
+        &[Some(unit_cube()), Some(offset), None],
+        &instances,
+    );
+
+    assert_eq!(culled.ranges, vec![0..2, 2..2, 2..6]);
+    assert_eq!(culled.instances, vec![1, 3, 0, 1, 2, 3]);
+    assert_eq!(
+        culled.stats,
+        CullStats {
+            drawn: 6,
+            culled: 6
+        }
+    );
+}
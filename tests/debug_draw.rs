@@ -0,0 +1,87 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use glam::{Mat4, Vec3};
+use wgpu_learning::{
+    camera,
+    debug_draw::{DebugDraw, SPHERE_SEGMENTS},
+    pipeline::RenderTargets,
+    post_process::HDR_FORMAT,
+    texture::Texture,
+};
+
+fn debug_draw() -> Option<(wgpu::Device, wgpu::Queue, DebugDraw)> {
+    let (device, queue) = common::device()?;
+    let targets = RenderTargets {
+        color_format: HDR_FORMAT,
+        depth_format: Some(Texture::DEPTH_FORMAT),
+        sample_count: 1,
+    };
+    let layout = camera::create_bind_group_layout(&device);
+    let debug_draw = DebugDraw::new(&device, targets, &layout).unwrap();
+    Some((device, queue, debug_draw))
+}
+
+#[test]
+fn shapes_are_made_of_lines() {
+    let Some((_device, _queue, mut debug_draw)) = debug_draw() else {
+        return;
+    };
+    let red = [1.0, 0.0, 0.0, 1.0];
+
+    debug_draw.line(Vec3::ZERO, Vec3::X, red);
+    assert_eq!(debug_draw.vertices().len(), 2);
+    assert_eq!(debug_draw.vertices()[1].position, [1.0, 0.0, 0.0]);
+    debug_draw.clear();
+
+    debug_draw.aabb(Vec3::ZERO, Vec3::ONE, red);
+    assert_eq!(debug_draw.vertices().len(), 12 * 2);
+    // Every edge is one unit long, along one axis
+    for line in debug_draw.vertices().chunks(2) {
+        let length = Vec3::from(line[0].position).distance(line[1].position.into());
+        assert!((length - 1.0).abs() < 1e-6);
+    }
+    debug_draw.clear();
+
+    debug_draw.sphere(Vec3::Y, 2.0, red);
+    assert_eq!(debug_draw.vertices().len(), 3 * SPHERE_SEGMENTS * 2);
+    for vertex in debug_draw.vertices() {
+        assert!((Vec3::from(vertex.position).distance(Vec3::Y) - 2.0).abs() < 1e-5);
+    }
+    debug_draw.clear();
+
+    debug_draw.axes(Mat4::from_translation(Vec3::Z), 0.5);
+    let vertices = debug_draw.vertices();
+    assert_eq!(vertices.len(), 6);
+    assert_eq!(vertices[0].position, [0.0, 0.0, 1.0]);
+    assert_eq!(vertices[1].position, [0.5, 0.0, 1.0]);
+    assert_eq!(vertices[5].color, [0.0, 0.0, 1.0, 1.0]);
+}
+
+#[test]
+fn the_buffer_only_grows_when_the_lines_do_not_fit() {
+    let Some((device, queue, mut debug_draw)) = debug_draw() else {
+        return;
+    };
+    let white = [1.0; 4];
+
+    for _ in 0..100 {
+        debug_draw.line(Vec3::ZERO, Vec3::ONE, white);
+    }
+    debug_draw.upload(&device, &queue);
+    assert_eq!(debug_draw.capacity(), 200);
+
+    // The same or fewer lines every frame keep the buffer
+    for count in [100, 10, 0, 100] {
+        debug_draw.clear();
+        for _ in 0..count {
+            debug_draw.line(Vec3::ZERO, Vec3::ONE, white);
+        }
+        debug_draw.upload(&device, &queue);
+        assert_eq!(debug_draw.capacity(), 200);
+    }
+    debug_draw.aabb(Vec3::ZERO, Vec3::ONE, white);
+    debug_draw.upload(&device, &queue);
+    assert!(debug_draw.capacity() >= 224);
+}
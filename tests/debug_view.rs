@@ -0,0 +1,33 @@
+use wgpu_learning::debug_view::{linearize_depth, shadow_map_inset, DebugView};
+use winit::dpi::PhysicalSize;
+
+#[test]
+fn debug_views_cycle_through_all_of_them() {
+    let mut view = DebugView::None;
+    for (index, &expected) in DebugView::ALL.iter().enumerate() {
+        assert_eq!(view, expected);
+        assert_eq!(view.index(), index as u32);
+        view = view.next();
+    }
+    assert_eq!(view, DebugView::None);
+    assert!(DebugView::Normals.replaces_frame());
+    assert!(!DebugView::ShadowMap.replaces_frame());
+}
+
+#[test]
+fn linearized_depth_goes_from_near_to_far() {
+    assert!((linearize_depth(0.0, 0.1, 100.0) - 0.1).abs() < 1e-6);
+    assert!((linearize_depth(1.0, 0.1, 100.0) - 100.0).abs() < 0.01);
+    // Most of the depth range is close to the camera
+    assert!(linearize_depth(0.5, 0.1, 100.0) < 1.0);
+}
+
+#[test]
+fn the_shadow_map_inset_sits_in_the_bottom_right_corner() {
+    let inset = shadow_map_inset(PhysicalSize::new(800, 600));
+    assert_eq!(inset.width, 200);
+    assert_eq!(inset.height, 200);
+    assert_eq!(inset.x + inset.width + 200 / 16, 800);
+    assert_eq!(inset.y + inset.height + 200 / 16, 600);
+    assert!(shadow_map_inset(PhysicalSize::new(2, 2)).is_empty());
+}
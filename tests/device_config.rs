@@ -0,0 +1,136 @@
+use wgpu::Features;
+use wgpu_learning::{device_config::DeviceConfig, error::AppError};
+
+#[test]
+fn optional_features_are_only_requested_where_available() {
+    let config = DeviceConfig {
+        required_features: Features::PUSH_CONSTANTS,
+        optional_features: Features::POLYGON_MODE_LINE | Features::TIMESTAMP_QUERY,
+        ..DeviceConfig::default()
+    };
+    let adapter_features =
+        Features::PUSH_CONSTANTS | Features::TIMESTAMP_QUERY | Features::MULTIVIEW;
+    assert_eq!(
+        config.features_for(adapter_features),
+        Ok(Features::PUSH_CONSTANTS | Features::TIMESTAMP_QUERY)
+    );
+}
+
+#[test]
+fn missing_required_features_are_listed() {
+    let config = DeviceConfig {
+        required_features: Features::PUSH_CONSTANTS
+            | Features::MULTIVIEW
+            | Features::POLYGON_MODE_LINE,
+        ..DeviceConfig::default()
+    };
+    assert_eq!(
+        config.features_for(Features::POLYGON_MODE_LINE),
+        Err(Features::PUSH_CONSTANTS | Features::MULTIVIEW)
+    );
+
+    let err = AppError::MissingFeatures {
+        adapter: "Some GPU".to_owned(),
+        missing: Features::PUSH_CONSTANTS | Features::MULTIVIEW,
+    };
+    assert_eq!(
+        err.to_string(),
+        "Some GPU lacks the required features PUSH_CONSTANTS, MULTIVIEW"
+    );
+}
+
+// Creating a device blocks on the GPU, which the web doesn't allow
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn headless_states_get_the_requested_features() {
+    use wgpu_learning::State;
+
+    let state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping device test: {err}");
+            return;
+        }
+    };
+    // Whatever was granted as optional can be required
+    let granted = state.features();
+    // Two GL devices at once don't get along
+    drop(state);
+    let config = DeviceConfig {
+        required_features: granted,
+        optional_features: Features::empty(),
+        ..DeviceConfig::default()
+    };
+    let state = pollster::block_on(State::new_headless_with_device(16, 16, 1, &config)).unwrap();
+    assert_eq!(state.features(), granted);
+    drop(state);
+
+    // No adapter has every feature
+    let config = DeviceConfig {
+        required_features: Features::all(),
+        ..DeviceConfig::default()
+    };
+    match pollster::block_on(State::new_headless_with_device(16, 16, 1, &config)) {
+        Err(AppError::MissingFeatures { missing, .. }) => {
+            assert!(!missing.is_empty());
+            assert!(!granted.intersects(missing));
+        }
+        Err(err) => panic!("expected missing features, got {err}"),
+        Ok(_) => panic!("expected missing features"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn trace_dirs_are_created_when_missing() {
+    use wgpu_learning::device_config::prepare_trace_dir;
+
+    let root = std::env::temp_dir().join(format!("wgpu_learning_trace_{}", std::process::id()));
+    let dir = root.join("nested").join("trace");
+    prepare_trace_dir(&dir).unwrap();
+    assert!(dir.is_dir());
+    // Only the directory is left behind
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+    // A file where the directory should be can't be traced into
+    let file = root.join("file");
+    std::fs::write(&file, b"not a directory").unwrap();
+    assert!(prepare_trace_dir(&file.join("trace")).is_err());
+
+    // An explicit path wins over WGPU_TRACE
+    let config = DeviceConfig {
+        trace_path: Some(dir.clone()),
+        ..DeviceConfig::default()
+    };
+    assert_eq!(config.trace_dir(), Some(dir));
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+// Creating a device blocks on the GPU, which the web doesn't allow
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn devices_write_their_api_trace() {
+    use wgpu_learning::State;
+
+    let dir = std::env::temp_dir()
+        .join(format!("wgpu_learning_api_trace_{}", std::process::id()))
+        .join("trace");
+    let config = DeviceConfig {
+        trace_path: Some(dir.clone()),
+        ..DeviceConfig::default()
+    };
+    let mut state = match pollster::block_on(State::new_headless_with_device(16, 16, 1, &config)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping trace test: {err}");
+            return;
+        }
+    };
+    state.render_to_vec().unwrap();
+    drop(state);
+
+    let trace = std::fs::read_to_string(dir.join("trace.ron")).expect("no trace was written");
+    assert!(trace.contains("Submit"), "{trace}");
+    std::fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use wgpu_learning::file_drop::{DropKind, DropLoader};
+
+#[test]
+fn dropped_files_are_told_apart_by_their_extension() {
+    assert_eq!(DropKind::of(Path::new("cube.obj")), Some(DropKind::Model));
+    assert_eq!(
+        DropKind::of(Path::new("a/b/Fox.GLB")),
+        Some(DropKind::Model)
+    );
+    assert_eq!(DropKind::of(Path::new("scene.gltf")), Some(DropKind::Model));
+    assert_eq!(
+        DropKind::of(Path::new("bricks.jpeg")),
+        Some(DropKind::Image)
+    );
+    assert_eq!(DropKind::of(Path::new("bricks.PNG")), Some(DropKind::Image));
+    assert_eq!(DropKind::of(Path::new("notes.txt")), None);
+    assert_eq!(DropKind::of(Path::new("Makefile")), None);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn failed_drops_dont_hold_back_the_next_ones() {
+    let dir =
+        std::env::temp_dir().join(format!("wgpu_learning_failed_drop_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let image = dir.join("blue.png");
+    image::RgbaImage::from_pixel(2, 2, image::Rgba([0, 0, 255, 255]))
+        .save(&image)
+        .unwrap();
+
+    let mut loader = DropLoader::new();
+    let missing = dir.join("missing.png");
+    assert_eq!(loader.load(&missing), Some(DropKind::Image));
+    assert_eq!(loader.load(&image), Some(DropKind::Image));
+    let loaded = loader.wait();
+    assert!(!loader.is_loading());
+    let paths: Vec<_> = loaded.iter().map(|(path, _)| path.clone()).collect();
+    assert_eq!(paths, [missing, image]);
+    assert!(loaded[0].1.is_err());
+    assert!(loaded[1].1.is_ok());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+// Headless rendering blocks on the GPU, which the web doesn't allow
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn dropped_files_replace_the_model_and_its_texture() {
+    use wgpu_learning::State;
+    use winit::event::WindowEvent;
+
+    let dir = std::env::temp_dir().join(format!("wgpu_learning_drop_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let diffuse_width = |state: &State| {
+        state.model().unwrap().materials[0]
+            .diffuse_texture
+            .texture
+            .width()
+    };
+    assert_eq!(diffuse_width(&state), 64);
+
+    let image = dir.join("red.png");
+    image::RgbaImage::from_pixel(4, 2, image::Rgba([255, 0, 0, 255]))
+        .save(&image)
+        .unwrap();
+    assert!(state.input(&WindowEvent::HoveredFile(image.clone())));
+    assert!(state.file_hovered());
+    assert!(state.input(&WindowEvent::DroppedFile(image)));
+    assert!(!state.file_hovered());
+    assert!(state.drop_status().unwrap().starts_with("Loading red.png"));
+    state.wait_for_assets();
+    assert_eq!(state.drop_status(), None);
+    assert_eq!(diffuse_width(&state), 4);
+
+    // A broken model leaves the old one, and says why in the HUD
+    let meshes = state.model().unwrap().meshes.len();
+    let broken = dir.join("broken.glb");
+    std::fs::write(&broken, b"not a glb").unwrap();
+    state.drop_file(&broken);
+    state.wait_for_assets();
+    assert!(state.drop_status().unwrap().contains("broken.glb"));
+    assert_eq!(state.model().unwrap().meshes.len(), meshes);
+    assert_eq!(diffuse_width(&state), 4);
+
+    // Unsupported files are only warned about, nothing starts loading
+    state.drop_file(&dir.join("notes.txt"));
+    assert!(state
+        .drop_status()
+        .unwrap()
+        .starts_with("Can't use broken.glb"));
+
+    let cube = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/cube/cube.obj");
+    state.drop_file(&cube);
+    state.wait_for_assets();
+    assert_eq!(state.drop_status(), None);
+    assert_eq!(diffuse_width(&state), 64);
+    state.render_to_vec().unwrap();
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use glam::{Quat, Vec3};
+use wgpu_learning::{
+    fixed_timestep::{FixedTimestep, MAX_FIXED_STEPS},
+    instance::{Instance, SteppedInstances},
+    light::{Lights, PointLight},
+};
+
+#[test]
+fn frames_take_as_many_steps_as_fit() {
+    let mut timestep = FixedTimestep::new(50);
+    assert_eq!(timestep.step(), Duration::from_millis(20));
+    assert_eq!(timestep.advance(Duration::from_millis(10)), 0);
+    assert_eq!(timestep.alpha(), 0.5);
+    // The leftover carries over
+    assert_eq!(timestep.advance(Duration::from_millis(35)), 2);
+    assert!((timestep.alpha() - 0.25).abs() < 1e-6);
+
+    // A long frame doesn't make the next one even longer, that time is gone
+    assert_eq!(timestep.advance(Duration::from_secs(10)), MAX_FIXED_STEPS);
+    assert_eq!(timestep.alpha(), 0.0);
+    assert_eq!(FixedTimestep::new(0).rate(), 1);
+}
+
+#[test]
+fn lights_and_instances_are_drawn_between_steps() {
+    let mut lights = Lights::new(None);
+    let id = lights
+        .add_light(PointLight::new(Vec3::X, Vec3::ONE))
+        .unwrap();
+    lights.begin_step();
+    lights.light_mut(id).unwrap().position = Vec3::new(3.0, 0.0, 0.0);
+    let halfway = lights.interpolated(0.5);
+    assert_eq!(
+        halfway.light(id).unwrap().position,
+        Vec3::new(2.0, 0.0, 0.0)
+    );
+    assert_eq!(lights.interpolated(1.0).light(id), lights.light(id));
+
+    let at = |x| Instance {
+        position: Vec3::new(x, 0.0, 0.0),
+        rotation: Quat::IDENTITY,
+    };
+    let mut instances = SteppedInstances::new(vec![at(0.0)]);
+    instances.move_to(vec![at(4.0)]);
+    // Only the step takes them there
+    assert_eq!(instances.current(), [at(0.0)]);
+    instances.begin_step();
+    assert_eq!(instances.current(), [at(4.0)]);
+    assert!(instances.is_moving());
+    assert_eq!(instances.interpolated(0.25), [at(1.0)]);
+    instances.begin_step();
+    assert!(!instances.is_moving());
+
+    // New instances can't move from anywhere
+    instances.move_to(vec![at(1.0), at(2.0)]);
+    instances.begin_step();
+    assert!(!instances.is_moving());
+}
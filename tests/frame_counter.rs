@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use web_time::Instant;
+use wgpu_learning::frame_counter::{FrameCounter, FrameTime, FrameTimeHistory};
+
+#[test]
+fn stats_are_only_reported_once_per_interval() {
+    let mut counter = FrameCounter::new(Duration::from_secs(1));
+    let start = Instant::now();
+
+    assert_eq!(counter.tick_at(start), None);
+    // 10 ms frames, except for a single 20 ms one
+    let mut now = start;
+    for i in 1..=98 {
+        now += Duration::from_millis(if i == 50 { 20 } else { 10 });
+        assert_eq!(counter.tick_at(now), None, "frame {i}");
+    }
+    assert_eq!(counter.stats(), None);
+
+    now += Duration::from_millis(10);
+    let stats = counter.tick_at(now).expect("the interval is over");
+    assert!((stats.fps - 99.0).abs() < 0.01, "{stats:?}");
+    assert_eq!(stats.avg_frame_time, Duration::from_secs(1) / 99);
+    assert_eq!(stats.min_frame_time, Duration::from_millis(10));
+    assert_eq!(stats.max_frame_time, Duration::from_millis(20));
+    assert_eq!(counter.stats(), Some(stats));
+
+    // A new interval starts from scratch
+    now += Duration::from_millis(30);
+    assert_eq!(counter.tick_at(now), None);
+    assert_eq!(counter.stats(), Some(stats));
+}
+
+#[test]
+fn encode_times_are_averaged_per_interval() {
+    let mut counter = FrameCounter::new(Duration::from_secs(1));
+    let start = Instant::now();
+    counter.tick_at(start);
+    counter.record_encode_time(Duration::from_millis(1));
+    counter.record_encode_time(Duration::from_millis(3));
+    let stats = counter.tick_at(start + Duration::from_secs(1)).unwrap();
+    assert_eq!(stats.avg_encode_time, Duration::from_millis(2));
+
+    // Without any recorded it's 0
+    let stats = counter.tick_at(start + Duration::from_secs(2)).unwrap();
+    assert_eq!(stats.avg_encode_time, Duration::ZERO);
+}
+
+#[test]
+fn the_history_keeps_the_last_frames_and_finds_the_spikes() {
+    let frame = |cpu_ms, gpu_ms: Option<u64>| FrameTime {
+        cpu: Duration::from_millis(cpu_ms),
+        gpu: gpu_ms.map(Duration::from_millis),
+    };
+    let mut history = FrameTimeHistory::new(4);
+    assert_eq!(history.median_cpu(), Duration::ZERO);
+    assert_eq!(history.scale(), Duration::from_millis(1));
+
+    for cpu in [50, 4, 5, 6, 25] {
+        history.push(frame(cpu, None));
+    }
+    // The 50 ms frame fell out
+    assert_eq!(history.len(), 4);
+    assert_eq!(history.iter().next(), Some(&frame(4, None)));
+    assert_eq!(history.median_cpu(), Duration::from_millis(6));
+    let spikes: Vec<_> = history
+        .iter()
+        .map(|frame| frame.is_spike(history.median_cpu()))
+        .collect();
+    assert_eq!(spikes, [false, false, false, true]);
+
+    // The GPU can take longer than the CPU, and the graph makes room for it
+    history.push(frame(3, Some(30)));
+    assert_eq!(history.scale(), Duration::from_millis(30));
+    history.push(frame(3, Some(1)));
+    history.push(FrameTime {
+        cpu: Duration::from_micros(30_200),
+        gpu: None,
+    });
+    assert_eq!(history.scale(), Duration::from_millis(31));
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use web_time::Instant;
+use wgpu_learning::frame_limiter::{self, FrameLimiter};
+
+#[test]
+fn frames_are_spaced_by_the_cap() {
+    let mut limiter = FrameLimiter::new(Some(50));
+    assert_eq!(limiter.frame_time(), Some(Duration::from_millis(20)));
+
+    let start = Instant::now();
+    assert_eq!(limiter.next_frame(), None);
+    limiter.frame_presented(start);
+    assert_eq!(
+        limiter.next_frame(),
+        Some(start + Duration::from_millis(20))
+    );
+
+    // A frame that finished early doesn't move the next target
+    limiter.frame_presented(start + Duration::from_millis(25));
+    assert_eq!(
+        limiter.next_frame(),
+        Some(start + Duration::from_millis(40))
+    );
+}
+
+#[test]
+fn falling_behind_does_not_rush_frames_to_catch_up() {
+    let mut limiter = FrameLimiter::new(Some(50));
+    let start = Instant::now();
+    limiter.frame_presented(start);
+
+    let late = start + Duration::from_millis(100);
+    limiter.frame_presented(late);
+    assert_eq!(limiter.next_frame(), Some(late));
+}
+
+#[test]
+fn no_cap_never_waits() {
+    let mut limiter = FrameLimiter::new(Some(0));
+    assert_eq!(limiter.max_fps(), None);
+    limiter.frame_presented(Instant::now());
+    assert_eq!(limiter.next_frame(), None);
+
+    limiter.set_max_fps(Some(30));
+    assert_eq!(limiter.max_fps(), Some(30));
+    limiter.set_max_fps(None);
+    assert_eq!(limiter.frame_time(), None);
+}
+
+#[test]
+fn vsync_makes_caps_above_the_refresh_rate_unnecessary() {
+    let fifo = wgpu::PresentMode::Fifo;
+    assert!(!frame_limiter::is_needed(144, fifo, Some(60_000)));
+    assert!(!frame_limiter::is_needed(60, fifo, Some(60_000)));
+    assert!(frame_limiter::is_needed(30, fifo, Some(60_000)));
+    // Without knowing the refresh rate or without vsync the cap applies
+    assert!(frame_limiter::is_needed(144, fifo, None));
+    assert!(frame_limiter::is_needed(
+        144,
+        wgpu::PresentMode::Immediate,
+        Some(60_000)
+    ));
+}
@@ -0,0 +1,99 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn the_graph_is_drawn_in_the_top_right_corner_with_the_spikes_in_red() {
+    use std::time::Duration;
+
+    use wgpu_learning::{
+        frame_counter::FrameTime, frame_time_graph::FrameTimeGraph, texture::padded_bytes_per_row,
+    };
+    use winit::dpi::PhysicalSize;
+
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+
+    let size = PhysicalSize::new(320, 100);
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("graph_target"),
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&Default::default());
+
+    let mut graph = FrameTimeGraph::new(&device, format).unwrap();
+    for cpu in [5, 5, 6, 5, 50] {
+        graph.record(FrameTime {
+            cpu: Duration::from_millis(cpu),
+            gpu: Some(Duration::from_millis(2)),
+        });
+    }
+    assert_eq!(graph.history().len(), 5);
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    // Only clears the target
+    drop(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        ..Default::default()
+    }));
+    graph.draw(&queue, &mut encoder, &view, size, 1.0);
+
+    let bytes_per_row = padded_bytes_per_row(size.width * 4);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("graph_readback"),
+        size: u64::from(bytes_per_row * size.height),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(size.height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let pixels = buffer.slice(..).get_mapped_range().to_vec();
+    let pixel = |x: u32, y: u32| {
+        let i = (y * bytes_per_row + x * 4) as usize;
+        [pixels[i], pixels[i + 1], pixels[i + 2]]
+    };
+
+    // Left of the graph and below it nothing changes
+    assert_eq!(pixel(4, 50), [255, 255, 255]);
+    assert_eq!(pixel(200, 95), [255, 255, 255]);
+    // The background darkens the empty part of the graph
+    let [r, g, b] = pixel(100, 20);
+    assert!(r < 255 && r == g && g == b, "{:?}", [r, g, b]);
+    // The newest frame is on the right, as tall as the graph
+    let [r, g, b] = pixel(311, 15);
+    assert!(r > 200 && g < 100 && b < 100, "{:?}", [r, g, b]);
+}
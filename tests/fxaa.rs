@@ -0,0 +1,131 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use wgpu_learning::fxaa::{Antialiasing, FxaaSettings, FxaaUniform};
+use winit::dpi::PhysicalSize;
+
+#[test]
+fn antialiasing_cycles_from_cheapest_to_best() {
+    let mut antialiasing = Antialiasing::None;
+    let mut seen = Vec::new();
+    for _ in 0..3 {
+        antialiasing = antialiasing.next();
+        seen.push(antialiasing);
+    }
+    assert_eq!(
+        seen,
+        [Antialiasing::Fxaa, Antialiasing::Msaa, Antialiasing::None]
+    );
+}
+
+#[test]
+fn the_uniform_has_the_texel_size_and_never_divides_by_0() {
+    let settings = FxaaSettings::default();
+    let uniform = FxaaUniform::new(settings, PhysicalSize::new(4, 8), true);
+    assert_eq!(uniform.texel_size, [0.25, 0.125]);
+    assert_eq!(uniform.edge_threshold, settings.edge_threshold);
+    assert_eq!(uniform.linear_input, 1);
+    let uniform = FxaaUniform::new(settings, PhysicalSize::new(0, 0), false);
+    assert_eq!(uniform.texel_size, [1.0, 1.0]);
+    assert_eq!(uniform.linear_input, 0);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn only_the_edges_get_smoothed() {
+    use wgpu_learning::{fxaa::Fxaa, texture::padded_bytes_per_row};
+
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+
+    let size = 16;
+    let format = wgpu::TextureFormat::Rgba8Unorm;
+    let extent = wgpu::Extent3d {
+        width: size,
+        height: size,
+        depth_or_array_layers: 1,
+    };
+    let texture = |label, usage| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        })
+    };
+    // White below the diagonal, black above it, with aliased steps
+    let source = texture(
+        "fxaa_source",
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    );
+    let pixels = (0..size)
+        .flat_map(|y| (0..size).flat_map(move |x| [if x < y { 255 } else { 0 }; 4]))
+        .collect::<Vec<u8>>();
+    queue.write_texture(
+        source.as_image_copy(),
+        &pixels,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(size * 4),
+            rows_per_image: Some(size),
+        },
+        extent,
+    );
+    let target = texture(
+        "fxaa_target",
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    );
+
+    let mut fxaa = Fxaa::new(&device, format).unwrap();
+    fxaa.set_source(
+        &device,
+        &queue,
+        &source.create_view(&Default::default()),
+        PhysicalSize::new(size, size),
+    );
+    let bytes_per_row = padded_bytes_per_row(size * 4);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("fxaa_readback"),
+        size: u64::from(bytes_per_row * size),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    fxaa.draw(&mut encoder, &target.create_view(&Default::default()));
+    encoder.copy_texture_to_buffer(
+        target.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(size),
+            },
+        },
+        extent,
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let data = buffer.slice(..).get_mapped_range();
+    let red = |x: u32, y: u32| data[(y * bytes_per_row + x * 4) as usize];
+
+    // Far from the edge nothing changes
+    assert_eq!(red(0, size - 1), 255);
+    assert_eq!(red(size - 1, 0), 0);
+    assert_eq!(red(2, 12), 255);
+    assert_eq!(red(12, 2), 0);
+    // Along it the steps get blended
+    let blended = (1..size - 1)
+        .flat_map(|y| [red(y - 1, y), red(y, y)])
+        .filter(|&red| red > 0 && red < 255)
+        .count();
+    assert!(blended > 0);
+}
@@ -0,0 +1,14 @@
+#![cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+
+use wgpu_learning::gamepad::apply_dead_zone;
+
+#[test]
+fn dead_zone_is_cut_out_and_the_rest_rescaled() {
+    assert_eq!(apply_dead_zone(0.0, 0.2), 0.0);
+    assert_eq!(apply_dead_zone(0.1, 0.2), 0.0);
+    assert_eq!(apply_dead_zone(-0.2, 0.2), 0.0);
+    assert!((apply_dead_zone(0.6, 0.2) - 0.5).abs() < 1e-6);
+    assert!((apply_dead_zone(-0.6, 0.2) + 0.5).abs() < 1e-6);
+    assert_eq!(apply_dead_zone(1.0, 0.2), 1.0);
+    assert_eq!(apply_dead_zone(-1.0, 0.2), -1.0);
+}
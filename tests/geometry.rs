@@ -0,0 +1,168 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use std::collections::HashMap;
+
+use glam::Vec3;
+use wgpu_learning::geometry::{self, Geometry};
+
+fn position(geometry: &Geometry, index: u32) -> Vec3 {
+    Vec3::from(geometry.vertices[index as usize].position)
+}
+
+/// Rounded, so vertices at the same place with different normals or
+/// texture coordinates count as the same point.
+fn point(geometry: &Geometry, index: u32) -> [i32; 3] {
+    (position(geometry, index) * 10_000.0)
+        .round()
+        .as_ivec3()
+        .to_array()
+}
+
+/// How many triangles go along each edge in each direction.
+fn edges(geometry: &Geometry) -> HashMap<([i32; 3], [i32; 3]), usize> {
+    let mut edges = HashMap::new();
+    for triangle in geometry.indices.chunks(3) {
+        for i in 0..3 {
+            let edge = (
+                point(geometry, triangle[i]),
+                point(geometry, triangle[(i + 1) % 3]),
+            );
+            *edges.entry(edge).or_insert(0) += 1;
+        }
+    }
+    edges
+}
+
+/// Every edge is between exactly two triangles, which go along it in
+/// opposite directions because they're wound the same way.
+fn assert_closed(geometry: &Geometry) {
+    let edges = edges(geometry);
+    for (&(from, to), &count) in &edges {
+        assert_eq!(count, 1, "{from:?} -> {to:?}");
+        assert_eq!(edges.get(&(to, from)), Some(&1), "{from:?} -> {to:?}");
+    }
+}
+
+/// The triangles are counter-clockwise seen from outside, which for these
+/// centered shapes means away from the origin.
+fn assert_facing_out(geometry: &Geometry) {
+    for triangle in geometry.indices.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| position(geometry, triangle[i]));
+        let normal = (b - a).cross(c - a);
+        assert!(normal.length() > 0.0, "degenerate triangle {triangle:?}");
+        assert!(normal.dot(a + b + c) > 0.0, "{triangle:?} faces inwards");
+    }
+}
+
+fn assert_normals_normalized(geometry: &Geometry) {
+    for vertex in &geometry.vertices {
+        let normal = Vec3::from(vertex.normal);
+        assert!((normal.length() - 1.0).abs() < 1e-5, "{normal}");
+        for uv in vertex.tex_coords {
+            assert!((0.0..=1.0).contains(&uv), "{:?}", vertex.tex_coords);
+        }
+    }
+    assert!(geometry
+        .indices
+        .iter()
+        .all(|&index| (index as usize) < geometry.vertices.len()));
+}
+
+#[test]
+fn the_cube_is_closed_and_faces_out() {
+    let cube = geometry::cube();
+    assert_eq!(cube.vertices.len(), 24);
+    assert_eq!(cube.indices.len(), 36);
+    assert_closed(&cube);
+    assert_facing_out(&cube);
+    assert_normals_normalized(&cube);
+    for vertex in &cube.vertices {
+        assert!(vertex.position.iter().all(|p| p.abs() == 0.5));
+    }
+}
+
+#[test]
+fn spheres_are_closed_and_face_out() {
+    for (rings, segments) in [(2, 3), (8, 16), (15, 7)] {
+        let sphere = geometry::uv_sphere(rings, segments);
+        assert_eq!(
+            sphere.indices.len(),
+            (segments * (rings - 1) * 6) as usize,
+            "{rings}x{segments}"
+        );
+        assert_closed(&sphere);
+        assert_facing_out(&sphere);
+        assert_normals_normalized(&sphere);
+        for vertex in &sphere.vertices {
+            let position = Vec3::from(vertex.position);
+            assert!((position.length() - 0.5).abs() < 1e-5);
+            assert!(position.normalize().abs_diff_eq(vertex.normal.into(), 1e-5));
+        }
+    }
+    // Too few rings and segments are the fewest there can be
+    assert_eq!(geometry::uv_sphere(0, 0), geometry::uv_sphere(2, 3));
+}
+
+#[test]
+fn cylinders_are_closed_and_face_out() {
+    for segments in [3, 4, 32] {
+        let cylinder = geometry::cylinder(segments);
+        assert_eq!(cylinder.indices.len(), (segments * 12) as usize);
+        assert_closed(&cylinder);
+        assert_facing_out(&cylinder);
+        assert_normals_normalized(&cylinder);
+    }
+}
+
+#[test]
+fn planes_face_up_with_one_edge_around_them() {
+    for subdivisions in [1, 2, 5] {
+        let plane = geometry::plane(subdivisions);
+        assert_eq!(
+            plane.vertices.len(),
+            ((subdivisions + 1) * (subdivisions + 1)) as usize
+        );
+        assert_eq!(
+            plane.indices.len(),
+            (subdivisions * subdivisions * 6) as usize
+        );
+        assert_normals_normalized(&plane);
+        for triangle in plane.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| position(&plane, triangle[i]));
+            assert!((b - a).cross(c - a).y > 0.0, "{triangle:?} faces down");
+        }
+
+        // The edges inside are shared like in the closed shapes, the ones
+        // around the outside only have one triangle
+        let edges = edges(&plane);
+        let outside = edges
+            .keys()
+            .filter(|(from, to)| !edges.contains_key(&(*to, *from)))
+            .count();
+        assert_eq!(outside, (subdivisions * 4) as usize);
+        assert!(edges.values().all(|&count| count == 1));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn meshes_upload_the_geometry() {
+    let Some((device, _queue)) = common::device() else {
+        return;
+    };
+
+    let sphere = geometry::uv_sphere(4, 8);
+    let mesh = wgpu_learning::model::Mesh::from_geometry(&device, "sphere", &sphere, 3);
+    assert_eq!(mesh.name, "sphere");
+    assert_eq!(mesh.material, 3);
+    assert_eq!(mesh.vertex_buffer.len(), sphere.vertices.len() as u32);
+    assert_eq!(
+        mesh.index_buffer.as_ref().unwrap().len(),
+        sphere.indices.len() as u32
+    );
+    assert!(mesh.index_buffer.is_some());
+    let bounds = mesh.bounds.unwrap();
+    assert!(bounds.min.abs_diff_eq(Vec3::splat(-0.5), 1e-5));
+    assert!(bounds.max.abs_diff_eq(Vec3::splat(0.5), 1e-5));
+}
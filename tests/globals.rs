@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use wgpu_learning::globals::Globals;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+#[test]
+fn time_stands_still_while_paused() {
+    let mut globals = Globals::new(PhysicalSize::new(640, 480));
+    assert_eq!(globals.uniform().resolution, [640.0, 480.0]);
+    assert_eq!(globals.uniform().frame, 0);
+
+    globals.advance(Duration::from_millis(250));
+    globals.advance(Duration::from_millis(250));
+    assert_eq!(globals.time(), Duration::from_millis(500));
+    assert_eq!(globals.uniform().time, 0.5);
+    assert_eq!(globals.uniform().delta_time, 0.25);
+    assert_eq!(globals.uniform().frame, 2);
+
+    // Frames still count, but no time passes
+    globals.set_paused(true);
+    globals.advance(Duration::from_secs(3));
+    assert!(globals.is_paused());
+    assert_eq!(globals.uniform().time, 0.5);
+    assert_eq!(globals.uniform().delta_time, 0.0);
+    assert_eq!(globals.uniform().frame, 3);
+
+    globals.set_paused(false);
+    globals.advance(Duration::from_millis(500));
+    assert_eq!(globals.uniform().time, 1.0);
+}
+
+#[test]
+fn the_resolution_and_cursor_are_in_pixels() {
+    let mut globals = Globals::new(PhysicalSize::new(1, 1));
+    globals.set_resolution(PhysicalSize::new(800, 600));
+    globals.set_cursor(PhysicalPosition::new(12.5, 300.0));
+    assert_eq!(globals.uniform().resolution, [800.0, 600.0]);
+    assert_eq!(globals.uniform().cursor, [12.5, 300.0]);
+}
+
+// Headless rendering blocks on the GPU, which the web doesn't allow
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn the_plasma_background_moves_with_the_time() {
+    use wgpu_learning::{key_bindings::Action, State};
+    use winit::event::ElementState;
+
+    let (width, height) = (32, 24);
+    let mut state = match pollster::block_on(State::new_headless(width, height, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    // Only the background is left, the lights and particles move even
+    // while paused
+    state.set_instances(&[]);
+    let lights: Vec<_> = state.lights().iter().map(|(id, _)| id).collect();
+    for id in lights {
+        state.lights_mut().remove_light(id);
+    }
+    if let Some(particles) = state.particles_mut() {
+        particles.emitters_mut().clear();
+    }
+    state.update(Duration::ZERO);
+    let skybox = state.render_to_vec().unwrap();
+    assert!(state.handle_action(Action::TogglePlasma, ElementState::Pressed));
+    assert!(state.plasma());
+    let plasma = state.render_to_vec().unwrap();
+    assert_ne!(plasma, skybox);
+
+    state.update(Duration::from_millis(50));
+    let later = state.render_to_vec().unwrap();
+    assert_ne!(later, plasma);
+
+    // Paused, the frame stays the same however much time passes
+    assert!(state.handle_action(Action::TogglePause, ElementState::Pressed));
+    assert!(state.is_paused());
+    state.update(Duration::from_millis(50));
+    let time = state.globals().time();
+    state.update(Duration::from_millis(50));
+    assert_eq!(state.globals().time(), time);
+    assert_eq!(state.render_to_vec().unwrap(), later);
+
+    state.resize(PhysicalSize::new(16, 16));
+    assert_eq!(state.globals().uniform().resolution, [16.0, 16.0]);
+}
@@ -0,0 +1,295 @@
+use std::path::Path;
+
+use wgpu_learning::gltf::{self, GltfError};
+
+fn f32_bytes(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn glb(json: &str, binary: &[u8]) -> Vec<u8> {
+    let mut json = json.as_bytes().to_vec();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let mut binary = binary.to_vec();
+    binary.resize(binary.len().next_multiple_of(4), 0);
+
+    let length = 12 + 8 + json.len() + 8 + binary.len();
+    let mut glb = Vec::new();
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    glb.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&binary);
+    glb
+}
+
+fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-5, "{actual:?} != {expected:?}");
+    }
+}
+
+#[test]
+fn nodes_transform_their_children_and_missing_normals_are_flat() {
+    let positions = f32_bytes(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    let json = format!(
+        r#"{{
+            "asset": {{ "version": "2.0" }},
+            "scene": 0,
+            "scenes": [{{ "nodes": [0] }}],
+            "nodes": [
+                {{ "translation": [0, 0, 5], "children": [1] }},
+                {{ "scale": [2, 2, 2], "mesh": 0 }}
+            ],
+            "meshes": [{{ "name": "triangle", "primitives": [{{ "attributes": {{ "POSITION": 0 }} }}] }}],
+            "accessors": [{{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }}],
+            "bufferViews": [{{ "buffer": 0, "byteLength": 36 }}],
+            "buffers": [{{ "byteLength": 36, "uri": "data:application/octet-stream;base64,{}" }}]
+        }}"#,
+        base64(&positions)
+    );
+
+    let document = gltf::parse(json.as_bytes(), Path::new("")).unwrap();
+    assert_eq!(document.primitives.len(), 1);
+    let primitive = &document.primitives[0];
+    assert_eq!(primitive.name, "triangle");
+    assert_eq!(primitive.material, None);
+    assert_eq!(primitive.indices, [0, 1, 2]);
+
+    let positions: Vec<_> = primitive.vertices.iter().map(|v| v.position).collect();
+    assert_eq!(
+        positions,
+        [[0.0, 0.0, 5.0], [2.0, 0.0, 5.0], [0.0, 2.0, 5.0]]
+    );
+    for vertex in &primitive.vertices {
+        assert_close(vertex.normal, [0.0, 0.0, 1.0]);
+        assert_eq!(vertex.tex_coords, [0.0, 0.0]);
+    }
+}
+
+#[test]
+fn glb_reads_u16_indices_and_sparse_accessors() {
+    // Sparse indices, sparse positions, normals, then the indices
+    let mut binary = Vec::new();
+    binary.extend([1u16, 2].iter().flat_map(|i| i.to_le_bytes()));
+    binary.extend(f32_bytes(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0]));
+    binary.extend(f32_bytes(&[0.0, 0.0, 1.0].repeat(3)));
+    binary.extend([0u16, 1, 2].iter().flat_map(|i| i.to_le_bytes()));
+    let json = r#"{
+        "asset": { "version": "2.0" },
+        "nodes": [{ "matrix": [-1,0,0,0, 0,1,0,0, 0,0,1,0, 0,0,0,1], "mesh": 0 }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0, "NORMAL": 1 }, "indices": 2 }] }],
+        "accessors": [
+            {
+                "componentType": 5126, "count": 3, "type": "VEC3",
+                "sparse": {
+                    "count": 2,
+                    "indices": { "bufferView": 0, "componentType": 5123 },
+                    "values": { "bufferView": 1 }
+                }
+            },
+            { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC3" },
+            { "bufferView": 3, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ],
+        "bufferViews": [
+            { "buffer": 0, "byteLength": 4 },
+            { "buffer": 0, "byteOffset": 4, "byteLength": 24 },
+            { "buffer": 0, "byteOffset": 28, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 64, "byteLength": 6 }
+        ],
+        "buffers": [{ "byteLength": 70 }]
+    }"#;
+
+    let document = gltf::parse(&glb(json, &binary), Path::new("")).unwrap();
+    let primitive = &document.primitives[0];
+    let positions: Vec<_> = primitive.vertices.iter().map(|v| v.position).collect();
+    // Mirrored on x by the node
+    assert_eq!(
+        positions,
+        [[0.0, 0.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+    );
+    // Which turns the triangle around, so it's wound the other way
+    assert_eq!(primitive.indices, [0, 2, 1]);
+    for vertex in &primitive.vertices {
+        assert_close(vertex.normal, [0.0, 0.0, 1.0]);
+    }
+}
+
+#[test]
+fn material_factors_become_textures() {
+    let json = r#"{
+        "asset": { "version": "2.0" },
+        "materials": [
+            {
+                "name": "red_metal",
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [1, 0, 0, 0.5],
+                    "metallicFactor": 1,
+                    "roughnessFactor": 0.5
+                }
+            },
+            { "name": "plain" }
+        ]
+    }"#;
+
+    let document = gltf::parse(json.as_bytes(), Path::new("")).unwrap();
+    assert!(document.primitives.is_empty());
+    let [red_metal, plain] = &document.materials[..] else {
+        panic!("{} materials", document.materials.len());
+    };
+
+    assert_eq!(red_metal.name, "red_metal");
+    let base_color = red_metal.base_color.as_ref().unwrap();
+    assert_eq!(base_color.dimensions(), (1, 1));
+    assert_eq!(base_color.get_pixel(0, 0).0, [255, 0, 0, 128]);
+    let metallic_roughness = red_metal.metallic_roughness.as_ref().unwrap();
+    assert_eq!(metallic_roughness.get_pixel(0, 0).0, [0, 128, 255, 255]);
+    assert!(red_metal.normal.is_none());
+
+    // Without factors or textures the defaults of Model::load_gltf apply
+    assert!(plain.base_color.is_none());
+    assert!(plain.metallic_roughness.is_none());
+}
+
+#[test]
+fn broken_files_are_errors() {
+    let missing =
+        std::env::temp_dir().join(format!("wgpu_learning_missing_{}.gltf", std::process::id()));
+    assert!(matches!(gltf::read(&missing), Err(GltfError::Io { .. })));
+    assert!(matches!(
+        gltf::parse(b"{ \"asset\": ", Path::new("")),
+        Err(GltfError::Json(_))
+    ));
+    assert!(matches!(
+        gltf::parse(br#"{ "asset": { "version": "1.0" } }"#, Path::new("")),
+        Err(GltfError::Invalid(_))
+    ));
+
+    // Two vertices of two components can't be read as positions
+    let json = r#"{
+        "asset": { "version": "2.0" },
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+        "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC2" }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 24 }],
+        "buffers": [{ "byteLength": 24 }]
+    }"#;
+    let binary = f32_bytes(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+    assert!(matches!(
+        gltf::parse(&glb(json, &binary), Path::new("")),
+        Err(GltfError::Invalid(message)) if message.ends_with("POSITION isn't a VEC3")
+    ));
+    // Nor can a view reaching past the end of memory
+    let json = json.replace(
+        r#""byteLength": 24 }],"#,
+        r#""byteOffset": 18446744073709551615, "byteLength": 24 }],"#,
+    );
+    assert!(matches!(
+        gltf::parse(&glb(&json, &binary), Path::new("")),
+        Err(GltfError::Invalid(_))
+    ));
+
+    let mut version_3 = glb(r#"{ "asset": { "version": "2.0" } }"#, &[]);
+    version_3[4] = 3;
+    assert!(matches!(
+        gltf::parse(&version_3, Path::new("")),
+        Err(GltfError::Glb(_))
+    ));
+}
+
+#[test]
+fn skinned_primitives_keep_their_joints_and_rigid_ones_follow_their_node() {
+    // One triangle's positions, its joints as u8, its weights, then the
+    // keyframe times and translations
+    let mut binary = f32_bytes(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    binary.extend([1u8, 0, 0, 0].repeat(3));
+    binary.extend(f32_bytes(&[2.0, 0.0, 0.0, 0.0].repeat(3)));
+    binary.extend(f32_bytes(&[0.0, 1.0]));
+    binary.extend(f32_bytes(&[0.0, 0.0, 0.0, 0.0, 3.0, 0.0]));
+    let json = r#"{
+        "asset": { "version": "2.0" },
+        "nodes": [
+            { "name": "root", "children": [1] },
+            { "name": "bone", "translation": [0, 1, 0] },
+            { "translation": [5, 0, 0], "mesh": 0, "skin": 0 },
+            { "translation": [0, 0, 7], "mesh": 0 }
+        ],
+        "skins": [{ "joints": [0, 1] }],
+        "meshes": [{ "primitives": [{
+            "attributes": { "POSITION": 0, "NORMAL": 0, "JOINTS_0": 1, "WEIGHTS_0": 2 }
+        }] }],
+        "animations": [{
+            "name": "Raise",
+            "samplers": [{ "input": 3, "output": 4, "interpolation": "STEP" }],
+            "channels": [
+                { "sampler": 0, "target": { "node": 1, "path": "translation" } },
+                { "sampler": 0, "target": { "node": 1, "path": "weights" } }
+            ]
+        }],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+            { "bufferView": 1, "componentType": 5121, "count": 3, "type": "VEC4" },
+            { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC4" },
+            { "bufferView": 3, "componentType": 5126, "count": 2, "type": "SCALAR" },
+            { "bufferView": 4, "componentType": 5126, "count": 2, "type": "VEC3" }
+        ],
+        "bufferViews": [
+            { "buffer": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 12 },
+            { "buffer": 0, "byteOffset": 48, "byteLength": 48 },
+            { "buffer": 0, "byteOffset": 96, "byteLength": 8 },
+            { "buffer": 0, "byteOffset": 104, "byteLength": 24 }
+        ],
+        "buffers": [{ "byteLength": 128 }]
+    }"#;
+
+    let document = gltf::parse(&glb(json, &binary), Path::new("")).unwrap();
+    let skeleton = document.skeleton.expect("the document has a skin");
+    assert_eq!(skeleton.nodes[1].parent, Some(0));
+    assert_eq!(skeleton.nodes[1].name, "bone");
+    // The file's skin, then one for each mesh node
+    let joints: Vec<_> = skeleton
+        .skins
+        .iter()
+        .map(|skin| skin.joints.clone())
+        .collect();
+    assert_eq!(joints, [vec![0, 1], vec![2], vec![3]]);
+    // Only the translation channel is kept
+    assert_eq!(skeleton.clips[0].name, "Raise");
+    assert_eq!(skeleton.clips[0].channels.len(), 1);
+
+    let [skinned, rigid] = &document.primitives[..] else {
+        panic!("{} primitives", document.primitives.len());
+    };
+    // Skinned vertices stay where the file has them, whatever their node
+    assert_eq!(skinned.vertices[1].position, [1.0, 0.0, 0.0]);
+    assert_eq!(skinned.skin_vertices[0].joints, [1, 0, 0, 0]);
+    assert_eq!(skinned.skin_vertices[0].weights, [1.0, 0.0, 0.0, 0.0]);
+
+    assert_eq!(rigid.vertices[1].position, [1.0, 0.0, 7.0]);
+    assert_eq!(rigid.skin_vertices[0].joints, [3, 0, 0, 0]);
+}
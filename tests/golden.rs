@@ -0,0 +1,215 @@
+// Rendering blocks on the GPU, which the web doesn't allow
+#![cfg(not(target_arch = "wasm32"))]
+
+// Renders known scenes at a fixed size through the headless renderer, with
+// the crate's own shaders and tonemapping, and compares them with the
+// reference images in tests/golden. On a mismatch the actual image and the
+// difference go to target/golden. Run with UPDATE_GOLDEN=1 to write new
+// references, and with e.g. GOLDEN_TOLERANCE=8 to allow more difference on
+// GPUs that render further off.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use glam::{Quat, Vec3};
+use image::RgbaImage;
+use wgpu_learning::{
+    geometry::{self, Geometry},
+    instance::Instance,
+    model::{compute_tangents, ModelVertex},
+    State,
+};
+
+const SIZE: u32 = 256;
+/// How far each channel may be off without GOLDEN_TOLERANCE, GPUs don't
+/// rasterize and blend exactly alike.
+const DEFAULT_TOLERANCE: u8 = 2;
+
+/// A headless state of `SIZE`x`SIZE`, `None` without an adapter, e.g. on CI
+/// runners without a GPU, the tests are skipped then.
+fn headless_state() -> Option<State> {
+    match pollster::block_on(State::new_headless(SIZE, SIZE, 1)) {
+        Ok(state) => Some(state),
+        Err(err) => {
+            eprintln!("Skipping golden test: {err}");
+            None
+        }
+    }
+}
+
+/// Draws `geometry` once at the origin, looked at straight on from the
+/// front, instead of the default scene.
+fn show_geometry(state: &mut State, geometry: &Geometry) {
+    state.set_geometry("golden", geometry);
+    state.set_instances(&[Instance {
+        position: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+    }]);
+    let camera = state.camera_mut();
+    camera.eye = Vec3::new(0.0, 0.0, 2.0);
+    camera.target = Vec3::ZERO;
+}
+
+/// Renders the first frame of `state`, before anything moved.
+fn render(state: &mut State) -> RgbaImage {
+    state.update(Duration::ZERO);
+    let pixels = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    RgbaImage::from_raw(SIZE, SIZE, pixels).unwrap()
+}
+
+/// The per-channel difference of `actual` and `expected`, and how many
+/// pixels differ by more than `tolerance` in some channel.
+fn diff(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> (RgbaImage, usize) {
+    let mut mismatches = 0;
+    let diff = RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let (a, e) = (actual.get_pixel(x, y).0, expected.get_pixel(x, y).0);
+        let channels = [0, 1, 2, 3].map(|i| a[i].abs_diff(e[i]));
+        if channels.iter().any(|&channel| channel > tolerance) {
+            mismatches += 1;
+        }
+        // Opaque, so differences in alpha show up too
+        image::Rgba([
+            channels[0].max(channels[3]),
+            channels[1].max(channels[3]),
+            channels[2].max(channels[3]),
+            255,
+        ])
+    });
+    (diff, mismatches)
+}
+
+/// The tolerance of the comparisons that don't need their own,
+/// GOLDEN_TOLERANCE or `DEFAULT_TOLERANCE`.
+fn tolerance() -> u8 {
+    match std::env::var("GOLDEN_TOLERANCE") {
+        Ok(tolerance) => tolerance
+            .parse()
+            .unwrap_or_else(|err| panic!("GOLDEN_TOLERANCE={tolerance:?}: {err}")),
+        Err(_) => DEFAULT_TOLERANCE,
+    }
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+/// Compares `actual` with the reference image `name`, or replaces the
+/// reference with UPDATE_GOLDEN=1.
+fn check_golden(name: &str, actual: &RgbaImage, tolerance: u8) {
+    let reference = golden_dir().join(format!("{name}.png"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some_and(|update| update == "1") {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&reference).unwrap();
+        eprintln!("Updated {}", reference.display());
+        return;
+    }
+
+    let expected = image::open(&reference)
+        .unwrap_or_else(|err| {
+            panic!(
+                "Can't open {}: {err}, run with UPDATE_GOLDEN=1 to create it",
+                reference.display()
+            )
+        })
+        .to_rgba8();
+    assert_eq!(actual.dimensions(), expected.dimensions(), "{name}");
+    let (diff_image, mismatches) = diff(actual, &expected, tolerance);
+    if mismatches == 0 {
+        return;
+    }
+
+    let out_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("target")
+        .join("golden");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let actual_path = out_dir.join(format!("{name}.actual.png"));
+    let diff_path = out_dir.join(format!("{name}.diff.png"));
+    actual.save(&actual_path).unwrap();
+    diff_image.save(&diff_path).unwrap();
+    panic!(
+        "{mismatches} pixels of {name} differ from {} by more than {tolerance}, see {} and {}",
+        reference.display(),
+        actual_path.display(),
+        diff_path.display()
+    );
+}
+
+#[test]
+fn diffs_only_count_pixels_beyond_the_tolerance() {
+    let expected = RgbaImage::from_pixel(2, 1, image::Rgba([100, 100, 100, 255]));
+    let mut actual = expected.clone();
+    actual.put_pixel(0, 0, image::Rgba([102, 100, 100, 255]));
+    assert_eq!(diff(&actual, &expected, 2).1, 0);
+
+    actual.put_pixel(1, 0, image::Rgba([100, 100, 100, 250]));
+    let (diff_image, mismatches) = diff(&actual, &expected, 2);
+    assert_eq!(mismatches, 1);
+    assert_eq!(diff_image.get_pixel(1, 0).0, [5, 5, 5, 255]);
+}
+
+#[test]
+fn clear_color() {
+    let Some(mut state) = headless_state() else {
+        return;
+    };
+    // Nothing but the clear color, no sky, model or light gizmos
+    state.set_skybox(None);
+    state.set_instances(&[]);
+    let lights: Vec<_> = state.lights().iter().map(|(id, _)| id).collect();
+    for id in lights {
+        state.lights_mut().remove_light(id);
+    }
+    state.set_clear_color(wgpu::Color {
+        r: 0.1,
+        g: 0.2,
+        b: 0.3,
+        a: 1.0,
+    });
+    let image = render(&mut state);
+    check_golden("clear_color", &image, tolerance());
+}
+
+#[test]
+fn triangle() {
+    let Some(mut state) = headless_state() else {
+        return;
+    };
+    let vertex = |x: f32, y: f32| ModelVertex {
+        position: [x, y, 0.0],
+        tex_coords: [x + 0.5, 0.5 - y],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [0.0; 3],
+        bitangent: [0.0; 3],
+    };
+    let mut vertices = vec![vertex(0.0, 0.5), vertex(-0.5, -0.5), vertex(0.5, -0.5)];
+    let indices = vec![0, 1, 2];
+    compute_tangents(&mut vertices, &indices);
+    show_geometry(&mut state, &Geometry { vertices, indices });
+    let image = render(&mut state);
+    check_golden("triangle", &image, tolerance());
+}
+
+#[test]
+fn textured_quad() {
+    let Some(mut state) = headless_state() else {
+        return;
+    };
+    // The plane faces up, turned to face the camera
+    let mut quad = geometry::plane(1);
+    for vertex in &mut quad.vertices {
+        let turn = |v: [f32; 3]| Quat::from_rotation_x(std::f32::consts::FRAC_PI_2) * Vec3::from(v);
+        vertex.position = turn(vertex.position).into();
+        vertex.normal = turn(vertex.normal).into();
+        vertex.tangent = turn(vertex.tangent).into();
+        vertex.bitangent = turn(vertex.bitangent).into();
+    }
+    show_geometry(&mut state, &quad);
+    let image = render(&mut state);
+    check_golden("textured_quad", &image, tolerance());
+}
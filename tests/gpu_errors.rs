@@ -0,0 +1,85 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use wgpu_learning::gpu_errors::ErrorContext;
+
+#[test]
+fn scopes_nest_and_end_with_their_guard() {
+    let context = ErrorContext::default();
+    context.set_frame(12);
+    assert_eq!(context.describe(), "in frame 12");
+
+    {
+        let _pass = context.scope("render pass");
+        assert_eq!(context.describe(), "in frame 12 during render pass");
+        {
+            let _upload = context.scope("texture upload");
+            assert_eq!(context.current_scope(), Some("texture upload"));
+        }
+        assert_eq!(context.current_scope(), Some("render pass"));
+    }
+    assert_eq!(context.current_scope(), None);
+}
+
+// The errors would panic with the feature
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "panic-on-gpu-error")))]
+#[test]
+fn uncaptured_errors_are_counted_and_lost_devices_noticed() {
+    use wgpu_learning::gpu_errors::GpuErrorHandlers;
+
+    let Some((device, _queue)) = common::device() else {
+        return;
+    };
+    let handlers = GpuErrorHandlers::install(&device);
+
+    // A texture can't be 0 texels wide
+    let _scope = handlers.context().scope("texture upload");
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("empty_texture"),
+        size: wgpu::Extent3d {
+            width: 0,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    assert_eq!(handlers.error_count(), 1);
+
+    assert_eq!(handlers.device_lost(), None);
+    device.destroy();
+    device.poll(wgpu::Maintain::Wait);
+    assert!(handlers.device_lost().is_some());
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "panic-on-gpu-error")))]
+#[test]
+fn recreated_devices_keep_rendering() {
+    use wgpu_learning::State;
+
+    let mut state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping GPU error test: {err}");
+            return;
+        }
+    };
+    let clear_color = wgpu::Color {
+        r: 0.2,
+        g: 0.4,
+        b: 0.6,
+        a: 1.0,
+    };
+    state.set_clear_color(clear_color);
+    let before = state.render_to_vec().unwrap();
+
+    assert!(!state.device_lost());
+    pollster::block_on(state.recreate_device()).unwrap();
+    assert_eq!(state.clear_color(), clear_color);
+    assert_eq!(state.render_to_vec().unwrap(), before);
+    assert_eq!(state.gpu_error_count(), 0);
+}
@@ -0,0 +1,62 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use wgpu_learning::gpu_memory::{self, format_bytes, GpuAllocation};
+
+#[test]
+fn allocations_count_until_dropped() {
+    // Other tests in this file don't allocate, so nothing else moves it
+    let before = gpu_memory::allocated();
+    let a = GpuAllocation::new(1000);
+    let b = GpuAllocation::new(24);
+    assert_eq!(gpu_memory::allocated(), before + 1024);
+    drop(a);
+    assert_eq!(gpu_memory::allocated(), before + 24);
+    assert_eq!(b.bytes(), 24);
+    drop(b);
+    assert_eq!(gpu_memory::allocated(), before);
+}
+
+#[test]
+fn bytes_are_shown_in_binary_units() {
+    assert_eq!(format_bytes(0), "0 B");
+    assert_eq!(format_bytes(1023), "1023 B");
+    assert_eq!(format_bytes(1536), "2 KiB");
+    assert_eq!(format_bytes(34 * 1024 * 1024), "34 MiB");
+    assert_eq!(format_bytes(3 << 40), "3072 GiB");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn texture_sizes_add_up_the_mips_layers_and_samples() {
+    let Some((device, _queue)) = common::device() else {
+        return;
+    };
+    let texture = |format, (width, height, layers), mip_level_count, sample_count| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    };
+
+    let rgba = wgpu::TextureFormat::Rgba8UnormSrgb;
+    // 8x8 + 4x4 + 2x2 + 1x1 texels
+    let mips = texture(rgba, (8, 8, 1), 4, 1);
+    assert_eq!(gpu_memory::texture_size(&mips), 85 * 4);
+    let layers = texture(rgba, (4, 4, 6), 1, 1);
+    assert_eq!(gpu_memory::texture_size(&layers), 6 * 16 * 4);
+    let msaa = texture(wgpu::TextureFormat::Rgba16Float, (4, 4, 1), 1, 4);
+    assert_eq!(gpu_memory::texture_size(&msaa), 4 * 16 * 8);
+    let depth = texture(wgpu::TextureFormat::Depth24PlusStencil8, (4, 4, 1), 1, 1);
+    assert_eq!(gpu_memory::texture_size(&depth), 16 * 5);
+}
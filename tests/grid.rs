@@ -0,0 +1,19 @@
+use wgpu_learning::grid::{grid_spacing, MIN_HEIGHT};
+
+#[test]
+fn the_spacing_follows_the_height_in_powers_of_ten() {
+    assert_eq!(grid_spacing(1.0), (1.0, 0.0));
+    let (spacing, blend) = grid_spacing(5.0);
+    assert_eq!(spacing, 1.0);
+    assert!((blend - 5f32.log10()).abs() < 1e-6);
+    assert_eq!(grid_spacing(50.0).0, 10.0);
+    assert_eq!(grid_spacing(0.5).0, 0.1);
+    // Below the ground it looks the same
+    assert_eq!(grid_spacing(-50.0), grid_spacing(50.0));
+}
+
+#[test]
+fn the_spacing_bottoms_out_on_the_ground() {
+    assert_eq!(grid_spacing(0.0), grid_spacing(MIN_HEIGHT));
+    assert!((grid_spacing(0.0).0 - MIN_HEIGHT).abs() < 1e-6);
+}
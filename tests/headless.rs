@@ -0,0 +1,1269 @@
+// Headless rendering blocks on the GPU, which the web doesn't allow
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{cell::Cell, rc::Rc, sync::Arc, time::Duration};
+
+use glam::{Quat, Vec3};
+use wgpu_learning::{
+    bloom::BloomSettings,
+    camera::CameraMode,
+    color::{self, SrgbEncoding},
+    compute::{Emitter, ParticleBlend},
+    debug_view::DebugView,
+    device_config::DeviceConfig,
+    error::AppError,
+    fxaa::Antialiasing,
+    gpu_memory,
+    input_map::GamepadAxis,
+    instance::Instance,
+    key_bindings::Action,
+    light::{PointLight, MAX_UNIFORM_LIGHTS},
+    outline::OutlineSettings,
+    render_graph::{self, Frame, FrameResources, RenderNode, ResourceDesc},
+    sampler::TextureFiltering,
+    split_screen::SplitMode,
+    tonemap::{Operator, Tonemap},
+    State, SurfaceErrorAction,
+};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::ElementState,
+};
+
+fn to_srgb_u8(c: f64) -> u8 {
+    (color::linear_to_srgb(c) * 255.0).round() as u8
+}
+
+#[test]
+fn clear_color_shows_up_in_headless_output() {
+    let (width, height) = (64, 48);
+    let mut state = match pollster::block_on(State::new_headless(width, height, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            // CI runners without a GPU (or a software adapter) can't run this
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+
+    let clear_color = wgpu::Color {
+        r: 0.8,
+        g: 0.1,
+        b: 0.4,
+        a: 1.0,
+    };
+    state.set_clear_color(clear_color);
+    // Without the skybox the background is the clear color
+    state.set_skybox(None);
+    state.update(Duration::ZERO);
+
+    // The clear color is part of the HDR scene, so it gets tonemapped too.
+    // Without an operator it shows up unchanged.
+    for tonemap in [
+        Tonemap {
+            operator: Operator::None,
+            exposure: 0.0,
+        },
+        Tonemap::default(),
+        Tonemap {
+            operator: Operator::Reinhard,
+            exposure: 1.0,
+        },
+    ] {
+        state.set_tonemap(tonemap);
+        let pixels = state
+            .render_to_vec()
+            .expect("failed to read back the frame");
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+
+        // The scene is in the middle of the frame, the corners only show the
+        // background. The target is sRGB, so the linear color got encoded.
+        let mapped = tonemap.apply(Vec3::new(
+            clear_color.r as f32,
+            clear_color.g as f32,
+            clear_color.b as f32,
+        ));
+        let expected = [
+            to_srgb_u8(mapped.x.into()),
+            to_srgb_u8(mapped.y.into()),
+            to_srgb_u8(mapped.z.into()),
+            255,
+        ];
+        let top_left = &pixels[..4];
+        for (actual, expected) in top_left.iter().zip(expected) {
+            assert!(
+                actual.abs_diff(expected) <= 2,
+                "{tonemap:?}: expected {expected:?}, got {top_left:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn surface_errors_are_recovered_from_when_possible() {
+    let mut state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+
+    assert_eq!(
+        state.handle_surface_error(wgpu::SurfaceError::Outdated),
+        SurfaceErrorAction::Reconfigured
+    );
+    assert_eq!(
+        state.handle_surface_error(wgpu::SurfaceError::Lost),
+        SurfaceErrorAction::Reconfigured
+    );
+    assert_eq!(
+        state.handle_surface_error(wgpu::SurfaceError::Timeout),
+        SurfaceErrorAction::SkipFrame
+    );
+    assert_eq!(
+        state.handle_surface_error(wgpu::SurfaceError::OutOfMemory),
+        SurfaceErrorAction::Exit
+    );
+    // Still renders after reconfiguring
+    assert!(state.render_to_vec().is_ok());
+}
+
+#[test]
+fn reloading_the_shaders_keeps_rendering() {
+    let mut state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+
+    state.reload_pipelines();
+    state.update(Duration::ZERO);
+    assert!(state.render_to_vec().is_ok());
+}
+
+#[test]
+fn the_gpu_info_and_memory_are_known() {
+    let state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+
+    let info = state.gpu_info();
+    assert!(info.describe().starts_with(&info.info.name));
+    assert!(info.limits.max_texture_dimension_2d >= 2048);
+    // At least the depth buffer and the HDR targets
+    assert!(gpu_memory::allocated() >= 3 * 16 * 16 * 4);
+}
+
+#[test]
+fn particles_can_be_resized_between_frames() {
+    let mut state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    if state.particle_count().is_none() {
+        eprintln!("Skipping particle test: compute shaders aren't supported");
+        return;
+    }
+
+    state.update(Duration::from_millis(16));
+    // Not a multiple of the workgroup size
+    state.set_particle_count(65);
+    assert_eq!(state.particle_count(), Some(65));
+    state.update(Duration::from_millis(16));
+    assert!(state.render_to_vec().is_ok());
+}
+
+#[test]
+fn particles_expire_and_never_overflow_the_buffer() {
+    let mut state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let Some(particles) = state.particles_mut() else {
+        eprintln!("Skipping particle test: compute shaders aren't supported");
+        return;
+    };
+    let mut emitter = Emitter::new(Vec3::new(0.0, 5.0, 0.0));
+    // 5 particles a frame, each living for 3 frames
+    emitter.rate = 80.0;
+    emitter.lifetime = 0.15;
+    *particles.emitters_mut() = vec![emitter];
+    let frame = Duration::from_micros(62_500);
+
+    for expected in [5, 10, 15, 15, 15] {
+        state.update(frame);
+        assert_eq!(state.alive_particle_count(), Some(expected));
+    }
+    assert!(state.render_to_vec().is_ok());
+
+    // Sorted back to front with alpha blending
+    state
+        .particles_mut()
+        .unwrap()
+        .set_blend(ParticleBlend::Alpha);
+    state.update(frame);
+    assert_eq!(state.alive_particle_count(), Some(15));
+    assert!(state.render_to_vec().is_ok());
+
+    // Particles that don't fit are dropped
+    state.set_particle_count(12);
+    for expected in [5, 10, 12] {
+        state.update(frame);
+        assert_eq!(state.alive_particle_count(), Some(expected));
+    }
+}
+
+#[test]
+fn vignette_darkens_the_corners_only() {
+    let (width, height) = (64, 48);
+    let mut state = match pollster::block_on(State::new_headless(width, height, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    state.set_clear_color(wgpu::Color::WHITE);
+    state.set_skybox(None);
+    // Nothing in front of the background
+    state.set_instances(&[]);
+    state.update(Duration::ZERO);
+
+    let pixel = |pixels: &[u8], x: u32, y: u32| {
+        let i = ((y * width + x) * 4) as usize;
+        pixels[i]
+    };
+    assert_eq!(state.post_effect(), "None");
+    let plain = state.render_to_vec().unwrap();
+    assert_eq!(state.next_post_effect(), "Vignette");
+    let vignette = state.render_to_vec().unwrap();
+
+    assert!(pixel(&vignette, 0, 0) < pixel(&plain, 0, 0));
+    let center = (width / 2, height / 2);
+    assert_eq!(
+        pixel(&vignette, center.0, center.1),
+        pixel(&plain, center.0, center.1)
+    );
+
+    // Back to the unchanged frame after the last effect
+    assert_eq!(state.next_post_effect(), "None");
+}
+
+#[test]
+fn movement_lasts_while_the_stick_is_pushed() {
+    let mut state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let start = state.camera().eye;
+
+    state
+        .input_map_mut()
+        .set_gamepad_axis(GamepadAxis::LeftStickY, 1.0);
+    state.update(Duration::from_millis(100));
+    let moved = state.camera().eye;
+    assert_ne!(moved, start);
+
+    state
+        .input_map_mut()
+        .set_gamepad_axis(GamepadAxis::LeftStickY, 0.0);
+    state.update(Duration::from_millis(100));
+    assert_eq!(state.camera().eye, moved);
+
+    // Nothing happens when other actions are released
+    assert!(!state.handle_action(Action::ClearColorPreset(0), ElementState::Released));
+    // No window to make fullscreen
+    assert!(!state.handle_action(Action::ToggleFullscreen, ElementState::Pressed));
+}
+
+#[test]
+fn the_exit_hook_runs_once() {
+    let mut state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let runs = Rc::new(Cell::new(0));
+    let hook_runs = runs.clone();
+    state.set_on_exit(move |state| {
+        // Can still render while shutting down
+        assert!(state.render_to_vec().is_ok());
+        hook_runs.set(hook_runs.get() + 1);
+    });
+
+    assert!(!state.is_exiting());
+    assert!(state.shut_down());
+    assert!(state.is_exiting());
+    assert!(!state.shut_down());
+    assert_eq!(runs.get(), 1);
+    // Nothing was being read back, so there's nothing to wait for
+    assert!(state.poll_shut_down());
+}
+
+#[test]
+fn wireframes_only_turn_on_where_supported() {
+    let mut state = match pollster::block_on(State::new_headless(64, 48, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    state.update(Duration::ZERO);
+    let filled = state.render_to_vec().expect("failed to render filled");
+
+    let supported = state.supports_wireframe();
+    assert_eq!(state.set_wireframe(true), supported);
+    assert_eq!(state.wireframe(), supported);
+    let wireframe = state
+        .render_to_vec()
+        .expect("failed to render the wireframe");
+    if supported {
+        // The background shows through between the lines
+        assert_ne!(filled, wireframe);
+    } else {
+        assert_eq!(filled, wireframe);
+    }
+
+    assert!(!state.set_wireframe(false));
+}
+
+#[test]
+fn non_srgb_targets_look_the_same() {
+    let render = |encoding| {
+        let mut state = pollster::block_on(State::new_headless_with_encoding(64, 48, 1, encoding))?;
+        assert_eq!(state.srgb_encoding(), encoding);
+        state.set_clear_color(color::PRESETS[4]);
+        state.update(Duration::ZERO);
+        Ok::<_, AppError>(state.render_to_vec().unwrap())
+    };
+    let srgb = match render(SrgbEncoding::Format) {
+        Ok(pixels) => pixels,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+
+    // The hardware and the shaders may round the encoded colors a bit
+    // differently
+    for encoding in [SrgbEncoding::View, SrgbEncoding::Shader] {
+        let pixels = match render(encoding) {
+            Ok(pixels) => pixels,
+            // GL adapters can't do sRGB views
+            Err(err @ AppError::SurfaceConfig { .. }) => {
+                eprintln!("Skipping {encoding:?}: {err}");
+                continue;
+            }
+            Err(err) => panic!("{err}"),
+        };
+        let worst = srgb
+            .iter()
+            .zip(&pixels)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap();
+        assert!(worst <= 2, "{encoding:?} differs by up to {worst}");
+    }
+}
+
+#[test]
+fn transparent_states_clear_to_alpha_0() {
+    let mut state = match pollster::block_on(State::new_headless(32, 32, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    assert!(!state.is_transparent());
+
+    state.set_transparent(true);
+    assert!(state.is_transparent());
+    state.update(Duration::ZERO);
+    let pixels = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    // Even with the skybox there, the corners only show the clear color
+    assert_eq!(&pixels[..4], [0, 0, 0, 0]);
+
+    state.set_transparent(false);
+    let pixels = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_eq!(pixels[3], 255);
+}
+
+#[test]
+fn headless_states_ignore_suspend_and_resume() {
+    let mut state = match pollster::block_on(State::new_headless(16, 16, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    // There's no surface to drop or recreate, rendering keeps working
+    state.suspend();
+    assert!(!state.is_suspended());
+    state.resume().unwrap();
+    assert!(!state.is_suspended());
+    assert!(state.render().is_ok());
+    assert_eq!(state.render_to_vec().unwrap().len(), 16 * 16 * 4);
+}
+
+#[test]
+fn clicks_pick_the_instance_under_the_cursor() {
+    let (width, height) = (64, 48);
+    let mut state = match pollster::block_on(State::new_headless(width, height, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let pick = |state: &mut State, x: f64, y: f64| {
+        state.pick(PhysicalPosition::new(x, y));
+        state
+            .render_to_vec()
+            .expect("failed to read back the frame");
+        state.update(Duration::ZERO);
+        state.picked()
+    };
+
+    // The camera looks at the middle of the grid
+    let picked = pick(&mut state, 32.0, 24.0).expect("nothing under the cursor");
+    assert!((picked.instance as usize) < state.instances().len());
+    assert_eq!(picked.mesh, 0);
+    assert_eq!(pick(&mut state, 0.0, 0.0), None);
+
+    // A click from before the resize doesn't pick what's there now
+    assert!(pick(&mut state, 32.0, 24.0).is_some());
+    state.pick(PhysicalPosition::new(32.0, 24.0));
+    state.resize(PhysicalSize::new(width * 2, height * 2));
+    state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    state.update(Duration::ZERO);
+    assert_eq!(state.picked(), None);
+    assert!(pick(&mut state, 64.0, 48.0).is_some());
+}
+
+#[test]
+fn selected_instances_get_an_outline() {
+    let mut state = match pollster::block_on(State::new_headless(64, 48, 4)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let base = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+
+    // Clicking selects what's under the cursor
+    state.pick(PhysicalPosition::new(32.0, 24.0));
+    state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    state.update(Duration::ZERO);
+    let picked = state.picked().expect("nothing under the cursor");
+    assert_eq!(state.selection(), Some(picked.instance));
+
+    state.set_outline_settings(OutlineSettings {
+        color: [0.0, 1.0, 0.0, 1.0],
+        thickness: 0.2,
+    });
+    let outlined = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert!(outlined
+        .chunks(4)
+        .zip(base.chunks(4))
+        .any(|(pixel, before)| pixel != before && pixel[1] > pixel[0] && pixel[1] > pixel[2]));
+
+    // Without thickness the stencil mark hides all of it
+    state.set_outline_settings(OutlineSettings {
+        thickness: 0.0,
+        ..state.outline_settings()
+    });
+    assert_eq!(state.render_to_vec().unwrap(), base);
+
+    state.set_outline_settings(OutlineSettings::default());
+    assert!(state.handle_action(Action::ToggleSelection, ElementState::Pressed));
+    assert_eq!(state.selection(), None);
+    assert_eq!(state.render_to_vec().unwrap(), base);
+    state.handle_action(Action::ToggleSelection, ElementState::Pressed);
+    assert_eq!(state.selection(), Some(0));
+}
+
+#[test]
+fn texture_filtering_cycles_on_the_first_material() {
+    let mut state = match pollster::block_on(State::new_headless(64, 48, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let base = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    let first_sampler = |state: &State| state.model().unwrap().materials[0].sampler().cloned();
+    assert!(first_sampler(&state).is_none());
+
+    assert!(state.handle_action(Action::CycleTextureFiltering, ElementState::Pressed));
+    assert_eq!(state.texture_filtering(), Some(TextureFiltering::Nearest));
+    let nearest = first_sampler(&state).unwrap();
+    state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+
+    for _ in 0..4 {
+        state.handle_action(Action::CycleTextureFiltering, ElementState::Pressed);
+        state
+            .render_to_vec()
+            .expect("failed to read back the frame");
+    }
+    // Round again, with the sampler from the first time
+    assert_eq!(state.texture_filtering(), Some(TextureFiltering::Nearest));
+    assert!(Arc::ptr_eq(&nearest, &first_sampler(&state).unwrap()));
+    assert!((3..=4).contains(&state.sampler_cache().len()));
+
+    state.set_texture_filtering(None);
+    assert!(first_sampler(&state).is_none());
+    assert_eq!(state.render_to_vec().unwrap(), base);
+}
+
+#[test]
+fn instances_outside_the_view_are_culled_until_frozen() {
+    let mut state = match pollster::block_on(State::new_headless(64, 48, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    // One instance where the camera looks and one behind it
+    let camera = *state.camera();
+    let behind = camera.eye + (camera.eye - camera.target);
+    state.set_instances(&[
+        Instance {
+            position: camera.target,
+            rotation: Quat::IDENTITY,
+        },
+        Instance {
+            position: behind,
+            rotation: Quat::IDENTITY,
+        },
+    ]);
+    let stats = state.cull_stats();
+    assert!(stats.drawn > 0);
+    assert_eq!(stats.drawn, stats.culled);
+    assert!(state.render().is_ok());
+
+    // Frozen, turning the camera away doesn't change what gets drawn
+    state.handle_action(Action::FreezeCulling, ElementState::Pressed);
+    assert!(state.culling_frozen());
+    let side = (camera.target - camera.eye).cross(camera.up);
+    state.camera_mut().target = camera.eye + side;
+    state.update(Duration::ZERO);
+    assert_eq!(state.cull_stats(), stats);
+
+    // Until culling catches up with the camera again
+    state.set_culling_frozen(false);
+    state.update(Duration::ZERO);
+    assert_eq!(state.cull_stats().drawn, 0);
+    assert!(state.render().is_ok());
+}
+
+#[test]
+fn render_bundles_draw_the_same_frames() {
+    let mut state = match pollster::block_on(State::new_headless(64, 48, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let frame = |state: &mut State, bundles: bool| {
+        state.set_render_bundles(bundles);
+        state.update(Duration::ZERO);
+        state
+            .render_to_vec()
+            .expect("failed to read back the frame")
+    };
+    assert!(state.render_bundles());
+    assert_eq!(frame(&mut state, true), frame(&mut state, false));
+
+    // The bundle follows what changes, the instances and the sample count
+    let bundled = frame(&mut state, true);
+    let moved: Vec<Instance> = state
+        .instances()
+        .iter()
+        .map(|instance| Instance {
+            position: instance.position + Vec3::X,
+            ..*instance
+        })
+        .collect();
+    state.set_instances(&moved);
+    let bundled_moved = frame(&mut state, true);
+    assert_ne!(bundled, bundled_moved);
+    assert_eq!(bundled_moved, frame(&mut state, false));
+
+    for sample_count in state.supported_sample_counts() {
+        state.set_sample_count(sample_count);
+        assert_eq!(
+            frame(&mut state, true),
+            frame(&mut state, false),
+            "{sample_count}x MSAA"
+        );
+    }
+    state.handle_action(Action::ToggleRenderBundles, ElementState::Pressed);
+    assert!(state.render_bundles());
+}
+
+#[test]
+fn shadows_only_darken_the_scene() {
+    let (width, height) = (64, 48);
+    let mut state = match pollster::block_on(State::new_headless(width, height, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    assert!(state.shadows());
+    state.update(Duration::ZERO);
+    let shadowed = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+
+    state.handle_action(Action::ToggleShadows, ElementState::Pressed);
+    assert!(!state.shadows());
+    let lit = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+
+    assert_ne!(shadowed, lit);
+    // Allow for rounding
+    assert!(shadowed
+        .iter()
+        .zip(&lit)
+        .all(|(&s, &l)| s <= l.saturating_add(1)));
+
+    assert_eq!(state.set_shadow_map_size(512), 512);
+    assert_eq!(state.shadow_map_size(), 512);
+}
+
+#[test]
+fn downlevel_limits_fall_back_to_uniform_arrays() {
+    let webgl2 = DeviceConfig {
+        limits: wgpu::Limits::downlevel_webgl2_defaults(),
+        ..DeviceConfig::default()
+    };
+    let mut frames = Vec::new();
+    for config in [DeviceConfig::default(), webgl2] {
+        let mut state =
+            match pollster::block_on(State::new_headless_with_device(32, 24, 1, &config)) {
+                Ok(state) => state,
+                Err(err) => {
+                    eprintln!("Skipping headless test: {err}");
+                    return;
+                }
+            };
+        state.update(Duration::ZERO);
+        frames.push((state.storage_support(), state.render_to_vec().unwrap()));
+    }
+
+    let [(_, storage_frame), (fallback, fallback_frame)] = &frames[..] else {
+        unreachable!();
+    };
+    assert!(!fallback.read_only(wgpu::ShaderStages::FRAGMENT));
+    // The same scene, only the lights get there differently
+    assert_eq!(fallback_frame, storage_frame);
+}
+
+#[test]
+fn every_light_adds_to_the_scene() {
+    let (width, height) = (64, 48);
+    // WebGL2 limits, so the lights fall back to a uniform array
+    let config = DeviceConfig {
+        limits: wgpu::Limits::downlevel_webgl2_defaults(),
+        ..DeviceConfig::default()
+    };
+    let mut state =
+        match pollster::block_on(State::new_headless_with_device(width, height, 1, &config)) {
+            Ok(state) => state,
+            Err(err) => {
+                eprintln!("Skipping headless test: {err}");
+                return;
+            }
+        };
+    assert_eq!(state.lights().max_lights(), Some(MAX_UNIFORM_LIGHTS));
+    state.set_shadows(false);
+    state.update(Duration::ZERO);
+    let lit = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+
+    let id = state
+        .lights_mut()
+        .add_light(PointLight::new(Vec3::new(0.0, 3.0, 3.0), Vec3::ONE))
+        .unwrap();
+    state.update(Duration::ZERO);
+    let brighter = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_ne!(lit, brighter);
+    assert!(brighter
+        .iter()
+        .zip(&lit)
+        .all(|(&b, &l)| b.saturating_add(1) >= l));
+
+    state.lights_mut().remove_light(id);
+    state.update(Duration::ZERO);
+    let again = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_eq!(lit, again);
+}
+
+#[test]
+fn bloom_only_brightens_and_follows_the_frame_size() {
+    let (width, height) = (64, 48);
+    let mut state = match pollster::block_on(State::new_headless(width, height, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    assert!(state.bloom());
+    state.handle_action(Action::ToggleBloom, ElementState::Pressed);
+    assert!(!state.bloom());
+    state.update(Duration::ZERO);
+    let without = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+
+    // Everything blooms without a threshold
+    state.set_bloom(true);
+    state.set_bloom_settings(BloomSettings {
+        threshold: 0.0,
+        intensity: 1.0,
+    });
+    let with = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_ne!(without, with);
+    // Allow for rounding
+    assert!(with
+        .iter()
+        .zip(&without)
+        .all(|(&w, &o)| w.saturating_add(1) >= o));
+
+    assert_eq!(state.bloom_mip_sizes()[0], (width / 2, height / 2));
+    state.resize(PhysicalSize::new(32, 32));
+    assert_eq!(state.bloom_mip_sizes()[0], (16, 16));
+    assert!(state.render_to_vec().is_ok());
+}
+
+#[test]
+fn scaled_frames_are_upscaled_to_the_window_size() {
+    let (width, height) = (64, 48);
+    let mut state = match pollster::block_on(State::new_headless(width, height, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let base = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert!(!state.frame_resources().contains(render_graph::SCALED));
+
+    for (scale, size) in [(0.5, (32, 24)), (2.0, (128, 96))] {
+        assert_eq!(state.set_render_scale(scale), scale);
+        assert_eq!(state.render_size(), PhysicalSize::new(size.0, size.1));
+        assert!(state.frame_resources().contains(render_graph::SCALED));
+        let frame = state
+            .render_to_vec()
+            .expect("failed to read back the frame");
+        assert_eq!(frame.len(), base.len());
+    }
+
+    assert_eq!(state.set_render_scale(10.0), 2.0);
+    assert!(state.handle_action(Action::DecreaseRenderScale, ElementState::Pressed));
+    assert_eq!(state.render_scale(), 1.75);
+    assert_eq!(state.set_render_scale(0.0), 0.25);
+    // A tiny window still gets a frame to draw into
+    state.resize(PhysicalSize::new(2, 2));
+    assert_eq!(state.render_size(), PhysicalSize::new(1, 1));
+    assert!(state.render_to_vec().is_ok());
+
+    state.resize(PhysicalSize::new(width, height));
+    state.handle_action(Action::IncreaseRenderScale, ElementState::Pressed);
+    state.set_render_scale(1.0);
+    assert!(!state.frame_resources().contains(render_graph::SCALED));
+    assert_eq!(state.render_to_vec().unwrap(), base);
+}
+
+#[test]
+fn fxaa_and_msaa_never_run_together() {
+    let mut state = match pollster::block_on(State::new_headless(64, 48, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    state.update(Duration::ZERO);
+    let base = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_eq!(state.antialiasing(), Antialiasing::None);
+
+    assert!(state.handle_action(Action::CycleAntialiasing, ElementState::Pressed));
+    assert_eq!(state.antialiasing(), Antialiasing::Fxaa);
+    assert!(state.frame_resources().contains(render_graph::LDR));
+    assert_eq!(
+        state.render_graph().names().last(),
+        Some(render_graph::FXAA_NODE)
+    );
+    let smoothed = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_eq!(smoothed.len(), base.len());
+    assert_ne!(smoothed, base);
+    // Only the edges change
+    let unchanged = smoothed.iter().zip(&base).filter(|(s, b)| s == b).count();
+    assert!(unchanged > base.len() / 2);
+    // And it keeps up with the render scale
+    state.set_render_scale(0.5);
+    assert!(state.render_to_vec().is_ok());
+    state.set_render_scale(1.0);
+
+    if state.supported_sample_counts().contains(&4) {
+        state.set_sample_count(4);
+        assert!(!state.fxaa());
+        state.set_fxaa(true);
+        assert_eq!(state.sample_count(), 1);
+        assert_eq!(
+            state.set_antialiasing(Antialiasing::Msaa),
+            Antialiasing::Msaa
+        );
+        assert!(!state.frame_resources().contains(render_graph::LDR));
+    }
+    state.set_antialiasing(Antialiasing::None);
+    assert!(!state.render_graph().contains(render_graph::FXAA_NODE));
+    assert_eq!(state.render_to_vec().unwrap(), base);
+}
+
+#[test]
+fn debug_views_cycle_back_to_the_scene() {
+    let mut state = match pollster::block_on(State::new_headless(64, 48, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    state.update(Duration::ZERO);
+    let base = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+
+    assert_eq!(state.set_debug_view(DebugView::Normals), DebugView::Normals);
+    assert_eq!(
+        state.render_graph().names().last(),
+        Some(render_graph::DEBUG_VIEW_NODE)
+    );
+    let normals = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_ne!(normals, base);
+
+    // Every supported view renders, and the last one goes back to the scene
+    state.set_debug_view(DebugView::None);
+    for _ in DebugView::ALL {
+        assert!(state.handle_action(Action::CycleDebugView, ElementState::Pressed));
+        let view = state.debug_view();
+        assert!(state.supports_debug_view(view));
+        assert!(state.render_to_vec().is_ok(), "{view:?} failed to render");
+        if view == DebugView::None {
+            break;
+        }
+    }
+    assert_eq!(state.debug_view(), DebugView::None);
+    assert!(!state.render_graph().contains(render_graph::DEBUG_VIEW_NODE));
+    assert_eq!(state.render_to_vec().unwrap(), base);
+}
+
+#[test]
+fn debug_lines_show_until_the_next_update() {
+    let mut state = match pollster::block_on(State::new_headless(64, 48, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    state.update(Duration::ZERO);
+    let base = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+
+    // Right through the middle of the view, in front of everything
+    let target = state.camera().target;
+    let eye = state.camera().eye;
+    let near = eye + (target - eye).normalize() * 0.5;
+    let debug_draw = state.debug_draw_mut();
+    debug_draw.set_depth_test(false);
+    debug_draw.line(near - Vec3::Y, near + Vec3::Y, [1.0, 0.0, 1.0, 1.0]);
+    debug_draw.line(near - Vec3::X, near + Vec3::X, [1.0, 0.0, 1.0, 1.0]);
+    let with_lines = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_ne!(with_lines, base);
+    // Rendering again keeps them
+    assert_eq!(state.render_to_vec().unwrap(), with_lines);
+
+    state.update(Duration::ZERO);
+    assert!(state.debug_draw().vertices().is_empty());
+    assert_eq!(state.render_to_vec().unwrap(), base);
+}
+
+#[test]
+fn the_grid_is_drawn_under_the_scene_until_toggled_off() {
+    let mut state = match pollster::block_on(State::new_headless(64, 48, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    state.update(Duration::ZERO);
+    let base = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+
+    assert!(state.handle_action(Action::ToggleGrid, ElementState::Pressed));
+    assert!(state.grid());
+    state.update(Duration::ZERO);
+    // With the origin's axes
+    assert_eq!(state.debug_draw().vertices().len(), 6);
+    let grid = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_ne!(grid, base);
+    // The model covers most of it, so most pixels stay the same
+    let unchanged = grid
+        .chunks(4)
+        .zip(base.chunks(4))
+        .filter(|(g, b)| g == b)
+        .count();
+    assert!(unchanged > 0);
+
+    assert!(state.handle_action(Action::ToggleGrid, ElementState::Pressed));
+    state.update(Duration::ZERO);
+    assert!(state.debug_draw().vertices().is_empty());
+    assert_eq!(state.render_to_vec().unwrap(), base);
+}
+
+#[test]
+fn orbiting_keeps_the_view_and_refocuses_on_the_scene() {
+    let mut state = match pollster::block_on(State::new_headless(64, 48, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    state.update(Duration::ZERO);
+    let fly = *state.camera();
+
+    assert!(state.handle_action(Action::ToggleCameraMode, ElementState::Pressed));
+    assert_eq!(state.camera_mode(), CameraMode::Orbit);
+    state.update(Duration::from_millis(16));
+    assert!(state.camera().eye.abs_diff_eq(fly.eye, 1e-4));
+    assert!(state.camera().target.abs_diff_eq(fly.target, 1e-4));
+
+    // Moving doesn't move the orbiting camera
+    state
+        .input_map_mut()
+        .set_gamepad_axis(GamepadAxis::LeftStickY, 1.0);
+    state.orbit_camera_mut().drag_orbit(40.0, 0.0);
+    state.update(Duration::from_millis(100));
+    assert!((state.camera().eye.distance(fly.target) - fly.eye.distance(fly.target)).abs() < 1e-3);
+    assert!(state.camera().eye.x < 0.0);
+    state
+        .input_map_mut()
+        .set_gamepad_axis(GamepadAxis::LeftStickY, 0.0);
+
+    assert!(state.handle_action(Action::FocusScene, ElementState::Pressed));
+    let bounds = state.scene_bounds().expect("the scene has a model");
+    let focus = state.orbit_camera().goal().focus;
+    assert!(focus.abs_diff_eq(bounds.center(), 1e-4));
+    for _ in 0..10 {
+        state.update(Duration::from_millis(100));
+    }
+    assert!(state.camera().target.abs_diff_eq(focus, 1e-3));
+    assert!(!state.orbit_camera().is_moving());
+
+    // Flying goes on from where the orbit left it
+    let orbited = *state.camera();
+    assert!(state.handle_action(Action::ToggleCameraMode, ElementState::Pressed));
+    state.update(Duration::ZERO);
+    assert_eq!(state.camera().eye, orbited.eye);
+}
+
+#[test]
+fn split_screens_follow_the_frame_and_move_the_focused_camera() {
+    let mut state = match pollster::block_on(State::new_headless(65, 48, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let single = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    // Nothing to focus without a split
+    assert!(!state.handle_action(Action::ToggleViewFocus, ElementState::Pressed));
+    assert_eq!(state.focused_view(), 0);
+
+    state.handle_action(Action::CycleSplitScreen, ElementState::Pressed);
+    state.handle_action(Action::CycleSplitScreen, ElementState::Pressed);
+    assert_eq!(state.split_mode(), SplitMode::Vertical);
+    assert_eq!(state.view_camera(0).aspect, 32.0 / 48.0);
+    assert_eq!(state.view_camera(1).aspect, 33.0 / 48.0);
+    state.update(Duration::ZERO);
+    let split = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_ne!(single, split);
+
+    assert!(state.handle_action(Action::ToggleViewFocus, ElementState::Pressed));
+    assert_eq!(state.focused_view(), 1);
+    let (first, second) = (state.view_camera(0).eye, state.view_camera(1).eye);
+    state
+        .input_map_mut()
+        .set_gamepad_axis(GamepadAxis::LeftStickY, 1.0);
+    state.update(Duration::from_millis(100));
+    state
+        .input_map_mut()
+        .set_gamepad_axis(GamepadAxis::LeftStickY, 0.0);
+    assert_eq!(state.view_camera(0).eye, first);
+    assert_ne!(state.view_camera(1).eye, second);
+
+    state.resize(PhysicalSize::new(41, 30));
+    assert_eq!(state.view_camera(0).aspect, 20.0 / 30.0);
+    assert_eq!(state.view_camera(1).aspect, 21.0 / 30.0);
+    assert!(state.render_to_vec().is_ok());
+
+    state.handle_action(Action::CycleSplitScreen, ElementState::Pressed);
+    assert_eq!(state.split_mode(), SplitMode::Single);
+    assert_eq!(state.focused_view(), 0);
+    assert_eq!(state.camera().aspect, 41.0 / 30.0);
+}
+
+/// Clears the frame to red, over whatever was drawn.
+struct ClearToRed;
+
+impl RenderNode for ClearToRed {
+    fn name(&self) -> &str {
+        "clear_to_red"
+    }
+
+    fn run(&mut self, frame: &mut Frame, _resources: &FrameResources) {
+        frame
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Clear To Red"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+    }
+}
+
+#[test]
+fn render_nodes_can_be_added_and_removed_while_running() {
+    let mut state = match pollster::block_on(State::new_headless(32, 32, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let names = |state: &State| {
+        state
+            .render_graph()
+            .names()
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&state), ["scene", "bloom", "post_process"]);
+    state.set_bloom(false);
+    assert_eq!(names(&state), ["scene", "post_process"]);
+    state.set_bloom(true);
+    assert_eq!(names(&state), ["scene", "bloom", "post_process"]);
+
+    let before = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    state.render_graph_mut().push(Box::new(ClearToRed));
+    let red = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert!(red.chunks(4).all(|pixel| pixel[..3] == [255, 0, 0]));
+
+    assert!(state.render_graph_mut().remove("clear_to_red").is_some());
+    let after = state
+        .render_to_vec()
+        .expect("failed to read back the frame");
+    assert_eq!(before, after);
+
+    // The state's own resources stay its own
+    let desc = ResourceDesc::frame(
+        wgpu::TextureFormat::Rgba8Unorm,
+        wgpu::TextureUsages::RENDER_ATTACHMENT,
+    );
+    assert!(!state.insert_frame_resource(render_graph::HDR, desc));
+    assert!(state.insert_frame_resource("extra", desc));
+    state.resize(PhysicalSize::new(16, 8));
+    let extra = state.frame_resources().texture("extra").unwrap();
+    assert_eq!((extra.width(), extra.height()), (16, 8));
+}
+
+/// Writes a glTF file with a big triangle skinned to one joint, and an
+/// animation moving the joint out past the far plane in 50 ms.
+fn write_skinned_triangle(dir: &std::path::Path) -> std::path::PathBuf {
+    let floats = |values: &[f32]| -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    };
+    let mut binary = floats(&[-3.0, -3.0, 0.0, 3.0, -3.0, 0.0, 0.0, 3.0, 0.0]);
+    binary.extend([0u8; 12]);
+    binary.extend(floats(&[1.0, 0.0, 0.0, 0.0].repeat(3)));
+    binary.extend(floats(&[0.0, 0.05]));
+    binary.extend(floats(&[0.0, 0.0, 0.0, 0.0, 0.0, -500.0]));
+    std::fs::write(dir.join("triangle.bin"), &binary).unwrap();
+
+    let json = r#"{
+        "asset": { "version": "2.0" },
+        "nodes": [{ "name": "joint" }, { "mesh": 0, "skin": 0 }],
+        "skins": [{ "joints": [0] }],
+        "meshes": [{ "primitives": [{
+            "attributes": { "POSITION": 0, "JOINTS_0": 1, "WEIGHTS_0": 2 }
+        }] }],
+        "animations": [{
+            "name": "Away",
+            "samplers": [{ "input": 3, "output": 4 }],
+            "channels": [{ "sampler": 0, "target": { "node": 0, "path": "translation" } }]
+        }],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+            { "bufferView": 1, "componentType": 5121, "count": 3, "type": "VEC4" },
+            { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC4" },
+            { "bufferView": 3, "componentType": 5126, "count": 2, "type": "SCALAR" },
+            { "bufferView": 4, "componentType": 5126, "count": 2, "type": "VEC3" }
+        ],
+        "bufferViews": [
+            { "buffer": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 12 },
+            { "buffer": 0, "byteOffset": 48, "byteLength": 48 },
+            { "buffer": 0, "byteOffset": 96, "byteLength": 8 },
+            { "buffer": 0, "byteOffset": 104, "byteLength": 24 }
+        ],
+        "buffers": [{ "byteLength": 128, "uri": "triangle.bin" }]
+    }"#;
+    let path = dir.join("triangle.gltf");
+    std::fs::write(&path, json).unwrap();
+    path
+}
+
+#[test]
+fn skinned_models_follow_their_animation() {
+    let dir = std::env::temp_dir().join(format!("wgpu_learning_skinned_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = write_skinned_triangle(&dir);
+
+    // With WebGL2 limits the joints fall back to a uniform array
+    let webgl2 = DeviceConfig {
+        limits: wgpu::Limits::downlevel_webgl2_defaults(),
+        ..DeviceConfig::default()
+    };
+    for config in [DeviceConfig::default(), webgl2] {
+        let mut state =
+            match pollster::block_on(State::new_headless_with_device(64, 48, 1, &config)) {
+                Ok(state) => state,
+                Err(err) => {
+                    eprintln!("Skipping headless test: {err}");
+                    break;
+                }
+            };
+
+        // The cube isn't skinned
+        assert!(!state.model().unwrap().is_skinned());
+        assert!(state.play_animation("Away", false).is_err());
+        state
+            .load_gltf(&path)
+            .expect("failed to load the skinned triangle");
+        assert!(state.model().unwrap().is_skinned());
+
+        // Without lights moving between frames only the animation changes
+        // them
+        let ids: Vec<_> = state.lights().iter().map(|(id, _)| id).collect();
+        for id in ids {
+            state.lights_mut().remove_light(id);
+        }
+        state.set_skybox(None);
+        state.set_instances(&[Instance {
+            position: Vec3::ZERO,
+            rotation: glam::Quat::IDENTITY,
+        }]);
+        state.update(Duration::ZERO);
+        let rest = state.render_to_vec().unwrap();
+
+        state.play_animation("Away", false).unwrap();
+        assert_eq!(state.render_to_vec().unwrap(), rest);
+        state.update(Duration::from_millis(100));
+        let away = state.render_to_vec().unwrap();
+        assert_ne!(away, rest);
+
+        state.set_instances(&[]);
+        assert_eq!(state.render_to_vec().unwrap(), away);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,62 @@
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceId, ElementState, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent},
+};
+
+use wgpu_learning::input::{InputState, PIXELS_PER_LINE};
+
+fn device_id() -> DeviceId {
+    // SAFETY: only compared with other ids, never passed to the platform
+    unsafe { DeviceId::dummy() }
+}
+
+fn wheel(delta: MouseScrollDelta) -> WindowEvent {
+    WindowEvent::MouseWheel {
+        device_id: device_id(),
+        delta,
+        phase: TouchPhase::Moved,
+    }
+}
+
+#[test]
+fn mouse_events_are_tracked() {
+    let mut input = InputState::default();
+    assert_eq!(input.cursor_position(), None);
+
+    assert!(input.process_event(&WindowEvent::CursorMoved {
+        device_id: device_id(),
+        position: PhysicalPosition::new(12.0, 34.0),
+    }));
+    assert_eq!(
+        input.cursor_position(),
+        Some(PhysicalPosition::new(12.0, 34.0))
+    );
+
+    assert!(input.process_event(&WindowEvent::MouseInput {
+        device_id: device_id(),
+        state: ElementState::Pressed,
+        button: MouseButton::Left,
+    }));
+    assert!(input.is_button_pressed(MouseButton::Left));
+    assert!(!input.is_button_pressed(MouseButton::Right));
+
+    // Losing focus releases the buttons
+    assert!(!input.process_event(&WindowEvent::Focused(false)));
+    assert!(!input.is_button_pressed(MouseButton::Left));
+
+    assert!(!input.process_event(&WindowEvent::Resized(PhysicalSize::new(1, 1))));
+}
+
+#[test]
+fn line_and_pixel_scrolling_add_up_until_the_end_of_the_frame() {
+    let mut input = InputState::default();
+    input.process_event(&wheel(MouseScrollDelta::LineDelta(0.0, 1.0)));
+    input.process_event(&wheel(MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+        0.0,
+        f64::from(PIXELS_PER_LINE) * 2.0,
+    ))));
+    assert_eq!(input.scroll_delta().y, 3.0);
+
+    input.end_frame();
+    assert_eq!(input.scroll_delta().y, 0.0);
+}
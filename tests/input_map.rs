@@ -0,0 +1,137 @@
+use wgpu_learning::input_map::{
+    names, AxisInput, GamepadAxis, GamepadButton, Input, InputBindings, InputMap,
+};
+use winit::{
+    event::{DeviceId, ElementState, MouseButton, WindowEvent},
+    keyboard::{Key, KeyCode, NamedKey, NativeKey, NativeKeyCode, PhysicalKey},
+};
+
+fn click(map: &mut InputMap, state: ElementState) -> bool {
+    map.process_event(&WindowEvent::MouseInput {
+        device_id: unsafe { DeviceId::dummy() },
+        state,
+        button: MouseButton::Middle,
+    })
+}
+
+#[test]
+fn the_strongest_input_moves_an_axis() {
+    let mut map = InputMap::default();
+    assert_eq!(map.axis(names::MOVE_FORWARD), 0.0);
+
+    map.set_gamepad_axis(GamepadAxis::LeftStickY, 0.4);
+    assert_eq!(map.axis(names::MOVE_FORWARD), 0.4);
+    map.set_gamepad_axis(GamepadAxis::LeftStickY, -3.0);
+    assert_eq!(map.axis(names::MOVE_FORWARD), -1.0);
+
+    // A key pushes it to the end, the other way wins by being further out
+    map.set_gamepad_axis(GamepadAxis::LeftStickY, -0.5);
+    let bindings = map.bindings().clone().bind_axis(
+        "zoom",
+        AxisInput::Gamepad {
+            axis: GamepadAxis::LeftStickY,
+            inverted: true,
+        },
+    );
+    map.set_bindings(bindings);
+    assert_eq!(map.axis("zoom"), 0.5);
+    assert_eq!(map.axis("nothing bound"), 0.0);
+}
+
+#[test]
+fn actions_are_pressed_until_released_and_just_pressed_for_a_frame() {
+    let bindings = InputBindings::empty()
+        .bind("grab", Input::Mouse(MouseButton::Middle))
+        .bind("grab", Input::Gamepad(GamepadButton::South));
+    let mut map = InputMap::new(bindings);
+    assert!(!map.is_pressed("grab"));
+
+    assert!(click(&mut map, ElementState::Pressed));
+    assert!(map.is_pressed("grab"));
+    assert!(map.just_pressed("grab"));
+    map.end_frame();
+    assert!(map.is_pressed("grab"));
+    assert!(!map.just_pressed("grab"));
+
+    // Pressed and released within a frame still counts
+    assert!(click(&mut map, ElementState::Released));
+    map.set_gamepad_button(GamepadButton::South, true);
+    map.set_gamepad_button(GamepadButton::South, false);
+    assert!(!map.is_pressed("grab"));
+    assert!(map.just_pressed("grab"));
+
+    // Losing the focus releases everything, and unbound inputs are left to
+    // the rest of the app
+    click(&mut map, ElementState::Pressed);
+    map.process_event(&WindowEvent::Focused(false));
+    assert!(!map.is_pressed("grab"));
+    map.set_bindings(InputBindings::empty());
+    assert!(!click(&mut map, ElementState::Pressed));
+    assert!(!map.is_pressed("grab"));
+}
+
+#[test]
+fn exit_follows_the_layout_until_rebound() {
+    // Escape is wherever the layout puts it
+    let escape = |map: &mut InputMap| {
+        map.process_key(
+            &PhysicalKey::Unidentified(NativeKeyCode::Unidentified),
+            &Key::Named(NamedKey::Escape),
+            ElementState::Pressed,
+        )
+    };
+    let mut map = InputMap::default();
+    assert!(escape(&mut map));
+    assert!(map.just_pressed(names::EXIT));
+
+    let mut bindings = InputBindings::default();
+    bindings.set_action(names::EXIT, vec![Input::Key(KeyCode::KeyQ)]);
+    let mut map = InputMap::new(bindings);
+    assert!(!escape(&mut map));
+    assert!(!map.just_pressed(names::EXIT));
+    assert!(map.process_key(
+        &PhysicalKey::Code(KeyCode::KeyQ),
+        &Key::Unidentified(NativeKey::Unidentified),
+        ElementState::Pressed,
+    ));
+    assert!(map.just_pressed(names::EXIT));
+}
+
+#[test]
+fn inputs_are_written_by_name() {
+    let inputs = [
+        (Input::Key(KeyCode::KeyW), "KeyW"),
+        (Input::Logical(NamedKey::Escape), "logical:Escape"),
+        (Input::Mouse(MouseButton::Left), "mouse:Left"),
+        (Input::Mouse(MouseButton::Other(7)), "mouse:7"),
+        (Input::Gamepad(GamepadButton::Start), "gamepad:Start"),
+    ];
+    for (input, name) in inputs {
+        assert_eq!(input.to_string(), name);
+        assert_eq!(name.parse::<Input>(), Ok(input));
+    }
+    let keys = AxisInput::Keys {
+        negative: KeyCode::KeyS,
+        positive: KeyCode::KeyW,
+    };
+    assert_eq!(keys.to_string(), "KeyS/KeyW");
+    assert_eq!("-KeyW/KeyS".parse::<AxisInput>(), Ok(keys));
+    assert_eq!(
+        "-gamepad:RightStickY".parse::<AxisInput>(),
+        Ok(AxisInput::Gamepad {
+            axis: GamepadAxis::RightStickY,
+            inverted: true,
+        })
+    );
+    for bad in [
+        "",
+        "KeyWW",
+        "mouse:Wheel",
+        "gamepad:Turbo",
+        "logical:KeyW",
+        "KeyS/",
+    ] {
+        assert!(bad.parse::<Input>().is_err(), "{bad}");
+        assert!(bad.parse::<AxisInput>().is_err(), "{bad}");
+    }
+}
@@ -0,0 +1,93 @@
+use wgpu_learning::key_bindings::{Action, KeyBindings, KeyTrigger};
+use winit::keyboard::{
+    Key, KeyCode, ModifiersState, NamedKey, NativeKey, NativeKeyCode, PhysicalKey,
+};
+
+fn action_for(bindings: &KeyBindings, code: KeyCode, modifiers: ModifiersState) -> Option<Action> {
+    bindings.action(
+        &PhysicalKey::Code(code),
+        &Key::Unidentified(NativeKey::Unidentified),
+        modifiers,
+    )
+}
+
+#[test]
+fn several_keys_trigger_the_same_action() {
+    let bindings = KeyBindings::default();
+
+    for code in [KeyCode::Equal, KeyCode::NumpadAdd] {
+        assert_eq!(
+            action_for(&bindings, code, ModifiersState::empty()),
+            Some(Action::IncreaseExposure)
+        );
+    }
+    let keys: Vec<_> = bindings.keys_for(Action::IncreaseExposure).collect();
+    assert_eq!(keys.len(), 2);
+}
+
+#[test]
+fn logical_keys_follow_the_layout() {
+    let bindings = KeyBindings::default();
+
+    // Escape is wherever the layout puts it
+    let action = bindings.action(
+        &PhysicalKey::Unidentified(NativeKeyCode::Unidentified),
+        &Key::Named(NamedKey::Escape),
+        ModifiersState::empty(),
+    );
+    assert_eq!(action, Some(Action::Exit));
+}
+
+#[test]
+fn modifiers_pick_the_most_specific_binding() {
+    let bindings = KeyBindings::empty()
+        .bind(KeyCode::Enter, Action::Screenshot)
+        .bind_with_modifiers(
+            KeyCode::Enter,
+            ModifiersState::ALT,
+            Action::ToggleExclusiveFullscreen,
+        );
+
+    assert_eq!(
+        action_for(&bindings, KeyCode::Enter, ModifiersState::empty()),
+        Some(Action::Screenshot)
+    );
+    assert_eq!(
+        action_for(&bindings, KeyCode::Enter, ModifiersState::ALT),
+        Some(Action::ToggleExclusiveFullscreen)
+    );
+    // Extra modifiers don't stop a binding from matching
+    assert_eq!(
+        action_for(
+            &bindings,
+            KeyCode::Enter,
+            ModifiersState::ALT | ModifiersState::SHIFT
+        ),
+        Some(Action::ToggleExclusiveFullscreen)
+    );
+}
+
+#[test]
+fn bindings_can_be_replaced() {
+    let bindings = KeyBindings::default()
+        .unbind(Action::Screenshot)
+        .bind(KeyCode::KeyP, Action::Screenshot);
+
+    assert_eq!(
+        action_for(&bindings, KeyCode::F12, ModifiersState::empty()),
+        None
+    );
+    // Binding a key again takes it away from the effects
+    assert_eq!(
+        action_for(&bindings, KeyCode::KeyP, ModifiersState::empty()),
+        Some(Action::Screenshot)
+    );
+    assert_eq!(bindings.keys_for(Action::NextPostEffect).count(), 0);
+    assert_eq!(
+        bindings.keys_for(Action::Screenshot).collect::<Vec<_>>(),
+        [(
+            &KeyTrigger::Physical(KeyCode::KeyP),
+            ModifiersState::empty()
+        )]
+    );
+}
@@ -0,0 +1,77 @@
+use glam::Vec3;
+use wgpu_learning::{
+    light::{self, LightBinding, Lights, PointLight, MAX_UNIFORM_LIGHTS},
+    pipeline::{LIGHT_SHADER_SOURCE, SHADER_SOURCE},
+    shader,
+};
+
+fn white_light(position: Vec3) -> PointLight {
+    PointLight::new(position, Vec3::ONE)
+}
+
+#[test]
+fn light_ids_survive_removing_other_lights() {
+    let mut lights = Lights::new(None);
+    let a = lights.add_light(white_light(Vec3::X)).unwrap();
+    let b = lights.add_light(white_light(Vec3::Y)).unwrap();
+    let c = lights.add_light(white_light(Vec3::Z)).unwrap();
+    assert_eq!(lights.len(), 3);
+
+    assert_eq!(lights.remove_light(b).unwrap().position, Vec3::Y);
+    assert!(lights.remove_light(b).is_none());
+    assert!(lights.light(b).is_none());
+    assert_eq!(lights.light(a).unwrap().position, Vec3::X);
+    assert_eq!(lights.light(c).unwrap().position, Vec3::Z);
+
+    lights.light_mut(c).unwrap().intensity = 2.0;
+    let raw = lights.to_raw();
+    assert_eq!(raw.len(), 2);
+    assert_eq!(raw[1].intensity, 2.0);
+    assert_eq!(lights.uniform().count, 2);
+
+    // Ids aren't reused
+    let d = lights.add_light(white_light(Vec3::ONE)).unwrap();
+    assert!(d != a && d != b && d != c);
+}
+
+#[test]
+fn the_uniform_fallback_holds_a_fixed_number_of_lights() {
+    assert_eq!(LightBinding::Storage.max_lights(), None);
+    let mut lights = Lights::new(LightBinding::Uniform.max_lights());
+    for _ in 0..MAX_UNIFORM_LIGHTS {
+        assert!(lights.add_light(white_light(Vec3::ONE)).is_some());
+    }
+    assert!(lights.add_light(white_light(Vec3::ONE)).is_none());
+    assert_eq!(lights.len(), MAX_UNIFORM_LIGHTS);
+}
+
+#[test]
+fn lights_orbit_around_the_y_axis() {
+    let mut lights = Lights::new(None);
+    let id = lights
+        .add_light(white_light(Vec3::new(2.0, 1.0, 0.0)))
+        .unwrap();
+    lights.orbit(90.0);
+    let position = lights.light(id).unwrap().position;
+    assert!((position.length() - Vec3::new(2.0, 1.0, 0.0).length()).abs() < 1e-5);
+    assert!((position.y - 1.0).abs() < 1e-5);
+    assert!(position.x.abs() < 1e-5);
+}
+
+#[test]
+fn shaders_validate_with_both_light_bindings() {
+    for binding in [LightBinding::Storage, LightBinding::Uniform] {
+        for (source, file) in [
+            (SHADER_SOURCE, "shader.wgsl"),
+            (LIGHT_SHADER_SOURCE, "light.wgsl"),
+        ] {
+            let source = light::shader_source(source, binding);
+            if let Err(err) = shader::validate(&source, file) {
+                panic!("{file} with {binding:?} lights: {err}");
+            }
+        }
+    }
+    // The fallback must actually replace the storage buffer
+    assert!(!light::shader_source(SHADER_SOURCE, LightBinding::Uniform)
+        .contains("var<storage, read> lights"));
+}
@@ -0,0 +1,63 @@
+use wgpu_learning::minimize::{FrameAction, MinimizeTracker};
+use winit::dpi::PhysicalSize;
+
+const SIZE: PhysicalSize<u32> = PhysicalSize::new(800, 600);
+const ZERO: PhysicalSize<u32> = PhysicalSize::new(0, 0);
+
+#[test]
+fn minimizing_to_zero_size_skips_frames_until_restored() {
+    let mut tracker = MinimizeTracker::default();
+    assert_eq!(tracker.begin_frame(SIZE), FrameAction::Render);
+
+    assert!(!tracker.resized(ZERO));
+    assert!(tracker.is_minimized());
+    assert_eq!(tracker.begin_frame(ZERO), FrameAction::Skip);
+
+    // Restoring resizes the window back, which configures the surface
+    assert!(tracker.resized(SIZE));
+    assert!(!tracker.is_minimized());
+    assert_eq!(tracker.begin_frame(SIZE), FrameAction::Render);
+}
+
+#[test]
+fn restoring_without_a_resize_event_reconfigures_from_the_window() {
+    let mut tracker = MinimizeTracker::default();
+    tracker.resized(ZERO);
+
+    let restored = PhysicalSize::new(1024, 768);
+    assert_eq!(
+        tracker.begin_frame(restored),
+        FrameAction::Reconfigure(restored)
+    );
+    assert_eq!(tracker.begin_frame(restored), FrameAction::Render);
+}
+
+#[test]
+fn occluded_windows_are_reconfigured_once_visible_again() {
+    let mut tracker = MinimizeTracker::default();
+    tracker.set_occluded(true);
+    assert!(tracker.is_minimized());
+    assert_eq!(tracker.begin_frame(SIZE), FrameAction::Skip);
+
+    tracker.set_occluded(false);
+    let restored = PhysicalSize::new(640, 480);
+    assert_eq!(
+        tracker.begin_frame(restored),
+        FrameAction::Reconfigure(restored)
+    );
+    assert_eq!(tracker.begin_frame(restored), FrameAction::Render);
+}
+
+#[test]
+fn a_resize_while_occluded_still_waits_for_the_window_to_show() {
+    let mut tracker = MinimizeTracker::default();
+    tracker.set_occluded(true);
+    tracker.resized(ZERO);
+    tracker.set_occluded(false);
+    // Still 0x0
+    assert!(tracker.is_minimized());
+    assert_eq!(tracker.begin_frame(ZERO), FrameAction::Skip);
+
+    assert!(tracker.resized(SIZE));
+    assert_eq!(tracker.begin_frame(SIZE), FrameAction::Render);
+}
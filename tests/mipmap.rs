@@ -0,0 +1,110 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use wgpu_learning::mipmap::mip_level_count;
+
+#[test]
+fn mip_chains_go_down_to_1x1() {
+    assert_eq!(mip_level_count(1, 1), 1);
+    assert_eq!(mip_level_count(2, 2), 2);
+    assert_eq!(mip_level_count(5, 3), 3);
+    assert_eq!(mip_level_count(300, 200), 9);
+    assert_eq!(mip_level_count(1024, 1), 11);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn odd_sized_levels_average_every_texel_they_cover() {
+    use wgpu_learning::{
+        mipmap::MipmapGenerator,
+        texture::{padded_bytes_per_row, Texture},
+    };
+
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+
+    // Red goes along the columns and green along the rows
+    let (columns, rows) = ([0, 50, 100, 150, 250], [0, 30, 240]);
+    let img = image::RgbaImage::from_fn(5, 3, |x, y| {
+        image::Rgba([columns[x as usize], rows[y as usize], 0, 255])
+    });
+    let mut generator = MipmapGenerator::new(&device);
+    let texture = Texture::from_image_mipmapped(
+        &device,
+        &queue,
+        &mut generator,
+        &image::DynamicImage::ImageRgba8(img),
+        Some("odd_texture"),
+        wgpu::TextureFormat::Rgba8Unorm,
+    )
+    .unwrap();
+
+    let texture = &texture.texture;
+    assert_eq!(texture.mip_level_count(), 3);
+    let size = texture.size();
+    let extents: Vec<_> = (0..3)
+        .map(|level| {
+            let extent = size.mip_level_size(level, wgpu::TextureDimension::D2);
+            (extent.width, extent.height)
+        })
+        .collect();
+    assert_eq!(extents, [(5, 3), (2, 1), (1, 1)]);
+
+    // Both smaller levels are a single row
+    let read_row = |level: u32, width: u32| {
+        let bytes_per_row = padded_bytes_per_row(width * 4);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("mip_readback"),
+            size: u64::from(bytes_per_row),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: level,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(1),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        let row = buffer.slice(..).get_mapped_range()[..(width * 4) as usize].to_vec();
+        row
+    };
+    let check = |actual: &[u8], expected: &[u8]| {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                actual.abs_diff(*expected) <= 1,
+                "{actual:?} vs {expected:?}"
+            );
+        }
+    };
+
+    // 5 columns go into 2 as 2/5, 2/5, 1/5 and 1/5, 2/5, 2/5, the middle
+    // one counting for both, and 3 rows into 1 as a third each. Sampling
+    // between two texels would leave out the last column and row.
+    let level_1 = read_row(1, 2);
+    check(&level_1, &[40, 90, 0, 255, 180, 90, 0, 255]);
+    let level_2 = read_row(2, 1);
+    check(&level_2, &[110, 90, 0, 255]);
+}
@@ -0,0 +1,53 @@
+use wgpu_learning::picking::{texel_at, Picked, BACKGROUND_ID};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+#[test]
+fn ids_round_trip() {
+    for picked in [
+        Picked {
+            instance: 0,
+            mesh: 0,
+        },
+        Picked {
+            instance: 99,
+            mesh: 3,
+        },
+        Picked {
+            instance: 0xFFFF,
+            mesh: 1,
+        },
+    ] {
+        assert_ne!(picked.id(), BACKGROUND_ID);
+        assert_eq!(Picked::from_id(picked.id()), Some(picked));
+    }
+}
+
+#[test]
+fn the_background_picks_nothing() {
+    assert_eq!(Picked::from_id(BACKGROUND_ID), None);
+}
+
+#[test]
+fn positions_map_to_the_texel_they_are_on() {
+    let size = PhysicalSize::new(64, 48);
+    assert_eq!(
+        texel_at(PhysicalPosition::new(0.0, 0.0), size),
+        Some((0, 0))
+    );
+    assert_eq!(
+        texel_at(PhysicalPosition::new(10.7, 3.2), size),
+        Some((10, 3))
+    );
+    assert_eq!(
+        texel_at(PhysicalPosition::new(63.9, 47.9), size),
+        Some((63, 47))
+    );
+}
+
+#[test]
+fn positions_outside_of_the_frame_map_to_nothing() {
+    let size = PhysicalSize::new(64, 48);
+    assert_eq!(texel_at(PhysicalPosition::new(64.0, 0.0), size), None);
+    assert_eq!(texel_at(PhysicalPosition::new(0.0, 48.0), size), None);
+    assert_eq!(texel_at(PhysicalPosition::new(-0.5, 10.0), size), None);
+}
@@ -0,0 +1,79 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use wgpu_learning::{
+    pipeline::{PipelineBuilder, RenderTargets},
+    shader::ShaderErrorKind,
+};
+
+const TARGETS: RenderTargets = RenderTargets {
+    color_format: wgpu::TextureFormat::Rgba8UnormSrgb,
+    depth_format: Some(wgpu::TextureFormat::Depth32Float),
+    sample_count: 1,
+};
+
+/// Draws a triangle from a vertex buffer holding positions.
+const TRIANGLE_SHADER: &str = "
+@vertex
+fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(position, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
+";
+
+const POSITION_LAYOUT: wgpu::VertexBufferLayout = wgpu::VertexBufferLayout {
+    array_stride: 12,
+    step_mode: wgpu::VertexStepMode::Vertex,
+    attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+};
+
+#[test]
+fn builds_pipelines_with_and_without_depth() {
+    let Some((device, _queue)) = common::device() else {
+        return;
+    };
+
+    PipelineBuilder::with_targets("Depth Pipeline", TARGETS)
+        .shader("triangle.wgsl", TRIANGLE_SHADER)
+        .vertex_buffer(POSITION_LAYOUT)
+        .build(&device)
+        .unwrap();
+
+    PipelineBuilder::with_targets("Overlay Pipeline", TARGETS)
+        .shader("triangle.wgsl", TRIANGLE_SHADER)
+        .vertex_buffer(POSITION_LAYOUT)
+        .depth_format(None)
+        .color_target(
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+        )
+        .cull_mode(None)
+        .build(&device)
+        .unwrap();
+}
+
+#[test]
+fn pipeline_errors_are_returned() {
+    let Some((device, _queue)) = common::device() else {
+        return;
+    };
+
+    // The shader reads a vertex attribute nothing provides
+    let err = PipelineBuilder::with_targets("Broken Pipeline", TARGETS)
+        .shader("triangle.wgsl", TRIANGLE_SHADER)
+        .build(&device)
+        .unwrap_err();
+    assert_eq!(err.kind, ShaderErrorKind::Pipeline);
+    assert!(err.to_string().contains("triangle.wgsl"), "{err}");
+
+    let err = PipelineBuilder::with_targets("Unparsable Pipeline", TARGETS)
+        .shader("broken.wgsl", "fn vs_main(")
+        .build(&device)
+        .unwrap_err();
+    assert_eq!(err.kind, ShaderErrorKind::Parse);
+}
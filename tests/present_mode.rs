@@ -0,0 +1,45 @@
+use wgpu::PresentMode;
+use wgpu_learning::present_mode::{select_present_mode, PresentModePreference};
+
+#[test]
+fn preferred_mode_is_used_when_supported() {
+    let all = [
+        PresentMode::Fifo,
+        PresentMode::Immediate,
+        PresentMode::Mailbox,
+    ];
+    assert_eq!(
+        select_present_mode(PresentModePreference::Vsync, &all),
+        PresentMode::Fifo
+    );
+    assert_eq!(
+        select_present_mode(PresentModePreference::Immediate, &all),
+        PresentMode::Immediate
+    );
+    assert_eq!(
+        select_present_mode(PresentModePreference::Mailbox, &all),
+        PresentMode::Mailbox
+    );
+    assert_eq!(
+        select_present_mode(PresentModePreference::Auto, &all),
+        PresentMode::Mailbox
+    );
+}
+
+#[test]
+fn unsupported_modes_fall_back() {
+    let fifo_and_immediate = [PresentMode::Fifo, PresentMode::Immediate];
+    assert_eq!(
+        select_present_mode(PresentModePreference::Auto, &fifo_and_immediate),
+        PresentMode::Immediate
+    );
+    // Mailbox never falls back to a mode that tears
+    assert_eq!(
+        select_present_mode(PresentModePreference::Mailbox, &fifo_and_immediate),
+        PresentMode::Fifo
+    );
+    assert_eq!(
+        select_present_mode(PresentModePreference::Immediate, &[PresentMode::Fifo]),
+        PresentMode::Fifo
+    );
+}
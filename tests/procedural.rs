@@ -0,0 +1,96 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use wgpu_learning::procedural;
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 128];
+
+#[test]
+fn checkerboards_alternate_every_cell() {
+    let image = procedural::checkerboard((10, 7), 3, RED, BLUE);
+    assert_eq!(image.dimensions(), (10, 7));
+    assert_eq!(image.get_pixel(0, 0).0, RED);
+    assert_eq!(image.get_pixel(2, 2).0, RED);
+    assert_eq!(image.get_pixel(3, 0).0, BLUE);
+    assert_eq!(image.get_pixel(0, 3).0, BLUE);
+    assert_eq!(image.get_pixel(3, 3).0, RED);
+    assert_eq!(image.get_pixel(9, 6).0, BLUE);
+    // Cells can't be empty
+    assert_eq!(
+        procedural::checkerboard((4, 4), 0, RED, BLUE),
+        procedural::checkerboard((4, 4), 1, RED, BLUE)
+    );
+}
+
+#[test]
+fn gradients_go_from_the_top_to_the_bottom() {
+    let image = procedural::gradient((3, 5), RED, BLUE);
+    for x in 0..3 {
+        assert_eq!(image.get_pixel(x, 0).0, RED);
+        assert_eq!(image.get_pixel(x, 4).0, BLUE);
+    }
+    assert_eq!(image.get_pixel(1, 2).0, [128, 0, 128, 192]);
+    // A single row is all the top color
+    assert_eq!(
+        procedural::gradient((2, 1), RED, BLUE).get_pixel(1, 0).0,
+        RED
+    );
+}
+
+#[test]
+fn noise_is_the_same_for_the_same_seed() {
+    let noise = procedural::value_noise((33, 17), 7, 4);
+    assert_eq!(noise, procedural::value_noise((33, 17), 7, 4));
+    assert_ne!(noise, procedural::value_noise((33, 17), 8, 4));
+    assert_ne!(noise, procedural::value_noise((33, 17), 7, 1));
+
+    // Gray, opaque and not all the same
+    let grays: Vec<u8> = noise
+        .pixels()
+        .map(|pixel| {
+            assert_eq!(pixel[0], pixel[1]);
+            assert_eq!(pixel[0], pixel[2]);
+            assert_eq!(pixel[3], 255);
+            pixel[0]
+        })
+        .collect();
+    let (min, max) = (grays.iter().min().unwrap(), grays.iter().max().unwrap());
+    assert!(max - min > 64, "{min}..{max}");
+
+    // The left edge continues the right one
+    let noise = procedural::value_noise((64, 64), 3, 3);
+    for y in 0..64 {
+        let (left, right) = (noise.get_pixel(0, y)[0], noise.get_pixel(63, y)[0]);
+        assert!(left.abs_diff(right) < 24, "row {y}: {left} and {right}");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn generated_textures_upload_at_any_size() {
+    use wgpu_learning::texture::Texture;
+
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+
+    // Rows that aren't a multiple of 256 bytes need padding
+    for size in [(1, 1), (37, 19), (65, 3), (64, 64)] {
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let textures = [
+            Texture::checkerboard(&device, &queue, size, 4, RED, BLUE),
+            Texture::gradient(&device, &queue, size, RED, BLUE),
+            Texture::value_noise(&device, &queue, size, 1, 3),
+        ];
+        queue.submit([]);
+        let error = pollster::block_on(device.pop_error_scope());
+        assert!(error.is_none(), "{size:?}: {error:?}");
+        for texture in textures {
+            let texture = &texture.texture;
+            assert_eq!((texture.width(), texture.height()), size);
+        }
+    }
+    let checker = Texture::uv_checker(&device, &queue).texture;
+    assert_eq!((checker.width(), checker.height()), (64, 64));
+}
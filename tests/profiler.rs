@@ -0,0 +1,14 @@
+use std::time::Duration;
+
+use wgpu_learning::profiler::ticks_to_duration;
+
+#[test]
+fn ticks_are_scaled_by_the_timestamp_period() {
+    assert_eq!(ticks_to_duration(0, 1.0), Duration::ZERO);
+    assert_eq!(ticks_to_duration(1_500, 1.0), Duration::from_nanos(1_500));
+    // e.g. a GPU whose timestamps tick at 24 MHz
+    assert_eq!(
+        ticks_to_duration(24_000, 41.666_668),
+        Duration::from_nanos(1_000_000)
+    );
+}
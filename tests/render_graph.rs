@@ -0,0 +1,72 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use wgpu_learning::render_graph::{
+    Frame, FrameResources, RenderGraph, RenderNode, BLOOM_NODE, POST_PROCESS_NODE, SCENE_NODE,
+};
+
+struct Named(&'static str);
+
+impl RenderNode for Named {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn run(&mut self, _frame: &mut Frame, _resources: &FrameResources) {}
+}
+
+#[test]
+fn nodes_go_where_they_are_put() {
+    let mut graph = RenderGraph::with_default_nodes();
+    assert_eq!(
+        graph.names().collect::<Vec<_>>(),
+        [SCENE_NODE, BLOOM_NODE, POST_PROCESS_NODE]
+    );
+
+    graph.insert_before(POST_PROCESS_NODE, Box::new(Named("outline")));
+    graph.insert_after(SCENE_NODE, Box::new(Named("fog")));
+    graph.insert_after("missing", Box::new(Named("hud")));
+    assert_eq!(
+        graph.names().collect::<Vec<_>>(),
+        ["scene", "fog", "bloom", "outline", "post_process", "hud"]
+    );
+
+    assert_eq!(
+        graph.remove("fog").map(|node| node.name().to_owned()),
+        Some("fog".into())
+    );
+    assert!(graph.remove("fog").is_none());
+    assert!(!graph.contains("fog"));
+    assert!(graph.contains("hud"));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn only_frame_sized_resources_follow_the_frame() {
+    use wgpu_learning::render_graph::ResourceDesc;
+    use winit::dpi::PhysicalSize;
+
+    let Some((device, _queue)) = common::device() else {
+        return;
+    };
+
+    let format = wgpu::TextureFormat::Rgba8Unorm;
+    let usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+    let mut resources = FrameResources::new(PhysicalSize::new(64, 48));
+    resources.insert(&device, "color", ResourceDesc::frame(format, usage));
+    resources.insert(&device, "lut", ResourceDesc::fixed(16, 16, format, usage));
+    let size = |resources: &FrameResources, name| {
+        let texture = resources.texture(name).unwrap();
+        (texture.width(), texture.height())
+    };
+    assert_eq!(size(&resources, "color"), (64, 48));
+
+    resources.resize(&device, PhysicalSize::new(32, 20));
+    assert_eq!(resources.size(), PhysicalSize::new(32, 20));
+    assert_eq!(size(&resources, "color"), (32, 20));
+    assert_eq!(size(&resources, "lut"), (16, 16));
+
+    assert!(resources.remove("lut"));
+    assert!(!resources.remove("lut"));
+    assert!(resources.get("lut").is_none());
+}
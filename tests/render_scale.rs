@@ -0,0 +1,42 @@
+use wgpu_learning::render_scale::{
+    box_filter_taps, clamp_render_scale, scaled_size, MAX_RENDER_SCALE, MIN_RENDER_SCALE,
+};
+use winit::dpi::PhysicalSize;
+
+#[test]
+fn render_scales_stay_in_range() {
+    assert_eq!(clamp_render_scale(0.5), 0.5);
+    assert_eq!(clamp_render_scale(0.0), MIN_RENDER_SCALE);
+    assert_eq!(clamp_render_scale(100.0), MAX_RENDER_SCALE);
+    assert_eq!(clamp_render_scale(f32::NAN), 1.0);
+}
+
+#[test]
+fn scaled_sizes_are_never_empty_or_too_big() {
+    let size = PhysicalSize::new(800, 600);
+    assert_eq!(scaled_size(size, 0.5, 8192), PhysicalSize::new(400, 300));
+    assert_eq!(scaled_size(size, 1.0, 8192), size);
+    assert_eq!(scaled_size(size, 2.0, 1024), PhysicalSize::new(1024, 1024));
+    assert_eq!(
+        scaled_size(PhysicalSize::new(1, 1), MIN_RENDER_SCALE, 8192),
+        PhysicalSize::new(1, 1)
+    );
+    assert_eq!(
+        scaled_size(PhysicalSize::new(3, 1), 0.25, 8192),
+        PhysicalSize::new(1, 1)
+    );
+}
+
+#[test]
+fn only_bigger_sources_are_box_filtered() {
+    let target = PhysicalSize::new(100, 50);
+    assert_eq!(box_filter_taps(PhysicalSize::new(50, 25), target), 1);
+    assert_eq!(box_filter_taps(target, target), 1);
+    assert_eq!(box_filter_taps(PhysicalSize::new(200, 100), target), 2);
+    // Rounding leaves some pixels covering a texel more
+    assert_eq!(box_filter_taps(PhysicalSize::new(150, 75), target), 2);
+    assert_eq!(
+        box_filter_taps(PhysicalSize::new(200, 100), PhysicalSize::new(0, 0)),
+        200
+    );
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use web_time::Instant;
+use wgpu_learning::run_config::{
+    BackgroundBehavior, FrameSchedule, RunConfig, BACKGROUND_FRAME_TIME,
+};
+
+#[test]
+fn focused_windows_always_draw_right_away() {
+    let now = Instant::now();
+    for behavior in [
+        BackgroundBehavior::KeepRunning,
+        BackgroundBehavior::Throttle,
+        BackgroundBehavior::Pause,
+    ] {
+        assert_eq!(behavior.schedule(true, now, now), FrameSchedule::Now);
+    }
+}
+
+#[test]
+fn throttled_windows_wait_for_the_background_frame_time() {
+    let last_frame = Instant::now();
+    let behavior = BackgroundBehavior::Throttle;
+
+    assert_eq!(
+        behavior.schedule(false, last_frame, last_frame + Duration::from_millis(10)),
+        FrameSchedule::At(last_frame + BACKGROUND_FRAME_TIME)
+    );
+    assert_eq!(
+        behavior.schedule(false, last_frame, last_frame + BACKGROUND_FRAME_TIME),
+        FrameSchedule::Now
+    );
+    assert!(!behavior.animates_in_background());
+}
+
+#[test]
+fn paused_windows_stay_idle() {
+    let now = Instant::now();
+    assert_eq!(
+        BackgroundBehavior::Pause.schedule(false, now, now + Duration::from_secs(10)),
+        FrameSchedule::Idle
+    );
+    assert!(BackgroundBehavior::KeepRunning.animates_in_background());
+}
+
+#[test]
+fn verbosity_only_applies_to_this_crate() {
+    let config = RunConfig {
+        verbosity: log::LevelFilter::Debug,
+        ..RunConfig::default()
+    };
+    assert_eq!(config.default_log_filter(), "warn,wgpu_learning=debug");
+}
@@ -0,0 +1,113 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use wgpu_learning::sampler::{SamplerDesc, TextureFiltering, MAX_ANISOTROPY};
+
+#[test]
+fn unsupported_samplers_are_corrected() {
+    // Anisotropy needs every filter to be linear
+    let desc = SamplerDesc {
+        anisotropy_clamp: 8,
+        ..SamplerDesc::nearest()
+    };
+    assert_eq!(desc.corrected(16), SamplerDesc::anisotropic(8));
+    // and is clamped to what the device does, which may be nothing
+    assert_eq!(
+        SamplerDesc::anisotropic(64).corrected(16).anisotropy_clamp,
+        MAX_ANISOTROPY
+    );
+    assert_eq!(
+        SamplerDesc::anisotropic(16).corrected(1),
+        SamplerDesc::trilinear()
+    );
+    assert_eq!(
+        SamplerDesc {
+            anisotropy_clamp: 0,
+            ..SamplerDesc::bilinear()
+        }
+        .corrected(16),
+        SamplerDesc::bilinear()
+    );
+
+    let desc = SamplerDesc {
+        lod_min_clamp: -1.0,
+        lod_max_clamp: -2.0,
+        ..SamplerDesc::trilinear()
+    }
+    .corrected(16);
+    assert_eq!((desc.lod_min_clamp, desc.lod_max_clamp), (0.0, 0.0));
+
+    for desc in [
+        SamplerDesc::nearest(),
+        SamplerDesc::bilinear(),
+        SamplerDesc::trilinear(),
+    ] {
+        assert_eq!(desc.corrected(1), desc);
+    }
+}
+
+#[test]
+fn texture_filtering_cycles_through_every_filter() {
+    let mut filtering = TextureFiltering::Nearest;
+    let mut seen = Vec::new();
+    for _ in 0..4 {
+        seen.push(filtering.desc());
+        filtering = filtering.next();
+    }
+    assert_eq!(filtering, TextureFiltering::Nearest);
+    assert_eq!(
+        seen,
+        [
+            SamplerDesc::nearest(),
+            SamplerDesc::bilinear(),
+            SamplerDesc::trilinear(),
+            SamplerDesc::anisotropic(MAX_ANISOTROPY),
+        ]
+    );
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn identical_descriptions_share_a_sampler() {
+    use std::sync::Arc;
+    use wgpu_learning::sampler::{max_anisotropy, SamplerCache};
+
+    let Some(adapter) = common::adapter() else {
+        return;
+    };
+    let (device, _queue) = common::device_on(&adapter);
+
+    let mut cache = SamplerCache::new(max_anisotropy(&adapter));
+    assert!(cache.is_empty());
+    let trilinear = cache.get(&device, &SamplerDesc::trilinear());
+    assert!(Arc::ptr_eq(
+        &trilinear,
+        &cache.get(&device, &SamplerDesc::trilinear())
+    ));
+    let repeating = cache.get(
+        &device,
+        &SamplerDesc::trilinear().with_address_mode(wgpu::AddressMode::Repeat),
+    );
+    assert!(!Arc::ptr_eq(&trilinear, &repeating));
+    assert_eq!(cache.len(), 2);
+
+    // What wgpu would reject becomes what it takes, which may be cached
+    // already
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let anisotropic = cache.get(
+        &device,
+        &SamplerDesc {
+            anisotropy_clamp: 64,
+            ..SamplerDesc::nearest()
+        },
+    );
+    assert!(pollster::block_on(device.pop_error_scope()).is_none());
+    if cache.max_anisotropy() == 1 {
+        assert!(Arc::ptr_eq(&anisotropic, &trilinear));
+    } else {
+        assert!(Arc::ptr_eq(
+            &anisotropic,
+            &cache.get(&device, &SamplerDesc::anisotropic(MAX_ANISOTROPY))
+        ));
+    }
+}
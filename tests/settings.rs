@@ -0,0 +1,228 @@
+use std::time::Duration;
+
+use web_time::Instant;
+use wgpu_learning::{
+    input_map::{AxisInput, GamepadAxis, Input, InputBindings},
+    present_mode::PresentModePreference,
+    run_config::RunConfig,
+    settings::{Debounce, Settings, VERSION},
+};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize, Position, Size},
+    event::MouseButton,
+    keyboard::KeyCode,
+};
+
+fn changed() -> Settings {
+    Settings {
+        window_size: Some(PhysicalSize::new(1024, 600)),
+        window_position: Some(PhysicalPosition::new(-20, 40)),
+        fullscreen: true,
+        maximized: true,
+        vsync: false,
+        msaa: 2,
+        camera_speed: 3.5,
+        clear_color: wgpu::Color {
+            r: 0.25,
+            g: 0.5,
+            b: 0.125,
+            a: 1.0,
+        },
+        input: InputBindings::default()
+            .bind("exit", Input::Key(KeyCode::KeyQ))
+            .bind("jump", Input::Mouse(MouseButton::Other(4)))
+            .bind_axis(
+                "look_y",
+                AxisInput::Gamepad {
+                    axis: GamepadAxis::RightStickY,
+                    inverted: true,
+                },
+            ),
+    }
+}
+
+#[test]
+fn settings_survive_a_round_trip() {
+    for settings in [Settings::default(), changed()] {
+        let toml = settings.to_toml();
+        assert!(toml.contains(&format!("version = {VERSION}")), "{toml}");
+        assert_eq!(Settings::from_toml(&toml), settings, "{toml}");
+    }
+}
+
+#[test]
+fn bad_values_fall_back_to_the_defaults() {
+    let settings = Settings::from_toml(
+        r#"
+        version = 1
+        favorite_color = "green"
+
+        [window]
+        width = 800
+        height = 0
+        fullscreen = "yes"
+
+        [graphics]
+        vsync = false
+        msaa = 3
+
+        [camera]
+        speed = -1.0
+
+        [scene]
+        clear_color = [1, 0.5, 0]
+        "#,
+    );
+    let defaults = Settings::default();
+    assert_eq!(settings.window_size, None);
+    assert_eq!(settings.fullscreen, defaults.fullscreen);
+    assert!(!settings.vsync);
+    assert_eq!(settings.msaa, defaults.msaa);
+    assert_eq!(settings.camera_speed, defaults.camera_speed);
+    // Three channels are opaque, integers are fine
+    assert_eq!(
+        settings.clear_color,
+        wgpu::Color {
+            r: 1.0,
+            g: 0.5,
+            b: 0.0,
+            a: 1.0
+        }
+    );
+
+    assert_eq!(Settings::from_toml("not = [toml"), defaults);
+
+    // Bad inputs are left out, bindings missing from the file stay
+    let settings = Settings::from_toml(
+        r#"
+        [input.actions]
+        exit = ["KeyQ", "KeyNope", 5]
+        toggle_fullscreen = "F11"
+
+        [input.axes]
+        move_right = []
+        "#,
+    );
+    assert_eq!(settings.input.inputs("exit"), [Input::Key(KeyCode::KeyQ)]);
+    assert_eq!(
+        settings.input.inputs("toggle_fullscreen"),
+        defaults.input.inputs("toggle_fullscreen")
+    );
+    assert!(settings.input.axis_inputs("move_right").is_empty());
+    assert_eq!(
+        settings.input.axis_inputs("move_forward"),
+        defaults.input.axis_inputs("move_forward")
+    );
+
+    assert_eq!(Settings::from_toml("window = 5"), defaults);
+}
+
+#[test]
+fn files_without_a_version_are_migrated() {
+    let settings = Settings::from_toml("[graphics]\nmsaa = 1\n");
+    assert_eq!(settings.msaa, 1);
+    assert!(settings
+        .to_toml()
+        .starts_with(&format!("version = {VERSION}\n")));
+    // Newer files are read as far as possible
+    assert_eq!(
+        Settings::from_toml("version = 99\n[graphics]\nmsaa = 8\n").msaa,
+        8
+    );
+}
+
+#[test]
+fn saving_keeps_the_rest_of_the_file() {
+    let dir = std::env::temp_dir().join(format!("wgpu_learning_settings_{}", std::process::id()));
+    let path = dir.join("nested").join("settings.toml");
+    let _ = std::fs::remove_dir_all(&dir);
+    // A missing file means the defaults
+    assert_eq!(Settings::load(&path), Settings::default());
+
+    changed().save(&path).unwrap();
+    assert_eq!(Settings::load(&path), changed());
+
+    std::fs::write(
+        &path,
+        "# My settings\nversion = 1\nextra = true\n\n[camera]\nspeed = 9.0 # fast\n",
+    )
+    .unwrap();
+    let settings = Settings {
+        window_position: None,
+        ..changed()
+    };
+    settings.save(&path).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("# My settings\n"), "{text}");
+    assert!(text.contains("extra = true"), "{text}");
+    assert!(!text.contains("\nx ="), "{text}");
+    assert_eq!(Settings::load(&path), settings);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn settings_describe_the_windows() {
+    let mut config = RunConfig::default();
+    changed().apply_to_config(&mut config);
+    assert_eq!(
+        config.window.inner_size,
+        Some(Size::Physical(PhysicalSize::new(1024, 600)))
+    );
+    assert_eq!(
+        config.window.position,
+        Some(Position::Physical(PhysicalPosition::new(-20, 40)))
+    );
+    assert!(config.window.fullscreen);
+    assert!(config.window.maximized);
+    assert_eq!(config.present_mode, PresentModePreference::Auto);
+    assert_eq!(config.sample_count, 2);
+}
+
+// Headless rendering blocks on the GPU, which the web doesn't allow
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn changes_made_while_running_get_saved() {
+    use wgpu_learning::State;
+
+    let mut state = match pollster::block_on(State::new_headless(8, 8, 1)) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Skipping headless test: {err}");
+            return;
+        }
+    };
+    let mut settings = changed();
+    settings.apply_to_state(&mut state);
+    assert_eq!(state.camera_controller().speed, 3.5);
+    assert_eq!(state.clear_color(), changed().clear_color);
+
+    state.camera_controller_mut().speed = 1.0;
+    settings.update_from_state(&state);
+    assert_eq!(settings.camera_speed, 1.0);
+    assert_eq!(settings.msaa, state.sample_count());
+    // Headless states have no window to take the placement from
+    assert_eq!(settings.window_size, changed().window_size);
+}
+
+#[test]
+fn placement_saves_wait_for_the_changes_to_stop() {
+    let delay = Duration::from_millis(500);
+    let start = Instant::now();
+    let mut debounce = Debounce::new(delay);
+    assert_eq!(debounce.deadline(), None);
+    assert!(!debounce.settled(start + delay));
+
+    // Every event of a live resize pushes the save back
+    debounce.changed(start);
+    debounce.changed(start + Duration::from_millis(300));
+    assert_eq!(
+        debounce.deadline(),
+        Some(start + Duration::from_millis(800))
+    );
+    assert!(!debounce.settled(start + delay));
+    assert!(debounce.settled(start + Duration::from_millis(800)));
+    // Once
+    assert!(!debounce.settled(start + Duration::from_secs(2)));
+    assert_eq!(debounce.deadline(), None);
+}
@@ -0,0 +1,66 @@
+use wgpu_learning::{
+    boids::BOIDS_SHADER_SOURCE,
+    compute::PARTICLE_SHADER_SOURCE,
+    debug_draw::DEBUG_DRAW_SHADER_SOURCE,
+    debug_view::DEBUG_VIEW_SHADER_SOURCE,
+    frame_time_graph::FRAME_TIME_GRAPH_SHADER_SOURCE,
+    fxaa::FXAA_SHADER_SOURCE,
+    grid::GRID_SHADER_SOURCE,
+    mipmap::MIPMAP_SHADER_SOURCE,
+    outline::OUTLINE_SHADER_SOURCE,
+    pipeline::{LIGHT_SHADER_SOURCE, SHADER_SOURCE},
+    post_process::{PASS_THROUGH_SHADER_SOURCE, VIGNETTE_SHADER_SOURCE},
+    render_scale::UPSCALE_SHADER_SOURCE,
+    shader::{validate, ShaderErrorKind},
+    skybox::SKYBOX_SHADER_SOURCE,
+    texture_array::TEXTURE_ARRAY_SHADER_SOURCE,
+    transparency::TRANSPARENCY_SHADER_SOURCE,
+};
+
+#[test]
+fn embedded_shaders_are_valid() {
+    validate(SHADER_SOURCE, "shader.wgsl").unwrap();
+    validate(LIGHT_SHADER_SOURCE, "light.wgsl").unwrap();
+    validate(SKYBOX_SHADER_SOURCE, "skybox.wgsl").unwrap();
+    validate(PARTICLE_SHADER_SOURCE, "particles.wgsl").unwrap();
+    validate(BOIDS_SHADER_SOURCE, "boids.wgsl").unwrap();
+    validate(PASS_THROUGH_SHADER_SOURCE, "post.wgsl").unwrap();
+    validate(VIGNETTE_SHADER_SOURCE, "vignette.wgsl").unwrap();
+    validate(MIPMAP_SHADER_SOURCE, "mipmap.wgsl").unwrap();
+    validate(FRAME_TIME_GRAPH_SHADER_SOURCE, "frame_time_graph.wgsl").unwrap();
+    validate(TEXTURE_ARRAY_SHADER_SOURCE, "texture_array.wgsl").unwrap();
+    validate(OUTLINE_SHADER_SOURCE, "outline.wgsl").unwrap();
+    validate(TRANSPARENCY_SHADER_SOURCE, "transparency.wgsl").unwrap();
+    validate(UPSCALE_SHADER_SOURCE, "upscale.wgsl").unwrap();
+    validate(FXAA_SHADER_SOURCE, "fxaa.wgsl").unwrap();
+    validate(DEBUG_VIEW_SHADER_SOURCE, "debug_view.wgsl").unwrap();
+    validate(DEBUG_DRAW_SHADER_SOURCE, "debug_draw.wgsl").unwrap();
+    validate(GRID_SHADER_SOURCE, "grid.wgsl").unwrap();
+}
+
+#[test]
+fn parse_errors_point_at_the_source() {
+    let source =
+        "@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(1.0)\n}\n";
+    let err = validate(source, "broken.wgsl").unwrap_err();
+
+    assert_eq!(err.kind, ShaderErrorKind::Parse);
+    assert_eq!(err.location.unwrap().line_number, 4);
+    let report = err.to_string();
+    assert!(report.contains("broken.wgsl:4:1"), "{report}");
+    assert!(report.contains("4 | }"), "{report}");
+    assert!(report.contains("  | ^"), "{report}");
+}
+
+#[test]
+fn validation_errors_point_at_the_source() {
+    let source = "@group(0) @binding(0) var<uniform> u: f32;\nfn f() {\n    u = 1.0;\n}\n";
+    let err = validate(source, "store.wgsl").unwrap_err();
+
+    assert_eq!(err.kind, ShaderErrorKind::Validation);
+    assert_eq!(err.location.unwrap().line_number, 3);
+    let report = err.to_string();
+    assert!(report.contains("store.wgsl:3:5"), "{report}");
+    assert!(report.contains("3 |     u = 1.0;"), "{report}");
+    assert!(report.contains("  |     ^"), "{report}");
+}
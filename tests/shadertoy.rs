@@ -0,0 +1,76 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use wgpu_learning::{shader::validate, shadertoy};
+
+const EXAMPLE: &str = include_str!("../examples/shadertoy.wgsl");
+
+#[test]
+fn the_example_compiles_with_the_prelude() {
+    validate(&shadertoy::assemble(EXAMPLE), "shadertoy.wgsl").unwrap();
+    // On its own it has no globals or entry points
+    assert!(validate(EXAMPLE, "shadertoy.wgsl").is_err());
+}
+
+#[test]
+fn errors_point_at_the_lines_of_the_file() {
+    let source = "fn main_image(frag_coord: vec2<f32>) -> vec4<f32> {\n    let x = 1.0\n    return vec4<f32>(x);\n}\n";
+    let err = validate(&shadertoy::assemble(source), "toy.wgsl").unwrap_err();
+    assert_eq!(err.location.map(|location| location.line_number), Some(3));
+    assert!(err.to_string().contains("toy.wgsl:3:"), "{err}");
+
+    let title = shadertoy::title("toy.wgsl", Some(&err));
+    assert!(title.starts_with("toy.wgsl: error at line 3: "), "{title}");
+    assert_eq!(shadertoy::title("toy.wgsl", None), "toy.wgsl - shadertoy");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn broken_shaders_keep_the_last_good_one() {
+    use std::time::Duration;
+
+    use wgpu_learning::shadertoy::ShaderToy;
+    use winit::dpi::PhysicalSize;
+
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let mut toy = ShaderToy::new(&device, format, PhysicalSize::new(8, 8));
+    assert!(!toy.has_shader());
+    toy.load(&device, "toy.wgsl", EXAMPLE).unwrap();
+    assert!(toy.has_shader() && toy.error().is_none());
+
+    // Missing main_image
+    assert!(toy.load(&device, "toy.wgsl", "fn nothing() {}").is_err());
+    assert!(toy.has_shader());
+    assert!(toy.error().is_some());
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: 8,
+            height: 8,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&Default::default());
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    toy.update(&queue, Duration::from_millis(16));
+    let mut encoder = device.create_command_encoder(&Default::default());
+    toy.render(&mut encoder, &view);
+    queue.submit([encoder.finish()]);
+    let error = pollster::block_on(device.pop_error_scope());
+    assert!(error.is_none(), "{error:?}");
+
+    // Fixing it clears the error
+    toy.load(&device, "toy.wgsl", EXAMPLE).unwrap();
+    assert!(toy.error().is_none());
+}
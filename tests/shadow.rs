@@ -0,0 +1,48 @@
+use glam::{Vec3, Vec3Swizzles, Vec4Swizzles};
+use wgpu_learning::shadow::{light_view_proj, SCENE_RADIUS};
+
+fn to_light_ndc(light_position: Vec3, point: Vec3) -> Vec3 {
+    let clip = light_view_proj(light_position) * point.extend(1.0);
+    clip.xyz() / clip.w
+}
+
+#[test]
+fn the_whole_scene_fits_into_the_shadow_map() {
+    for light_position in [
+        Vec3::new(2.0, 2.0, 2.0),
+        Vec3::new(-3.0, 1.0, 0.5),
+        // Straight above, where look_at needs another up vector
+        Vec3::new(0.0, 4.0, 0.0),
+    ] {
+        // Points on a sphere just inside the scene
+        for direction in [
+            Vec3::new(-1.0, 0.0, -1.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, -1.0),
+            Vec3::Y,
+            Vec3::NEG_Y,
+            light_position,
+        ] {
+            let point = direction.normalize() * SCENE_RADIUS * 0.99;
+            let ndc = to_light_ndc(light_position, point);
+            let inside = ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 && (0.0..=1.0).contains(&ndc.z);
+            assert!(
+                inside,
+                "{point} is at {ndc} for a light at {light_position}"
+            );
+        }
+    }
+}
+
+#[test]
+fn points_closer_to_the_light_have_less_depth() {
+    let light_position = Vec3::new(2.0, 2.0, 2.0);
+    let toward_light = light_position.normalize();
+    let near = to_light_ndc(light_position, toward_light);
+    let far = to_light_ndc(light_position, -toward_light);
+    assert!(near.z < far.z);
+    // The origin is right in the middle of the map
+    assert!(to_light_ndc(light_position, Vec3::ZERO)
+        .xy()
+        .abs_diff_eq(glam::Vec2::ZERO, 1e-5));
+}
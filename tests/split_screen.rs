@@ -0,0 +1,76 @@
+use wgpu_learning::split_screen::{SplitMode, Viewport, MAX_VIEWS};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+#[test]
+fn modes_cycle_through_all_the_splits() {
+    let mut mode = SplitMode::default();
+    assert_eq!(mode, SplitMode::Single);
+    let mut seen = vec![mode];
+    for _ in 0..3 {
+        mode = mode.next();
+        seen.push(mode);
+    }
+    assert_eq!(
+        seen,
+        [
+            SplitMode::Single,
+            SplitMode::Horizontal,
+            SplitMode::Vertical,
+            SplitMode::Single
+        ]
+    );
+    assert!(seen.iter().all(|mode| mode.view_count() <= MAX_VIEWS));
+}
+
+#[test]
+fn odd_sizes_split_without_gaps_or_overlaps() {
+    let size = PhysicalSize::new(101, 75);
+    let [left, right] = SplitMode::Vertical.viewports(size)[..] else {
+        panic!("a vertical split has two views");
+    };
+    assert_eq!(left.x + left.width, right.x);
+    assert_eq!(left.width + right.width, 101);
+    assert_eq!((left.height, right.height), (75, 75));
+
+    let [top, bottom] = SplitMode::Horizontal.viewports(size)[..] else {
+        panic!("a horizontal split has two views");
+    };
+    assert_eq!(top.y + top.height, bottom.y);
+    assert_eq!(top.height + bottom.height, 75);
+    assert_eq!((top.width, bottom.width), (101, 101));
+
+    // Every pixel is in exactly one view
+    for mode in [
+        SplitMode::Single,
+        SplitMode::Horizontal,
+        SplitMode::Vertical,
+    ] {
+        let viewports = mode.viewports(size);
+        for y in 0..size.height {
+            for x in 0..size.width {
+                let position = PhysicalPosition::new(f64::from(x) + 0.5, f64::from(y) + 0.5);
+                let count = viewports
+                    .iter()
+                    .filter(|viewport| viewport.contains(position))
+                    .count();
+                assert_eq!(count, 1, "{mode:?} at {x}x{y}");
+            }
+        }
+    }
+}
+
+#[test]
+fn halves_have_their_own_aspect_ratio() {
+    let viewports = SplitMode::Vertical.viewports(PhysicalSize::new(200, 100));
+    assert_eq!(
+        viewports[1],
+        Viewport {
+            x: 100,
+            y: 0,
+            width: 100,
+            height: 100
+        }
+    );
+    assert_eq!(viewports[0].aspect(), 1.0);
+    assert!(SplitMode::Vertical.viewports(PhysicalSize::new(1, 10))[0].is_empty());
+}
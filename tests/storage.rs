@@ -0,0 +1,42 @@
+use wgpu::{DownlevelFlags, Limits, ShaderStages};
+use wgpu_learning::storage::{ArrayBinding, StorageSupport};
+
+#[test]
+fn storage_buffers_need_the_flag_of_the_stage_and_room_in_the_limits() {
+    let all = DownlevelFlags::all();
+    let support = StorageSupport::new(all, &Limits::default());
+    assert!(support.read_only(ShaderStages::VERTEX_FRAGMENT | ShaderStages::COMPUTE));
+    assert_eq!(
+        support.binding(ShaderStages::FRAGMENT, 16),
+        ArrayBinding::Storage
+    );
+
+    // WebGL2 allows no storage buffers whatever the flags say
+    let webgl2 = StorageSupport::new(all, &Limits::downlevel_webgl2_defaults());
+    assert!(!webgl2.read_only(ShaderStages::FRAGMENT));
+    assert_eq!(
+        webgl2.binding(ShaderStages::FRAGMENT, 16),
+        ArrayBinding::Uniform { len: 16 }
+    );
+
+    // Every stage has to be able to read it
+    let vertex_only = StorageSupport::new(DownlevelFlags::VERTEX_STORAGE, &Limits::default());
+    assert!(vertex_only.read_only(ShaderStages::VERTEX));
+    assert!(!vertex_only.read_only(ShaderStages::VERTEX_FRAGMENT));
+}
+
+#[test]
+fn uniform_arrays_replace_the_storage_declaration() {
+    let source = "var<storage, read> lights: array<Light>;\nfn f() {}";
+    assert_eq!(
+        ArrayBinding::Storage.shader_source(source, "lights", "Light"),
+        source
+    );
+    let uniform = ArrayBinding::Uniform { len: 8 };
+    assert_eq!(uniform.max_len(), Some(8));
+    assert_eq!(
+        uniform.shader_source(source, "lights", "Light"),
+        "var<uniform> lights: array<Light, 8>;\nfn f() {}"
+    );
+    assert_eq!(uniform.binding_type(), wgpu::BufferBindingType::Uniform);
+}
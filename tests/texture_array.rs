@@ -0,0 +1,189 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use image::GenericImageView;
+use wgpu_learning::texture::resize_layers;
+
+fn solid(width: u32, height: u32, color: [u8; 4]) -> image::DynamicImage {
+    image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+        width,
+        height,
+        image::Rgba(color),
+    ))
+}
+
+#[test]
+fn resized_layers_all_have_the_same_size() {
+    let layers = resize_layers(
+        &[
+            solid(16, 16, [255, 0, 0, 255]),
+            solid(40, 8, [0, 255, 0, 255]),
+        ],
+        16,
+        16,
+    );
+    assert!(layers.iter().all(|layer| layer.dimensions() == (16, 16)));
+    assert_eq!(layers[1].get_pixel(3, 3).0, [0, 255, 0, 255]);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn layers_of_different_sizes_are_rejected() {
+    use wgpu_learning::texture::{Texture, TextureArrayError};
+
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+
+    let err = Texture::array_from_images(&device, &queue, &[], None).err();
+    assert_eq!(err, Some(TextureArrayError::NoImages));
+
+    let images = [
+        solid(8, 8, [255, 0, 0, 255]),
+        solid(8, 8, [0, 255, 0, 255]),
+        solid(4, 8, [0, 0, 255, 255]),
+    ];
+    let err = Texture::array_from_images(&device, &queue, &images, None)
+        .err()
+        .unwrap();
+    assert_eq!(
+        err,
+        TextureArrayError::SizeMismatch {
+            layer: 2,
+            expected: (8, 8),
+            found: (4, 8),
+        }
+    );
+    let message = err.to_string();
+    assert!(
+        message.contains("layer 2 is 4x8 but layer 0 is 8x8"),
+        "{message}"
+    );
+
+    let array = Texture::array_from_images(&device, &queue, &images[..2], None).unwrap();
+    assert_eq!(array.texture.depth_or_array_layers(), 2);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn every_instance_shows_its_own_layer_in_one_draw() {
+    use glam::{Quat, Vec3};
+    use wgpu_learning::{
+        camera::Camera,
+        geometry,
+        instance::{Instance, TexturedInstance},
+        pipeline::RenderTargets,
+        texture::padded_bytes_per_row,
+        texture_array::TextureArrayBatch,
+    };
+
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+
+    let (width, height) = (128, 64);
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let mut batch = TextureArrayBatch::new(
+        &device,
+        &queue,
+        RenderTargets {
+            color_format: format,
+            depth_format: None,
+            sample_count: 1,
+        },
+        &geometry::cube(),
+        &[solid(4, 4, [255, 0, 0, 255]), solid(4, 4, [0, 0, 255, 255])],
+    )
+    .unwrap();
+    assert_eq!(batch.layer_count(), 2);
+
+    batch.set_camera(
+        &queue,
+        &Camera {
+            eye: Vec3::new(0.0, 0.0, 5.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            aspect: width as f32 / height as f32,
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+        },
+    );
+    let cube = |x: f32, layer| TexturedInstance {
+        instance: Instance {
+            position: Vec3::new(x, 0.0, 0.0),
+            rotation: Quat::IDENTITY,
+        },
+        layer,
+    };
+    batch.set_instances(&device, &queue, &[cube(-1.0, 0), cube(1.0, 1)]);
+    assert_eq!(batch.instance_count(), 2);
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("texture_array_target"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&Default::default());
+    let mut encoder = device.create_command_encoder(&Default::default());
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        ..Default::default()
+    });
+    batch.draw(&mut render_pass);
+    drop(render_pass);
+
+    let bytes_per_row = padded_bytes_per_row(width * 4);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("texture_array_readback"),
+        size: u64::from(bytes_per_row * height),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let pixels = buffer.slice(..).get_mapped_range().to_vec();
+    let pixel = |x: u32, y: u32| {
+        let i = (y * bytes_per_row + x * 4) as usize;
+        [pixels[i], pixels[i + 1], pixels[i + 2]]
+    };
+
+    // The left cube has the first layer, the right one the second
+    let [r, g, b] = pixel(47, 32);
+    assert!(r > 100 && g == 0 && b == 0, "{:?}", [r, g, b]);
+    let [r, g, b] = pixel(81, 32);
+    assert!(r == 0 && g == 0 && b > 100, "{:?}", [r, g, b]);
+    // And in between there's nothing
+    assert_eq!(pixel(64, 32), [0, 0, 0]);
+}
@@ -0,0 +1,53 @@
+use glam::Vec3;
+use wgpu_learning::tonemap::{Operator, Tonemap, EXPOSURE_STEP, MAX_EXPOSURE};
+
+#[test]
+fn bright_colors_stop_clipping() {
+    let bright = Vec3::splat(2.0);
+    let brighter = Vec3::splat(8.0);
+    assert_eq!(Operator::None.apply(bright), Operator::None.apply(brighter));
+    for operator in [Operator::Reinhard, Operator::Aces] {
+        let (mapped, mapped_brighter) = (operator.apply(bright), operator.apply(brighter));
+        assert!(mapped.max_element() < 1.0, "{operator:?}");
+        assert!(mapped_brighter.x > mapped.x, "{operator:?}");
+        // Black stays black
+        assert!(
+            operator.apply(Vec3::ZERO).max_element() < 0.01,
+            "{operator:?}"
+        );
+    }
+}
+
+#[test]
+fn operators_cycle_through_all_of_them() {
+    let mut operator = Operator::default();
+    let mut seen = Vec::new();
+    for _ in Operator::ALL {
+        seen.push(operator);
+        operator = operator.next();
+    }
+    assert_eq!(operator, Operator::default());
+    for operator in Operator::ALL {
+        assert!(seen.contains(&operator));
+    }
+}
+
+#[test]
+fn exposure_doubles_the_scene_per_stop_within_its_limits() {
+    let mut tonemap = Tonemap {
+        operator: Operator::None,
+        exposure: 0.0,
+    };
+    tonemap.adjust_exposure(4);
+    assert_eq!(tonemap.exposure, 4.0 * EXPOSURE_STEP);
+    assert_eq!(tonemap.uniform().exposure, (4.0 * EXPOSURE_STEP).exp2());
+
+    tonemap.exposure = 1.0;
+    assert_eq!(tonemap.apply(Vec3::splat(0.25)), Vec3::splat(0.5));
+
+    tonemap.adjust_exposure(1000);
+    assert_eq!(tonemap.exposure, MAX_EXPOSURE);
+    tonemap.adjust_exposure(-1000);
+    assert_eq!(tonemap.exposure, -MAX_EXPOSURE);
+    assert!(tonemap.describe().contains("-8.00 EV"));
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use glam::Vec2;
+use web_time::Instant;
+use wgpu_learning::touch::{Gesture, TouchGestures, STALE_TOUCH};
+use winit::event::TouchPhase;
+
+#[test]
+fn one_finger_orbits() {
+    let now = Instant::now();
+    let mut touch = TouchGestures::default();
+    touch.touch(7, TouchPhase::Started, Vec2::new(100.0, 100.0), now);
+    touch.touch(7, TouchPhase::Moved, Vec2::new(110.0, 95.0), now);
+    touch.touch(7, TouchPhase::Ended, Vec2::new(120.0, 90.0), now);
+
+    let gesture = touch.take_gesture();
+    assert_eq!(gesture.orbit, Vec2::new(20.0, -10.0));
+    assert_eq!(gesture.pan, Vec2::ZERO);
+    assert_eq!(gesture.pinch, 1.0);
+    assert_eq!(touch.touches(), 0);
+    // Taken
+    assert!(touch.take_gesture().is_empty());
+}
+
+#[test]
+fn two_fingers_pinch_and_pan() {
+    let now = Instant::now();
+    let mut touch = TouchGestures::default();
+    touch.touch(1, TouchPhase::Started, Vec2::new(100.0, 100.0), now);
+    touch.touch(2, TouchPhase::Started, Vec2::new(200.0, 100.0), now);
+    assert_eq!(touch.touches(), 2);
+
+    // Spreading apart around the same middle
+    touch.touch(1, TouchPhase::Moved, Vec2::new(50.0, 100.0), now);
+    touch.touch(2, TouchPhase::Moved, Vec2::new(250.0, 100.0), now);
+    let gesture = touch.take_gesture();
+    assert!((gesture.pinch - 2.0).abs() < 1e-6, "{gesture:?}");
+    assert_eq!(gesture.pan, Vec2::ZERO);
+    assert_eq!(gesture.orbit, Vec2::ZERO);
+
+    // Both dragging down by the same amount
+    touch.touch(1, TouchPhase::Moved, Vec2::new(50.0, 140.0), now);
+    touch.touch(2, TouchPhase::Moved, Vec2::new(250.0, 140.0), now);
+    let gesture = touch.take_gesture();
+    assert_eq!(gesture.pan, Vec2::new(0.0, 40.0));
+    assert!((gesture.pinch - 1.0).abs() < 1e-3, "{gesture:?}");
+
+    // Lifting one leaves the other orbiting, without a jump
+    touch.touch(1, TouchPhase::Cancelled, Vec2::new(50.0, 140.0), now);
+    touch.touch(2, TouchPhase::Moved, Vec2::new(260.0, 140.0), now);
+    assert_eq!(
+        touch.take_gesture(),
+        Gesture {
+            orbit: Vec2::new(10.0, 0.0),
+            ..Gesture::default()
+        }
+    );
+}
+
+#[test]
+fn lost_ends_dont_leave_phantom_fingers() {
+    let start = Instant::now();
+    let mut touch = TouchGestures::default();
+    // Its Ended never comes
+    touch.touch(1, TouchPhase::Started, Vec2::new(10.0, 10.0), start);
+
+    // The same id coming down again starts over
+    touch.touch(1, TouchPhase::Started, Vec2::new(300.0, 300.0), start);
+    assert_eq!(touch.touches(), 1);
+    assert!(touch.take_gesture().is_empty());
+
+    // A new finger long after is alone, so it orbits instead of pinching
+    let later = start + STALE_TOUCH + Duration::from_millis(1);
+    touch.touch(2, TouchPhase::Started, Vec2::new(50.0, 50.0), later);
+    assert_eq!(touch.touches(), 1);
+    touch.touch(2, TouchPhase::Moved, Vec2::new(60.0, 50.0), later);
+    assert_eq!(touch.take_gesture().orbit, Vec2::new(10.0, 0.0));
+
+    // Moves of a finger whose start was lost count from there on
+    touch.touch(3, TouchPhase::Moved, Vec2::new(0.0, 0.0), later);
+    assert_eq!(touch.touches(), 2);
+    assert!(touch.take_gesture().is_empty());
+    touch.reset();
+    assert_eq!(touch.touches(), 0);
+}
@@ -0,0 +1,219 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use glam::{Quat, Vec3};
+use wgpu_learning::{
+    camera::Camera,
+    instance::{Instance, TintedInstance},
+    pipeline::BlendMode,
+    transparency::{sort_draws, view_depth, DrawBatch, SceneObject},
+};
+
+fn camera() -> Camera {
+    Camera {
+        eye: Vec3::new(0.0, 0.0, 5.0),
+        target: Vec3::ZERO,
+        up: Vec3::Y,
+        aspect: 1.0,
+        fovy: 45.0,
+        znear: 0.1,
+        zfar: 100.0,
+    }
+}
+
+/// A pane at `z` facing the camera.
+fn pane(z: f32, color: [f32; 4], blend_mode: BlendMode) -> SceneObject {
+    SceneObject {
+        mesh: 1,
+        instance: TintedInstance {
+            instance: Instance {
+                position: Vec3::new(0.0, 0.0, z),
+                rotation: Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+            },
+            color,
+        },
+        blend_mode,
+    }
+}
+
+#[test]
+fn transparent_objects_come_after_the_opaque_ones_from_back_to_front() {
+    let camera = camera();
+    assert_eq!(view_depth(&camera, Vec3::new(3.0, 1.0, 1.0)), 4.0);
+
+    let red = [1.0, 0.0, 0.0, 0.5];
+    let cube = SceneObject {
+        mesh: 0,
+        ..pane(-1.0, red, BlendMode::Opaque)
+    };
+    let objects = [
+        pane(1.0, red, BlendMode::AlphaBlend),
+        cube,
+        pane(-2.0, red, BlendMode::Additive),
+        // As far away as the next one, so they keep their order
+        pane(0.0, [0.0, 1.0, 0.0, 0.5], BlendMode::AlphaBlend),
+        pane(0.0, [0.0, 0.0, 1.0, 0.5], BlendMode::AlphaBlend),
+        pane(1.0, red, BlendMode::Opaque),
+    ];
+    let (instances, batches) = sort_draws(&objects, &camera);
+
+    let depths = instances
+        .iter()
+        .map(|instance| instance.instance.position.z)
+        .collect::<Vec<_>>();
+    assert_eq!(depths, [-1.0, 1.0, -2.0, 0.0, 0.0, 1.0]);
+    assert_eq!(instances[3].color[1], 1.0);
+    assert_eq!(instances[4].color[2], 1.0);
+    // Neighbours that can share a draw call do
+    let batch = |mesh, blend_mode, instances| DrawBatch {
+        mesh,
+        blend_mode,
+        instances,
+    };
+    assert_eq!(
+        batches,
+        [
+            batch(0, BlendMode::Opaque, 0..1),
+            batch(1, BlendMode::Opaque, 1..2),
+            batch(1, BlendMode::Additive, 2..3),
+            batch(1, BlendMode::AlphaBlend, 3..6),
+        ]
+    );
+}
+
+#[test]
+fn only_opaque_blend_modes_replace_what_is_behind() {
+    for mode in BlendMode::ALL {
+        let replaces = mode.blend_state() == wgpu::BlendState::REPLACE;
+        assert_eq!(replaces, !mode.is_transparent(), "{mode:?}");
+    }
+    assert_eq!(BlendMode::default(), BlendMode::Opaque);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn the_closest_pane_ends_up_on_top_whatever_the_order() {
+    use wgpu_learning::{
+        pipeline::RenderTargets,
+        texture::{padded_bytes_per_row, Texture},
+        transparency::{demo_meshes, TransparencyScene},
+    };
+
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+
+    let size = 64;
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let mut scene = TransparencyScene::new(
+        &device,
+        RenderTargets {
+            color_format: format,
+            depth_format: Some(Texture::DEPTH_FORMAT),
+            sample_count: 1,
+        },
+        &demo_meshes(),
+    )
+    .unwrap();
+
+    let extent = wgpu::Extent3d {
+        width: size,
+        height: size,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("transparency_target"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let depth = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("transparency_depth"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: Texture::DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&Default::default());
+    let depth_view = depth.create_view(&Default::default());
+    let bytes_per_row = padded_bytes_per_row(size * 4);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("transparency_readback"),
+        size: u64::from(bytes_per_row * size),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut render = |objects: Vec<SceneObject>| {
+        scene.set_objects(objects);
+        scene.update(&device, &queue, &camera());
+        let mut encoder = device.create_command_encoder(&Default::default());
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+        scene.draw_opaque(&mut render_pass);
+        scene.draw_transparent(&mut render_pass);
+        drop(render_pass);
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(size),
+                },
+            },
+            extent,
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        let i = ((size / 2) * bytes_per_row + (size / 2) * 4) as usize;
+        let pixel = buffer.slice(..).get_mapped_range()[i..i + 3].to_vec();
+        buffer.unmap();
+        pixel
+    };
+
+    let red = pane(0.0, [1.0, 0.0, 0.0, 0.5], BlendMode::AlphaBlend);
+    let blue = pane(1.0, [0.0, 0.0, 1.0, 0.5], BlendMode::AlphaBlend);
+    let front_first = render(vec![blue, red]);
+    let back_first = render(vec![red, blue]);
+    // The blue one in front has the last say either way
+    assert_eq!(front_first, back_first);
+    let [r, g, b] = [front_first[0], front_first[1], front_first[2]];
+    assert!(b > r && r > 0 && g == 0, "{:?}", [r, g, b]);
+
+    // An opaque pane behind the glass shows through it although it's
+    // listed last, and hides what's behind itself
+    let green = pane(-1.0, [0.0, 1.0, 0.0, 1.0], BlendMode::Opaque);
+    let far = pane(-2.0, [1.0, 1.0, 1.0, 1.0], BlendMode::Additive);
+    let pixel = render(vec![far, blue, green]);
+    let [r, g, b] = [pixel[0], pixel[1], pixel[2]];
+    assert!(g > 0 && b > 0 && r == 0, "{:?}", [r, g, b]);
+}
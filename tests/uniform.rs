@@ -0,0 +1,234 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+use std::mem::offset_of;
+
+use wgpu_learning::{
+    bloom::BloomUniform,
+    camera::CameraUniform,
+    debug_view::DebugViewUniform,
+    fxaa::FxaaUniform,
+    globals::GlobalsUniform,
+    light::{LightRaw, LightsUniform},
+    shadow::ShadowUniform,
+    tonemap::TonemapUniform,
+    uniform::{check_layout, dynamic_stride, LayoutError, Uniform, UniformField, WgslType},
+};
+
+#[test]
+fn the_scene_uniforms_match_their_wgsl_structs() {
+    check_layout::<BloomUniform>().unwrap();
+    check_layout::<CameraUniform>().unwrap();
+    check_layout::<FxaaUniform>().unwrap();
+    check_layout::<DebugViewUniform>().unwrap();
+    check_layout::<GlobalsUniform>().unwrap();
+    check_layout::<LightRaw>().unwrap();
+    check_layout::<LightsUniform>().unwrap();
+    check_layout::<ShadowUniform>().unwrap();
+    check_layout::<TonemapUniform>().unwrap();
+}
+
+/// A vec3 followed by a scalar, which WGSL packs into the vec3's last 4
+/// bytes.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PackedVec3 {
+    direction: [f32; 3],
+    intensity: f32,
+}
+
+impl Uniform for PackedVec3 {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "direction",
+            offset_of!(PackedVec3, direction),
+            WgslType::Vec3,
+        ),
+        UniformField::new(
+            "intensity",
+            offset_of!(PackedVec3, intensity),
+            WgslType::F32,
+        ),
+    ];
+}
+
+#[test]
+fn scalars_fill_the_end_of_a_vec3() {
+    check_layout::<PackedVec3>().unwrap();
+}
+
+/// Two vec3s without padding in between, the second one starts 4 bytes
+/// too early.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct UnpaddedVec3s {
+    position: [f32; 3],
+    color: [f32; 3],
+    _padding: [f32; 2],
+}
+
+impl Uniform for UnpaddedVec3s {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "position",
+            offset_of!(UnpaddedVec3s, position),
+            WgslType::Vec3,
+        ),
+        UniformField::new("color", offset_of!(UnpaddedVec3s, color), WgslType::Vec3),
+    ];
+}
+
+#[test]
+fn misaligned_fields_are_caught() {
+    assert_eq!(
+        check_layout::<UnpaddedVec3s>(),
+        Err(LayoutError::FieldOffset {
+            field: "color",
+            expected: 16,
+            actual: 12,
+        })
+    );
+}
+
+/// A mat3 as 3 packed columns of 3 floats, WGSL pads every column to 16
+/// bytes so the mat3 is 48 bytes long.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PackedMat3 {
+    normal_matrix: [[f32; 3]; 3],
+    scale: f32,
+    _padding: [f32; 2],
+}
+
+impl Uniform for PackedMat3 {
+    const FIELDS: &'static [UniformField] = &[
+        UniformField::new(
+            "normal_matrix",
+            offset_of!(PackedMat3, normal_matrix),
+            WgslType::Mat3,
+        ),
+        UniformField::new("scale", offset_of!(PackedMat3, scale), WgslType::F32),
+    ];
+}
+
+#[test]
+fn packed_mat3s_are_caught() {
+    assert_eq!(
+        check_layout::<PackedMat3>(),
+        Err(LayoutError::FieldOffset {
+            field: "scale",
+            expected: 48,
+            actual: 36,
+        })
+    );
+}
+
+/// A single float, WebGL2 needs the buffer padded to 16 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Unpadded {
+    time: f32,
+}
+
+impl Uniform for Unpadded {
+    const FIELDS: &'static [UniformField] = &[UniformField::new(
+        "time",
+        offset_of!(Unpadded, time),
+        WgslType::F32,
+    )];
+}
+
+#[test]
+fn sizes_are_rounded_to_16_bytes() {
+    assert_eq!(
+        check_layout::<Unpadded>(),
+        Err(LayoutError::Size {
+            expected: 16,
+            actual: 4,
+        })
+    );
+}
+
+#[test]
+fn dynamic_strides_round_up_to_the_alignment() {
+    for (size, stride_64, stride_256) in
+        [(4, 64, 256), (64, 64, 256), (80, 128, 256), (300, 320, 512)]
+    {
+        assert_eq!(dynamic_stride(size, 64), stride_64, "{size} bytes");
+        assert_eq!(dynamic_stride(size, 256), stride_256, "{size} bytes");
+    }
+}
+
+/// A model matrix per object.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Object {
+    model: [[f32; 4]; 4],
+}
+
+impl Uniform for Object {
+    const FIELDS: &'static [UniformField] = &[UniformField::new(
+        "model",
+        offset_of!(Object, model),
+        WgslType::Mat4,
+    )];
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn growing_keeps_the_offsets_of_the_values() {
+    use wgpu_learning::uniform::DynamicUniform;
+
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+
+    let mut objects = DynamicUniform::<Object>::new(&device, "Objects", wgpu::ShaderStages::VERTEX);
+    let stride = objects.stride();
+    assert_eq!(
+        stride,
+        dynamic_stride(64, device.limits().min_uniform_buffer_offset_alignment)
+    );
+    assert_eq!(objects.capacity(), 1);
+
+    let object = |i: usize| Object {
+        model: glam::Mat4::from_translation(glam::Vec3::splat(i as f32)).to_cols_array_2d(),
+    };
+    let first = objects.push(&object(0));
+    objects.upload(&device, &queue);
+    let first_buffer = objects.buffer().global_id();
+
+    let offsets: Vec<u32> = (1..5).map(|i| objects.push(&object(i))).collect();
+    assert_eq!(first, 0);
+    assert_eq!(offsets, vec![stride, 2 * stride, 3 * stride, 4 * stride]);
+    objects.upload(&device, &queue);
+    assert_eq!(objects.len(), 5);
+    assert_eq!(objects.capacity(), 8);
+    assert_ne!(objects.buffer().global_id(), first_buffer);
+
+    // The values moved over to the new buffer where they were
+    let size = objects.buffer().size();
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("objects_readback"),
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_buffer_to_buffer(objects.buffer(), 0, &readback, 0, size);
+    queue.submit(std::iter::once(encoder.finish()));
+    readback.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::Maintain::Wait);
+    let data = readback.slice(..).get_mapped_range();
+    for (i, offset) in std::iter::once(first).chain(offsets).enumerate() {
+        let offset = offset as usize;
+        let value: &Object = bytemuck::from_bytes(&data[offset..offset + 64]);
+        assert_eq!(value.model, object(i).model, "object {i}");
+    }
+
+    // Clearing starts from the first offset again without shrinking
+    objects.clear();
+    assert!(objects.is_empty());
+    assert_eq!(objects.push(&object(0)), 0);
+    assert_eq!(objects.capacity(), 8);
+}
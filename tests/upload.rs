@@ -0,0 +1,65 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use wgpu_learning::upload::UploadArena;
+
+fn read_back(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &wgpu::Buffer) -> Vec<u32> {
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("upload_readback"),
+        size: buffer.size(),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_buffer_to_buffer(buffer, 0, &readback, 0, buffer.size());
+    queue.submit(std::iter::once(encoder.finish()));
+    readback.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::Maintain::Wait);
+    let data = readback.slice(..).get_mapped_range();
+    bytemuck::cast_slice(&data).to_vec()
+}
+
+#[test]
+fn writes_land_where_they_were_told_and_chunks_get_reused() {
+    let Some((device, queue)) = common::device() else {
+        return;
+    };
+    let target = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("upload_target"),
+        size: 64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let mut uploads = UploadArena::new(32);
+    for frame in 0..4_u32 {
+        let mut encoder = device.create_command_encoder(&Default::default());
+        // Three writes of 16 bytes don't fit into one chunk of 32
+        for i in 0..3_u32 {
+            let values = [frame, i, frame * 10 + i, 7];
+            uploads.write(&device, &mut encoder, &target, u64::from(i) * 16, &values);
+        }
+        uploads.finish();
+        queue.submit(std::iter::once(encoder.finish()));
+        uploads.recall();
+        device.poll(wgpu::Maintain::Wait);
+
+        let data = read_back(&device, &queue, &target);
+        let expected: Vec<u32> = (0..3).flat_map(|i| [frame, i, frame * 10 + i, 7]).collect();
+        assert_eq!(&data[..12], &expected[..]);
+        assert_eq!(&data[12..], &[0; 4]);
+    }
+    // The frames take turns with the same chunks. The second frame needs
+    // two more, the ones of the first are only known to be mapped again
+    // when it's done
+    assert!(uploads.chunk_count() <= 4, "{}", uploads.chunk_count());
+
+    // Bigger writes than a chunk get one of their own
+    let mut encoder = device.create_command_encoder(&Default::default());
+    uploads.write(&device, &mut encoder, &target, 0, &[9_u32; 16]);
+    uploads.finish();
+    queue.submit(std::iter::once(encoder.finish()));
+    uploads.recall();
+    assert_eq!(read_back(&device, &queue, &target), vec![9; 16]);
+}
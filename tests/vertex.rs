@@ -0,0 +1,36 @@
+use std::mem;
+
+use wgpu_learning::vertex::Vertex;
+
+#[test]
+fn vertex_layout_matches_struct() {
+    let desc = Vertex::desc();
+
+    assert_eq!(desc.array_stride, mem::size_of::<Vertex>() as u64);
+    assert_eq!(desc.step_mode, wgpu::VertexStepMode::Vertex);
+    assert_eq!(desc.attributes.len(), 3);
+
+    let position = &desc.attributes[0];
+    assert_eq!(position.shader_location, 0);
+    assert_eq!(position.offset, mem::offset_of!(Vertex, position) as u64);
+    assert_eq!(position.format, wgpu::VertexFormat::Float32x3);
+
+    let color = &desc.attributes[1];
+    assert_eq!(color.shader_location, 1);
+    assert_eq!(color.offset, mem::offset_of!(Vertex, color) as u64);
+    assert_eq!(color.format, wgpu::VertexFormat::Float32x3);
+
+    let tex_coords = &desc.attributes[2];
+    assert_eq!(tex_coords.shader_location, 2);
+    assert_eq!(
+        tex_coords.offset,
+        mem::offset_of!(Vertex, tex_coords) as u64
+    );
+    assert_eq!(tex_coords.format, wgpu::VertexFormat::Float32x2);
+
+    let last = desc.attributes.last().unwrap();
+    assert_eq!(
+        last.offset + last.format.size(),
+        mem::size_of::<Vertex>() as u64
+    );
+}
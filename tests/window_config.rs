@@ -0,0 +1,58 @@
+use std::io::Cursor;
+
+use wgpu_learning::window_config::{load_icon, visible_position, MonitorArea};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+#[test]
+fn icons_are_decoded_from_png() {
+    let image = image::RgbaImage::from_pixel(16, 16, image::Rgba([255, 0, 0, 255]));
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .unwrap();
+
+    assert!(load_icon(&png).is_ok());
+}
+
+#[test]
+fn bad_icons_are_errors() {
+    assert!(load_icon(b"not a png").is_err());
+}
+
+#[test]
+fn windows_off_every_monitor_get_centered() {
+    let primary = MonitorArea {
+        position: PhysicalPosition::new(0, 0),
+        size: PhysicalSize::new(1920, 1080),
+    };
+    // A second monitor left of the primary one
+    let left = MonitorArea {
+        position: PhysicalPosition::new(-1280, 0),
+        size: PhysicalSize::new(1280, 1024),
+    };
+    let monitors = [left, primary];
+    let size = PhysicalSize::new(800, 600);
+
+    let on_left = PhysicalPosition::new(-1000, 100);
+    assert_eq!(
+        visible_position(on_left, size, &monitors, Some(primary)),
+        on_left
+    );
+    // The left monitor was unplugged
+    assert_eq!(
+        visible_position(on_left, size, &[primary], Some(primary)),
+        PhysicalPosition::new(560, 240)
+    );
+    // Right of both, and no primary monitor
+    assert_eq!(
+        visible_position(PhysicalPosition::new(1920, 0), size, &monitors, None),
+        PhysicalPosition::new(-1040, 212)
+    );
+    // Nothing to check against
+    assert_eq!(visible_position(on_left, size, &[], None), on_left);
+    // Bigger than the monitor, the title bar stays reachable
+    assert_eq!(
+        primary.center(PhysicalSize::new(4000, 3000)),
+        primary.position
+    );
+}